@@ -16,6 +16,7 @@ else:
 
 parser = argparse.ArgumentParser(description='Export a .blend scene as .glb')
 parser.add_argument('filepath', metavar='FILEPATH', help='a .blend file')
+parser.add_argument('--thumbnail', metavar='THUMBNAIL', help='a .png file to render the scene into')
 args = parser.parse_args(argv)
 
 stem, ext = os.path.splitext(args.filepath)
@@ -72,6 +73,36 @@ def write_geometry(f, obj):
         write_transform(f, obj)
         write_tags(f, obj)
 
+def render_thumbnail(filepath):
+    scene = bpy.context.scene
+
+    # Scenes without a camera are seen from above one corner, fitted to the bounds of every mesh
+    if not scene.camera:
+        camera = bpy.data.objects.new('Thumbnail', bpy.data.cameras.new('Thumbnail'))
+        camera.rotation_euler = (math.radians(60.0), 0.0, math.radians(45.0))
+        scene.collection.objects.link(camera)
+
+        coords = []
+        for obj in scene.objects:
+            if obj.type == 'MESH':
+                for corner in obj.bound_box:
+                    coords.extend(obj.matrix_world @ mathutils.Vector(corner))
+
+        if coords:
+            camera.location, _ = camera.camera_fit_coords(bpy.context.evaluated_depsgraph_get(),
+                                                          coords)
+
+        scene.camera = camera
+
+    scene.render.engine = 'BLENDER_WORKBENCH'
+    scene.render.resolution_x = 256
+    scene.render.resolution_y = 144
+    scene.render.resolution_percentage = 100
+    scene.render.image_settings.file_format = 'PNG'
+    scene.render.filepath = filepath
+
+    bpy.ops.render.render(write_still=True)
+
 def write_scene_refs(f, obj):
     f.write('\n\n[[scene.ref]]\n')
 
@@ -118,4 +149,7 @@ with open(args.filepath, 'w') as f:
 
         for collection in scene.children:
             for obj in collection.objects:
-                write_scene_refs(f, obj)
\ No newline at end of file
+                write_scene_refs(f, obj)
+
+if args.thumbnail:
+    render_thumbnail(args.thumbnail)
@@ -0,0 +1,68 @@
+import argparse
+import math
+import os
+import random
+import struct
+import wave
+
+# Writes the footstep sounds of each surface, art/sound/footstep/<surface>_<n>.wav, which are
+# synthesized from filtered noise and decaying tones so that they need no recorded samples
+
+parser = argparse.ArgumentParser(description='Synthesize footstep sounds')
+parser.add_argument('directory', metavar='DIRECTORY', help='the art/sound/footstep directory')
+parser.add_argument('--variants', type=int, default=2, help='sounds written for each surface')
+args = parser.parse_args()
+
+SAMPLE_RATE = 44100
+
+# Each surface: (seconds, attack seconds, decay rate, low-pass amount, noise gain, tones), where
+# each tone is (frequency, gain, decay rate, sweep), and a sweep raises the pitch over time as a
+# bubble does
+SURFACES = {
+    'concrete': (0.12, 0.002, 45.0, 0.35, 1.0, [(110.0, 0.3, 60.0, 0.0)]),
+    'grass': (0.2, 0.02, 18.0, 0.6, 0.8, []),
+    'metal': (0.3, 0.001, 14.0, 0.25, 0.5, [
+        (820.0, 0.25, 12.0, 0.0),
+        (1270.0, 0.2, 16.0, 0.0),
+        (2110.0, 0.12, 22.0, 0.0),
+    ]),
+    'water': (0.25, 0.01, 16.0, 0.15, 0.7, [(260.0, 0.3, 25.0, 2.0)]),
+    'wood': (0.14, 0.002, 35.0, 0.2, 0.6, [(180.0, 0.45, 40.0, 0.0), (350.0, 0.25, 50.0, 0.0)]),
+}
+
+def synthesize(rng, seconds, attack, decay, low_pass, noise_gain, tones):
+    # Each variant is pitched slightly differently so that repeated steps do not sound identical
+    pitch = rng.uniform(0.92, 1.08)
+    samples = []
+    filtered = 0.0
+
+    for i in range(int(seconds * SAMPLE_RATE)):
+        t = i / SAMPLE_RATE
+        envelope = min(t / attack, 1.0) * math.exp(-decay * t)
+        filtered += low_pass * (rng.uniform(-1.0, 1.0) - filtered)
+        sample = noise_gain * filtered
+
+        for frequency, gain, tone_decay, sweep in tones:
+            sample += gain * math.exp(-tone_decay * t) * math.sin(
+                2.0 * math.pi * frequency * pitch * (1.0 + sweep * t) * t)
+
+        samples.append(sample * envelope)
+
+    # Normalized to half of full scale, leaving headroom for the mixer
+    peak = max(abs(sample) for sample in samples) or 1.0
+
+    return [sample * 0.5 / peak for sample in samples]
+
+os.makedirs(args.directory, exist_ok=True)
+
+for name, surface in SURFACES.items():
+    for variant in range(1, args.variants + 1):
+        rng = random.Random(f'{name}_{variant}')
+        samples = synthesize(rng, *surface)
+        path = os.path.join(args.directory, f'{name}_{variant}.wav')
+
+        with wave.open(path, 'wb') as f:
+            f.setnchannels(1)
+            f.setsampwidth(2)
+            f.setframerate(SAMPLE_RATE)
+            f.writeframes(b''.join(struct.pack('<h', int(sample * 32767)) for sample in samples))
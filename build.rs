@@ -1,15 +1,54 @@
+#[path = "src/render/compressed_bitmap.rs"]
+mod compressed_bitmap;
+
+#[allow(dead_code)]
+#[path = "src/game/difficulty.rs"]
+mod difficulty;
+
+#[allow(dead_code)]
+#[path = "src/game/impact_table.rs"]
+mod impact_table;
+
+#[allow(dead_code)]
+#[path = "src/integrity.rs"]
+mod integrity;
+
+#[path = "src/render/material_animation.rs"]
+mod material_animation;
+
+#[allow(dead_code)]
+#[path = "src/game/objectives.rs"]
+mod objectives;
+
+#[allow(dead_code)]
+#[path = "src/level/placement.rs"]
+mod placement;
+
 use {
-    self::tools::*,
+    self::{
+        compressed_bitmap::{CompressedBitmap, CompressedBitmaps, CompressedFormat},
+        difficulty::DifficultyTable,
+        impact_table::ImpactTable,
+        integrity::{read_hash, AssetKind},
+        material_animation::{MaterialAnimation, MaterialAnimations},
+        objectives::{ObjectiveInfo, ObjectiveTable},
+        placement::Placements,
+        tools::*,
+    },
     anyhow::{bail, Context},
     lazy_static::lazy_static,
     log::{error, info, trace},
-    pak::PakBuf,
+    pak::{bitmap::BitmapFormat, BitmapId, Pak, PakBuf},
+    serde::Deserialize,
     shaderc::{CompileOptions, EnvVersion, SpirvVersion, TargetEnv},
     simplelog::{CombinedLogger, ConfigBuilder, LevelFilter, WriteLogger},
     std::{
         collections::HashMap,
         env::var,
-        fs::{metadata, read_dir, remove_file, write, File, OpenOptions},
+        fs::{
+            create_dir_all, metadata, read_dir, read_to_string, remove_file, write, File,
+            OpenOptions,
+        },
         path::{Path, PathBuf, MAIN_SEPARATOR},
         process::Command,
         time::SystemTime,
@@ -111,6 +150,16 @@ mod tools {
         res
     }
 
+    /// Reads the TOML of a table, such as `table/difficulty.tbl`, which is baked into a pak as a
+    /// blob.
+    pub fn read_table(pak: &mut PakBuf, key: &str) -> anyhow::Result<String> {
+        let data = pak
+            .read_blob(key)
+            .with_context(|| format!("Reading {key}"))?;
+
+        String::from_utf8(data).with_context(|| format!("Reading {key} as text"))
+    }
+
     pub fn rerun_if_changed(path: impl AsRef<Path>) {
         println!(
             "cargo:rerun-if-changed={}",
@@ -174,19 +223,112 @@ mod tools {
     }
 
     pub fn write_pak_bindings(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> anyhow::Result<()> {
+        let mut pak = PakBuf::open(src)?;
         let mut bindings = String::new();
-        for key in PakBuf::open(src)?.keys() {
+        let mut manifest = vec![];
+        let mut materials = vec![];
+        let mut scenes = vec![];
+        let mut sounds = vec![];
+        let mut thumbnails = vec![];
+        let keys = pak.keys().map(str::to_owned).collect::<Vec<_>>();
+        for key in &keys {
+            let Some(kind) = AssetKind::of(&pak, key) else {
+                info!("Skipping binding of unsupported key {key}");
+
+                continue;
+            };
+
+            // Each key is typed by the asset it names so that mismatched keys do not compile
+            let ty = match kind {
+                AssetKind::Font => "FontKey",
+                AssetKind::Bitmap => "BitmapKey",
+                AssetKind::Material => "MaterialKey",
+                AssetKind::Model => "ModelKey",
+                AssetKind::Scene => "SceneKey",
+                AssetKind::Blob if key.starts_with("sound/") => "SoundKey",
+                AssetKind::Blob => "BlobKey",
+            };
+
+            // The game checks the pak against these hashes at startup, see `src/integrity.rs`
+            let hash = read_hash(&mut pak, key, kind)
+                .with_context(|| format!("Reading {key} to hash it"))?;
+            manifest.push(format!(
+                "crate::integrity::ManifestEntry::new(r#\"{key}\"#, \
+                crate::integrity::AssetKind::{kind:?}, {hash:#018x})"
+            ));
+
+            let name = key
+                .to_ascii_uppercase()
+                .replace(['\\', '/', '-', '.', '!'], "_");
+
+            match ty {
+                "MaterialKey" => materials.push(name.clone()),
+                "SceneKey" => scenes.push(name.clone()),
+                "SoundKey" => sounds.push(name.clone()),
+                _ => (),
+            }
+
+            // Thumbnails of scenes are found by the key of their scene, such as
+            // `scene/thumbnail/level_01.png` for `scene/level_01`
+            if let Some(stem) = key
+                .strip_prefix("scene/thumbnail/")
+                .and_then(|key| key.strip_suffix(".png"))
+                .filter(|_| ty == "BitmapKey")
+            {
+                let scene = format!("scene/{stem}")
+                    .to_ascii_uppercase()
+                    .replace(['\\', '/', '-', '.', '!'], "_");
+                thumbnails.push(format!("({scene}, {name})"));
+            }
+
             bindings.push_str("pub const ");
-            bindings.push_str(
-                key.to_ascii_uppercase()
-                    .replace(['\\', '/', '-', '.', '!'], "_")
-                    .as_str(),
-            );
-            bindings.push_str(": &str = r#\"");
+            bindings.push_str(&name);
+            bindings.push_str(": crate::asset_key::");
+            bindings.push_str(ty);
+            bindings.push_str(" = crate::asset_key::");
+            bindings.push_str(ty);
+            bindings.push_str("::new(r#\"");
             bindings.push_str(key);
-            bindings.push_str("\"#;\n");
+            bindings.push_str("\"#);\n");
+        }
+
+        // Materials may be found by key, such as the decals listed by the impact table
+        if !materials.is_empty() {
+            materials.sort();
+            bindings.push_str("pub const MATERIALS: &[crate::asset_key::MaterialKey] = &[");
+            bindings.push_str(&materials.join(", "));
+            bindings.push_str("];\n");
+        }
+
+        // Scenes may also be chosen at runtime, such as by benchmark arguments
+        if !scenes.is_empty() {
+            scenes.sort();
+            bindings.push_str("pub const SCENES: &[crate::asset_key::SceneKey] = &[");
+            bindings.push_str(&scenes.join(", "));
+            bindings.push_str("];\n");
         }
 
+        // Sounds may be found by name, such as the footsteps of each surface
+        if !sounds.is_empty() {
+            sounds.sort();
+            bindings.push_str("pub const SOUNDS: &[crate::asset_key::SoundKey] = &[");
+            bindings.push_str(&sounds.join(", "));
+            bindings.push_str("];\n");
+        }
+
+        // Always written, so that the level select screen compiles without any thumbnails
+        thumbnails.sort();
+        bindings.push_str(
+            "pub const SCENE_THUMBNAILS: &[(crate::asset_key::SceneKey, \
+            crate::asset_key::BitmapKey)] = &[",
+        );
+        bindings.push_str(&thumbnails.join(", "));
+        bindings.push_str("];\n");
+
+        bindings.push_str("pub const MANIFEST: &[crate::integrity::ManifestEntry] = &[");
+        bindings.push_str(&manifest.join(", "));
+        bindings.push_str("];\n");
+
         write(&dst, bindings)?;
 
         info!("Wrote bindings to {}", dst.as_ref().display());
@@ -219,6 +361,8 @@ fn main() -> anyhow::Result<()> {
 }
 
 fn build() -> anyhow::Result<()> {
+    write_build_hash();
+
     if metadata(CARGO_MANIFEST_DIR.join("art/scene/level_01.blend"))?.len() < 1024 {
         bail!("Git LFS objects have not been downloaded; see README.md");
     }
@@ -235,8 +379,14 @@ fn build() -> anyhow::Result<()> {
 
     let changed = build_fonts(&mut timestamps).context("Building fonts")?
         | export_models(&mut timestamps).context("Exporting models")?
-        | export_scenes(&mut timestamps).context("Exporting scenes")?;
+        | export_scenes(&mut timestamps).context("Exporting scenes")?
+        | tables_changed(&mut timestamps).context("Reading tables")?;
     bake_pak("art", &mut timestamps, changed)?;
+    compress_bitmaps().context("Compressing bitmaps")?;
+    write_material_animations().context("Writing material animations")?;
+    check_difficulty_table().context("Checking difficulty table")?;
+    check_impact_table().context("Checking impact table")?;
+    write_objective_table().context("Writing objective table")?;
 
     let changed = compile_shaders(&mut timestamps)?;
     bake_pak("res", &mut timestamps, changed)?;
@@ -283,6 +433,318 @@ fn bake_pak(
     Ok(())
 }
 
+/// Writes block-compressed copies, including mips, of every material bitmap in the art pak.
+fn compress_bitmaps() -> anyhow::Result<()> {
+    use intel_tex_2::{bc5, bc7, RgSurface, RgbaSurface};
+
+    let pak_path = TARGET_DIR.join("art.pak");
+    let dst_path = TARGET_DIR.join(compressed_bitmap::FILE_NAME);
+
+    // Compression is slow, so it only runs when the pak has been baked again
+    if let (Ok(pak), Ok(dst)) = (metadata(&pak_path), metadata(&dst_path)) {
+        if dst.modified()? >= pak.modified()? {
+            return Ok(());
+        }
+    }
+
+    info!("Compressing bitmaps");
+
+    let mut pak = PakBuf::open(&pak_path).context("Opening pak")?;
+    let material_keys = pak
+        .keys()
+        .filter(|key| key.starts_with("material/"))
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+
+    // Normal maps only keep X and Y, which are stored in red and green
+    let mut bitmap_ids = HashMap::<BitmapId, CompressedFormat>::new();
+    for key in material_keys {
+        let material = pak.read_material(&key).context("Reading material")?;
+
+        bitmap_ids.insert(material.color, CompressedFormat::Bc7);
+        bitmap_ids.insert(material.normal, CompressedFormat::Bc5);
+        bitmap_ids.insert(material.params, CompressedFormat::Bc7);
+
+        if let Some(emissive) = material.emissive {
+            bitmap_ids.insert(emissive, CompressedFormat::Bc7);
+        }
+    }
+
+    let bc7_settings = bc7::alpha_basic_settings();
+    let mut bitmaps = CompressedBitmaps::with_capacity(bitmap_ids.len());
+    for (id, format) in bitmap_ids {
+        let bitmap = pak.read_bitmap_id(id).context("Reading bitmap")?;
+        let (width, height) = (bitmap.width(), bitmap.height());
+        let mut rgba = to_rgba(bitmap.format(), bitmap.pixels());
+        let mut mips = vec![];
+
+        for mip_level in 0..u32::BITS - width.max(height).max(1).leading_zeros() {
+            let (mip_width, mip_height) = CompressedBitmap::mip_size(width, height, mip_level);
+
+            if mip_level > 0 {
+                let (src_width, src_height) =
+                    CompressedBitmap::mip_size(width, height, mip_level - 1);
+                rgba = downsample(&rgba, src_width, src_height);
+            }
+
+            // The encoder works on whole blocks so the edges are repeated to fill the last ones
+            let (block_width, block_height, data) = pad_to_blocks(&rgba, mip_width, mip_height);
+            let blocks = match format {
+                CompressedFormat::Bc5 => {
+                    let rg = data
+                        .chunks_exact(4)
+                        .flat_map(|pixel| [pixel[0], pixel[1]])
+                        .collect::<Vec<_>>();
+
+                    bc5::compress_blocks(&RgSurface {
+                        data: &rg,
+                        width: block_width,
+                        height: block_height,
+                        stride: block_width * 2,
+                    })
+                }
+                CompressedFormat::Bc7 => bc7::compress_blocks(
+                    &bc7_settings,
+                    &RgbaSurface {
+                        data: &data,
+                        width: block_width,
+                        height: block_height,
+                        stride: block_width * 4,
+                    },
+                ),
+            };
+
+            debug_assert_eq!(
+                blocks.len(),
+                CompressedBitmap::mip_len(width, height, mip_level)
+            );
+
+            mips.push(blocks);
+        }
+
+        bitmaps.insert(
+            id,
+            CompressedBitmap {
+                format,
+                has_alpha: bitmap.format() == BitmapFormat::Rgba,
+                width,
+                height,
+                mips,
+            },
+        );
+    }
+
+    write(
+        &dst_path,
+        bincode::serialize(&bitmaps).context("Serializing")?,
+    )
+    .context("Writing compressed bitmaps")?;
+
+    info!("Wrote {} compressed bitmaps", bitmaps.len());
+
+    Ok(())
+}
+
+/// Sets `MOOD_BUILD_HASH`, which save files record, to the current git commit.
+fn write_build_hash() {
+    rerun_if_changed(".git/HEAD");
+
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_owned())
+        .unwrap_or_else(|| "unknown".to_owned());
+
+    println!("cargo:rustc-env=MOOD_BUILD_HASH={hash}");
+}
+
+/// Checks that the difficulty table baked into the art pak parses.
+fn check_difficulty_table() -> anyhow::Result<()> {
+    let mut pak = PakBuf::open(TARGET_DIR.join("art.pak")).context("Opening pak")?;
+    let table = DifficultyTable::parse(&read_table(&mut pak, "table/difficulty.tbl")?)?;
+
+    info!("Checked {} difficulties", table.difficulties.len());
+
+    Ok(())
+}
+
+/// Checks that the impact table baked into the art pak parses, and that each decal material and
+/// sound it lists is within the art pak.
+fn check_impact_table() -> anyhow::Result<()> {
+    let mut pak = PakBuf::open(TARGET_DIR.join("art.pak")).context("Opening pak")?;
+    let table = ImpactTable::parse(&read_table(&mut pak, "table/impact.tbl")?)?;
+
+    for effect in table.effects() {
+        if let Some(decal) = &effect.decal {
+            pak.material_id(decal)
+                .with_context(|| format!("Unknown material {decal}"))?;
+        }
+
+        for sound in &effect.sounds {
+            pak.blob_id(sound)
+                .with_context(|| format!("Unknown sound {sound}"))?;
+        }
+    }
+
+    info!("Checked {} impact effects", table.effects().count());
+
+    Ok(())
+}
+
+/// material ID within the art pak.
+fn write_material_animations() -> anyhow::Result<()> {
+    #[derive(Deserialize)]
+    struct AnimationFile {
+        #[serde(default)]
+        material: Vec<AnimationEntry>,
+    }
+
+    #[derive(Deserialize)]
+    struct AnimationEntry {
+        key: String,
+
+        #[serde(flatten)]
+        animation: MaterialAnimation,
+    }
+
+    let src_path = CARGO_MANIFEST_DIR.join("art/material_animation.toml");
+
+    rerun_if_changed(&src_path);
+
+    let file: AnimationFile = toml::from_str(&read_to_string(&src_path)?)?;
+    let pak = PakBuf::open(TARGET_DIR.join("art.pak")).context("Opening pak")?;
+    let mut animations = MaterialAnimations::with_capacity(file.material.len());
+    for entry in file.material {
+        let id = pak
+            .material_id(&entry.key)
+            .with_context(|| format!("Unknown material {}", entry.key))?;
+        animations.insert(id, entry.animation);
+    }
+
+    write(
+        TARGET_DIR.join(material_animation::FILE_NAME),
+        bincode::serialize(&animations).context("Serializing")?,
+    )
+    .context("Writing material animations")?;
+
+    info!("Wrote {} material animations", animations.len());
+
+    Ok(())
+}
+
+/// Writes the objectives listed in `art/objectives.toml`, checking that each belongs to a scene of
+/// the art pak, has text, and has an id which is unique within its scene.
+fn write_objective_table() -> anyhow::Result<()> {
+    #[derive(Deserialize)]
+    struct ObjectiveFile {
+        #[serde(default)]
+        objective: Vec<ObjectiveEntry>,
+    }
+
+    #[derive(Deserialize)]
+    struct ObjectiveEntry {
+        scene: String,
+
+        #[serde(flatten)]
+        info: ObjectiveInfo,
+    }
+
+    let src_path = CARGO_MANIFEST_DIR.join("art/objectives.toml");
+
+    rerun_if_changed(&src_path);
+
+    let file: ObjectiveFile = toml::from_str(&read_to_string(&src_path)?)?;
+    let pak = PakBuf::open(TARGET_DIR.join("art.pak")).context("Opening pak")?;
+    let mut table = ObjectiveTable::default();
+    let mut count = 0;
+    for entry in file.objective {
+        pak.scene_id(&entry.scene)
+            .with_context(|| format!("Unknown scene {}", entry.scene))?;
+
+        let objectives = table.levels.entry(entry.scene).or_default();
+
+        if entry.info.text.trim().is_empty() {
+            bail!("Objective {} has no text", entry.info.id);
+        }
+
+        if objectives.iter().any(|info| info.id == entry.info.id) {
+            bail!("Duplicate objective {}", entry.info.id);
+        }
+
+        objectives.push(entry.info);
+        count += 1;
+    }
+
+    write(
+        TARGET_DIR.join(objectives::FILE_NAME),
+        bincode::serialize(&table).context("Serializing")?,
+    )
+    .context("Writing objective table")?;
+
+    info!("Wrote {count} objectives");
+
+    Ok(())
+}
+
+/// Halves the size of an RGBA image using a box filter.
+fn downsample(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let (dst_width, dst_height) = ((width >> 1).max(1), (height >> 1).max(1));
+    let mut res = Vec::with_capacity((dst_width * dst_height * 4) as usize);
+
+    for y in 0..dst_height {
+        for x in 0..dst_width {
+            for channel in 0..4 {
+                let mut sum = 0u32;
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let src_x = (x * 2 + dx).min(width - 1);
+                    let src_y = (y * 2 + dy).min(height - 1);
+                    sum += rgba[((src_y * width + src_x) * 4 + channel) as usize] as u32;
+                }
+
+                res.push(((sum + 2) / 4) as u8);
+            }
+        }
+    }
+
+    res
+}
+
+/// Returns the image resized up to whole blocks by repeating the last row and column.
+fn pad_to_blocks(rgba: &[u8], width: u32, height: u32) -> (u32, u32, Vec<u8>) {
+    let block_size = CompressedBitmap::BLOCK_SIZE;
+    let padded_width = (width + block_size - 1) / block_size * block_size;
+    let padded_height = (height + block_size - 1) / block_size * block_size;
+    let mut res = Vec::with_capacity((padded_width * padded_height * 4) as usize);
+
+    for y in 0..padded_height {
+        let src_y = y.min(height - 1);
+        for x in 0..padded_width {
+            let src = ((src_y * width + x.min(width - 1)) * 4) as usize;
+            res.extend_from_slice(&rgba[src..src + 4]);
+        }
+    }
+
+    (padded_width, padded_height, res)
+}
+
+fn to_rgba(format: BitmapFormat, pixels: &[u8]) -> Vec<u8> {
+    match format {
+        BitmapFormat::R => pixels.iter().flat_map(|&r| [r, 0, 0, 0xff]).collect(),
+        BitmapFormat::Rg => pixels
+            .chunks_exact(2)
+            .flat_map(|rg| [rg[0], rg[1], 0, 0xff])
+            .collect(),
+        BitmapFormat::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 0xff])
+            .collect(),
+        BitmapFormat::Rgba => pixels.to_vec(),
+    }
+}
+
 fn build_fonts(timestamps: &mut Timestamps) -> anyhow::Result<bool> {
     rerun_if_changed(FONTBM_PATH.as_path());
 
@@ -513,9 +975,6 @@ fn compile_shaders(timestamps: &mut Timestamps) -> anyhow::Result<bool> {
             addl_opts.add_macro_definition(name, value);
         }
 
-        #[cfg(target_os = "macos")]
-        addl_opts.add_macro_definition("MOLTEN_VK", Some("1"));
-
         let compiler = Compiler::new().unwrap();
         let spirv_code = compiler
             .compile_into_spirv(
@@ -532,6 +991,8 @@ fn compile_shaders(timestamps: &mut Timestamps) -> anyhow::Result<bool> {
                     "rgen" => ShaderKind::RayGeneration,
                     "rchit" => ShaderKind::ClosestHit,
                     "rmiss" => ShaderKind::Miss,
+                    "task" => ShaderKind::Task,
+                    "mesh" => ShaderKind::Mesh,
                     _ => unimplemented!(),
                 },
                 &path.to_string_lossy(),
@@ -559,9 +1020,11 @@ fn compile_shaders(timestamps: &mut Timestamps) -> anyhow::Result<bool> {
 
     let glsl_paths = glob([shader_dir.join("*.glsl").to_string_lossy()])?;
     let shader_paths = glob(
-        ["*.comp", "*.vert", "*.frag", "*.rgen", "*.rchit", "*.rmiss"]
-            .into_iter()
-            .map(|path| shader_dir.join(path).to_string_lossy().to_string()),
+        [
+            "*.comp", "*.vert", "*.frag", "*.rgen", "*.rchit", "*.rmiss", "*.task", "*.mesh",
+        ]
+        .into_iter()
+        .map(|path| shader_dir.join(path).to_string_lossy().to_string()),
     )?;
 
     let mut has_changes = false;
@@ -668,6 +1131,48 @@ fn export_models(timestamps: &mut Timestamps) -> anyhow::Result<bool> {
     Ok(has_changes)
 }
 
+/// Moves the refs of an exported scene to the placements saved by the level editor.
+fn apply_placements(scene_path: &Path, placements_path: &Path) -> anyhow::Result<()> {
+    info!("Applying {}", placements_path.display());
+
+    let placements: Placements = toml::from_str(&read_to_string(placements_path)?)?;
+    let mut scene: toml::Value = toml::from_str(&read_to_string(scene_path)?)?;
+
+    let scene_refs = scene
+        .get_mut("scene")
+        .and_then(|scene| scene.get_mut("ref"))
+        .and_then(toml::Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(toml::Value::as_table_mut);
+
+    for scene_ref in scene_refs {
+        let Some(placement) = scene_ref
+            .get("id")
+            .and_then(toml::Value::as_str)
+            .and_then(|id| placements.get(id))
+        else {
+            continue;
+        };
+
+        let floats = |values: &[f32]| {
+            toml::Value::Array(
+                values
+                    .iter()
+                    .map(|&value| toml::Value::Float(value as _))
+                    .collect(),
+            )
+        };
+
+        scene_ref.insert("position".to_owned(), floats(&placement.position));
+        scene_ref.insert("rotation".to_owned(), floats(&placement.rotation));
+    }
+
+    write(scene_path, toml::to_string(&scene)?)?;
+
+    Ok(())
+}
+
 fn export_scenes(timestamps: &mut Timestamps) -> anyhow::Result<bool> {
     rerun_if_changed("bin/blender_export_scene.py");
 
@@ -684,7 +1189,24 @@ fn export_scenes(timestamps: &mut Timestamps) -> anyhow::Result<bool> {
 
         let mut toml_path = entry_path.clone();
         toml_path.set_extension("toml");
-        if has_changed(&entry_path, timestamps) || has_changed(&toml_path, timestamps) {
+
+        // Placements are optional, so a missing file is not a change
+        let placements_path = entry_path
+            .with_file_name(placement::DIR_NAME)
+            .join(toml_path.file_name().unwrap());
+        let has_placements = metadata(&placements_path).is_ok();
+
+        // Thumbnails are shown by the level select screen
+        let thumbnail_path = entry_path
+            .with_file_name("thumbnail")
+            .join(toml_path.file_name().unwrap())
+            .with_extension("png");
+
+        if has_changed(&entry_path, timestamps)
+            || has_changed(&toml_path, timestamps)
+            || has_placements && has_changed(&placements_path, timestamps)
+            || metadata(&thumbnail_path).is_err()
+        {
             has_changes = true;
 
             if metadata(&toml_path).is_ok() {
@@ -693,6 +1215,8 @@ fn export_scenes(timestamps: &mut Timestamps) -> anyhow::Result<bool> {
 
             info!("Exporting {}", toml_path.display());
 
+            create_dir_all(thumbnail_path.parent().unwrap()).context("Creating thumbnails")?;
+
             let mut blender = Command::new(BLENDER_PATH.as_os_str())
                 .arg(entry_path.as_os_str().to_string_lossy().as_ref())
                 .arg("--background")
@@ -700,6 +1224,8 @@ fn export_scenes(timestamps: &mut Timestamps) -> anyhow::Result<bool> {
                 .args(["--python", "bin/blender_export_scene.py"])
                 .arg("--")
                 .arg(toml_path.as_os_str().to_string_lossy().as_ref())
+                .arg("--thumbnail")
+                .arg(thumbnail_path.as_os_str().to_string_lossy().as_ref())
                 .current_dir(CARGO_MANIFEST_DIR.as_path())
                 .spawn()
                 .context("Spawning blender")?;
@@ -707,9 +1233,33 @@ fn export_scenes(timestamps: &mut Timestamps) -> anyhow::Result<bool> {
                 bail!("Blender failed");
             }
 
+            if has_placements {
+                apply_placements(&toml_path, &placements_path).context("Applying placements")?;
+                timestamps.insert(
+                    placements_path.clone(),
+                    metadata(&placements_path)?.modified()?,
+                );
+            }
+
             timestamps.insert(entry_path.clone(), metadata(&entry_path)?.modified()?);
         }
     }
 
     Ok(has_changes)
 }
+
+/// Returns `true` if any table of `art/table`, which the art pak holds as blobs, has changed.
+fn tables_changed(timestamps: &mut Timestamps) -> anyhow::Result<bool> {
+    let tables = glob([CARGO_MANIFEST_DIR.join("art/table/*.tbl").to_str().unwrap()])
+        .context("Reading tables")?;
+
+    let mut has_changes = false;
+    for entry in &tables {
+        if has_changed(entry, timestamps) {
+            has_changes = true;
+            timestamps.insert(entry.clone(), metadata(entry)?.modified()?);
+        }
+    }
+
+    Ok(has_changes)
+}
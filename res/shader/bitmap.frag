@@ -3,6 +3,7 @@
 
 layout(push_constant) uniform PushConstants {
     layout(offset = 40) uint atlas_idx;
+    uint tint;
 } push_const;
 
 layout(binding = 0) uniform sampler2D atlas_sampler_nne[];
@@ -12,5 +13,6 @@ layout(location = 0) in vec2 texture0;
 layout(location = 0) out vec4 color_out;
 
 void main() {
-    color_out = texture(atlas_sampler_nne[push_const.atlas_idx], texture0);
+    color_out = texture(atlas_sampler_nne[push_const.atlas_idx], texture0)
+              * unpackUnorm4x8(push_const.tint);
 }
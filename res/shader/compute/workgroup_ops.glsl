@@ -0,0 +1,85 @@
+// Reductions and scans of WORKGROUP_OPS_TYPE values across the invocations of a workgroup, which
+// the including shader defines before including this file after declaring its workgroup size.
+//
+// Devices with subgroup arithmetic run each workgroup as a single subgroup, so these are subgroup
+// operations. Otherwise SHARED_WORKGROUP_OPS is defined and they go through shared memory, which
+// requires a power-of-two workgroup size and that every invocation of the workgroup calls them.
+
+#ifdef SHARED_WORKGROUP_OPS
+
+shared WORKGROUP_OPS_TYPE workgroup_ops_buf[gl_WorkGroupSize.x];
+
+WORKGROUP_OPS_TYPE workgroup_add(WORKGROUP_OPS_TYPE value) {
+    uint idx = gl_LocalInvocationIndex;
+
+    workgroup_ops_buf[idx] = value;
+    barrier();
+
+    for (uint stride = gl_WorkGroupSize.x >> 1; stride > 0; stride >>= 1) {
+        if (idx < stride) {
+            workgroup_ops_buf[idx] += workgroup_ops_buf[idx + stride];
+        }
+
+        barrier();
+    }
+
+    WORKGROUP_OPS_TYPE sum = workgroup_ops_buf[0];
+    barrier();
+
+    return sum;
+}
+
+bool workgroup_elect() {
+    return gl_LocalInvocationIndex == 0;
+}
+
+WORKGROUP_OPS_TYPE workgroup_exclusive_add(WORKGROUP_OPS_TYPE value) {
+    uint idx = gl_LocalInvocationIndex;
+
+    workgroup_ops_buf[idx] = value;
+    barrier();
+
+    for (uint offset = 1; offset < gl_WorkGroupSize.x; offset <<= 1) {
+        WORKGROUP_OPS_TYPE addend = idx >= offset
+                                  ? workgroup_ops_buf[idx - offset]
+                                  : WORKGROUP_OPS_TYPE(0);
+        barrier();
+
+        workgroup_ops_buf[idx] += addend;
+        barrier();
+    }
+
+    WORKGROUP_OPS_TYPE sum = workgroup_ops_buf[idx] - value;
+    barrier();
+
+    return sum;
+}
+
+WORKGROUP_OPS_TYPE workgroup_max(WORKGROUP_OPS_TYPE value) {
+    uint idx = gl_LocalInvocationIndex;
+
+    workgroup_ops_buf[idx] = value;
+    barrier();
+
+    for (uint stride = gl_WorkGroupSize.x >> 1; stride > 0; stride >>= 1) {
+        if (idx < stride) {
+            workgroup_ops_buf[idx] = max(workgroup_ops_buf[idx], workgroup_ops_buf[idx + stride]);
+        }
+
+        barrier();
+    }
+
+    WORKGROUP_OPS_TYPE res = workgroup_ops_buf[0];
+    barrier();
+
+    return res;
+}
+
+#else
+
+#define workgroup_add subgroupAdd
+#define workgroup_elect subgroupElect
+#define workgroup_exclusive_add subgroupExclusiveAdd
+#define workgroup_max subgroupMax
+
+#endif
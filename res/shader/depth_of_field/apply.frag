@@ -0,0 +1,29 @@
+#version 460 core
+
+layout(push_constant) uniform PushConstants {
+    layout(offset = 0) float z_near;
+    layout(offset = 4) float focus_distance;
+    layout(offset = 8) float focus_range;
+} push_const;
+
+layout(binding = 0) uniform sampler2D blur_sampler;
+layout(binding = 1) uniform sampler2D depth_sampler;
+
+layout(location = 0) in vec2 ndc;
+
+// Blended over the framebuffer by the amount each pixel is out of focus
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    vec2 uv = ndc * 0.5 + 0.5;
+    float depth = textureLod(depth_sampler, uv, 0.0).r;
+
+    // Depth is reverse-Z with an infinite far plane, so view depth is the near plane over depth
+    // and the sky, left at zero, is as far as anything may be
+    float view_depth = depth > 0.0 ? push_const.z_near / depth : 1e30;
+    float blur = clamp(abs(view_depth - push_const.focus_distance) / push_const.focus_range,
+                       0.0,
+                       1.0);
+
+    color_out = vec4(textureLod(blur_sampler, uv, 0.0).rgb, blur);
+}
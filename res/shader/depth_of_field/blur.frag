@@ -0,0 +1,30 @@
+#version 460 core
+
+// Samples spread over a disc by the golden angle, which gives the round blur of a lens
+const uint SAMPLE_COUNT = 24;
+const float GOLDEN_ANGLE = 2.39996323;
+
+layout(push_constant) uniform PushConstants {
+    layout(offset = 0) vec2 radius;
+} push_const;
+
+layout(binding = 0) uniform sampler2D color_sampler;
+
+layout(location = 0) in vec2 ndc;
+
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    vec2 uv = ndc * 0.5 + 0.5;
+    vec3 color = vec3(0.0);
+
+    for (uint idx = 0; idx < SAMPLE_COUNT; idx++) {
+        float distance = sqrt((float(idx) + 0.5) / float(SAMPLE_COUNT));
+        float angle = float(idx) * GOLDEN_ANGLE;
+        vec2 offset = vec2(cos(angle), sin(angle)) * distance * push_const.radius;
+
+        color += textureLod(color_sampler, uv + offset, 0.0).rgb;
+    }
+
+    color_out = vec4(color / float(SAMPLE_COUNT), 1.0);
+}
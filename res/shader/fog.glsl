@@ -0,0 +1,34 @@
+// Exponential height fog: the density of the fog falls off exponentially with height above
+// fog_height, so that low ground fills with fog while high ground stays clear
+//
+// Parameters match the fog of Sky (src/render/sky.rs)
+
+// The sky is fogged as though it were a surface this many meters away
+const float FOG_SKY_DISTANCE = 1000.0;
+
+// Returns how much, from zero to one, of the light travelling along a ray is replaced by fog; the
+// direction need not be normalized
+float fog_amount(vec3 origin,
+                 vec3 direction,
+                 float distance,
+                 float density,
+                 float height,
+                 float height_falloff) {
+    if (density <= 0.0) {
+        return 0.0;
+    }
+
+    direction = normalize(direction);
+
+    // The density along the ray is integrated in closed form, which is the density at the origin
+    // times the distance when the ray is level or the fog is even
+    float origin_density = density * exp(-height_falloff * (origin.y - height));
+    float falloff = height_falloff * direction.y * distance;
+    float optical_depth = origin_density * distance;
+
+    if (abs(falloff) > 1e-4) {
+        optical_depth *= (1.0 - exp(-falloff)) / falloff;
+    }
+
+    return 1.0 - exp(-optical_depth);
+}
@@ -0,0 +1,14 @@
+#version 460 core
+
+layout(location = 0) out vec2 ndc_out;
+
+void main() {
+    // A single triangle which covers the screen
+    vec2 ndc = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2) * 2.0 - 1.0;
+
+    ndc_out = ndc;
+
+    // Depth of zero is infinitely far when using reverse-Z, so depth tests may select pixels
+    // which no model covered
+    gl_Position = vec4(ndc, 0.0, 1.0);
+}
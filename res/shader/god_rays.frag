@@ -0,0 +1,45 @@
+#version 460 core
+
+#include "sky.glsl"
+
+layout(push_constant) uniform PushConstants {
+    layout(offset = 0) vec3 sun_direction;
+    layout(offset = 12) float turbidity;
+    layout(offset = 16) vec2 sun_position;
+    layout(offset = 24) float intensity;
+} push_const;
+
+layout(binding = 0) uniform sampler2D depth_sampler;
+
+layout(location = 0) in vec2 ndc;
+
+layout(location = 0) out vec4 color_out;
+
+// Samples taken along the line from each pixel to the sun
+const uint SAMPLE_COUNT = 48u;
+
+// Weight kept by each sample relative to the one before, so that shafts fade away from the sun
+const float DECAY = 0.96;
+
+void main() {
+    vec2 tex_coord = ndc * 0.5 + 0.5;
+    vec2 sample_step = (push_const.sun_position - tex_coord) / float(SAMPLE_COUNT);
+    float weight = 1.0;
+    float light = 0.0;
+
+    // Sunlight passes only through pixels of sky, which are left at the cleared depth of zero with
+    // reverse-Z, so models in front of the sun cast shafts of shadow between the shafts of light
+    for (uint idx = 0u; idx < SAMPLE_COUNT; idx++) {
+        tex_coord += sample_step;
+        light += weight * float(textureLod(depth_sampler, tex_coord, 0.0).r == 0.0);
+        weight *= DECAY;
+    }
+
+    // Sunlight is dimmed and reddened by the air it passes through, as in sky_color
+    vec3 sun_direction = normalize(push_const.sun_direction);
+    vec3 extinction = SKY_RAYLEIGH + SKY_MIE * push_const.turbidity;
+    vec3 sunlight = exp(-extinction * sky_air_mass(sun_direction.y))
+                  * smoothstep(-0.15, 0.05, sun_direction.y);
+
+    color_out = vec4(sunlight * light * push_const.intensity / float(SAMPLE_COUNT), 0.0);
+}
@@ -0,0 +1,24 @@
+#version 460 core
+
+const vec4 EDGE_COLOR = vec4(1.0, 1.0, 1.0, 0.8);
+const vec4 FILL_COLOR = vec4(0.0, 0.6, 1.0, 0.25);
+const vec4 HIGHLIGHT_COLOR = vec4(1.0, 0.8, 0.0, 0.5);
+
+layout(push_constant) uniform PushConstants {
+    layout(offset = 64) uint highlight_triangle_index;
+    layout(offset = 68) uint edges;
+} push_const;
+
+layout(location = 0) flat in uint triangle_index;
+
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    if (push_const.edges != 0) {
+        color_out = EDGE_COLOR;
+    } else if (triangle_index == push_const.highlight_triangle_index) {
+        color_out = HIGHLIGHT_COLOR;
+    } else {
+        color_out = FILL_COLOR;
+    }
+}
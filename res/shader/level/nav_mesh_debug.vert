@@ -0,0 +1,18 @@
+#version 460 core
+
+layout(push_constant) uniform PushConstants {
+    layout(offset = 0) mat4 projection_view;
+} push_const;
+
+// Three vertices for each triangle of the navigation mesh, in triangle order
+layout(binding = 0) restrict readonly buffer VertexBuffer {
+    vec4 vertex_buf[];
+};
+
+layout(location = 0) flat out uint triangle_index_out;
+
+void main() {
+    triangle_index_out = gl_VertexIndex / 3;
+
+    gl_Position = push_const.projection_view * vec4(vertex_buf[gl_VertexIndex].xyz, 1.0);
+}
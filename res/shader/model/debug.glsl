@@ -0,0 +1,30 @@
+// Values of DebugMode (src/render/debug.rs)
+const uint DEBUG_MODE_OFF = 0u;
+const uint DEBUG_MODE_WIREFRAME = 1u;
+const uint DEBUG_MODE_NORMALS = 2u;
+const uint DEBUG_MODE_TEX_COORDS = 3u;
+const uint DEBUG_MODE_OVERDRAW = 4u;
+const uint DEBUG_MODE_MESH_IDS = 5u;
+
+// Each layer of overdraw adds this color, so overlapping layers heat up from red to white
+const vec3 DEBUG_OVERDRAW_COLOR = vec3(0.25, 0.08, 0.02);
+
+const vec3 DEBUG_WIREFRAME_COLOR = vec3(1.0);
+
+// Returns a distinct, stable color for the given id
+vec3 debug_id_color(uint id) {
+    // Integer hash from "Hash Functions for GPU Rendering" (Jarzynski and Olano, 2020)
+    uint state = id * 747796405u + 2891336453u;
+    uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    uint hash = (word >> 22u) ^ word;
+
+    return vec3(uvec3(hash, hash >> 8u, hash >> 16u) & 0xFFu) / 255.0;
+}
+
+vec3 debug_normal_color(vec3 normal) {
+    return 0.5 * normalize(normal) + 0.5;
+}
+
+vec3 debug_tex_coord_color(vec2 tex_coord) {
+    return vec3(fract(tex_coord), 0.0);
+}
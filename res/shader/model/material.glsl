@@ -1,7 +1,87 @@
 const uint8_t MATERIAL_FLAGS_EMISSIVE = uint8_t(1);
+const uint8_t MATERIAL_FLAGS_MASKED = uint8_t(2);
+const uint8_t MATERIAL_FLAGS_TWO_SIDED = uint8_t(4);
+
+// Masked and two-sided materials are each drawn using their own raster pipeline variant
+const uint MATERIAL_VARIANT_COUNT = 4u;
+
+// Material textures are stored consecutively, starting with color (see ModelBuffer::load_material)
+const uint MATERIAL_TEXTURE_NORMAL = 1u;
+const uint MATERIAL_TEXTURE_PARAMS = 2u;
+const uint MATERIAL_TEXTURE_EMISSIVE = 3u;
 
 struct Material {
     uint32_t color_idx;
     uint8_t flags;
-    uint8_t[3] _0;
+
+    // Selects the sampler of the material textures (see material_fns.glsl)
+    uint8_t sampler_idx;
+
+    uint8_t[2] _0;
+
+    // Texture coordinates added each second
+    vec2 scroll;
+
+    // Flipbook frames shown each second
+    float fps;
+
+    // Flipbook frame count in the low 16 bits followed by the columns and rows of the frame grid,
+    // or zero (see MaterialData::pack_frames)
+    uint32_t frames;
+
+    uint32_t[2] _1;
+
+    // Multiplies the emissive texture (see ModelBuffer::set_material_emissive)
+    vec3 emissive_color;
+    float emissive_intensity;
 };
+
+// Returns the raster pipeline variant of a material (see MaterialVariant in raster.rs)
+uint material_variant(uint8_t flags) {
+    return ((flags & MATERIAL_FLAGS_MASKED) != uint8_t(0) ? 1u : 0u)
+         | ((flags & MATERIAL_FLAGS_TWO_SIDED) != uint8_t(0) ? 2u : 0u);
+}
+
+// Returns the light given off by a material with the given emissive texel, or none if the material
+// is not emissive
+vec3 material_emission(Material material, vec4 emissive_texel) {
+    if ((material.flags & MATERIAL_FLAGS_EMISSIVE) == uint8_t(0)) {
+        return vec3(0.0);
+    }
+
+    return emissive_texel.rgb * material.emissive_color * material.emissive_intensity;
+}
+
+// Normal textures may be BC5-compressed, which only stores X and Y, so Z is always rebuilt from
+// the unit length of the tangent-space normal
+vec3 material_normal(vec4 normal_texel) {
+    vec2 xy = normal_texel.rg * 2.0 - 1.0;
+
+    return vec3(xy, sqrt(max(1.0 - dot(xy, xy), 0.0)));
+}
+
+// Returns the size of one flipbook frame within the textures of a material
+vec2 material_frame_size(Material material) {
+    if (material.frames == 0u) {
+        return vec2(1.0);
+    }
+
+    return 1.0 / vec2((material.frames >> 16) & 0xFFu, material.frames >> 24);
+}
+
+// Returns the animated texture coordinates of a material: scrolled and then moved into the current
+// flipbook frame
+vec2 material_uv(Material material, vec2 uv, float time) {
+    uv += material.scroll * time;
+
+    if (material.frames == 0u) {
+        return uv;
+    }
+
+    uint frame_count = material.frames & 0xFFFFu;
+    uint columns = (material.frames >> 16) & 0xFFu;
+    uint frame = uint(time * material.fps) % max(frame_count, 1u);
+    vec2 frame_size = material_frame_size(material);
+
+    return (vec2(frame % columns, frame / columns) + fract(uv)) * frame_size;
+}
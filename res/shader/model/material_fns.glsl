@@ -0,0 +1,48 @@
+// Material textures are sampled with one of a small set of immutable samplers, which each material
+// selects (see MaterialSampler in material_animation.rs); the shader which includes this declares
+// the material_textures array
+
+layout(binding = 12) uniform sampler material_sampler_0;
+layout(binding = 13) uniform sampler material_sampler_1;
+layout(binding = 14) uniform sampler material_sampler_2;
+layout(binding = 15) uniform sampler material_sampler_3;
+layout(binding = 16) uniform sampler material_sampler_4;
+layout(binding = 17) uniform sampler material_sampler_5;
+layout(binding = 18) uniform sampler material_sampler_6;
+layout(binding = 19) uniform sampler material_sampler_7;
+
+// Opaque types may not be returned from functions, so each sampler has its own case
+#define MATERIAL_SAMPLERS(SAMPLE) \
+    switch (uint(material.sampler_idx)) { \
+        case 0u: return SAMPLE(material_sampler_0); \
+        case 1u: return SAMPLE(material_sampler_1); \
+        case 3u: return SAMPLE(material_sampler_3); \
+        case 4u: return SAMPLE(material_sampler_4); \
+        case 5u: return SAMPLE(material_sampler_5); \
+        case 6u: return SAMPLE(material_sampler_6); \
+        case 7u: return SAMPLE(material_sampler_7); \
+        /* Repeating, linear and fully anisotropic, as materials are by default */ \
+        default: return SAMPLE(material_sampler_2); \
+    }
+
+// Samples a texture of a material using explicit gradients
+vec4 material_texture_grad(Material material, uint texture_idx, vec2 uv, vec2 uv_ddx, vec2 uv_ddy) {
+#define SAMPLE(material_sampler) textureGrad( \
+    sampler2D(material_textures[nonuniformEXT(texture_idx)], material_sampler), uv, uv_ddx, uv_ddy)
+
+    MATERIAL_SAMPLERS(SAMPLE)
+
+#undef SAMPLE
+}
+
+// Samples a texture of a material at the given mip level
+vec4 material_texture_lod(Material material, uint texture_idx, vec2 uv, float lod) {
+#define SAMPLE(material_sampler) textureLod( \
+    sampler2D(material_textures[nonuniformEXT(texture_idx)], material_sampler), uv, lod)
+
+    MATERIAL_SAMPLERS(SAMPLE)
+
+#undef SAMPLE
+}
+
+#undef MATERIAL_SAMPLERS
@@ -0,0 +1,21 @@
+// Indices and vertices share the geometry buffer, which is read using its device address; the
+// shader must declare push_const.geometry_address before including this file
+
+layout(buffer_reference, std430, buffer_reference_align = 2)
+restrict readonly buffer Index16Buffer {
+    uint16_t[] indices;
+};
+
+layout(buffer_reference, std430, buffer_reference_align = 4)
+restrict readonly buffer Index32Buffer {
+    uint32_t[] indices;
+};
+
+layout(buffer_reference, std430, buffer_reference_align = 4)
+restrict readonly buffer VertexBuffer {
+    float32_t[] vertices;
+};
+
+#define index16_buf Index16Buffer(push_const.geometry_address).indices
+#define index32_buf Index32Buffer(push_const.geometry_address).indices
+#define vertex_buf VertexBuffer(push_const.geometry_address).vertices
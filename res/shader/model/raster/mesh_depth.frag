@@ -0,0 +1,4 @@
+#version 460 core
+
+// The depth pre-pass only writes depth, so that the shading pass which follows runs each pixel once
+void main() {}
@@ -3,33 +3,130 @@
 #extension GL_EXT_shader_explicit_arithmetic_types_int8 : require
 #extension GL_EXT_shader_explicit_arithmetic_types_int32 : require
 
+#include "../../fog.glsl"
+#include "../../sky.glsl"
+#include "../debug.glsl"
 #include "../material.glsl"
+#include "../reflection_probe.glsl"
+
+layout(constant_id = 0) const uint DEBUG_MODE = 0u; // DEBUG_MODE_OFF
+layout(constant_id = 1) const bool MASKED = false;
 
 layout(binding = 0) uniform CameraBuffer {
     mat4 projection_view;
+    vec3 position;
+    uint reflection_probe_count;
+    vec3 sun_direction;
+    float sky_turbidity;
+    float time;
+    float fog_density;
+    float fog_height;
+    float fog_height_falloff;
+    vec3 fog_color;
 } camera;
 
-layout(binding = 8) restrict readonly buffer MaterialBuffer {
+layout(binding = 5) restrict readonly buffer MaterialBuffer {
     Material[] material_buf;
 };
 
-layout(binding = 9) uniform sampler2D texture_sampler_llr[];
+layout(binding = 6) uniform texture2D material_textures[];
+
+layout(binding = 7) restrict readonly buffer ReflectionProbeBuffer {
+    ReflectionProbe[] reflection_probe_buf;
+};
+
+layout(binding = 8) uniform samplerCubeArray reflection_probe_sampler;
+
+#include "../material_fns.glsl"
+#include "../reflection_probe_fns.glsl"
 
 layout(location = 0) in vec3 world_position;
 layout(location = 1) in vec3 world_normal;
 layout(location = 2) in vec2 texture0;
 layout(location = 3) flat in uint material_idx;
+layout(location = 4) flat in uint mesh_idx;
+layout(location = 5) flat in vec4 tint;
+layout(location = 6) flat in vec2 material_params_scale;
+layout(location = 8) in vec4 world_tangent;
 
 layout(location = 0) out vec4 color_out;
 
 void main() {
+    switch (DEBUG_MODE) {
+        case DEBUG_MODE_WIREFRAME:
+            color_out = vec4(DEBUG_WIREFRAME_COLOR, 1.0);
+            return;
+        case DEBUG_MODE_NORMALS:
+            color_out = vec4(debug_normal_color(world_normal), 1.0);
+            return;
+        case DEBUG_MODE_TEX_COORDS:
+            color_out = vec4(debug_tex_coord_color(texture0), 1.0);
+            return;
+        case DEBUG_MODE_OVERDRAW:
+            color_out = vec4(DEBUG_OVERDRAW_COLOR, 1.0);
+            return;
+        case DEBUG_MODE_MESH_IDS:
+            color_out = vec4(debug_id_color(mesh_idx), 1.0);
+            return;
+    }
+
     Material material = material_buf[material_idx];
 
-    color_out = texture(texture_sampler_llr[nonuniformEXT(material.color_idx)], texture0);
+    // Flipbook frames wrap within their cell of the textures, so gradients come from the original
+    // coordinates to avoid selecting the smallest mip level along the seams
+    vec2 uv = material_uv(material, texture0, camera.time);
+    vec2 uv_ddx = dFdx(texture0) * material_frame_size(material);
+    vec2 uv_ddy = dFdy(texture0) * material_frame_size(material);
+
+    color_out = material_texture_grad(material, material.color_idx, uv, uv_ddx, uv_ddy) * tint;
 
-    float lit = dot(normalize(vec3(0.2, 1, 0)), world_normal);
+    // Masked materials, such as foliage and grates, cut out their transparent parts
+    if (MASKED && color_out.a < 0.5) {
+        discard;
+    }
+
+    // Params hold roughness and metalness in the red and green channels
+    vec4 params = material_texture_grad(
+        material, material.color_idx + MATERIAL_TEXTURE_PARAMS, uv, uv_ddx, uv_ddy);
+    float roughness = clamp(params.r * material_params_scale.x, 0.0, 1.0);
+    float metalness = clamp(params.g * material_params_scale.y, 0.0, 1.0);
+
+    // Normal textures are in tangent space; the sign of the tangent W selects the bitangent
+    vec3 normal = normalize(world_normal);
+    vec3 tangent = normalize(world_tangent.xyz - normal * dot(normal, world_tangent.xyz));
+    vec3 bitangent = cross(normal, tangent) * world_tangent.w;
+    normal = normalize(mat3(tangent, bitangent, normal) * material_normal(material_texture_grad(
+        material, material.color_idx + MATERIAL_TEXTURE_NORMAL, uv, uv_ddx, uv_ddy)));
+
+    vec3 view_dir = normalize(world_position - camera.position);
+    vec3 reflect_dir = reflect(view_dir, normal);
+    vec3 reflection = camera.reflection_probe_count == 0
+                    ? sky_color(reflect_dir, camera.sun_direction, camera.sky_turbidity)
+                    : reflection_probe_color(camera.reflection_probe_count,
+                                             world_position,
+                                             reflect_dir);
+
+    // Metals reflect using their own color while other surfaces have a faint reflection which
+    // fades as they roughen
+    vec3 specular = mix(vec3(0.04 * (1.0 - roughness)), color_out.rgb, metalness) * reflection;
+
+    float lit = dot(normalize(vec3(0.2, 1, 0)), normal);
     //color_out.rgb = vec3(1);
-    color_out.rgb *= world_normal;
+    color_out.rgb *= normal * (1.0 - metalness);
+    color_out.rgb += specular;
+
+    if ((material.flags & MATERIAL_FLAGS_EMISSIVE) != uint8_t(0)) {
+        color_out.rgb += material_emission(material, material_texture_grad(
+            material, material.color_idx + MATERIAL_TEXTURE_EMISSIVE, uv, uv_ddx, uv_ddy));
+    }
+
+    float fog = fog_amount(camera.position,
+                           world_position - camera.position,
+                           distance(world_position, camera.position),
+                           camera.fog_density,
+                           camera.fog_height,
+                           camera.fog_height_falloff);
+    color_out.rgb = mix(color_out.rgb, camera.fog_color, fog);
 
     //vec3 camera_dir = normalize(camera.position);
     //float light = abs(dot(ubo.camera_pos, normal));
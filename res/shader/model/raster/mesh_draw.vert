@@ -1,8 +1,10 @@
 #version 450
+#extension GL_EXT_buffer_reference : require
 #extension GL_EXT_shader_explicit_arithmetic_types_float32 : require
 #extension GL_EXT_shader_explicit_arithmetic_types_int8 : require
 #extension GL_EXT_shader_explicit_arithmetic_types_int16 : require
 #extension GL_EXT_shader_explicit_arithmetic_types_int32 : require
+#extension GL_EXT_shader_explicit_arithmetic_types_int64 : require
 
 #include "../../quat.glsl"
 #include "../mesh.glsl"
@@ -17,36 +19,38 @@ layout(binding = 1) restrict readonly buffer DrawInstanceBuffer {
     uint32_t[] draw_instance_buf;
 };
 
-layout(binding = 2) buffer Index16Buffer {
-    uint16_t[] index16_buf;
-};
-
-layout(binding = 3) buffer Index32Buffer {
-    uint32_t[] index32_buf;
-};
-
-layout(binding = 4) buffer VertexBuffer {
-    float32_t[] vertex_buf;
-};
-
-layout(binding = 5) restrict readonly buffer MeshInstanceBuffer {
+layout(binding = 2) restrict readonly buffer MeshInstanceBuffer {
     MeshInstance[] mesh_instance_buf;
 };
 
-layout(binding = 6) restrict readonly buffer MeshBuffer {
+layout(binding = 3) restrict readonly buffer MeshBuffer {
     Mesh[] mesh_buf;
 };
 
-layout(binding = 7) restrict readonly buffer ModelInstanceBuffer {
+layout(binding = 4) restrict readonly buffer ModelInstanceBuffer {
     ModelInstance[] model_instance_buf;
 };
 
+layout(push_constant) uniform PushConstants {
+    uint64_t geometry_address;
+} push_const;
+
+#include "geometry_buf.glsl"
 #include "../mesh_fns.glsl"
 
 layout(location = 0) out vec3 world_position_out;
 layout(location = 1) out vec3 world_normal_out;
 layout(location = 2) out vec2 texture_out;
 layout(location = 3) flat out uint material_idx_out;
+layout(location = 4) flat out uint mesh_idx_out;
+layout(location = 5) flat out vec4 tint_out;
+layout(location = 6) flat out vec2 material_params_scale_out;
+layout(location = 7) flat out uint model_instance_idx_out;
+layout(location = 8) out vec4 world_tangent_out;
+
+// The depth pre-pass and the shading pass test depth for equality, so both pipelines which use
+// this shader must produce bit-identical positions
+invariant gl_Position;
 
 void main() {
     uint mesh_instance_idx = draw_instance_buf[gl_InstanceIndex];
@@ -59,12 +63,20 @@ void main() {
     Vertex vertex = mesh_vertex(mesh, vertex_index);
 
     world_normal_out = quat_transform(model_instance.rotation, vertex.normal);
+    world_tangent_out = vec4(quat_transform(model_instance.rotation, vertex.tangent.xyz),
+                             vertex.tangent.w);
     world_position_out = quat_transform(model_instance.rotation, vertex.position)
                        + model_instance.translation;
 
     texture_out = vertex.texture0;
 
     material_idx_out = material_idx;
+    mesh_idx_out = mesh_instance.mesh_idx;
+    model_instance_idx_out = mesh_instance.model_instance_idx;
+
+    tint_out = model_instance.tint;
+    material_params_scale_out = vec2(model_instance.roughness_scale,
+                                     model_instance.metalness_scale);
 
     gl_Position = camera.projection_view
                 * vec4(world_position_out, 1.0);
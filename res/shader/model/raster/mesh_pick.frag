@@ -0,0 +1,50 @@
+#version 460 core
+#extension GL_EXT_nonuniform_qualifier : require
+#extension GL_EXT_shader_explicit_arithmetic_types_int8 : require
+#extension GL_EXT_shader_explicit_arithmetic_types_int32 : require
+
+#include "../material.glsl"
+
+layout(binding = 0) uniform CameraBuffer {
+    mat4 projection_view;
+    vec3 position;
+    uint reflection_probe_count;
+    vec3 sun_direction;
+    float sky_turbidity;
+    float time;
+} camera;
+
+layout(binding = 5) restrict readonly buffer MaterialBuffer {
+    Material[] material_buf;
+};
+
+layout(binding = 6) uniform texture2D material_textures[];
+
+#include "../material_fns.glsl"
+
+layout(location = 2) in vec2 texture0;
+layout(location = 3) flat in uint material_idx;
+layout(location = 5) flat in vec4 tint;
+layout(location = 7) flat in uint model_instance_idx;
+
+layout(location = 0) out uint pick_id_out;
+
+void main() {
+    Material material = material_buf[material_idx];
+
+    // Masked materials are cut out exactly as mesh_draw.frag cuts them, so picks pass through the
+    // gaps of foliage and grates
+    if ((material.flags & MATERIAL_FLAGS_MASKED) != uint8_t(0)) {
+        vec2 uv = material_uv(material, texture0, camera.time);
+        vec2 uv_ddx = dFdx(texture0) * material_frame_size(material);
+        vec2 uv_ddy = dFdy(texture0) * material_frame_size(material);
+        float alpha = material_texture_grad(material, material.color_idx, uv, uv_ddx, uv_ddy).a;
+
+        if (alpha * tint.a < 0.5) {
+            discard;
+        }
+    }
+
+    // Zero is cleared where nothing is drawn
+    pick_id_out = model_instance_idx + 1;
+}
@@ -0,0 +1,13 @@
+// Triangles drawn by each mesh shader workgroup, one per invocation
+const uint MESHLET_TRIANGLE_COUNT = 64;
+
+// Each task shader workgroup draws one mesh instance as a number of meshlets
+struct MeshTaskPayload {
+    uint32_t mesh_instance_idx;
+};
+
+struct TaskCommand {
+    uint32_t group_count_x;
+    uint32_t group_count_y;
+    uint32_t group_count_z;
+};
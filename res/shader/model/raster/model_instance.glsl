@@ -2,4 +2,9 @@ struct ModelInstance {
     uint32_t[8] material_indices;
     f32vec4 rotation;
     f32vec3 translation;
+    uint32_t model_idx;
+    f32vec4 tint;
+    float32_t roughness_scale;
+    float32_t metalness_scale;
+    uint32_t layer_mask;
 };
@@ -0,0 +1,10 @@
+// A mesh of a model instance which uses an emissive material (see Light in ray_trace.rs)
+struct Light {
+    // Rows of the object-to-world matrix: points are transformed by vec4(p, 1.0) * transform
+    mat3x4 transform;
+
+    uint32_t model_instance_index;
+    uint32_t mesh_index;
+    uint32_t triangle_count;
+    uint32_t _0;
+};
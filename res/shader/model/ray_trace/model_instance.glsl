@@ -1,4 +1,8 @@
 struct ModelInstance {
     uint32_t[8] material_indices;
     uint32_t mesh_index;
+    f32vec4 tint;
+    float32_t roughness_scale;
+    float32_t metalness_scale;
+    f32vec4 highlight;
 };
@@ -2,5 +2,29 @@ struct RayPayload {
     vec3 origin;
     vec3 direction;
 
+    // Light emitted by the surface hit or the sky, or the debug color of the surface
     vec3 color;
+
+    // Diffuse reflectance of the surface hit, which the ray gen shader lights
+    vec3 albedo;
+
+    // World-space normal of the surface hit, facing the ray origin
+    vec3 normal;
+
+    // Distance along the ray direction of the hit, or a negative value for a miss
+    float hit_t;
+
+    // One of the DEBUG_MODE_* values from debug.glsl
+    uint debug_mode;
+
+    // Seconds used to animate materials
+    float time;
+
+    // One more than the index of the model instance hit, or zero for a miss
+    uint pick_id;
+};
+
+// Shadow rays skip closest hit shaders, so only the shadow miss shader writes this payload
+struct ShadowPayload {
+    bool visible;
 };
@@ -0,0 +1,30 @@
+// Bindings shared by the shaders which read the surfaces of the scene: closest hits shade the
+// surface which was hit and the ray gen shader samples points on emissive surfaces
+
+layout(binding = 2) buffer Index16Buffer {
+    uint16_t[] index16_buf;
+};
+
+layout(binding = 2) buffer Index32Buffer {
+    uint32_t[] index32_buf;
+};
+
+layout(binding = 2) buffer VertexBuffer {
+    float32_t[] vertex_buf;
+};
+
+layout(binding = 3) buffer MaterialBuffer {
+    Material[] material_buf;
+};
+
+layout(binding = 4) buffer MeshBuffer {
+    Mesh[] mesh_buf;
+};
+
+layout(binding = 6) buffer ModelInstanceBuffer {
+    ModelInstance[] model_instance_buf;
+};
+
+layout(binding = 7) uniform texture2D material_textures[];
+
+#include "../material_fns.glsl"
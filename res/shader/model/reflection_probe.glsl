@@ -0,0 +1,5 @@
+// Matches ReflectionProbe (src/render/model/reflection_probe.rs)
+struct ReflectionProbe {
+    vec3 position;
+    float radius;
+};
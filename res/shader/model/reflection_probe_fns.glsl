@@ -0,0 +1,22 @@
+// Requires reflection_probe_buf and reflection_probe_sampler to be declared
+
+// Returns the environment color in the given direction as seen by the reflection probe which best
+// covers the given position: the probe which is nearest relative to its radius
+//
+// There must be at least one probe
+vec3 reflection_probe_color(uint probe_count, vec3 position, vec3 direction) {
+    uint probe_idx = 0;
+    float probe_dist = 3.402823466e+38;
+
+    for (uint idx = 0; idx < probe_count; idx++) {
+        ReflectionProbe probe = reflection_probe_buf[idx];
+        float dist = distance(position, probe.position) / probe.radius;
+
+        if (dist < probe_dist) {
+            probe_idx = idx;
+            probe_dist = dist;
+        }
+    }
+
+    return texture(reflection_probe_sampler, vec4(direction, float(probe_idx))).rgb;
+}
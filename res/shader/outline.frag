@@ -0,0 +1,55 @@
+#version 460 core
+
+struct Highlight {
+    vec4 color;
+    uint id;
+};
+
+layout(push_constant) uniform PushConstants {
+    layout(offset = 0) uint highlight_count;
+    layout(offset = 4) int width;
+} push_const;
+
+// The model instance index plus one covering each pixel, or zero where there is none
+layout(binding = 0) uniform usampler2D id_sampler;
+
+layout(binding = 1) restrict readonly buffer HighlightBuffer {
+    Highlight highlight_buf[];
+};
+
+layout(location = 0) in vec2 ndc;
+
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    ivec2 size = textureSize(id_sampler, 0);
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    uint id = texelFetch(id_sampler, pixel, 0).r;
+    int width = push_const.width;
+
+    // Pixels near, but not of, a highlighted model instance are part of its outline
+    for (int y = -width; y <= width; y++) {
+        for (int x = -width; x <= width; x++) {
+            if (x * x + y * y > width * width) {
+                continue;
+            }
+
+            uint neighbor_id = texelFetch(id_sampler,
+                                          clamp(pixel + ivec2(x, y), ivec2(0), size - 1),
+                                          0).r;
+
+            if (neighbor_id == 0 || neighbor_id == id) {
+                continue;
+            }
+
+            for (uint idx = 0; idx < push_const.highlight_count; idx++) {
+                if (highlight_buf[idx].id == neighbor_id) {
+                    color_out = highlight_buf[idx].color;
+                    return;
+                }
+            }
+        }
+    }
+
+    discard;
+}
@@ -0,0 +1,12 @@
+#version 460 core
+
+// The overlay, which is the size of the framebuffer
+layout(binding = 0) uniform sampler2D overlay_sampler;
+
+layout(location = 0) in vec2 ndc;
+
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    color_out = texelFetch(overlay_sampler, ivec2(gl_FragCoord.xy), 0);
+}
@@ -4,10 +4,22 @@ layout(location = 0) in vec2 uv;
 
 layout(set = 0, binding = 0) uniform sampler2D image_sampler_nnr;
 
+// Colorblind filter (src/render/colorblind.rs), which is the identity when off
+layout(set = 0, binding = 1) uniform sampler3D lut_sampler_llc;
+
+// The HUD, premultiplied by alpha, which covers the same area as the image at a resolution which
+// dynamic resolution does not lower
+layout(set = 0, binding = 2) uniform sampler2D hud_sampler_nnr;
+
 layout(location = 0) out vec4 color;
 
 void main() {
-    vec3 image_sample = texture(image_sampler_nnr, uv).rgb;
+    vec4 hud_sample = texture(hud_sampler_nnr, uv);
+    vec3 image_sample = hud_sample.rgb + texture(image_sampler_nnr, uv).rgb * (1.0 - hud_sample.a);
+
+    // Texels are centered, so the ends of each channel sample the first and last texels exactly
+    float lut_size = float(textureSize(lut_sampler_llc, 0).x);
+    vec3 lut_coord = image_sample * ((lut_size - 1.0) / lut_size) + 0.5 / lut_size;
 
-    color = vec4(image_sample, 1.0);
+    color = vec4(texture(lut_sampler_llc, lut_coord).rgb, 1.0);
 }
@@ -0,0 +1,25 @@
+#version 460 core
+
+struct Vertex {
+    vec2 position;
+    uint color;
+    uint _0;
+};
+
+layout(push_constant) uniform PushConstants {
+    layout(offset = 0) vec2 framebuffer_size;
+} push_const;
+
+// Three vertices for each triangle, in framebuffer pixels
+layout(binding = 0) restrict readonly buffer VertexBuffer {
+    Vertex vertex_buf[];
+};
+
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    Vertex vertex = vertex_buf[gl_VertexIndex];
+
+    color_out = unpackUnorm4x8(vertex.color);
+    gl_Position = vec4(vertex.position / push_const.framebuffer_size * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,35 @@
+#version 460 core
+
+#include "fog.glsl"
+#include "sky.glsl"
+
+layout(push_constant) uniform PushConstants {
+    layout(offset = 0) mat4 inv_projection_view;
+    layout(offset = 64) vec3 camera_position;
+    layout(offset = 76) float turbidity;
+    layout(offset = 80) vec3 sun_direction;
+    layout(offset = 92) float fog_density;
+    layout(offset = 96) vec3 fog_color;
+    layout(offset = 108) float fog_height;
+    layout(offset = 112) float fog_height_falloff;
+} push_const;
+
+layout(location = 0) in vec2 ndc;
+
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    // Unproject a point on the near plane (depth of one with reverse-Z) to find the view direction
+    vec4 near_position = push_const.inv_projection_view * vec4(ndc, 1.0, 1.0);
+    vec3 direction = near_position.xyz / near_position.w - push_const.camera_position;
+
+    vec3 color = sky_color(direction, push_const.sun_direction, push_const.turbidity);
+    float fog = fog_amount(push_const.camera_position,
+                           direction,
+                           FOG_SKY_DISTANCE,
+                           push_const.fog_density,
+                           push_const.fog_height,
+                           push_const.fog_height_falloff);
+
+    color_out = vec4(mix(color, push_const.fog_color, fog), 1.0);
+}
@@ -0,0 +1,62 @@
+// Analytic daylight sky which approximates single scattering in a uniform atmosphere: Rayleigh
+// scattering gives the blue gradient which reddens when the sun is low, while Mie scattering
+// (scaled by turbidity) gives the bright haze around the sun
+//
+// Parameters match Sky (src/render/sky.rs)
+
+const float SKY_PI = 3.14159265;
+
+// Scale applied to scattered light before exposure
+const float SKY_INTENSITY = 60.0;
+
+// Rayleigh scattering coefficients of red, green and blue light (proportional to 1/λ⁴)
+const vec3 SKY_RAYLEIGH = vec3(0.051, 0.12, 0.3);
+
+// Mie scattering coefficient for each unit of turbidity
+const float SKY_MIE = 0.01;
+
+// Henyey-Greenstein asymmetry of Mie scattering
+const float SKY_MIE_G = 0.76;
+
+const vec3 SKY_NIGHT_COLOR = vec3(0.002, 0.003, 0.008);
+
+// Returns the relative amount of air along a ray leaving the ground at the given elevation sine
+float sky_air_mass(float elevation) {
+    return 1.0 / (max(elevation, 0.0) + 0.1);
+}
+
+// Returns the displayable sky color in the given direction; directions need not be normalized
+vec3 sky_color(vec3 direction, vec3 sun_direction, float turbidity) {
+    direction = normalize(direction);
+    sun_direction = normalize(sun_direction);
+
+    float mie = SKY_MIE * turbidity;
+    vec3 extinction = SKY_RAYLEIGH + mie;
+
+    float cos_theta = dot(direction, sun_direction);
+    float rayleigh_phase = 3.0 / (16.0 * SKY_PI) * (1.0 + cos_theta * cos_theta);
+    float g2 = SKY_MIE_G * SKY_MIE_G;
+    float mie_phase = (1.0 - g2)
+                    / (4.0 * SKY_PI * pow(1.0 + g2 - 2.0 * SKY_MIE_G * cos_theta, 1.5));
+
+    // Sunlight is dimmed and reddened by the air it passes through before scattering
+    vec3 sunlight = exp(-extinction * sky_air_mass(sun_direction.y));
+
+    // Scattered light saturates as the view ray passes through more air towards the horizon
+    vec3 view_transmittance = exp(-extinction * sky_air_mass(direction.y));
+    vec3 inscatter = sunlight
+                   * (SKY_RAYLEIGH * rayleigh_phase + mie * mie_phase) / extinction
+                   * (1.0 - view_transmittance);
+
+    float sun_disk = smoothstep(0.9997, 0.9999, cos_theta);
+    vec3 color = SKY_INTENSITY * inscatter + 20.0 * sun_disk * sunlight * view_transmittance;
+
+    // Fade to night once the sun has set
+    color *= smoothstep(-0.15, 0.05, sun_direction.y);
+    color += SKY_NIGHT_COLOR;
+
+    // There is no ground, so darken below the horizon
+    color *= mix(0.3, 1.0, smoothstep(-0.1, 0.0, direction.y));
+
+    return 1.0 - exp(-color);
+}
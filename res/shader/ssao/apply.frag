@@ -0,0 +1,21 @@
+#version 460 core
+
+layout(binding = 0) uniform sampler2D occlusion_sampler;
+
+layout(location = 0) in vec2 ndc;
+
+// Multiplied with the framebuffer by blending
+layout(location = 0) out vec4 color_out;
+
+void main() {
+    vec2 uv = ndc * 0.5 + 0.5;
+    vec2 texel = 1.0 / vec2(textureSize(occlusion_sampler, 0));
+
+    // Four bilinear taps average a 4x4 block, which hides the noise of the occlusion pass
+    float occlusion = 0.25 * (texture(occlusion_sampler, uv + texel * vec2(-1.0, -1.0)).r
+                            + texture(occlusion_sampler, uv + texel * vec2(1.0, -1.0)).r
+                            + texture(occlusion_sampler, uv + texel * vec2(-1.0, 1.0)).r
+                            + texture(occlusion_sampler, uv + texel * vec2(1.0, 1.0)).r);
+
+    color_out = vec4(vec3(occlusion), 1.0);
+}
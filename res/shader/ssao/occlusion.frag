@@ -0,0 +1,95 @@
+#version 460 core
+
+// Horizon-based ambient occlusion (in the style of HBAO+): the depth buffer is marched in several
+// directions around each pixel and the highest horizon above the surface, relative to its normal,
+// is accumulated as occlusion
+
+layout(constant_id = 0) const uint DIRECTION_COUNT = 6;
+layout(constant_id = 1) const uint STEP_COUNT = 4;
+
+const float TAU = 6.28318531;
+
+layout(push_constant) uniform PushConstants {
+    layout(offset = 0) vec2 inv_focal_len;
+    layout(offset = 8) float z_near;
+    layout(offset = 12) float radius;
+    layout(offset = 16) float intensity;
+} push_const;
+
+layout(binding = 0) uniform sampler2D depth_sampler;
+
+layout(location = 0) in vec2 ndc;
+
+layout(location = 0) out float occlusion_out;
+
+// Depth is reverse-Z with an infinite far plane, so view depth is the near plane over depth
+vec3 view_position(vec2 position_ndc, float depth) {
+    float z = push_const.z_near / depth;
+
+    return vec3(position_ndc * push_const.inv_focal_len * z, z);
+}
+
+float sample_depth(vec2 position_ndc) {
+    return textureLod(depth_sampler, position_ndc * 0.5 + 0.5, 0.0).r;
+}
+
+void main() {
+    float depth = sample_depth(ndc);
+
+    // Nothing was drawn here
+    if (depth == 0.0) {
+        occlusion_out = 1.0;
+        return;
+    }
+
+    vec3 position = view_position(ndc, depth);
+    vec3 normal = normalize(cross(dFdx(position), dFdy(position)));
+
+    if (dot(normal, position) > 0.0) {
+        normal = -normal;
+    }
+
+    // Interleaved gradient noise rotates the directions of each pixel; the apply pass blurs the
+    // resulting pattern
+    float noise = fract(52.9829189 * fract(dot(gl_FragCoord.xy, vec2(0.06711056, 0.00583715))));
+
+    // Size of the radius on screen, in normalized device coordinates
+    vec2 screen_radius = push_const.radius / (position.z * push_const.inv_focal_len);
+    float radius_sq = push_const.radius * push_const.radius;
+    float occlusion = 0.0;
+
+    for (uint direction_idx = 0; direction_idx < DIRECTION_COUNT; direction_idx++) {
+        float angle = (float(direction_idx) + noise) / float(DIRECTION_COUNT) * TAU;
+        vec2 direction = vec2(cos(angle), sin(angle)) * screen_radius;
+
+        // A small bias above the tangent plane hides self-occlusion of flat surfaces
+        float max_horizon = 0.1;
+
+        for (uint step_idx = 0; step_idx < STEP_COUNT; step_idx++) {
+            vec2 sample_ndc = ndc + direction * (float(step_idx) + noise) / float(STEP_COUNT);
+            float sampled_depth = sample_depth(sample_ndc);
+
+            if (sampled_depth == 0.0) {
+                continue;
+            }
+
+            vec3 offset = view_position(sample_ndc, sampled_depth) - position;
+            float dist_sq = dot(offset, offset);
+
+            if (dist_sq > radius_sq || dist_sq == 0.0) {
+                continue;
+            }
+
+            float horizon = dot(offset, normal) * inversesqrt(dist_sq);
+
+            if (horizon > max_horizon) {
+                occlusion += (horizon - max_horizon) * (1.0 - dist_sq / radius_sq);
+                max_horizon = horizon;
+            }
+        }
+    }
+
+    occlusion_out = clamp(1.0 - push_const.intensity * occlusion / float(DIRECTION_COUNT),
+                          0.0,
+                          1.0);
+}
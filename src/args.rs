@@ -7,6 +7,18 @@ pub struct Args {
     #[arg(long, default_value_t = false)]
     pub benchmark: bool,
 
+    /// Spawn this many extra copies of the benchmark scene models, in a grid
+    #[arg(long, default_value_t = 0)]
+    pub benchmark_instances: usize,
+
+    /// Write benchmark frame times to this file (CSV if it ends in .csv, otherwise JSON)
+    #[arg(long)]
+    pub benchmark_output: Option<std::path::PathBuf>,
+
+    /// Scene key to benchmark
+    #[arg(long, default_value = "scene/level_01")]
+    pub benchmark_scene: String,
+
     /// Enable Vulkan debug layers
     #[arg(long, default_value_t = false)]
     #[cfg(debug_assertions)]
@@ -20,10 +32,32 @@ pub struct Args {
     #[arg(long, default_value_t = false)]
     pub disable_ray_tracing: bool,
 
+    /// Select the GPU by index or by part of its name, overriding the config (GPUs are listed in
+    /// the log)
+    #[arg(long)]
+    pub gpu: Option<String>,
+
     /// Disable audio
     #[arg(long, default_value_t = false)]
     pub mute: bool,
 
+    /// Replay a demo file recorded with --record-demo, then exit
+    #[arg(long, conflicts_with = "record_demo")]
+    pub play_demo: Option<std::path::PathBuf>,
+
+    /// Record gameplay input to a demo file
+    #[arg(long)]
+    pub record_demo: Option<std::path::PathBuf>,
+
+    /// Load RenderDoc, if installed, so that F8 captures a frame
+    #[arg(long, default_value_t = false)]
+    pub renderdoc: bool,
+
+    /// Read every asset at startup and compare it with the hashes recorded when the paks were
+    /// built, instead of only checking that every asset is present
+    #[arg(long, default_value_t = false)]
+    pub verify_assets: bool,
+
     /// Run in windowed mode
     #[arg(long, default_value_t = false)]
     pub window: bool,
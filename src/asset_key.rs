@@ -0,0 +1,57 @@
+//! Typed keys of the assets stored in the `.pak` files.
+//!
+//! The bindings generated by `build.rs` give each key the type of the asset it names, so passing a
+//! bitmap key where a font is expected is caught at compile time instead of when loading.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+macro_rules! asset_key {
+    ($($(#[$attr:meta])* $name:ident,)*) => {
+        $(
+            $(#[$attr])*
+            #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+            pub struct $name(&'static str);
+
+            impl $name {
+                /// Used by the generated bindings; keys should not be constructed by hand.
+                pub const fn new(key: &'static str) -> Self {
+                    Self(key)
+                }
+
+                /// Returns the key as stored in the `.pak` file.
+                pub const fn as_str(self) -> &'static str {
+                    self.0
+                }
+            }
+
+            impl Display for $name {
+                fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+                    f.write_str(self.0)
+                }
+            }
+        )*
+    };
+}
+
+asset_key! {
+    /// Key of a bitmap, such as a user interface image or cursor.
+    BitmapKey,
+
+    /// Key of a blob which is not a sound, such as a compiled shader.
+    BlobKey,
+
+    /// Key of a bitmap font.
+    FontKey,
+
+    /// Key of a material.
+    MaterialKey,
+
+    /// Key of a model.
+    ModelKey,
+
+    /// Key of a scene.
+    SceneKey,
+
+    /// Key of a sound effect or music blob.
+    SoundKey,
+}
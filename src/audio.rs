@@ -0,0 +1,939 @@
+use {
+    crate::{
+        asset_key::SoundKey,
+        game::events::{GameEvent, GameEventListener},
+        level::ambient::{AmbientEmitter, AmbientEmitters},
+    },
+    glam::Vec3,
+    kira::{
+        manager::{backend::Backend, error::AddSubTrackError, AudioManager},
+        sound::{
+            static_sound::{StaticSoundData, StaticSoundHandle},
+            PlaybackRate, PlaybackState,
+        },
+        track::{
+            effect::{
+                delay::{DelayBuilder, DelayHandle},
+                reverb::{ReverbBuilder, ReverbHandle},
+            },
+            TrackBuilder, TrackHandle,
+        },
+        tween::{Tween, Value},
+        Volume,
+    },
+    log::warn,
+    std::{borrow::Cow, collections::HashMap, str::FromStr, time::Duration},
+};
+
+/// Returns the amplitude and the panning (`0.0` is left, `1.0` is right) of a sound at the given
+/// position, heard within the given radius, by a listener at the given position whose right is the
+/// given direction.
+pub fn spatial_mix(
+    position: Vec3,
+    radius: f32,
+    volume: f32,
+    listener_position: Vec3,
+    listener_right: Vec3,
+) -> (f32, f32) {
+    let offset = position - listener_position;
+    let distance = offset.length();
+
+    // Squared falloff sounds closer to a real source than linear falloff, but still reaches
+    // silence at the radius
+    let falloff = (1.0 - distance / radius.max(f32::EPSILON)).max(0.0);
+    let volume = volume * falloff * falloff;
+
+    // Nearby sounds surround the listener, so they are panned less
+    let panning = 0.5 + 0.5 * offset.normalize_or_zero().dot(listener_right) * distance.min(1.0);
+
+    (volume, panning.clamp(0.0, 1.0))
+}
+
+/// The acoustics of a space, which set the reverb and echo of sounds played within it.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ReverbPreset {
+    Cathedral,
+    Corridor,
+
+    /// Open spaces, where sounds play without reverb.
+    #[default]
+    Dry,
+
+    Hall,
+    Room,
+}
+
+impl ReverbPreset {
+    pub const ALL: [Self; 5] = [
+        Self::Cathedral,
+        Self::Corridor,
+        Self::Dry,
+        Self::Hall,
+        Self::Room,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Cathedral => "cathedral",
+            Self::Corridor => "corridor",
+            Self::Dry => "dry",
+            Self::Hall => "hall",
+            Self::Room => "room",
+        }
+    }
+
+    fn params(self) -> ReverbParams {
+        match self {
+            Self::Cathedral => ReverbParams {
+                damping: 0.2,
+                delay_feedback: -12.0,
+                delay_mix: 0.15,
+                delay_time: 0.35,
+                feedback: 0.95,
+                mix: 0.5,
+            },
+            Self::Corridor => ReverbParams {
+                damping: 0.5,
+                delay_feedback: -18.0,
+                delay_mix: 0.1,
+                delay_time: 0.08,
+                feedback: 0.7,
+                mix: 0.3,
+            },
+            Self::Dry => ReverbParams {
+                damping: 0.8,
+                delay_feedback: -60.0,
+                delay_mix: 0.0,
+                delay_time: 0.05,
+                feedback: 0.5,
+                mix: 0.0,
+            },
+            Self::Hall => ReverbParams {
+                damping: 0.3,
+                delay_feedback: -18.0,
+                delay_mix: 0.08,
+                delay_time: 0.2,
+                feedback: 0.85,
+                mix: 0.35,
+            },
+            Self::Room => ReverbParams {
+                damping: 0.7,
+                delay_feedback: -60.0,
+                delay_mix: 0.0,
+                delay_time: 0.05,
+                feedback: 0.6,
+                mix: 0.2,
+            },
+        }
+    }
+}
+
+impl FromStr for ReverbPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown reverb {s}"))
+    }
+}
+
+/// Effect-track settings of a [`ReverbPreset`].
+#[derive(Clone, Copy, Debug)]
+struct ReverbParams {
+    /// How much high frequencies are absorbed by the reverb (`0.0..=1.0`).
+    damping: f64,
+
+    /// Volume, in decibels, of each echo compared to the previous one.
+    delay_feedback: f64,
+
+    /// Fraction (`0.0..=1.0`) of the output which is echo.
+    delay_mix: f64,
+
+    /// Seconds between echoes.
+    delay_time: f64,
+
+    /// How long the reverb rings out (`0.0..=1.0`).
+    feedback: f64,
+
+    /// Fraction (`0.0..=1.0`) of the output which is reverb.
+    mix: f64,
+}
+
+/// A mixer track with reverb and echo effects which world sounds play through, so that the space
+/// around the listener changes how they sound.
+pub struct ReverbMixer {
+    delay: DelayHandle,
+    preset: ReverbPreset,
+    reverb: ReverbHandle,
+    track: TrackHandle,
+}
+
+impl ReverbMixer {
+    /// Seconds taken to blend between presets, so that moving between spaces is not abrupt.
+    const BLEND_SECS: f32 = 1.0;
+
+    pub fn new<B: Backend>(audio: &mut AudioManager<B>) -> Result<Self, AddSubTrackError> {
+        let ReverbParams {
+            damping,
+            delay_feedback,
+            delay_mix,
+            delay_time,
+            feedback,
+            mix,
+        } = ReverbPreset::Dry.params();
+        let mut track = TrackBuilder::new();
+        let delay = track.add_effect(
+            DelayBuilder::new()
+                .delay_time(delay_time)
+                .feedback(Volume::Decibels(delay_feedback))
+                .mix(delay_mix),
+        );
+        let reverb = track.add_effect(
+            ReverbBuilder::new()
+                .damping(damping)
+                .feedback(feedback)
+                .mix(mix),
+        );
+        let track = audio.add_sub_track(track)?;
+
+        Ok(Self {
+            delay,
+            preset: ReverbPreset::Dry,
+            reverb,
+            track,
+        })
+    }
+
+    /// Returns the given sound, played through this track.
+    pub fn route(&self, sound: &StaticSoundData) -> StaticSoundData {
+        sound.with_modified_settings(|settings| settings.output_destination(&self.track))
+    }
+
+    /// Blends the effects towards the given preset, if it differs from the current one.
+    pub fn set_preset(&mut self, preset: ReverbPreset) {
+        if preset == self.preset {
+            return;
+        }
+
+        self.preset = preset;
+
+        let ReverbParams {
+            damping,
+            delay_feedback,
+            delay_mix,
+            delay_time,
+            feedback,
+            mix,
+        } = preset.params();
+        let tween = Tween {
+            duration: Duration::from_secs_f32(Self::BLEND_SECS),
+            ..Default::default()
+        };
+
+        // The delay time is not blended because sweeping it changes the pitch of the echoes
+        if let Err(err) = self
+            .delay
+            .set_delay_time(delay_time, Default::default())
+            .and(
+                self.delay
+                    .set_feedback(Volume::Decibels(delay_feedback), tween),
+            )
+            .and(self.delay.set_mix(delay_mix, tween))
+            .and(self.reverb.set_damping(damping, tween))
+            .and(self.reverb.set_feedback(feedback, tween))
+            .and(self.reverb.set_mix(mix, tween))
+        {
+            warn!("Unable to set reverb: {err}");
+        }
+    }
+}
+
+/// The loop of one ambient emitter, which only plays while it is among the loudest the listener
+/// hears and is otherwise virtual: tracked but silent.
+struct AmbientVoice {
+    emitter: AmbientEmitter,
+
+    /// The playing loop, or `None` while virtual.
+    handle: Option<StaticSoundHandle>,
+
+    /// Seconds into the loop, which advances while virtual so that a promoted loop resumes where
+    /// it would have been had it kept playing.
+    position: f64,
+
+    sound: StaticSoundData,
+}
+
+/// A sound played once at a position in the level, such as a gunshot or a footstep.
+struct OneShotVoice {
+    /// Amplitude of the sound at its position, which includes any variation chosen by [`SfxBank`].
+    gain: f32,
+
+    handle: StaticSoundHandle,
+    position: Vec3,
+    priority: SoundPriority,
+
+    /// Amplitude heard by the listener as of the latest mix.
+    volume: f32,
+}
+
+/// How important a sound is to hear, which decides which voice is stolen when a one-shot sound
+/// plays while every voice is in use.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum SoundPriority {
+    /// Ambient loops and footsteps.
+    Low,
+
+    /// Impacts.
+    Normal,
+
+    /// Gunfire and pickups.
+    High,
+}
+
+/// The sounds of a loaded level: the ambient loops, which play for as long as the level is loaded,
+/// and the one-shot sounds of things happening within it.
+///
+/// Levels may have more sounds than there are voices to play them, so only the loudest few loops
+/// play at once and the rest are virtual until the listener comes near enough to hear them. A
+/// one-shot sound which plays while every voice is in use steals the voice of the least important
+/// sound, quietest first, or is not played if every other sound is more important.
+///
+/// Kira does not stop sounds when their handles are dropped, so the sounds are stopped when this
+/// is dropped instead.
+pub struct SoundWorld {
+    listener_position: Vec3,
+    listener_right: Vec3,
+    one_shots: Vec<OneShotVoice>,
+    voices: Vec<AmbientVoice>,
+}
+
+impl SoundWorld {
+    /// Seconds taken to fade loops in when promoted and out when virtualized or stopped.
+    const FADE_SECS: f32 = 0.5;
+
+    /// Sounds beyond this many are virtual or stolen, quietest first.
+    const MAX_VOICES: usize = 16;
+
+    /// Amplitude below which a playing loop becomes virtual.
+    const MIN_VOLUME: f32 = 0.005;
+
+    /// Distance, in meters, beyond which one-shot sounds cannot be heard.
+    const ONE_SHOT_RADIUS: f32 = 40.0;
+
+    /// Amplitude a virtual loop must reach to play again, which is above [`Self::MIN_VOLUME`] so
+    /// that a listener standing at the edge of hearing does not restart it over and over.
+    const PROMOTE_VOLUME: f32 = 0.01;
+
+    /// Returns which of the given loops should play, given the amplitude of each and whether it
+    /// plays now, when no more than the given number of voices are free.
+    fn audible_voices(voices: &[(f32, bool)], free_voices: usize) -> Vec<bool> {
+        let mut order = (0..voices.len())
+            .filter(|&idx| {
+                let (volume, is_playing) = voices[idx];
+
+                volume
+                    >= if is_playing {
+                        Self::MIN_VOLUME
+                    } else {
+                        Self::PROMOTE_VOLUME
+                    }
+            })
+            .collect::<Vec<_>>();
+        order.sort_by(|&lhs, &rhs| voices[rhs].0.total_cmp(&voices[lhs].0));
+
+        let mut res = vec![false; voices.len()];
+
+        for idx in order.into_iter().take(free_voices) {
+            res[idx] = true;
+        }
+
+        res
+    }
+
+    /// Prepares the loop of each ambient emitter, all of which are virtual until the first update.
+    pub fn spawn(
+        emitters: &AmbientEmitters,
+        sounds: &HashMap<SoundKey, StaticSoundData>,
+        reverb: Option<&ReverbMixer>,
+    ) -> Self {
+        let mut voices = vec![];
+
+        for emitter in emitters.iter() {
+            let Some(sound) = sounds.get(&emitter.sound) else {
+                warn!("Ambient sound {} not loaded", emitter.sound.as_str());
+                continue;
+            };
+            let sound = reverb
+                .map(|reverb| reverb.route(sound))
+                .unwrap_or_else(|| sound.clone())
+                .with_modified_settings(|settings| {
+                    settings.loop_region(..).volume(Volume::Amplitude(0.0))
+                });
+
+            voices.push(AmbientVoice {
+                emitter: *emitter,
+                handle: None,
+                position: 0.0,
+                sound,
+            });
+        }
+
+        Self {
+            listener_position: Vec3::ZERO,
+            listener_right: Vec3::X,
+            one_shots: vec![],
+            voices,
+        }
+    }
+
+    /// Plays a sound once at the given position, mixed for the listener of the latest update, if
+    /// it is loud enough to hear and a voice is free or may be stolen for it.
+    pub fn play<B: Backend>(
+        &mut self,
+        audio: &mut AudioManager<B>,
+        reverb: Option<&ReverbMixer>,
+        sound: &StaticSoundData,
+        position: Vec3,
+        priority: SoundPriority,
+    ) {
+        let gain = match sound.settings.volume {
+            Value::Fixed(volume) => volume.as_amplitude() as f32,
+            _ => 1.0,
+        };
+        let (volume, panning) = spatial_mix(
+            position,
+            Self::ONE_SHOT_RADIUS,
+            gain,
+            self.listener_position,
+            self.listener_right,
+        );
+
+        if volume < Self::MIN_VOLUME {
+            return;
+        }
+
+        let playing = self
+            .voices
+            .iter()
+            .filter(|voice| voice.handle.is_some())
+            .count()
+            + self.one_shots.len();
+
+        if playing >= Self::MAX_VOICES {
+            let ambient_voices = self
+                .voices
+                .iter()
+                .enumerate()
+                .filter(|(_, voice)| voice.handle.is_some())
+                .map(|(idx, voice)| {
+                    let (volume, _) = voice
+                        .emitter
+                        .mix(self.listener_position, self.listener_right);
+
+                    (Some(idx), SoundPriority::Low, volume)
+                });
+            let one_shot_voices = self
+                .one_shots
+                .iter()
+                .map(|voice| (None, voice.priority, voice.volume));
+            let candidates = ambient_voices.chain(one_shot_voices).collect::<Vec<_>>();
+            let Some(steal_idx) = Self::stolen_voice(
+                &candidates
+                    .iter()
+                    .map(|&(_, priority, volume)| (priority, volume))
+                    .collect::<Vec<_>>(),
+                priority,
+                volume,
+            ) else {
+                return;
+            };
+
+            // Ambient loops are virtualized rather than forgotten, so they resume once a voice is
+            // free again
+            let handle = match candidates[steal_idx].0 {
+                Some(idx) => self.voices[idx].handle.take(),
+                None => {
+                    let one_shot_idx = steal_idx - (candidates.len() - self.one_shots.len());
+
+                    Some(self.one_shots.swap_remove(one_shot_idx).handle)
+                }
+            };
+
+            if let Some(mut handle) = handle {
+                if let Err(err) = handle.stop(Default::default()) {
+                    warn!("Unable to stop sound: {err}");
+                }
+            }
+        }
+
+        let sound = reverb
+            .map(|reverb| reverb.route(sound))
+            .unwrap_or_else(|| sound.clone())
+            .with_modified_settings(|settings| {
+                settings
+                    .panning(panning as f64)
+                    .volume(Volume::Amplitude(volume as _))
+            });
+
+        match audio.play(sound) {
+            Ok(handle) => self.one_shots.push(OneShotVoice {
+                gain,
+                handle,
+                position,
+                priority,
+                volume,
+            }),
+            Err(err) => warn!("Unable to play sound: {err}"),
+        }
+    }
+
+    /// Returns which of the given voices, each a priority and an amplitude, a new sound with the
+    /// given priority and amplitude should steal, or `None` if every voice is more important.
+    fn stolen_voice(
+        voices: &[(SoundPriority, f32)],
+        priority: SoundPriority,
+        volume: f32,
+    ) -> Option<usize> {
+        let (idx, &(lowest_priority, lowest_volume)) = voices
+            .iter()
+            .enumerate()
+            .min_by(|(_, lhs), (_, rhs)| lhs.0.cmp(&rhs.0).then(lhs.1.total_cmp(&rhs.1)))?;
+
+        (lowest_priority < priority || lowest_priority == priority && lowest_volume < volume)
+            .then_some(idx)
+    }
+
+    /// Mixes each sound for a listener at the given position, whose right is the given direction,
+    /// playing the loudest loops in the voices one-shot sounds leave free and virtualizing the
+    /// rest.
+    pub fn update<B: Backend>(
+        &mut self,
+        audio: &mut AudioManager<B>,
+        listener_position: Vec3,
+        right: Vec3,
+        dt: f32,
+    ) {
+        let tween = Tween {
+            duration: Duration::from_secs_f32(Self::FADE_SECS),
+            ..Default::default()
+        };
+
+        self.listener_position = listener_position;
+        self.listener_right = right;
+        self.one_shots
+            .retain(|voice| voice.handle.state() != PlaybackState::Stopped);
+
+        for voice in &mut self.one_shots {
+            let (volume, panning) = spatial_mix(
+                voice.position,
+                Self::ONE_SHOT_RADIUS,
+                voice.gain,
+                listener_position,
+                right,
+            );
+            voice.volume = volume;
+
+            if let Err(err) = voice
+                .handle
+                .set_volume(Volume::Amplitude(volume as _), Default::default())
+                .and(voice.handle.set_panning(panning as f64, Default::default()))
+            {
+                warn!("Unable to mix sound: {err}");
+            }
+        }
+
+        let mixes = self
+            .voices
+            .iter()
+            .map(|voice| voice.emitter.mix(listener_position, right))
+            .collect::<Vec<_>>();
+        let audible = Self::audible_voices(
+            &self
+                .voices
+                .iter()
+                .zip(&mixes)
+                .map(|(voice, &(volume, _))| (volume, voice.handle.is_some()))
+                .collect::<Vec<_>>(),
+            Self::MAX_VOICES.saturating_sub(self.one_shots.len()),
+        );
+
+        for ((voice, (volume, panning)), is_audible) in
+            self.voices.iter_mut().zip(mixes).zip(audible)
+        {
+            let duration = voice.sound.duration().as_secs_f64();
+
+            voice.position = match &voice.handle {
+                Some(handle) => handle.position(),
+                None => voice.position + dt as f64,
+            };
+
+            if duration > 0.0 {
+                voice.position %= duration;
+            }
+
+            if !is_audible {
+                if let Some(mut handle) = voice.handle.take() {
+                    if let Err(err) = handle.stop(tween) {
+                        warn!("Unable to stop ambient sound: {err}");
+                    }
+                }
+
+                continue;
+            }
+
+            if voice.handle.is_none() {
+                let position = voice.position;
+                let sound = voice
+                    .sound
+                    .with_modified_settings(|settings| settings.start_position(position));
+
+                match audio.play(sound) {
+                    Ok(handle) => voice.handle = Some(handle),
+                    Err(err) => {
+                        warn!("Unable to play ambient sound: {err}");
+
+                        continue;
+                    }
+                }
+            }
+
+            let handle = voice.handle.as_mut().unwrap();
+
+            if let Err(err) = handle
+                .set_volume(Volume::Amplitude(volume as _), tween)
+                .and(handle.set_panning(panning as f64, tween))
+            {
+                warn!("Unable to mix ambient sound: {err}");
+            }
+        }
+    }
+
+    /// Returns the number of sounds which are playing, along with the number of sounds, including
+    /// virtual loops.
+    pub fn voice_count(&self) -> (usize, usize) {
+        (
+            self.voices
+                .iter()
+                .filter(|voice| voice.handle.is_some())
+                .count()
+                + self.one_shots.len(),
+            self.voices.len() + self.one_shots.len(),
+        )
+    }
+}
+
+impl Drop for SoundWorld {
+    fn drop(&mut self) {
+        let tween = Tween {
+            duration: Duration::from_secs_f32(Self::FADE_SECS),
+            ..Default::default()
+        };
+
+        for handle in self
+            .voices
+            .iter_mut()
+            .filter_map(|voice| voice.handle.as_mut())
+            .chain(self.one_shots.iter_mut().map(|voice| &mut voice.handle))
+        {
+            if let Err(err) = handle.stop(tween) {
+                warn!("Unable to stop sound: {err}");
+            }
+        }
+    }
+}
+
+/// How intense music should be, from zero while exploring to one in the thick of combat; each
+/// kill raises it, and it falls back once combat has stopped for a while.
+///
+/// There are no music tracks yet; once there are, this chooses between their layers.
+#[derive(Debug, Default)]
+pub struct MusicIntensity {
+    intensity: f32,
+
+    /// Seconds since the latest kill.
+    calm_secs: f32,
+}
+
+impl MusicIntensity {
+    /// Seconds without a kill before intensity begins to fall.
+    const CALM_DELAY_SECS: f32 = 8.0;
+
+    /// Intensity lost each second once calm.
+    const FALL_PER_SEC: f32 = 0.1;
+
+    const KILL_INTENSITY: f32 = 0.25;
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.calm_secs += dt;
+
+        if self.calm_secs > Self::CALM_DELAY_SECS {
+            self.intensity = (self.intensity - Self::FALL_PER_SEC * dt).max(0.0);
+        }
+    }
+}
+
+impl GameEventListener for MusicIntensity {
+    fn on_game_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::EnemyKilled { .. } => {
+                self.intensity = (self.intensity + Self::KILL_INTENSITY).min(1.0);
+                self.calm_secs = 0.0;
+            }
+
+            // The end of a level is a resolution, so the music settles at once
+            GameEvent::LevelCompleted { .. } => self.intensity = 0.0,
+
+            GameEvent::ObjectiveAdded { .. }
+            | GameEvent::ObjectiveCompleted { .. }
+            | GameEvent::SecretFound { .. } => (),
+        }
+    }
+}
+
+/// The sounds which may play for one event.
+struct SfxEvent {
+    last_index: Option<usize>,
+    sounds: Vec<StaticSoundData>,
+}
+
+/// Groups several sounds for each event, such as four samples of `footstep_concrete`, so that an
+/// event which repeats quickly does not play the identical sound each time.
+///
+/// A different sound than the previous one is chosen each time, when possible, and every sound is
+/// played with a slightly different pitch and volume.
+pub struct SfxBank {
+    events: HashMap<Cow<'static, str>, SfxEvent>,
+    seed: u32,
+}
+
+impl SfxBank {
+    /// Largest change of playback rate, which also changes pitch, as a factor.
+    const PITCH_JITTER: f64 = 0.06;
+
+    /// Largest change of volume, as an amplitude factor.
+    const VOLUME_JITTER: f64 = 0.1;
+
+    /// Adds sounds to an event, creating the event if needed.
+    pub fn insert(
+        &mut self,
+        event: impl Into<Cow<'static, str>>,
+        sounds: impl IntoIterator<Item = StaticSoundData>,
+    ) -> &mut Self {
+        self.events
+            .entry(event.into())
+            .or_insert_with(|| SfxEvent {
+                last_index: None,
+                sounds: vec![],
+            })
+            .sounds
+            .extend(sounds);
+        self
+    }
+
+    fn next_index(&mut self, event: &str) -> Option<usize> {
+        let seed = self.next_random();
+        let event = self.events.get_mut(event)?;
+        let count = event.sounds.len();
+
+        if count == 0 {
+            return None;
+        }
+
+        // Choose from every sound except the previous one, which is skipped over
+        let index = match event.last_index.filter(|_| count > 1) {
+            Some(last_index) => {
+                let index = (seed * (count - 1) as f64) as usize % (count - 1);
+
+                if index >= last_index {
+                    index + 1
+                } else {
+                    index
+                }
+            }
+            None => (seed * count as f64) as usize % count,
+        };
+
+        event.last_index = Some(index);
+
+        Some(index)
+    }
+
+    /// Simple xorshift generator returning values in `0..1`; sound variation does not need
+    /// anything better.
+    fn next_random(&mut self) -> f64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+
+        self.seed as f64 / (u32::MAX as f64 + 1.0)
+    }
+
+    /// Returns a sound of the given event, ready to be played, or `None` if the event has no
+    /// sounds.
+    pub fn sound(&mut self, event: &str) -> Option<StaticSoundData> {
+        let index = self.next_index(event)?;
+        let playback_rate = 1.0 + (self.next_random() * 2.0 - 1.0) * Self::PITCH_JITTER;
+        let volume = 1.0 - self.next_random() * Self::VOLUME_JITTER;
+
+        Some(
+            self.events[event].sounds[index].with_modified_settings(|settings| {
+                settings
+                    .playback_rate(PlaybackRate::Factor(playback_rate))
+                    .volume(Volume::Amplitude(volume))
+            }),
+        )
+    }
+}
+
+impl Default for SfxBank {
+    fn default() -> Self {
+        Self {
+            events: Default::default(),
+            seed: 0x2545_f491,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, kira::sound::static_sound::StaticSoundSettings, std::sync::Arc};
+
+    fn silence() -> StaticSoundData {
+        StaticSoundData {
+            sample_rate: 44_100,
+            frames: Arc::new([]),
+            settings: StaticSoundSettings::new(),
+        }
+    }
+
+    #[test]
+    pub fn virtualize_quiet_voices() {
+        // Quiet loops are virtual, and a loop between the two thresholds keeps playing only if it
+        // already was
+        let between = (SoundWorld::MIN_VOLUME + SoundWorld::PROMOTE_VOLUME) * 0.5;
+
+        assert_eq!(
+            SoundWorld::audible_voices(
+                &[(0.0, true), (between, true), (between, false)],
+                SoundWorld::MAX_VOICES
+            ),
+            [false, true, false]
+        );
+
+        // Only the loudest loops play
+        let voices = (0..SoundWorld::MAX_VOICES + 2)
+            .map(|idx| (1.0 / (idx + 1) as f32, false))
+            .rev()
+            .collect::<Vec<_>>();
+        let audible = SoundWorld::audible_voices(&voices, SoundWorld::MAX_VOICES);
+
+        assert_eq!(
+            audible.iter().filter(|&&is_audible| is_audible).count(),
+            SoundWorld::MAX_VOICES
+        );
+        assert!(!audible[0]);
+        assert!(!audible[1]);
+        assert!(audible[SoundWorld::MAX_VOICES + 1]);
+    }
+
+    #[test]
+    pub fn steal_least_important_voices() {
+        let voices = [
+            (SoundPriority::High, 0.1),
+            (SoundPriority::Low, 0.5),
+            (SoundPriority::Low, 0.2),
+            (SoundPriority::Normal, 0.05),
+        ];
+
+        // The quietest of the least important voices is stolen by anything more important or
+        // louder
+        assert_eq!(
+            SoundWorld::stolen_voice(&voices, SoundPriority::Normal, 0.01),
+            Some(2)
+        );
+        assert_eq!(
+            SoundWorld::stolen_voice(&voices, SoundPriority::Low, 0.3),
+            Some(2)
+        );
+
+        // Quieter sounds of the same importance are not played at all
+        assert_eq!(
+            SoundWorld::stolen_voice(&voices, SoundPriority::Low, 0.1),
+            None
+        );
+        assert_eq!(
+            SoundWorld::stolen_voice(&voices[..1], SoundPriority::Normal, 1.0),
+            None
+        );
+    }
+
+    #[test]
+    pub fn music_intensity_falls_when_calm() {
+        let mut music = MusicIntensity {
+            intensity: 0.5,
+            calm_secs: 0.0,
+        };
+        music.update(MusicIntensity::CALM_DELAY_SECS);
+
+        assert_eq!(music.intensity(), 0.5);
+
+        music.update(1.0);
+
+        assert_eq!(music.intensity(), 0.5 - MusicIntensity::FALL_PER_SEC);
+
+        music.on_game_event(&GameEvent::LevelCompleted {
+            level: "level_01".to_owned(),
+            secs: 60.0,
+        });
+
+        assert_eq!(music.intensity(), 0.0);
+    }
+
+    #[test]
+    pub fn no_immediate_repeats() {
+        let mut sfx = SfxBank::default();
+        sfx.insert("footstep_concrete", (0..4).map(|_| silence()));
+
+        let mut last_index = None;
+        let mut counts = [0; 4];
+
+        for _ in 0..1_000 {
+            let index = sfx.next_index("footstep_concrete");
+
+            assert_ne!(index, last_index);
+
+            counts[index.unwrap()] += 1;
+            last_index = index;
+        }
+
+        assert!(counts.iter().all(|&count| count > 100));
+    }
+
+    #[test]
+    pub fn parse_reverb_preset() {
+        assert_eq!("cathedral".parse(), Ok(ReverbPreset::Cathedral));
+        assert_eq!("Hall".parse(), Ok(ReverbPreset::Hall));
+        assert!("cave".parse::<ReverbPreset>().is_err());
+
+        for preset in ReverbPreset::ALL {
+            assert_eq!(preset.name().parse(), Ok(preset));
+        }
+    }
+
+    #[test]
+    pub fn single_sound() {
+        let mut sfx = SfxBank::default();
+        sfx.insert("beep", [silence()]);
+
+        assert_eq!(sfx.next_index("beep"), Some(0));
+        assert_eq!(sfx.next_index("beep"), Some(0));
+        assert!(sfx.sound("beep").is_some());
+        assert!(sfx.sound("boop").is_none());
+    }
+}
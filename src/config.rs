@@ -1,7 +1,17 @@
 use {
-    crate::{fs::project_dirs, render::model::ModelBufferTechnique},
+    crate::{
+        display::UiScale,
+        fs::project_dirs,
+        input::KeyBinding,
+        limiter::LimiterStrategy,
+        render::{
+            colorblind::ColorblindFilter,
+            model::{AmbientOcclusion, ModelBufferTechnique, TextureFiltering},
+        },
+        ui::crosshair::CrosshairStyle,
+    },
     screen_13::prelude::*,
-    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize},
     std::{
         fmt::Debug,
         fs::{metadata, read_to_string, write},
@@ -10,40 +20,269 @@ use {
     },
 };
 
+fn default_ambient_occlusion() -> AmbientOcclusion {
+    AmbientOcclusion::default()
+}
+
+fn default_captions() -> bool {
+    false
+}
+
+fn default_cheats() -> bool {
+    false
+}
+
+fn default_colorblind_filter() -> ColorblindFilter {
+    ColorblindFilter::default()
+}
+
+fn default_crosshair_color() -> [u8; 4] {
+    [0xff, 0xff, 0xff, 0xc0]
+}
+
+fn default_crosshair_style() -> CrosshairStyle {
+    CrosshairStyle::default()
+}
+
 fn default_framerate_limit() -> usize {
     60
 }
 
+fn default_framerate_limiter() -> LimiterStrategy {
+    LimiterStrategy::default()
+}
+
+fn default_dynamic_resolution() -> bool {
+    true
+}
+
+fn default_flash_intensity() -> f32 {
+    1.0
+}
+
+fn default_fov() -> f32 {
+    45.0
+}
+
+fn default_fullscreen_mode() -> FullscreenMode {
+    FullscreenMode::default()
+}
+
 fn default_graphics() -> Option<ModelBufferTechnique> {
     None
 }
 
+fn default_gpu() -> Option<String> {
+    None
+}
+
+fn default_head_bob() -> f32 {
+    1.0
+}
+
+fn default_hud_scale() -> f32 {
+    1.0
+}
+
+fn default_master_volume() -> f32 {
+    1.0
+}
+
+fn default_monitor() -> Option<usize> {
+    None
+}
+
+fn default_mouse_acceleration() -> f32 {
+    0.0
+}
+
+fn default_mouse_raw_input() -> bool {
+    true
+}
+
 fn default_mouse_sensitivity() -> f32 {
     100.0
 }
 
-fn default_v_sync() -> bool {
-    false
+fn default_mouse_smoothing() -> f32 {
+    0.0
+}
+
+fn default_quick_load_key() -> KeyBinding {
+    KeyBinding(VirtualKeyCode::F9)
+}
+
+fn default_quick_save_key() -> KeyBinding {
+    KeyBinding(VirtualKeyCode::F5)
+}
+
+fn default_reverb() -> bool {
+    true
+}
+
+fn default_streaming_budget() -> f32 {
+    2.0
+}
+
+fn default_texture_filtering() -> TextureFiltering {
+    TextureFiltering::default()
+}
+
+fn default_ui_scale() -> f32 {
+    1.0
+}
+
+fn default_v_sync() -> VSync {
+    VSync::default()
+}
+
+/// Reads `v_sync` either as a mode or as the `true` or `false` written by earlier versions, which
+/// only had v-sync on or off.
+fn deserialize_v_sync<'de, D>(deserializer: D) -> Result<VSync, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Setting {
+        Enabled(bool),
+        Mode(VSync),
+    }
+
+    Ok(match Setting::deserialize(deserializer)? {
+        Setting::Enabled(true) => VSync::On,
+        Setting::Enabled(false) => VSync::Off,
+        Setting::Mode(v_sync) => v_sync,
+    })
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Config {
+    #[serde(default = "default_ambient_occlusion")]
+    pub ambient_occlusion: AmbientOcclusion,
+
+    #[serde(default = "default_captions")]
+    pub captions: bool,
+
+    /// Cheat codes typed during play, such as `iddqd`, take effect; meant for developers and
+    /// testers.
+    #[serde(default = "default_cheats")]
+    pub cheats: bool,
+
+    /// Shifts the colors of each frame for players with a color vision deficiency.
+    #[serde(default = "default_colorblind_filter")]
+    pub colorblind_filter: ColorblindFilter,
+
+    /// RGBA color of the crosshair.
+    #[serde(default = "default_crosshair_color")]
+    pub crosshair_color: [u8; 4],
+
+    #[serde(default = "default_crosshair_style")]
+    pub crosshair_style: CrosshairStyle,
+
+    /// Lowers the resolution while frames take longer than the framerate limit allows; has no
+    /// effect with v-sync.
+    #[serde(default = "default_dynamic_resolution")]
+    pub dynamic_resolution: bool,
+
+    /// Brightness (`0.0..=1.0`) of flashes, such as explosions; lower values are easier on
+    /// players sensitive to flashing light.
+    #[serde(default = "default_flash_intensity")]
+    pub flash_intensity: f32,
+
+    /// Vertical field of view of the camera, in degrees (`30.0..=110.0`).
+    #[serde(default = "default_fov")]
+    pub fov: f32,
+
     #[serde(default = "default_framerate_limit")]
     pub framerate_limit: usize,
 
+    #[serde(default = "default_framerate_limiter")]
+    pub framerate_limiter: LimiterStrategy,
+
+    /// How the window fills the screen when not running with `--window`.
+    #[serde(default = "default_fullscreen_mode")]
+    pub fullscreen_mode: FullscreenMode,
+
     #[serde(default = "default_graphics")]
     pub graphics: Option<ModelBufferTechnique>,
 
+    /// Index or part of the name of the GPU to use; if unset the most capable GPU is used.
+    #[serde(default = "default_gpu")]
+    pub gpu: Option<String>,
+
+    /// Scale of the camera bob while walking; zero disables it.
+    #[serde(default = "default_head_bob")]
+    pub head_bob: f32,
+
+    /// Size of the HUD, such as the crosshair, on top of the UI scale (`0.5..=2.0`).
+    #[serde(default = "default_hud_scale")]
+    pub hud_scale: f32,
+
+    /// Loudness (`0.0..=1.0`) of every sound.
+    #[serde(default = "default_master_volume")]
+    pub master_volume: f32,
+
+    /// Index of the display the game appears on, in the order the platform lists them; if unset
+    /// the primary display is used.
+    #[serde(default = "default_monitor")]
+    pub monitor: Option<usize>,
+
+    /// Extra look gain for each window width per second of mouse speed; zero disables
+    /// acceleration.
+    #[serde(default = "default_mouse_acceleration")]
+    pub mouse_acceleration: f32,
+
+    /// Reads unaccelerated mouse motion from the device instead of the cursor position, when the
+    /// platform supports it.
+    #[serde(default = "default_mouse_raw_input")]
+    pub mouse_raw_input: bool,
+
     #[serde(default = "default_mouse_sensitivity")]
     pub mouse_sensitivity: f32,
 
-    #[serde(default = "default_v_sync")]
-    pub v_sync: bool,
+    /// Fraction (`0.0..0.95`) of previous mouse movement kept each 60th of a second; zero
+    /// disables smoothing.
+    #[serde(default = "default_mouse_smoothing")]
+    pub mouse_smoothing: f32,
+
+    #[serde(default = "default_quick_load_key")]
+    pub quick_load_key: KeyBinding,
+
+    #[serde(default = "default_quick_save_key")]
+    pub quick_save_key: KeyBinding,
+
+    /// Sounds within the level echo based on the space around the player.
+    #[serde(default = "default_reverb")]
+    pub reverb: bool,
+
+    /// Milliseconds each frame which streaming textures and building acceleration structures may
+    /// take during play.
+    #[serde(default = "default_streaming_budget")]
+    pub streaming_budget: f32,
+
+    #[serde(default = "default_texture_filtering")]
+    pub texture_filtering: TextureFiltering,
+
+    /// Size of the UI on top of the scaling of the display (`0.5..=2.0`); adjusted in game using
+    /// Ctrl with plus or minus.
+    #[serde(default = "default_ui_scale")]
+    pub ui_scale: f32,
+
+    /// How frames are presented; modes the display does not support fall back to one which it
+    /// does, see [`display::select_present_mode`](crate::display::select_present_mode).
+    #[serde(default = "default_v_sync", deserialize_with = "deserialize_v_sync")]
+    pub v_sync: VSync,
 }
 
 impl Config {
     const FILE_NAME: &str = "config.toml";
 
+    pub const MAX_FOV: f32 = 110.0;
+    pub const MAX_HUD_SCALE: f32 = 2.0;
+    pub const MIN_FOV: f32 = 30.0;
+    pub const MIN_HUD_SCALE: f32 = 0.5;
+
     fn local_path() -> PathBuf {
         project_dirs()
             .map(|dirs| dirs.data_local_dir().to_path_buf())
@@ -54,7 +293,15 @@ impl Config {
     pub fn read() -> Self {
         let mut res: Self = Self::read_path(Self::local_path());
 
+        res.flash_intensity = res.flash_intensity.clamp(0.0, 1.0);
+        res.fov = res.fov.clamp(Self::MIN_FOV, Self::MAX_FOV);
         res.framerate_limit = res.framerate_limit.clamp(60, 480);
+        res.hud_scale = res
+            .hud_scale
+            .clamp(Self::MIN_HUD_SCALE, Self::MAX_HUD_SCALE);
+        res.master_volume = res.master_volume.clamp(0.0, 1.0);
+        res.streaming_budget = res.streaming_budget.max(0.0);
+        res.ui_scale = res.ui_scale.clamp(UiScale::MIN, UiScale::MAX);
 
         res
     }
@@ -114,10 +361,104 @@ impl Config {
 impl Default for Config {
     fn default() -> Self {
         Self {
+            ambient_occlusion: default_ambient_occlusion(),
+            captions: default_captions(),
+            cheats: default_cheats(),
+            colorblind_filter: default_colorblind_filter(),
+            crosshair_color: default_crosshair_color(),
+            crosshair_style: default_crosshair_style(),
+            dynamic_resolution: default_dynamic_resolution(),
+            flash_intensity: default_flash_intensity(),
+            fov: default_fov(),
             framerate_limit: default_framerate_limit(),
+            framerate_limiter: default_framerate_limiter(),
+            fullscreen_mode: default_fullscreen_mode(),
+            gpu: default_gpu(),
             graphics: default_graphics(),
+            head_bob: default_head_bob(),
+            hud_scale: default_hud_scale(),
+            master_volume: default_master_volume(),
+            monitor: default_monitor(),
+            mouse_acceleration: default_mouse_acceleration(),
+            mouse_raw_input: default_mouse_raw_input(),
             mouse_sensitivity: default_mouse_sensitivity(),
+            mouse_smoothing: default_mouse_smoothing(),
+            quick_load_key: default_quick_load_key(),
+            quick_save_key: default_quick_save_key(),
+            reverb: default_reverb(),
+            streaming_budget: default_streaming_budget(),
+            texture_filtering: default_texture_filtering(),
+            ui_scale: default_ui_scale(),
             v_sync: default_v_sync(),
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum FullscreenMode {
+    /// A window covering the whole display, which switches quickly and keeps the desktop
+    /// resolution.
+    Borderless,
+
+    /// Takes over the display, which may change its resolution and refresh rate.
+    #[default]
+    Exclusive,
+}
+
+/// How finished frames are handed to the display.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum VSync {
+    /// Frames are shown as soon as they finish, which may tear; the framerate limit applies.
+    #[default]
+    Off,
+
+    /// Frames wait for the display to refresh, so there is no tearing but input may lag.
+    On,
+
+    /// Frames wait for the display to refresh unless they are late, which may then tear.
+    Adaptive,
+
+    /// The newest finished frame is shown at each refresh, so there is no tearing and little lag;
+    /// the framerate limit applies.
+    Mailbox,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn read_key_bindings() {
+        let config = toml::from_str::<Config>("quick_save_key = \"F6\"").unwrap();
+
+        assert_eq!(config.quick_load_key, KeyBinding(VirtualKeyCode::F9));
+        assert_eq!(config.quick_save_key, KeyBinding(VirtualKeyCode::F6));
+        assert!(toml::from_str::<Config>("quick_load_key = \"F12\"").is_err());
+
+        let txt = toml::to_string(&config).unwrap();
+
+        assert_eq!(
+            toml::from_str::<Config>(&txt).unwrap().quick_save_key,
+            config.quick_save_key
+        );
+    }
+
+    #[test]
+    pub fn read_v_sync() {
+        let v_sync = |txt: &str| toml::from_str::<Config>(txt).unwrap().v_sync;
+
+        assert_eq!(v_sync(""), VSync::Off);
+        assert_eq!(v_sync("v_sync = true"), VSync::On);
+        assert_eq!(v_sync("v_sync = false"), VSync::Off);
+        assert_eq!(v_sync("v_sync = \"Mailbox\""), VSync::Mailbox);
+        assert!(toml::from_str::<Config>("v_sync = \"Sometimes\"").is_err());
+
+        let config = Config {
+            v_sync: VSync::Adaptive,
+            ..Default::default()
+        };
+        let txt = toml::to_string(&config).unwrap();
+
+        assert_eq!(v_sync(&txt), VSync::Adaptive);
+    }
+}
@@ -0,0 +1,296 @@
+use {
+    crate::{config::Config, fs::project_dirs},
+    log::{set_boxed_logger, set_max_level, LevelFilter, Log, Metadata, Record},
+    screen_13::prelude::*,
+    std::{
+        backtrace::Backtrace,
+        collections::VecDeque,
+        fmt::Write as _,
+        fs::{create_dir_all, read_to_string, remove_file, write},
+        panic::PanicInfo,
+        path::PathBuf,
+        sync::{Mutex, OnceLock},
+        thread,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// Name of the file, in the cache directory, which holds the path of an unreported crash report.
+const LAST_CRASH_FILE_NAME: &str = "last_crash";
+
+static CONFIG: OnceLock<String> = OnceLock::new();
+static DEVICE: OnceLock<String> = OnceLock::new();
+static LOG_LINES: Mutex<LogLines> = Mutex::new(LogLines::new());
+
+/// Fixed-capacity history of the most recent log lines, oldest first.
+struct LogLines {
+    lines: VecDeque<String>,
+}
+
+impl LogLines {
+    /// Number of log lines included in a crash report.
+    const CAPACITY: usize = 200;
+
+    const fn new() -> Self {
+        Self {
+            lines: VecDeque::new(),
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    fn push(&mut self, line: String) {
+        if self.lines.len() == Self::CAPACITY {
+            self.lines.pop_front();
+        }
+
+        self.lines.push_back(line);
+    }
+}
+
+/// Logger which records each line for crash reports before forwarding it to the console logger,
+/// if any.
+struct RingLogger {
+    console: Option<Box<dyn Log>>,
+}
+
+impl Log for RingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= LevelFilter::Info
+            || self
+                .console
+                .as_ref()
+                .is_some_and(|console| console.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() <= LevelFilter::Info {
+            if let Ok(mut lines) = LOG_LINES.lock() {
+                lines.push(format!(
+                    "{:<5} {}: {}",
+                    record.level(),
+                    record.target(),
+                    record.args()
+                ));
+            }
+        }
+
+        if let Some(console) = &self.console {
+            console.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(console) = &self.console {
+            console.flush();
+        }
+    }
+}
+
+fn cache_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.cache_dir().to_path_buf())
+}
+
+/// Installs the global logger; debug builds also print to the console as configured by
+/// `RUST_LOG`.
+pub fn init_logger() {
+    #[cfg(debug_assertions)]
+    let console = {
+        let console = pretty_env_logger::formatted_builder()
+            .parse_default_env()
+            .build();
+
+        set_max_level(console.filter().max(LevelFilter::Info));
+
+        Some(Box::new(console) as Box<dyn Log>)
+    };
+
+    #[cfg(not(debug_assertions))]
+    let console = {
+        set_max_level(LevelFilter::Info);
+
+        None
+    };
+
+    set_boxed_logger(Box::new(RingLogger { console })).unwrap_or_default();
+}
+
+/// Records the config in use so that it may be included in crash reports.
+pub fn set_config(config: &Config) {
+    CONFIG.get_or_init(|| toml::to_string(config).unwrap_or_default());
+}
+
+/// Records device and driver details so that they may be included in crash reports.
+pub fn set_device(device: &Device) {
+    DEVICE.get_or_init(|| {
+        let properties = &device.physical_device.properties_v1_0;
+        let driver = &device.physical_device.properties_v1_2;
+
+        format!(
+            "name = {}\ntype = {:?}\nvendor_id = {:#06x}\ndevice_id = {:#06x}\n\
+            api_version = {}.{}.{}\ndriver = {} ({:?})\ndriver_info = {}\n\
+            driver_version = {:#x}\n",
+            properties.device_name,
+            properties.device_type,
+            properties.vendor_id,
+            properties.device_id,
+            vk::api_version_major(properties.api_version),
+            vk::api_version_minor(properties.api_version),
+            vk::api_version_patch(properties.api_version),
+            driver.driver_name,
+            driver.driver_id,
+            driver.driver_info,
+            properties.driver_version,
+        )
+    });
+}
+
+/// Returns the path of a crash report written by a previous run which has not yet been shown to
+/// the user, if any.
+pub fn take_last_report() -> Option<PathBuf> {
+    let last_crash_path = cache_dir()?.join(LAST_CRASH_FILE_NAME);
+    let report_path = read_to_string(&last_crash_path).ok()?;
+
+    remove_file(last_crash_path).unwrap_or_default();
+
+    Some(PathBuf::from(report_path.trim()))
+}
+
+/// Writes a crash report for the given panic into the cache directory, returning the path of
+/// the report.
+///
+/// This is called from within the panic hook and so it must not panic itself.
+pub fn write_report(panic_info: &PanicInfo) -> Option<PathBuf> {
+    let backtrace = Backtrace::force_capture();
+    let log_lines = LOG_LINES.try_lock().ok();
+    let report = report(
+        "crashed",
+        &panic_details(panic_info, &backtrace),
+        log_lines.iter().flat_map(|log_lines| log_lines.iter()),
+    );
+    let report_path = write_cache_file("crash", &report)?;
+
+    write(
+        cache_dir()?.join(LAST_CRASH_FILE_NAME),
+        report_path.to_string_lossy().as_bytes(),
+    )
+    .ok()?;
+
+    Some(report_path)
+}
+
+/// Writes a report of an error which was shown to the player instead of crashing, such as content
+/// which failed to load, into the cache directory, returning the path of the report.
+pub fn write_error_report(err: &anyhow::Error) -> Option<PathBuf> {
+    let log_lines = LOG_LINES.lock().ok();
+    let report = report(
+        "failed",
+        &format!("## Error\n\n{err:?}\n"),
+        log_lines.iter().flat_map(|log_lines| log_lines.iter()),
+    );
+
+    write_cache_file("error", &report)
+}
+
+/// Writes a new file, named using the given prefix and the current time, into the cache
+/// directory.
+fn write_cache_file(prefix: &str, contents: &str) -> Option<PathBuf> {
+    let cache_dir = cache_dir()?;
+    create_dir_all(&cache_dir).ok()?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let path = cache_dir.join(format!("{prefix}-{timestamp}.txt"));
+
+    write(&path, contents).ok()?;
+
+    Some(path)
+}
+
+/// Returns the panic and backtrace sections of a crash report.
+fn panic_details(panic_info: &PanicInfo, backtrace: &Backtrace) -> String {
+    let message = panic_info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| {
+            panic_info
+                .payload()
+                .downcast_ref::<String>()
+                .map(String::as_str)
+        })
+        .unwrap_or("(unknown)");
+    let location = panic_info
+        .location()
+        .map(|location| location.to_string())
+        .unwrap_or_default();
+
+    format!(
+        "## Panic\n\nthread '{}' panicked at {location}:\n{message}\n\n\
+        ## Backtrace\n\n{backtrace}",
+        thread::current().name().unwrap_or("<unnamed>")
+    )
+}
+
+/// Returns a report, for the player to send to developers, with the given details followed by the
+/// config, device and recent log lines.
+fn report<'a>(summary: &str, details: &str, log_lines: impl Iterator<Item = &'a str>) -> String {
+    let mut report = String::new();
+
+    // Writing to a String cannot fail
+    writeln!(
+        report,
+        "{} {} {summary}\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    )
+    .unwrap_or_default();
+    writeln!(report, "{details}").unwrap_or_default();
+    writeln!(
+        report,
+        "## Config\n\n{}",
+        CONFIG.get().map(String::as_str).unwrap_or("(not read)\n")
+    )
+    .unwrap_or_default();
+    writeln!(
+        report,
+        "## Device\n\n{}",
+        DEVICE
+            .get()
+            .map(String::as_str)
+            .unwrap_or("(not created)\n")
+    )
+    .unwrap_or_default();
+    writeln!(report, "## Log\n").unwrap_or_default();
+
+    for line in log_lines {
+        writeln!(report, "{line}").unwrap_or_default();
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn log_lines_capacity() {
+        let mut log_lines = LogLines::new();
+
+        for idx in 0..LogLines::CAPACITY + 10 {
+            log_lines.push(idx.to_string());
+        }
+
+        assert_eq!(log_lines.iter().count(), LogLines::CAPACITY);
+        assert_eq!(log_lines.iter().next(), Some("10"));
+        assert_eq!(
+            log_lines.iter().last(),
+            Some((LogLines::CAPACITY + 9).to_string().as_str())
+        );
+    }
+}
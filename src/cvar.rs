@@ -0,0 +1,425 @@
+//! Console variables ("cvars"): settings and debug toggles which are read and changed by name, such
+//! as `set render.fov 60` from the console.
+//!
+//! Persistent cvars are views of [`Config`] fields, so the config file holds exactly the
+//! persistent subset; other cvars are kept here and reset each time the game starts.
+
+use {
+    crate::config::Config,
+    anyhow::{bail, Context},
+    bitflags::bitflags,
+    std::{
+        collections::BTreeMap,
+        fmt::Write,
+        mem::{discriminant, take},
+    },
+};
+
+/// Called with the new value each time a cvar changes.
+type ChangeCallback = Box<dyn FnMut(&CvarValue)>;
+
+bitflags! {
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub struct CvarFlags: u8 {
+        /// The cvar is a field of the config and is written to the config file when it changes.
+        const PERSISTENT = 0b0000_0001;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CvarValue {
+    Bool(bool),
+    Float(f32),
+    Int(i64),
+}
+
+impl CvarValue {
+    pub fn as_bool(self) -> bool {
+        match self {
+            Self::Bool(value) => value,
+            Self::Float(value) => value != 0.0,
+            Self::Int(value) => value != 0,
+        }
+    }
+
+    pub fn as_float(self) -> f32 {
+        match self {
+            Self::Bool(value) => value as u8 as _,
+            Self::Float(value) => value,
+            Self::Int(value) => value as _,
+        }
+    }
+
+    pub fn as_int(self) -> i64 {
+        match self {
+            Self::Bool(value) => value as _,
+            Self::Float(value) => value.round() as _,
+            Self::Int(value) => value,
+        }
+    }
+
+    fn kind(self) -> &'static str {
+        match self {
+            Self::Bool(_) => "bool",
+            Self::Float(_) => "float",
+            Self::Int(_) => "int",
+        }
+    }
+
+    /// Parses text as a value of the same kind as this one.
+    fn parse(self, text: &str) -> anyhow::Result<Self> {
+        Ok(match self {
+            Self::Bool(_) => Self::Bool(match text.to_ascii_lowercase().as_str() {
+                "1" | "on" | "true" => true,
+                "0" | "off" | "false" => false,
+                _ => bail!("Expected true or false, not `{text}`"),
+            }),
+            Self::Float(_) => Self::Float(
+                text.parse()
+                    .with_context(|| format!("Expected a number, not `{text}`"))?,
+            ),
+            Self::Int(_) => Self::Int(
+                text.parse()
+                    .with_context(|| format!("Expected a whole number, not `{text}`"))?,
+            ),
+        })
+    }
+}
+
+impl std::fmt::Display for CvarValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Float(value) => write!(f, "{value}"),
+            Self::Int(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+enum CvarStorage {
+    /// A field of the config; the setter clamps values to the range the field allows.
+    Config {
+        get: fn(&Config) -> CvarValue,
+        set: fn(&mut Config, CvarValue),
+    },
+    Value(CvarValue),
+}
+
+struct Cvar {
+    callbacks: Vec<ChangeCallback>,
+    description: &'static str,
+    flags: CvarFlags,
+    storage: CvarStorage,
+}
+
+impl Cvar {
+    fn get(&self, config: &Config) -> CvarValue {
+        match &self.storage {
+            CvarStorage::Config { get, .. } => get(config),
+            CvarStorage::Value(value) => *value,
+        }
+    }
+}
+
+/// Every cvar, by name.
+///
+/// Names are grouped by a prefix such as `audio.`, `debug.` or `render.`.
+pub struct Cvars {
+    cvars: BTreeMap<&'static str, Cvar>,
+
+    /// Set when a persistent cvar changes, until taken so that the config is written.
+    is_config_changed: bool,
+}
+
+impl Default for Cvars {
+    fn default() -> Self {
+        let mut res = Self {
+            cvars: Default::default(),
+            is_config_changed: false,
+        };
+
+        res.register_config(
+            "audio.master_volume",
+            "Loudness of every sound (0 to 1)",
+            |config| CvarValue::Float(config.master_volume),
+            |config, value| config.master_volume = value.as_float().clamp(0.0, 1.0),
+        );
+        res.register_config(
+            "debug.cheats",
+            "Cheat codes typed during play take effect",
+            |config| CvarValue::Bool(config.cheats),
+            |config, value| config.cheats = value.as_bool(),
+        );
+        res.register_config(
+            "input.mouse_sensitivity",
+            "Look speed of the mouse",
+            |config| CvarValue::Float(config.mouse_sensitivity),
+            |config, value| config.mouse_sensitivity = value.as_float().max(0.0),
+        );
+        res.register_config(
+            "render.flash_intensity",
+            "Brightness of flashes, such as explosions (0 to 1)",
+            |config| CvarValue::Float(config.flash_intensity),
+            |config, value| config.flash_intensity = value.as_float().clamp(0.0, 1.0),
+        );
+        res.register_config(
+            "render.fov",
+            "Vertical field of view, in degrees",
+            |config| CvarValue::Float(config.fov),
+            |config, value| config.fov = value.as_float().clamp(Config::MIN_FOV, Config::MAX_FOV),
+        );
+        res.register_config(
+            "render.framerate_limit",
+            "Most frames each second without v-sync (60 to 480)",
+            |config| CvarValue::Int(config.framerate_limit as _),
+            |config, value| config.framerate_limit = value.as_int().clamp(60, 480) as _,
+        );
+        res.register_config(
+            "render.head_bob",
+            "Scale of the camera bob while walking",
+            |config| CvarValue::Float(config.head_bob),
+            |config, value| config.head_bob = value.as_float().max(0.0),
+        );
+        res.register_config(
+            "render.streaming_budget",
+            "Milliseconds of streaming work started each frame during play",
+            |config| CvarValue::Float(config.streaming_budget),
+            |config, value| config.streaming_budget = value.as_float().max(0.0),
+        );
+        res.register_config(
+            "ui.captions",
+            "Shows captions of speech and sounds",
+            |config| CvarValue::Bool(config.captions),
+            |config, value| config.captions = value.as_bool(),
+        );
+        res.register_config(
+            "ui.hud_scale",
+            "Size of the HUD (0.5 to 2)",
+            |config| CvarValue::Float(config.hud_scale),
+            |config, value| {
+                config.hud_scale = value
+                    .as_float()
+                    .clamp(Config::MIN_HUD_SCALE, Config::MAX_HUD_SCALE)
+            },
+        );
+
+        res.register(
+            "debug.show_frame_graph",
+            "Shows frame times and statistics (F3)",
+            CvarValue::Bool(false),
+        );
+        res.register(
+            "debug.show_navmesh",
+            "Draws the navigation mesh (F2)",
+            CvarValue::Bool(false),
+        );
+        res.register(
+            "render.depth_prepass",
+            "Draws the depth of opaque models before shading them",
+            CvarValue::Bool(false),
+        );
+
+        res
+    }
+}
+
+impl Cvars {
+    /// Runs a console command, returning the text to print.
+    ///
+    /// The commands are `get <name>`, `set <name> <value>` and `list [prefix]`.
+    pub fn execute(&mut self, config: &mut Config, command: &str) -> anyhow::Result<String> {
+        let mut args = command.split_whitespace();
+
+        match (args.next(), args.next(), args.next(), args.next()) {
+            (Some("get"), Some(name), None, None) => {
+                let value = self.get(config, name).context("Unknown cvar")?;
+
+                Ok(format!("{name} = {value}"))
+            }
+            (Some("list"), prefix, None, None) => {
+                let mut res = String::new();
+
+                for (name, cvar) in self
+                    .cvars
+                    .iter()
+                    .filter(|(name, _)| name.starts_with(prefix.unwrap_or_default()))
+                {
+                    let persistent = if cvar.flags.contains(CvarFlags::PERSISTENT) {
+                        " (saved)"
+                    } else {
+                        ""
+                    };
+
+                    writeln!(
+                        res,
+                        "{name} = {}: {}{persistent}",
+                        cvar.get(config),
+                        cvar.description
+                    )
+                    .unwrap();
+                }
+
+                res.pop();
+
+                Ok(res)
+            }
+            (Some("set"), Some(name), Some(text), None) => {
+                let value = self
+                    .get(config, name)
+                    .context("Unknown cvar")?
+                    .parse(text)?;
+                self.set(config, name, value)?;
+
+                Ok(format!("{name} = {}", self.get(config, name).unwrap()))
+            }
+            (None, ..) => Ok(String::new()),
+            _ => bail!("Usage: get <name>, set <name> <value> or list [prefix]"),
+        }
+    }
+
+    pub fn get(&self, config: &Config, name: &str) -> Option<CvarValue> {
+        self.cvars.get(name).map(|cvar| cvar.get(config))
+    }
+
+    /// Returns `true` if the named cvar is set, or `false` if it is not or does not exist.
+    pub fn is_set(&self, config: &Config, name: &str) -> bool {
+        self.get(config, name).is_some_and(CvarValue::as_bool)
+    }
+
+    /// Calls `callback` with the new value each time the named cvar changes.
+    pub fn on_change(&mut self, name: &str, callback: impl FnMut(&CvarValue) + 'static) {
+        self.cvars
+            .get_mut(name)
+            .unwrap_or_else(|| panic!("Unknown cvar {name}"))
+            .callbacks
+            .push(Box::new(callback));
+    }
+
+    fn register(&mut self, name: &'static str, description: &'static str, value: CvarValue) {
+        self.cvars.insert(
+            name,
+            Cvar {
+                callbacks: vec![],
+                description,
+                flags: CvarFlags::empty(),
+                storage: CvarStorage::Value(value),
+            },
+        );
+    }
+
+    fn register_config(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        get: fn(&Config) -> CvarValue,
+        set: fn(&mut Config, CvarValue),
+    ) {
+        self.cvars.insert(
+            name,
+            Cvar {
+                callbacks: vec![],
+                description,
+                flags: CvarFlags::PERSISTENT,
+                storage: CvarStorage::Config { get, set },
+            },
+        );
+    }
+
+    /// Sets the named cvar, which must be given a value of the same kind it has, calling its change
+    /// callbacks if the value changed.
+    pub fn set(&mut self, config: &mut Config, name: &str, value: CvarValue) -> anyhow::Result<()> {
+        let cvar = self.cvars.get_mut(name).context("Unknown cvar")?;
+        let prev_value = cvar.get(config);
+
+        if discriminant(&value) != discriminant(&prev_value) {
+            bail!("Expected a {} value for {name}", prev_value.kind());
+        }
+
+        match &mut cvar.storage {
+            CvarStorage::Config { set, .. } => set(config, value),
+            CvarStorage::Value(stored) => *stored = value,
+        }
+
+        let value = cvar.get(config);
+
+        if value != prev_value {
+            self.is_config_changed |= cvar.flags.contains(CvarFlags::PERSISTENT);
+
+            for callback in &mut cvar.callbacks {
+                callback(&value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` once for any number of persistent cvar changes, after which the config
+    /// should be written.
+    pub fn take_config_changed(&mut self) -> bool {
+        take(&mut self.is_config_changed)
+    }
+
+    /// Flips the named boolean cvar.
+    pub fn toggle(&mut self, config: &mut Config, name: &str) -> anyhow::Result<()> {
+        let value = !self.get(config, name).context("Unknown cvar")?.as_bool();
+
+        self.set(config, name, CvarValue::Bool(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{cell::Cell, rc::Rc},
+    };
+
+    #[test]
+    pub fn get_and_set() {
+        let mut config = Config::default();
+        let mut cvars = Cvars::default();
+        let changes = Rc::new(Cell::new(0));
+
+        cvars.on_change("render.fov", {
+            let changes = Rc::clone(&changes);
+            move |_| changes.set(changes.get() + 1)
+        });
+
+        assert_eq!(
+            cvars.execute(&mut config, "set render.fov 60").unwrap(),
+            "render.fov = 60"
+        );
+        assert_eq!(config.fov, 60.0);
+        assert!(cvars.take_config_changed());
+        assert!(!cvars.take_config_changed());
+
+        // Setting the same value is not a change, and values are kept within range
+        cvars.execute(&mut config, "set render.fov 60").unwrap();
+        cvars.execute(&mut config, "set render.fov 1000").unwrap();
+        assert_eq!(config.fov, Config::MAX_FOV);
+        assert_eq!(changes.get(), 2);
+
+        // Debug toggles are not saved
+        cvars.toggle(&mut config, "debug.show_navmesh").unwrap();
+        assert!(cvars.is_set(&config, "debug.show_navmesh"));
+        assert!(!cvars.take_config_changed());
+        assert_eq!(
+            cvars
+                .execute(&mut config, "get debug.show_navmesh")
+                .unwrap(),
+            "debug.show_navmesh = true"
+        );
+
+        assert!(cvars
+            .execute(&mut config, "set debug.show_navmesh 2")
+            .is_err());
+        assert!(cvars.execute(&mut config, "set render.nope 1").is_err());
+        assert!(cvars
+            .set(&mut config, "render.fov", CvarValue::Bool(true))
+            .is_err());
+        assert_eq!(
+            cvars.execute(&mut config, "list audio.").unwrap(),
+            "audio.master_volume = 1: Loudness of every sound (0 to 1) (saved)"
+        );
+    }
+}
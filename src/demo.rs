@@ -0,0 +1,488 @@
+//! Demos record the input and frame time of each frame of play to a file and replay them through
+//! the same update loop, so that bug reports and smoke tests of the play state are reproducible.
+//!
+//! Frames are only recorded while the active [`Ui`](crate::ui::Ui) accepts demo input, which keeps
+//! loading screens (whose length depends on the machine) out of the recording. The header records
+//! the level the first frame was played in, which playback loads directly instead of replaying the
+//! menus.
+
+use {
+    crate::{config::Config, game::difficulty::Difficulty},
+    anyhow::{bail, Context},
+    screen_13::prelude::*,
+    serde::{Deserialize, Serialize},
+    std::{
+        fs::File,
+        io::{BufReader, ErrorKind, Write},
+        path::Path,
+    },
+    winit::{event::DeviceId, window::WindowId},
+};
+
+/// Keys which demos record; other keys are not used by the game and are left out, so these are
+/// also the only keys which may be bound to actions.
+pub const KEYS: [VirtualKeyCode; 60] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::Escape,
+    VirtualKeyCode::F1,
+    VirtualKeyCode::F2,
+    VirtualKeyCode::F3,
+    VirtualKeyCode::F4,
+    VirtualKeyCode::F5,
+    VirtualKeyCode::F6,
+    VirtualKeyCode::F7,
+    VirtualKeyCode::F8,
+    VirtualKeyCode::F9,
+    VirtualKeyCode::Left,
+    VirtualKeyCode::Up,
+    VirtualKeyCode::Right,
+    VirtualKeyCode::Down,
+    VirtualKeyCode::Back,
+    VirtualKeyCode::Return,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::Tab,
+    VirtualKeyCode::LAlt,
+    VirtualKeyCode::LControl,
+    VirtualKeyCode::LShift,
+    VirtualKeyCode::RAlt,
+    VirtualKeyCode::RControl,
+    VirtualKeyCode::RShift,
+];
+
+/// Identifies demo files and the version of their contents; demos of other versions are refused
+/// because their input would not replay the same way.
+const MAGIC: [u8; 4] = *b"MDMO";
+const VERSION: u32 = 2;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+enum DemoButton {
+    Left,
+    Right,
+    Middle,
+    Other(u16),
+}
+
+impl DemoButton {
+    fn from_mouse_button(button: MouseButton) -> Self {
+        match button {
+            MouseButton::Left => Self::Left,
+            MouseButton::Right => Self::Right,
+            MouseButton::Middle => Self::Middle,
+            MouseButton::Other(button) => Self::Other(button),
+        }
+    }
+
+    fn mouse_button(self) -> MouseButton {
+        match self {
+            Self::Left => MouseButton::Left,
+            Self::Right => MouseButton::Right,
+            Self::Middle => MouseButton::Middle,
+            Self::Other(button) => MouseButton::Other(button),
+        }
+    }
+}
+
+/// The subset of window and device events which the game reads as input.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+enum DemoEvent {
+    CursorMoved { x: f64, y: f64 },
+    Key { key: u8, pressed: bool },
+    MouseButton { button: DemoButton, pressed: bool },
+    MouseMotion { x: f64, y: f64 },
+    MouseWheel { x: f32, y: f32 },
+    MouseWheelPixels { x: f64, y: f64 },
+}
+
+impl DemoEvent {
+    fn from_event(event: &Event<()>) -> Option<Self> {
+        let state_pressed = |state: &ElementState| *state == ElementState::Pressed;
+
+        match event {
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta: (x, y) },
+                ..
+            } => Some(Self::MouseMotion { x: *x, y: *y }),
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::CursorMoved { position, .. } => Some(Self::CursorMoved {
+                    x: position.x,
+                    y: position.y,
+                }),
+                WindowEvent::KeyboardInput { input, .. } => {
+                    let key = input.virtual_keycode?;
+                    let key = KEYS.iter().position(|&k| k == key)? as u8;
+
+                    Some(Self::Key {
+                        key,
+                        pressed: state_pressed(&input.state),
+                    })
+                }
+                WindowEvent::MouseInput { button, state, .. } => Some(Self::MouseButton {
+                    button: DemoButton::from_mouse_button(*button),
+                    pressed: state_pressed(state),
+                }),
+                WindowEvent::MouseWheel { delta, .. } => Some(match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Self::MouseWheel { x: *x, y: *y },
+                    MouseScrollDelta::PixelDelta(position) => Self::MouseWheelPixels {
+                        x: position.x,
+                        y: position.y,
+                    },
+                }),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    #[allow(deprecated)]
+    fn to_event(self) -> Event<'static, ()> {
+        // Replayed events do not come from a real window or device
+        let device_id = unsafe { DeviceId::dummy() };
+        let window_id = unsafe { WindowId::dummy() };
+        let state = |pressed| {
+            if pressed {
+                ElementState::Pressed
+            } else {
+                ElementState::Released
+            }
+        };
+
+        let event = match self {
+            Self::MouseMotion { x, y } => {
+                return Event::DeviceEvent {
+                    device_id,
+                    event: DeviceEvent::MouseMotion { delta: (x, y) },
+                }
+            }
+            Self::CursorMoved { x, y } => WindowEvent::CursorMoved {
+                device_id,
+                position: PhysicalPosition::new(x, y),
+                modifiers: Default::default(),
+            },
+            Self::Key { key, pressed } => WindowEvent::KeyboardInput {
+                device_id,
+                input: KeyboardInput {
+                    scancode: 0,
+                    state: state(pressed),
+                    virtual_keycode: Some(KEYS[key as usize]),
+                    modifiers: Default::default(),
+                },
+                is_synthetic: false,
+            },
+            Self::MouseButton { button, pressed } => WindowEvent::MouseInput {
+                device_id,
+                state: state(pressed),
+                button: button.mouse_button(),
+                modifiers: Default::default(),
+            },
+            Self::MouseWheel { x, y } => WindowEvent::MouseWheel {
+                device_id,
+                delta: MouseScrollDelta::LineDelta(x, y),
+                phase: TouchPhase::Moved,
+                modifiers: Default::default(),
+            },
+            Self::MouseWheelPixels { x, y } => WindowEvent::MouseWheel {
+                device_id,
+                delta: MouseScrollDelta::PixelDelta(PhysicalPosition::new(x, y)),
+                phase: TouchPhase::Moved,
+                modifiers: Default::default(),
+            },
+        };
+
+        Event::WindowEvent { window_id, event }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct DemoFrame {
+    dt: f32,
+    events: Vec<DemoEvent>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct DemoHeader {
+    magic: [u8; 4],
+    version: u32,
+
+    /// The settings the demo was recorded with, which affect how input plays (such as mouse
+    /// sensitivity) and so are used again during playback.
+    config: Config,
+
+    level: DemoLevel,
+}
+
+/// The level a demo starts in, as it was when the first frame was recorded.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DemoLevel {
+    pub difficulty: Difficulty,
+
+    /// Key of the scene played.
+    pub scene: String,
+
+    /// Name of the scene ref the player started at, if not the default spawn point.
+    pub spawn: Option<String>,
+}
+
+/// Writes each frame to the demo file as it is recorded, so that a demo of a crash is kept.
+pub struct DemoRecorder {
+    /// The settings to write into the header, which waits for the first frame because the level
+    /// is not known before then.
+    config: Option<Config>,
+
+    file: File,
+    frame_count: usize,
+}
+
+impl DemoRecorder {
+    pub fn create(path: impl AsRef<Path>, config: &Config) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file =
+            File::create(path).with_context(|| format!("Creating demo file {}", path.display()))?;
+
+        info!("Recording demo to {}", path.display());
+
+        Ok(Self {
+            config: Some(config.clone()),
+            file,
+            frame_count: 0,
+        })
+    }
+
+    /// Writes a frame, first writing the header with the level returned by `level` if this is the
+    /// first frame.
+    pub fn record(
+        &mut self,
+        dt: f32,
+        events: &[Event<()>],
+        level: impl FnOnce() -> Option<DemoLevel>,
+    ) -> anyhow::Result<()> {
+        if let Some(config) = self.config.take() {
+            let header = DemoHeader {
+                magic: MAGIC,
+                version: VERSION,
+                config,
+                level: level().context("Recording outside of a level")?,
+            };
+
+            bincode::serialize_into(&mut self.file, &header).context("Writing demo header")?;
+        }
+
+        let frame = DemoFrame {
+            dt,
+            events: events.iter().filter_map(DemoEvent::from_event).collect(),
+        };
+
+        // Each frame is written whole so that a demo cut short by a crash still reads back
+        let data = bincode::serialize(&frame).context("Serializing demo frame")?;
+        self.file.write_all(&data).context("Writing demo frame")?;
+        self.frame_count += 1;
+
+        Ok(())
+    }
+}
+
+impl Drop for DemoRecorder {
+    fn drop(&mut self) {
+        info!("Recorded {} demo frames", self.frame_count);
+    }
+}
+
+pub struct DemoPlayer {
+    config: Config,
+    events: Vec<Event<'static, ()>>,
+    frame_count: usize,
+    level: DemoLevel,
+    reader: BufReader<File>,
+}
+
+impl DemoPlayer {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(
+            File::open(path).with_context(|| format!("Opening demo file {}", path.display()))?,
+        );
+        let header: DemoHeader =
+            bincode::deserialize_from(&mut reader).context("Reading demo header")?;
+
+        if header.magic != MAGIC {
+            bail!("Not a demo file: {}", path.display());
+        }
+
+        if header.version != VERSION {
+            bail!(
+                "Demo version {} is not supported (expected {VERSION})",
+                header.version
+            );
+        }
+
+        info!("Playing demo {}", path.display());
+
+        Ok(Self {
+            config: header.config,
+            events: vec![],
+            frame_count: 0,
+            level: header.level,
+            reader,
+        })
+    }
+
+    /// The settings the demo was recorded with.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The level the demo starts in.
+    pub fn level(&self) -> &DemoLevel {
+        &self.level
+    }
+
+    /// Reads the next frame, returning its frame time and input events, or `None` once the demo
+    /// has ended.
+    pub fn next_frame(&mut self) -> Option<(f32, &[Event<'static, ()>])> {
+        let frame: DemoFrame = match bincode::deserialize_from(&mut self.reader) {
+            Ok(frame) => frame,
+            Err(err) => {
+                // A demo which ends part way through a frame was cut short while recording
+                let is_eof = matches!(
+                    &*err,
+                    bincode::ErrorKind::Io(err) if err.kind() == ErrorKind::UnexpectedEof
+                );
+
+                if !is_eof {
+                    warn!("Unable to read demo frame: {err}");
+                }
+
+                info!("Played {} demo frames", self.frame_count);
+
+                return None;
+            }
+        };
+
+        self.frame_count += 1;
+        self.events.clear();
+        self.events
+            .extend(frame.events.into_iter().map(DemoEvent::to_event));
+
+        Some((frame.dt, &self.events))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::fs::remove_file};
+
+    #[test]
+    pub fn demo_round_trip() {
+        let path = std::env::temp_dir().join(format!("mood-demo-{}.bin", std::process::id()));
+        let level = DemoLevel {
+            difficulty: Difficulty::Hard,
+            scene: "scene/level_01".to_owned(),
+            spawn: Some("Checkpoint_a".to_owned()),
+        };
+        let mut recorder = DemoRecorder::create(&path, &Config::default()).unwrap();
+        recorder
+            .record(1.0 / 60.0, &[], || Some(level.clone()))
+            .unwrap();
+
+        // The level is only asked for once, when the header is written
+        recorder.record(1.0 / 30.0, &[], || None).unwrap();
+        drop(recorder);
+
+        let mut player = DemoPlayer::open(&path).unwrap();
+
+        assert_eq!(player.level(), &level);
+        assert_eq!(player.next_frame().map(|(dt, _)| dt), Some(1.0 / 60.0));
+        assert_eq!(player.next_frame().map(|(dt, _)| dt), Some(1.0 / 30.0));
+        assert!(player.next_frame().is_none());
+
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn events_round_trip() {
+        let events = [
+            DemoEvent::CursorMoved { x: 12.5, y: 3.0 },
+            DemoEvent::Key {
+                key: KEYS
+                    .iter()
+                    .position(|&key| key == VirtualKeyCode::W)
+                    .unwrap() as _,
+                pressed: true,
+            },
+            DemoEvent::MouseButton {
+                button: DemoButton::Other(8),
+                pressed: false,
+            },
+            DemoEvent::MouseMotion { x: -4.0, y: 2.0 },
+            DemoEvent::MouseWheel { x: 0.0, y: -1.0 },
+        ];
+
+        for event in events {
+            assert_eq!(DemoEvent::from_event(&event.to_event()), Some(event));
+        }
+    }
+
+    #[test]
+    pub fn frames_round_trip() {
+        let frames = [
+            DemoFrame {
+                dt: 1.0 / 60.0,
+                events: vec![DemoEvent::MouseMotion { x: 1.0, y: 0.0 }],
+            },
+            DemoFrame {
+                dt: 1.0 / 30.0,
+                events: vec![],
+            },
+        ];
+        let mut data = vec![];
+
+        for frame in &frames {
+            data.extend(bincode::serialize(frame).unwrap());
+        }
+
+        let mut reader = data.as_slice();
+
+        for frame in &frames {
+            let read: DemoFrame = bincode::deserialize_from(&mut reader).unwrap();
+
+            assert_eq!(read.dt, frame.dt);
+            assert_eq!(read.events, frame.events);
+        }
+
+        assert!(bincode::deserialize_from::<_, DemoFrame>(&mut reader).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use {
+    crate::config::{FullscreenMode, VSync},
+    screen_13::prelude::*,
+    std::sync::Arc,
+    winit::{monitor::MonitorHandle, window::Fullscreen},
+};
+
+/// Returns the monitor at `index` in the platform order, or the primary monitor if there is no
+/// index or no such monitor.
+pub fn select_monitor(
+    mut available_monitors: impl Iterator<Item = MonitorHandle>,
+    primary_monitor: Option<MonitorHandle>,
+    index: Option<usize>,
+) -> Option<MonitorHandle> {
+    if let Some(index) = index {
+        if let Some(monitor) = available_monitors.nth(index) {
+            return Some(monitor);
+        }
+
+        warn!("Monitor {index} not found, using primary monitor");
+
+        return primary_monitor;
+    }
+
+    primary_monitor.or_else(|| available_monitors.next())
+}
+
+/// Returns the fullscreen state of a window shown on the given monitor.
+pub fn fullscreen(mode: FullscreenMode, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+    match mode {
+        FullscreenMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+        FullscreenMode::Exclusive => {
+            let monitor = monitor?;
+            let size = monitor.size();
+
+            // Prefer the native resolution at the highest refresh rate the monitor offers
+            let video_mode = monitor.video_modes().max_by_key(|video_mode| {
+                (
+                    video_mode.size() == size,
+                    video_mode.size().width * video_mode.size().height,
+                    video_mode.refresh_rate_millihertz(),
+                    video_mode.bit_depth(),
+                )
+            });
+
+            if video_mode.is_none() {
+                warn!("No exclusive video modes, using borderless fullscreen");
+            }
+
+            Some(
+                video_mode
+                    .map(Fullscreen::Exclusive)
+                    .unwrap_or(Fullscreen::Borderless(Some(monitor))),
+            )
+        }
+    }
+}
+
+/// Returns the fullscreen mode a running window is shown in, or `None` if it is windowed.
+pub fn fullscreen_mode(window: &Window) -> Option<FullscreenMode> {
+    window.fullscreen().map(|fullscreen| match fullscreen {
+        Fullscreen::Borderless(_) => FullscreenMode::Borderless,
+        Fullscreen::Exclusive(_) => FullscreenMode::Exclusive,
+    })
+}
+
+/// Returns the size and position of a window which is three quarters of the monitor size and
+/// centered on it.
+pub fn windowed_rect(monitor: &MonitorHandle) -> (PhysicalSize<u32>, PhysicalPosition<i32>) {
+    let monitor_position = monitor.position();
+    let monitor_size = monitor.size();
+    let window_size = PhysicalSize::new(monitor_size.width * 3 / 4, monitor_size.height * 3 / 4);
+    let window_position = PhysicalPosition::new(
+        monitor_position.x + (monitor_size.width / 2 - window_size.width / 2) as i32,
+        monitor_position.y + (monitor_size.height / 2 - window_size.height / 2) as i32,
+    );
+
+    (window_size, window_position)
+}
+
+/// Switches a running window between windowed mode (`None`) and the given fullscreen mode on the
+/// given monitor, as when display settings are changed.
+pub fn set_fullscreen(window: &Window, mode: Option<FullscreenMode>, monitor: Option<usize>) {
+    let monitor = select_monitor(
+        window.available_monitors(),
+        window.primary_monitor(),
+        monitor,
+    );
+
+    if let Some(mode) = mode {
+        info!("Switching to {mode:?} fullscreen");
+
+        window.set_fullscreen(fullscreen(mode, monitor));
+    } else {
+        info!("Switching to windowed mode");
+
+        window.set_fullscreen(None);
+
+        if let Some(monitor) = monitor {
+            let (window_size, window_position) = windowed_rect(&monitor);
+            window.set_inner_size(window_size);
+            window.set_outer_position(window_position);
+        }
+    }
+}
+
+/// Returns `true` if frames presented with the given mode wait for the display to refresh, which
+/// paces the game without the framerate limiter.
+pub fn is_display_synced(present_mode: vk::PresentModeKHR) -> bool {
+    matches!(
+        present_mode,
+        vk::PresentModeKHR::FIFO | vk::PresentModeKHR::FIFO_RELAXED
+    )
+}
+
+/// Returns the present modes which give the given v-sync setting, most preferred first; each
+/// ends with FIFO, which every display supports.
+pub fn present_modes(v_sync: VSync) -> &'static [vk::PresentModeKHR] {
+    match v_sync {
+        VSync::Off => &[
+            vk::PresentModeKHR::IMMEDIATE,
+            vk::PresentModeKHR::MAILBOX,
+            vk::PresentModeKHR::FIFO,
+        ],
+        VSync::On => &[vk::PresentModeKHR::FIFO],
+        VSync::Adaptive => &[vk::PresentModeKHR::FIFO_RELAXED, vk::PresentModeKHR::FIFO],
+        VSync::Mailbox => &[vk::PresentModeKHR::MAILBOX, vk::PresentModeKHR::FIFO],
+    }
+}
+
+/// Returns the first of the [`present_modes`] of the given v-sync setting which is `supported`,
+/// which is the mode the swapchain presents with.
+pub fn select_present_mode(v_sync: VSync, supported: &[vk::PresentModeKHR]) -> vk::PresentModeKHR {
+    let present_modes = present_modes(v_sync);
+    let present_mode = present_modes
+        .iter()
+        .copied()
+        .find(|present_mode| supported.contains(present_mode))
+        .unwrap_or(vk::PresentModeKHR::FIFO);
+
+    if present_mode != present_modes[0] {
+        info!(
+            "{:?} is not supported, using {present_mode:?}",
+            present_modes[0]
+        );
+    }
+
+    present_mode
+}
+
+/// Returns the present modes the display supports for the given window, or none if they could
+/// not be read.
+pub fn supported_present_modes(device: &Arc<Device>, window: &Window) -> Vec<vk::PresentModeKHR> {
+    Surface::create(device, window)
+        .and_then(|surface| Surface::present_modes(&surface))
+        .unwrap_or_else(|err| {
+            warn!("Unable to read present modes: {err}");
+
+            vec![]
+        })
+}
+
+/// Sizes of the framebuffer and cursor, which together set the size of all UI elements, derived
+/// from one scale factor so that they stay consistent across resolutions and display densities.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UiScale {
+    /// Height of the framebuffer which the game and UI are drawn into, in pixels.
+    pub framebuffer_height: u32,
+
+    /// Window pixels covered by each framebuffer pixel, which the cursor is also drawn at.
+    pub pixel_scale: f32,
+}
+
+impl UiScale {
+    pub const MAX: f32 = 2.0;
+    pub const MIN: f32 = 0.5;
+
+    /// Window pixels of each framebuffer pixel at a scale of one on a display without scaling;
+    /// a 1080 pixel tall window has a 300 pixel tall framebuffer.
+    const REFERENCE_PIXEL_SCALE: f64 = 3.6;
+
+    /// Amount the scale changes with each adjustment.
+    pub const STEP: f32 = 0.25;
+
+    /// Returns the scale of a window with the given height, in physical pixels, and DPI scale
+    /// factor (as reported by the platform) using the `ui_scale` config option.
+    pub fn new(window_height: u32, window_scale_factor: f64, ui_scale: f32) -> Self {
+        let ui_scale = ui_scale.clamp(Self::MIN, Self::MAX) as f64;
+        let pixel_scale = Self::REFERENCE_PIXEL_SCALE * window_scale_factor * ui_scale;
+        let framebuffer_height = (window_height as f64 / pixel_scale).round() as u32;
+        let framebuffer_height = framebuffer_height.clamp(1, window_height.max(1));
+
+        Self {
+            framebuffer_height,
+            pixel_scale: window_height as f32 / framebuffer_height as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn present_mode_fallback() {
+        let all = [
+            vk::PresentModeKHR::IMMEDIATE,
+            vk::PresentModeKHR::MAILBOX,
+            vk::PresentModeKHR::FIFO,
+            vk::PresentModeKHR::FIFO_RELAXED,
+        ];
+
+        assert_eq!(
+            select_present_mode(VSync::Off, &all),
+            vk::PresentModeKHR::IMMEDIATE
+        );
+        assert_eq!(
+            select_present_mode(VSync::Adaptive, &all),
+            vk::PresentModeKHR::FIFO_RELAXED
+        );
+        assert_eq!(
+            select_present_mode(VSync::Mailbox, &all),
+            vk::PresentModeKHR::MAILBOX
+        );
+
+        // Without immediate presentation, mailbox still avoids waiting for the display
+        assert_eq!(
+            select_present_mode(VSync::Off, &[vk::PresentModeKHR::MAILBOX]),
+            vk::PresentModeKHR::MAILBOX
+        );
+
+        // FIFO is always supported, even when the supported modes could not be read
+        for v_sync in [VSync::Off, VSync::On, VSync::Adaptive, VSync::Mailbox] {
+            assert_eq!(
+                select_present_mode(v_sync, &[vk::PresentModeKHR::FIFO]),
+                vk::PresentModeKHR::FIFO
+            );
+            assert_eq!(select_present_mode(v_sync, &[]), vk::PresentModeKHR::FIFO);
+        }
+
+        assert!(!is_display_synced(vk::PresentModeKHR::MAILBOX));
+        assert!(is_display_synced(vk::PresentModeKHR::FIFO_RELAXED));
+    }
+
+    #[test]
+    pub fn ui_scale() {
+        // 1080p without display scaling matches the original fixed framebuffer
+        let scale = UiScale::new(1080, 1.0, 1.0);
+        assert_eq!(scale.framebuffer_height, 300);
+        assert_eq!(scale.pixel_scale, 3.6);
+
+        // 4K with 200% display scaling has UI of the same physical size as 1080p
+        assert_eq!(UiScale::new(2160, 2.0, 1.0).framebuffer_height, 300);
+
+        // Larger UI scales have fewer, larger framebuffer pixels
+        assert_eq!(UiScale::new(1080, 1.0, 2.0).framebuffer_height, 150);
+        assert_eq!(
+            UiScale::new(1080, 1.0, 100.0),
+            UiScale::new(1080, 1.0, UiScale::MAX)
+        );
+
+        // Tiny windows never have an empty framebuffer
+        assert_eq!(UiScale::new(1, 1.0, 1.0).framebuffer_height, 1);
+    }
+}
@@ -0,0 +1,127 @@
+//! Releases the cursor while the window does not have focus, such as after alt-tabbing away from
+//! fullscreen, and pauses input until the window is clicked again so that the click which brings
+//! the game back is not also used by it.
+
+use screen_13::prelude::*;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum State {
+    #[default]
+    Focused,
+
+    /// The window does not have focus.
+    Lost,
+
+    /// The window has focus again but waits for a click before grabbing the cursor.
+    Regained,
+}
+
+impl State {
+    fn next(self, event: &WindowEvent, grab: bool) -> Self {
+        match (self, event) {
+            (_, WindowEvent::Focused(false)) => Self::Lost,
+            (Self::Lost, WindowEvent::Focused(true)) if grab => Self::Regained,
+            (Self::Lost, WindowEvent::Focused(true)) => Self::Focused,
+            (
+                Self::Regained,
+                WindowEvent::MouseInput {
+                    button: MouseButton::Left,
+                    state: ElementState::Pressed,
+                    ..
+                },
+            ) => Self::Focused,
+            (state, _) => state,
+        }
+    }
+}
+
+/// Decides whether the cursor is grabbed and whether input is used, from the window focus and
+/// what the active UI state asks for.
+#[derive(Debug, Default)]
+pub struct WindowFocus {
+    /// The active UI state wants the cursor confined to the window while it has focus.
+    grab: bool,
+
+    state: State,
+}
+
+impl WindowFocus {
+    fn apply(&self, window: &Window) {
+        let grab = self.grab && self.state == State::Focused;
+        let mode = if grab {
+            CursorGrabMode::Confined
+        } else {
+            CursorGrabMode::None
+        };
+
+        window.set_cursor_grab(mode).unwrap_or_default();
+
+        // The UI draws its own cursor, so the system cursor is only shown while it is needed to
+        // switch windows or to click back into this one
+        window.set_cursor_visible(self.state != State::Focused);
+    }
+
+    /// Handles the window events of a frame; called after the UI has updated so that the click
+    /// which resumes input is not also used by the UI.
+    pub fn handle_events(&mut self, window: &Window, events: &[Event<()>]) {
+        for event in events {
+            let Event::WindowEvent { event, .. } = event else {
+                continue;
+            };
+
+            let state = self.state.next(event, self.grab);
+
+            if state != self.state {
+                self.state = state;
+                self.apply(window);
+            }
+        }
+    }
+
+    /// Returns `true` if input should be used: the window has focus and has been clicked since
+    /// the cursor was released.
+    pub fn is_active(&self) -> bool {
+        self.state == State::Focused
+    }
+
+    /// Returns `true` if the window has focus again but waits to be clicked.
+    pub fn is_awaiting_click(&self) -> bool {
+        self.state == State::Regained
+    }
+
+    /// Sets whether the cursor is confined to the window while the window has focus.
+    pub fn set_grab(&mut self, window: &Window, grab: bool) {
+        self.grab = grab;
+        self.apply(window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn resume_after_click() {
+        let click = WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state: ElementState::Pressed,
+            button: MouseButton::Left,
+            modifiers: Default::default(),
+        };
+
+        let state = State::Focused.next(&WindowEvent::Focused(false), true);
+
+        assert_eq!(state, State::Lost);
+        assert_eq!(state.next(&click, true), State::Lost);
+
+        let state = state.next(&WindowEvent::Focused(true), true);
+
+        assert_eq!(state, State::Regained);
+        assert_eq!(state.next(&click, true), State::Focused);
+
+        // Without a grabbed cursor there is nothing to click back into
+        let state = State::Lost.next(&WindowEvent::Focused(true), false);
+
+        assert_eq!(state, State::Focused);
+    }
+}
@@ -0,0 +1,123 @@
+/// Collects a rolling history of frame times so that stutter may be measured and displayed.
+#[derive(Debug)]
+pub struct FrameStats {
+    frame_count: usize,
+    frame_times: Box<[f32; Self::HISTORY_LEN]>,
+    head: usize,
+}
+
+impl FrameStats {
+    /// Number of frames kept in the history; large enough that the 0.1% low is a single frame.
+    pub const HISTORY_LEN: usize = 1_000;
+
+    /// Average frame time (in seconds) of the history, or zero if there is no history.
+    pub fn average_frame_time(&self) -> f32 {
+        self.frame_times().sum::<f32>() / self.len().max(1) as f32
+    }
+
+    /// Frame times (in seconds) from oldest to newest.
+    pub fn frame_times(&self) -> impl DoubleEndedIterator<Item = f32> + ExactSizeIterator + '_ {
+        let len = self.len();
+        let start = (self.head + Self::HISTORY_LEN - len) % Self::HISTORY_LEN;
+
+        (0..len).map(move |idx| self.frame_times[(start + idx) % Self::HISTORY_LEN])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frame_count == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.frame_count.min(Self::HISTORY_LEN)
+    }
+
+    /// Average frames per second of the slowest `fraction` of frames in the history; for example
+    /// `0.01` returns the "1% low".
+    pub fn low_frames_per_sec(&self, fraction: f32) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+
+        let mut frame_times = self.frame_times().collect::<Vec<_>>();
+        frame_times.sort_by(|lhs, rhs| rhs.total_cmp(lhs));
+
+        let count = ((frame_times.len() as f32 * fraction).ceil() as usize).max(1);
+        let average = frame_times[0..count].iter().sum::<f32>() / count as f32;
+
+        if average > 0.0 {
+            1.0 / average
+        } else {
+            0.0
+        }
+    }
+
+    /// The "1% low" frames per second.
+    pub fn one_percent_low(&self) -> f32 {
+        self.low_frames_per_sec(0.01)
+    }
+
+    /// The "0.1% low" frames per second.
+    pub fn point_one_percent_low(&self) -> f32 {
+        self.low_frames_per_sec(0.001)
+    }
+
+    /// Records the time (in seconds) of a single frame, replacing the oldest frame once the
+    /// history is full.
+    pub fn push(&mut self, dt: f32) {
+        self.frame_times[self.head] = dt;
+        self.head = (self.head + 1) % Self::HISTORY_LEN;
+        self.frame_count += 1;
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self {
+            frame_count: 0,
+            frame_times: Box::new([0.0; Self::HISTORY_LEN]),
+            head: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn frame_stats_ring_buffer() {
+        let mut stats = FrameStats::default();
+
+        assert!(stats.is_empty());
+        assert_eq!(stats.one_percent_low(), 0.0);
+
+        for idx in 0..FrameStats::HISTORY_LEN + 10 {
+            stats.push(idx as f32);
+        }
+
+        assert_eq!(stats.len(), FrameStats::HISTORY_LEN);
+        assert_eq!(stats.frame_times().next(), Some(10.0));
+        assert_eq!(
+            stats.frame_times().next_back(),
+            Some((FrameStats::HISTORY_LEN + 9) as f32)
+        );
+    }
+
+    #[test]
+    pub fn frame_stats_lows() {
+        let mut stats = FrameStats::default();
+
+        for _ in 0..990 {
+            stats.push(0.01);
+        }
+
+        for _ in 0..10 {
+            stats.push(0.1);
+        }
+
+        assert!((stats.average_frame_time() - 0.0109).abs() < 1e-5);
+        assert!((stats.one_percent_low() - 10.0).abs() < 1e-3);
+        assert!((stats.point_one_percent_low() - 10.0).abs() < 1e-3);
+        assert!((stats.low_frames_per_sec(1.0) - 1.0 / 0.0109).abs() < 1e-1);
+    }
+}
@@ -0,0 +1,190 @@
+//! Achievements, which unlock once the player has done something enough times over every session,
+//! and are kept in the local data directory.
+
+use {
+    super::{
+        events::{GameEvent, GameEventListener},
+        save_file::{self, Versioned},
+    },
+    crate::fs::project_dirs,
+    anyhow::bail,
+    log::info,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fmt,
+        mem::take,
+        path::PathBuf,
+    },
+};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Achievement {
+    /// Complete a level.
+    Escapist,
+
+    /// Kill 100 enemies.
+    Exterminator,
+
+    /// Kill an enemy.
+    FirstBlood,
+
+    /// Find 10 secrets.
+    Snoop,
+
+    /// Complete a level in under five minutes.
+    Sprinter,
+}
+
+impl Achievement {
+    pub const ALL: [Self; 5] = [
+        Self::Escapist,
+        Self::Exterminator,
+        Self::FirstBlood,
+        Self::Snoop,
+        Self::Sprinter,
+    ];
+
+    const SPRINT_SECS: f32 = 300.0;
+
+    /// Returns the progress the given event makes towards this achievement.
+    fn progress(self, event: &GameEvent) -> u32 {
+        match (self, event) {
+            (Self::Escapist, GameEvent::LevelCompleted { .. })
+            | (Self::Exterminator | Self::FirstBlood, GameEvent::EnemyKilled { .. })
+            | (Self::Snoop, GameEvent::SecretFound { .. }) => 1,
+            (Self::Sprinter, GameEvent::LevelCompleted { secs, .. }) => {
+                (*secs < Self::SPRINT_SECS) as u32
+            }
+            _ => 0,
+        }
+    }
+
+    /// Returns the progress at which this achievement unlocks.
+    fn goal(self) -> u32 {
+        match self {
+            Self::Exterminator => 100,
+            Self::Snoop => 10,
+            Self::Escapist | Self::FirstBlood | Self::Sprinter => 1,
+        }
+    }
+}
+
+impl fmt::Display for Achievement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Escapist => "Escapist",
+            Self::Exterminator => "Exterminator",
+            Self::FirstBlood => "First Blood",
+            Self::Snoop => "Snoop",
+            Self::Sprinter => "Sprinter",
+        })
+    }
+}
+
+/// Counts progress towards each achievement as game events are received.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Achievements {
+    progress: BTreeMap<Achievement, u32>,
+    unlocked: BTreeSet<Achievement>,
+
+    /// Achievements unlocked since [`Self::take_unlocked`] was last called.
+    #[serde(skip)]
+    newly_unlocked: Vec<Achievement>,
+}
+
+impl Achievements {
+    const FILE_NAME: &str = "achievements.bin";
+
+    fn path() -> PathBuf {
+        project_dirs()
+            .map(|dirs| dirs.data_local_dir().to_path_buf())
+            .unwrap_or_default()
+            .join(Self::FILE_NAME)
+    }
+
+    /// Reads the achievements of every earlier session, which are none if there were none.
+    pub fn read() -> anyhow::Result<Self> {
+        let path = Self::path();
+
+        info!("Reading {}", path.display());
+
+        Ok(save_file::read(&path)?.unwrap_or_default())
+    }
+
+    /// Returns the achievements unlocked since this was last called, in the order unlocked.
+    pub fn take_unlocked(&mut self) -> Vec<Achievement> {
+        take(&mut self.newly_unlocked)
+    }
+
+    pub fn write(&self) -> anyhow::Result<()> {
+        save_file::write(&Self::path(), self)
+    }
+}
+
+impl GameEventListener for Achievements {
+    fn on_game_event(&mut self, event: &GameEvent) {
+        for achievement in Achievement::ALL {
+            let progress = achievement.progress(event);
+
+            if progress == 0 || self.unlocked.contains(&achievement) {
+                continue;
+            }
+
+            let total = self.progress.entry(achievement).or_default();
+            *total += progress;
+
+            if *total >= achievement.goal() {
+                self.progress.remove(&achievement);
+                self.unlocked.insert(achievement);
+                self.newly_unlocked.push(achievement);
+            }
+        }
+    }
+}
+
+impl Versioned for Achievements {
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, _: &[u8]) -> anyhow::Result<Self> {
+        bail!("Unknown achievements version {version}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn unlock_achievements() {
+        let mut achievements = Achievements::default();
+        let completed = |secs| GameEvent::LevelCompleted {
+            level: "level_01".to_owned(),
+            secs,
+        };
+
+        achievements.on_game_event(&completed(600.0));
+
+        assert_eq!(achievements.take_unlocked(), [Achievement::Escapist]);
+
+        achievements.on_game_event(&completed(60.0));
+
+        assert_eq!(achievements.take_unlocked(), [Achievement::Sprinter]);
+
+        for _ in 0..9 {
+            achievements.on_game_event(&GameEvent::SecretFound {
+                secret: "vault".to_owned(),
+            });
+        }
+
+        assert!(achievements.take_unlocked().is_empty());
+        assert_eq!(achievements.progress[&Achievement::Snoop], 9);
+
+        // Unlocked achievements stay unlocked when saved
+        let saved = save_file::encode(&achievements).unwrap();
+        let achievements = save_file::decode::<Achievements>(&saved).unwrap();
+
+        assert!(achievements.unlocked.contains(&Achievement::Sprinter));
+        assert!(achievements.newly_unlocked.is_empty());
+    }
+}
@@ -0,0 +1,518 @@
+use {
+    super::{
+        difficulty::Difficulty,
+        inventory::Inventory,
+        objectives::Objectives,
+        save_file::{self, Versioned},
+        world_delta::WorldDeltas,
+    },
+    crate::{fs::project_dirs, level::scene::Scene},
+    anyhow::{bail, Context},
+    glam::Vec3,
+    log::info,
+    serde::{Deserialize, Serialize},
+    std::{fs::read, io::ErrorKind, path::PathBuf, str::from_utf8},
+};
+
+/// A place in a level which saves the game the first time the player reaches it.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    id: String,
+    position: Vec3,
+    radius: f32,
+    reached: bool,
+}
+
+impl Checkpoint {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// The checkpoints of a level.
+#[derive(Debug, Default)]
+pub struct Checkpoints {
+    checkpoints: Vec<Checkpoint>,
+}
+
+impl Checkpoints {
+    /// Distance from the player at which checkpoints are reached, unless given the property
+    /// `radius`.
+    pub const DEFAULT_RADIUS: f32 = 2.0;
+
+    /// Prefix of scene ref names which become checkpoints, such as `Checkpoint_bridge(radius=4)`.
+    pub const PREFIX: &str = "Checkpoint";
+
+    pub fn from_scene(scene: &Scene) -> Self {
+        let mut res = Self::default();
+
+        for (scene_ref, id) in scene.refs_prefixed(Self::PREFIX) {
+            res.insert(
+                id.name,
+                scene_ref.position(),
+                id.property("radius").unwrap_or(Self::DEFAULT_RADIUS),
+            );
+        }
+
+        res
+    }
+
+    pub fn insert(&mut self, id: impl Into<String>, position: Vec3, radius: f32) {
+        self.checkpoints.push(Checkpoint {
+            id: id.into(),
+            position,
+            radius,
+            reached: false,
+        });
+    }
+
+    /// Returns the checkpoint the player has reached since the previous update, if any; each
+    /// checkpoint is only reached once.
+    pub fn update(&mut self, player_position: Vec3) -> Option<&Checkpoint> {
+        self.checkpoints.iter_mut().find_map(|checkpoint| {
+            let reached = !checkpoint.reached
+                && checkpoint.position.distance_squared(player_position)
+                    <= checkpoint.radius * checkpoint.radius;
+
+            if reached {
+                checkpoint.reached = true;
+            }
+
+            reached.then_some(&*checkpoint)
+        })
+    }
+}
+
+/// The state of a game in progress, written each time the player reaches a checkpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SaveGame {
+    /// Id of the checkpoint which was reached.
+    pub checkpoint: String,
+
+    /// The difficulty chosen when the game was started.
+    #[serde(default)]
+    pub difficulty: Difficulty,
+
+    pub inventory: Inventory,
+
+    /// Names of the mods which were active, lowest priority first; the level may differ if the
+    /// save is read with other mods.
+    #[serde(default)]
+    pub mods: Vec<String>,
+
+    /// Progress through the objectives of the level.
+    #[serde(default)]
+    pub objectives: Objectives,
+
+    pub pitch: f32,
+
+    /// Where the player stands on the walkable region of the level.
+    pub position: [f32; 3],
+
+    /// Key of the scene the checkpoint is in; older saves record an empty key, and respawn the
+    /// player at the spawn point of any scene.
+    #[serde(default)]
+    pub scene: String,
+
+    pub weapon: String,
+
+    /// Changes made to each level, which are applied when a level is entered again.
+    #[serde(default)]
+    pub world_deltas: WorldDeltas,
+
+    pub yaw: f32,
+}
+
+/// The layout of [`SaveGame`] at version 1, before it recorded the active mods.
+#[derive(Deserialize)]
+struct SaveGameV1 {
+    checkpoint: String,
+    inventory: Inventory,
+    pitch: f32,
+    position: [f32; 3],
+    weapon: String,
+    yaw: f32,
+}
+
+impl From<SaveGameV1> for SaveGame {
+    fn from(save_game: SaveGameV1) -> Self {
+        Self {
+            checkpoint: save_game.checkpoint,
+            difficulty: Difficulty::default(),
+            inventory: save_game.inventory,
+            mods: vec![],
+            objectives: Objectives::default(),
+            pitch: save_game.pitch,
+            position: save_game.position,
+            scene: String::new(),
+            weapon: save_game.weapon,
+            world_deltas: WorldDeltas::default(),
+            yaw: save_game.yaw,
+        }
+    }
+}
+
+/// The layout of [`SaveGame`] at version 2, before it recorded the difficulty.
+#[derive(Deserialize)]
+struct SaveGameV2 {
+    checkpoint: String,
+    inventory: Inventory,
+    mods: Vec<String>,
+    pitch: f32,
+    position: [f32; 3],
+    weapon: String,
+    yaw: f32,
+}
+
+impl From<SaveGameV2> for SaveGame {
+    fn from(save_game: SaveGameV2) -> Self {
+        Self {
+            checkpoint: save_game.checkpoint,
+            difficulty: Difficulty::default(),
+            inventory: save_game.inventory,
+            mods: save_game.mods,
+            objectives: Objectives::default(),
+            pitch: save_game.pitch,
+            position: save_game.position,
+            scene: String::new(),
+            weapon: save_game.weapon,
+            world_deltas: WorldDeltas::default(),
+            yaw: save_game.yaw,
+        }
+    }
+}
+
+/// The layout of [`SaveGame`] at version 3, before it recorded changes made to levels.
+#[derive(Deserialize)]
+struct SaveGameV3 {
+    checkpoint: String,
+    difficulty: Difficulty,
+    inventory: Inventory,
+    mods: Vec<String>,
+    pitch: f32,
+    position: [f32; 3],
+    weapon: String,
+    yaw: f32,
+}
+
+impl From<SaveGameV3> for SaveGame {
+    fn from(save_game: SaveGameV3) -> Self {
+        Self {
+            checkpoint: save_game.checkpoint,
+            difficulty: save_game.difficulty,
+            inventory: save_game.inventory,
+            mods: save_game.mods,
+            objectives: Objectives::default(),
+            pitch: save_game.pitch,
+            position: save_game.position,
+            scene: String::new(),
+            weapon: save_game.weapon,
+            world_deltas: WorldDeltas::default(),
+            yaw: save_game.yaw,
+        }
+    }
+}
+
+/// The layout of [`SaveGame`] at version 4, before it recorded objectives.
+#[derive(Deserialize)]
+struct SaveGameV4 {
+    checkpoint: String,
+    difficulty: Difficulty,
+    inventory: Inventory,
+    mods: Vec<String>,
+    pitch: f32,
+    position: [f32; 3],
+    weapon: String,
+    world_deltas: WorldDeltas,
+    yaw: f32,
+}
+
+impl From<SaveGameV4> for SaveGame {
+    fn from(save_game: SaveGameV4) -> Self {
+        Self {
+            checkpoint: save_game.checkpoint,
+            difficulty: save_game.difficulty,
+            inventory: save_game.inventory,
+            mods: save_game.mods,
+            objectives: Objectives::default(),
+            pitch: save_game.pitch,
+            position: save_game.position,
+            scene: String::new(),
+            weapon: save_game.weapon,
+            world_deltas: save_game.world_deltas,
+            yaw: save_game.yaw,
+        }
+    }
+}
+
+/// The layout of [`SaveGame`] at version 5, before it recorded the scene.
+#[derive(Deserialize)]
+struct SaveGameV5 {
+    checkpoint: String,
+    difficulty: Difficulty,
+    inventory: Inventory,
+    mods: Vec<String>,
+    objectives: Objectives,
+    pitch: f32,
+    position: [f32; 3],
+    weapon: String,
+    world_deltas: WorldDeltas,
+    yaw: f32,
+}
+
+impl From<SaveGameV5> for SaveGame {
+    fn from(save_game: SaveGameV5) -> Self {
+        Self {
+            checkpoint: save_game.checkpoint,
+            difficulty: save_game.difficulty,
+            inventory: save_game.inventory,
+            mods: save_game.mods,
+            objectives: save_game.objectives,
+            pitch: save_game.pitch,
+            position: save_game.position,
+            scene: String::new(),
+            weapon: save_game.weapon,
+            world_deltas: save_game.world_deltas,
+            yaw: save_game.yaw,
+        }
+    }
+}
+
+impl SaveGame {
+    const AUTOSAVE_FILE_NAME: &str = "autosave.bin";
+
+    /// Autosaves were written as TOML before saves were versioned.
+    const LEGACY_AUTOSAVE_FILE_NAME: &str = "autosave.toml";
+
+    fn autosave_path() -> PathBuf {
+        project_dirs()
+            .map(|dirs| dirs.data_local_dir().to_path_buf())
+            .unwrap_or_default()
+            .join(Self::AUTOSAVE_FILE_NAME)
+    }
+
+    /// Reads the latest autosave, or its backup if it is damaged; returns `None` if the game has
+    /// not been autosaved.
+    pub fn read_autosave() -> anyhow::Result<Option<Self>> {
+        let path = Self::autosave_path();
+
+        info!("Reading {}", path.display());
+
+        if let Some(save_game) = save_file::read(&path)? {
+            return Ok(Some(save_game));
+        }
+
+        let legacy_path = path.with_file_name(Self::LEGACY_AUTOSAVE_FILE_NAME);
+
+        match read(&legacy_path) {
+            Ok(data) => save_file::decode(&data).map(Some),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("Reading {}", legacy_path.display())),
+        }
+    }
+
+    pub fn write_autosave(&self) -> anyhow::Result<()> {
+        let path = Self::autosave_path();
+
+        info!("Writing {}", path.display());
+
+        save_file::write(&path, self)
+    }
+}
+
+impl Versioned for SaveGame {
+    const VERSION: u32 = 6;
+
+    fn migrate(version: u32, data: &[u8]) -> anyhow::Result<Self> {
+        match version {
+            0 => toml::from_str(from_utf8(data)?).context("Parsing TOML autosave"),
+            1 => bincode::deserialize::<SaveGameV1>(data)
+                .map(Into::into)
+                .context("Deserializing autosave"),
+            2 => bincode::deserialize::<SaveGameV2>(data)
+                .map(Into::into)
+                .context("Deserializing autosave"),
+            3 => bincode::deserialize::<SaveGameV3>(data)
+                .map(Into::into)
+                .context("Deserializing autosave"),
+            4 => bincode::deserialize::<SaveGameV4>(data)
+                .map(Into::into)
+                .context("Deserializing autosave"),
+            5 => bincode::deserialize::<SaveGameV5>(data)
+                .map(Into::into)
+                .context("Deserializing autosave"),
+            _ => bail!("Unknown autosave version {version}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::game::world_delta::WorldChange, glam::vec3};
+
+    #[test]
+    pub fn reach_checkpoints() {
+        let mut checkpoints = Checkpoints::default();
+        checkpoints.insert("Checkpoint_a", Vec3::ZERO, 1.0);
+        checkpoints.insert("Checkpoint_b", vec3(10.0, 0.0, 0.0), 2.0);
+
+        assert!(checkpoints.update(vec3(5.0, 0.0, 0.0)).is_none());
+        assert_eq!(
+            checkpoints.update(vec3(0.5, 0.0, 0.0)).map(Checkpoint::id),
+            Some("Checkpoint_a")
+        );
+
+        // Checkpoints are only reached once
+        assert!(checkpoints.update(Vec3::ZERO).is_none());
+        assert_eq!(
+            checkpoints.update(vec3(8.5, 0.0, 0.0)).map(Checkpoint::id),
+            Some("Checkpoint_b")
+        );
+    }
+
+    #[test]
+    pub fn serialize_save_game() {
+        let mut world_deltas = WorldDeltas::default();
+        world_deltas.record("scene/level_01", "pickup_ammo_a", WorldChange::Collected);
+
+        let mut objectives = Objectives::default();
+        objectives.add("find_key");
+
+        let save_game = SaveGame {
+            checkpoint: "Checkpoint_a".to_owned(),
+            difficulty: Difficulty::Hard,
+            inventory: Inventory::default(),
+            mods: vec!["a".to_owned()],
+            objectives,
+            pitch: -10.0,
+            position: [1.0, 2.0, 3.0],
+            scene: "scene/level_01".to_owned(),
+            weapon: "laser".to_owned(),
+            world_deltas,
+            yaw: 45.0,
+        };
+
+        let saved = save_file::encode(&save_game).unwrap();
+
+        assert_eq!(save_file::decode::<SaveGame>(&saved).unwrap(), save_game);
+
+        // Autosaves written before saves were versioned are upgraded
+        let legacy = toml::to_string(&save_game).unwrap();
+
+        assert_eq!(
+            save_file::decode::<SaveGame>(legacy.as_bytes()).unwrap(),
+            save_game
+        );
+
+        // Autosaves written before the scene was recorded respawn at the spawn point
+        let legacy_save_game = SaveGame {
+            scene: String::new(),
+            ..save_game.clone()
+        };
+
+        // Autosaves written before mods were recorded have none
+        let v1 = bincode::serialize(&(
+            &save_game.checkpoint,
+            &save_game.inventory,
+            save_game.pitch,
+            save_game.position,
+            &save_game.weapon,
+            save_game.yaw,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            SaveGame::migrate(1, &v1).unwrap(),
+            SaveGame {
+                difficulty: Difficulty::Normal,
+                mods: vec![],
+                objectives: Objectives::default(),
+                world_deltas: WorldDeltas::default(),
+                ..legacy_save_game.clone()
+            }
+        );
+
+        // Autosaves written before the difficulty was recorded are played at normal difficulty
+        let v2 = bincode::serialize(&(
+            &save_game.checkpoint,
+            &save_game.inventory,
+            &save_game.mods,
+            save_game.pitch,
+            save_game.position,
+            &save_game.weapon,
+            save_game.yaw,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            SaveGame::migrate(2, &v2).unwrap(),
+            SaveGame {
+                difficulty: Difficulty::Normal,
+                objectives: Objectives::default(),
+                world_deltas: WorldDeltas::default(),
+                ..legacy_save_game.clone()
+            }
+        );
+
+        // Autosaves written before changes to levels were recorded respawn everything
+        let v3 = bincode::serialize(&(
+            &save_game.checkpoint,
+            save_game.difficulty,
+            &save_game.inventory,
+            &save_game.mods,
+            save_game.pitch,
+            save_game.position,
+            &save_game.weapon,
+            save_game.yaw,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            SaveGame::migrate(3, &v3).unwrap(),
+            SaveGame {
+                objectives: Objectives::default(),
+                world_deltas: WorldDeltas::default(),
+                ..legacy_save_game.clone()
+            }
+        );
+
+        // Autosaves written before objectives were recorded have none, so play lists those which
+        // the level starts with
+        let v4 = bincode::serialize(&(
+            &save_game.checkpoint,
+            save_game.difficulty,
+            &save_game.inventory,
+            &save_game.mods,
+            save_game.pitch,
+            save_game.position,
+            &save_game.weapon,
+            &save_game.world_deltas,
+            save_game.yaw,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            SaveGame::migrate(4, &v4).unwrap(),
+            SaveGame {
+                objectives: Objectives::default(),
+                ..legacy_save_game.clone()
+            }
+        );
+
+        let v5 = bincode::serialize(&(
+            &save_game.checkpoint,
+            save_game.difficulty,
+            &save_game.inventory,
+            &save_game.mods,
+            &save_game.objectives,
+            save_game.pitch,
+            save_game.position,
+            &save_game.weapon,
+            &save_game.world_deltas,
+            save_game.yaw,
+        ))
+        .unwrap();
+
+        assert_eq!(SaveGame::migrate(5, &v5).unwrap(), legacy_save_game);
+    }
+}
@@ -0,0 +1,165 @@
+//! Multipliers of gameplay parameters for each difficulty, such as the damage enemies deal, which
+//! are read from the TOML of `art/table/difficulty.tbl` in the art pak so that they may be tuned
+//! without changing code.
+//!
+//! This module is also compiled by `build.rs`, which checks the table, and so may only depend on
+//! `anyhow`, `serde` and `toml`.
+
+use {
+    anyhow::{bail, Context},
+    serde::{Deserialize, Serialize},
+    std::collections::HashMap,
+};
+
+/// The difficulty chosen when starting a new game, which is written into save games.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Self; 3] = [Self::Easy, Self::Normal, Self::Hard];
+
+    /// Returns the difficulty with the given [`Self::name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.name() == name)
+    }
+
+    /// Returns the name shown to players, such as on the new game menu.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Easy => "Easy",
+            Self::Normal => "Normal",
+            Self::Hard => "Hard",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Easy => "easy",
+            Self::Normal => "normal",
+            Self::Hard => "hard",
+        }
+    }
+}
+
+/// Multipliers of gameplay parameters at one difficulty; each is one at normal difficulty.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DifficultyRules {
+    /// Multiplier of the rounds given by ammo pickups; less than one makes ammo scarce.
+    pub ammo_pickups: f32,
+
+    /// Multiplier of the damage dealt by enemies.
+    pub enemy_damage: f32,
+
+    /// Multiplier of the health of enemies, which divides the damage they take.
+    pub enemy_health: f32,
+
+    /// Multiplier of all damage taken by the player, including from their own weapons.
+    pub player_damage_taken: f32,
+}
+
+impl Default for DifficultyRules {
+    fn default() -> Self {
+        Self {
+            ammo_pickups: 1.0,
+            enemy_damage: 1.0,
+            enemy_health: 1.0,
+            player_damage_taken: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DifficultyTable {
+    pub difficulties: HashMap<Difficulty, DifficultyRules>,
+}
+
+impl DifficultyTable {
+    /// Parses a table of TOML with a section of multipliers for each difficulty, by name, checking
+    /// that each difficulty is known and that every multiplier is positive.
+    pub fn parse(toml: &str) -> anyhow::Result<Self> {
+        let file: HashMap<String, DifficultyRules> = toml::from_str(toml)?;
+        let mut table = Self::default();
+        for (name, rules) in file {
+            let difficulty = Difficulty::from_name(&name)
+                .with_context(|| format!("Unknown difficulty {name}"))?;
+
+            if [
+                rules.ammo_pickups,
+                rules.enemy_damage,
+                rules.enemy_health,
+                rules.player_damage_taken,
+            ]
+            .into_iter()
+            .any(|multiplier| multiplier <= 0.0 || !multiplier.is_finite())
+            {
+                bail!("Invalid multiplier of difficulty {name}");
+            }
+
+            table.difficulties.insert(difficulty, rules);
+        }
+
+        Ok(table)
+    }
+
+    /// Returns the rules of the given difficulty, or those of normal difficulty if the table does
+    /// not have it.
+    pub fn rules(&self, difficulty: Difficulty) -> DifficultyRules {
+        self.difficulties
+            .get(&difficulty)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn difficulty_or_default_rules() {
+        let hard = DifficultyRules {
+            ammo_pickups: 0.5,
+            enemy_damage: 2.0,
+            enemy_health: 1.5,
+            player_damage_taken: 1.25,
+        };
+        let table = DifficultyTable {
+            difficulties: HashMap::from([(Difficulty::Hard, hard)]),
+        };
+
+        assert_eq!(table.rules(Difficulty::Hard), hard);
+        assert_eq!(table.rules(Difficulty::Easy), DifficultyRules::default());
+
+        for difficulty in Difficulty::ALL {
+            assert_eq!(Difficulty::from_name(difficulty.name()), Some(difficulty));
+        }
+    }
+
+    #[test]
+    pub fn parse_difficulty_table() {
+        let table = |name: &str, ammo_pickups: f32| {
+            DifficultyTable::parse(&format!(
+                "{name} = {{ ammo_pickups = {ammo_pickups:?}, enemy_damage = 2.0, \
+                enemy_health = 1.5, player_damage_taken = 1.25 }}"
+            ))
+        };
+
+        let hard = table("hard", 0.5).unwrap();
+
+        assert_eq!(hard.rules(Difficulty::Hard).ammo_pickups, 0.5);
+        assert_eq!(hard.rules(Difficulty::Hard).enemy_damage, 2.0);
+        assert_eq!(hard.rules(Difficulty::Normal), DifficultyRules::default());
+        assert!(table("nightmare", 0.5).is_err());
+        assert!(table("easy", 0.0).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+//! Gameplay events, such as killing an enemy, which the systems where they happen publish to a bus
+//! and which listeners, such as achievements and HUD toasts, receive without either knowing about
+//! the other.
+//!
+//! Events are queued as they are published and delivered together, once each update, so that a
+//! listener never runs part way through a simulation step.
+
+use super::world::EntityId;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    /// The local player killed another player.
+    EnemyKilled { enemy: EntityId },
+
+    /// The local player reached the exit of a level.
+    LevelCompleted {
+        level: String,
+
+        /// Seconds of play from the start of the level.
+        secs: f32,
+    },
+
+    /// An objective was listed on the HUD.
+    ObjectiveAdded { id: String, text: String },
+
+    /// An objective was completed and removed from the HUD.
+    ObjectiveCompleted { id: String, text: String },
+
+    /// The local player found a secret for the first time in this session.
+    SecretFound { secret: String },
+}
+
+pub trait GameEventListener {
+    fn on_game_event(&mut self, event: &GameEvent);
+}
+
+/// Events published since they were last dispatched.
+#[derive(Debug, Default)]
+pub struct GameEventBus {
+    pending: Vec<GameEvent>,
+}
+
+impl GameEventBus {
+    /// Delivers each pending event, in the order published, to each listener in the order given.
+    ///
+    /// Returns `true` if there were any events.
+    pub fn dispatch(&mut self, listeners: &mut [&mut dyn GameEventListener]) -> bool {
+        if self.pending.is_empty() {
+            return false;
+        }
+
+        for event in self.pending.drain(..) {
+            for listener in listeners.iter_mut() {
+                listener.on_game_event(&event);
+            }
+        }
+
+        true
+    }
+
+    pub fn publish(&mut self, event: GameEvent) {
+        self.pending.push(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Vec<GameEvent>);
+
+    impl GameEventListener for Recorder {
+        fn on_game_event(&mut self, event: &GameEvent) {
+            self.0.push(event.clone());
+        }
+    }
+
+    #[test]
+    pub fn dispatch_to_each_listener() {
+        let mut bus = GameEventBus::default();
+        let mut lhs = Recorder::default();
+        let mut rhs = Recorder::default();
+
+        assert!(!bus.dispatch(&mut [&mut lhs, &mut rhs]));
+
+        let events = [
+            GameEvent::SecretFound {
+                secret: "vault".to_owned(),
+            },
+            GameEvent::LevelCompleted {
+                level: "level_01".to_owned(),
+                secs: 60.0,
+            },
+        ];
+
+        for event in events.iter().cloned() {
+            bus.publish(event);
+        }
+
+        assert!(bus.dispatch(&mut [&mut lhs, &mut rhs]));
+        assert_eq!(lhs.0, events);
+        assert_eq!(rhs.0, events);
+
+        // Events are only delivered once
+        assert!(!bus.dispatch(&mut [&mut lhs]));
+        assert_eq!(lhs.0.len(), events.len());
+    }
+}
@@ -0,0 +1,85 @@
+use super::{
+    difficulty::{Difficulty, DifficultyRules, DifficultyTable},
+    inventory::PickupKind,
+    world::EntityId,
+};
+
+/// The rules of the game in progress, which the simulation queries to scale gameplay parameters
+/// by the difficulty the player chose.
+///
+/// Damage is scaled from the point of view of [`Self::player`]: every other player of the world
+/// is an enemy.
+#[derive(Clone, Debug, Default)]
+pub struct GameRules {
+    difficulty: Difficulty,
+
+    /// The player takes no damage, which is a cheat.
+    god_mode: bool,
+
+    /// The player who chose the difficulty, if any; without one, every player is an enemy.
+    player: Option<EntityId>,
+
+    rules: DifficultyRules,
+    table: DifficultyTable,
+}
+
+impl GameRules {
+    pub fn new(table: DifficultyTable, player: EntityId) -> Self {
+        let mut res = Self {
+            player: Some(player),
+            table,
+            ..Default::default()
+        };
+        res.set_difficulty(Difficulty::default());
+
+        res
+    }
+
+    /// Returns the damage `victim` takes when `attacker` deals the given damage.
+    pub fn damage(&self, damage: f32, attacker: EntityId, victim: EntityId) -> f32 {
+        let is_player = |id| self.player == Some(id);
+        let mut damage = damage;
+
+        if !is_player(attacker) {
+            damage *= self.rules.enemy_damage;
+        }
+
+        if is_player(victim) {
+            if self.god_mode {
+                0.0
+            } else {
+                damage * self.rules.player_damage_taken
+            }
+        } else {
+            damage / self.rules.enemy_health.max(f32::EPSILON)
+        }
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    pub fn god_mode(&self) -> bool {
+        self.god_mode
+    }
+
+    /// Returns what collecting a pickup gives, which for ammo depends on the difficulty; at least
+    /// one round is always given.
+    pub fn pickup(&self, kind: &PickupKind) -> PickupKind {
+        match kind {
+            &PickupKind::Ammo(count) => {
+                PickupKind::Ammo(((count as f32 * self.rules.ammo_pickups).round() as u32).max(1))
+            }
+            kind => kind.clone(),
+        }
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        self.rules = self.table.rules(difficulty);
+    }
+
+    pub fn set_god_mode(&mut self, god_mode: bool) {
+        self.god_mode = god_mode;
+    }
+}
@@ -0,0 +1,225 @@
+//! Effects of shots and explosions hitting each kind of surface, such as the decal left behind,
+//! the particles thrown off and the sound heard, which are read from the TOML of
+//! `art/table/impact.tbl` in the art pak so that they may be tuned without changing code.
+//!
+//! This module is also compiled by `build.rs`, which checks the table, and so may only depend on
+//! `anyhow`, `serde` and `toml`.
+
+use {
+    anyhow::{bail, Context},
+    serde::Deserialize,
+    std::collections::HashMap,
+};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImpactKind {
+    Bullet,
+    Explosion,
+}
+
+impl ImpactKind {
+    pub const ALL: [Self; 2] = [Self::Bullet, Self::Explosion];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bullet => "bullet",
+            Self::Explosion => "explosion",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct ImpactEffect {
+    /// Key of the material of the decal left on the surface.
+    #[serde(default)]
+    pub decal: Option<String>,
+
+    /// Name of the particle effect, listed by the table, thrown off the surface.
+    #[serde(default)]
+    pub particles: Option<String>,
+
+    /// Keys of the sounds, one of which plays for each impact.
+    #[serde(default)]
+    pub sounds: Vec<String>,
+}
+
+/// Sprites thrown off a surface, away from where it was hit.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct ParticleEffect {
+    pub color: [u8; 3],
+
+    /// Number of sprites thrown off by each impact.
+    pub count: u32,
+
+    /// Seconds each sprite lasts, fading out.
+    pub lifetime: f32,
+
+    /// Radius, in meters, of each sprite once fully grown.
+    pub radius: f32,
+
+    /// Meters per second each sprite is thrown at.
+    pub speed: f32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImpactTable {
+    /// Effect of each kind of impact on surfaces which do not have one of their own.
+    pub defaults: HashMap<ImpactKind, ImpactEffect>,
+
+    /// Particle effects keyed by the name which impact effects refer to them by, such as `sparks`.
+    pub particles: HashMap<String, ParticleEffect>,
+
+    /// Effects keyed by the name of the surface, such as `metal`.
+    pub surfaces: HashMap<String, HashMap<ImpactKind, ImpactEffect>>,
+}
+
+impl ImpactTable {
+    /// Parses a table of TOML with an `[[impact]]` entry for each kind of impact on a surface, or
+    /// on any surface when the entry has none, and a `[particles.<name>]` section for each
+    /// particle effect, checking that every effect refers to a listed particle effect.
+    pub fn parse(toml: &str) -> anyhow::Result<Self> {
+        #[derive(Deserialize)]
+        struct ImpactFile {
+            #[serde(default)]
+            impact: Vec<ImpactEntry>,
+
+            #[serde(default)]
+            particles: HashMap<String, ParticleEffect>,
+        }
+
+        #[derive(Deserialize)]
+        struct ImpactEntry {
+            kind: ImpactKind,
+
+            #[serde(default)]
+            surface: Option<String>,
+
+            #[serde(flatten)]
+            effect: ImpactEffect,
+        }
+
+        let file: ImpactFile = toml::from_str(toml)?;
+        let mut table = Self {
+            particles: file.particles,
+            ..Default::default()
+        };
+
+        for (name, particles) in &table.particles {
+            if particles.lifetime <= 0.0 || !particles.lifetime.is_finite() {
+                bail!("Invalid lifetime of particles {name}");
+            }
+        }
+
+        for entry in file.impact {
+            if let Some(particles) = &entry.effect.particles {
+                table
+                    .particles
+                    .get(particles)
+                    .with_context(|| format!("Unknown particles {particles}"))?;
+            }
+
+            match entry.surface {
+                Some(surface) => table
+                    .surfaces
+                    .entry(surface)
+                    .or_default()
+                    .insert(entry.kind, entry.effect),
+                None => table.defaults.insert(entry.kind, entry.effect),
+            };
+        }
+
+        Ok(table)
+    }
+
+    /// Returns the effect of an impact on the given surface, or the default effect of the kind of
+    /// impact if the surface does not have one.
+    pub fn effect(&self, surface: &str, kind: ImpactKind) -> Option<&ImpactEffect> {
+        self.surfaces
+            .get(surface)
+            .and_then(|effects| effects.get(&kind))
+            .or_else(|| self.defaults.get(&kind))
+    }
+
+    /// Returns every effect of the table, such as to load their sounds.
+    pub fn effects(&self) -> impl Iterator<Item = &ImpactEffect> {
+        self.defaults
+            .values()
+            .chain(self.surfaces.values().flat_map(HashMap::values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn surface_or_default_effect() {
+        let effect = |sound: &str| ImpactEffect {
+            sounds: vec![sound.to_owned()],
+            ..Default::default()
+        };
+        let table = ImpactTable {
+            defaults: HashMap::from([(ImpactKind::Bullet, effect("ricochet"))]),
+            particles: Default::default(),
+            surfaces: HashMap::from([(
+                "metal".to_owned(),
+                HashMap::from([(ImpactKind::Bullet, effect("clang"))]),
+            )]),
+        };
+
+        assert_eq!(
+            table.effect("metal", ImpactKind::Bullet),
+            Some(&effect("clang"))
+        );
+        assert_eq!(
+            table.effect("wood", ImpactKind::Bullet),
+            Some(&effect("ricochet"))
+        );
+        assert_eq!(table.effect("metal", ImpactKind::Explosion), None);
+        assert_eq!(table.effects().count(), 2);
+    }
+
+    #[test]
+    pub fn parse_impact_table() {
+        let table = ImpactTable::parse(
+            r#"
+            [[impact]]
+            kind = "bullet"
+            particles = "dust"
+            sounds = ["sound/impact/concrete_1.ogg"]
+
+            [[impact]]
+            kind = "bullet"
+            surface = "metal"
+            decal = "material/bullet_hole"
+
+            [particles.dust]
+            color = [128, 128, 128]
+            count = 6
+            lifetime = 0.5
+            radius = 0.05
+            speed = 2.0
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            table.effect("wood", ImpactKind::Bullet).unwrap().particles,
+            Some("dust".to_owned())
+        );
+        assert_eq!(
+            table.effect("metal", ImpactKind::Bullet).unwrap().decal,
+            Some("material/bullet_hole".to_owned())
+        );
+        assert_eq!(table.particles["dust"].count, 6);
+        assert!(ImpactTable::parse(
+            r#"
+            [[impact]]
+            kind = "explosion"
+            particles = "smoke"
+            "#
+        )
+        .is_err());
+    }
+}
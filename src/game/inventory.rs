@@ -0,0 +1,153 @@
+use {
+    crate::level::scene::RefId,
+    log::warn,
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeSet, fmt::Display},
+};
+
+/// What the player receives from a pickup.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum PickupKind {
+    Ammo(u32),
+    Health(u32),
+
+    /// A key with the given name, such as `red`, which doors and triggers may check for.
+    Key(String),
+
+    /// A weapon with the given [`WeaponInfo::id`](super::weapons::WeaponInfo::id).
+    Weapon(String),
+}
+
+impl PickupKind {
+    /// Prefix of scene ref names which become pickups.
+    pub const PREFIX: &str = "Pickup.";
+
+    /// Parses a scene ref id into a pickup kind using the level naming conventions:
+    ///
+    /// - `Pickup.Ammo.<count>`
+    /// - `Pickup.Health.<amount>`
+    /// - `Pickup.Key.<name>`
+    /// - `Pickup.Weapon.<id>`
+    pub fn from_id(id: &RefId) -> Option<Self> {
+        let (kind, value) = id.name.strip_prefix(Self::PREFIX)?.split_once('.')?;
+        let parse_count = || {
+            value
+                .parse()
+                .map_err(|err| warn!("Invalid pickup {}: {err}", id.name))
+                .ok()
+        };
+
+        match kind {
+            "Ammo" => parse_count().map(Self::Ammo),
+            "Health" => parse_count().map(Self::Health),
+            "Key" if !value.is_empty() => Some(Self::Key(value.to_owned())),
+            "Weapon" if !value.is_empty() => Some(Self::Weapon(value.to_owned())),
+            _ => {
+                warn!("Unknown pickup {}", id.name);
+
+                None
+            }
+        }
+    }
+}
+
+impl Display for PickupKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ammo(count) => write!(f, "{count} ammo"),
+            Self::Health(amount) => write!(f, "{amount} health"),
+            Self::Key(name) => write!(f, "{name} key"),
+            Self::Weapon(id) => write!(f, "{id}"),
+        }
+    }
+}
+
+/// The items carried by the player, which are written into save games.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Inventory {
+    pub ammo: u32,
+    pub health: u32,
+    pub keys: BTreeSet<String>,
+
+    /// Ids of the weapons the player has picked up.
+    pub weapons: BTreeSet<String>,
+}
+
+impl Inventory {
+    pub const MAX_AMMO: u32 = 200;
+    pub const MAX_HEALTH: u32 = 100;
+
+    /// Adds the contents of a pickup, returning `false` if the player has no use for it, such as
+    /// health when already at full health.
+    pub fn add(&mut self, kind: &PickupKind) -> bool {
+        match kind {
+            PickupKind::Ammo(count) => {
+                if self.ammo >= Self::MAX_AMMO {
+                    return false;
+                }
+
+                self.ammo = self.ammo.saturating_add(*count).min(Self::MAX_AMMO);
+
+                true
+            }
+            PickupKind::Health(amount) => {
+                if self.health >= Self::MAX_HEALTH {
+                    return false;
+                }
+
+                self.health = self.health.saturating_add(*amount).min(Self::MAX_HEALTH);
+
+                true
+            }
+            PickupKind::Key(name) => self.keys.insert(name.clone()),
+            PickupKind::Weapon(id) => self.weapons.insert(id.clone()),
+        }
+    }
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            ammo: 0,
+            health: Self::MAX_HEALTH,
+            keys: Default::default(),
+            weapons: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn parse_pickups() {
+        let parse = |id| PickupKind::from_id(&RefId::parse(id));
+
+        assert_eq!(parse("Pickup.Health.25"), Some(PickupKind::Health(25)));
+        assert_eq!(parse("Pickup.Ammo.20"), Some(PickupKind::Ammo(20)));
+        assert_eq!(
+            parse("Pickup.Key.red"),
+            Some(PickupKind::Key("red".to_owned()))
+        );
+        assert_eq!(
+            parse("Pickup.Weapon.scatter_laser"),
+            Some(PickupKind::Weapon("scatter_laser".to_owned()))
+        );
+        assert_eq!(parse("Pickup.Health.lots"), None);
+        assert_eq!(parse("Pickup.Armor.50"), None);
+        assert_eq!(parse("Probe_hall"), None);
+    }
+
+    #[test]
+    pub fn serialize_inventory() {
+        let mut inventory = Inventory::default();
+        inventory.add(&PickupKind::Ammo(20));
+        inventory.add(&PickupKind::Key("red".to_owned()));
+        inventory.add(&PickupKind::Weapon("laser".to_owned()));
+
+        let saved = toml::to_string(&inventory).unwrap();
+
+        assert_eq!(toml::from_str::<Inventory>(&saved).unwrap(), inventory);
+    }
+}
@@ -0,0 +1,16 @@
+pub mod achievements;
+pub mod checkpoint;
+pub mod difficulty;
+pub mod events;
+pub mod game_rules;
+pub mod impact_table;
+pub mod inventory;
+pub mod objectives;
+pub mod physics;
+pub mod profile;
+pub mod projectile;
+pub mod quick_save;
+pub mod save_file;
+pub mod weapons;
+pub mod world;
+pub mod world_delta;
@@ -0,0 +1,154 @@
+//! Goals of each level, such as finding a key, which the HUD lists until they are completed.
+//! `build.rs` reads the objectives of every level from `art/objectives.toml` and writes them into
+//! a file next to the pak so that they may be written without changing code.
+//!
+//! Levels add and complete objectives with triggers, such as `Trigger_objective(add=find_key)`
+//! and `Trigger_objective(complete=find_key)`, and an objective with the id `exit` is completed
+//! when the player reaches the exit. Saves record objectives by id, and the text shown for each
+//! is read from the table, so that the text may change between versions.
+//!
+//! This module is also compiled by `build.rs` and so may only depend on `serde`.
+
+use {
+    serde::{Deserialize, Serialize},
+    std::collections::{BTreeSet, HashMap},
+};
+
+/// Name of the file, next to `art.pak`, which holds the objective table.
+pub const FILE_NAME: &str = "art_objectives.bin";
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ObjectiveInfo {
+    /// Name of the objective, unique within its level, which triggers refer to.
+    pub id: String,
+
+    /// Listed from the start of the level, instead of once a trigger adds it.
+    #[serde(default)]
+    pub start: bool,
+
+    /// What the player is asked to do, shown on the HUD.
+    pub text: String,
+}
+
+impl ObjectiveInfo {
+    /// Returns the text of the objective with the given id, or the id if there is no such
+    /// objective.
+    pub fn text<'a>(infos: &'a [Self], id: &'a str) -> &'a str {
+        infos
+            .iter()
+            .find(|info| info.id == id)
+            .map_or(id, |info| info.text.as_str())
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ObjectiveTable {
+    /// The objectives of each level, by scene key, in the order they are listed.
+    pub levels: HashMap<String, Vec<ObjectiveInfo>>,
+}
+
+impl ObjectiveTable {
+    /// Returns the objectives of the given scene, which has none if the table does not have it.
+    pub fn level(&self, scene: &str) -> &[ObjectiveInfo] {
+        self.levels
+            .get(scene)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+/// The progress of the player through the objectives of the current level, which is written into
+/// save games.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Objectives {
+    /// Ids of the objectives which are listed, in the order they were added.
+    active: Vec<String>,
+
+    completed: BTreeSet<String>,
+}
+
+impl Objectives {
+    /// Id of the objective which is completed when the player reaches the exit of the level.
+    pub const EXIT: &str = "exit";
+
+    /// Returns the progress at the start of a level with the given objectives.
+    pub fn new(infos: &[ObjectiveInfo]) -> Self {
+        Self {
+            active: infos
+                .iter()
+                .filter(|info| info.start)
+                .map(|info| info.id.clone())
+                .collect(),
+            completed: Default::default(),
+        }
+    }
+
+    /// Ids of the objectives which are listed, in the order they were added.
+    pub fn active(&self) -> impl Iterator<Item = &str> {
+        self.active.iter().map(String::as_str)
+    }
+
+    /// Lists an objective, returning `false` if it is already listed or has been completed.
+    pub fn add(&mut self, id: &str) -> bool {
+        if self.completed.contains(id) || self.active.iter().any(|active| active == id) {
+            return false;
+        }
+
+        self.active.push(id.to_owned());
+
+        true
+    }
+
+    /// Completes an objective, whether or not it was listed, returning `false` if it had already
+    /// been completed.
+    pub fn complete(&mut self, id: &str) -> bool {
+        if !self.completed.insert(id.to_owned()) {
+            return false;
+        }
+
+        self.active.retain(|active| active != id);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn add_and_complete_objectives() {
+        let infos = [
+            ObjectiveInfo {
+                id: "find_key".to_owned(),
+                start: true,
+                text: "Find the red key".to_owned(),
+            },
+            ObjectiveInfo {
+                id: "open_vault".to_owned(),
+                start: false,
+                text: "Open the vault".to_owned(),
+            },
+        ];
+        let mut objectives = Objectives::new(&infos);
+
+        assert!(objectives.active().eq(["find_key"]));
+        assert!(!objectives.add("find_key"));
+        assert!(objectives.add("open_vault"));
+        assert!(objectives.active().eq(["find_key", "open_vault"]));
+        assert!(objectives.complete("find_key"));
+        assert!(!objectives.complete("find_key"));
+        assert!(!objectives.add("find_key"));
+        assert!(objectives.active().eq(["open_vault"]));
+
+        assert_eq!(ObjectiveInfo::text(&infos, "open_vault"), "Open the vault");
+        assert_eq!(ObjectiveInfo::text(&infos, "unknown"), "unknown");
+
+        let saved = bincode::serialize(&objectives).unwrap();
+
+        assert_eq!(
+            bincode::deserialize::<Objectives>(&saved).unwrap(),
+            objectives
+        );
+    }
+}
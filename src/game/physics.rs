@@ -0,0 +1,434 @@
+//! Rigid bodies for small props, such as barrels and debris, which fall, tumble and bounce off the
+//! level collision mesh and are pushed around by players.
+//!
+//! Bodies are spheres so that contacts with the collision mesh use the same sphere sweep as
+//! players do; props which are not round still tumble, they just roll a little too well.
+//!
+//! Ragdolls are chains of such spheres, one at each joint, held together by bones of fixed length
+//! which are solved after the joints move.
+
+use {
+    super::world::{EntityId, Player, Transform},
+    crate::level::{collision::CollisionMesh, scene::RefId},
+    glam::{vec3, Mat4, Quat, Vec3},
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+};
+
+/// A dynamic sphere, which moves the transform of its entity each step.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RigidBody {
+    /// Radians per second around each axis.
+    pub angular_velocity: Vec3,
+
+    /// Kilograms, which decides how far bodies push each other.
+    pub mass: f32,
+
+    pub radius: f32,
+
+    /// Meters per second.
+    pub velocity: Vec3,
+}
+
+impl RigidBody {
+    /// Fraction of speed along a surface which is lost each second of contact.
+    const FRICTION: f32 = 2.0;
+
+    /// Acceleration of every body, in meters per second squared.
+    pub const GRAVITY: Vec3 = vec3(0.0, -9.81, 0.0);
+
+    /// Bodies move at most this fraction of their radius each substep, because the collision
+    /// mesh may only push a sphere out of walls which it does not pass through.
+    const MAX_STEP_FRACTION: f32 = 0.5;
+
+    const MAX_SUBSTEPS: usize = 8;
+
+    /// Fraction of the speed into a surface, or another body, which is kept after bouncing.
+    const RESTITUTION: f32 = 0.3;
+
+    /// Slower impacts do not bounce, so that resting bodies do not jitter.
+    const RESTITUTION_MIN_SPEED: f32 = 1.0;
+
+    /// Speed below which a body lying on the ground comes to rest.
+    const REST_SPEED: f32 = 0.05;
+
+    pub fn new(radius: f32, mass: f32) -> Self {
+        Self {
+            angular_velocity: Vec3::ZERO,
+            mass: mass.max(0.1),
+            radius: radius.max(0.01),
+            velocity: Vec3::ZERO,
+        }
+    }
+
+    /// Parses a scene ref id, such as `prop_barrel(radius=0.4, mass=20)`, into a body; refs named
+    /// `prop_*` are physics props.
+    ///
+    /// Props with a `health` property, such as `prop_barrel(health=50)`, are also destructible, see
+    /// [`World::set_prop_health`](super::world::World::set_prop_health).
+    pub fn from_id(id: &RefId) -> Option<Self> {
+        id.name.starts_with("prop_").then(|| {
+            Self::new(
+                id.property("radius").unwrap_or(0.25),
+                id.property("mass").unwrap_or(5.0),
+            )
+        })
+    }
+
+    /// Gives the body at least the speed which moves it out of any players it overlaps by the end
+    /// of the step.
+    fn push_by_players(&mut self, position: Vec3, players: &[Vec3], dt: f32) {
+        for &player in players {
+            // Players are upright cylinders from their feet to their eyes
+            let offset = position - player;
+
+            if offset.y < -self.radius || offset.y > Player::EYE_OFFSET.y + self.radius {
+                continue;
+            }
+
+            let horizontal = vec3(offset.x, 0.0, offset.z);
+            let distance = horizontal.length();
+            let min_distance = Player::RADIUS + self.radius;
+
+            if distance >= min_distance {
+                continue;
+            }
+
+            let direction = if distance > f32::EPSILON {
+                horizontal / distance
+            } else {
+                Vec3::X
+            };
+            let push_speed = (min_distance - distance) / dt;
+            let speed = self.velocity.dot(direction);
+
+            if speed < push_speed {
+                self.velocity += direction * (push_speed - speed);
+            }
+        }
+    }
+
+    /// Bounces and slows the body after it touched a surface with the given normal.
+    fn respond_to_contact(&mut self, normal: Vec3, dt: f32) {
+        let normal_speed = self.velocity.dot(normal);
+        let tangent =
+            (self.velocity - normal * normal_speed) * (1.0 - Self::FRICTION * dt).max(0.0);
+        let normal_speed = if normal_speed >= 0.0 {
+            normal_speed
+        } else if -normal_speed > Self::RESTITUTION_MIN_SPEED {
+            -normal_speed * Self::RESTITUTION
+        } else {
+            0.0
+        };
+
+        self.velocity = tangent + normal * normal_speed;
+
+        // Rolling without slipping: the contact point does not move against the surface
+        self.angular_velocity = normal.cross(tangent) / self.radius;
+
+        if normal.y > 0.7 && self.velocity.length() < Self::REST_SPEED {
+            self.angular_velocity = Vec3::ZERO;
+            self.velocity = Vec3::ZERO;
+        }
+    }
+}
+
+/// Keeps two joints of a ragdoll at the distance they had in the pose it started from.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+struct Bone {
+    /// Direction from the parent joint to the child joint in the bind pose.
+    bind_direction: Vec3,
+
+    /// Position of the parent joint in the bind pose.
+    bind_position: Vec3,
+
+    child: usize,
+    length: f32,
+    parent: usize,
+}
+
+/// A joint of the pose a ragdoll starts from.
+#[derive(Clone, Copy, Debug)]
+pub struct RagdollJoint {
+    /// Where the joint is in the pose the skinned mesh, or each segment mesh, was modeled in.
+    pub bind_position: Vec3,
+
+    /// Index of the joint this one hangs from, or `None` for the root, such as the pelvis.
+    pub parent: Option<usize>,
+
+    pub position: Vec3,
+}
+
+/// A body which goes limp, such as an actor which died: spheres at each joint fall and slide along
+/// the level while bones keep them together.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Ragdoll {
+    bones: Vec<Bone>,
+    positions: Vec<Vec3>,
+    radius: f32,
+
+    /// Every joint has come to rest, so steps do nothing.
+    resting: bool,
+
+    /// Meters per second of each joint.
+    velocities: Vec<Vec3>,
+}
+
+impl Ragdoll {
+    /// Times the bones are solved each step; more is stiffer.
+    const BONE_ITERATIONS: usize = 8;
+
+    /// Fraction of speed which joints in contact with the level lose each second.
+    const FRICTION: f32 = 8.0;
+
+    /// Speed below which every joint of a ragdoll lying still comes to rest.
+    const REST_SPEED: f32 = 0.05;
+
+    /// Starts a ragdoll from the last pose of an actor, with every joint moving at the given
+    /// velocity; parents must come before their children.
+    pub fn new(joints: &[RagdollJoint], radius: f32, velocity: Vec3) -> Self {
+        let bones = joints
+            .iter()
+            .enumerate()
+            .filter_map(|(child, joint)| {
+                let parent = joint.parent?;
+
+                debug_assert!(parent < child);
+
+                let bind_offset = joint.bind_position - joints[parent].bind_position;
+
+                Some(Bone {
+                    bind_direction: bind_offset.try_normalize().unwrap_or(Vec3::Y),
+                    bind_position: joints[parent].bind_position,
+                    child,
+                    length: joint.position.distance(joints[parent].position),
+                    parent,
+                })
+            })
+            .collect();
+
+        Self {
+            bones,
+            positions: joints.iter().map(|joint| joint.position).collect(),
+            radius: radius.max(0.01),
+            resting: false,
+            velocities: vec![velocity; joints.len()],
+        }
+    }
+
+    /// Returns a matrix for each bone, in the order of the joints they end at, which moves the
+    /// bind pose to the current pose; these are the skin matrices of a skinned mesh, or the
+    /// transforms of separate meshes modeled in the bind pose for each segment.
+    ///
+    /// Bones only know their direction, so a segment keeps the twist of the bind pose.
+    pub fn bone_matrices(&self) -> impl Iterator<Item = Mat4> + '_ {
+        self.bones.iter().map(|bone| {
+            let parent = self.positions[bone.parent];
+            let direction = (self.positions[bone.child] - parent)
+                .try_normalize()
+                .unwrap_or(bone.bind_direction);
+
+            Mat4::from_translation(parent)
+                * Mat4::from_quat(Quat::from_rotation_arc(bone.bind_direction, direction))
+                * Mat4::from_translation(-bone.bind_position)
+        })
+    }
+
+    /// Returns the position of each joint.
+    pub fn joints(&self) -> &[Vec3] {
+        &self.positions
+    }
+
+    /// Moves each bone back towards its length, sharing the correction between both joints.
+    fn solve_bones(&mut self) {
+        for bone in &self.bones {
+            let offset = self.positions[bone.child] - self.positions[bone.parent];
+            let distance = offset.length();
+
+            if distance <= f32::EPSILON {
+                continue;
+            }
+
+            let correction = offset * (0.5 * (distance - bone.length) / distance);
+            self.positions[bone.parent] += correction;
+            self.positions[bone.child] -= correction;
+        }
+    }
+
+    /// Advances the ragdoll by `dt` seconds.
+    pub fn step(&mut self, collision: &CollisionMesh, dt: f32) {
+        if self.resting {
+            return;
+        }
+
+        let start = self.positions.clone();
+
+        // Joints move freely first, then bones pull them back together and the level pushes them
+        // back out of any walls the bones pulled them into
+        for (position, velocity) in self.positions.iter_mut().zip(&mut self.velocities) {
+            *velocity += RigidBody::GRAVITY * dt;
+            *position = slide(collision, *position, *velocity * dt, self.radius);
+        }
+
+        for _ in 0..Self::BONE_ITERATIONS {
+            let solved = self.positions.clone();
+
+            self.solve_bones();
+
+            for (position, solved) in self.positions.iter_mut().zip(solved) {
+                *position = collision.slide(solved, *position - solved, self.radius);
+            }
+        }
+
+        let mut any_contact = false;
+
+        for ((position, velocity), start) in
+            self.positions.iter().zip(&mut self.velocities).zip(start)
+        {
+            // Anything other than the unobstructed move is the level pushing back
+            let contact = position.distance_squared(start + *velocity * dt) > 1e-8;
+            *velocity = (*position - start) / dt;
+
+            if contact {
+                *velocity *= (1.0 - Self::FRICTION * dt).max(0.0);
+                any_contact = true;
+            }
+        }
+
+        self.resting = any_contact
+            && self
+                .velocities
+                .iter()
+                .all(|velocity| velocity.length() < Self::REST_SPEED);
+
+        if self.resting {
+            self.velocities.fill(Vec3::ZERO);
+        }
+    }
+
+    /// Returns the transform of the whole ragdoll, at its root joint and facing along its first
+    /// bone.
+    pub fn transform(&self) -> Transform {
+        let rotation = self
+            .bones
+            .first()
+            .and_then(|bone| {
+                let direction =
+                    (self.positions[bone.child] - self.positions[bone.parent]).try_normalize()?;
+
+                Some(Quat::from_rotation_arc(bone.bind_direction, direction))
+            })
+            .unwrap_or(Quat::IDENTITY);
+
+        Transform {
+            position: self.positions.first().copied().unwrap_or_default(),
+            rotation,
+        }
+    }
+}
+
+/// Moves a sphere through the level, in substeps short enough that it cannot pass through walls.
+fn slide(collision: &CollisionMesh, mut position: Vec3, motion: Vec3, radius: f32) -> Vec3 {
+    let substeps = ((motion.length() / (radius * RigidBody::MAX_STEP_FRACTION)).ceil() as usize)
+        .clamp(1, RigidBody::MAX_SUBSTEPS);
+    let substep_motion = motion / substeps as f32;
+
+    for _ in 0..substeps {
+        position = collision.slide(position, substep_motion, radius);
+    }
+
+    position
+}
+
+/// Pushes overlapping bodies apart, in proportion to their masses, and bounces them off each
+/// other.
+fn separate_bodies(
+    bodies: &mut BTreeMap<EntityId, RigidBody>,
+    transforms: &mut BTreeMap<EntityId, Transform>,
+) {
+    let ids = bodies.keys().copied().collect::<Box<_>>();
+
+    for (idx, &a) in ids.iter().enumerate() {
+        for &b in &ids[idx + 1..] {
+            let (Some(a_position), Some(b_position)) = (
+                transforms.get(&a).map(|transform| transform.position),
+                transforms.get(&b).map(|transform| transform.position),
+            ) else {
+                continue;
+            };
+
+            let mut a_body = bodies[&a];
+            let mut b_body = bodies[&b];
+            let offset = b_position - a_position;
+            let distance = offset.length();
+            let min_distance = a_body.radius + b_body.radius;
+
+            if distance >= min_distance || distance <= f32::EPSILON {
+                continue;
+            }
+
+            let normal = offset / distance;
+            let a_weight = a_body.mass.recip();
+            let b_weight = b_body.mass.recip();
+            let total_weight = a_weight + b_weight;
+            let overlap = min_distance - distance;
+
+            transforms.get_mut(&a).unwrap().position -= normal * overlap * a_weight / total_weight;
+            transforms.get_mut(&b).unwrap().position += normal * overlap * b_weight / total_weight;
+
+            let approach_speed = (b_body.velocity - a_body.velocity).dot(normal);
+
+            if approach_speed < 0.0 {
+                let impulse = -(1.0 + RigidBody::RESTITUTION) * approach_speed / total_weight;
+                a_body.velocity -= normal * impulse * a_weight;
+                b_body.velocity += normal * impulse * b_weight;
+                bodies.insert(a, a_body);
+                bodies.insert(b, b_body);
+            }
+        }
+    }
+}
+
+/// Advances each body by `dt` seconds, moving the transform of its entity; `players` are the
+/// positions where players stand.
+pub fn step(
+    bodies: &mut BTreeMap<EntityId, RigidBody>,
+    transforms: &mut BTreeMap<EntityId, Transform>,
+    collision: &CollisionMesh,
+    players: &[Vec3],
+    dt: f32,
+) {
+    for (id, body) in bodies.iter_mut() {
+        let Some(transform) = transforms.get_mut(id) else {
+            continue;
+        };
+
+        body.velocity += RigidBody::GRAVITY * dt;
+
+        body.push_by_players(transform.position, players, dt);
+
+        let motion = body.velocity * dt;
+        let substeps = ((motion.length() / (body.radius * RigidBody::MAX_STEP_FRACTION)).ceil()
+            as usize)
+            .clamp(1, RigidBody::MAX_SUBSTEPS);
+        let substep_motion = motion / substeps as f32;
+        let mut contact_normal = Vec3::ZERO;
+
+        for _ in 0..substeps {
+            let position = collision.slide(transform.position, substep_motion, body.radius);
+
+            // Any difference from the unobstructed move is the collision mesh pushing back
+            contact_normal += position - (transform.position + substep_motion);
+            transform.position = position;
+        }
+
+        if let Some(normal) = contact_normal.try_normalize() {
+            body.respond_to_contact(normal, dt);
+        }
+
+        transform.rotation =
+            (Quat::from_scaled_axis(body.angular_velocity * dt) * transform.rotation).normalize();
+    }
+
+    separate_bodies(bodies, transforms);
+}
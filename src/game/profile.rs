@@ -0,0 +1,91 @@
+//! Totals of what the player has done over every session, kept in the local data directory.
+
+use {
+    super::{
+        events::{GameEvent, GameEventListener},
+        save_file::{self, Versioned},
+    },
+    crate::fs::project_dirs,
+    anyhow::bail,
+    log::info,
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, path::PathBuf},
+};
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct StatsProfile {
+    /// Fewest seconds taken to complete each level.
+    pub best_times: BTreeMap<String, f32>,
+
+    pub enemies_killed: u32,
+    pub levels_completed: u32,
+    pub secrets_found: u32,
+}
+
+impl StatsProfile {
+    const FILE_NAME: &str = "profile.bin";
+
+    fn path() -> PathBuf {
+        project_dirs()
+            .map(|dirs| dirs.data_local_dir().to_path_buf())
+            .unwrap_or_default()
+            .join(Self::FILE_NAME)
+    }
+
+    /// Reads the profile, which is empty if the game has not been played before.
+    pub fn read() -> anyhow::Result<Self> {
+        let path = Self::path();
+
+        info!("Reading {}", path.display());
+
+        Ok(save_file::read(&path)?.unwrap_or_default())
+    }
+
+    pub fn write(&self) -> anyhow::Result<()> {
+        save_file::write(&Self::path(), self)
+    }
+}
+
+impl GameEventListener for StatsProfile {
+    fn on_game_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::EnemyKilled { .. } => self.enemies_killed += 1,
+            GameEvent::LevelCompleted { level, secs } => {
+                self.levels_completed += 1;
+
+                let best_time = self.best_times.entry(level.clone()).or_insert(*secs);
+                *best_time = best_time.min(*secs);
+            }
+            GameEvent::ObjectiveAdded { .. } | GameEvent::ObjectiveCompleted { .. } => (),
+            GameEvent::SecretFound { .. } => self.secrets_found += 1,
+        }
+    }
+}
+
+impl Versioned for StatsProfile {
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, _: &[u8]) -> anyhow::Result<Self> {
+        bail!("Unknown profile version {version}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn keep_best_times() {
+        let mut profile = StatsProfile::default();
+
+        for secs in [90.0, 60.0, 120.0] {
+            profile.on_game_event(&GameEvent::LevelCompleted {
+                level: "level_01".to_owned(),
+                secs,
+            });
+        }
+
+        assert_eq!(profile.levels_completed, 3);
+        assert_eq!(profile.best_times["level_01"], 60.0);
+    }
+}
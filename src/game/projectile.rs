@@ -0,0 +1,242 @@
+//! Projectiles fired by weapons which do not hit-scan, such as rockets and grenades.
+//!
+//! Each step a projectile falls under gravity and sweeps the segment it travels against the level
+//! and the players it may hit. Rockets detonate on contact with anything, while grenades bounce off
+//! the level until their fuse runs out or they come near a player. Detonations deal splash damage
+//! which falls off with distance from the blast.
+
+use {
+    super::{physics::RigidBody, world::EntityId},
+    crate::{
+        art,
+        asset_key::{MaterialKey, ModelKey},
+        level::collision::CollisionMesh,
+        math::Ray,
+    },
+    glam::Vec3,
+    serde::{Deserialize, Serialize},
+};
+
+/// Static definition of a kind of projectile.
+#[derive(Clone, Copy, Debug)]
+pub struct ProjectileInfo {
+    /// Fraction of speed kept when bouncing off the level; projectiles which do not bounce
+    /// detonate on contact.
+    pub bounce: Option<f32>,
+
+    /// Color of the trail and of the flash when detonating.
+    pub color: [u8; 3],
+
+    /// Seconds after firing at which the projectile detonates on its own.
+    pub fuse: f32,
+
+    /// Multiplies gravity; rockets fly straight.
+    pub gravity_scale: f32,
+
+    pub material: MaterialKey,
+    pub model: ModelKey,
+
+    /// Distance from a player at which the projectile detonates.
+    pub proximity_radius: f32,
+
+    pub radius: f32,
+
+    /// Meters per second when fired.
+    pub speed: f32,
+
+    /// Damage dealt at the center of a detonation, falling off to none at `splash_radius`.
+    pub splash_damage: f32,
+
+    pub splash_radius: f32,
+}
+
+impl ProjectileInfo {
+    pub const GRENADE: Self = Self {
+        bounce: Some(0.45),
+        color: [0xff, 0xc0, 0x40],
+        fuse: 2.5,
+        gravity_scale: 1.0,
+        material: art::MATERIAL_DARK_GREY,
+        model: art::MODEL_PROP_CAPSULE,
+        proximity_radius: 0.5,
+        radius: 0.1,
+        speed: 15.0,
+        splash_damage: 80.0,
+        splash_radius: 5.0,
+    };
+
+    pub const ROCKET: Self = Self {
+        bounce: None,
+        color: [0xff, 0x80, 0x20],
+        fuse: 5.0,
+        gravity_scale: 0.0,
+        material: art::MATERIAL_ACCENT,
+        model: art::MODEL_PROP_CAPSULE,
+        proximity_radius: 0.5,
+        radius: 0.1,
+        speed: 25.0,
+        splash_damage: 100.0,
+        splash_radius: 4.0,
+    };
+
+    /// Returns the damage dealt at the given distance from a detonation.
+    pub fn splash_damage(&self, distance: f32) -> f32 {
+        self.splash_damage * (1.0 - distance / self.splash_radius).max(0.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum ProjectileKind {
+    Grenade,
+    Rocket,
+}
+
+impl ProjectileKind {
+    pub const ALL: [Self; 2] = [Self::Grenade, Self::Rocket];
+
+    pub fn info(self) -> &'static ProjectileInfo {
+        match self {
+            Self::Grenade => &ProjectileInfo::GRENADE,
+            Self::Rocket => &ProjectileInfo::ROCKET,
+        }
+    }
+}
+
+/// A projectile in flight, which moves the transform of its entity each step.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Projectile {
+    /// Seconds since firing.
+    pub age: f32,
+
+    pub kind: ProjectileKind,
+
+    /// The player who fired the projectile, which it never detonates near.
+    pub owner: EntityId,
+
+    /// Meters per second.
+    pub velocity: Vec3,
+}
+
+impl Projectile {
+    /// Bounces slower than this come to rest, so that grenades do not jitter on the ground.
+    const REST_SPEED: f32 = 0.5;
+
+    /// Starts a projectile flying along the given ray.
+    pub fn new(kind: ProjectileKind, owner: EntityId, ray: Ray) -> Self {
+        Self {
+            age: 0.0,
+            kind,
+            owner,
+            velocity: ray.normal() * kind.info().speed,
+        }
+    }
+
+    /// Advances the projectile by `dt` seconds from `position`, which is moved to where it ends up;
+    /// `actors` are the centers of the players it may hit, each of radius `actor_radius`.
+    ///
+    /// Returns `true` if the projectile detonated, in which case `position` is the blast center.
+    pub fn step(
+        &mut self,
+        position: &mut Vec3,
+        collision: &CollisionMesh,
+        actors: &[Vec3],
+        actor_radius: f32,
+        dt: f32,
+    ) -> bool {
+        let info = self.kind.info();
+
+        self.age += dt;
+        self.velocity += RigidBody::GRAVITY * info.gravity_scale * dt;
+
+        let start = *position;
+        let motion = self.velocity * dt;
+        let distance = motion.length();
+        let mut end = start + motion;
+        let mut detonated = false;
+
+        if let Some(direction) = motion.try_normalize() {
+            if let Some(hit) = collision.raycast(Ray::new(start, direction), distance + info.radius)
+            {
+                end = hit.position + hit.normal * info.radius;
+
+                match info.bounce {
+                    Some(bounce) => {
+                        let speed_into = self.velocity.dot(hit.normal);
+                        self.velocity = (self.velocity - 2.0 * speed_into * hit.normal) * bounce;
+
+                        if self.velocity.length() < Self::REST_SPEED {
+                            self.velocity = Vec3::ZERO;
+                        }
+                    }
+                    None => detonated = true,
+                }
+            }
+        }
+
+        // Players are hit anywhere along the segment travelled, so fast projectiles do not pass
+        // through them between steps
+        let reach = actor_radius + info.proximity_radius;
+        if let Some(closest) = actors
+            .iter()
+            .map(|&actor| closest_point_on_segment(start, end, actor))
+            .zip(actors)
+            .filter(|(closest, &actor)| closest.distance_squared(actor) <= reach * reach)
+            .map(|(closest, _)| closest)
+            .min_by(|lhs, rhs| {
+                lhs.distance_squared(start)
+                    .total_cmp(&rhs.distance_squared(start))
+            })
+        {
+            end = closest;
+            detonated = true;
+        }
+
+        *position = end;
+
+        detonated || self.age >= info.fuse
+    }
+}
+
+fn closest_point_on_segment(start: Vec3, end: Vec3, point: Vec3) -> Vec3 {
+    let segment = end - start;
+    let length_sq = segment.length_squared();
+
+    if length_sq <= f32::EPSILON {
+        return start;
+    }
+
+    start + segment * ((point - start).dot(segment) / length_sq).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, glam::vec3};
+
+    #[test]
+    pub fn closest_point() {
+        let start = Vec3::ZERO;
+        let end = vec3(0.0, 0.0, -4.0);
+
+        assert_eq!(
+            closest_point_on_segment(start, end, vec3(1.0, 0.0, -2.0)),
+            vec3(0.0, 0.0, -2.0)
+        );
+        assert_eq!(
+            closest_point_on_segment(start, end, vec3(0.0, 0.0, 3.0)),
+            start
+        );
+        assert_eq!(closest_point_on_segment(start, start, Vec3::ONE), start);
+    }
+
+    #[test]
+    pub fn splash_falls_off() {
+        let info = ProjectileInfo::ROCKET;
+
+        assert_eq!(info.splash_damage(0.0), info.splash_damage);
+        assert_eq!(
+            info.splash_damage(info.splash_radius * 0.5),
+            info.splash_damage * 0.5
+        );
+        assert_eq!(info.splash_damage(info.splash_radius * 2.0), 0.0);
+    }
+}
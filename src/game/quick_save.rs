@@ -0,0 +1,219 @@
+use {
+    super::{
+        difficulty::Difficulty,
+        objectives::Objectives,
+        save_file::{self, Versioned},
+        world::{World, WorldV1, WorldV2},
+    },
+    crate::fs::project_dirs,
+    anyhow::{bail, Context},
+    log::info,
+    serde::{Deserialize, Serialize},
+    std::{
+        path::PathBuf,
+        thread::{spawn, JoinHandle},
+    },
+};
+
+/// A snapshot of the game in progress which the player may save and load at any time.
+///
+/// Unlike [`SaveGame`](super::checkpoint::SaveGame), which only records the player, this holds
+/// the whole simulated [`World`]. There is a single quick save slot, and files are read and
+/// written on a background thread so that saving does not interrupt the game.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct QuickSave {
+    /// The difficulty chosen when the game was started.
+    pub difficulty: Difficulty,
+
+    /// Progress through the objectives of the level.
+    pub objectives: Objectives,
+
+    pub pitch: f32,
+
+    /// Key of the scene which was being played; older quick saves record an empty key, and are
+    /// not loaded into any scene.
+    pub scene: String,
+
+    pub weapon: String,
+    pub world: World,
+    pub yaw: f32,
+}
+
+/// The layout of [`QuickSave`] up to version 1, before the world held projectiles.
+#[derive(Deserialize)]
+struct QuickSaveV1 {
+    pitch: f32,
+    weapon: String,
+    world: WorldV1,
+    yaw: f32,
+}
+
+impl From<QuickSaveV1> for QuickSave {
+    fn from(quick_save: QuickSaveV1) -> Self {
+        Self {
+            difficulty: Difficulty::default(),
+            objectives: Objectives::default(),
+            pitch: quick_save.pitch,
+            scene: String::new(),
+            weapon: quick_save.weapon,
+            world: quick_save.world.into(),
+            yaw: quick_save.yaw,
+        }
+    }
+}
+
+/// The layout of [`QuickSave`] at version 2, before it recorded the difficulty.
+#[derive(Deserialize)]
+struct QuickSaveV2 {
+    pitch: f32,
+    weapon: String,
+    world: WorldV2,
+    yaw: f32,
+}
+
+impl From<QuickSaveV2> for QuickSave {
+    fn from(quick_save: QuickSaveV2) -> Self {
+        Self {
+            difficulty: Difficulty::default(),
+            objectives: Objectives::default(),
+            pitch: quick_save.pitch,
+            scene: String::new(),
+            weapon: quick_save.weapon,
+            world: quick_save.world.into(),
+            yaw: quick_save.yaw,
+        }
+    }
+}
+
+/// The layout of [`QuickSave`] at version 3, before the world held destructible bodies.
+#[derive(Deserialize)]
+struct QuickSaveV3 {
+    difficulty: Difficulty,
+    pitch: f32,
+    weapon: String,
+    world: WorldV2,
+    yaw: f32,
+}
+
+impl From<QuickSaveV3> for QuickSave {
+    fn from(quick_save: QuickSaveV3) -> Self {
+        Self {
+            difficulty: quick_save.difficulty,
+            objectives: Objectives::default(),
+            pitch: quick_save.pitch,
+            scene: String::new(),
+            weapon: quick_save.weapon,
+            world: quick_save.world.into(),
+            yaw: quick_save.yaw,
+        }
+    }
+}
+
+/// The layout of [`QuickSave`] at version 4, before it recorded objectives.
+#[derive(Deserialize)]
+struct QuickSaveV4 {
+    difficulty: Difficulty,
+    pitch: f32,
+    weapon: String,
+    world: World,
+    yaw: f32,
+}
+
+impl From<QuickSaveV4> for QuickSave {
+    fn from(quick_save: QuickSaveV4) -> Self {
+        Self {
+            difficulty: quick_save.difficulty,
+            objectives: Objectives::default(),
+            pitch: quick_save.pitch,
+            scene: String::new(),
+            weapon: quick_save.weapon,
+            world: quick_save.world,
+            yaw: quick_save.yaw,
+        }
+    }
+}
+
+/// The layout of [`QuickSave`] at version 5, before it recorded the scene.
+#[derive(Deserialize)]
+struct QuickSaveV5 {
+    difficulty: Difficulty,
+    objectives: Objectives,
+    pitch: f32,
+    weapon: String,
+    world: World,
+    yaw: f32,
+}
+
+impl From<QuickSaveV5> for QuickSave {
+    fn from(quick_save: QuickSaveV5) -> Self {
+        Self {
+            difficulty: quick_save.difficulty,
+            objectives: quick_save.objectives,
+            pitch: quick_save.pitch,
+            scene: String::new(),
+            weapon: quick_save.weapon,
+            world: quick_save.world,
+            yaw: quick_save.yaw,
+        }
+    }
+}
+
+impl QuickSave {
+    const FILE_NAME: &str = "quicksave.bin";
+
+    fn path() -> PathBuf {
+        project_dirs()
+            .map(|dirs| dirs.data_local_dir().to_path_buf())
+            .unwrap_or_default()
+            .join(Self::FILE_NAME)
+    }
+
+    /// Starts reading the quick save slot, or its backup if it is damaged; the result is `None`
+    /// if the player has not quick saved.
+    pub fn read() -> JoinHandle<anyhow::Result<Option<Self>>> {
+        spawn(|| {
+            let path = Self::path();
+
+            info!("Reading {}", path.display());
+
+            save_file::read(&path)
+        })
+    }
+
+    /// Starts writing this snapshot into the quick save slot, replacing any previous one.
+    pub fn write(self) -> JoinHandle<anyhow::Result<()>> {
+        spawn(move || {
+            let path = Self::path();
+
+            info!("Writing {}", path.display());
+
+            save_file::write(&path, &self)
+        })
+    }
+}
+
+impl Versioned for QuickSave {
+    const VERSION: u32 = 6;
+
+    fn migrate(version: u32, data: &[u8]) -> anyhow::Result<Self> {
+        match version {
+            // Version 0 is uncompressed and without a header, but otherwise the same as version 1
+            0 | 1 => bincode::deserialize::<QuickSaveV1>(data)
+                .map(Into::into)
+                .context("Deserializing quick save"),
+            2 => bincode::deserialize::<QuickSaveV2>(data)
+                .map(Into::into)
+                .context("Deserializing quick save"),
+            3 => bincode::deserialize::<QuickSaveV3>(data)
+                .map(Into::into)
+                .context("Deserializing quick save"),
+            4 => bincode::deserialize::<QuickSaveV4>(data)
+                .map(Into::into)
+                .context("Deserializing quick save"),
+            5 => bincode::deserialize::<QuickSaveV5>(data)
+                .map(Into::into)
+                .context("Deserializing quick save"),
+            _ => bail!("Unknown quick save version {version}"),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! The file format of saved games: a header holding the version of the save and the build which
+//! wrote it, followed by the save compressed with zstd.
+//!
+//! Saves written by older versions are upgraded as they are read. Each write keeps the file it
+//! replaces as a backup, which is read instead when the newer file is damaged, such as when the
+//! game crashed part way through writing it.
+
+use {
+    anyhow::{bail, Context},
+    bincode::Options,
+    log::{debug, warn},
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    std::{
+        fs::{self, create_dir_all, rename},
+        io::ErrorKind,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Identifies the build which wrote a save, for diagnosing saves which cannot be read.
+pub const BUILD_HASH: &str = env!("MOOD_BUILD_HASH");
+
+/// First bytes of every save; files without them were written before saves were versioned.
+const MAGIC: [u8; 4] = *b"MOOD";
+
+/// Level of zstd compression; saves are small, so this is chosen to write quickly.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Largest header which is read, so that a damaged length cannot allocate without bound.
+const MAX_HEADER_LEN: u64 = 256;
+
+#[derive(Deserialize, Serialize)]
+struct Header {
+    magic: [u8; 4],
+    version: u32,
+    build: String,
+}
+
+/// A save which may be written with [`write`] and read with [`read`].
+pub trait Versioned: DeserializeOwned + Serialize {
+    /// The version this build writes; increment it whenever the serialized layout changes, and
+    /// upgrade the previous layout in [`Self::migrate`].
+    const VERSION: u32;
+
+    /// Upgrades the decompressed data of a save written at an older version; version `0` is the
+    /// file written before saves had a header.
+    fn migrate(version: u32, data: &[u8]) -> anyhow::Result<Self>;
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension("bak")
+}
+
+/// Returns a save from the contents of a file, upgrading it if needed.
+pub fn decode<T: Versioned>(data: &[u8]) -> anyhow::Result<T> {
+    if !data.starts_with(&MAGIC) {
+        return T::migrate(0, data).context("Upgrading unversioned save");
+    }
+
+    // Headers are written by bincode::serialize, which uses fixed-width integers
+    let mut reader = data;
+    let header: Header = bincode::options()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_HEADER_LEN)
+        .deserialize_from(&mut reader)
+        .context("Reading header")?;
+
+    if header.version > T::VERSION {
+        bail!(
+            "Save version {} is newer than {} (written by build {})",
+            header.version,
+            T::VERSION,
+            header.build
+        );
+    }
+
+    if header.build != BUILD_HASH {
+        debug!("Save was written by build {}", header.build);
+    }
+
+    let data = zstd::decode_all(reader).context("Decompressing save")?;
+
+    if header.version < T::VERSION {
+        T::migrate(header.version, &data)
+            .with_context(|| format!("Upgrading save version {}", header.version))
+    } else {
+        bincode::deserialize(&data).context("Deserializing save")
+    }
+}
+
+/// Returns the contents of a file holding the given save.
+pub fn encode<T: Versioned>(save: &T) -> anyhow::Result<Vec<u8>> {
+    let mut data = bincode::serialize(&Header {
+        magic: MAGIC,
+        version: T::VERSION,
+        build: BUILD_HASH.to_owned(),
+    })?;
+    let save = bincode::serialize(save).context("Serializing save")?;
+
+    data.extend(zstd::encode_all(save.as_slice(), COMPRESSION_LEVEL).context("Compressing save")?);
+
+    Ok(data)
+}
+
+/// Reads a save, or its backup if it cannot be read; returns `None` if neither file exists.
+pub fn read<T: Versioned>(path: &Path) -> anyhow::Result<Option<T>> {
+    let read_file = |path: &Path| match fs::read(path) {
+        Ok(data) => decode(&data)
+            .map(Some)
+            .with_context(|| format!("Reading {}", path.display())),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("Reading {}", path.display())),
+    };
+
+    match read_file(path) {
+        Ok(Some(save)) => Ok(Some(save)),
+        Ok(None) => read_file(&backup_path(path)),
+        Err(err) => {
+            warn!("{err:#}; reading the backup instead");
+
+            match read_file(&backup_path(path)) {
+                Ok(Some(save)) => Ok(Some(save)),
+                _ => Err(err),
+            }
+        }
+    }
+}
+
+/// Writes a save, keeping the file it replaces as a backup.
+///
+/// The save is written beside the file and then renamed over it, so the file is either the old
+/// save or the new one even if writing is interrupted.
+pub fn write<T: Versioned>(path: &Path, save: &T) -> anyhow::Result<()> {
+    let data = encode(save)?;
+
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir).context("Creating save directory")?;
+    }
+
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, data).with_context(|| format!("Writing {}", temp_path.display()))?;
+
+    if path.exists() {
+        rename(path, backup_path(path)).context("Keeping backup")?;
+    }
+
+    rename(&temp_path, path).with_context(|| format!("Writing {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{env::temp_dir, process},
+    };
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Save {
+        name: String,
+        score: u32,
+    }
+
+    impl Versioned for Save {
+        const VERSION: u32 = 1;
+
+        fn migrate(version: u32, data: &[u8]) -> anyhow::Result<Self> {
+            match version {
+                // Only the name was saved
+                0 => Ok(Self {
+                    name: String::from_utf8(data.to_vec())?,
+                    score: 0,
+                }),
+                _ => bail!("Unknown version {version}"),
+            }
+        }
+    }
+
+    #[test]
+    pub fn encode_and_upgrade() {
+        let save = Save {
+            name: "level_01".to_owned(),
+            score: 42,
+        };
+        let data = encode(&save).unwrap();
+
+        assert_eq!(decode::<Save>(&data).unwrap(), save);
+
+        // Damaged saves are errors rather than panics
+        assert!(decode::<Save>(&data[..data.len() - 4]).is_err());
+
+        let upgraded = decode::<Save>(b"level_02").unwrap();
+
+        assert_eq!(upgraded.name, "level_02");
+        assert_eq!(upgraded.score, 0);
+    }
+
+    #[test]
+    pub fn read_backup_of_damaged_save() {
+        let path = temp_dir().join(format!("mood-save-test-{}.bin", process::id()));
+        let first = Save {
+            name: "first".to_owned(),
+            score: 1,
+        };
+
+        assert!(read::<Save>(&path).unwrap().is_none());
+
+        write(&path, &first).unwrap();
+        write(
+            &path,
+            &Save {
+                name: "second".to_owned(),
+                score: 2,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(read::<Save>(&path).unwrap().unwrap().name, "second");
+
+        // Cut the newest save short, as if the game crashed while writing it
+        let data = fs::read(&path).unwrap();
+        fs::write(&path, &data[..data.len() / 2]).unwrap();
+
+        assert_eq!(read::<Save>(&path).unwrap(), Some(first));
+
+        fs::remove_file(&path).unwrap_or_default();
+        fs::remove_file(backup_path(&path)).unwrap_or_default();
+    }
+}
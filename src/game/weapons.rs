@@ -0,0 +1,311 @@
+use {
+    super::projectile::ProjectileKind,
+    crate::{
+        art,
+        asset_key::{MaterialKey, ModelKey, SoundKey},
+        math::{Aabb, Ray},
+    },
+    glam::{vec3, Quat, Vec3},
+};
+
+/// Static definition of a weapon.
+#[derive(Clone, Copy, Debug)]
+pub struct WeaponInfo {
+    /// Name used by level designers, such as in `Pickup.Weapon.laser`.
+    pub id: &'static str,
+
+    pub name: &'static str,
+
+    /// Shots per second while the trigger is held.
+    pub fire_rate: f32,
+
+    /// Damage dealt by each hit-scan ray.
+    pub damage: f32,
+
+    /// The projectile launched along each ray instead of hit-scanning, if any.
+    pub projectile: Option<ProjectileKind>,
+
+    /// Maximum angle (in degrees) each ray may deviate from the aim direction.
+    pub spread: f32,
+
+    /// Number of hit-scan rays cast for each shot.
+    pub pellets: u32,
+
+    /// Maximum distance a hit-scan ray may travel.
+    pub range: f32,
+
+    pub fire_sound: SoundKey,
+
+    /// Caption key shown when firing, if captions are enabled.
+    pub fire_caption: &'static str,
+
+    pub model: ModelKey,
+    pub material: MaterialKey,
+}
+
+impl WeaponInfo {
+    pub const LASER: Self = Self {
+        id: "laser",
+        name: "Laser",
+        fire_rate: 6.0,
+        damage: 10.0,
+        projectile: None,
+        spread: 0.5,
+        pellets: 1,
+        range: 100.0,
+        fire_sound: art::SOUND_DIGITAL_THREE_TONE_1_OGG,
+        fire_caption: "laser_fire",
+        model: art::MODEL_PROP_LASER,
+        material: art::MATERIAL_ACCENT,
+    };
+
+    pub const SCATTER_LASER: Self = Self {
+        id: "scatter_laser",
+        name: "Scatter Laser",
+        fire_rate: 1.25,
+        damage: 6.0,
+        projectile: None,
+        spread: 6.0,
+        pellets: 8,
+        range: 25.0,
+        fire_sound: art::SOUND_DIGITAL_THREE_TONE_1_OGG,
+        fire_caption: "scatter_laser_fire",
+        model: art::MODEL_PROP_LASER,
+        material: art::MATERIAL_DARK_ACCENT,
+    };
+
+    pub const ROCKET_LAUNCHER: Self = Self {
+        id: "rocket_launcher",
+        name: "Rocket Launcher",
+        fire_rate: 1.0,
+        damage: 0.0,
+        projectile: Some(ProjectileKind::Rocket),
+        spread: 0.0,
+        pellets: 1,
+        range: 0.0,
+        fire_sound: art::SOUND_DIGITAL_THREE_TONE_1_OGG,
+        fire_caption: "rocket_launcher_fire",
+        model: art::MODEL_PROP_LASER,
+        material: art::MATERIAL_BLACK,
+    };
+
+    pub const GRENADE_LAUNCHER: Self = Self {
+        id: "grenade_launcher",
+        name: "Grenade Launcher",
+        fire_rate: 1.5,
+        damage: 0.0,
+        projectile: Some(ProjectileKind::Grenade),
+        spread: 1.0,
+        pellets: 1,
+        range: 0.0,
+        fire_sound: art::SOUND_DIGITAL_THREE_TONE_1_OGG,
+        fire_caption: "grenade_launcher_fire",
+        model: art::MODEL_PROP_LASER,
+        material: art::MATERIAL_DARK_GREY,
+    };
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+    pub damage: f32,
+    pub distance: f32,
+    pub position: Vec3,
+    pub target_index: usize,
+}
+
+/// One ray of a shot, along with the closest target volume it hit; the rays of projectile weapons
+/// are where each projectile is launched and never hit anything.
+#[derive(Clone, Copy, Debug)]
+pub struct Pellet {
+    pub hit: Option<Hit>,
+    pub ray: Ray,
+}
+
+/// The weapons carried by the player and the state of the currently selected one.
+pub struct Weapons {
+    cooldown: f32,
+    current: usize,
+    recoil: f32,
+    seed: u32,
+    weapons: Vec<WeaponInfo>,
+}
+
+impl Weapons {
+    /// Seconds it takes the view model to settle after firing.
+    const RECOIL_SECS: f32 = 0.15;
+
+    pub fn new(weapons: impl IntoIterator<Item = WeaponInfo>) -> Self {
+        let weapons = weapons.into_iter().collect::<Vec<_>>();
+
+        debug_assert!(!weapons.is_empty());
+
+        Self {
+            cooldown: 0.0,
+            current: 0,
+            recoil: 0.0,
+            seed: 0x9e37_79b9,
+            weapons,
+        }
+    }
+
+    pub fn current(&self) -> &WeaponInfo {
+        &self.weapons[self.current]
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// Selects the weapon `offset` places after the current one, wrapping around at either end.
+    pub fn cycle(&mut self, offset: isize) {
+        let len = self.weapons.len() as isize;
+
+        self.select((self.current as isize + offset).rem_euclid(len) as _);
+    }
+
+    /// Fires the current weapon if it has cooled down, casting hit-scan rays from `position` in
+    /// the direction given by `yaw` and `pitch` (in degrees) against the given target volumes, or
+    /// aiming the projectiles of weapons which launch them.
+    ///
+    /// Returns `None` if the weapon did not fire, otherwise each ray along with its closest hit.
+    pub fn fire(
+        &mut self,
+        position: Vec3,
+        yaw: f32,
+        pitch: f32,
+        targets: &[Aabb],
+    ) -> Option<Vec<Pellet>> {
+        if self.cooldown > 0.0 {
+            return None;
+        }
+
+        let info = *self.current();
+
+        self.cooldown = 1.0 / info.fire_rate;
+        self.recoil = Self::RECOIL_SECS;
+
+        let aim =
+            Quat::from_rotation_y(yaw.to_radians()) * Quat::from_rotation_x(pitch.to_radians());
+        let mut pellets = Vec::with_capacity(info.pellets as _);
+
+        for _ in 0..info.pellets {
+            let spread_yaw = (self.next_random() * 2.0 - 1.0) * info.spread;
+            let spread_pitch = (self.next_random() * 2.0 - 1.0) * info.spread;
+            let direction = aim
+                * Quat::from_rotation_y(spread_yaw.to_radians())
+                * Quat::from_rotation_x(spread_pitch.to_radians())
+                * -Vec3::Z;
+            let ray = Ray::new(position, direction.normalize());
+            let hit = info
+                .projectile
+                .is_none()
+                .then(|| hit_scan(ray, targets, info.range))
+                .flatten()
+                .map(|hit| Hit {
+                    damage: info.damage,
+                    ..hit
+                });
+
+            pellets.push(Pellet { hit, ray });
+        }
+
+        Some(pellets)
+    }
+
+    /// Selects the weapon at the given index, if it exists.
+    pub fn select(&mut self, index: usize) {
+        if index < self.weapons.len() && index != self.current {
+            self.current = index;
+            self.cooldown = self.cooldown.max(Self::RECOIL_SECS);
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+        self.recoil = (self.recoil - dt).max(0.0);
+    }
+
+    /// Returns the view model offset relative to the view model camera.
+    pub fn view_model_position(&self) -> Vec3 {
+        let kick = self.recoil / Self::RECOIL_SECS;
+
+        vec3(0.3, -0.25, -0.6 + 0.1 * kick)
+    }
+
+    /// Simple xorshift generator; weapon spread does not need anything better.
+    fn next_random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+
+        self.seed as f32 / u32::MAX as f32
+    }
+}
+
+/// Returns the closest target volume hit by the given ray within `range`.
+pub fn hit_scan(ray: Ray, targets: &[Aabb], range: f32) -> Option<Hit> {
+    targets
+        .iter()
+        .copied()
+        .enumerate()
+        .filter_map(|(target_index, target)| {
+            ray.intersect_aabb(target)
+                .filter(|distance| *distance <= range)
+                .map(|distance| Hit {
+                    damage: 0.0,
+                    distance,
+                    position: ray.point_at(distance),
+                    target_index,
+                })
+        })
+        .min_by(|lhs, rhs| lhs.distance.total_cmp(&rhs.distance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn hit_scan_closest() {
+        let targets = [
+            Aabb::from_center_half_extents(vec3(0.0, 0.0, -10.0), Vec3::ONE),
+            Aabb::from_center_half_extents(vec3(0.0, 0.0, -5.0), Vec3::ONE),
+            Aabb::from_center_half_extents(vec3(5.0, 0.0, -2.0), Vec3::ONE),
+        ];
+        let ray = Ray::new(Vec3::ZERO, -Vec3::Z);
+        let hit = hit_scan(ray, &targets, 100.0).unwrap();
+
+        assert_eq!(hit.target_index, 1);
+        assert!((hit.distance - 4.0).abs() < 1e-5);
+        assert!(hit_scan(ray, &targets, 3.0).is_none());
+    }
+
+    #[test]
+    pub fn cycle() {
+        let mut weapons = Weapons::new([
+            WeaponInfo::LASER,
+            WeaponInfo::SCATTER_LASER,
+            WeaponInfo::LASER,
+        ]);
+
+        weapons.cycle(-1);
+
+        assert_eq!(weapons.current_index(), 2);
+
+        weapons.cycle(2);
+
+        assert_eq!(weapons.current_index(), 1);
+    }
+
+    #[test]
+    pub fn fire_rate() {
+        let mut weapons = Weapons::new([WeaponInfo::LASER]);
+
+        assert!(weapons.fire(Vec3::ZERO, 0.0, 0.0, &[]).is_some());
+        assert!(weapons.fire(Vec3::ZERO, 0.0, 0.0, &[]).is_none());
+
+        weapons.update(1.0 / WeaponInfo::LASER.fire_rate);
+
+        assert!(weapons.fire(Vec3::ZERO, 0.0, 0.0, &[]).is_some());
+    }
+}
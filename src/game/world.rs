@@ -0,0 +1,1502 @@
+use {
+    super::{
+        game_rules::GameRules,
+        inventory::{Inventory, PickupKind},
+        physics::{self, Ragdoll, RigidBody},
+        projectile::{Projectile, ProjectileKind},
+    },
+    crate::{
+        level::{
+            collision::CollisionMesh,
+            entities::{EntityKind, Mover},
+            nav_mesh::{MeshLocation, NavigationMesh, OffMeshLink},
+        },
+        math::{Aabb, Ray},
+    },
+    glam::{vec2, vec3, Quat, Vec2, Vec3},
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+};
+
+/// Identifies an entity of a [`World`]; ids are never reused, so they stay valid in save games
+/// and may be shared between peers.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct EntityId(u32);
+
+/// The input of one player for one simulation step.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PlayerInput {
+    /// Direction to walk relative to where the player faces: `+x` is left and `+y` is forwards.
+    pub movement: Vec2,
+
+    pub pitch: f32,
+    pub sprint: bool,
+    pub yaw: f32,
+}
+
+/// A player of the world, which walks on the navigation mesh.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Player {
+    /// Height above the location at which the player rides a lift; lifts carry the players on
+    /// them each step, so it is not saved.
+    #[serde(skip)]
+    pub elevation: f32,
+
+    pub inventory: Inventory,
+
+    /// Where the player stands, or the start of the off-mesh link they are travelling.
+    pub location: MeshLocation,
+
+    pub pitch: f32,
+
+    /// The off-mesh link, such as a ladder, which the player is part way along; walking input is
+    /// ignored until it is finished.
+    pub traversal: Option<Traversal>,
+
+    pub yaw: f32,
+}
+
+impl Player {
+    /// Offset from where the player stands to their eyes.
+    pub const EYE_OFFSET: Vec3 = vec3(0.0, 1.7, 0.0);
+
+    /// Radius of the sphere, centered halfway up the player, which slides along level collision.
+    pub const RADIUS: f32 = 0.3;
+
+    const SPRINT_SCALE: f32 = 1.5;
+
+    /// Walking speed, in meters per second.
+    const WALK_SPEED: f32 = 4.0;
+
+    pub fn eye_position(&self) -> Vec3 {
+        self.position() + Self::EYE_OFFSET
+    }
+
+    /// Returns where the player stands, which is along an off-mesh link while one is travelled or
+    /// above their location while riding a lift.
+    pub fn position(&self) -> Vec3 {
+        self.traversal
+            .map(|traversal| traversal.link.position_at(traversal.progress))
+            .unwrap_or_else(|| self.location.position() + Vec3::Y * self.elevation)
+    }
+}
+
+/// Progress of a player along an off-mesh link.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Traversal {
+    /// The link, oriented from the end the player entered.
+    pub link: OffMeshLink,
+
+    /// Fraction of the link travelled.
+    pub progress: f32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Transform {
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+/// Something which happened during a simulation step, for presentation such as sounds and
+/// captions.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WorldEvent {
+    /// A detonation or shot damaged a player or destructible body by the given amount, at the
+    /// given position.
+    Damaged {
+        amount: u32,
+        position: Vec3,
+        victim: EntityId,
+    },
+
+    /// A projectile detonated at the given position and has been despawned.
+    Detonated {
+        kind: ProjectileKind,
+        position: Vec3,
+        projectile: EntityId,
+    },
+
+    /// A destructible body, such as a barrel, was brought to zero health by a detonation or shot
+    /// and has been despawned.
+    Destroyed { body: EntityId, position: Vec3 },
+
+    /// A player's health was brought to zero by a projectile or shot which `killer` fired.
+    Killed { killer: EntityId, player: EntityId },
+
+    /// A door finished opening.
+    Opened { mover: EntityId },
+
+    /// A player collected a pickup, which has been despawned from the given position.
+    PickedUp {
+        kind: PickupKind,
+        pickup: EntityId,
+        player: EntityId,
+        position: Vec3,
+    },
+
+    /// A player walked the given distance, in meters.
+    Walked { distance: f32, player: EntityId },
+}
+
+/// The simulated state of a level: the players, kinematic entities, props and pickups which
+/// change as the game is played, as opposed to static level geometry.
+///
+/// Entities are ids with components stored alongside them, and each step runs the systems which
+/// update those components from player inputs. The whole state is serializable so that it may
+/// be written into save games or sent to peers, and it holds no rendering state: presentation
+/// maps entity ids onto model instances using [`World::interpolate`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct World {
+    bodies: BTreeMap<EntityId, RigidBody>,
+    movers: BTreeMap<EntityId, Mover>,
+    next_id: u32,
+    pickups: BTreeMap<EntityId, PickupKind>,
+    players: BTreeMap<EntityId, Player>,
+
+    /// Transforms as of the previous step, which drawing interpolates from.
+    #[serde(skip)]
+    previous_transforms: BTreeMap<EntityId, Transform>,
+
+    projectiles: BTreeMap<EntityId, Projectile>,
+
+    /// Health of the bodies which detonations destroy.
+    prop_health: BTreeMap<EntityId, f32>,
+
+    ragdolls: BTreeMap<EntityId, Ragdoll>,
+    transforms: BTreeMap<EntityId, Transform>,
+}
+
+/// The layout of [`World`] before projectiles, which older quick saves hold.
+#[derive(Deserialize)]
+pub struct WorldV1 {
+    bodies: BTreeMap<EntityId, RigidBody>,
+    movers: BTreeMap<EntityId, Mover>,
+    next_id: u32,
+    pickups: BTreeMap<EntityId, PickupKind>,
+    players: BTreeMap<EntityId, Player>,
+    ragdolls: BTreeMap<EntityId, Ragdoll>,
+    transforms: BTreeMap<EntityId, Transform>,
+}
+
+impl From<WorldV1> for World {
+    fn from(world: WorldV1) -> Self {
+        Self {
+            bodies: world.bodies,
+            movers: world.movers,
+            next_id: world.next_id,
+            pickups: world.pickups,
+            players: world.players,
+            previous_transforms: Default::default(),
+            projectiles: Default::default(),
+            prop_health: Default::default(),
+            ragdolls: world.ragdolls,
+            transforms: world.transforms,
+        }
+    }
+}
+
+/// The layout of [`World`] before bodies could be destroyed, which older quick saves hold.
+#[derive(Deserialize)]
+pub struct WorldV2 {
+    bodies: BTreeMap<EntityId, RigidBody>,
+    movers: BTreeMap<EntityId, Mover>,
+    next_id: u32,
+    pickups: BTreeMap<EntityId, PickupKind>,
+    players: BTreeMap<EntityId, Player>,
+    projectiles: BTreeMap<EntityId, Projectile>,
+    ragdolls: BTreeMap<EntityId, Ragdoll>,
+    transforms: BTreeMap<EntityId, Transform>,
+}
+
+impl From<WorldV2> for World {
+    fn from(world: WorldV2) -> Self {
+        Self {
+            bodies: world.bodies,
+            movers: world.movers,
+            next_id: world.next_id,
+            pickups: world.pickups,
+            players: world.players,
+            previous_transforms: Default::default(),
+            projectiles: world.projectiles,
+            prop_health: Default::default(),
+            ragdolls: world.ragdolls,
+            transforms: world.transforms,
+        }
+    }
+}
+
+impl World {
+    /// Distance from a player at which pickups are collected.
+    const PICKUP_RADIUS: f32 = 1.0;
+
+    /// Change in speed, in meters per second, of a one kilogram body at the center of a
+    /// detonation.
+    const SPLASH_IMPULSE: f32 = 20.0;
+
+    /// Returns the volumes which currently block players.
+    pub fn blocking_volumes(&self) -> impl Iterator<Item = Aabb> + '_ {
+        self.movers.values().filter_map(Mover::blocking_volume)
+    }
+
+    /// Returns `true` if the entity has not been despawned.
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.transforms.contains_key(&id)
+    }
+
+    /// Removes an entity and all of its components.
+    pub fn despawn(&mut self, id: EntityId) {
+        self.bodies.remove(&id);
+        self.movers.remove(&id);
+        self.pickups.remove(&id);
+        self.players.remove(&id);
+        self.previous_transforms.remove(&id);
+        self.projectiles.remove(&id);
+        self.prop_health.remove(&id);
+        self.ragdolls.remove(&id);
+        self.transforms.remove(&id);
+    }
+
+    /// Returns the transform of each entity, blended between the previous and current steps by
+    /// `alpha`.
+    pub fn interpolate(&self, alpha: f32) -> impl Iterator<Item = (EntityId, Transform)> + '_ {
+        self.transforms.iter().map(move |(&id, transform)| {
+            let previous = self.previous_transforms.get(&id).unwrap_or(transform);
+
+            (
+                id,
+                Transform {
+                    position: previous.position.lerp(transform.position, alpha),
+                    rotation: previous.rotation.slerp(transform.rotation, alpha),
+                },
+            )
+        })
+    }
+
+    /// Fully opens a door and keeps it open, such as one the player opened on an earlier visit to
+    /// the level.
+    pub fn latch_open(&mut self, id: EntityId) {
+        if let Some(mover) = self.movers.get_mut(&id) {
+            mover.latch_open();
+        }
+    }
+
+    /// Returns the kind of a pickup which has not been collected.
+    pub fn pickup(&self, id: EntityId) -> Option<&PickupKind> {
+        self.pickups.get(&id)
+    }
+
+    pub fn player(&self, id: EntityId) -> Option<&Player> {
+        self.players.get(&id)
+    }
+
+    pub fn player_mut(&mut self, id: EntityId) -> Option<&mut Player> {
+        self.players.get_mut(&id)
+    }
+
+    /// Returns each projectile in flight along with its transform, such as to draw its model and
+    /// trail.
+    pub fn projectiles(&self) -> impl Iterator<Item = (EntityId, &Projectile, Transform)> + '_ {
+        self.projectiles
+            .iter()
+            .filter_map(|(&id, projectile)| Some((id, projectile, *self.transforms.get(&id)?)))
+    }
+
+    /// Returns the ragdoll of an entity, such as to pose the skinned mesh of a dead actor.
+    pub fn ragdoll(&self, id: EntityId) -> Option<&Ragdoll> {
+        self.ragdolls.get(&id)
+    }
+
+    /// Damages the player or destructible body which a hit-scan shot of `attacker` hit at the
+    /// given position; damage to players is scaled by `rules`, as splash damage is.
+    pub fn shoot(
+        &mut self,
+        rules: &GameRules,
+        attacker: EntityId,
+        victim: EntityId,
+        damage: f32,
+        position: Vec3,
+        events: &mut Vec<WorldEvent>,
+    ) {
+        if let Some(player) = self.players.get_mut(&victim) {
+            let damage = rules.damage(damage, attacker, victim).round() as u32;
+
+            Self::hurt_player(player, victim, attacker, damage, position, events);
+        } else if let Some(health) = self.prop_health.get_mut(&victim) {
+            // Props are not enemies, so their damage is not scaled by the difficulty
+            *health -= damage;
+            events.push(WorldEvent::Damaged {
+                amount: damage.round() as _,
+                position,
+                victim,
+            });
+
+            if *health <= 0.0 {
+                let position = self
+                    .transforms
+                    .get(&victim)
+                    .map_or(position, |transform| transform.position);
+                self.despawn(victim);
+                events.push(WorldEvent::Destroyed {
+                    body: victim,
+                    position,
+                });
+            }
+        }
+    }
+
+    /// Returns the volumes which hit-scan shots of `shooter` may hit, along with the living player
+    /// or body of each; blocking volumes, such as closed doors, have neither and only stop shots.
+    pub fn shot_targets(&self, shooter: EntityId) -> Vec<(Option<EntityId>, Aabb)> {
+        let players = self
+            .players
+            .iter()
+            .filter(|&(&id, player)| id != shooter && player.inventory.health > 0)
+            .map(|(&id, player)| {
+                (
+                    Some(id),
+                    Aabb::from_center_half_extents(
+                        player.position() + Player::EYE_OFFSET * 0.5,
+                        vec3(Player::RADIUS, Player::EYE_OFFSET.y * 0.5, Player::RADIUS),
+                    ),
+                )
+            });
+        let bodies = self.bodies.iter().filter_map(|(&id, body)| {
+            Some((
+                Some(id),
+                Aabb::from_center_half_extents(
+                    self.transforms.get(&id)?.position,
+                    Vec3::splat(body.radius),
+                ),
+            ))
+        });
+
+        self.blocking_volumes()
+            .map(|volume| (None, volume))
+            .chain(players)
+            .chain(bodies)
+            .collect()
+    }
+
+    fn spawn(&mut self, transform: Transform) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.next_id += 1;
+        self.transforms.insert(id, transform);
+
+        id
+    }
+
+    /// Adds a physics prop which falls from the given transform.
+    pub fn spawn_body(&mut self, body: RigidBody, transform: Transform) -> EntityId {
+        let id = self.spawn(transform);
+        self.bodies.insert(id, body);
+
+        id
+    }
+
+    /// Makes a body destructible: detonations damage it, and it is despawned once it has no health
+    /// left.
+    pub fn set_prop_health(&mut self, id: EntityId, health: f32) {
+        if self.bodies.contains_key(&id) {
+            self.prop_health.insert(id, health);
+        }
+    }
+
+    /// Adds a kinematic entity which begins at the given transform.
+    pub fn spawn_mover(&mut self, kind: EntityKind, transform: Transform) -> EntityId {
+        let id = self.spawn(transform);
+        self.movers
+            .insert(id, Mover::new(kind, transform.position, transform.rotation));
+
+        id
+    }
+
+    pub fn spawn_pickup(&mut self, kind: PickupKind, transform: Transform) -> EntityId {
+        let id = self.spawn(transform);
+        self.pickups.insert(id, kind);
+
+        id
+    }
+
+    /// Adds a player with a new inventory, standing at the given location.
+    pub fn spawn_player(&mut self, location: MeshLocation) -> EntityId {
+        let id = self.spawn(Transform {
+            position: location.position(),
+            rotation: Quat::IDENTITY,
+        });
+        self.players.insert(
+            id,
+            Player {
+                elevation: 0.0,
+                inventory: Default::default(),
+                location,
+                pitch: 0.0,
+                traversal: None,
+                yaw: 0.0,
+            },
+        );
+
+        id
+    }
+
+    /// Adds a projectile fired by a player along the given ray.
+    pub fn spawn_projectile(
+        &mut self,
+        kind: ProjectileKind,
+        owner: EntityId,
+        ray: Ray,
+    ) -> EntityId {
+        let id = self.spawn(Transform {
+            position: ray.position(),
+            rotation: Quat::from_rotation_arc(-Vec3::Z, ray.normal()),
+        });
+        self.projectiles
+            .insert(id, Projectile::new(kind, owner, ray));
+
+        id
+    }
+
+    /// Adds a ragdoll, which replaces an actor that died; the entity follows its root joint.
+    pub fn spawn_ragdoll(&mut self, ragdoll: Ragdoll) -> EntityId {
+        let id = self.spawn(ragdoll.transform());
+        self.ragdolls.insert(id, ragdoll);
+
+        id
+    }
+
+    /// Runs each system once, advancing the world by `dt` seconds; players without an input stand
+    /// still, and damage and pickups are scaled by the difficulty of `rules`.
+    pub fn step(
+        &mut self,
+        nav_mesh: &mut NavigationMesh,
+        collision: &CollisionMesh,
+        inputs: &BTreeMap<EntityId, PlayerInput>,
+        rules: &GameRules,
+        dt: f32,
+        events: &mut Vec<WorldEvent>,
+    ) {
+        self.previous_transforms.clone_from(&self.transforms);
+
+        self.update_players(nav_mesh, collision, inputs, dt, events);
+        self.update_movers(dt, events);
+        self.update_bodies(collision, dt);
+        self.update_ragdolls(collision, dt);
+        self.update_projectiles(collision, rules, dt, events);
+        self.update_pickups(rules, events);
+    }
+
+    /// Moves a player to the given location without interpolating from where they were, such as
+    /// when respawning.
+    pub fn teleport_player(&mut self, id: EntityId, location: MeshLocation) {
+        let Some(player) = self.players.get_mut(&id) else {
+            return;
+        };
+
+        player.elevation = 0.0;
+        player.location = location;
+        player.traversal = None;
+
+        let transform = Transform {
+            position: location.position(),
+            rotation: Quat::from_rotation_y(player.yaw.to_radians()),
+        };
+        self.previous_transforms.insert(id, transform);
+        self.transforms.insert(id, transform);
+    }
+
+    /// Returns the transform of an entity as of the latest step.
+    pub fn transform(&self, id: EntityId) -> Option<Transform> {
+        self.transforms.get(&id).copied()
+    }
+
+    /// Takes damage from the health of a living player, who is killed by `killer` if it reaches
+    /// zero.
+    fn hurt_player(
+        player: &mut Player,
+        id: EntityId,
+        killer: EntityId,
+        damage: u32,
+        position: Vec3,
+        events: &mut Vec<WorldEvent>,
+    ) {
+        let health = player.inventory.health;
+
+        if damage == 0 || health == 0 {
+            return;
+        }
+
+        player.inventory.health = health.saturating_sub(damage);
+        events.push(WorldEvent::Damaged {
+            amount: damage,
+            position,
+            victim: id,
+        });
+
+        if player.inventory.health == 0 {
+            events.push(WorldEvent::Killed { killer, player: id });
+        }
+    }
+
+    /// Damages players, and pushes and damages bodies, which the level does not shield from a
+    /// detonation of a projectile fired by `owner`.
+    fn splash(
+        &mut self,
+        collision: &CollisionMesh,
+        rules: &GameRules,
+        kind: ProjectileKind,
+        owner: EntityId,
+        position: Vec3,
+        events: &mut Vec<WorldEvent>,
+    ) {
+        let info = kind.info();
+        let is_exposed = |target: Vec3| {
+            let offset = target - position;
+            let distance = offset.length();
+
+            distance <= f32::EPSILON
+                || collision
+                    .raycast(Ray::new(position, offset / distance), distance)
+                    .is_none()
+        };
+
+        for (&id, player) in &mut self.players {
+            let center = player.position() + Player::EYE_OFFSET * 0.5;
+            let damage = rules
+                .damage(info.splash_damage(center.distance(position)), owner, id)
+                .round() as u32;
+
+            if damage > 0 && is_exposed(center) {
+                Self::hurt_player(player, id, owner, damage, center, events);
+            }
+        }
+
+        let mut destroyed = vec![];
+
+        for (id, body) in &mut self.bodies {
+            let Some(transform) = self.transforms.get(id) else {
+                continue;
+            };
+
+            let offset = transform.position - position;
+            let damage = info.splash_damage(offset.length());
+            let falloff = damage / info.splash_damage;
+
+            if falloff > 0.0 && is_exposed(transform.position) {
+                body.velocity +=
+                    offset.try_normalize().unwrap_or(Vec3::Y) * falloff * Self::SPLASH_IMPULSE
+                        / body.mass;
+
+                // Props are not enemies, so their damage is not scaled by the difficulty
+                if let Some(health) = self.prop_health.get_mut(id) {
+                    *health -= damage;
+                    events.push(WorldEvent::Damaged {
+                        amount: damage.round() as _,
+                        position: transform.position,
+                        victim: *id,
+                    });
+
+                    if *health <= 0.0 {
+                        destroyed.push((*id, transform.position));
+                    }
+                }
+            }
+        }
+
+        for (id, position) in destroyed {
+            self.despawn(id);
+            events.push(WorldEvent::Destroyed { body: id, position });
+        }
+    }
+
+    fn update_bodies(&mut self, collision: &CollisionMesh, dt: f32) {
+        let players = self
+            .players
+            .values()
+            .map(Player::position)
+            .collect::<Box<_>>();
+
+        physics::step(
+            &mut self.bodies,
+            &mut self.transforms,
+            collision,
+            &players,
+            dt,
+        );
+    }
+
+    fn update_movers(&mut self, dt: f32, events: &mut Vec<WorldEvent>) {
+        for (id, mover) in &mut self.movers {
+            // Players standing on a lift are carried with it
+            let riders = self
+                .players
+                .iter()
+                .filter(|(_, player)| {
+                    player.traversal.is_none() && mover.platform_height(player.position()).is_some()
+                })
+                .map(|(&id, _)| id)
+                .collect::<Vec<_>>();
+
+            // Doors open for whichever player is closest
+            let player_position = self
+                .players
+                .values()
+                .map(Player::eye_position)
+                .min_by(|lhs, rhs| {
+                    lhs.distance_squared(mover.current_position())
+                        .total_cmp(&rhs.distance_squared(mover.current_position()))
+                })
+                .unwrap_or(Vec3::INFINITY);
+
+            if mover.update(dt, player_position) {
+                events.push(WorldEvent::Opened { mover: *id });
+            }
+
+            if let Some(top) = mover.blocking_volume().map(|volume| volume.max().y) {
+                for rider in &riders {
+                    let player = self.players.get_mut(rider).unwrap();
+                    player.elevation = (top - player.location.position().y).max(0.0);
+
+                    self.transforms.insert(
+                        *rider,
+                        Transform {
+                            position: player.position(),
+                            rotation: Quat::from_rotation_y(player.yaw.to_radians()),
+                        },
+                    );
+                }
+            }
+
+            self.transforms.insert(
+                *id,
+                Transform {
+                    position: mover.current_position(),
+                    rotation: mover.current_rotation(),
+                },
+            );
+        }
+    }
+
+    fn update_pickups(&mut self, rules: &GameRules, events: &mut Vec<WorldEvent>) {
+        let radius_sq = Self::PICKUP_RADIUS * Self::PICKUP_RADIUS;
+        let mut collected = vec![];
+
+        for (&player_id, player) in &mut self.players {
+            let player_position = player.position();
+
+            for (&pickup_id, kind) in &self.pickups {
+                if collected.contains(&pickup_id) {
+                    continue;
+                }
+
+                let Some(transform) = self.transforms.get(&pickup_id) else {
+                    continue;
+                };
+
+                if transform.position.distance_squared(player_position) > radius_sq {
+                    continue;
+                }
+
+                let kind = rules.pickup(kind);
+
+                if player.inventory.add(&kind) {
+                    collected.push(pickup_id);
+                    events.push(WorldEvent::PickedUp {
+                        kind,
+                        pickup: pickup_id,
+                        player: player_id,
+                        position: transform.position,
+                    });
+                }
+            }
+        }
+
+        for id in collected {
+            self.despawn(id);
+        }
+    }
+
+    fn update_players(
+        &mut self,
+        nav_mesh: &mut NavigationMesh,
+        collision: &CollisionMesh,
+        inputs: &BTreeMap<EntityId, PlayerInput>,
+        dt: f32,
+        events: &mut Vec<WorldEvent>,
+    ) {
+        for (&id, player) in &mut self.players {
+            let input = inputs.get(&id).copied().unwrap_or_default();
+
+            player.pitch = input.pitch;
+            player.yaw = input.yaw;
+
+            let mut movement = input.movement;
+
+            if input.sprint {
+                movement.y *= Player::SPRINT_SCALE;
+            }
+
+            let yaw = (player.yaw - 90.0).to_radians();
+            let (yaw_sin, yaw_cos) = yaw.sin_cos();
+            let direction = vec2(
+                yaw_sin * movement.x - yaw_cos * movement.y,
+                yaw_cos * movement.x + yaw_sin * movement.y,
+            ) * dt
+                * Player::WALK_SPEED;
+
+            // Walking into the start of an off-mesh link, such as a ladder, begins travelling it
+            if player.traversal.is_none() {
+                player.traversal =
+                    nav_mesh
+                        .link_at(player.location, direction)
+                        .map(|link| Traversal {
+                            link,
+                            progress: 0.0,
+                        });
+            }
+
+            if let Some(mut traversal) = player.traversal {
+                traversal.progress += dt / traversal.link.duration().max(f32::EPSILON);
+
+                if traversal.progress < 1.0 {
+                    player.traversal = Some(traversal);
+                } else {
+                    player.location = traversal.link.end;
+                    player.traversal = None;
+                }
+
+                self.transforms.insert(
+                    id,
+                    Transform {
+                        position: player.position(),
+                        rotation: Quat::from_rotation_y(player.yaw.to_radians()),
+                    },
+                );
+
+                continue;
+            }
+
+            // Walls slide the player along them before the move is kept on the walkable region
+            let previous_position = player.position();
+            let body_position = previous_position + Player::EYE_OFFSET * 0.5;
+            let motion = collision.slide(
+                body_position,
+                vec3(direction.x, 0.0, direction.y),
+                Player::RADIUS,
+            ) - body_position;
+            let location = nav_mesh.walk(player.location, vec2(motion.x, motion.z));
+
+            // Closed doors and other kinematic entities stop the player from walking through them
+            let feet = location.position() + Vec3::Y * player.elevation;
+            let body_position = feet + Player::EYE_OFFSET * 0.5;
+            if !self
+                .movers
+                .values()
+                .filter_map(Mover::blocking_volume)
+                .any(|volume| volume.contains(body_position))
+            {
+                player.location = location;
+
+                // Stepping off a raised lift lands on the walkable region nearest the feet, such
+                // as the floor the lift rose to
+                if player.elevation > 0.0
+                    && !self
+                        .movers
+                        .values()
+                        .any(|mover| mover.platform_height(feet).is_some())
+                {
+                    player.elevation = 0.0;
+                    player.location = nav_mesh.locate(feet);
+                }
+            }
+
+            self.transforms.insert(
+                id,
+                Transform {
+                    position: player.position(),
+                    rotation: Quat::from_rotation_y(player.yaw.to_radians()),
+                },
+            );
+
+            events.push(WorldEvent::Walked {
+                distance: (player.position() - previous_position).length(),
+                player: id,
+            });
+        }
+    }
+
+    fn update_projectiles(
+        &mut self,
+        collision: &CollisionMesh,
+        rules: &GameRules,
+        dt: f32,
+        events: &mut Vec<WorldEvent>,
+    ) {
+        let mut detonated = vec![];
+
+        for (&id, projectile) in &mut self.projectiles {
+            let Some(transform) = self.transforms.get_mut(&id) else {
+                continue;
+            };
+
+            let actors = self
+                .players
+                .iter()
+                .filter(|(&player_id, _)| player_id != projectile.owner)
+                .map(|(_, player)| player.position() + Player::EYE_OFFSET * 0.5)
+                .collect::<Box<_>>();
+
+            if projectile.step(
+                &mut transform.position,
+                collision,
+                &actors,
+                Player::RADIUS,
+                dt,
+            ) {
+                detonated.push((id, projectile.kind, projectile.owner, transform.position));
+            } else if let Some(direction) = projectile.velocity.try_normalize() {
+                transform.rotation = Quat::from_rotation_arc(-Vec3::Z, direction);
+            }
+        }
+
+        for (id, kind, owner, position) in detonated {
+            self.despawn(id);
+            events.push(WorldEvent::Detonated {
+                kind,
+                position,
+                projectile: id,
+            });
+            self.splash(collision, rules, kind, owner, position, events);
+        }
+    }
+
+    fn update_ragdolls(&mut self, collision: &CollisionMesh, dt: f32) {
+        for (&id, ragdoll) in &mut self.ragdolls {
+            ragdoll.step(collision, dt);
+            self.transforms.insert(id, ragdoll.transform());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            game::{
+                difficulty::{Difficulty, DifficultyRules, DifficultyTable},
+                physics::RagdollJoint,
+            },
+            level::nav_mesh::LinkKind,
+        },
+        std::collections::HashMap,
+    };
+
+    fn floor() -> (NavigationMesh, CollisionMesh) {
+        let vertices = [
+            vec3(-20.0, 0.0, -20.0),
+            vec3(20.0, 0.0, -20.0),
+            vec3(-20.0, 0.0, 20.0),
+            vec3(20.0, 0.0, 20.0),
+        ];
+        let indices = [0, 1, 3, 0, 3, 2];
+
+        (
+            NavigationMesh::new(&indices, &vertices),
+            CollisionMesh::new(&[], &[]),
+        )
+    }
+
+    fn transform(position: Vec3) -> Transform {
+        Transform {
+            position,
+            rotation: Quat::IDENTITY,
+        }
+    }
+
+    #[test]
+    pub fn collect_pickups() {
+        let (mut nav_mesh, collision) = floor();
+        let mut world = World::default();
+        let player = world.spawn_player(nav_mesh.locate(Vec3::ZERO));
+        world.player_mut(player).unwrap().inventory.health = 90;
+
+        let first = world.spawn_pickup(PickupKind::Health(25), transform(Vec3::ZERO));
+        let second = world.spawn_pickup(PickupKind::Health(25), transform(vec3(0.5, 0.0, 0.0)));
+        let key = world.spawn_pickup(
+            PickupKind::Key("red".to_owned()),
+            transform(vec3(10.0, 0.0, 0.0)),
+        );
+        let mut events = vec![];
+
+        // The second health pickup is left because health is full after the first
+        world.step(
+            &mut nav_mesh,
+            &collision,
+            &Default::default(),
+            &GameRules::default(),
+            0.1,
+            &mut events,
+        );
+
+        assert!(events.contains(&WorldEvent::PickedUp {
+            kind: PickupKind::Health(25),
+            pickup: first,
+            player,
+            position: Vec3::ZERO,
+        }));
+        assert_eq!(
+            world.player(player).unwrap().inventory.health,
+            Inventory::MAX_HEALTH
+        );
+        assert!(!world.transforms.contains_key(&first));
+        assert!(world.transforms.contains_key(&second));
+
+        world.teleport_player(player, nav_mesh.locate(vec3(10.0, 0.0, 0.0)));
+        world.step(
+            &mut nav_mesh,
+            &collision,
+            &Default::default(),
+            &GameRules::default(),
+            0.1,
+            &mut events,
+        );
+
+        assert!(world.player(player).unwrap().inventory.keys.contains("red"));
+        assert!(world.pickups.get(&key).is_none());
+    }
+
+    #[test]
+    pub fn difficulty_scales_damage_and_pickups() {
+        let (mut nav_mesh, collision) = floor();
+        let mut world = World::default();
+        let player = world.spawn_player(nav_mesh.locate(Vec3::ZERO));
+        let enemy = world.spawn_player(nav_mesh.locate(vec3(10.0, 0.0, 0.0)));
+        let table = DifficultyTable {
+            difficulties: HashMap::from([(
+                Difficulty::Hard,
+                DifficultyRules {
+                    ammo_pickups: 0.5,
+                    enemy_damage: 2.0,
+                    enemy_health: 4.0,
+                    player_damage_taken: 1.5,
+                },
+            )]),
+        };
+        let mut rules = GameRules::new(table, player);
+
+        assert_eq!(rules.damage(10.0, enemy, player), 10.0);
+
+        rules.set_difficulty(Difficulty::Hard);
+
+        assert_eq!(rules.damage(10.0, enemy, player), 30.0);
+        assert_eq!(rules.damage(10.0, player, player), 15.0);
+        assert_eq!(rules.damage(10.0, player, enemy), 2.5);
+
+        let pickup = world.spawn_pickup(PickupKind::Ammo(25), transform(Vec3::ZERO));
+        let mut events = vec![];
+
+        world.step(
+            &mut nav_mesh,
+            &collision,
+            &Default::default(),
+            &rules,
+            0.1,
+            &mut events,
+        );
+
+        assert!(events.contains(&WorldEvent::PickedUp {
+            kind: PickupKind::Ammo(13),
+            pickup,
+            player,
+            position: Vec3::ZERO,
+        }));
+    }
+
+    #[test]
+    pub fn bodies_fall_and_are_pushed() {
+        let (mut nav_mesh, _) = floor();
+        let collision = CollisionMesh::new(
+            &[0, 1, 3, 0, 3, 2],
+            &[
+                vec3(-20.0, 0.0, -20.0),
+                vec3(20.0, 0.0, -20.0),
+                vec3(-20.0, 0.0, 20.0),
+                vec3(20.0, 0.0, 20.0),
+            ],
+        );
+        let mut world = World::default();
+        let player = world.spawn_player(nav_mesh.locate(vec3(5.0, 0.0, 0.0)));
+        let barrel = world.spawn_body(RigidBody::new(0.25, 5.0), transform(vec3(0.0, 3.0, 0.0)));
+        let mut events = vec![];
+
+        for _ in 0..300 {
+            world.step(
+                &mut nav_mesh,
+                &collision,
+                &Default::default(),
+                &GameRules::default(),
+                1.0 / 60.0,
+                &mut events,
+            );
+        }
+
+        // The barrel comes to rest on the floor
+        assert!((world.transforms[&barrel].position.y - 0.25).abs() < 0.01);
+        assert_eq!(world.bodies[&barrel].velocity, Vec3::ZERO);
+
+        // A player walking into it pushes it away
+        world.teleport_player(player, nav_mesh.locate(vec3(-0.4, 0.0, 0.0)));
+        world.step(
+            &mut nav_mesh,
+            &collision,
+            &Default::default(),
+            &GameRules::default(),
+            1.0 / 60.0,
+            &mut events,
+        );
+
+        assert!(world.transforms[&barrel].position.x >= 0.15 - 1e-3);
+        assert!(world.bodies[&barrel].velocity.x > 0.0);
+    }
+
+    #[test]
+    pub fn ragdoll_falls_down_stairs() {
+        // One step, half a meter tall, with the upper floor behind -Z
+        let vertices = [
+            vec3(-5.0, 0.5, -10.0),
+            vec3(5.0, 0.5, -10.0),
+            vec3(-5.0, 0.5, 0.0),
+            vec3(5.0, 0.5, 0.0),
+            vec3(-5.0, 0.0, 0.0),
+            vec3(5.0, 0.0, 0.0),
+            vec3(-5.0, 0.0, 10.0),
+            vec3(5.0, 0.0, 10.0),
+        ];
+        let indices = [0, 1, 3, 0, 3, 2, 2, 3, 5, 2, 5, 4, 4, 5, 7, 4, 7, 6];
+        let collision = CollisionMesh::new(&indices, &vertices);
+        let (mut nav_mesh, _) = floor();
+
+        // A chain of three joints lying across the edge of the step
+        let joints = [
+            (vec3(0.0, 1.5, -0.6), None),
+            (vec3(0.0, 1.5, 0.0), Some(0)),
+            (vec3(0.0, 1.5, 0.6), Some(1)),
+        ]
+        .map(|(position, parent)| RagdollJoint {
+            bind_position: position - Vec3::Y,
+            parent,
+            position,
+        });
+        let mut world = World::default();
+        let ragdoll = world.spawn_ragdoll(Ragdoll::new(&joints, 0.1, Vec3::ZERO));
+        let mut events = vec![];
+
+        for _ in 0..300 {
+            world.step(
+                &mut nav_mesh,
+                &collision,
+                &Default::default(),
+                &GameRules::default(),
+                1.0 / 60.0,
+                &mut events,
+            );
+        }
+
+        let positions = world.ragdoll(ragdoll).unwrap().joints().to_vec();
+
+        // Joints stay out of the level and bones keep their lengths
+        assert!(positions
+            .iter()
+            .all(|joint| joint.y > if joint.z < -0.1 { 0.5 } else { 0.0 }));
+        assert!(positions[2].y < 1.0);
+
+        for pair in positions.windows(2) {
+            assert!((pair[0].distance(pair[1]) - 0.6).abs() < 0.06);
+        }
+
+        // Bone matrices move each parent joint from the bind pose to where it lies now
+        for (matrix, parent) in world.ragdoll(ragdoll).unwrap().bone_matrices().zip(&joints) {
+            let position = matrix.transform_point3(parent.bind_position);
+
+            assert!(positions
+                .iter()
+                .any(|joint| joint.distance(position) < 1e-4));
+        }
+
+        assert_eq!(world.transforms[&ragdoll].position, positions[0]);
+    }
+
+    #[test]
+    pub fn detonations_destroy_props() {
+        let (mut nav_mesh, _) = floor();
+        let collision = CollisionMesh::new(
+            &[0, 1, 3, 0, 3, 2],
+            &[
+                vec3(-20.0, 0.0, -20.0),
+                vec3(20.0, 0.0, -20.0),
+                vec3(-20.0, 0.0, 20.0),
+                vec3(20.0, 0.0, 20.0),
+            ],
+        );
+        let mut world = World::default();
+        let shooter = world.spawn_player(nav_mesh.locate(vec3(10.0, 0.0, 10.0)));
+        let barrel = world.spawn_body(RigidBody::new(0.25, 5.0), transform(vec3(1.0, 0.25, 0.0)));
+        let locker = world.spawn_body(RigidBody::new(0.25, 5.0), transform(vec3(-1.0, 0.25, 0.0)));
+        let rock = world.spawn_body(RigidBody::new(0.25, 5.0), transform(vec3(0.0, 0.25, 1.0)));
+        world.set_prop_health(barrel, 10.0);
+        world.set_prop_health(locker, 1000.0);
+
+        let rocket = world.spawn_projectile(
+            ProjectileKind::Rocket,
+            shooter,
+            Ray::new(vec3(0.0, 1.0, 0.0), -Vec3::Y),
+        );
+        let mut destroyed = vec![];
+
+        for _ in 0..60 {
+            let mut events = vec![];
+            world.step(
+                &mut nav_mesh,
+                &collision,
+                &Default::default(),
+                &GameRules::default(),
+                1.0 / 60.0,
+                &mut events,
+            );
+            destroyed.extend(events.into_iter().filter_map(|event| match event {
+                WorldEvent::Destroyed { body, .. } => Some(body),
+                _ => None,
+            }));
+
+            if !world.contains(rocket) {
+                break;
+            }
+        }
+
+        // Only the prop with too little health is destroyed; bodies without health are not
+        assert_eq!(destroyed, [barrel]);
+        assert!(!world.contains(barrel));
+        assert!(world.contains(locker));
+        assert!(world.contains(rock));
+    }
+
+    #[test]
+    pub fn shots_damage_by_difficulty() {
+        let (nav_mesh, _) = floor();
+        let mut world = World::default();
+        let player = world.spawn_player(nav_mesh.locate(Vec3::ZERO));
+        let enemy = world.spawn_player(nav_mesh.locate(vec3(10.0, 0.0, 0.0)));
+        let barrel = world.spawn_body(RigidBody::new(0.25, 5.0), transform(vec3(5.0, 0.25, 0.0)));
+        world.set_prop_health(barrel, 10.0);
+
+        let targets = world.shot_targets(player);
+
+        assert_eq!(targets.len(), 2);
+        assert!(targets.iter().all(|&(victim, _)| victim != Some(player)));
+
+        let table = DifficultyTable {
+            difficulties: HashMap::from([(
+                Difficulty::Hard,
+                DifficultyRules {
+                    enemy_health: 4.0,
+                    ..Default::default()
+                },
+            )]),
+        };
+        let mut rules = GameRules::new(table, player);
+        rules.set_difficulty(Difficulty::Hard);
+
+        let mut events = vec![];
+        world.shoot(&rules, player, enemy, 20.0, Vec3::X, &mut events);
+        world.shoot(&rules, player, barrel, 20.0, Vec3::X, &mut events);
+
+        // Enemies take damage scaled by the difficulty, but props do not
+        assert_eq!(
+            world.player(enemy).unwrap().inventory.health,
+            Inventory::MAX_HEALTH - 5
+        );
+        assert!(events.contains(&WorldEvent::Destroyed {
+            body: barrel,
+            position: vec3(5.0, 0.25, 0.0),
+        }));
+        assert!(!world.contains(barrel));
+    }
+
+    #[test]
+    pub fn projectiles_detonate_with_splash() {
+        let (mut nav_mesh, _) = floor();
+        let collision = CollisionMesh::new(
+            &[0, 1, 3, 0, 3, 2],
+            &[
+                vec3(-20.0, 0.0, -20.0),
+                vec3(20.0, 0.0, -20.0),
+                vec3(-20.0, 0.0, 20.0),
+                vec3(20.0, 0.0, 20.0),
+            ],
+        );
+        let mut world = World::default();
+        let shooter = world.spawn_player(nav_mesh.locate(vec3(0.0, 0.0, 5.0)));
+        let target = world.spawn_player(nav_mesh.locate(vec3(0.0, 0.0, -5.0)));
+        let body_height = Player::EYE_OFFSET.y * 0.5;
+        let mut events = vec![];
+        let detonations = |events: &[WorldEvent]| {
+            events
+                .iter()
+                .filter_map(|event| match event {
+                    WorldEvent::Detonated {
+                        kind, projectile, ..
+                    } => Some((*kind, *projectile)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut step = |world: &mut World, events: &mut Vec<WorldEvent>| {
+            events.clear();
+            world.step(
+                &mut nav_mesh,
+                &collision,
+                &Default::default(),
+                &GameRules::default(),
+                1.0 / 60.0,
+                events,
+            );
+        };
+
+        // A rocket aimed at the target detonates before reaching it, and the shooter is too far
+        // away to be hurt
+        let rocket = world.spawn_projectile(
+            ProjectileKind::Rocket,
+            shooter,
+            Ray::new(vec3(0.0, body_height, 4.0), -Vec3::Z),
+        );
+
+        for _ in 0..60 {
+            step(&mut world, &mut events);
+
+            if !world.contains(rocket) {
+                break;
+            }
+        }
+
+        assert!(!world.contains(rocket));
+        assert_eq!(detonations(&events), [(ProjectileKind::Rocket, rocket)]);
+        assert!(events.iter().any(|event| matches!(
+            event,
+            WorldEvent::Damaged { victim, .. } if *victim == target
+        )));
+        assert!(world.player(target).unwrap().inventory.health < Inventory::MAX_HEALTH);
+        assert_eq!(
+            world.player(shooter).unwrap().inventory.health,
+            Inventory::MAX_HEALTH
+        );
+
+        // A grenade bounces along the floor until its fuse runs out
+        let grenade = world.spawn_projectile(
+            ProjectileKind::Grenade,
+            shooter,
+            Ray::new(vec3(0.0, 1.0, 5.0), vec3(1.0, -1.0, 0.0).normalize()),
+        );
+        let mut steps = 0;
+
+        while world.contains(grenade) {
+            assert!(world.transforms[&grenade].position.y > 0.0);
+
+            step(&mut world, &mut events);
+            steps += 1;
+        }
+
+        assert!(steps as f32 >= ProjectileKind::Grenade.info().fuse * 60.0 - 1.0);
+        assert_eq!(detonations(&events), [(ProjectileKind::Grenade, grenade)]);
+
+        // Bringing the target to zero health kills them once
+        world.player_mut(target).unwrap().inventory.health = 1;
+        let rocket = world.spawn_projectile(
+            ProjectileKind::Rocket,
+            shooter,
+            Ray::new(vec3(0.0, body_height, 4.0), -Vec3::Z),
+        );
+        let mut kills = vec![];
+
+        for _ in 0..60 {
+            step(&mut world, &mut events);
+            kills.extend(
+                events
+                    .iter()
+                    .filter(|event| matches!(event, WorldEvent::Killed { .. }))
+                    .cloned(),
+            );
+
+            if !world.contains(rocket) {
+                break;
+            }
+        }
+
+        assert_eq!(
+            kills,
+            [WorldEvent::Killed {
+                killer: shooter,
+                player: target,
+            }]
+        );
+    }
+
+    #[test]
+    pub fn climb_ladder() {
+        let vertices = [
+            vec3(-10.0, 0.0, -10.0),
+            vec3(10.0, 0.0, -10.0),
+            vec3(-10.0, 0.0, 10.0),
+            vec3(10.0, 0.0, 10.0),
+            vec3(-10.0, 5.0, -30.0),
+            vec3(10.0, 5.0, -30.0),
+            vec3(-10.0, 5.0, -11.0),
+            vec3(10.0, 5.0, -11.0),
+        ];
+        let indices = [0, 1, 3, 0, 3, 2, 4, 5, 7, 4, 7, 6];
+        let mut nav_mesh = NavigationMesh::new(&indices, &vertices);
+        nav_mesh.add_link(
+            LinkKind::Ladder,
+            vec3(0.0, 0.0, -9.5),
+            vec3(0.0, 5.0, -11.5),
+        );
+
+        let collision = CollisionMesh::new(&[], &[]);
+        let mut world = World::default();
+        let player = world.spawn_player(nav_mesh.locate(vec3(0.0, 0.0, -9.0)));
+        let inputs = BTreeMap::from([(
+            player,
+            PlayerInput {
+                movement: Vec2::Y,
+                ..Default::default()
+            },
+        )]);
+        let mut events = vec![];
+
+        for _ in 0..10 {
+            world.step(
+                &mut nav_mesh,
+                &collision,
+                &inputs,
+                &GameRules::default(),
+                0.1,
+                &mut events,
+            );
+        }
+
+        // Part way up, the player is between the floors
+        let climbing = world.player(player).unwrap();
+
+        assert!(climbing.traversal.is_some());
+        assert!(climbing.position().y > 0.0 && climbing.position().y < 5.0);
+
+        for _ in 0..30 {
+            world.step(
+                &mut nav_mesh,
+                &collision,
+                &inputs,
+                &GameRules::default(),
+                0.1,
+                &mut events,
+            );
+        }
+
+        let position = world.player(player).unwrap().position();
+
+        assert!(world.player(player).unwrap().traversal.is_none());
+        assert!((position.y - 5.0).abs() < 1e-3);
+        assert!(position.z < -11.5);
+    }
+
+    #[test]
+    pub fn ride_lift() {
+        let (mut nav_mesh, collision) = floor();
+        let mut world = World::default();
+        let lift = world.spawn_mover(
+            EntityKind::Lift {
+                offset: vec3(0.0, 4.0, 0.0),
+                pause_secs: 2.0,
+            },
+            transform(Vec3::ZERO),
+        );
+        let player = world.spawn_player(nav_mesh.locate(Vec3::ZERO));
+        let mut events = vec![];
+        let mut step = |world: &mut World, inputs: &BTreeMap<EntityId, PlayerInput>| {
+            world.step(
+                &mut nav_mesh,
+                &collision,
+                inputs,
+                &GameRules::default(),
+                1.0,
+                &mut events,
+            );
+        };
+
+        // The player rises with the lift, standing on top of it
+        for _ in 0..4 {
+            step(&mut world, &Default::default());
+
+            let lift_y = world.transform(lift).unwrap().position.y;
+            let player_y = world.player(player).unwrap().position().y;
+
+            assert!(lift_y > 0.0);
+            assert!((player_y - lift_y).abs() < 1e-5);
+            assert_eq!(world.transform(player).unwrap().position.y, player_y);
+        }
+
+        assert!((world.player(player).unwrap().position().y - 4.0).abs() < 1e-5);
+
+        // Walking off the raised lift lands on the floor, the only walkable region
+        let inputs = BTreeMap::from([(
+            player,
+            PlayerInput {
+                movement: Vec2::Y,
+                ..Default::default()
+            },
+        )]);
+        step(&mut world, &inputs);
+
+        let position = world.player(player).unwrap().position();
+
+        assert_eq!(world.player(player).unwrap().elevation, 0.0);
+        assert_eq!(position.y, 0.0);
+        assert!(position.length() > 1.5);
+    }
+
+    #[test]
+    pub fn interpolate_and_serialize() {
+        let (mut nav_mesh, collision) = floor();
+        let mut world = World::default();
+        let player = world.spawn_player(nav_mesh.locate(vec3(5.0, 0.0, 0.0)));
+        let inputs = BTreeMap::from([(
+            player,
+            PlayerInput {
+                movement: Vec2::Y,
+                ..Default::default()
+            },
+        )]);
+        let mut events = vec![];
+
+        world.step(
+            &mut nav_mesh,
+            &collision,
+            &inputs,
+            &GameRules::default(),
+            0.25,
+            &mut events,
+        );
+
+        // Facing -Z, walking forwards for a quarter second moves one meter
+        let position = world.player(player).unwrap().location.position();
+
+        assert!((position - vec3(5.0, 0.0, -1.0)).length() < 1e-3);
+
+        let (_, halfway) = world.interpolate(0.5).next().unwrap();
+
+        assert!((halfway.position - vec3(5.0, 0.0, -0.5)).length() < 1e-3);
+
+        let saved = bincode::serialize(&world).unwrap();
+        let loaded = bincode::deserialize::<World>(&saved).unwrap();
+
+        assert_eq!(loaded.player(player), world.player(player));
+        assert_eq!(loaded.transforms, world.transforms);
+    }
+}
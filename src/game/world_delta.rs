@@ -0,0 +1,121 @@
+//! Changes which players make to levels, such as collecting pickups, which are recorded in save
+//! games and applied when a level is entered again so that backtracking through levels does not
+//! respawn everything.
+//!
+//! Changes are recorded by the name of the scene ref each entity was spawned from, which unlike
+//! an [`EntityId`](super::world::EntityId) is the same each time the level is loaded.
+
+use {
+    serde::{Deserialize, Serialize},
+    std::collections::BTreeMap,
+};
+
+/// How an entity spawned from a scene ref has changed.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum WorldChange {
+    /// A pickup was collected, so it is not spawned again.
+    Collected,
+
+    /// A destructible prop was destroyed, so it is not spawned again.
+    Destroyed,
+
+    /// A door was opened, so it is latched open.
+    Opened,
+}
+
+/// The changes made to one level, by scene ref name.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct WorldDelta {
+    changes: BTreeMap<String, WorldChange>,
+}
+
+impl WorldDelta {
+    /// Returns how the entity spawned from the named scene ref has changed, if at all.
+    pub fn change(&self, name: &str) -> Option<WorldChange> {
+        self.changes.get(name).copied()
+    }
+}
+
+/// The changes made to each level of the game in progress, by scene key.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct WorldDeltas {
+    levels: BTreeMap<String, WorldDelta>,
+}
+
+impl WorldDeltas {
+    /// Adds the changes of `other`, such as those of an earlier session read from its autosave.
+    pub fn extend(&mut self, other: Self) {
+        for (scene, delta) in other.levels {
+            self.levels
+                .entry(scene)
+                .or_default()
+                .changes
+                .extend(delta.changes);
+        }
+    }
+
+    /// Returns the changes made to the given scene, if any.
+    pub fn level(&self, scene: &str) -> Option<&WorldDelta> {
+        self.levels.get(scene)
+    }
+
+    /// Records that the entity spawned from the named scene ref of a scene has changed; a later
+    /// change of the same entity replaces an earlier one.
+    pub fn record(&mut self, scene: &str, name: &str, change: WorldChange) {
+        self.levels
+            .entry(scene.to_owned())
+            .or_default()
+            .changes
+            .insert(name.to_owned(), change);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn record_changes_per_level() {
+        let mut deltas = WorldDeltas::default();
+        deltas.record("scene/level_01", "pickup_health_a", WorldChange::Collected);
+        deltas.record("scene/level_01", "door_hub", WorldChange::Opened);
+        deltas.record("scene/level_02", "prop_barrel_a", WorldChange::Destroyed);
+
+        let level_01 = deltas.level("scene/level_01").unwrap();
+
+        assert_eq!(
+            level_01.change("pickup_health_a"),
+            Some(WorldChange::Collected)
+        );
+        assert_eq!(level_01.change("door_hub"), Some(WorldChange::Opened));
+        assert_eq!(level_01.change("prop_barrel_a"), None);
+        assert_eq!(
+            deltas
+                .level("scene/level_02")
+                .and_then(|level| level.change("prop_barrel_a")),
+            Some(WorldChange::Destroyed)
+        );
+        assert!(deltas.level("scene/level_03").is_none());
+
+        let mut earlier = WorldDeltas::default();
+        earlier.record("scene/level_01", "pickup_ammo_a", WorldChange::Collected);
+        deltas.extend(earlier);
+
+        assert_eq!(
+            deltas
+                .level("scene/level_01")
+                .and_then(|level| level.change("pickup_ammo_a")),
+            Some(WorldChange::Collected)
+        );
+        assert_eq!(
+            deltas
+                .level("scene/level_01")
+                .and_then(|level| level.change("door_hub")),
+            Some(WorldChange::Opened)
+        );
+
+        let saved = bincode::serialize(&deltas).unwrap();
+
+        assert_eq!(bincode::deserialize::<WorldDeltas>(&saved).unwrap(), deltas);
+    }
+}
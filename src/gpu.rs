@@ -0,0 +1,127 @@
+use {crate::render::capabilities::DeviceCapabilities, screen_13::prelude::*};
+
+/// Returns the index of the physical device to use: the one given by `gpu` as either an index or
+/// part of its name, otherwise the most capable kind of device, preferring those which support
+/// ray tracing.
+///
+/// Ray tracing is judged as the renderer would use it, so portability-subset drivers which report
+/// it are not preferred for it.
+pub fn select_physical_device(physical_devices: &[PhysicalDevice], gpu: Option<&str>) -> usize {
+    for (index, physical_device) in physical_devices.iter().enumerate() {
+        info!(
+            "GPU {index}: {} ({:?}, ray tracing {})",
+            physical_device.properties_v1_0.device_name,
+            physical_device.properties_v1_0.device_type,
+            if DeviceCapabilities::from_physical_device(physical_device).ray_tracing {
+                "supported"
+            } else {
+                "unsupported"
+            },
+        );
+    }
+
+    let adapters = physical_devices
+        .iter()
+        .map(|physical_device| Adapter {
+            device_type: physical_device.properties_v1_0.device_type,
+            name: &physical_device.properties_v1_0.device_name,
+            ray_tracing: DeviceCapabilities::from_physical_device(physical_device).ray_tracing,
+        })
+        .collect::<Box<_>>();
+    let index = select_adapter(&adapters, gpu);
+
+    if let Some(adapter) = adapters.get(index) {
+        info!("Using GPU {index}: {}", adapter.name);
+    }
+
+    index
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Adapter<'a> {
+    device_type: vk::PhysicalDeviceType,
+    name: &'a str,
+    ray_tracing: bool,
+}
+
+fn select_adapter(adapters: &[Adapter], gpu: Option<&str>) -> usize {
+    if let Some(gpu) = gpu.map(str::trim).filter(|gpu| !gpu.is_empty()) {
+        if let Ok(index) = gpu.parse::<usize>() {
+            if index < adapters.len() {
+                return index;
+            }
+
+            warn!("GPU {index} not found");
+        } else {
+            let gpu = gpu.to_lowercase();
+
+            if let Some(index) = adapters
+                .iter()
+                .position(|adapter| adapter.name.to_lowercase().contains(&gpu))
+            {
+                return index;
+            }
+
+            warn!("No GPU named {gpu}");
+        }
+    }
+
+    let device_type_rank = |device_type| match device_type {
+        vk::PhysicalDeviceType::DISCRETE_GPU => 3,
+        vk::PhysicalDeviceType::INTEGRATED_GPU => 2,
+        vk::PhysicalDeviceType::VIRTUAL_GPU => 1,
+        _ => 0,
+    };
+
+    // Keeps the first of equally ranked adapters, which is the one the platform lists first
+    adapters
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, adapter)| (adapter.ray_tracing, device_type_rank(adapter.device_type)))
+        .map(|(index, _)| index)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADAPTERS: [Adapter; 3] = [
+        Adapter {
+            device_type: vk::PhysicalDeviceType::INTEGRATED_GPU,
+            name: "Intel(R) UHD Graphics 630",
+            ray_tracing: false,
+        },
+        Adapter {
+            device_type: vk::PhysicalDeviceType::DISCRETE_GPU,
+            name: "NVIDIA GeForce RTX 3060 Laptop GPU",
+            ray_tracing: true,
+        },
+        Adapter {
+            device_type: vk::PhysicalDeviceType::CPU,
+            name: "llvmpipe (LLVM 15.0.7, 256 bits)",
+            ray_tracing: false,
+        },
+    ];
+
+    #[test]
+    pub fn select_gpu() {
+        assert_eq!(select_adapter(&ADAPTERS, None), 1);
+        assert_eq!(select_adapter(&ADAPTERS, Some("0")), 0);
+        assert_eq!(select_adapter(&ADAPTERS, Some("intel")), 0);
+        assert_eq!(select_adapter(&ADAPTERS, Some(" LLVMpipe ")), 2);
+
+        // Unknown GPUs fall back to the default
+        assert_eq!(select_adapter(&ADAPTERS, Some("3")), 1);
+        assert_eq!(select_adapter(&ADAPTERS, Some("radeon")), 1);
+        assert_eq!(select_adapter(&[], None), 0);
+
+        // Without ray tracing, discrete GPUs are preferred
+        let adapters = ADAPTERS.map(|adapter| Adapter {
+            ray_tracing: false,
+            ..adapter
+        });
+        assert_eq!(select_adapter(&adapters, None), 1);
+    }
+}
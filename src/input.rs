@@ -0,0 +1,299 @@
+use {
+    crate::demo::KEYS,
+    gilrs::{Button, EventType, Gilrs},
+    glam::Vec2,
+    screen_13::prelude::*,
+    serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer},
+};
+
+/// Extra mouse buttons, usually found on the side of the mouse.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ExtraButton {
+    Back,
+    Forward,
+}
+
+impl ExtraButton {
+    fn from_mouse_button(button: MouseButton) -> Option<Self> {
+        // Platforms number these buttons differently: X11 and Wayland report 8 and 9, while
+        // Windows and macOS report the first two buttons after middle
+        match button {
+            MouseButton::Other(3 | 8) => Some(Self::Back),
+            MouseButton::Other(4 | 9) => Some(Self::Forward),
+            _ => None,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Self::Back => 0,
+            Self::Forward => 1,
+        }
+    }
+}
+
+/// A key which the player binds to an action in the config, written by the name of the key, such as
+/// `"F5"`; only keys which demos record may be bound, so that bound actions replay.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct KeyBinding(pub VirtualKeyCode);
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+
+        KEYS.iter()
+            .copied()
+            .find(|key| format!("{key:?}") == name)
+            .map(Self)
+            .ok_or_else(|| D::Error::custom(format!("Unbindable key {name}")))
+    }
+}
+
+impl Serialize for KeyBinding {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&format!("{:?}", self.0))
+    }
+}
+
+/// Gamepad buttons used to navigate menus; other buttons are ignored.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum GamepadButton {
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+
+    /// The bottom face button: A on Xbox controllers and Cross on PlayStation controllers.
+    South,
+
+    /// The right face button: B on Xbox controllers and Circle on PlayStation controllers.
+    East,
+}
+
+impl GamepadButton {
+    const COUNT: usize = 6;
+
+    fn from_button(button: Button) -> Option<Self> {
+        match button {
+            Button::DPadUp => Some(Self::DPadUp),
+            Button::DPadDown => Some(Self::DPadDown),
+            Button::DPadLeft => Some(Self::DPadLeft),
+            Button::DPadRight => Some(Self::DPadRight),
+            Button::South => Some(Self::South),
+            Button::East => Some(Self::East),
+            _ => None,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as _
+    }
+}
+
+/// Buttons pressed on any connected gamepad.
+#[derive(Debug, Default)]
+pub struct GamepadBuf {
+    pressed: [bool; GamepadButton::COUNT],
+}
+
+impl GamepadBuf {
+    /// Returns `true` if the button was pressed on any gamepad during the current frame.
+    pub fn is_pressed(&self, button: GamepadButton) -> bool {
+        self.pressed[button.index()]
+    }
+}
+
+/// Updates `gamepad` with the gamepad events received since the previous frame; call once per
+/// frame alongside [`update_input`]. Without `gilrs` (gamepads are unsupported on this platform
+/// or input is being replayed) no buttons are pressed.
+pub fn update_gamepad(gamepad: &mut GamepadBuf, gilrs: Option<&mut Gilrs>) {
+    gamepad.pressed = Default::default();
+
+    let Some(gilrs) = gilrs else {
+        return;
+    };
+
+    while let Some(event) = gilrs.next_event() {
+        if let EventType::ButtonPressed(button, _) = event.event {
+            if let Some(button) = GamepadButton::from_button(button) {
+                gamepad.pressed[button.index()] = true;
+            }
+        }
+    }
+}
+
+/// Mouse input which is not tracked by [`MouseBuf`]: raw motion, the scroll wheel and extra
+/// buttons.
+#[derive(Debug, Default)]
+pub struct MouseExtraBuf {
+    down: [bool; 2],
+    has_raw_motion: bool,
+    pressed: [bool; 2],
+    raw_motion: Vec2,
+    released: [bool; 2],
+    wheel_delta: f32,
+}
+
+impl MouseExtraBuf {
+    /// Number of pixels of touchpad scrolling treated as a single line of wheel movement.
+    const PIXELS_PER_LINE: f32 = 20.0;
+
+    /// Returns `true` once the platform has reported raw mouse motion, which some platforms and
+    /// remote desktop sessions never do.
+    pub fn has_raw_motion(&self) -> bool {
+        self.has_raw_motion
+    }
+
+    pub fn is_down(&self, button: ExtraButton) -> bool {
+        self.down[button.index()]
+    }
+
+    /// Returns `true` if the button was pressed during the current frame.
+    pub fn is_pressed(&self, button: ExtraButton) -> bool {
+        self.pressed[button.index()]
+    }
+
+    /// Returns `true` if the button was released during the current frame.
+    pub fn is_released(&self, button: ExtraButton) -> bool {
+        self.released[button.index()]
+    }
+
+    /// Unaccelerated mouse movement, in device counts, accumulated during the current frame;
+    /// unlike cursor positions this is not limited by the window or affected by cursor warps.
+    pub fn raw_motion(&self) -> Vec2 {
+        self.raw_motion
+    }
+
+    /// Vertical scroll wheel movement, in lines, accumulated during the current frame; positive
+    /// values scroll up (away from the user).
+    pub fn wheel_delta(&self) -> f32 {
+        self.wheel_delta
+    }
+
+    fn handle_button(&mut self, button: ExtraButton, state: ElementState) {
+        let index = button.index();
+
+        match state {
+            ElementState::Pressed => {
+                self.down[index] = true;
+                self.pressed[index] = true;
+            }
+            ElementState::Released => {
+                self.down[index] = false;
+                self.released[index] = true;
+            }
+        }
+    }
+}
+
+/// Updates `mouse_extra` with the events of the current frame; call once per frame alongside
+/// [`update_input`].
+pub fn update_mouse_extra(mouse_extra: &mut MouseExtraBuf, events: &[Event<()>]) {
+    mouse_extra.pressed = Default::default();
+    mouse_extra.released = Default::default();
+    mouse_extra.raw_motion = Vec2::ZERO;
+    mouse_extra.wheel_delta = 0.0;
+
+    for event in events {
+        if let Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta: (x, y) },
+            ..
+        } = event
+        {
+            mouse_extra.has_raw_motion = true;
+            mouse_extra.raw_motion += Vec2::new(*x as f32, *y as f32);
+        }
+
+        if let Event::WindowEvent { event, .. } = event {
+            match event {
+                WindowEvent::MouseInput { button, state, .. } => {
+                    if let Some(button) = ExtraButton::from_mouse_button(*button) {
+                        mouse_extra.handle_button(button, *state);
+                    }
+                }
+                WindowEvent::MouseWheel { delta, .. } => {
+                    mouse_extra.wheel_delta += match delta {
+                        MouseScrollDelta::LineDelta(_, y) => *y,
+                        MouseScrollDelta::PixelDelta(position) => {
+                            position.y as f32 / MouseExtraBuf::PIXELS_PER_LINE
+                        }
+                    };
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Turns per-frame mouse movement into look movement, optionally smoothed over several frames and
+/// accelerated when the mouse moves quickly.
+#[derive(Debug, Default)]
+pub struct MouseLook {
+    smoothed: Vec2,
+}
+
+impl MouseLook {
+    /// Frame duration which `smoothing` is given in relation to, so that smoothing feels the same
+    /// at any framerate.
+    const SMOOTHING_SECS: f32 = 1.0 / 60.0;
+
+    /// Returns the look movement for the given mouse movement, which is in window widths.
+    ///
+    /// `smoothing` is the fraction of the previous movement kept each 60th of a second, where zero
+    /// disables smoothing, and `acceleration` is the extra gain for each window width per second of
+    /// mouse speed, where zero disables acceleration.
+    pub fn update(&mut self, delta: Vec2, dt: f32, smoothing: f32, acceleration: f32) -> Vec2 {
+        let delta = if acceleration > 0.0 && dt > 0.0 {
+            let speed = delta.length() / dt;
+
+            delta * (1.0 + acceleration * speed)
+        } else {
+            delta
+        };
+
+        if smoothing <= 0.0 {
+            self.smoothed = delta;
+
+            return delta;
+        }
+
+        let keep = smoothing.clamp(0.0, 0.95).powf(dt / Self::SMOOTHING_SECS);
+        self.smoothed = delta.lerp(self.smoothed, keep);
+
+        self.smoothed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, glam::vec2};
+
+    #[test]
+    pub fn mouse_look() {
+        let mut look = MouseLook::default();
+        let delta = vec2(0.01, -0.02);
+
+        assert_eq!(look.update(delta, 0.01, 0.0, 0.0), delta);
+
+        // Smoothing spreads a single movement over the following frames
+        let mut look = MouseLook::default();
+        let first = look.update(delta, 0.01, 0.5, 0.0);
+
+        assert!(first.x < delta.x);
+        assert!(look.update(Vec2::ZERO, 0.01, 0.5, 0.0).x > 0.0);
+
+        // Faster movement is accelerated more
+        let mut look = MouseLook::default();
+        let slow = look.update(delta, 0.1, 0.0, 1.0);
+        let fast = look.update(delta, 0.01, 0.0, 1.0);
+
+        assert!(slow.x > delta.x);
+        assert!(fast.x > slow.x);
+    }
+}
@@ -0,0 +1,158 @@
+//! Checks the `.pak` files against the manifest of keys and content hashes which `build.rs`
+//! records into the generated bindings, so that damaged or mismatched content is reported before
+//! loading instead of failing partway through with `InvalidData`.
+//!
+//! This module is also compiled by `build.rs` and so may only depend on `bincode`, `pak` and
+//! `serde`.
+
+use {
+    pak::{Pak, PakBuf},
+    serde::Serialize,
+    std::{
+        fmt::{Display, Formatter, Result as FmtResult},
+        io::{Error, ErrorKind},
+    },
+};
+
+/// The kind of asset a manifest key names, which decides how it is read.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AssetKind {
+    Bitmap,
+    Blob,
+    Font,
+    Material,
+    Model,
+    Scene,
+}
+
+impl AssetKind {
+    /// Returns the kind of the asset with the given key, or `None` if the pak does not have it.
+    pub fn of(pak: &PakBuf, key: &str) -> Option<Self> {
+        if pak.bitmap_font_id(key).is_some() {
+            Some(Self::Font)
+        } else if pak.bitmap_id(key).is_some() {
+            Some(Self::Bitmap)
+        } else if pak.material_id(key).is_some() {
+            Some(Self::Material)
+        } else if pak.model_id(key).is_some() {
+            Some(Self::Model)
+        } else if pak.scene_id(key).is_some() {
+            Some(Self::Scene)
+        } else if pak.blob_id(key).is_some() {
+            Some(Self::Blob)
+        } else {
+            None
+        }
+    }
+}
+
+/// A key of a pak as it was when the pak was built.
+#[derive(Clone, Copy, Debug)]
+pub struct ManifestEntry {
+    pub key: &'static str,
+    pub kind: AssetKind,
+
+    /// See [`read_hash`].
+    pub hash: u64,
+}
+
+impl ManifestEntry {
+    /// Used by the generated bindings.
+    pub const fn new(key: &'static str, kind: AssetKind, hash: u64) -> Self {
+        Self { key, kind, hash }
+    }
+}
+
+/// What is wrong with a key of a pak.
+#[derive(Debug)]
+pub enum Problem {
+    /// The key cannot be read, or its content differs from when the pak was built.
+    Corrupted { key: &'static str, reason: String },
+
+    /// The pak has no asset of the expected kind with the key.
+    Missing { key: &'static str },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Corrupted { key, reason } => write!(f, "{key} is corrupted ({reason})"),
+            Self::Missing { key } => write!(f, "{key} is missing"),
+        }
+    }
+}
+
+/// FNV-1a, which is small enough to share with `build.rs`; it detects damage, not tampering.
+pub fn hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Returns the hash of an asset as it is serialized, for assets which are not plain bytes.
+fn hash_serialized(asset: &impl Serialize) -> Result<u64, Error> {
+    bincode::serialize(asset)
+        .map(|data| hash(&data))
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+}
+
+/// Reads an asset and returns the hash of its content: the bytes of blobs, the pixels of bitmaps
+/// and font pages, and the serialized bytes of materials, models and scenes.
+pub fn read_hash(pak: &mut PakBuf, key: &str, kind: AssetKind) -> Result<u64, Error> {
+    Ok(match kind {
+        AssetKind::Bitmap => hash(pak.read_bitmap(key)?.pixels()),
+        AssetKind::Blob => hash(&pak.read_blob(key)?),
+        AssetKind::Font => pak
+            .read_bitmap_font(key)?
+            .pages()
+            .iter()
+            .fold(0, |res, page| res ^ hash(page.pixels()).rotate_left(1)),
+        AssetKind::Material => hash_serialized(&pak.read_material(key)?)?,
+        AssetKind::Model => hash_serialized(&pak.read_model(key)?)?,
+        AssetKind::Scene => hash_serialized(&pak.read_scene(key)?)?,
+    })
+}
+
+/// Checks that the pak has every key of the manifest, as the same kind of asset, and when
+/// `read_contents` is set also reads each asset and compares the hash of its content.
+pub fn verify(pak: &mut PakBuf, manifest: &[ManifestEntry], read_contents: bool) -> Vec<Problem> {
+    let mut problems = vec![];
+
+    for entry in manifest {
+        if AssetKind::of(pak, entry.key) != Some(entry.kind) {
+            problems.push(Problem::Missing { key: entry.key });
+
+            continue;
+        }
+
+        if !read_contents {
+            continue;
+        }
+
+        match read_hash(pak, entry.key, entry.kind) {
+            Ok(hash) if hash == entry.hash => (),
+            Ok(_) => problems.push(Problem::Corrupted {
+                key: entry.key,
+                reason: "content differs".to_owned(),
+            }),
+            Err(err) => problems.push(Problem::Corrupted {
+                key: entry.key,
+                reason: err.to_string(),
+            }),
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn fnv1a() {
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(hash(b"ab"), hash(b"ba"));
+    }
+}
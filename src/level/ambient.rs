@@ -0,0 +1,152 @@
+use {
+    super::scene::Scene,
+    crate::{art, asset_key::SoundKey, audio::spatial_mix},
+    glam::Vec3,
+    log::warn,
+    std::path::Path,
+};
+
+/// A looping sound placed in the level, such as the hum of a generator.
+#[derive(Clone, Copy, Debug)]
+pub struct AmbientEmitter {
+    pub position: Vec3,
+
+    /// Distance, in meters, beyond which the sound cannot be heard.
+    pub radius: f32,
+
+    pub sound: SoundKey,
+
+    /// Amplitude of the sound at the emitter.
+    pub volume: f32,
+}
+
+impl AmbientEmitter {
+    pub const DEFAULT_RADIUS: f32 = 10.0;
+
+    /// Returns the amplitude and the panning (`0.0` is left, `1.0` is right) of this sound as heard
+    /// by a listener at the given position, whose right is the given direction.
+    pub fn mix(&self, listener_position: Vec3, listener_right: Vec3) -> (f32, f32) {
+        spatial_mix(
+            self.position,
+            self.radius,
+            self.volume,
+            listener_position,
+            listener_right,
+        )
+    }
+}
+
+/// The ambient sounds of a level.
+///
+/// Emitters are scene refs prefixed with [`AmbientEmitters::PREFIX`] which play the ambient sound
+/// named after the prefix, for example `Ambient_hum` plays `sound/ambient/hum.ogg`; the sound may
+/// instead be set using the `sound` property, and the `radius` and `volume` properties set how far
+/// away and how loud it is heard.
+#[derive(Debug, Default)]
+pub struct AmbientEmitters {
+    emitters: Vec<AmbientEmitter>,
+}
+
+impl AmbientEmitters {
+    pub const PREFIX: &str = "Ambient";
+
+    const SOUND_DIR: &str = "sound/ambient/";
+
+    pub fn from_scene(scene: &Scene) -> Self {
+        let mut res = Self::default();
+
+        for (scene_ref, id) in scene.refs_prefixed(Self::PREFIX) {
+            // Blender names copies of an object with a numbered suffix, such as `Ambient_hum.001`
+            let name = id.property::<String>("sound").unwrap_or_else(|| {
+                id.name[Self::PREFIX.len()..]
+                    .trim_start_matches('_')
+                    .split('.')
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned()
+            });
+
+            if let Some(sound) = Self::find_sound(&name) {
+                res.insert(AmbientEmitter {
+                    position: scene_ref.position(),
+                    radius: id
+                        .property("radius")
+                        .unwrap_or(AmbientEmitter::DEFAULT_RADIUS),
+                    sound,
+                    volume: id.property("volume").unwrap_or(1.0),
+                });
+            } else {
+                warn!(
+                    "Ignoring ambient emitter {} with unknown sound {name}",
+                    id.name
+                );
+            }
+        }
+
+        res
+    }
+
+    fn find_sound(name: &str) -> Option<SoundKey> {
+        Self::sounds().find(|sound| {
+            Path::new(sound.as_str())
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| stem.eq_ignore_ascii_case(name))
+        })
+    }
+
+    pub fn insert(&mut self, emitter: AmbientEmitter) {
+        self.emitters.push(emitter);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &AmbientEmitter> {
+        self.emitters.iter()
+    }
+
+    /// Returns every ambient sound, which are the art sounds named `sound/ambient/*`; these are
+    /// loaded along with a level because the sounds of its emitters are not known until the scene
+    /// has loaded.
+    pub fn sounds() -> impl Iterator<Item = SoundKey> {
+        art::SOUNDS
+            .iter()
+            .copied()
+            .filter(|sound| sound.as_str().starts_with(Self::SOUND_DIR))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, glam::vec3};
+
+    #[test]
+    pub fn ambient_emitter_mix() {
+        let emitter = AmbientEmitter {
+            position: vec3(5.0, 0.0, 0.0),
+            radius: 10.0,
+            sound: art::SOUND_DIGITAL_THREE_TONE_1_OGG,
+            volume: 0.5,
+        };
+
+        // Standing on the emitter it is heard at full volume from both sides
+        let (volume, panning) = emitter.mix(emitter.position, Vec3::X);
+
+        assert_eq!(volume, 0.5);
+        assert_eq!(panning, 0.5);
+
+        // The emitter is to the right of a listener facing -Z
+        let (volume, panning) = emitter.mix(Vec3::ZERO, Vec3::X);
+
+        assert!(volume > 0.0 && volume < 0.5);
+        assert_eq!(panning, 1.0);
+
+        // ...and to the left of a listener facing +Z
+        let (_, panning) = emitter.mix(Vec3::ZERO, -Vec3::X);
+
+        assert_eq!(panning, 0.0);
+
+        // Beyond the radius it cannot be heard
+        let (volume, _) = emitter.mix(vec3(-6.0, 0.0, 0.0), Vec3::X);
+
+        assert_eq!(volume, 0.0);
+    }
+}
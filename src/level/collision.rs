@@ -0,0 +1,427 @@
+use {
+    super::{
+        nav_mesh::{closest_point_triangle, ClosestPoint},
+        scene::{read_geometry, Scene},
+        surface::Surface,
+    },
+    crate::math::{Aabb, Ray},
+    glam::Vec3,
+    log::warn,
+};
+
+#[derive(Clone, Copy, Debug)]
+struct Triangle {
+    surface: Surface,
+    vertices: [Vec3; 3],
+}
+
+impl Triangle {
+    fn aabb(self) -> Aabb {
+        Aabb::from_points(self.vertices).unwrap()
+    }
+
+    fn centroid(self) -> Vec3 {
+        (self.vertices[0] + self.vertices[1] + self.vertices[2]) / 3.0
+    }
+
+    fn closest_point(self, position: Vec3) -> Vec3 {
+        match closest_point_triangle(position, self.vertices) {
+            ClosestPoint::Edge(_, position) | ClosestPoint::Face(position) => position,
+            ClosestPoint::Vertex(idx) => self.vertices[idx],
+        }
+    }
+
+    fn normal(self) -> Vec3 {
+        let [a, b, c] = self.vertices;
+
+        (b - a).cross(c - a).normalize_or_zero()
+    }
+
+    /// Returns the distance along the ray to the triangle, which is hit from either side, using the
+    /// Möller–Trumbore algorithm.
+    fn intersect_ray(self, ray: Ray) -> Option<f32> {
+        const EPSILON: f32 = 1e-7;
+
+        let [a, b, c] = self.vertices;
+        let ab = b - a;
+        let ac = c - a;
+        let p = ray.normal().cross(ac);
+        let det = ab.dot(p);
+
+        if det.abs() < EPSILON {
+            return None;
+        }
+
+        let inv_det = det.recip();
+        let ap = ray.position() - a;
+        let u = ap.dot(p) * inv_det;
+
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = ap.cross(ab);
+        let v = ray.normal().dot(q) * inv_det;
+
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = ac.dot(q) * inv_det;
+
+        (distance >= 0.0).then_some(distance)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum NodeKind {
+    Branch { left: usize, right: usize },
+    Leaf { start: usize, end: usize },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    aabb: Aabb,
+    kind: NodeKind,
+}
+
+/// The closest point where a ray hit the collision mesh.
+#[derive(Clone, Copy, Debug)]
+pub struct RaycastHit {
+    pub distance: f32,
+
+    /// Surface normal facing the ray origin.
+    pub normal: Vec3,
+
+    pub position: Vec3,
+
+    pub surface: Surface,
+}
+
+/// Static triangle-soup collider of a level, kept in a bounding volume hierarchy.
+///
+/// Collision geometry is separate from render geometry so that level designers may use simple
+/// shapes for walls and leave out decorative detail which the player should not catch on.
+#[derive(Debug, Default)]
+pub struct CollisionMesh {
+    nodes: Vec<Node>,
+    triangles: Vec<Triangle>,
+}
+
+impl CollisionMesh {
+    /// Largest number of triangles stored in each leaf node.
+    const LEAF_SIZE: usize = 4;
+
+    /// Prefix of scene geometry names which become part of the collision mesh, for example
+    /// `Collision_walls`.
+    pub const PREFIX: &str = "Collision";
+
+    /// Number of times a sliding sphere is pushed out of intersecting triangles each move.
+    const SLIDE_ITERATIONS: usize = 4;
+
+    pub fn new(indices: &[u32], vertices: &[Vec3]) -> Self {
+        Self::from_triangles(
+            indices
+                .chunks_exact(3)
+                .map(|triangle| Triangle {
+                    surface: Surface::default(),
+                    vertices: [0, 1, 2].map(|idx| vertices[triangle[idx] as usize]),
+                })
+                .collect(),
+        )
+    }
+
+    fn from_triangles(mut triangles: Vec<Triangle>) -> Self {
+        triangles.retain(|triangle| triangle.normal() != Vec3::ZERO);
+
+        let mut nodes = vec![];
+
+        if !triangles.is_empty() {
+            let len = triangles.len();
+            Self::build_node(&mut nodes, &mut triangles, 0, len);
+        }
+
+        Self { nodes, triangles }
+    }
+
+    pub fn from_scene(scene: &Scene) -> Self {
+        Self::from_scene_prefixed(scene, Self::PREFIX)
+    }
+
+    /// Reads all scene geometry whose name starts with the given prefix; each geometry has the
+    /// surface given by its `kind` property.
+    fn from_scene_prefixed(scene: &Scene, prefix: &str) -> Self {
+        let mut triangles = vec![];
+
+        for (geom, id) in scene.geometries_prefixed(prefix) {
+            let (indices, vertices) = read_geometry(&geom);
+
+            if indices.is_empty() {
+                warn!("Ignoring empty geometry {}", id.name);
+
+                continue;
+            }
+
+            let surface = id.property("kind").unwrap_or_default();
+
+            triangles.extend(indices.chunks_exact(3).map(|triangle| Triangle {
+                surface,
+                vertices: [0, 1, 2].map(|idx| vertices[triangle[idx] as usize]),
+            }));
+        }
+
+        Self::from_triangles(triangles)
+    }
+
+    /// Reads the scene geometry which sets the surface of the ground; see [`Surface`].
+    pub fn surfaces_from_scene(scene: &Scene) -> Self {
+        Self::from_scene_prefixed(scene, Surface::PREFIX)
+    }
+
+    /// Sorts the given range of triangles into a subtree, returning the index of its root node.
+    fn build_node(
+        nodes: &mut Vec<Node>,
+        triangles: &mut [Triangle],
+        start: usize,
+        end: usize,
+    ) -> usize {
+        let aabb = triangles[start..end]
+            .iter()
+            .map(|triangle| triangle.aabb())
+            .reduce(Aabb::union)
+            .unwrap();
+        let node_index = nodes.len();
+        nodes.push(Node {
+            aabb,
+            kind: NodeKind::Leaf { start, end },
+        });
+
+        if end - start <= Self::LEAF_SIZE {
+            return node_index;
+        }
+
+        // Split at the median centroid along the longest axis of the centroid bounds
+        let centroids =
+            Aabb::from_points(triangles[start..end].iter().map(|t| t.centroid())).unwrap();
+        let extent = centroids.max() - centroids.min();
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        triangles[start..end]
+            .sort_unstable_by(|a, b| a.centroid()[axis].total_cmp(&b.centroid()[axis]));
+
+        let mid = (start + end) / 2;
+        let left = Self::build_node(nodes, triangles, start, mid);
+        let right = Self::build_node(nodes, triangles, mid, end);
+        nodes[node_index].kind = NodeKind::Branch { left, right };
+
+        node_index
+    }
+
+    /// Appends the indices of triangles whose bounds overlap the given box.
+    fn query(&self, aabb: Aabb, triangle_indices: &mut Vec<usize>) {
+        let mut stack = vec![];
+
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(node_index) = stack.pop() {
+            let node = self.nodes[node_index];
+
+            if !node.aabb.intersects(aabb) {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Branch { left, right } => stack.extend([left, right]),
+                NodeKind::Leaf { start, end } => triangle_indices.extend(
+                    (start..end).filter(|&idx| self.triangles[idx].aabb().intersects(aabb)),
+                ),
+            }
+        }
+    }
+
+    /// Returns the closest hit of the ray within `max_distance`.
+    pub fn raycast(&self, ray: Ray, max_distance: f32) -> Option<RaycastHit> {
+        let mut stack = vec![];
+        let mut closest: Option<(f32, Triangle)> = None;
+
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(node_index) = stack.pop() {
+            let node = self.nodes[node_index];
+            let max_distance = closest
+                .map(|(distance, _)| distance)
+                .unwrap_or(max_distance);
+
+            if !node
+                .aabb
+                .intersect_ray(ray)
+                .is_some_and(|distance| distance <= max_distance)
+            {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Branch { left, right } => stack.extend([left, right]),
+                NodeKind::Leaf { start, end } => {
+                    for triangle in self.triangles[start..end].iter().copied() {
+                        if let Some(distance) = triangle
+                            .intersect_ray(ray)
+                            .filter(|distance| *distance <= max_distance)
+                        {
+                            if closest.map_or(true, |(closest, _)| distance < closest) {
+                                closest = Some((distance, triangle));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        closest.map(|(distance, triangle)| {
+            let normal = triangle.normal();
+
+            RaycastHit {
+                distance,
+                normal: if normal.dot(ray.normal()) > 0.0 {
+                    -normal
+                } else {
+                    normal
+                },
+                position: ray.point_at(distance),
+                surface: triangle.surface,
+            }
+        })
+    }
+
+    /// Moves a sphere by `motion`, pushing it out of any triangles it would overlap so that it
+    /// slides along walls instead of passing through them, and returns the new position.
+    ///
+    /// Motion each call should be smaller than `radius` or thin walls may be skipped over.
+    pub fn slide(&self, position: Vec3, motion: Vec3, radius: f32) -> Vec3 {
+        let mut position = position + motion;
+        let mut triangle_indices = vec![];
+
+        for _ in 0..Self::SLIDE_ITERATIONS {
+            triangle_indices.clear();
+            self.query(
+                Aabb::from_center_half_extents(position, Vec3::splat(radius)),
+                &mut triangle_indices,
+            );
+
+            let mut pushed = false;
+
+            for triangle in triangle_indices.iter().map(|&idx| self.triangles[idx]) {
+                let offset = position - triangle.closest_point(position);
+                let distance = offset.length();
+
+                if distance >= radius {
+                    continue;
+                }
+
+                // A sphere centered on the surface is pushed out along the face normal
+                let direction = if distance > f32::EPSILON {
+                    offset / distance
+                } else {
+                    triangle.normal()
+                };
+
+                position += direction * (radius - distance);
+                pushed = true;
+            }
+
+            if !pushed {
+                break;
+            }
+        }
+
+        position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, glam::vec3};
+
+    /// A single wall on the plane x = 1, two units wide and tall.
+    fn wall() -> CollisionMesh {
+        let vertices = [
+            vec3(1.0, -1.0, -1.0),
+            vec3(1.0, -1.0, 1.0),
+            vec3(1.0, 1.0, 1.0),
+            vec3(1.0, 1.0, -1.0),
+        ];
+        let indices = [0, 1, 2, 0, 2, 3];
+
+        CollisionMesh::new(&indices, &vertices)
+    }
+
+    #[test]
+    pub fn raycast() {
+        let wall = wall();
+        let hit = wall.raycast(Ray::new(Vec3::ZERO, Vec3::X), 10.0).unwrap();
+
+        assert!((hit.distance - 1.0).abs() < 1e-5);
+        assert!(hit.normal.abs_diff_eq(-Vec3::X, 1e-5));
+        assert!(wall.raycast(Ray::new(Vec3::ZERO, Vec3::X), 0.5).is_none());
+        assert!(wall.raycast(Ray::new(Vec3::ZERO, -Vec3::X), 10.0).is_none());
+        assert!(wall
+            .raycast(Ray::new(vec3(0.0, 2.0, 0.0), Vec3::X), 10.0)
+            .is_none());
+    }
+
+    #[test]
+    pub fn raycast_many_triangles() {
+        // Grid of quads facing +Z so that the hierarchy has several levels
+        let mut indices = vec![];
+        let mut vertices = vec![];
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let base_vertex = vertices.len() as u32;
+                let (x, y) = (x as f32, y as f32);
+                vertices.extend([
+                    vec3(x, y, 0.0),
+                    vec3(x + 1.0, y, 0.0),
+                    vec3(x + 1.0, y + 1.0, 0.0),
+                    vec3(x, y + 1.0, 0.0),
+                ]);
+                indices.extend([0, 1, 2, 0, 2, 3].map(|idx| base_vertex + idx));
+            }
+        }
+
+        let mesh = CollisionMesh::new(&indices, &vertices);
+
+        assert!(mesh.nodes.len() > 1);
+
+        let hit = mesh
+            .raycast(Ray::new(vec3(6.5, 2.5, 5.0), -Vec3::Z), 10.0)
+            .unwrap();
+
+        assert!(hit.position.abs_diff_eq(vec3(6.5, 2.5, 0.0), 1e-5));
+    }
+
+    #[test]
+    pub fn slide_along_wall() {
+        let wall = wall();
+
+        // Walking diagonally into the wall keeps only the motion along it
+        let position = wall.slide(vec3(0.7, 0.0, 0.0), vec3(0.2, 0.0, 0.2), 0.25);
+
+        assert!(position.x <= 0.75 + 1e-5);
+        assert!((position.z - 0.2).abs() < 1e-5);
+
+        // Nothing is in the way here
+        let position = wall.slide(vec3(-1.0, 0.0, 0.0), vec3(0.2, 0.0, 0.2), 0.25);
+
+        assert!(position.abs_diff_eq(vec3(-0.8, 0.0, 0.2), 1e-5));
+    }
+}
@@ -0,0 +1,360 @@
+use {
+    super::scene::RefId,
+    crate::math::Aabb,
+    glam::{vec3, Quat, Vec3},
+    serde::{Deserialize, Serialize},
+};
+
+/// Shapes the linear progress of a moving entity.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Easing {
+    Linear,
+    SmoothStep,
+    EaseInOutCubic,
+}
+
+impl Easing {
+    /// Maps a linear `t` in `0.0..=1.0` onto the easing curve.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Self::Linear => t,
+            Self::SmoothStep => t * t * (3.0 - 2.0 * t),
+            Self::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum EntityKind {
+    /// Slides open by `offset` (in local space) while the player is within `trigger_radius`.
+    Door { offset: Vec3, trigger_radius: f32 },
+
+    /// Travels back and forth by `offset` (in local space), pausing at each end.
+    Lift { offset: Vec3, pause_secs: f32 },
+
+    /// Spins around the local `axis` at the given speed.
+    Rotator { axis: Vec3, degrees_per_sec: f32 },
+}
+
+impl EntityKind {
+    /// Parses a scene ref id into an entity kind using the level naming conventions:
+    ///
+    /// - `door_*`: sliding door which opens upwards
+    /// - `door_side_*`: sliding door which opens sideways
+    /// - `lift_*`: vertical moving platform
+    /// - `rotator_*`: prop spinning around the vertical axis
+    ///
+    /// Default values may be overridden using properties named after the fields of each kind,
+    /// for example `lift_east(pause_secs=3)`.
+    pub fn from_id(id: &RefId) -> Option<Self> {
+        let name = id.name;
+
+        if name.starts_with("door_side_") {
+            Some(Self::Door {
+                offset: id.property_vec3("offset").unwrap_or(vec3(2.0, 0.0, 0.0)),
+                trigger_radius: id.property("trigger_radius").unwrap_or(3.0),
+            })
+        } else if name.starts_with("door_") {
+            Some(Self::Door {
+                offset: id.property_vec3("offset").unwrap_or(vec3(0.0, 3.0, 0.0)),
+                trigger_radius: id.property("trigger_radius").unwrap_or(3.0),
+            })
+        } else if name.starts_with("lift_") {
+            Some(Self::Lift {
+                offset: id.property_vec3("offset").unwrap_or(vec3(0.0, 4.0, 0.0)),
+                pause_secs: id.property("pause_secs").unwrap_or(2.0),
+            })
+        } else if name.starts_with("rotator_") {
+            Some(Self::Rotator {
+                axis: id
+                    .property_vec3("axis")
+                    .map(Vec3::normalize_or_zero)
+                    .filter(|axis| *axis != Vec3::ZERO)
+                    .unwrap_or(Vec3::Y),
+                degrees_per_sec: id.property("degrees_per_sec").unwrap_or(45.0),
+            })
+        } else {
+            None
+        }
+    }
+
+    fn duration_secs(self) -> f32 {
+        match self {
+            Self::Door { .. } => 0.75,
+            Self::Lift { .. } => 4.0,
+            Self::Rotator { .. } => 0.0,
+        }
+    }
+
+    fn easing(self) -> Easing {
+        match self {
+            Self::Door { .. } => Easing::SmoothStep,
+            Self::Lift { .. } => Easing::EaseInOutCubic,
+            Self::Rotator { .. } => Easing::Linear,
+        }
+    }
+}
+
+/// A kinematic entity, which moves independently of the static level geometry.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Mover {
+    kind: EntityKind,
+    position: Vec3,
+    rotation: Quat,
+
+    /// Linear progress along the path of movement, or the current angle for rotators.
+    progress: f32,
+
+    /// Direction of travel for lifts, or the pause time remaining if travel has stopped; doors
+    /// are latched open by this instead.
+    state: EntityState,
+}
+
+impl Mover {
+    /// Half-size of the volume which blocks the player when a door is closed.
+    const DOOR_HALF_EXTENTS: Vec3 = vec3(1.0, 1.5, 0.25);
+
+    /// Half-size of the platform of a lift, the top of which is at the position of the lift.
+    const LIFT_HALF_EXTENTS: Vec3 = vec3(1.5, 0.25, 1.5);
+
+    /// Distance the feet of a player may be above or below the top of a lift while standing on it.
+    const LIFT_STEP: f32 = 0.25;
+
+    /// Half-size of the volume a rotating prop sweeps, which blocks the player whatever the
+    /// current angle is.
+    const ROTATOR_HALF_EXTENTS: Vec3 = vec3(1.0, 1.0, 1.0);
+
+    /// Creates a mover at the start of its path, which begins at `position` and `rotation`.
+    pub fn new(kind: EntityKind, position: Vec3, rotation: Quat) -> Self {
+        Self {
+            kind,
+            position,
+            rotation,
+            progress: 0.0,
+            state: EntityState::Moving { rising: true },
+        }
+    }
+
+    /// Returns the volume which currently blocks the player, if any.
+    pub fn blocking_volume(&self) -> Option<Aabb> {
+        match self.kind {
+            // Mostly-open doors no longer block movement
+            EntityKind::Door { .. } if self.progress < 0.5 => Some(Aabb::from_center_half_extents(
+                self.current_position(),
+                (self.rotation * Self::DOOR_HALF_EXTENTS).abs(),
+            )),
+            EntityKind::Door { .. } => None,
+            EntityKind::Lift { .. } => {
+                let half_extents = (self.rotation * Self::LIFT_HALF_EXTENTS).abs();
+
+                Some(Aabb::from_center_half_extents(
+                    self.current_position() - Vec3::Y * half_extents.y,
+                    half_extents,
+                ))
+            }
+            EntityKind::Rotator { .. } => Some(Aabb::from_center_half_extents(
+                self.position,
+                Self::ROTATOR_HALF_EXTENTS,
+            )),
+        }
+    }
+
+    /// Returns the height of the top of a lift if a player whose feet are at the given position
+    /// stands on it; other kinds carry nothing.
+    pub fn platform_height(&self, feet: Vec3) -> Option<f32> {
+        if !matches!(self.kind, EntityKind::Lift { .. }) {
+            return None;
+        }
+
+        let volume = self.blocking_volume()?;
+        let (min, max) = (volume.min(), volume.max());
+
+        (feet.x >= min.x
+            && feet.x <= max.x
+            && feet.z >= min.z
+            && feet.z <= max.z
+            && (feet.y - max.y).abs() <= Self::LIFT_STEP)
+            .then_some(max.y)
+    }
+
+    pub fn current_position(&self) -> Vec3 {
+        match self.kind {
+            EntityKind::Door { offset, .. } | EntityKind::Lift { offset, .. } => {
+                self.position + self.rotation * offset * self.kind.easing().apply(self.progress)
+            }
+            EntityKind::Rotator { .. } => self.position,
+        }
+    }
+
+    pub fn current_rotation(&self) -> Quat {
+        match self.kind {
+            EntityKind::Rotator { axis, .. } => {
+                self.rotation * Quat::from_axis_angle(axis, self.progress.to_radians())
+            }
+            _ => self.rotation,
+        }
+    }
+
+    /// Fully opens a door and keeps it open, such as one the player opened on an earlier visit to
+    /// the level; other kinds are unaffected.
+    pub fn latch_open(&mut self) {
+        if matches!(self.kind, EntityKind::Door { .. }) {
+            self.progress = 1.0;
+            self.state = EntityState::Latched;
+        }
+    }
+
+    /// Advances this mover; doors open while the given player position is nearby.
+    ///
+    /// Returns `true` if a door finished opening during this update.
+    pub fn update(&mut self, dt: f32, player_position: Vec3) -> bool {
+        let step = dt / self.kind.duration_secs().max(f32::EPSILON);
+
+        match self.kind {
+            EntityKind::Door { .. } if self.state == EntityState::Latched => (),
+            EntityKind::Door { trigger_radius, .. } => {
+                let is_open = self.position.distance_squared(player_position)
+                    < trigger_radius * trigger_radius;
+                let step = if is_open { step } else { -step };
+                let was_open = self.progress >= 1.0;
+
+                self.progress = (self.progress + step).clamp(0.0, 1.0);
+
+                return !was_open && self.progress >= 1.0;
+            }
+            EntityKind::Lift { pause_secs, .. } => match self.state {
+                EntityState::Paused { secs, rising } => {
+                    let secs = secs - dt;
+
+                    self.state = if secs <= 0.0 {
+                        EntityState::Moving { rising }
+                    } else {
+                        EntityState::Paused { secs, rising }
+                    };
+                }
+                EntityState::Moving { rising } => {
+                    self.progress += if rising { step } else { -step };
+
+                    if !(0.0..=1.0).contains(&self.progress) {
+                        self.progress = self.progress.clamp(0.0, 1.0);
+                        self.state = EntityState::Paused {
+                            secs: pause_secs,
+                            rising: !rising,
+                        };
+                    }
+                }
+                EntityState::Latched => (),
+            },
+            EntityKind::Rotator {
+                degrees_per_sec, ..
+            } => {
+                self.progress = (self.progress + degrees_per_sec * dt) % 360.0;
+            }
+        }
+
+        false
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+enum EntityState {
+    Moving {
+        rising: bool,
+    },
+    Paused {
+        secs: f32,
+        rising: bool,
+    },
+
+    /// Open for good, whether or not the player is nearby.
+    Latched,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_approx(lhs: f32, rhs: f32) {
+        assert!(
+            lhs.is_finite() && rhs.is_finite() && (lhs - rhs).abs() < 1e-5,
+            "{lhs} is not approximately {rhs}"
+        );
+    }
+
+    #[test]
+    pub fn easing_endpoints() {
+        for easing in [Easing::Linear, Easing::SmoothStep, Easing::EaseInOutCubic] {
+            assert_approx(easing.apply(0.0), 0.0);
+            assert_approx(easing.apply(0.5), 0.5);
+            assert_approx(easing.apply(1.0), 1.0);
+            assert_approx(easing.apply(2.0), 1.0);
+        }
+    }
+
+    #[test]
+    pub fn entity_kind_from_id() {
+        let from_id = |id| EntityKind::from_id(&RefId::parse(id));
+
+        assert!(matches!(
+            from_id("door_side_01"),
+            Some(EntityKind::Door { offset, .. }) if offset.x > 0.0
+        ));
+        assert!(matches!(
+            from_id("door_01"),
+            Some(EntityKind::Door { offset, .. }) if offset.y > 0.0
+        ));
+        assert!(matches!(from_id("lift_a"), Some(EntityKind::Lift { .. })));
+        assert!(matches!(
+            from_id("lift_b(pause_secs=5)"),
+            Some(EntityKind::Lift { pause_secs, .. }) if pause_secs == 5.0
+        ));
+        assert!(matches!(
+            from_id("rotator_fan"),
+            Some(EntityKind::Rotator { .. })
+        ));
+        assert!(from_id("Spawn").is_none());
+    }
+
+    #[test]
+    pub fn latch_door_open() {
+        let kind = EntityKind::from_id(&RefId::parse("door_01")).unwrap();
+        let mut door = Mover::new(kind, Vec3::ZERO, Quat::IDENTITY);
+
+        // Doors report finishing opening once, and close again once the player leaves
+        assert!(!door.update(0.5, Vec3::ZERO));
+        assert!(door.update(0.5, Vec3::ZERO));
+        assert!(!door.update(0.5, Vec3::ZERO));
+
+        door.update(1.0, Vec3::INFINITY);
+
+        assert!(door.blocking_volume().is_some());
+
+        door.latch_open();
+        door.update(1.0, Vec3::INFINITY);
+
+        assert!(door.blocking_volume().is_none());
+    }
+
+    #[test]
+    pub fn lift_platform() {
+        let kind = EntityKind::from_id(&RefId::parse("lift_a")).unwrap();
+        let mut lift = Mover::new(kind, Vec3::ZERO, Quat::IDENTITY);
+
+        // Only feet on top of the platform stand on it
+        assert_eq!(lift.platform_height(vec3(1.0, 0.1, -1.0)), Some(0.0));
+        assert!(lift.platform_height(vec3(2.0, 0.0, 0.0)).is_none());
+        assert!(lift.platform_height(vec3(0.0, 1.0, 0.0)).is_none());
+
+        lift.update(4.0, Vec3::INFINITY);
+
+        assert_eq!(lift.platform_height(vec3(0.0, 4.0, 0.0)), Some(4.0));
+    }
+}
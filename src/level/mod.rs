@@ -1,9 +1,64 @@
+pub mod ambient;
+pub mod collision;
+pub mod entities;
 pub mod nav_mesh;
+pub mod placement;
+pub mod reverb;
+pub mod scene;
+pub mod surface;
+pub mod triggers;
 
-use self::nav_mesh::NavigationMesh;
+use {
+    self::{
+        ambient::AmbientEmitters, collision::CollisionMesh, nav_mesh::NavigationMesh,
+        reverb::ReverbZones, scene::Scene, triggers::Triggers,
+    },
+    crate::render::sky::Sky,
+};
 
 pub struct Level {
+    pub ambient_emitters: AmbientEmitters,
+    pub collision: CollisionMesh,
     pub nav_mesh: NavigationMesh,
+    pub reverb_zones: ReverbZones,
+    pub scene: Scene,
+
+    /// Ground which sets the sound of footsteps, found by casting rays down from the player.
+    pub surfaces: CollisionMesh,
+
+    pub triggers: Triggers,
 }
 
-impl Level {}
+impl Level {
+    /// Name of the scene ref whose properties set the sky, such as
+    /// `Sky(time_of_day=18.5, turbidity=4)`; a `day_length` in seconds makes the sun move and a
+    /// `fog_density` fills the level with fog.
+    pub const SKY_REF_NAME: &str = "Sky";
+
+    /// Returns the sky described by the scene, using default values for missing properties.
+    pub fn read_sky(scene: &Scene) -> Sky {
+        let mut sky = Sky::default();
+
+        if let Some((_, id)) = scene
+            .refs_named()
+            .find(|(_, id)| id.name == Self::SKY_REF_NAME)
+        {
+            sky.day_length = id.property("day_length").unwrap_or(sky.day_length);
+            sky.time_of_day = id.property("time_of_day").unwrap_or(sky.time_of_day);
+            sky.sun_azimuth = id.property("sun_azimuth").unwrap_or(sky.sun_azimuth);
+            sky.sun_max_elevation = id
+                .property("sun_max_elevation")
+                .unwrap_or(sky.sun_max_elevation);
+            sky.turbidity = id.property("turbidity").unwrap_or(sky.turbidity);
+            sky.fog_color = id.property_vec3("fog_color").unwrap_or(sky.fog_color);
+            sky.fog_density = id.property("fog_density").unwrap_or(sky.fog_density);
+            sky.fog_height = id.property("fog_height").unwrap_or(sky.fog_height);
+            sky.fog_height_falloff = id
+                .property("fog_height_falloff")
+                .unwrap_or(sky.fog_height_falloff);
+            sky.god_rays = id.property("god_rays").unwrap_or(sky.god_rays);
+        }
+
+        sky
+    }
+}
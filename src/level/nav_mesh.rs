@@ -1,9 +1,20 @@
 use {
-    glam::{vec3, Mat4, Quat, Vec2, Vec3},
-    std::collections::HashMap,
+    super::scene::Scene,
+    crate::{
+        render::{camera::Camera, capabilities::DeviceCapabilities, debug::capture::pass_name},
+        res,
+    },
+    anyhow::Context,
+    bytemuck::{bytes_of, cast_slice, Pod, Zeroable},
+    glam::{vec2, vec3, Mat4, Quat, Vec2, Vec3, Vec4},
+    log::warn,
+    pak::Pak,
+    screen_13::prelude::*,
+    serde::{Deserialize, Serialize},
+    std::{collections::HashMap, sync::Arc},
 };
 
-fn closest_point_triangle(p: Vec3, [a, b, c]: [Vec3; 3]) -> ClosestPoint {
+pub(super) fn closest_point_triangle(p: Vec3, [a, b, c]: [Vec3; 3]) -> ClosestPoint {
     // From implementation described in Real-Time Collision Detection by Christer Ericson 2005
 
     let ab = b - a;
@@ -146,13 +157,13 @@ fn triangle_neighbors(triangle_indices: &[[usize; 3]]) -> Vec<NeighborIndices> {
     res
 }
 
-enum ClosestPoint {
+pub(super) enum ClosestPoint {
     Edge(usize, Vec3),
     Face(Vec3),
     Vertex(usize),
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub struct MeshLocation {
     triangle_index: usize,
     position: Vec3,
@@ -165,14 +176,93 @@ impl MeshLocation {
     }
 }
 
+/// How an off-mesh link is travelled.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum LinkKind {
+    /// One-way arc, such as off a ledge or across a gap.
+    Jump,
+
+    /// Two-way climb, such as between floors.
+    Ladder,
+}
+
+impl LinkKind {
+    /// Offset from the start of a link to its end, in the local space of the scene ref, when no
+    /// `offset` property is given.
+    fn default_offset(self) -> Vec3 {
+        match self {
+            Self::Jump => vec3(0.0, -3.0, -2.0),
+            Self::Ladder => vec3(0.0, 3.0, -0.5),
+        }
+    }
+
+    /// Meters per second along the link.
+    fn speed(self) -> f32 {
+        match self {
+            Self::Jump => 6.0,
+            Self::Ladder => 2.0,
+        }
+    }
+}
+
+/// A connection between two locations which walking cannot reach, such as a ladder between floors
+/// which are not joined by walkable triangles.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct OffMeshLink {
+    pub end: MeshLocation,
+    pub kind: LinkKind,
+    pub start: MeshLocation,
+}
+
+impl OffMeshLink {
+    /// Height of the middle of a jump above the straight line between its ends.
+    const JUMP_HEIGHT: f32 = 0.75;
+
+    /// Seconds taken to travel the whole link.
+    pub fn duration(&self) -> f32 {
+        self.start.position.distance(self.end.position) / self.kind.speed()
+    }
+
+    /// Returns the position on the link after travelling the given fraction of it.
+    pub fn position_at(&self, progress: f32) -> Vec3 {
+        let progress = progress.clamp(0.0, 1.0);
+        let position = self.start.position.lerp(self.end.position, progress);
+
+        match self.kind {
+            LinkKind::Jump => {
+                position + Vec3::Y * 4.0 * Self::JUMP_HEIGHT * progress * (1.0 - progress)
+            }
+            LinkKind::Ladder => position,
+        }
+    }
+
+    fn reversed(self) -> Self {
+        Self {
+            end: self.start,
+            kind: self.kind,
+            start: self.end,
+        }
+    }
+}
+
 /// Defines a navigable x/z plane built off the data of a mesh.
 pub struct NavigationMesh {
+    links: Vec<OffMeshLink>,
     neighbor_indices: Vec<NeighborIndices>,
     triangle_indices: Vec<[usize; 3]>,
     vertices: Vec<Vec3>,
 }
 
 impl NavigationMesh {
+    /// Distance from the start of an off-mesh link within which walkers take it.
+    const LINK_RADIUS: f32 = 0.5;
+
+    /// Prefixes of scene refs which place off-mesh links, for example
+    /// `ladder_tower(offset=0 6 -0.5)`; the ref is the start of the link and `offset`, in the
+    /// local space of the ref, is the way to its end.
+    pub const LINK_PREFIXES: [(&str, LinkKind); 2] =
+        [("jump_", LinkKind::Jump), ("ladder_", LinkKind::Ladder)];
+
     /// Constructs a new navigation mesh given a set of position vertices and their indices which
     /// define a triangulated mesh. Faces are clockwise, given as triangle indices a-b-c.
     pub fn new(indices: &[u32], vertices: &[Vec3]) -> Self {
@@ -192,12 +282,66 @@ impl NavigationMesh {
         }
 
         Self {
+            links: vec![],
             neighbor_indices: triangle_neighbors(&triangle_indices),
             triangle_indices,
             vertices: vertices.iter().copied().collect(),
         }
     }
 
+    /// Adds an off-mesh link between the navigable positions closest to the given positions.
+    ///
+    /// Links are taken by walking towards their end, so the end should be offset sideways from
+    /// the start: a ladder ends a little way onto the floor it climbs to.
+    pub fn add_link(&mut self, kind: LinkKind, start: Vec3, end: Vec3) {
+        self.links.push(OffMeshLink {
+            end: self.locate(end),
+            kind,
+            start: self.locate(start),
+        });
+    }
+
+    /// Adds the off-mesh links placed by scene refs, see [`Self::LINK_PREFIXES`].
+    pub fn add_scene_links(&mut self, scene: &Scene) {
+        for (prefix, kind) in Self::LINK_PREFIXES {
+            for (scene_ref, id) in scene.refs_prefixed(prefix) {
+                let offset = id
+                    .property_vec3("offset")
+                    .unwrap_or_else(|| kind.default_offset());
+
+                if vec2(offset.x, offset.z).length() < 0.1 {
+                    warn!("Off-mesh link {} cannot be walked into", id.name);
+                }
+
+                let start = scene_ref.position();
+                let end = start + scene_ref.rotation() * offset;
+
+                self.add_link(kind, start, end);
+            }
+        }
+    }
+
+    /// Returns the off-mesh link, oriented from the end being entered, which a walker at the given
+    /// location takes by moving in the given world direction.
+    pub fn link_at(&self, location: MeshLocation, direction: Vec2) -> Option<OffMeshLink> {
+        let radius_sq = Self::LINK_RADIUS * Self::LINK_RADIUS;
+
+        self.links
+            .iter()
+            .copied()
+            .flat_map(|link| {
+                let reversed = (link.kind == LinkKind::Ladder).then(|| link.reversed());
+
+                [Some(link), reversed].into_iter().flatten()
+            })
+            .find(|link| {
+                let heading = link.end.position - link.start.position;
+
+                location.position.distance_squared(link.start.position) <= radius_sq
+                    && direction.dot(vec2(heading.x, heading.z)) > 0.0
+            })
+    }
+
     /// Gets the navigable position closest to the given world position.
     ///
     /// Returns a location which has been clamped to the mesh surface.
@@ -305,6 +449,129 @@ struct NeighborIndices {
     edges: [Option<usize>; 3],
 }
 
+/// Draws a navigation mesh over the framebuffer as translucent triangles with edge lines, so that
+/// bad exports of the walkable region can be spotted in-game.
+pub struct NavMeshDebug {
+    edge_pipeline: Option<Arc<GraphicPipeline>>,
+    fill_pipeline: Arc<GraphicPipeline>,
+    vertex_buf: Arc<Buffer>,
+    vertex_count: u32,
+}
+
+impl NavMeshDebug {
+    pub fn new(device: &Arc<Device>, nav_mesh: &NavigationMesh) -> anyhow::Result<Self> {
+        let mut res_pak = res::open_pak().context("Opening pak")?;
+        let vert = res_pak
+            .read_blob(res::SHADER_LEVEL_NAV_MESH_DEBUG_VERT_SPIRV.as_str())
+            .context("Reading vert shader")?;
+        let frag = res_pak
+            .read_blob(res::SHADER_LEVEL_NAV_MESH_DEBUG_FRAG_SPIRV.as_str())
+            .context("Reading frag shader")?;
+
+        let create_pipeline = |info: GraphicPipelineInfoBuilder| {
+            GraphicPipeline::create(
+                device,
+                info.blend(BlendMode::ALPHA)
+                    .cull_mode(vk::CullModeFlags::NONE),
+                [
+                    Shader::new_vertex(vert.as_slice()),
+                    Shader::new_fragment(frag.as_slice()),
+                ],
+            )
+            .map(Arc::new)
+        };
+
+        let fill_pipeline =
+            create_pipeline(GraphicPipelineInfo::new()).context("Creating fill pipeline")?;
+        let edge_pipeline = if DeviceCapabilities::new(device).wireframe {
+            Some(
+                create_pipeline(GraphicPipelineInfo::new().polygon_mode(vk::PolygonMode::LINE))
+                    .context("Creating edge pipeline")?,
+            )
+        } else {
+            warn!("Navigation mesh edges require fill mode non-solid");
+
+            None
+        };
+
+        let vertices = nav_mesh
+            .triangle_indices
+            .iter()
+            .flatten()
+            .map(|&index| nav_mesh.vertices[index].extend(1.0))
+            .collect::<Box<[Vec4]>>();
+        let vertex_buf = Arc::new(
+            Buffer::create_from_slice(
+                device,
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+                cast_slice(&vertices),
+            )
+            .context("Creating vertex buffer")?,
+        );
+
+        Ok(Self {
+            edge_pipeline,
+            fill_pipeline,
+            vertex_buf,
+            vertex_count: vertices.len() as _,
+        })
+    }
+
+    /// Records drawing of the navigation mesh, with the triangle of the given location
+    /// highlighted.
+    ///
+    /// The mesh is drawn without depth testing so that parts hidden by level geometry remain
+    /// visible.
+    pub fn debug_draw(
+        &self,
+        render_graph: &mut RenderGraph,
+        framebuffer: impl Into<AnyImageNode>,
+        camera: &Camera,
+        location: MeshLocation,
+    ) {
+        #[derive(Clone, Copy, Pod, Zeroable)]
+        #[repr(C)]
+        struct PushConstants {
+            projection_view: Mat4,
+            highlight_triangle_index: u32,
+            edges: u32,
+            _0: [u8; 8],
+        }
+
+        let framebuffer = framebuffer.into();
+        let framebuffer_info = render_graph.node_info(framebuffer);
+        let aspect_ratio = framebuffer_info.width as f32 / framebuffer_info.height as f32;
+        let vertex_buf = render_graph.bind_node(&self.vertex_buf);
+        let vertex_count = self.vertex_count;
+
+        let mut push_consts = PushConstants {
+            projection_view: camera.projection_view(aspect_ratio, Camera::Z_NEAR),
+            highlight_triangle_index: location.triangle_index as _,
+            edges: 0,
+            _0: Default::default(),
+        };
+
+        for pipeline in Some(&self.fill_pipeline)
+            .into_iter()
+            .chain(&self.edge_pipeline)
+        {
+            render_graph
+                .begin_pass(pass_name("Navigation mesh debug"))
+                .bind_pipeline(pipeline)
+                .read_descriptor(0, vertex_buf)
+                .load_color(0, framebuffer)
+                .store_color(0, framebuffer)
+                .record_subpass(move |subpass, _| {
+                    subpass
+                        .push_constants(bytes_of(&push_consts))
+                        .draw(vertex_count, 1, 0, 0);
+                });
+
+            push_consts.edges = 1;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -335,6 +602,45 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn link_at() {
+        let vertices = [
+            vec3(-10.0, 0.0, -10.0),
+            vec3(10.0, 0.0, -10.0),
+            vec3(-10.0, 0.0, 10.0),
+            vec3(10.0, 0.0, 10.0),
+            vec3(-10.0, 5.0, -30.0),
+            vec3(10.0, 5.0, -30.0),
+            vec3(-10.0, 5.0, -11.0),
+            vec3(10.0, 5.0, -11.0),
+        ];
+        let indices = [0, 1, 3, 0, 3, 2, 4, 5, 7, 4, 7, 6];
+
+        let mut nav_mesh = NavigationMesh::new(&indices, &vertices);
+        nav_mesh.add_link(
+            LinkKind::Ladder,
+            vec3(0.0, 0.0, -9.5),
+            vec3(0.0, 5.0, -11.5),
+        );
+
+        let bottom = nav_mesh.locate(vec3(0.0, 0.0, -9.5));
+        let top = nav_mesh.locate(vec3(0.0, 5.0, -11.5));
+
+        // Ladders are taken by walking towards their other end, from either end
+        assert!(nav_mesh.link_at(bottom, -Vec2::Y).is_some());
+        assert!(nav_mesh.link_at(bottom, Vec2::Y).is_none());
+        assert!(nav_mesh.link_at(bottom, Vec2::X).is_none());
+
+        let down = nav_mesh.link_at(top, Vec2::Y).unwrap();
+
+        assert_eq!(down.end, bottom);
+        assert!(down.duration() > 0.0);
+        assert_eq!(down.position_at(1.0), bottom.position());
+        assert!(nav_mesh
+            .link_at(nav_mesh.locate(Vec3::ZERO), -Vec2::Y)
+            .is_none());
+    }
+
     #[test]
     pub fn locate() {
         let vertices = [
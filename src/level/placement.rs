@@ -0,0 +1,76 @@
+//! Placements of scene refs moved by the level editor, which `build.rs` applies to the scene it
+//! exports from Blender before baking, so that edits survive re-exporting the `.blend` file.
+//!
+//! Placements of `art/scene/<name>.blend` are stored in `art/scene/placements/<name>.toml`.
+//!
+//! This module is also compiled by `build.rs` and so may only depend on `serde`.
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the directory, next to the scenes, which holds their placements.
+pub const DIR_NAME: &str = "placements";
+
+/// The transform of one scene ref, in the coordinates of the exported scene.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Placement {
+    /// The full id of the scene ref, including any properties.
+    pub id: String,
+
+    pub position: [f32; 3],
+
+    /// Quaternion, as `[x, y, z, w]`.
+    pub rotation: [f32; 4],
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Placements {
+    #[serde(default, rename = "placement")]
+    placements: Vec<Placement>,
+}
+
+impl Placements {
+    pub fn get(&self, id: &str) -> Option<&Placement> {
+        self.placements.iter().find(|placement| placement.id == id)
+    }
+
+    /// Adds a placement, replacing any previous placement of the same scene ref.
+    pub fn insert(&mut self, placement: Placement) {
+        if let Some(existing) = self
+            .placements
+            .iter_mut()
+            .find(|existing| existing.id == placement.id)
+        {
+            *existing = placement;
+        } else {
+            self.placements.push(placement);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn insert_placement() {
+        let mut placements = Placements::default();
+        placements.insert(Placement {
+            id: "Crate_a".to_owned(),
+            position: [0.0; 3],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+        });
+        placements.insert(Placement {
+            id: "Crate_a".to_owned(),
+            position: [1.0, 2.0, 3.0],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+        });
+
+        assert_eq!(placements.placements.len(), 1);
+        assert_eq!(placements.get("Crate_a").unwrap().position, [1.0, 2.0, 3.0]);
+        assert!(placements.get("Crate_b").is_none());
+
+        let saved = toml::to_string(&placements).unwrap();
+
+        assert_eq!(toml::from_str::<Placements>(&saved).unwrap(), placements);
+    }
+}
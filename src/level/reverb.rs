@@ -0,0 +1,87 @@
+use {
+    super::{
+        scene::{read_geometry, Scene},
+        triggers::TriggerShape,
+    },
+    crate::audio::ReverbPreset,
+    glam::Vec3,
+    log::warn,
+};
+
+/// A volume of the level with its own acoustics.
+#[derive(Clone, Debug)]
+struct ReverbZone {
+    preset: ReverbPreset,
+    shape: TriggerShape,
+}
+
+/// The reverb zones of a level.
+///
+/// Zones are scene geometry prefixed with [`ReverbZones::PREFIX`] which set their preset using
+/// the `kind` property, for example `Reverb_nave(kind=cathedral)`; like triggers, zones use the
+/// bounding box of their geometry unless given the property `convex=true`. Anywhere outside of
+/// every zone is [`ReverbPreset::Dry`].
+#[derive(Debug, Default)]
+pub struct ReverbZones {
+    zones: Vec<ReverbZone>,
+}
+
+impl ReverbZones {
+    pub const PREFIX: &str = "Reverb";
+
+    pub fn from_scene(scene: &Scene) -> Self {
+        let mut res = Self::default();
+
+        for (geom, id) in scene.geometries_prefixed(Self::PREFIX) {
+            let (indices, vertices) = read_geometry(&geom);
+            let convex = id.property("convex").unwrap_or_default();
+
+            if let Some(shape) = TriggerShape::new(&indices, &vertices, convex) {
+                res.insert(id.property("kind").unwrap_or_default(), shape);
+            } else {
+                warn!("Ignoring empty reverb zone {}", id.name);
+            }
+        }
+
+        res
+    }
+
+    pub fn insert(&mut self, preset: ReverbPreset, shape: TriggerShape) {
+        self.zones.push(ReverbZone { preset, shape });
+    }
+
+    /// Returns the preset of the zone containing the listener; where zones overlap the first one
+    /// in the scene is used.
+    pub fn preset(&self, listener_position: Vec3) -> ReverbPreset {
+        self.zones
+            .iter()
+            .find(|zone| zone.shape.contains(listener_position))
+            .map(|zone| zone.preset)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::math::Aabb, glam::vec3};
+
+    #[test]
+    pub fn reverb_zone_preset() {
+        let mut zones = ReverbZones::default();
+        zones.insert(
+            ReverbPreset::Corridor,
+            TriggerShape::Aabb(Aabb::from_center_half_extents(Vec3::ZERO, Vec3::ONE)),
+        );
+        zones.insert(
+            ReverbPreset::Cathedral,
+            TriggerShape::Aabb(Aabb::from_center_half_extents(
+                vec3(10.0, 0.0, 0.0),
+                Vec3::splat(10.0),
+            )),
+        );
+
+        assert_eq!(zones.preset(vec3(-5.0, 0.0, 0.0)), ReverbPreset::Dry);
+        assert_eq!(zones.preset(vec3(0.5, 0.0, 0.0)), ReverbPreset::Corridor);
+        assert_eq!(zones.preset(vec3(5.0, 0.0, 0.0)), ReverbPreset::Cathedral);
+    }
+}
@@ -0,0 +1,193 @@
+use {
+    glam::{vec3, Mat4, Vec3},
+    log::warn,
+    pak::scene::{SceneBuf, SceneBufGeometry, SceneBufRef},
+    std::{fmt::Debug, str::FromStr},
+};
+
+/// Reads the triangle indices and world-space vertex positions of scene geometry.
+pub fn read_geometry(geom: &SceneBufGeometry) -> (Vec<u32>, Vec<Vec3>) {
+    let transform = Mat4::from_rotation_translation(geom.rotation(), geom.position());
+    let indices = geom.index_buf().as_u32();
+    let vertex_data = geom.vertex_data();
+    let vertex_count = vertex_data.len() / 12;
+    let mut vertices = Vec::with_capacity(vertex_count);
+
+    for idx in 0..vertex_count {
+        let vertex = &vertex_data[idx * 12..];
+        let x = f32::from_ne_bytes([vertex[0], vertex[1], vertex[2], vertex[3]]);
+        let y = f32::from_ne_bytes([vertex[4], vertex[5], vertex[6], vertex[7]]);
+        let z = f32::from_ne_bytes([vertex[8], vertex[9], vertex[10], vertex[11]]);
+        let vertex = transform.mul_vec4(vec3(x, y, z).extend(1.0)).truncate();
+
+        vertices.push(vertex);
+    }
+
+    (indices, vertices)
+}
+
+/// A scene ref id split into its name and an optional properties suffix.
+///
+/// Properties are written after the name in parentheses, for example
+/// `lift_east(pause_secs=3, offset=0 6 0)`, so that level designers may tweak gameplay values
+/// without code changes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RefId<'a> {
+    pub name: &'a str,
+    properties: &'a str,
+}
+
+impl<'a> RefId<'a> {
+    pub fn parse(id: &'a str) -> Self {
+        let id = id.trim();
+
+        if let Some((name, properties)) = id.strip_suffix(')').and_then(|id| id.split_once('(')) {
+            Self {
+                name: name.trim_end(),
+                properties,
+            }
+        } else {
+            Self {
+                name: id,
+                properties: "",
+            }
+        }
+    }
+
+    /// Key/value pairs of the properties suffix, in the order written.
+    pub fn properties(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.properties
+            .split(',')
+            .filter_map(|property| property.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+    }
+
+    /// Parses the value of the given property, if present.
+    ///
+    /// Values which fail to parse are logged and treated as missing.
+    pub fn property<T>(&self, key: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Debug,
+    {
+        let (_, value) = self.properties().find(|(property, _)| *property == key)?;
+
+        value
+            .parse()
+            .map_err(|err| warn!("Invalid property {key} of {}: {err:?}", self.name))
+            .ok()
+    }
+
+    /// Parses the value of the given property as three whitespace-separated numbers.
+    pub fn property_vec3(&self, key: &str) -> Option<Vec3> {
+        let value = self.property::<String>(key)?;
+        let mut components = value.split_whitespace().map(str::parse::<f32>);
+
+        match (
+            components.next(),
+            components.next(),
+            components.next(),
+            components.next(),
+        ) {
+            (Some(Ok(x)), Some(Ok(y)), Some(Ok(z)), None) => Some(Vec3::new(x, y, z)),
+            _ => {
+                warn!("Invalid property {key} of {}: {value}", self.name);
+
+                None
+            }
+        }
+    }
+}
+
+/// Wraps the scene data of a level with queries used by gameplay code.
+pub struct Scene {
+    buf: SceneBuf,
+}
+
+impl Scene {
+    pub fn new(buf: SceneBuf) -> Self {
+        Self { buf }
+    }
+
+    /// Returns the geometry with the given id.
+    pub fn geometry(&self, id: &str) -> Option<SceneBufGeometry<'_>> {
+        self.buf.geometries().find(|geom| geom.id() == Some(id))
+    }
+
+    /// Returns geometries whose name starts with the given prefix, along with the parsed id.
+    pub fn geometries_prefixed<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (SceneBufGeometry<'a>, RefId<'a>)> {
+        self.buf.geometries().filter_map(move |geom| {
+            let id = RefId::parse(geom.id()?);
+
+            id.name.starts_with(prefix).then_some((geom, id))
+        })
+    }
+
+    /// Returns the first ref with the given name, ignoring any properties suffix.
+    pub fn find_ref(&self, name: &str) -> Option<SceneBufRef<'_>> {
+        self.refs_named()
+            .find(|(_, id)| id.name == name)
+            .map(|(scene_ref, _)| scene_ref)
+    }
+
+    pub fn refs(&self) -> impl Iterator<Item = SceneBufRef<'_>> {
+        self.buf.refs()
+    }
+
+    /// Returns refs which have an id, along with the parsed id.
+    pub fn refs_named(&self) -> impl Iterator<Item = (SceneBufRef<'_>, RefId<'_>)> {
+        self.refs().filter_map(|scene_ref| {
+            let id = RefId::parse(scene_ref.id()?);
+
+            Some((scene_ref, id))
+        })
+    }
+
+    /// Returns refs whose name starts with the given prefix, along with the parsed id.
+    pub fn refs_prefixed<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (SceneBufRef<'a>, RefId<'a>)> {
+        self.refs_named()
+            .filter(move |(_, id)| id.name.starts_with(prefix))
+    }
+
+    /// Returns refs which have the given tag.
+    pub fn refs_tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = SceneBufRef<'a>> {
+        self.refs()
+            .filter(move |scene_ref| scene_ref.tags().iter().any(|ref_tag| ref_tag == tag))
+    }
+
+    /// Returns refs positioned within `radius` of `center`.
+    pub fn refs_within(&self, center: Vec3, radius: f32) -> impl Iterator<Item = SceneBufRef<'_>> {
+        let radius_sq = radius * radius;
+
+        self.refs()
+            .filter(move |scene_ref| scene_ref.position().distance_squared(center) <= radius_sq)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, glam::vec3};
+
+    #[test]
+    pub fn ref_id_parse() {
+        let id = RefId::parse("lift_east (pause_secs=3, offset=0 6 0, broken=yes)");
+
+        assert_eq!(id.name, "lift_east");
+        assert_eq!(id.properties().count(), 3);
+        assert_eq!(id.property::<f32>("pause_secs"), Some(3.0));
+        assert_eq!(id.property_vec3("offset"), Some(vec3(0.0, 6.0, 0.0)));
+        assert_eq!(id.property::<f32>("broken"), None);
+        assert_eq!(id.property::<f32>("missing"), None);
+
+        let id = RefId::parse("Spawn");
+
+        assert_eq!(id.name, "Spawn");
+        assert_eq!(id.properties().count(), 0);
+    }
+}
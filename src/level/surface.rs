@@ -0,0 +1,93 @@
+use {
+    crate::{art, asset_key::SoundKey},
+    std::str::FromStr,
+};
+
+/// What the ground is made of, which sets the sound of footsteps on it.
+///
+/// Surfaces are given to scene geometry prefixed with [`Surface::PREFIX`] using the `kind`
+/// property, for example `Surface_catwalk(kind=metal)`; ground without surface geometry above or
+/// below it is [`Surface::Concrete`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Surface {
+    #[default]
+    Concrete,
+    Grass,
+    Metal,
+    Water,
+    Wood,
+}
+
+impl Surface {
+    pub const ALL: [Self; 5] = [
+        Self::Concrete,
+        Self::Grass,
+        Self::Metal,
+        Self::Water,
+        Self::Wood,
+    ];
+
+    /// Prefix of scene geometry names which set the surface of the ground they cover.
+    pub const PREFIX: &str = "Surface";
+
+    /// Returns the sound bank event of footsteps on this surface.
+    pub fn footstep_event(self) -> &'static str {
+        match self {
+            Self::Concrete => "footstep_concrete",
+            Self::Grass => "footstep_grass",
+            Self::Metal => "footstep_metal",
+            Self::Water => "footstep_water",
+            Self::Wood => "footstep_wood",
+        }
+    }
+
+    /// Returns the sounds of footsteps on this surface, which are the art sounds named
+    /// `sound/footstep/<surface>*`, such as `sound/footstep/metal_1.wav`.
+    pub fn footstep_sounds(self) -> impl Iterator<Item = SoundKey> {
+        let prefix = format!("sound/footstep/{}", self.name());
+
+        art::SOUNDS
+            .iter()
+            .copied()
+            .filter(move |sound| sound.as_str().starts_with(&prefix))
+    }
+
+    /// Returns the name of this surface as written by level designers, such as `metal`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Concrete => "concrete",
+            Self::Grass => "grass",
+            Self::Metal => "metal",
+            Self::Water => "water",
+            Self::Wood => "wood",
+        }
+    }
+}
+
+impl FromStr for Surface {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|surface| surface.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown surface {s}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn parse_surface() {
+        assert_eq!("metal".parse(), Ok(Surface::Metal));
+        assert_eq!("Wood".parse(), Ok(Surface::Wood));
+        assert!("lava".parse::<Surface>().is_err());
+
+        for surface in Surface::ALL {
+            assert_eq!(surface.name().parse(), Ok(surface));
+            assert!(surface.footstep_event().ends_with(surface.name()));
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use {
+    super::scene::{read_geometry, RefId, Scene},
+    crate::math::{Aabb, Plane},
+    glam::Vec3,
+    log::{debug, warn},
+    std::collections::HashMap,
+};
+
+/// The region of space covered by a trigger.
+#[derive(Clone, Debug)]
+pub enum TriggerShape {
+    Aabb(Aabb),
+
+    /// Planes facing away from the inside of a convex volume.
+    Convex(Box<[Plane]>),
+}
+
+impl TriggerShape {
+    /// Builds a shape from triangle geometry; the geometry must be convex when `convex` is set.
+    pub fn new(indices: &[u32], vertices: &[Vec3], convex: bool) -> Option<Self> {
+        if !convex {
+            return Aabb::from_points(vertices.iter().copied()).map(Self::Aabb);
+        }
+
+        if vertices.is_empty() {
+            return None;
+        }
+
+        let center = vertices.iter().copied().sum::<Vec3>() / vertices.len() as f32;
+        let planes = indices
+            .chunks_exact(3)
+            .filter_map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|idx| vertices[triangle[idx] as usize]);
+                let normal = (b - a).cross(c - a).try_normalize()?;
+                let plane = Plane::from_position_normal(a, normal);
+
+                // Face winding is not consistent between exports so we orient using the center
+                Some(if plane.signed_distance(center) > 0.0 {
+                    Plane::from_position_normal(a, -normal)
+                } else {
+                    plane
+                })
+            })
+            .collect::<Box<_>>();
+
+        (!planes.is_empty()).then_some(Self::Convex(planes))
+    }
+
+    pub fn contains(&self, position: Vec3) -> bool {
+        match self {
+            Self::Aabb(aabb) => aabb.contains(position),
+            Self::Convex(planes) => planes
+                .iter()
+                .all(|plane| plane.signed_distance(position) <= 0.0),
+        }
+    }
+}
+
+/// A named volume which reports when the player enters or exits it.
+#[derive(Clone, Debug)]
+pub struct Trigger {
+    id: String,
+    inside: bool,
+    shape: TriggerShape,
+}
+
+impl Trigger {
+    pub fn id(&self) -> RefId<'_> {
+        RefId::parse(&self.id)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TriggerEventKind {
+    Enter,
+    Exit,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TriggerEvent {
+    pub kind: TriggerEventKind,
+
+    /// Index of the trigger within [`Triggers`].
+    pub trigger_index: usize,
+}
+
+/// The trigger volumes of a level.
+#[derive(Debug, Default)]
+pub struct Triggers {
+    triggers: Vec<Trigger>,
+}
+
+impl Triggers {
+    /// Prefix of scene geometry names which become triggers.
+    ///
+    /// Triggers use the bounding box of their geometry unless given the property `convex=true`,
+    /// for example `Trigger_vault(convex=true)`.
+    pub const PREFIX: &str = "Trigger";
+
+    pub fn from_scene(scene: &Scene) -> Self {
+        let mut res = Self::default();
+
+        for (geom, id) in scene.geometries_prefixed(Self::PREFIX) {
+            let (indices, vertices) = read_geometry(&geom);
+            let convex = id.property("convex").unwrap_or_default();
+
+            if let Some(shape) = TriggerShape::new(&indices, &vertices, convex) {
+                res.insert(geom.id().unwrap_or_default(), shape);
+            } else {
+                warn!("Ignoring empty trigger {}", id.name);
+            }
+        }
+
+        res
+    }
+
+    pub fn get(&self, trigger_index: usize) -> &Trigger {
+        &self.triggers[trigger_index]
+    }
+
+    pub fn insert(&mut self, id: impl Into<String>, shape: TriggerShape) {
+        self.triggers.push(Trigger {
+            id: id.into(),
+            inside: false,
+            shape,
+        });
+    }
+
+    /// Updates which triggers contain the player, appending an event for each trigger which the
+    /// player has entered or exited since the previous update.
+    pub fn update(&mut self, player_position: Vec3, events: &mut Vec<TriggerEvent>) {
+        for (trigger_index, trigger) in self.triggers.iter_mut().enumerate() {
+            let inside = trigger.shape.contains(player_position);
+
+            if inside != trigger.inside {
+                trigger.inside = inside;
+                events.push(TriggerEvent {
+                    kind: if inside {
+                        TriggerEventKind::Enter
+                    } else {
+                        TriggerEventKind::Exit
+                    },
+                    trigger_index,
+                });
+            }
+        }
+    }
+}
+
+/// A function called when the player enters or exits a trigger.
+pub type TriggerHook<C> = fn(&mut C, &Trigger, TriggerEventKind);
+
+/// Maps trigger names to the hooks which run when their events occur, so that level designers
+/// may use triggers without engine changes once a hook exists.
+///
+/// `C` is the context given to each hook, such as the game state which hooks may change.
+pub struct TriggerHooks<C> {
+    hooks: HashMap<&'static str, TriggerHook<C>>,
+}
+
+impl<C> TriggerHooks<C> {
+    /// Calls the hook of each event, if the trigger name has one.
+    pub fn dispatch(&self, context: &mut C, triggers: &Triggers, events: &[TriggerEvent]) {
+        for event in events {
+            let trigger = triggers.get(event.trigger_index);
+
+            if let Some(hook) = self.hooks.get(trigger.id().name) {
+                hook(context, trigger, event.kind);
+            } else {
+                debug!("Unhandled trigger {:?} {}", event.kind, trigger.id().name);
+            }
+        }
+    }
+
+    pub fn register(&mut self, name: &'static str, hook: TriggerHook<C>) -> &mut Self {
+        self.hooks.insert(name, hook);
+        self
+    }
+}
+
+impl<C> Default for TriggerHooks<C> {
+    fn default() -> Self {
+        Self {
+            hooks: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, glam::vec3};
+
+    #[test]
+    pub fn trigger_events() {
+        let mut triggers = Triggers::default();
+        triggers.insert(
+            "Trigger_a",
+            TriggerShape::Aabb(Aabb::from_center_half_extents(Vec3::ZERO, Vec3::ONE)),
+        );
+
+        let mut events = vec![];
+        triggers.update(vec3(5.0, 0.0, 0.0), &mut events);
+
+        assert!(events.is_empty());
+
+        triggers.update(Vec3::ZERO, &mut events);
+        triggers.update(vec3(0.5, 0.0, 0.0), &mut events);
+        triggers.update(vec3(5.0, 0.0, 0.0), &mut events);
+
+        assert_eq!(
+            events.iter().map(|event| event.kind).collect::<Vec<_>>(),
+            [TriggerEventKind::Enter, TriggerEventKind::Exit]
+        );
+    }
+
+    #[test]
+    pub fn convex_shape() {
+        // Tetrahedron with mixed face winding
+        let vertices = [
+            Vec3::ZERO,
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+        ];
+        let indices = [0, 1, 2, 0, 3, 1, 0, 2, 3, 1, 2, 3];
+        let shape = TriggerShape::new(&indices, &vertices, true).unwrap();
+
+        assert!(shape.contains(vec3(0.1, 0.1, 0.1)));
+        assert!(!shape.contains(vec3(0.5, 0.5, 0.5)));
+        assert!(!shape.contains(vec3(-0.1, 0.1, 0.1)));
+    }
+}
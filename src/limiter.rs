@@ -0,0 +1,164 @@
+use {
+    serde::{Deserialize, Serialize},
+    std::{
+        hint::spin_loop,
+        thread::sleep,
+        time::{Duration, Instant},
+    },
+};
+
+/// How a [`FramerateLimiter`] waits for the end of each frame interval.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum LimiterStrategy {
+    /// Busy-waits for the whole interval: the most accurate, but burns a full core.
+    Spin,
+
+    /// Sleeps for the whole interval: the least CPU usage, but only as accurate as the OS timer.
+    Sleep,
+
+    /// Sleeps for most of the interval and then busy-waits for the remainder.
+    #[default]
+    SleepSpin,
+}
+
+/// Limits the rate at which frames are produced when v-sync is not enabled.
+///
+/// Frame deadlines are scheduled from the previous deadline rather than from when the current
+/// frame started, so time spent presenting (and any time the driver blocks during acquire) is
+/// included in each interval instead of being added on top of it.
+#[derive(Debug)]
+pub struct FramerateLimiter {
+    /// Time between the previous release and the start of the latest wait.
+    busy_time: Option<Duration>,
+
+    deadline: Option<Instant>,
+    released_at: Option<Instant>,
+    spin_threshold: Duration,
+    strategy: LimiterStrategy,
+}
+
+impl FramerateLimiter {
+    const MAX_SPIN_THRESHOLD: Duration = Duration::from_millis(4);
+    const MIN_SPIN_THRESHOLD: Duration = Duration::from_micros(500);
+
+    pub fn new(strategy: LimiterStrategy) -> Self {
+        Self {
+            busy_time: None,
+            deadline: None,
+            released_at: None,
+            spin_threshold: Duration::from_millis(1),
+            strategy,
+        }
+    }
+
+    /// Waits until the current frame interval has ended and returns the actual time (in
+    /// seconds) since the previous frame was released, or `frame_dt` for the first frame.
+    pub fn wait(&mut self, framerate_limit: usize, frame_dt: f32) -> f32 {
+        let interval = Duration::from_secs_f32(1.0 / framerate_limit.max(1) as f32);
+        let now = Instant::now();
+
+        self.busy_time = self.released_at.map(|released_at| now - released_at);
+        let deadline = self
+            .deadline
+            .map(|deadline| deadline + interval)
+            .filter(|deadline| *deadline > now)
+            // We missed the deadline (or this is the first frame) so start a new schedule
+            .unwrap_or(now);
+
+        match self.strategy {
+            LimiterStrategy::Spin => Self::spin_until(deadline),
+            LimiterStrategy::Sleep => Self::sleep_until(deadline),
+            LimiterStrategy::SleepSpin => {
+                let sleep_deadline = deadline.checked_sub(self.spin_threshold);
+
+                if let Some(sleep_deadline) =
+                    sleep_deadline.filter(|sleep_deadline| *sleep_deadline > Instant::now())
+                {
+                    Self::sleep_until(sleep_deadline);
+
+                    // Track how badly the OS oversleeps so we spin only as long as required
+                    let oversleep = Instant::now().saturating_duration_since(sleep_deadline);
+                    self.spin_threshold = ((self.spin_threshold * 7 + oversleep * 5 / 4) / 8)
+                        .clamp(Self::MIN_SPIN_THRESHOLD, Self::MAX_SPIN_THRESHOLD);
+                }
+
+                Self::spin_until(deadline);
+            }
+        }
+
+        let released_at = Instant::now();
+        let dt = self
+            .released_at
+            .map(|prev| (released_at - prev).as_secs_f32())
+            .unwrap_or(frame_dt);
+
+        self.deadline = Some(deadline);
+        self.released_at = Some(released_at);
+
+        dt
+    }
+
+    /// Returns the time (in seconds) between the release of the previous frame and the start of
+    /// the latest wait, which is the time the frame took without the limiter, or `None` for the
+    /// first frame.
+    ///
+    /// This includes any time the driver blocks while presenting or acquiring, so it rises when
+    /// the GPU, and not only the CPU, is slower than the framerate limit.
+    pub fn busy_time(&self) -> Option<f32> {
+        self.busy_time.map(|busy_time| busy_time.as_secs_f32())
+    }
+
+    fn sleep_until(deadline: Instant) {
+        let now = Instant::now();
+
+        if deadline > now {
+            sleep(deadline - now);
+        }
+    }
+
+    fn spin_until(deadline: Instant) {
+        while Instant::now() < deadline {
+            spin_loop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_limited(strategy: LimiterStrategy) {
+        const FRAMERATE_LIMIT: usize = 200;
+        const FRAME_COUNT: usize = 10;
+
+        let mut limiter = FramerateLimiter::new(strategy);
+        let started = Instant::now();
+
+        limiter.wait(FRAMERATE_LIMIT, 0.0);
+
+        for _ in 0..FRAME_COUNT {
+            let dt = limiter.wait(FRAMERATE_LIMIT, 0.0);
+
+            assert!(dt > 0.0);
+        }
+
+        let elapsed = (Instant::now() - started).as_secs_f32();
+
+        assert!(elapsed >= FRAME_COUNT as f32 / FRAMERATE_LIMIT as f32 * 0.99);
+    }
+
+    #[test]
+    pub fn limiter_sleep() {
+        assert_limited(LimiterStrategy::Sleep);
+    }
+
+    #[test]
+    pub fn limiter_sleep_spin() {
+        assert_limited(LimiterStrategy::SleepSpin);
+    }
+
+    #[test]
+    pub fn limiter_spin() {
+        assert_limited(LimiterStrategy::Spin);
+    }
+}
@@ -1,13 +1,112 @@
 mod art {
     include!(concat!(env!("OUT_DIR"), "/art.rs"));
 
-    use {super::env::current_exe_dir, pak::PakBuf, std::io::Error};
+    use {
+        super::{
+            asset_key::BlobKey,
+            env::current_exe_dir,
+            game::{
+                difficulty::DifficultyTable,
+                impact_table::ImpactTable,
+                objectives::{self, ObjectiveTable},
+            },
+            mods::{active_mods, PakStack},
+            render::{
+                compressed_bitmap::{self, CompressedBitmaps},
+                material_animation::{self, MaterialAnimations},
+            },
+        },
+        log::{info, warn},
+        pak::PakBuf,
+        std::{fs::read, io::Error, str::from_utf8},
+    };
 
     pub fn open_pak() -> Result<PakBuf, Error> {
         let path = current_exe_dir().join("art.pak");
 
         PakBuf::open(path)
     }
+
+    /// Opens `art.pak` with the active mods layered over it.
+    pub fn open_pak_stack() -> Result<PakStack, Error> {
+        PakStack::open(open_pak()?, active_mods())
+    }
+
+    /// Reads the block-compressed bitmaps baked alongside the pak, or returns none if they are
+    /// missing or unreadable, in which case uncompressed bitmaps are used.
+    pub fn read_compressed_bitmaps() -> CompressedBitmaps {
+        let path = current_exe_dir().join(compressed_bitmap::FILE_NAME);
+
+        read(&path)
+            .map_err(|err| info!("No compressed bitmaps: {err}"))
+            .ok()
+            .and_then(|data| {
+                bincode::deserialize(&data)
+                    .map_err(|err| warn!("Unable to read compressed bitmaps: {err}"))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Reads the difficulty table from the pak, or returns an empty table if it is missing or
+    /// invalid, in which case every difficulty plays as normal.
+    pub fn read_difficulty_table() -> DifficultyTable {
+        read_table(TABLE_DIFFICULTY_TBL, DifficultyTable::parse)
+            .map_err(|err| warn!("Unable to read difficulty table: {err}"))
+            .unwrap_or_default()
+    }
+
+    /// Reads the impact table from the pak, or returns an empty table if it is missing or invalid,
+    /// in which case impacts have no effects.
+    pub fn read_impact_table() -> ImpactTable {
+        read_table(TABLE_IMPACT_TBL, ImpactTable::parse)
+            .map_err(|err| warn!("Unable to read impact table: {err}"))
+            .unwrap_or_default()
+    }
+
+    /// Reads the material animations baked alongside the pak, or returns none if they are missing
+    /// or unreadable, in which case materials are still.
+    pub fn read_material_animations() -> MaterialAnimations {
+        let path = current_exe_dir().join(material_animation::FILE_NAME);
+
+        read(&path)
+            .map_err(|err| info!("No material animations: {err}"))
+            .ok()
+            .and_then(|data| {
+                bincode::deserialize(&data)
+                    .map_err(|err| warn!("Unable to read material animations: {err}"))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Reads the objective table baked alongside the pak, or returns an empty table if it is
+    /// missing or unreadable, in which case levels have no objectives.
+    pub fn read_objective_table() -> ObjectiveTable {
+        let path = current_exe_dir().join(objectives::FILE_NAME);
+
+        read(&path)
+            .map_err(|err| info!("No objective table: {err}"))
+            .ok()
+            .and_then(|data| {
+                bincode::deserialize(&data)
+                    .map_err(|err| warn!("Unable to read objective table: {err}"))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Parses the TOML of a table from the topmost pak which has it, so that mods may replace it.
+    fn read_table<T>(
+        key: BlobKey,
+        parse: impl FnOnce(&str) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let mut paks = open_pak_stack()?;
+        let (_, pak) = paks.resolve(key.as_str());
+        let data = pak.read_blob(key.as_str())?;
+
+        parse(from_utf8(&data)?)
+    }
 }
 
 mod res {
@@ -35,43 +134,114 @@ mod fs {
 }
 
 mod args;
+mod asset_key;
+mod audio;
 mod config;
+mod crash;
+mod cvar;
+mod demo;
+mod display;
 mod env;
+mod focus;
+mod frame_stats;
+mod game;
+mod gpu;
+mod input;
+mod integrity;
 mod level;
+mod limiter;
 mod math;
+mod mods;
 mod render;
+mod resolution;
+mod timestep;
 mod ui;
 
 use {
     self::{
         args::Args,
+        asset_key::BitmapKey,
+        audio::ReverbMixer,
         config::Config,
-        ui::{bench::Bench, boot::Boot, CursorStyle, DrawContext, Ui, UpdateContext},
+        cvar::Cvars,
+        demo::{DemoPlayer, DemoRecorder},
+        display::UiScale,
+        focus::WindowFocus,
+        frame_stats::FrameStats,
+        input::{update_gamepad, update_mouse_extra, GamepadBuf, MouseExtraBuf},
+        limiter::FramerateLimiter,
+        render::{
+            capabilities::DeviceCapabilities,
+            colorblind::{self, ColorblindFilter},
+            debug::capture::{pass_name, FrameCapture},
+            frame_timer::GpuFrameTimer,
+            model::ModelBufferTechnique,
+        },
+        resolution::DynamicResolution,
+        timestep::FixedTimestep,
+        ui::{
+            bench::Bench,
+            boot::{Boot, DemoBoot},
+            captions::Captions,
+            error::ErrorScreen,
+            ui_sound::UiSounds,
+            CursorStyle, DrawContext, Ui, UpdateContext,
+        },
     },
-    anyhow::Context,
+    anyhow::{anyhow, Context},
     bytemuck::{bytes_of, cast_slice},
     clap::Parser,
+    gilrs::Gilrs,
     glam::{vec3, vec4, Mat4},
-    kira::manager::{backend::cpal::CpalBackend, AudioManager, AudioManagerSettings},
+    kira::{
+        manager::{backend::cpal::CpalBackend, AudioManager, AudioManagerSettings},
+        tween::Tween,
+        Volume,
+    },
     pak::{bitmap::BitmapFormat, Pak, PakBuf},
     screen_13::prelude::*,
     screen_13_fx::{ImageFormat, ImageLoader, TransitionPipeline},
     std::{
+        io,
         panic::{set_hook, take_hook},
         process::exit,
         sync::Arc,
-        time::Instant,
     },
 };
 
 fn main() {
-    #[cfg(debug_assertions)]
-    pretty_env_logger::init();
-
+    crash::init_logger();
     set_thread_panic_hook();
 
     let args = Args::parse();
-    let config = Config::read();
+
+    // Demo paths are given on the command line, so their problems exit instead of panicking
+    let mut demo_player = args.play_demo.as_ref().map(|path| {
+        DemoPlayer::open(path)
+            .with_context(|| format!("Opening demo {}", path.display()))
+            .unwrap_or_else(|err| {
+                error!("{err:?}");
+                exit(1);
+            })
+    });
+
+    // Demos play back with the settings they were recorded with
+    let mut config = demo_player
+        .as_ref()
+        .map(|demo_player| demo_player.config().clone())
+        .unwrap_or_else(Config::read);
+    let mut demo_recorder = args.record_demo.as_ref().map(|path| {
+        DemoRecorder::create(path, &config)
+            .with_context(|| format!("Creating demo {}", path.display()))
+            .unwrap_or_else(|err| {
+                error!("{err:?}");
+                exit(1);
+            })
+    });
+
+    crash::set_config(&config);
+
+    let mut frame_capture = FrameCapture::load(args.renderdoc);
 
     let mut event_loop = EventLoop::new();
 
@@ -80,20 +250,23 @@ fn main() {
         event_loop = event_loop.debug(true);
     }
 
+    // Hybrid graphics laptops may list their integrated GPU first, which lacks ray tracing
+    let gpu = args.gpu.clone().or_else(|| config.gpu.clone());
+    event_loop = event_loop.select_physical_device(move |physical_devices| {
+        gpu::select_physical_device(physical_devices, gpu.as_deref())
+    });
+
+    let monitor = display::select_monitor(
+        event_loop.available_monitors(),
+        event_loop.primary_monitor(),
+        config.monitor,
+    );
+
     if args.window {
-        if let Some(monitor) = event_loop
-            .primary_monitor()
-            .or_else(|| event_loop.available_monitors().next())
-        {
+        if let Some(monitor) = &monitor {
             // If the --window argument is provided we render in windowed mode where the window is
             // three quarters of the total screen size and centered in the screen
-            let monitor_size = monitor.size();
-            let window_size =
-                PhysicalSize::new(monitor_size.width * 3 / 4, monitor_size.height * 3 / 4);
-            let window_position = PhysicalPosition::new(
-                monitor_size.width / 2 - window_size.width / 2,
-                monitor_size.height / 2 - window_size.height / 2,
-            );
+            let (window_size, window_position) = display::windowed_rect(monitor);
             event_loop = event_loop.window(|window| {
                 window
                     .with_inner_size(window_size)
@@ -105,7 +278,8 @@ fn main() {
                 event_loop.window(|window| window.with_inner_size(PhysicalSize::new(1280, 720)));
         }
     } else {
-        event_loop = event_loop.fullscreen_mode(FullscreenMode::Exclusive);
+        let fullscreen = display::fullscreen(config.fullscreen_mode, monitor);
+        event_loop = event_loop.window(|window| window.with_fullscreen(fullscreen));
     }
 
     let not_mute = !args.mute;
@@ -114,6 +288,49 @@ fn main() {
             .context("Creating audio")
             .unwrap()
     });
+    let mut reverb = audio.as_mut().and_then(|audio| {
+        ReverbMixer::new(audio)
+            .map_err(|err| warn!("Unable to create reverb: {err}"))
+            .ok()
+    });
+
+    let mut cvars = Cvars::default();
+
+    // Every sound, including those heard through the reverb track, plays through the main track
+    if let Some(audio) = &mut audio {
+        let mut main_track = audio.main_track();
+        let mut set_master_volume = move |volume: f32| {
+            if let Err(err) =
+                main_track.set_volume(Volume::Amplitude(volume as _), Tween::default())
+            {
+                warn!("Unable to set master volume: {err}");
+            }
+        };
+
+        set_master_volume(config.master_volume);
+        cvars.on_change("audio.master_volume", move |value| {
+            set_master_volume(value.as_float())
+        });
+    }
+
+    // Without res.pak there is nothing to draw an error screen with, so its problems exit here
+    if let Err(err) = verify_pak(
+        "res.pak",
+        res::open_pak(),
+        res::MANIFEST,
+        args.verify_assets,
+    ) {
+        error!("{err:?}");
+        exit(1);
+    }
+
+    let art_err = verify_pak(
+        "art.pak",
+        art::open_pak(),
+        art::MANIFEST,
+        args.verify_assets,
+    )
+    .err();
 
     let mut res_pak = res::open_pak().unwrap();
     let window_icon = read_icon(res::ICON_WINDOW, &mut res_pak);
@@ -124,10 +341,29 @@ fn main() {
                 .with_title(fs::APPLICATION)
                 .with_window_icon(Some(window_icon))
         })
-        .sync_display(config.v_sync)
+        .present_modes(display::present_modes(config.v_sync))
         .build()
         .unwrap();
 
+    crash::set_device(&event_loop.device);
+    render::pipeline_cache::read(&event_loop.device);
+
+    let capabilities = DeviceCapabilities::new(&event_loop.device);
+
+    info!("{capabilities:?}");
+
+    if capabilities.portability_subset {
+        info!("Portability subset driver; ray tracing and mesh shaders are disabled");
+    }
+
+    // The swapchain picks from the same modes, so this is the mode it presents with
+    let present_mode = display::select_present_mode(
+        config.v_sync,
+        &display::supported_present_modes(&event_loop.device, &event_loop.window),
+    );
+
+    info!("Presenting with {present_mode:?}");
+
     let mut pool = LazyPool::new(&event_loop.device);
 
     trace!("Starting");
@@ -147,13 +383,13 @@ fn main() {
             [
                 Shader::new_vertex(
                     res_pak
-                        .read_blob(res::SHADER_CURSOR_VERT_SPIRV)
+                        .read_blob(res::SHADER_CURSOR_VERT_SPIRV.as_str())
                         .unwrap()
                         .as_slice(),
                 ),
                 Shader::new_fragment(
                     res_pak
-                        .read_blob(res::SHADER_CURSOR_FRAG_SPIRV)
+                        .read_blob(res::SHADER_CURSOR_FRAG_SPIRV.as_str())
                         .unwrap()
                         .as_slice(),
                 ),
@@ -168,13 +404,13 @@ fn main() {
             [
                 Shader::new_vertex(
                     res_pak
-                        .read_blob(res::SHADER_PRESENT_VERT_SPIRV)
+                        .read_blob(res::SHADER_PRESENT_VERT_SPIRV.as_str())
                         .unwrap()
                         .as_slice(),
                 ),
                 Shader::new_fragment(
                     res_pak
-                        .read_blob(res::SHADER_PRESENT_FRAG_SPIRV)
+                        .read_blob(res::SHADER_PRESENT_FRAG_SPIRV.as_str())
                         .unwrap()
                         .as_slice(),
                 ),
@@ -183,37 +419,213 @@ fn main() {
         .unwrap(),
     );
     let mut transition_pipeline = TransitionPipeline::new(&event_loop.device);
+    let colorblind_lut = colorblind::create_lut(&event_loop.device).unwrap();
+
+    // Filter held by the lookup table, which is filled on the first frame
+    let mut colorblind_lut_filter: Option<ColorblindFilter> = None;
 
     let mut ui: Option<Box<dyn Ui>> = Some(if args.benchmark {
-        Box::new(Bench::boot(&event_loop.device))
+        let scene = art::SCENES
+            .iter()
+            .copied()
+            .find(|scene| scene.as_str() == args.benchmark_scene)
+            .unwrap_or_else(|| {
+                let scenes = art::SCENES.iter().map(|scene| scene.as_str());
+
+                error!(
+                    "Unknown benchmark scene {} (expected one of: {})",
+                    args.benchmark_scene,
+                    scenes.collect::<Vec<_>>().join(", ")
+                );
+
+                exit(1);
+            });
+
+        Box::new(Bench::boot(
+            &event_loop.device,
+            scene,
+            args.benchmark_instances,
+            args.benchmark_output.clone(),
+        ))
+    } else if let Some(demo_player) = &demo_player {
+        Box::new(DemoBoot::new(
+            &event_loop.device,
+            demo_player.level().clone(),
+        ))
     } else {
         Box::new(Boot::new(&event_loop.device))
     });
 
+    // Content problems are shown instead of failing partway through loading
+    if let Some(err) = art_err {
+        ui = Some(Box::new(ErrorScreen::new(&event_loop.device, err)));
+    }
+
     let mut allow_cursor = true;
+    let mut captions = Captions::new().unwrap();
+
+    if let Some(report_path) = crash::take_last_report() {
+        let message = format!("Crash report written to {}", report_path.display());
+
+        warn!("{message}");
+        captions.push_notice(message, 10.0);
+    }
+
+    // Players who chose raster graphics do not need to be told
+    if !capabilities.ray_tracing && config.graphics != Some(ModelBufferTechnique::Raster) {
+        let message = "Ray tracing is not supported by this GPU; select another using --gpu";
+
+        warn!("{message}");
+        captions.push_notice(message, 10.0);
+    }
+
     let mut cursor = None;
+    let mut focus = WindowFocus::default();
+    let mut ui_sounds = UiSounds::default();
+    let mut dynamic_resolution = DynamicResolution::default();
+    let mut gpu_frame_timer = GpuFrameTimer::new(&event_loop.device);
+    let mut frame_stats = FrameStats::default();
+    let mut gamepad = GamepadBuf::default();
+    let mut gilrs = Gilrs::new()
+        .map_err(|err| warn!("Gamepads are unavailable: {err}"))
+        .ok();
+    let mut limiter = FramerateLimiter::new(config.framerate_limiter);
     let mut keyboard = KeyBuf::default();
     let mut mouse = MouseBuf::default();
+    let mut mouse_extra = MouseExtraBuf::default();
+    let mut timestep = FixedTimestep::default();
+
+    // The event loop consumes the device but we need it afterwards to save the pipeline cache
+    let device = Arc::clone(&event_loop.device);
 
     event_loop
         .run(move |frame| {
-            update_input(&mut keyboard, &mut mouse, frame.events);
+            let is_demo_frame = ui.as_ref().unwrap().accepts_demo_input();
+            let mut demo_dt = None;
+
+            // While a demo plays it replaces all input; states which do not accept demo input
+            // (such as loading screens) receive none
+            let events = if let Some(demo_player) = &mut demo_player {
+                if is_demo_frame {
+                    if let Some((dt, events)) = demo_player.next_frame() {
+                        demo_dt = Some(dt);
+                        events
+                    } else {
+                        frame.render_graph.clear_color_image(frame.swapchain_image);
+                        *frame.will_exit = true;
+
+                        return;
+                    }
+                } else {
+                    &[]
+                }
+            } else {
+                frame.events
+            };
 
-            let mut dt = frame.dt;
+            update_input(&mut keyboard, &mut mouse, events);
+            update_mouse_extra(&mut mouse_extra, events);
 
-            // Framerate limiter
-            if !config.v_sync && !args.disable_framerate_limit {
-                let framerate_limit = 1.0 / config.framerate_limit as f32;
-                let started = Instant::now();
-                while dt < framerate_limit {
-                    dt = frame.dt + (Instant::now() - started).as_secs_f32();
+            // Demos do not record gamepads, so they are ignored during playback
+            update_gamepad(
+                &mut gamepad,
+                gilrs.as_mut().filter(|_| demo_player.is_none()),
+            );
+
+            let is_limited =
+                !display::is_display_synced(present_mode) && !args.disable_framerate_limit;
+            let dt = if is_limited {
+                limiter.wait(config.framerate_limit, frame.dt)
+            } else {
+                frame.dt
+            };
+
+            // Without the limiter there is no target frame time; GPU time is used where timestamps
+            // are supported because CPU time does not show when the GPU is the bottleneck
+            if is_limited && config.dynamic_resolution {
+                let frame_time = match &gpu_frame_timer {
+                    Some(gpu_frame_timer) => gpu_frame_timer.frame_time(),
+                    None => limiter.busy_time(),
+                };
+
+                if let Some(frame_time) = frame_time {
+                    dynamic_resolution.update(frame_time, 1.0 / config.framerate_limit as f32);
                 }
             }
+            let dt = demo_dt.unwrap_or(dt);
+
+            if is_demo_frame {
+                if let Some(recorder) = &mut demo_recorder {
+                    let level = || ui.as_ref().unwrap().demo_level();
+
+                    if let Err(err) = recorder.record(dt, events, level) {
+                        warn!("Demo recording stopped: {err:#}");
+
+                        demo_recorder = None;
+                    }
+                }
+            }
+
+            frame_stats.push(dt);
+            captions.update(dt);
+
+            // Errors are logged in full when polled, so only their first line is captioned
+            #[cfg(feature = "hot-shaders")]
+            for err in render::hot_shader::poll() {
+                captions.push_text(ui::captions::Speaker::Narrator, err, 10.0);
+            }
+
+            let fixed_steps = timestep.advance(dt);
 
+            // Alt+Enter switches between windowed mode and the configured fullscreen mode
+            if keyboard.is_pressed(&VirtualKeyCode::Return)
+                && (keyboard.is_held(&VirtualKeyCode::LAlt)
+                    || keyboard.is_held(&VirtualKeyCode::RAlt))
+            {
+                let is_windowed = display::fullscreen_mode(frame.window).is_none();
+
+                display::set_fullscreen(
+                    frame.window,
+                    is_windowed.then_some(config.fullscreen_mode),
+                    config.monitor,
+                );
+            }
+
+            // Ctrl with plus or minus adjusts the UI scale, which is previewed immediately
+            if keyboard.is_held(&VirtualKeyCode::LControl)
+                || keyboard.is_held(&VirtualKeyCode::RControl)
+            {
+                let step = if keyboard.is_pressed(&VirtualKeyCode::Equals)
+                    || keyboard.is_pressed(&VirtualKeyCode::NumpadAdd)
+                {
+                    UiScale::STEP
+                } else if keyboard.is_pressed(&VirtualKeyCode::Minus)
+                    || keyboard.is_pressed(&VirtualKeyCode::NumpadSubtract)
+                {
+                    -UiScale::STEP
+                } else {
+                    0.0
+                };
+                let ui_scale = (config.ui_scale + step).clamp(UiScale::MIN, UiScale::MAX);
+
+                if ui_scale != config.ui_scale {
+                    info!("UI scale: {ui_scale}");
+
+                    config.ui_scale = ui_scale;
+
+                    if demo_player.is_none() {
+                        if let Err(err) = config.write() {
+                            warn!("Unable to write config: {err}");
+                        }
+                    }
+                }
+            }
+
+            let ui_scale = UiScale::new(frame.height, frame.window.scale_factor(), config.ui_scale);
             let framebuffer_height = if keyboard.is_held(&VirtualKeyCode::Tab) {
                 frame.height
             } else {
-                300
+                dynamic_resolution.framebuffer_height(ui_scale.framebuffer_height)
             };
             let framebuffer_width = frame.width * framebuffer_height / frame.height;
             let framebuffer_image = frame.render_graph.bind_node(
@@ -231,21 +643,78 @@ fn main() {
             let framebuffer_scale = (frame.width as f32 / framebuffer_width as f32)
                 .max(frame.height as f32 / framebuffer_height as f32);
 
+            // The HUD is not scaled by dynamic resolution, so it has the size the framebuffer
+            // would have at full scale
+            let hud_height = ui_scale.framebuffer_height;
+            let hud_image = frame.render_graph.bind_node(
+                pool.lease(ImageInfo::new_2d(
+                    vk::Format::R8G8B8A8_UNORM,
+                    frame.width * hud_height / frame.height,
+                    hud_height,
+                    vk::ImageUsageFlags::COLOR_ATTACHMENT
+                        | vk::ImageUsageFlags::SAMPLED
+                        | vk::ImageUsageFlags::TRANSFER_DST,
+                ))
+                .unwrap(),
+            );
+
+            if let Some(gpu_frame_timer) = &mut gpu_frame_timer {
+                gpu_frame_timer.record_begin(frame.render_graph, framebuffer_image);
+            }
+
+            frame
+                .render_graph
+                .clear_color_image_value(hud_image, [0x00, 0x00, 0x00, 0x00]);
+
+            if let Some(frame_capture) = &mut frame_capture {
+                frame_capture.update();
+
+                if keyboard.is_pressed(&VirtualKeyCode::F8) {
+                    frame_capture.trigger(format!(
+                        "{framebuffer_width}x{framebuffer_height} framebuffer, {:.2} ms average \
+                        frame time",
+                        frame_stats.average_frame_time() * 1_000.0
+                    ));
+                }
+            }
+
             ui = ui.take().unwrap().update(UpdateContext {
                 audio: audio.as_mut(),
-                config: &config,
+                captions: &mut captions,
+                config: &mut config,
                 cursor: &mut cursor,
+                cvars: &mut cvars,
                 dt,
-                events: frame.events,
+                events,
+                fixed_steps,
+                focus: &mut focus,
+                frame_stats: &frame_stats,
                 framebuffer_aspect_ratio: framebuffer_width as f32 / framebuffer_height as f32,
                 framebuffer_height,
                 framebuffer_scale,
                 framebuffer_width,
+                gamepad: &gamepad,
+                is_demo_playback: demo_player.is_some(),
                 keyboard: &keyboard,
                 mouse: &mouse,
+                mouse_extra: &mouse_extra,
+                reverb: reverb.as_mut(),
+                ui_sounds: &mut ui_sounds,
                 window: frame.window,
             });
 
+            if cvars.take_config_changed() && demo_player.is_none() {
+                if let Err(err) = config.write() {
+                    warn!("Unable to write config: {err}");
+                }
+            }
+
+            if let Some((audio, sound)) = audio.as_mut().zip(ui_sounds.take_frame_sound(dt)) {
+                if let Err(err) = audio.play(sound) {
+                    warn!("Unable to play sound: {err}");
+                }
+            }
+
             if ui.is_none() {
                 frame.render_graph.clear_color_image(frame.swapchain_image);
                 *frame.will_exit = true;
@@ -254,18 +723,36 @@ fn main() {
             }
 
             ui.as_mut().unwrap().draw(DrawContext {
+                captions: &captions,
                 dt,
+                fixed_alpha: timestep.alpha(),
+                frame_stats: &frame_stats,
                 framebuffer_image,
+                hud_image,
                 pool: &mut pool,
+                present_mode,
                 render_graph: frame.render_graph,
+                resolution_scale: framebuffer_height as f32 / ui_scale.framebuffer_height as f32,
                 transition_pipeline: &mut transition_pipeline,
             });
 
+            let colorblind_lut = frame.render_graph.bind_node(&colorblind_lut);
+
+            if colorblind_lut_filter != Some(config.colorblind_filter) {
+                colorblind_lut_filter = Some(config.colorblind_filter);
+                config
+                    .colorblind_filter
+                    .record_lut(&mut pool, frame.render_graph, colorblind_lut)
+                    .unwrap();
+            }
+
             frame
                 .render_graph
-                .begin_pass("Present")
+                .begin_pass(pass_name("Present"))
                 .bind_pipeline(&present_graphic_pipeline)
                 .read_descriptor(0, framebuffer_image)
+                .read_descriptor(1, colorblind_lut)
+                .read_descriptor(2, hud_image)
                 .store_color(0, frame.swapchain_image)
                 .record_subpass(move |subpass, _| {
                     subpass.push_constants(cast_slice(
@@ -293,16 +780,12 @@ fn main() {
                     } => {
                         allow_cursor = true;
                     }
-                    Event::WindowEvent {
-                        event: WindowEvent::Focused(true),
-                        ..
-                    } => {
-                        frame.window.set_cursor_visible(false);
-                    }
                     _ => (),
                 }
             }
 
+            focus.handle_events(frame.window, frame.events);
+
             if allow_cursor {
                 if let Some(cursor) = cursor {
                     let (mouse_x, mouse_y) = mouse.position();
@@ -312,7 +795,7 @@ fn main() {
                     let pixel_offset = match cursor {
                         CursorStyle::Pointer | CursorStyle::PointerShadow => 0.0,
                     };
-                    let pixel_scale = 3.0;
+                    let pixel_scale = ui_scale.pixel_scale;
 
                     let cursor_offset = pixel_scale * 2.0 * pixel_offset / frame.width as f32;
 
@@ -326,7 +809,7 @@ fn main() {
                     let render_aspect_ratio = frame.render_aspect_ratio();
                     frame
                         .render_graph
-                        .begin_pass("Cursor")
+                        .begin_pass(pass_name("Cursor"))
                         .bind_pipeline(&cursor_pipeline)
                         .read_descriptor(0, cursor)
                         .load_color(0, frame.swapchain_image)
@@ -344,14 +827,20 @@ fn main() {
                         });
                 }
             }
+
+            if let Some(gpu_frame_timer) = &mut gpu_frame_timer {
+                gpu_frame_timer.record_end(frame.render_graph, frame.swapchain_image);
+            }
         })
         .unwrap();
 
+    render::pipeline_cache::write(&device);
+
     trace!("OK");
 }
 
-fn read_cursor(key: &str, res_pak: &mut PakBuf, image_loader: &mut ImageLoader) -> Arc<Image> {
-    let bitmap = res_pak.read_bitmap(key).unwrap();
+fn read_cursor(key: BitmapKey, res_pak: &mut PakBuf, image_loader: &mut ImageLoader) -> Arc<Image> {
+    let bitmap = res_pak.read_bitmap(key.as_str()).unwrap();
 
     debug_assert_eq!(bitmap.format(), BitmapFormat::Rgba);
 
@@ -367,21 +856,66 @@ fn read_cursor(key: &str, res_pak: &mut PakBuf, image_loader: &mut ImageLoader)
         .unwrap()
 }
 
-fn read_icon(key: &str, res_pak: &mut PakBuf) -> Icon {
-    let bitmap = res_pak.read_bitmap(key).unwrap();
+fn read_icon(key: BitmapKey, res_pak: &mut PakBuf) -> Icon {
+    let bitmap = res_pak.read_bitmap(key.as_str()).unwrap();
 
     debug_assert_eq!(bitmap.format(), BitmapFormat::Rgba);
 
     Icon::from_rgba(bitmap.pixels().to_vec(), bitmap.width(), bitmap.height()).unwrap()
 }
 
-/// Makes sure that any thread which panics causes the program to exit.
+/// Checks a pak against the manifest recorded when it was built, and returns an error listing the
+/// first few problems if any keys are missing or, when `read_contents` is set, damaged.
+fn verify_pak(
+    name: &str,
+    pak: Result<PakBuf, io::Error>,
+    manifest: &[integrity::ManifestEntry],
+    read_contents: bool,
+) -> anyhow::Result<()> {
+    const MAX_LISTED_PROBLEMS: usize = 8;
+
+    let mut pak = pak.with_context(|| format!("Opening {name}"))?;
+    let problems = integrity::verify(&mut pak, manifest, read_contents);
+
+    if problems.is_empty() {
+        info!("Verified {name} ({} assets)", manifest.len());
+
+        return Ok(());
+    }
+
+    for problem in &problems {
+        error!("{name}: {problem}");
+    }
+
+    // Each problem becomes a line of the error screen
+    let mut listed = problems.iter().take(MAX_LISTED_PROBLEMS).rev();
+    let mut err = if problems.len() > MAX_LISTED_PROBLEMS {
+        anyhow!("...and {} more", problems.len() - MAX_LISTED_PROBLEMS)
+    } else {
+        anyhow!("{}", listed.next().unwrap())
+    };
+
+    for problem in listed {
+        err = err.context(problem.to_string());
+    }
+
+    Err(err.context(format!(
+        "{name} has {} missing or damaged assets; reinstalling the game may fix this",
+        problems.len()
+    )))
+}
+
+/// Makes sure that any thread which panics writes a crash report and causes the program to exit.
 fn set_thread_panic_hook() {
     let orig_hook = take_hook();
 
     set_hook(Box::new(move |panic_info| {
         orig_hook(panic_info);
 
+        if let Some(report_path) = crash::write_report(panic_info) {
+            eprintln!("Crash report written to {}", report_path.display());
+        }
+
         exit(1);
     }));
 }
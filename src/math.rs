@@ -29,6 +29,12 @@ impl Plane {
         }
     }
 
+    /// Returns the distance of the given position in front of (positive) or behind (negative)
+    /// this plane.
+    pub fn signed_distance(self, position: Vec3) -> f32 {
+        position.dot(self.normal) - self.distance
+    }
+
     pub fn intersect_ray(self, ray: Ray) -> Option<Vec3> {
         let t = -(self.distance + ray.position.dot(self.normal)) / ray.normal.dot(self.normal);
 
@@ -53,7 +59,97 @@ impl Ray {
         Self { position, normal }
     }
 
+    pub fn intersect_aabb(self, aabb: Aabb) -> Option<f32> {
+        aabb.intersect_ray(self)
+    }
+
     pub fn intersect_plane(self, plane: Plane) -> Option<Vec3> {
         plane.intersect_ray(self)
     }
+
+    pub fn normal(self) -> Vec3 {
+        self.normal
+    }
+
+    pub fn point_at(self, distance: f32) -> Vec3 {
+        self.position + self.normal * distance
+    }
+
+    pub fn position(self) -> Vec3 {
+        self.position
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    min: Vec3,
+    max: Vec3,
+}
+
+impl Aabb {
+    pub fn from_center_half_extents(center: Vec3, half_extents: Vec3) -> Self {
+        debug_assert!(half_extents.cmpge(Vec3::ZERO).all());
+
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    /// Returns the smallest box containing all of the given positions, or `None` if there are
+    /// none.
+    pub fn from_points(positions: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        positions.into_iter().fold(None, |aabb, position| {
+            Some(match aabb {
+                Some(Self { min, max }) => Self {
+                    min: min.min(position),
+                    max: max.max(position),
+                },
+                None => Self {
+                    min: position,
+                    max: position,
+                },
+            })
+        })
+    }
+
+    pub fn contains(self, position: Vec3) -> bool {
+        position.cmpge(self.min).all() && position.cmple(self.max).all()
+    }
+
+    /// Returns `true` if the two boxes overlap or touch.
+    pub fn intersects(self, other: Self) -> bool {
+        self.min.cmple(other.max).all() && self.max.cmpge(other.min).all()
+    }
+
+    pub fn max(self) -> Vec3 {
+        self.max
+    }
+
+    pub fn min(self) -> Vec3 {
+        self.min
+    }
+
+    /// Returns the smallest box containing both boxes.
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Returns the distance along the ray to the nearest intersection, using the slab method.
+    pub fn intersect_ray(self, ray: Ray) -> Option<f32> {
+        let inv_normal = ray.normal.recip();
+        let t0 = (self.min - ray.position) * inv_normal;
+        let t1 = (self.max - ray.position) * inv_normal;
+        let t_min = t0.min(t1).max_element();
+        let t_max = t0.max(t1).min_element();
+
+        if t_max >= t_min.max(0.0) {
+            Some(t_min.max(0.0))
+        } else {
+            None
+        }
+    }
 }
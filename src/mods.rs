@@ -0,0 +1,173 @@
+//! Mods are paks placed in the `mods` directory of the local data directory, which are layered over
+//! `art.pak` so that their keys override, or add to, those of the game.
+//!
+//! Mods are layered in order of file name, so `50_textures.pak` overrides `10_maps.pak` where both
+//! have a key. Each key is read wholly from the topmost pak which has it, and the IDs inside an
+//! asset, such as the materials of a scene, refer to that same pak.
+//!
+//! `res.pak` is never layered: it holds the shaders this build was compiled against.
+
+use {
+    crate::{fs::project_dirs, integrity::AssetKind},
+    log::{info, warn},
+    pak::PakBuf,
+    std::{
+        fs::read_dir,
+        io::Error,
+        path::{Path, PathBuf},
+        sync::OnceLock,
+    },
+};
+
+/// Index of a pak within a [`PakStack`]; the base pak is layer zero and each mod is one higher than
+/// the mod it overrides.
+pub type PakLayer = usize;
+
+/// The base pak of a [`PakStack`].
+pub const BASE_LAYER: PakLayer = 0;
+
+const DIR_NAME: &str = "mods";
+const EXTENSION: &str = "pak";
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModInfo {
+    /// File name of the pak, without its extension.
+    pub name: String,
+
+    pub path: PathBuf,
+}
+
+/// Returns the mods layered over the game, lowest priority first.
+///
+/// The mods directory is scanned once, so that every pak stack opened while the game runs has the
+/// same layers and IDs qualified by layer stay valid.
+pub fn active_mods() -> &'static [ModInfo] {
+    static MODS: OnceLock<Vec<ModInfo>> = OnceLock::new();
+
+    MODS.get_or_init(|| {
+        let Some(dir) = mods_dir() else {
+            return vec![];
+        };
+
+        let mods = scan(&dir);
+
+        for mod_info in &mods {
+            info!("Mod {} ({})", mod_info.name, mod_info.path.display());
+        }
+
+        mods
+    })
+}
+
+/// Returns the names of the active mods, as recorded in save games.
+pub fn active_mod_names() -> Vec<String> {
+    active_mods()
+        .iter()
+        .map(|mod_info| mod_info.name.clone())
+        .collect()
+}
+
+pub fn mods_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.data_local_dir().join(DIR_NAME))
+}
+
+/// Returns the paks in the given directory sorted by file name; a missing directory has none.
+fn scan(dir: &Path) -> Vec<ModInfo> {
+    let entries = match read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            info!("No mods: {err}");
+
+            return vec![];
+        }
+    };
+
+    let mut mods = entries
+        .filter_map(|entry| {
+            entry
+                .map_err(|err| warn!("Unable to read mods directory: {err}"))
+                .ok()
+        })
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == EXTENSION)
+        })
+        .filter_map(|path| {
+            Some(ModInfo {
+                name: path.file_stem()?.to_string_lossy().into_owned(),
+                path,
+            })
+        })
+        .collect::<Vec<_>>();
+    mods.sort_unstable_by(|lhs, rhs| lhs.path.cmp(&rhs.path));
+
+    mods
+}
+
+/// A base pak with mod paks layered over it.
+pub struct PakStack {
+    layers: Vec<PakBuf>,
+}
+
+impl PakStack {
+    /// Opens each of the given mods over the base pak; mods which cannot be opened are an error,
+    /// because skipping one would change the layer of every mod above it.
+    pub fn open(base: PakBuf, mods: &[ModInfo]) -> Result<Self, Error> {
+        let mut layers = Vec::with_capacity(1 + mods.len());
+        layers.push(base);
+
+        for mod_info in mods {
+            layers.push(PakBuf::open(&mod_info.path)?);
+        }
+
+        Ok(Self { layers })
+    }
+
+    /// Returns the topmost pak which has the given key, along with its layer; keys which no layer
+    /// has are looked up in the base pak, so that reading them fails as it would without mods.
+    pub fn resolve(&mut self, key: &str) -> (PakLayer, &mut PakBuf) {
+        let layer = self
+            .layers
+            .iter()
+            .rposition(|pak| AssetKind::of(pak, key).is_some())
+            .unwrap_or(BASE_LAYER);
+
+        (layer, &mut self.layers[layer])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{
+            env::temp_dir,
+            fs::{create_dir_all, remove_dir_all, write},
+            process,
+        },
+    };
+
+    #[test]
+    pub fn scan_sorts_paks() {
+        let dir = temp_dir().join(format!("mood-mods-test-{}", process::id()));
+        create_dir_all(&dir).unwrap();
+
+        for file_name in ["b.pak", "a.pak", "readme.txt"] {
+            write(dir.join(file_name), b"").unwrap();
+        }
+
+        create_dir_all(dir.join("c.pak")).unwrap();
+
+        let names = scan(&dir)
+            .into_iter()
+            .map(|mod_info| mod_info.name)
+            .collect::<Vec<_>>();
+
+        remove_dir_all(&dir).unwrap();
+
+        assert_eq!(names, ["a", "b"]);
+        assert!(scan(&dir).is_empty());
+    }
+}
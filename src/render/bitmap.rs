@@ -1,6 +1,10 @@
 pub use rect_packer::Rect;
 
 use {
+    super::{
+        debug::capture::pass_name,
+        transfer::{PendingUploads, TransferQueue},
+    },
     crate::res,
     anyhow::Context,
     bytemuck::{bytes_of, Pod, Zeroable},
@@ -23,16 +27,25 @@ impl fmt::Debug for Atlas {
     }
 }
 
+/// A bitmap packed into an atlas: the atlas index and rectangle, whether it has alpha, and the
+/// color it is multiplied by when drawn.
 #[derive(Clone, Copy, Debug)]
-pub struct Bitmap(usize, Rect, bool);
+pub struct Bitmap(usize, Rect, bool, [u8; 3]);
 
 impl Bitmap {
+    const WHITE: [u8; 3] = [0xff; 3];
+
     pub fn size(self) -> (u32, u32) {
         (
             self.1.width.try_into().unwrap_or_default(),
             self.1.height.try_into().unwrap_or_default(),
         )
     }
+
+    /// Returns this bitmap multiplied by the given color when drawn.
+    pub fn tinted(self, tint: [u8; 3]) -> Self {
+        Self(self.0, self.1, self.2, tint)
+    }
 }
 
 #[derive(Debug)]
@@ -41,10 +54,12 @@ pub struct BitmapBuffer {
     bitmap_pipeline: Arc<GraphicPipeline>,
     device: Arc<Device>,
     pending_bitmaps: Vec<(Bitmap, Arc<Image>)>,
+    pending_uploads: PendingUploads,
     pool: LazyPool,
+    transfer_queue: TransferQueue,
 
     temp_atlas_nodes: Vec<ImageNode>,
-    temp_alpha_images: Vec<(u32, Rect, Rect)>,
+    temp_alpha_images: Vec<(u32, Rect, Rect, [u8; 3])>,
 }
 
 impl BitmapBuffer {
@@ -70,13 +85,13 @@ impl BitmapBuffer {
                 [
                     Shader::new_vertex(
                         res_pak
-                            .read_blob(res::SHADER_BITMAP_VERT_SPIRV)
+                            .read_blob(res::SHADER_BITMAP_VERT_SPIRV.as_str())
                             .context("Reading vert shader")?
                             .as_slice(),
                     ),
                     Shader::new_fragment(
                         res_pak
-                            .read_blob(res::SHADER_BITMAP_FRAG_SPIRV)
+                            .read_blob(res::SHADER_BITMAP_FRAG_SPIRV.as_str())
                             .context("Reading frag shader")?
                             .as_slice(),
                     ),
@@ -90,7 +105,9 @@ impl BitmapBuffer {
             bitmap_pipeline,
             device,
             pending_bitmaps: Default::default(),
+            pending_uploads: Default::default(),
             pool,
+            transfer_queue: TransferQueue::new(&device),
             temp_atlas_nodes: Default::default(),
             temp_alpha_images: Default::default(),
         })
@@ -129,9 +146,13 @@ impl BitmapBuffer {
             let mut render_graph = RenderGraph::new();
             let image_node = render_graph.bind_node(&image);
             render_graph.clear_color_image(image_node);
+
+            // Bitmaps are copied into the atlas using the transfer queue, which must not start
+            // until the clear has finished
             render_graph
                 .resolve()
-                .submit(&mut self.pool, 0, queue_index)?;
+                .submit(&mut self.pool, 0, queue_index)?
+                .wait_until_executed()?;
 
             atlas_idx = Some(self.atlases.len());
             self.atlases.push(Atlas {
@@ -153,7 +174,7 @@ impl BitmapBuffer {
         rect.x += 1;
         rect.y += 1;
 
-        let bitmap = Bitmap(atlas_idx, rect, has_alpha);
+        let bitmap = Bitmap(atlas_idx, rect, has_alpha, Bitmap::WHITE);
         self.pending_bitmaps.push((bitmap, image));
 
         if self.pending_bitmaps.len() >= Self::PENDING_BITMAP_BATCH_SIZE {
@@ -173,6 +194,7 @@ impl BitmapBuffer {
         let framebuffer_info = render_graph.node_info(framebuffer_image);
 
         self.record_pending_bitmaps(0)?;
+        self.pending_uploads.wait()?;
 
         self.temp_atlas_nodes.clear();
 
@@ -181,11 +203,14 @@ impl BitmapBuffer {
                 .push(render_graph.bind_node(&atlas.image));
         }
 
-        for (Bitmap(atlas_idx, atlas_rect, has_alpha), bitmap_rect) in bitmaps.into_iter().copied()
+        for (Bitmap(atlas_idx, atlas_rect, has_alpha, tint), bitmap_rect) in
+            bitmaps.into_iter().copied()
         {
             let atlas_image = self.temp_atlas_nodes[atlas_idx];
 
+            // Tinted bitmaps are drawn, like those with alpha, because copies cannot change color
             if has_alpha
+                || tint != Bitmap::WHITE
                 || bitmap_rect.x < 0
                 || bitmap_rect.y < 0
                 || bitmap_rect.x + bitmap_rect.width < 0
@@ -196,7 +221,7 @@ impl BitmapBuffer {
                 || bitmap_rect.y + bitmap_rect.height >= framebuffer_info.height as i32
             {
                 self.temp_alpha_images
-                    .push((atlas_idx as _, atlas_rect, bitmap_rect));
+                    .push((atlas_idx as _, atlas_rect, bitmap_rect, tint));
             } else if atlas_rect.width == bitmap_rect.width
                 && atlas_rect.height == bitmap_rect.height
             {
@@ -263,7 +288,7 @@ impl BitmapBuffer {
         if !self.temp_alpha_images.is_empty() {
             let framebuffer_info = render_graph.node_info(framebuffer_image);
             let mut pass = render_graph
-                .begin_pass("Bitmaps")
+                .begin_pass(pass_name("Bitmaps"))
                 .bind_pipeline(&self.bitmap_pipeline)
                 .load_color(0, framebuffer_image)
                 .store_color(0, framebuffer_image);
@@ -276,7 +301,8 @@ impl BitmapBuffer {
             let alpha_images = self.temp_alpha_images.drain(..).collect::<Box<[_]>>();
 
             pass.record_subpass(move |subpass, _| {
-                for (atlas_idx, atlas_rect, bitmap_rect) in alpha_images.iter().copied() {
+                for (atlas_idx, atlas_rect, bitmap_rect, [r, g, b]) in alpha_images.iter().copied()
+                {
                     subpass
                         .push_constants(bytes_of(&BitmapPushConstants {
                             src: [
@@ -293,6 +319,7 @@ impl BitmapBuffer {
                             ],
                             color_size: [framebuffer_info.width, framebuffer_info.height],
                             atlas_idx,
+                            tint: u32::from_le_bytes([r, g, b, 0xff]),
                         }))
                         .draw(6, 1, 0, 0);
                 }
@@ -315,7 +342,7 @@ impl BitmapBuffer {
                 .push(render_graph.bind_node(&atlas.image));
         }
 
-        for (Bitmap(atlas_idx, rect, _), image) in self.pending_bitmaps.drain(..) {
+        for (Bitmap(atlas_idx, rect, ..), image) in self.pending_bitmaps.drain(..) {
             let atlas_node = self.temp_atlas_nodes[atlas_idx];
             let image_node = render_graph.bind_node(image);
 
@@ -340,9 +367,10 @@ impl BitmapBuffer {
             );
         }
 
-        render_graph
-            .resolve()
-            .submit(&mut self.pool, 0, queue_index)?;
+        let cmd_buf = self
+            .transfer_queue
+            .submit(render_graph, &mut self.pool, queue_index)?;
+        self.pending_uploads.push(cmd_buf)?;
 
         Ok(())
     }
@@ -355,4 +383,5 @@ struct BitmapPushConstants {
     dst: [u32; 4],
     color_size: [u32; 2],
     atlas_idx: u32,
+    tint: u32,
 }
@@ -1,4 +1,5 @@
 use {
+    super::{capabilities::DeviceCapabilities, debug::capture::pass_name},
     crate::res,
     anyhow::Context,
     bytemuck::{bytes_of, Pod, Zeroable},
@@ -21,7 +22,7 @@ pub struct BoundingSpherePipeline {
     dist_sq: Arc<ComputePipeline>,
     reduce_avg: Arc<ComputePipeline>,
     reduce_dist_sq: Arc<ComputePipeline>,
-    subgroup_size: u32,
+    workgroup_size: u32,
 }
 
 #[cfg(feature = "hot-shaders")]
@@ -31,127 +32,90 @@ pub struct BoundingSpherePipeline {
     dist_sq: HotComputePipeline,
     reduce_avg: HotComputePipeline,
     reduce_dist_sq: HotComputePipeline,
-    subgroup_size: u32,
+    workgroup_size: u32,
 }
 
 impl BoundingSpherePipeline {
     #[cfg(not(feature = "hot-shaders"))]
     pub fn new(device: &Arc<Device>, res_pak: &mut PakBuf) -> anyhow::Result<Self> {
-        let Vulkan11Properties { subgroup_size, .. } = device.physical_device.properties_v1_1;
-
-        let avg = Arc::new(
-            ComputePipeline::create(
-                &device,
-                ComputePipelineInfo::default(),
-                Shader::new_compute(
-                    read_blob(res_pak, res::SHADER_COMPUTE_BOUNDING_SPHERE_AVG_COMP_SPIRV)?
-                        .as_slice(),
-                )
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
-            )
-            .context("Creating average pipeline")?,
-        );
-
-        let dist_sq = Arc::new(
-            ComputePipeline::create(
-                &device,
-                ComputePipelineInfo::default(),
-                Shader::new_compute(
-                    read_blob(
-                        res_pak,
-                        res::SHADER_COMPUTE_BOUNDING_SPHERE_DIST_SQ_COMP_SPIRV,
-                    )?
-                    .as_slice(),
-                )
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
-            )
-            .context("Creating distance squared pipeline")?,
-        );
-
-        let reduce_avg = Arc::new(
+        let capabilities = DeviceCapabilities::new(device);
+        let mut create = |subgroup_key, shared_key| {
             ComputePipeline::create(
-                &device,
+                device,
                 ComputePipelineInfo::default(),
                 Shader::new_compute(
                     read_blob(
                         res_pak,
-                        res::SHADER_COMPUTE_BOUNDING_SPHERE_REDUCE_AVG_COMP_SPIRV,
+                        if capabilities.subgroup_ops {
+                            subgroup_key
+                        } else {
+                            shared_key
+                        },
                     )?
                     .as_slice(),
                 )
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
+                .specialization_info(capabilities.workgroup_specialization_info()),
             )
-            .context("Creating reduce average pipeline")?,
-        );
+            .map(Arc::new)
+        };
 
-        let reduce_dist_sq = Arc::new(
-            ComputePipeline::create(
-                &device,
-                ComputePipelineInfo::default(),
-                Shader::new_compute(
-                    read_blob(
-                        res_pak,
-                        res::SHADER_COMPUTE_BOUNDING_SPHERE_REDUCE_DIST_SQ_COMP_SPIRV,
-                    )?
-                    .as_slice(),
-                )
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
-            )
-            .context("Creating reduce distance squared pipeline")?,
-        );
+        let avg = create(
+            res::SHADER_COMPUTE_BOUNDING_SPHERE_AVG_COMP_SUBGROUP_SPIRV,
+            res::SHADER_COMPUTE_BOUNDING_SPHERE_AVG_COMP_SHARED_SPIRV,
+        )
+        .context("Creating average pipeline")?;
+        let dist_sq = create(
+            res::SHADER_COMPUTE_BOUNDING_SPHERE_DIST_SQ_COMP_SUBGROUP_SPIRV,
+            res::SHADER_COMPUTE_BOUNDING_SPHERE_DIST_SQ_COMP_SHARED_SPIRV,
+        )
+        .context("Creating distance squared pipeline")?;
+        let reduce_avg = create(
+            res::SHADER_COMPUTE_BOUNDING_SPHERE_REDUCE_AVG_COMP_SUBGROUP_SPIRV,
+            res::SHADER_COMPUTE_BOUNDING_SPHERE_REDUCE_AVG_COMP_SHARED_SPIRV,
+        )
+        .context("Creating reduce average pipeline")?;
+        let reduce_dist_sq = create(
+            res::SHADER_COMPUTE_BOUNDING_SPHERE_REDUCE_DIST_SQ_COMP_SUBGROUP_SPIRV,
+            res::SHADER_COMPUTE_BOUNDING_SPHERE_REDUCE_DIST_SQ_COMP_SHARED_SPIRV,
+        )
+        .context("Creating reduce distance squared pipeline")?;
 
         Ok(Self {
             avg,
             dist_sq,
             reduce_avg,
             reduce_dist_sq,
-            subgroup_size,
+            workgroup_size: capabilities.workgroup_size,
         })
     }
 
     #[cfg(feature = "hot-shaders")]
     pub fn new(device: &Arc<Device>) -> anyhow::Result<Self> {
-        let PhysicalDeviceVulkan11Properties { subgroup_size, .. } = device.vulkan_1_1_properties;
+        let capabilities = DeviceCapabilities::new(device);
         let shader_dir = res_shader_dir();
+        let create = |path| {
+            HotComputePipeline::create(
+                device,
+                ComputePipelineInfo::default(),
+                capabilities.hot_workgroup_ops_shader(shader_dir.join(path)),
+            )
+        };
 
-        let avg = HotComputePipeline::create(
-            &device,
-            ComputePipelineInfo::default(),
-            HotShader::new_compute(shader_dir.join("compute/bounding_sphere_avg.comp"))
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
-        )
-        .context("Creating hot average pipeline")?;
-
-        let dist_sq = HotComputePipeline::create(
-            &device,
-            ComputePipelineInfo::default(),
-            HotShader::new_compute(shader_dir.join("compute/bounding_sphere_dist_sq.comp"))
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
-        )
-        .context("Creating hot distance squared pipeline")?;
-
-        let reduce_avg = HotComputePipeline::create(
-            &device,
-            ComputePipelineInfo::default(),
-            HotShader::new_compute(shader_dir.join("compute/bounding_sphere_reduce_avg.comp"))
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
-        )
-        .context("Creating hot reduce average pipeline")?;
-
-        let reduce_dist_sq = HotComputePipeline::create(
-            &device,
-            ComputePipelineInfo::default(),
-            HotShader::new_compute(shader_dir.join("compute/bounding_sphere_reduce_dist_sq.comp"))
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
-        )
-        .context("Creating hot reduce distance squared pipeline")?;
+        let avg =
+            create("compute/bounding_sphere_avg.comp").context("Creating hot average pipeline")?;
+        let dist_sq = create("compute/bounding_sphere_dist_sq.comp")
+            .context("Creating hot distance squared pipeline")?;
+        let reduce_avg = create("compute/bounding_sphere_reduce_avg.comp")
+            .context("Creating hot reduce average pipeline")?;
+        let reduce_dist_sq = create("compute/bounding_sphere_reduce_dist_sq.comp")
+            .context("Creating hot reduce distance squared pipeline")?;
 
         Ok(Self {
             avg,
             dist_sq,
             reduce_avg,
             reduce_dist_sq,
-            subgroup_size,
+            workgroup_size: capabilities.workgroup_size,
         })
     }
 
@@ -195,8 +159,8 @@ impl BoundingSpherePipeline {
 
         let vertex_len = vertex_count * vertex_stride * size_of::<f32>() as u32;
 
-        let workgroup_count = (vertex_count + self.subgroup_size - 1) / self.subgroup_size;
-        let reduce_count = (workgroup_count + self.subgroup_size - 1) / self.subgroup_size;
+        let workgroup_count = (vertex_count + self.workgroup_size - 1) / self.workgroup_size;
+        let reduce_count = (workgroup_count + self.workgroup_size - 1) / self.workgroup_size;
 
         let avg_workgroup_buf = render_graph.bind_node(pool.lease(BufferInfo::new(
             workgroup_count as vk::DeviceSize * size_of::<Vec4>() as vk::DeviceSize,
@@ -228,7 +192,7 @@ impl BoundingSpherePipeline {
         }
 
         render_graph
-            .begin_pass("bounding sphere average")
+            .begin_pass(pass_name("bounding sphere average"))
             .bind_pipeline(self.avg())
             .read_descriptor(0, vertex_buf)
             .write_descriptor(1, avg_workgroup_buf)
@@ -248,10 +212,10 @@ impl BoundingSpherePipeline {
 
             while reduce_count > 1 {
                 let input_len = reduce_count;
-                reduce_count = (reduce_count + self.subgroup_size - 1) / self.subgroup_size;
+                reduce_count = (reduce_count + self.workgroup_size - 1) / self.workgroup_size;
 
                 render_graph
-                    .begin_pass("bounding sphere reduce average")
+                    .begin_pass(pass_name("bounding sphere reduce average"))
                     .bind_pipeline(self.reduce_avg())
                     .read_descriptor(0, input_buf)
                     .write_descriptor(1, output_buf)
@@ -280,7 +244,7 @@ impl BoundingSpherePipeline {
         );
 
         render_graph
-            .begin_pass("bounding sphere distance squared")
+            .begin_pass(pass_name("bounding sphere distance squared"))
             .bind_pipeline(self.dist_sq())
             .read_descriptor(0, vertex_buf)
             .read_descriptor_as(1, avg_buf, 0..size_of::<Vec3>() as _)
@@ -301,10 +265,10 @@ impl BoundingSpherePipeline {
 
             while reduce_count > 1 {
                 let input_len = reduce_count;
-                reduce_count = (reduce_count + self.subgroup_size - 1) / self.subgroup_size;
+                reduce_count = (reduce_count + self.workgroup_size - 1) / self.workgroup_size;
 
                 render_graph
-                    .begin_pass("bounding sphere reduce distance squared")
+                    .begin_pass(pass_name("bounding sphere reduce distance squared"))
                     .bind_pipeline(self.reduce_dist_sq())
                     .read_descriptor(0, input_buf)
                     .write_descriptor(1, output_buf)
@@ -356,23 +320,12 @@ impl BoundingSpherePipeline {
 
         res
     }
-
-    fn subgroup_specialization_info(subgroup_size: u32) -> SpecializationInfo {
-        SpecializationInfo {
-            data: subgroup_size.to_ne_bytes().to_vec(),
-            map_entries: vec![vk::SpecializationMapEntry {
-                constant_id: 0,
-                offset: 0,
-                size: size_of::<u32>(),
-            }],
-        }
-    }
 }
 
 #[cfg(test)]
-pub(super) mod tests {
+mod tests {
     use {
-        super::*,
+        super::{super::tests::TestDevice, *},
         bytemuck::{cast_slice, from_bytes, NoUninit, Pod, Zeroable},
         glam::{vec3, Vec3},
         rand::{rngs::SmallRng, Rng, SeedableRng},
@@ -400,7 +353,7 @@ pub(super) mod tests {
     ) where
         T: NoUninit,
     {
-        let device = Arc::new(Device::create_headless(DeviceInfo::new()).unwrap());
+        let device = TestDevice::new();
         let mut pool = LazyPool::new(&device);
 
         #[cfg(not(feature = "hot-shaders"))]
@@ -486,7 +439,7 @@ pub(super) mod tests {
         );
     }
 
-    #[cfg_attr(not(target_os = "macos"), test)]
+    #[test]
     pub fn bounding_sphere1() {
         let mut rng = SmallRng::seed_from_u64(42);
         let vertices = repeat_with(|| {
@@ -506,7 +459,7 @@ pub(super) mod tests {
         assert_bounding_sphere(&vertices, Vec3::ZERO, 1.0, 0.01);
     }
 
-    #[cfg_attr(not(target_os = "macos"), test)]
+    #[test]
     pub fn bounding_sphere2() {
         let mut vertices = repeat_with(|| [0f32, 0.0, 0.0])
             .take(29)
@@ -518,7 +471,7 @@ pub(super) mod tests {
         assert_bounding_sphere(&vertices, Vec3::ZERO, 4.0, 0.0001);
     }
 
-    #[cfg_attr(not(target_os = "macos"), test)]
+    #[test]
     pub fn bounding_sphere3() {
         let vertices = [
             vec3(2.0, 1.0, -1.0).to_array(),
@@ -1,10 +1,12 @@
 #![allow(unused)]
 
 use {
-    glam::{Mat4, Vec3},
-    std::{cell::Cell, ops::Range},
+    glam::{uvec2, vec2, vec3, Mat4, Quat, UVec2, Vec2, Vec3, Vec4},
+    serde::Deserialize,
+    std::{cell::Cell, f32::consts::TAU, ops::Range},
 };
 
+#[derive(Clone, Copy, Debug)]
 pub struct Camera {
     pub aspect_ratio: f32,
     pub fov_y: f32,
@@ -12,3 +14,557 @@ pub struct Camera {
     pub yaw: f32,
     pub position: Vec3,
 }
+
+impl Camera {
+    /// Distance to the near plane; there is no far plane because depth is stored reversed (near
+    /// is 1.0 and infinity is 0.0) which keeps precision high across large levels.
+    pub const Z_NEAR: f32 = 0.1;
+
+    /// Returns a camera at `position` looking along `-Z` turned by `rotation`, which is the way
+    /// scene refs face. Any roll of `rotation` is lost.
+    pub fn from_transform(position: Vec3, rotation: Quat, fov_y: f32) -> Self {
+        let forward = rotation * -Vec3::Z;
+
+        Self {
+            aspect_ratio: 0.0,
+            fov_y,
+            pitch: forward.y.clamp(-1.0, 1.0).asin().to_degrees(),
+            yaw: (-forward.x).atan2(-forward.z).to_degrees(),
+            position,
+        }
+    }
+
+    /// Returns the reverse-Z projection of this camera, with an infinite far plane, multiplied by
+    /// its view transform.
+    pub fn projection_view(&self, aspect_ratio: f32, z_near: f32) -> Mat4 {
+        let rotation = Quat::from_rotation_y(self.yaw.to_radians())
+            * Quat::from_rotation_x(self.pitch.to_radians());
+
+        // Equivalent to a left-handed look-at matrix with -Y up, except that it remains valid when
+        // looking straight up or down (such as when capturing reflection probes)
+        let view = Mat4::from_scale(vec3(1.0, -1.0, -1.0))
+            * Mat4::from_quat(rotation.inverse())
+            * Mat4::from_translation(-self.position);
+        let projection =
+            Mat4::perspective_infinite_reverse_lh(self.fov_y.to_radians(), aspect_ratio, z_near);
+
+        projection * view
+    }
+}
+
+/// Returns the planes which bound the view of a reverse-Z projection with an infinite far plane:
+/// the four sides and then the near plane. Each is `(normal, distance)` with the normal facing
+/// into the view, so points in view have a non-negative `normal.dot(point) + distance`.
+pub fn frustum_planes(projection_view: Mat4) -> [Vec4; 5] {
+    let rows = projection_view.transpose();
+
+    // Depth is 1.0 at the near plane and approaches 0.0 at infinity, so there is no far plane
+    [
+        rows.w_axis + rows.x_axis,
+        rows.w_axis - rows.x_axis,
+        rows.w_axis + rows.y_axis,
+        rows.w_axis - rows.y_axis,
+        rows.w_axis - rows.z_axis,
+    ]
+    .map(|plane| plane / plane.truncate().length())
+}
+
+/// Returns the position, in framebuffer pixels, of a world position in front of the camera.
+pub fn project(projection_view: Mat4, framebuffer_size: Vec2, position: Vec3) -> Option<Vec2> {
+    let clip = projection_view * position.extend(1.0);
+
+    (clip.w > 0.0).then(|| (vec2(clip.x, clip.y) / clip.w + 1.0) * 0.5 * framebuffer_size)
+}
+
+/// A rectangle of a framebuffer, in pixels, which one camera is drawn into; several viewports let
+/// split-screen players, mirrors or security camera monitors share one framebuffer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Viewport {
+    pub offset: UVec2,
+    pub extent: UVec2,
+}
+
+impl Viewport {
+    /// Returns the viewport which covers a whole framebuffer.
+    pub fn full(width: u32, height: u32) -> Self {
+        Self {
+            offset: UVec2::ZERO,
+            extent: uvec2(width, height),
+        }
+    }
+
+    pub fn aspect_ratio(self) -> f32 {
+        self.extent.x as f32 / self.extent.y.max(1) as f32
+    }
+
+    pub fn contains(self, position: UVec2) -> bool {
+        position.cmpge(self.offset).all() && position.cmplt(self.offset + self.extent).all()
+    }
+
+    /// Splits this viewport into a grid, such as two columns for two split-screen players,
+    /// returning the cells row by row; the last row and column take any leftover pixels.
+    pub fn split(self, columns: u32, rows: u32) -> impl Iterator<Item = Self> {
+        let grid = uvec2(columns.max(1), rows.max(1));
+        let cell = self.extent / grid;
+
+        (0..grid.y).flat_map(move |row| {
+            (0..grid.x).map(move |column| {
+                let index = uvec2(column, row);
+                let offset = self.offset + index * cell;
+                let end = UVec2::select(
+                    (index + 1).cmpeq(grid),
+                    self.offset + self.extent,
+                    offset + cell,
+                );
+
+                Self {
+                    offset,
+                    extent: end - offset,
+                }
+            })
+        })
+    }
+}
+
+/// Procedural motion composed onto a [`Camera`] without changing it: head bob while walking, a
+/// wider field of view while sprinting and shake from gameplay events such as explosions.
+///
+/// Gameplay keeps using the undisturbed camera, so aiming and collision are unaffected.
+#[derive(Debug, Default)]
+pub struct CameraEffects {
+    bob_phase: f32,
+
+    /// Fades head bob in and out as walking starts and stops.
+    bob_weight: f32,
+
+    fov_kick: f32,
+    shakes: Vec<CameraShake>,
+    time: f32,
+}
+
+impl CameraEffects {
+    /// Vertical distance the head moves at each step.
+    const BOB_HEIGHT: f32 = 0.03;
+
+    /// Sideways distance the head sways with each pair of steps.
+    const BOB_SWAY: f32 = 0.02;
+
+    /// Radians of bob phase per unit walked; each step is half a cycle of sway.
+    const BOB_PHASE_PER_DISTANCE: f32 = 2.6;
+
+    /// Rate, per second, at which head bob and field of view kick approach their targets.
+    const EASE_RATE: f32 = 8.0;
+
+    /// Degrees added to the field of view while sprinting.
+    const FOV_KICK: f32 = 8.0;
+
+    /// Largest pitch and yaw change, in degrees, of a shake with a strength of one.
+    const SHAKE_ANGLE: f32 = 2.0;
+
+    /// Largest offset of a shake with a strength of one.
+    const SHAKE_OFFSET: f32 = 0.05;
+
+    /// Returns the camera with every effect applied.
+    pub fn apply(&self, camera: &Camera) -> Camera {
+        let yaw = camera.yaw.to_radians();
+        let right = vec3(yaw.cos(), 0.0, -yaw.sin());
+        let bob = self.bob_weight
+            * (right * self.bob_phase.sin() * Self::BOB_SWAY
+                + Vec3::Y * (2.0 * self.bob_phase).sin().abs() * Self::BOB_HEIGHT);
+
+        let strength = self.shake_strength();
+        let t = self.time;
+
+        // Unrelated frequencies make the shake look random without a noise function
+        let shake = |phase: f32| {
+            strength * (0.6 * (t * 37.0 + phase).sin() + 0.4 * (t * 23.0 + 2.0 * phase).sin())
+        };
+
+        Camera {
+            aspect_ratio: camera.aspect_ratio,
+            fov_y: camera.fov_y + self.fov_kick,
+            pitch: camera.pitch + shake(0.0) * Self::SHAKE_ANGLE,
+            yaw: camera.yaw + shake(1.7) * Self::SHAKE_ANGLE,
+            position: camera.position
+                + bob
+                + vec3(shake(3.1), shake(4.3), shake(5.9)) * Self::SHAKE_OFFSET,
+        }
+    }
+
+    /// Shakes the camera with the given strength, which fades out over `duration` seconds; a
+    /// strength of one is a nearby explosion.
+    ///
+    /// Shakes which overlap add together.
+    pub fn shake(&mut self, strength: f32, duration: f32) {
+        if strength > 0.0 && duration > 0.0 {
+            self.shakes.push(CameraShake {
+                duration,
+                remaining: duration,
+                strength,
+            });
+        }
+    }
+
+    fn shake_strength(&self) -> f32 {
+        self.shakes
+            .iter()
+            .map(|shake| {
+                let fade = shake.remaining / shake.duration;
+
+                shake.strength * fade * fade
+            })
+            .sum()
+    }
+
+    /// Advances the effects by `dt` seconds for a player moving at `speed` units per second.
+    ///
+    /// `head_bob` scales the head bob, where zero disables it for players prone to motion
+    /// sickness.
+    pub fn update(&mut self, dt: f32, speed: f32, is_sprinting: bool, head_bob: f32) {
+        let ease = 1.0 - (-Self::EASE_RATE * dt).exp();
+        let bob_weight = if speed > 0.1 { head_bob.max(0.0) } else { 0.0 };
+        let fov_kick = if is_sprinting { Self::FOV_KICK } else { 0.0 };
+
+        self.bob_phase = (self.bob_phase + speed * dt * Self::BOB_PHASE_PER_DISTANCE) % TAU;
+        self.bob_weight += (bob_weight - self.bob_weight) * ease;
+        self.fov_kick += (fov_kick - self.fov_kick) * ease;
+        self.time += dt;
+
+        self.shakes.retain_mut(|shake| {
+            shake.remaining -= dt;
+            shake.remaining > 0.0
+        });
+
+        if self.shakes.is_empty() {
+            self.time = 0.0;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CameraShake {
+    duration: f32,
+    remaining: f32,
+    strength: f32,
+}
+
+/// A camera pose at a given time along a [`CameraPath`].
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct CameraKeyframe {
+    /// Seconds since the start of the path.
+    pub time: f32,
+    pub position: [f32; 3],
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+/// A scripted camera flythrough made of linearly interpolated keyframes.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CameraPath {
+    #[serde(rename = "keyframe")]
+    keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    /// Parses a camera path from TOML text containing `[[keyframe]]` tables.
+    pub fn from_toml(txt: &str) -> anyhow::Result<Self> {
+        let mut res: Self = toml::from_str(txt)?;
+
+        anyhow::ensure!(!res.keyframes.is_empty(), "Camera path has no keyframes");
+
+        res.keyframes
+            .sort_by(|lhs, rhs| lhs.time.total_cmp(&rhs.time));
+
+        Ok(res)
+    }
+
+    /// Returns a path which circles `center` once over `duration` seconds, looking at it from
+    /// `height` above.
+    pub fn orbit(center: Vec3, radius: f32, height: f32, duration: f32) -> Self {
+        const KEYFRAME_COUNT: usize = 33;
+
+        let pitch = -height.atan2(radius).to_degrees();
+        let keyframes = (0..KEYFRAME_COUNT)
+            .map(|idx| {
+                let t = idx as f32 / (KEYFRAME_COUNT - 1) as f32;
+                let yaw = t * 360.0;
+                let direction = Quat::from_rotation_y(yaw.to_radians()).mul_vec3(Vec3::Z);
+
+                CameraKeyframe {
+                    time: t * duration,
+                    position: (center + direction * radius + Vec3::Y * height).to_array(),
+                    pitch,
+                    yaw,
+                }
+            })
+            .collect();
+
+        Self { keyframes }
+    }
+
+    /// Total length of the path in seconds.
+    pub fn duration(&self) -> f32 {
+        self.keyframes
+            .last()
+            .map(|keyframe| keyframe.time)
+            .unwrap_or_default()
+    }
+
+    /// Moves the camera to the interpolated pose at the given time, which is clamped to the path.
+    pub fn sample(&self, time: f32, camera: &mut Camera) {
+        let next_idx = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time > time)
+            .unwrap_or(self.keyframes.len() - 1);
+        let prev_idx = next_idx.saturating_sub(1);
+        let prev = self.keyframes[prev_idx];
+        let next = self.keyframes[next_idx];
+
+        let t = if next.time > prev.time {
+            ((time - prev.time) / (next.time - prev.time)).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        camera.position = Vec3::from_array(prev.position).lerp(Vec3::from_array(next.position), t);
+        camera.pitch = prev.pitch + (next.pitch - prev.pitch) * t;
+        camera.yaw = prev.yaw + (next.yaw - prev.yaw) * t;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::f32::consts::FRAC_PI_2};
+
+    fn assert_approx(lhs: f32, rhs: f32) {
+        assert!(
+            lhs.is_finite() && rhs.is_finite() && (lhs - rhs).abs() < 1e-5,
+            "{lhs} is not approximately {rhs}"
+        );
+    }
+
+    #[test]
+    pub fn project_position() {
+        let camera = Camera {
+            aspect_ratio: 2.0,
+            fov_y: 90.0,
+            pitch: 0.0,
+            yaw: 0.0,
+            position: Vec3::ZERO,
+        };
+        let projection_view = camera.projection_view(camera.aspect_ratio, Camera::Z_NEAR);
+        let framebuffer_size = vec2(200.0, 100.0);
+
+        // The camera looks down -Z, with +X to the right and +Y up the screen
+        let center = project(projection_view, framebuffer_size, -Vec3::Z).unwrap();
+
+        assert!(center.distance(vec2(100.0, 50.0)) < 0.01);
+
+        let right = project(projection_view, framebuffer_size, Vec3::new(1.0, 0.0, -1.0));
+        let up = project(projection_view, framebuffer_size, Vec3::new(0.0, 1.0, -1.0));
+
+        assert!(right.unwrap().x > center.x);
+        assert!(up.unwrap().y < center.y);
+        assert!(project(projection_view, framebuffer_size, Vec3::Z).is_none());
+    }
+
+    #[test]
+    pub fn projection_view_look_at() {
+        let mut camera = Camera {
+            aspect_ratio: 1.0,
+            fov_y: 60.0,
+            pitch: 30.0,
+            yaw: -40.0,
+            position: vec3(1.0, 2.0, 3.0),
+        };
+
+        let rotation = Quat::from_rotation_y(camera.yaw.to_radians())
+            * Quat::from_rotation_x(camera.pitch.to_radians());
+        let look_at = Mat4::perspective_infinite_reverse_lh(60f32.to_radians(), 1.0, 0.1)
+            * Mat4::look_at_lh(
+                camera.position,
+                camera.position - rotation.mul_vec3(Vec3::Z),
+                -Vec3::Y,
+            );
+
+        assert!(camera.projection_view(1.0, 0.1).abs_diff_eq(look_at, 1e-5));
+
+        // Looking straight down must not produce NaN
+        camera.pitch = -90.0;
+
+        assert!(camera.projection_view(1.0, 0.1).is_finite());
+    }
+
+    #[test]
+    pub fn from_transform() {
+        let rotation = Quat::from_rotation_y(0.7) * Quat::from_rotation_x(-0.3);
+        let camera = Camera::from_transform(Vec3::ONE, rotation, 60.0);
+
+        assert_approx(camera.yaw, 0.7f32.to_degrees());
+        assert_approx(camera.pitch, -0.3f32.to_degrees());
+
+        let camera = Camera::from_transform(Vec3::ZERO, Quat::from_rotation_x(FRAC_PI_2), 60.0);
+
+        // Looking straight up loses a little precision
+        assert!((camera.pitch - 90.0).abs() < 0.1);
+    }
+
+    #[test]
+    pub fn frustum() {
+        let camera = Camera {
+            aspect_ratio: 1.0,
+            fov_y: 90.0,
+            pitch: 0.0,
+            yaw: 0.0,
+            position: Vec3::ZERO,
+        };
+        let planes = frustum_planes(camera.projection_view(1.0, 0.1));
+        let is_visible = |point: Vec3, radius: f32| {
+            planes
+                .iter()
+                .all(|plane| plane.truncate().dot(point) + plane.w >= -radius)
+        };
+
+        // The camera looks along -Z
+        assert!(is_visible(vec3(0.0, 0.0, -10.0), 0.0));
+        assert!(is_visible(vec3(0.0, 0.0, -1e6), 0.0));
+        assert!(!is_visible(vec3(0.0, 0.0, 10.0), 1.0));
+        assert!(!is_visible(vec3(20.0, 0.0, -10.0), 1.0));
+        assert!(is_visible(vec3(20.0, 0.0, -10.0), 8.0));
+        assert!(!is_visible(vec3(0.0, 0.0, -0.05), 0.01));
+    }
+
+    #[test]
+    pub fn viewport_split() {
+        let viewports = Viewport::full(1921, 1080).split(2, 1).collect::<Vec<_>>();
+
+        assert_eq!(
+            viewports,
+            [
+                Viewport {
+                    offset: UVec2::ZERO,
+                    extent: uvec2(960, 1080)
+                },
+                Viewport {
+                    offset: uvec2(960, 0),
+                    extent: uvec2(961, 1080)
+                }
+            ]
+        );
+        assert!(viewports[1].contains(uvec2(960, 0)));
+        assert!(!viewports[1].contains(uvec2(959, 0)));
+        assert!(!viewports[1].contains(uvec2(1921, 0)));
+        assert_eq!(Viewport::full(4, 4).split(2, 2).count(), 4);
+    }
+
+    #[test]
+    pub fn camera_effects() {
+        let camera = Camera {
+            aspect_ratio: 1.0,
+            fov_y: 45.0,
+            pitch: 0.0,
+            yaw: 0.0,
+            position: Vec3::ZERO,
+        };
+        let mut effects = CameraEffects::default();
+
+        // Sprinting widens the field of view, which returns once the player stops
+        for _ in 0..120 {
+            effects.update(1.0 / 60.0, 6.0, true, 1.0);
+        }
+
+        assert!(effects.apply(&camera).fov_y > 45.0 + CameraEffects::FOV_KICK * 0.9);
+        assert!(effects.apply(&camera).position != Vec3::ZERO);
+
+        for _ in 0..120 {
+            effects.update(1.0 / 60.0, 0.0, false, 1.0);
+        }
+
+        assert!(effects.apply(&camera).fov_y < 45.0 + 0.1);
+        assert!(effects.apply(&camera).position.length() < 1e-3);
+
+        // Shakes fade out completely once their duration has passed
+        effects.shake(1.0, 0.5);
+        effects.update(0.1, 0.0, false, 1.0);
+
+        assert!(effects.shake_strength() > 0.0);
+
+        effects.update(0.5, 0.0, false, 1.0);
+
+        assert_approx(effects.shake_strength(), 0.0);
+        assert_approx(effects.apply(&camera).pitch, 0.0);
+    }
+
+    #[test]
+    pub fn camera_path_orbit() {
+        let center = vec3(1.0, 2.0, 3.0);
+        let path = CameraPath::orbit(center, 10.0, 10.0, 8.0);
+        let mut camera = Camera {
+            aspect_ratio: 1.0,
+            fov_y: 45.0,
+            pitch: 0.0,
+            yaw: 0.0,
+            position: Vec3::ZERO,
+        };
+
+        assert_approx(path.duration(), 8.0);
+
+        for time in [0.0, 1.0, 2.5, 6.0] {
+            path.sample(time, &mut camera);
+
+            // The camera always looks down at the center
+            let rotation = Quat::from_rotation_y(camera.yaw.to_radians())
+                * Quat::from_rotation_x(camera.pitch.to_radians());
+            let forward = -rotation.mul_vec3(Vec3::Z);
+            let to_center = (center - camera.position).normalize();
+
+            assert!(forward.dot(to_center) > 0.999);
+            assert_approx(camera.pitch, -45.0);
+        }
+    }
+
+    #[test]
+    pub fn camera_path_sample() {
+        let path = CameraPath::from_toml(
+            r#"
+            [[keyframe]]
+            time = 2.0
+            position = [10.0, 0.0, 0.0]
+            pitch = 10.0
+            yaw = 90.0
+
+            [[keyframe]]
+            time = 0.0
+            position = [0.0, 0.0, 0.0]
+            pitch = 0.0
+            yaw = 0.0
+            "#,
+        )
+        .unwrap();
+        let mut camera = Camera {
+            aspect_ratio: 1.0,
+            fov_y: 45.0,
+            pitch: 0.0,
+            yaw: 0.0,
+            position: Vec3::ZERO,
+        };
+
+        assert_approx(path.duration(), 2.0);
+
+        path.sample(1.0, &mut camera);
+
+        assert_approx(camera.position.x, 5.0);
+        assert_approx(camera.pitch, 5.0);
+        assert_approx(camera.yaw, 45.0);
+
+        path.sample(3.0, &mut camera);
+
+        assert_approx(camera.position.x, 10.0);
+        assert_approx(camera.yaw, 90.0);
+
+        path.sample(-1.0, &mut camera);
+
+        assert_approx(camera.position.x, 0.0);
+    }
+}
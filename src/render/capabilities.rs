@@ -0,0 +1,105 @@
+//! What the device supports beyond core Vulkan, so that pipelines select shader variants and
+//! features are disabled cleanly on drivers which lack them.
+//!
+//! Portability-subset drivers, such as MoltenVK which implements Vulkan over Metal, are detected
+//! at run time rather than by the platform the game was built for, so the same binary runs on
+//! those drivers and on native ones.
+
+use {screen_13::prelude::*, std::mem::size_of};
+
+#[cfg(feature = "hot-shaders")]
+use {super::hot_shader::watch, screen_13_hot::prelude::*, std::path::PathBuf};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DeviceCapabilities {
+    /// Mesh instances may be drawn by task and mesh shaders instead of indirect draw commands.
+    pub mesh_shaders: bool,
+
+    /// Set for drivers which only implement the portability subset of Vulkan.
+    pub portability_subset: bool,
+
+    pub ray_tracing: bool,
+
+    /// Compute shaders which reduce or scan run each workgroup as a single subgroup using
+    /// subgroup arithmetic; otherwise they use shared memory.
+    pub subgroup_ops: bool,
+
+    /// Debug views may draw the edges of triangles.
+    pub wireframe: bool,
+
+    /// Invocations of each workgroup of compute shaders which reduce or scan; inputs of those
+    /// shaders are aligned to it.
+    pub workgroup_size: u32,
+}
+
+impl DeviceCapabilities {
+    /// Macro which compiles reductions and scans using shared memory instead of subgroup
+    /// arithmetic, see `compute/workgroup_ops.glsl`.
+    pub const SHARED_WORKGROUP_OPS: &str = "SHARED_WORKGROUP_OPS";
+
+    /// Workgroup size of reductions and scans which use shared memory; a power of two.
+    const SHARED_WORKGROUP_SIZE: u32 = 64;
+
+    pub fn new(device: &Device) -> Self {
+        Self::from_physical_device(&device.physical_device)
+    }
+
+    /// Returns the capabilities of a device before it is created, such as while choosing one.
+    pub fn from_physical_device(physical_device: &PhysicalDevice) -> Self {
+        let portability_subset =
+            physical_device.properties_v1_2.driver_id == vk::DriverId::MOLTENVK;
+        let Vulkan11Properties {
+            subgroup_size,
+            subgroup_supported_operations,
+            subgroup_supported_stages,
+            ..
+        } = physical_device.properties_v1_1;
+
+        // Metal picks the SIMD width of each pipeline itself, so on portability drivers a
+        // workgroup of the reported subgroup size may span several subgroups
+        let subgroup_ops = !portability_subset
+            && subgroup_size.is_power_of_two()
+            && subgroup_supported_stages.contains(vk::ShaderStageFlags::COMPUTE)
+            && subgroup_supported_operations
+                .contains(vk::SubgroupFeatureFlags::BASIC | vk::SubgroupFeatureFlags::ARITHMETIC);
+
+        Self {
+            mesh_shaders: !portability_subset && physical_device.mesh_shader_properties.is_some(),
+            portability_subset,
+            ray_tracing: !portability_subset && physical_device.ray_trace_properties.is_some(),
+            subgroup_ops,
+            wireframe: physical_device.features_v1_0.fill_mode_non_solid,
+            workgroup_size: if subgroup_ops {
+                subgroup_size
+            } else {
+                Self::SHARED_WORKGROUP_SIZE
+            },
+        }
+    }
+
+    /// Returns a hot compute shader which reduces or scans, compiled for how this device runs
+    /// them and specialized with the workgroup size.
+    #[cfg(feature = "hot-shaders")]
+    pub fn hot_workgroup_ops_shader(self, path: PathBuf) -> HotShaderBuilder {
+        let shader = HotShader::new_compute(watch(path))
+            .specialization_info(self.workgroup_specialization_info());
+
+        if self.subgroup_ops {
+            shader
+        } else {
+            shader.macro_definition(Self::SHARED_WORKGROUP_OPS, None)
+        }
+    }
+
+    /// Specializes the workgroup size of compute shaders declared with `local_size_x_id = 0`.
+    pub fn workgroup_specialization_info(self) -> SpecializationInfo {
+        SpecializationInfo {
+            data: self.workgroup_size.to_ne_bytes().to_vec(),
+            map_entries: vec![vk::SpecializationMapEntry {
+                constant_id: 0,
+                offset: 0,
+                size: size_of::<u32>(),
+            }],
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Filters which shift the colors of each frame so that players with a color vision deficiency
+//! may tell apart colors they would otherwise confuse, such as the red and green of a hit marker
+//! against foliage.
+//!
+//! Each filter simulates how a color is seen with the deficiency and moves the difference into
+//! channels which are still seen ("daltonization"). The filter is baked into a small 3D lookup
+//! table which the present pass samples, so that the cost does not depend on the filter.
+
+use {
+    super::lease_buffer,
+    glam::{Mat3, Vec3},
+    screen_13::prelude::*,
+    serde::{Deserialize, Serialize},
+    std::{fmt, sync::Arc},
+};
+
+/// Texels along each side of the lookup table; the present pass blends between them.
+const LUT_SIZE: u32 = 16;
+
+/// Creates the lookup table image, which [`ColorblindFilter::record_lut`] fills.
+pub fn create_lut(device: &Arc<Device>) -> Result<Arc<Image>, DriverError> {
+    Ok(Arc::new(Image::create(
+        device,
+        ImageInfo::new_3d(
+            vk::Format::R8G8B8A8_UNORM,
+            LUT_SIZE,
+            LUT_SIZE,
+            LUT_SIZE,
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        ),
+    )?))
+}
+
+/// Converts RGB to the response of the long, medium and short wavelength cones.
+fn rgb_to_lms() -> Mat3 {
+    Mat3::from_cols_array_2d(&[
+        [17.8824, 43.5161, 4.11935],
+        [3.45565, 27.1554, 3.86714],
+        [0.0299566, 0.184309, 1.46709],
+    ])
+    .transpose()
+}
+
+fn lms_to_rgb() -> Mat3 {
+    Mat3::from_cols_array_2d(&[
+        [0.0809444479, -0.130504409, 0.116721066],
+        [-0.0102485335, 0.0540193266, -0.113614708],
+        [-0.000365296938, -0.00412161469, 0.693511405],
+    ])
+    .transpose()
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ColorblindFilter {
+    #[default]
+    Off,
+
+    /// For players who do not see green well.
+    Deuteranopia,
+
+    /// For players who do not see red well.
+    Protanopia,
+
+    /// For players who do not see blue well.
+    Tritanopia,
+}
+
+impl ColorblindFilter {
+    pub const ALL: [Self; 4] = [
+        Self::Off,
+        Self::Deuteranopia,
+        Self::Protanopia,
+        Self::Tritanopia,
+    ];
+
+    /// Returns the color, with channels from zero to one, which is shown in place of the given
+    /// color.
+    pub fn apply(self, color: Vec3) -> Vec3 {
+        let Some(simulation) = self.simulation() else {
+            return color;
+        };
+
+        let seen = lms_to_rgb() * simulation * rgb_to_lms() * color;
+
+        // Differences which are not seen are moved into the green and blue channels, where they are
+        let shift = Mat3::from_cols_array_2d(&[[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]])
+            .transpose();
+
+        (color + shift * (color - seen)).clamp(Vec3::ZERO, Vec3::ONE)
+    }
+
+    pub fn index(self) -> usize {
+        Self::ALL.iter().position(|&filter| filter == self).unwrap()
+    }
+
+    /// Returns the following filter, wrapping around to [`ColorblindFilter::Off`] after the last
+    /// one.
+    pub fn next(self) -> Self {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()]
+    }
+
+    /// Returns the texels of the lookup table, `RGBA8` with red varying fastest and then green.
+    fn lut_texels(self) -> Vec<u8> {
+        let scale = 1.0 / (LUT_SIZE - 1) as f32;
+        let mut res = Vec::with_capacity(LUT_SIZE.pow(3) as usize * 4);
+
+        for b in 0..LUT_SIZE {
+            for g in 0..LUT_SIZE {
+                for r in 0..LUT_SIZE {
+                    let color = Vec3::new(r as f32, g as f32, b as f32) * scale;
+                    let [r, g, b] = (self.apply(color) * 255.0).round().to_array();
+
+                    res.extend_from_slice(&[r as u8, g as u8, b as u8, 0xff]);
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Records uploading the lookup table of this filter into an image from [`create_lut`],
+    /// which the present pass samples with the color of each pixel.
+    pub fn record_lut(
+        self,
+        pool: &mut impl Pool<BufferInfoBuilder, Buffer>,
+        render_graph: &mut RenderGraph,
+        lut: impl Into<AnyImageNode>,
+    ) -> Result<(), DriverError> {
+        let buf = lease_buffer(pool, &self.lut_texels(), vk::BufferUsageFlags::TRANSFER_SRC)?;
+        let buf = render_graph.bind_node(buf);
+
+        render_graph.copy_buffer_to_image(buf, lut);
+
+        Ok(())
+    }
+
+    /// Returns how the cones see colors with this deficiency.
+    fn simulation(self) -> Option<Mat3> {
+        let rows = match self {
+            Self::Off => return None,
+            Self::Deuteranopia => [[1.0, 0.0, 0.0], [0.494207, 0.0, 1.24827], [0.0, 0.0, 1.0]],
+            Self::Protanopia => [[0.0, 2.02344, -2.52581], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            Self::Tritanopia => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [-0.395913, 0.801109, 0.0]],
+        };
+
+        Some(Mat3::from_cols_array_2d(&rows).transpose())
+    }
+}
+
+impl fmt::Display for ColorblindFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Off => "Off",
+            Self::Deuteranopia => "Deuteranopia",
+            Self::Protanopia => "Protanopia",
+            Self::Tritanopia => "Tritanopia",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn filters_keep_greys() {
+        for filter in ColorblindFilter::ALL {
+            for grey in [0.0, 0.5, 1.0] {
+                let color = Vec3::splat(grey);
+
+                assert!(filter.apply(color).abs_diff_eq(color, 1e-3), "{filter}");
+            }
+        }
+
+        let red = Vec3::X;
+
+        assert_eq!(ColorblindFilter::Off.apply(red), red);
+        assert_ne!(ColorblindFilter::Deuteranopia.apply(red), red);
+    }
+
+    #[test]
+    pub fn lut_texels() {
+        let texels = ColorblindFilter::Off.lut_texels();
+
+        assert_eq!(texels.len(), LUT_SIZE.pow(3) as usize * 4);
+        assert_eq!(&texels[0..4], &[0x00, 0x00, 0x00, 0xff]);
+        assert_eq!(&texels[4..8], &[0x11, 0x00, 0x00, 0xff]);
+        assert_eq!(&texels[texels.len() - 4..], &[0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    pub fn next_wraps() {
+        assert_eq!(ColorblindFilter::Off.next(), ColorblindFilter::Deuteranopia);
+        assert_eq!(ColorblindFilter::Tritanopia.next(), ColorblindFilter::Off);
+    }
+}
@@ -0,0 +1,70 @@
+//! Block-compressed variants of the material bitmaps in `art.pak`, which `build.rs` bakes into a
+//! file next to the pak and the loader uploads directly when the device supports BC textures.
+//!
+//! This module is also compiled by `build.rs` and so may only depend on `pak` and `serde`.
+
+use {
+    pak::BitmapId,
+    serde::{Deserialize, Serialize},
+    std::collections::HashMap,
+};
+
+/// Name of the file, next to `art.pak`, which holds the compressed bitmaps.
+pub const FILE_NAME: &str = "art_bc.bin";
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum CompressedFormat {
+    /// Two channels, used for normal maps which store only X and Y.
+    Bc5,
+
+    /// Four channels, used for all other bitmaps.
+    Bc7,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CompressedBitmap {
+    pub format: CompressedFormat,
+    pub has_alpha: bool,
+    pub width: u32,
+    pub height: u32,
+
+    /// Blocks of each mip level, starting with the full size image.
+    pub mips: Vec<Vec<u8>>,
+}
+
+impl CompressedBitmap {
+    /// Width and height, in pixels, of each block.
+    pub const BLOCK_SIZE: u32 = 4;
+
+    /// Size, in bytes, of each block for both BC5 and BC7.
+    pub const BLOCK_LEN: usize = 16;
+
+    /// Returns the number of bytes in the given mip level of a bitmap of the given size.
+    pub fn mip_len(width: u32, height: u32, mip_level: u32) -> usize {
+        let (width, height) = Self::mip_size(width, height, mip_level);
+        let blocks_x = (width + Self::BLOCK_SIZE - 1) / Self::BLOCK_SIZE;
+        let blocks_y = (height + Self::BLOCK_SIZE - 1) / Self::BLOCK_SIZE;
+
+        (blocks_x * blocks_y) as usize * Self::BLOCK_LEN
+    }
+
+    /// Returns the size, in pixels, of the given mip level of a bitmap of the given size.
+    pub fn mip_size(width: u32, height: u32, mip_level: u32) -> (u32, u32) {
+        ((width >> mip_level).max(1), (height >> mip_level).max(1))
+    }
+}
+
+pub type CompressedBitmaps = HashMap<BitmapId, CompressedBitmap>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn mip_lens() {
+        assert_eq!(CompressedBitmap::mip_len(256, 256, 0), 64 * 64 * 16);
+        assert_eq!(CompressedBitmap::mip_len(256, 256, 6), 16);
+        assert_eq!(CompressedBitmap::mip_len(256, 256, 8), 16);
+        assert_eq!(CompressedBitmap::mip_len(10, 6, 0), 3 * 2 * 16);
+    }
+}
@@ -0,0 +1,142 @@
+use {
+    libloading::Library,
+    renderdoc::{RenderDoc, V141},
+    std::{
+        mem::{forget, take},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex,
+        },
+    },
+};
+
+#[cfg(target_os = "linux")]
+const LIBRARY_NAME: &str = "librenderdoc.so";
+
+#[cfg(target_os = "macos")]
+const LIBRARY_NAME: &str = "librenderdoc.dylib";
+
+#[cfg(target_os = "windows")]
+const LIBRARY_NAME: &str = "renderdoc.dll";
+
+/// Set while the frame being captured is recorded, so that [`pass_name`] notes the names of its
+/// passes.
+static IS_RECORDING_PASS_NAMES: AtomicBool = AtomicBool::new(false);
+
+/// Names of the passes of the frame being captured, in the order they were recorded.
+static PASS_NAMES: Mutex<Vec<String>> = Mutex::new(vec![]);
+
+/// Returns the given render graph pass name, noting it so that a pending frame capture lists it.
+///
+/// Every pass is named using this, so that captures show which passes made the frame.
+pub fn pass_name<N: AsRef<str>>(name: N) -> N {
+    if IS_RECORDING_PASS_NAMES.load(Ordering::Relaxed) {
+        if let Ok(mut pass_names) = PASS_NAMES.lock() {
+            pass_names.push(name.as_ref().to_owned());
+        }
+    }
+
+    name
+}
+
+/// Captures single frames using RenderDoc, which is much faster than launching the game through
+/// the RenderDoc UI each time.
+///
+/// Captures are written to the RenderDoc temporary directory and opened from the RenderDoc UI
+/// using its "Attach to running instance" option, or directly from the file.
+pub struct FrameCapture {
+    api: RenderDoc<V141>,
+
+    /// Comments written to the pending capture once RenderDoc has finished it.
+    comments: Option<String>,
+
+    /// Set from a trigger until the following update, which starts noting pass names.
+    is_triggered: bool,
+
+    /// Number of captures RenderDoc had made as of the previous update.
+    num_captures: u32,
+}
+
+impl FrameCapture {
+    /// Returns the RenderDoc API if RenderDoc launched this process, otherwise when `load` is set,
+    /// loads the installed RenderDoc library.
+    ///
+    /// RenderDoc hooks Vulkan when it is loaded, so this must be called before the device is
+    /// created.
+    pub fn load(load: bool) -> Option<Self> {
+        if load {
+            // Safety: RenderDoc is made to be loaded into any process, where it hooks graphics APIs
+            match unsafe { Library::new(LIBRARY_NAME) } {
+                // RenderDoc must stay loaded for as long as the device exists
+                Ok(library) => forget(library),
+                Err(err) => warn!("Unable to load {LIBRARY_NAME}: {err}"),
+            }
+        }
+
+        let api = RenderDoc::<V141>::new()
+            .map_err(|err| {
+                if load {
+                    warn!("Unable to use RenderDoc: {err}");
+                }
+            })
+            .ok()?;
+        let num_captures = api.get_num_captures();
+
+        info!("RenderDoc loaded: F8 captures a frame");
+
+        Some(Self {
+            api,
+            comments: None,
+            is_triggered: false,
+            num_captures,
+        })
+    }
+
+    /// Captures the next frame presented, annotating the capture with the given comments and the
+    /// names of the passes of the frame.
+    pub fn trigger(&mut self, comments: String) {
+        info!("Capturing frame");
+
+        self.api.trigger_capture();
+        self.comments = Some(comments);
+        self.is_triggered = true;
+    }
+
+    /// Annotates captures which RenderDoc has finished since the previous update.
+    ///
+    /// This is called at the start of each frame: RenderDoc captures the frame after the one which
+    /// triggered it, so pass names are noted from the update after the trigger until the next.
+    pub fn update(&mut self) {
+        if IS_RECORDING_PASS_NAMES.swap(false, Ordering::Relaxed) {
+            if let (Some(comments), Ok(mut pass_names)) = (&mut self.comments, PASS_NAMES.lock()) {
+                comments.push_str("\n\nPasses:\n");
+                comments.push_str(&take(&mut *pass_names).join("\n"));
+            }
+        }
+
+        if self.is_triggered {
+            self.is_triggered = false;
+
+            if let Ok(mut pass_names) = PASS_NAMES.lock() {
+                pass_names.clear();
+            }
+
+            IS_RECORDING_PASS_NAMES.store(true, Ordering::Relaxed);
+        }
+
+        let num_captures = self.api.get_num_captures();
+
+        for index in self.num_captures..num_captures {
+            if let Some((path, _)) = self.api.get_capture(index) {
+                info!("Captured frame to {}", path.display());
+
+                if let Some(comments) = self.comments.take() {
+                    self.api
+                        .set_capture_file_comments(path.to_str(), comments.as_str());
+                }
+            }
+        }
+
+        self.num_captures = num_captures;
+    }
+}
@@ -0,0 +1,74 @@
+pub mod capture;
+
+use std::fmt;
+
+/// Alternate ways of drawing models which are useful while debugging content.
+///
+/// The value of each mode is shared with the shaders (see `res/shader/model/debug.glsl`).
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(u32)]
+pub enum DebugMode {
+    #[default]
+    Off = 0,
+    Wireframe = 1,
+    Normals = 2,
+    TexCoords = 3,
+    Overdraw = 4,
+    MeshIds = 5,
+}
+
+impl DebugMode {
+    pub const ALL: [Self; 6] = [
+        Self::Off,
+        Self::Wireframe,
+        Self::Normals,
+        Self::TexCoords,
+        Self::Overdraw,
+        Self::MeshIds,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns the following mode, wrapping around to [`DebugMode::Off`] after the last one.
+    pub fn next(self) -> Self {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()]
+    }
+}
+
+impl fmt::Display for DebugMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Off => "Off",
+            Self::Wireframe => "Wireframe",
+            Self::Normals => "World normals",
+            Self::TexCoords => "Texture coordinates",
+            Self::Overdraw => "Overdraw",
+            Self::MeshIds => "Mesh IDs",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn debug_mode_next() {
+        for (index, mode) in DebugMode::ALL.iter().copied().enumerate() {
+            assert_eq!(mode.index(), index);
+        }
+
+        assert_eq!(DebugMode::Off.next(), DebugMode::Wireframe);
+        assert_eq!(DebugMode::MeshIds.next(), DebugMode::Off);
+
+        let mut mode = DebugMode::Off;
+
+        for _ in 0..DebugMode::ALL.len() {
+            mode = mode.next();
+        }
+
+        assert_eq!(mode, DebugMode::Off);
+    }
+}
@@ -0,0 +1,227 @@
+use {
+    super::debug::capture::pass_name,
+    crate::res,
+    anyhow::Context,
+    bytemuck::{bytes_of, Pod, Zeroable},
+    glam::Vec2,
+    screen_13::prelude::*,
+    std::sync::Arc,
+};
+
+#[cfg(not(feature = "hot-shaders"))]
+use {super::read_blob, pak::PakBuf};
+
+#[cfg(feature = "hot-shaders")]
+use {
+    super::{hot_shader::watch, res_shader_dir},
+    screen_13_hot::prelude::*,
+};
+
+/// Blur of models nearer or farther than the focus distance, like a camera lens, such as for the
+/// level drawn behind the menu.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthOfField {
+    /// Meters in front of the camera which are in focus.
+    pub focus_distance: f32,
+
+    /// Meters either side of the focus distance over which the blur reaches full strength.
+    pub focus_range: f32,
+}
+
+/// Blurs a copy of the framebuffer at half resolution and blends it back over the framebuffer by
+/// how far each pixel of a depth image is from the focus distance.
+#[derive(Debug)]
+pub struct DepthOfFieldPipeline {
+    #[cfg(not(feature = "hot-shaders"))]
+    apply: Arc<GraphicPipeline>,
+
+    #[cfg(feature = "hot-shaders")]
+    apply: HotGraphicPipeline,
+
+    #[cfg(not(feature = "hot-shaders"))]
+    blur: Arc<GraphicPipeline>,
+
+    #[cfg(feature = "hot-shaders")]
+    blur: HotGraphicPipeline,
+}
+
+impl DepthOfFieldPipeline {
+    /// Height of the framebuffer, in pixels, for each pixel of the radius of full blur.
+    const PIXELS_PER_RADIUS: f32 = 90.0;
+
+    #[cfg(not(feature = "hot-shaders"))]
+    pub fn new(device: &Arc<Device>, res_pak: &mut PakBuf) -> anyhow::Result<Self> {
+        let fullscreen_vert = read_blob(res_pak, res::SHADER_FULLSCREEN_VERT_SPIRV)?;
+
+        let apply = Arc::new(
+            GraphicPipeline::create(
+                device,
+                GraphicPipelineInfo::new().blend(BlendMode::ALPHA),
+                [
+                    Shader::new_vertex(fullscreen_vert.as_slice()),
+                    Shader::new_fragment(
+                        read_blob(res_pak, res::SHADER_DEPTH_OF_FIELD_APPLY_FRAG_SPIRV)?.as_slice(),
+                    )
+                    .image_sampler(0, Self::linear_sampler_info())
+                    .image_sampler(1, Self::nearest_sampler_info()),
+                ],
+            )
+            .context("Creating apply pipeline")?,
+        );
+
+        let blur = Arc::new(
+            GraphicPipeline::create(
+                device,
+                GraphicPipelineInfo::new(),
+                [
+                    Shader::new_vertex(fullscreen_vert.as_slice()),
+                    Shader::new_fragment(
+                        read_blob(res_pak, res::SHADER_DEPTH_OF_FIELD_BLUR_FRAG_SPIRV)?.as_slice(),
+                    )
+                    .image_sampler(0, Self::linear_sampler_info()),
+                ],
+            )
+            .context("Creating blur pipeline")?,
+        );
+
+        Ok(Self { apply, blur })
+    }
+
+    #[cfg(feature = "hot-shaders")]
+    pub fn new(device: &Arc<Device>) -> anyhow::Result<Self> {
+        let shader_dir = res_shader_dir();
+
+        let apply = HotGraphicPipeline::create(
+            device,
+            GraphicPipelineInfo::new().blend(BlendMode::ALPHA),
+            [
+                HotShader::new_vertex(watch(shader_dir.join("fullscreen.vert"))),
+                HotShader::new_fragment(watch(shader_dir.join("depth_of_field/apply.frag")))
+                    .image_sampler(0, Self::linear_sampler_info())
+                    .image_sampler(1, Self::nearest_sampler_info()),
+            ],
+        )
+        .context("Creating hot apply pipeline")?;
+
+        let blur = HotGraphicPipeline::create(
+            device,
+            GraphicPipelineInfo::new(),
+            [
+                HotShader::new_vertex(watch(shader_dir.join("fullscreen.vert"))),
+                HotShader::new_fragment(watch(shader_dir.join("depth_of_field/blur.frag")))
+                    .image_sampler(0, Self::linear_sampler_info()),
+            ],
+        )
+        .context("Creating hot blur pipeline")?;
+
+        Ok(Self { apply, blur })
+    }
+
+    #[inline(always)]
+    fn apply(&mut self) -> &Arc<GraphicPipeline> {
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.apply;
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.apply.hot();
+
+        res
+    }
+
+    #[inline(always)]
+    fn blur(&mut self) -> &Arc<GraphicPipeline> {
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.blur;
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.blur.hot();
+
+        res
+    }
+
+    fn linear_sampler_info() -> SamplerInfo {
+        SamplerInfo::new()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .build()
+    }
+
+    fn nearest_sampler_info() -> SamplerInfo {
+        SamplerInfo::new()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .build()
+    }
+
+    /// Records depth of field using the given reverse-Z depth image, both of which must be
+    /// sampled, into the framebuffer.
+    ///
+    /// `z_near` must match the projection used to draw the depth image.
+    pub fn record(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        pool: &mut impl Pool<ImageInfoBuilder, Image>,
+        framebuffer: impl Into<AnyImageNode>,
+        depth_image: impl Into<AnyImageNode>,
+        depth_of_field: DepthOfField,
+        z_near: f32,
+    ) -> Result<(), DriverError> {
+        #[derive(Clone, Copy, Pod, Zeroable)]
+        #[repr(C)]
+        struct ApplyPushConstants {
+            z_near: f32,
+            focus_distance: f32,
+            focus_range: f32,
+        }
+
+        let framebuffer = framebuffer.into();
+        let depth_image = depth_image.into();
+        let framebuffer_info = render_graph.node_info(framebuffer);
+        let blur_image = render_graph.bind_node(pool.lease(ImageInfo::new_2d(
+            framebuffer_info.fmt,
+            (framebuffer_info.width >> 1).max(1),
+            (framebuffer_info.height >> 1).max(1),
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+        ))?);
+
+        // The radius is in texture coordinates, so it is the same number of pixels both ways
+        let radius_pixels = framebuffer_info.height as f32 / Self::PIXELS_PER_RADIUS;
+        let radius = Vec2::new(
+            radius_pixels / framebuffer_info.width as f32,
+            radius_pixels / framebuffer_info.height as f32,
+        );
+        let push_consts = ApplyPushConstants {
+            z_near,
+            focus_distance: depth_of_field.focus_distance,
+            focus_range: depth_of_field.focus_range.max(f32::EPSILON),
+        };
+
+        render_graph
+            .begin_pass(pass_name("Depth of field blur"))
+            .bind_pipeline(self.blur())
+            .read_descriptor(0, framebuffer)
+            .store_color(0, blur_image)
+            .record_subpass(move |subpass, _| {
+                subpass.push_constants(bytes_of(&radius)).draw(3, 1, 0, 0);
+            });
+
+        render_graph
+            .begin_pass(pass_name("Apply depth of field"))
+            .bind_pipeline(self.apply())
+            .read_descriptor(0, blur_image)
+            .read_descriptor(1, depth_image)
+            .load_color(0, framebuffer)
+            .store_color(0, framebuffer)
+            .record_subpass(move |subpass, _| {
+                subpass
+                    .push_constants(bytes_of(&push_consts))
+                    .draw(3, 1, 0, 0);
+            });
+
+        Ok(())
+    }
+}
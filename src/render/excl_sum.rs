@@ -1,4 +1,5 @@
 use {
+    super::{capabilities::DeviceCapabilities, debug::capture::pass_name},
     crate::{math::align_up_u32, res},
     anyhow::Context,
     pak::PakBuf,
@@ -17,7 +18,7 @@ use {super::res_shader_dir, screen_13_hot::prelude::*};
 pub struct ExclusiveSumPipeline {
     reduce: Arc<ComputePipeline>,
     scan: Arc<ComputePipeline>,
-    subgroup_size: u32,
+    workgroup_size: u32,
 }
 
 #[cfg(feature = "hot-shaders")]
@@ -25,22 +26,31 @@ pub struct ExclusiveSumPipeline {
 pub struct ExclusiveSumPipeline {
     reduce: HotComputePipeline,
     scan: HotComputePipeline,
-    subgroup_size: u32,
+    workgroup_size: u32,
 }
 
 impl ExclusiveSumPipeline {
     #[cfg(not(feature = "hot-shaders"))]
     pub fn new(device: &Arc<Device>, res_pak: &mut PakBuf) -> anyhow::Result<Self> {
-        let Vulkan11Properties { subgroup_size, .. } = device.physical_device.properties_v1_1;
+        let capabilities = DeviceCapabilities::new(device);
+        let (reduce_key, scan_key) = if capabilities.subgroup_ops {
+            (
+                res::SHADER_COMPUTE_EXCL_SUM_REDUCE_COMP_SUBGROUP_SPIRV,
+                res::SHADER_COMPUTE_EXCL_SUM_SCAN_COMP_SUBGROUP_SPIRV,
+            )
+        } else {
+            (
+                res::SHADER_COMPUTE_EXCL_SUM_REDUCE_COMP_SHARED_SPIRV,
+                res::SHADER_COMPUTE_EXCL_SUM_SCAN_COMP_SHARED_SPIRV,
+            )
+        };
 
         let reduce = Arc::new(
             ComputePipeline::create(
                 &device,
                 ComputePipelineInfo::default(),
-                Shader::new_compute(
-                    read_blob(res_pak, res::SHADER_COMPUTE_EXCL_SUM_REDUCE_COMP_SPIRV)?.as_slice(),
-                )
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
+                Shader::new_compute(read_blob(res_pak, reduce_key)?.as_slice())
+                    .specialization_info(capabilities.workgroup_specialization_info()),
             )
             .context("Creating reduce pipeline")?,
         );
@@ -49,10 +59,8 @@ impl ExclusiveSumPipeline {
             ComputePipeline::create(
                 &device,
                 ComputePipelineInfo::default(),
-                Shader::new_compute(
-                    read_blob(res_pak, res::SHADER_COMPUTE_EXCL_SUM_SCAN_COMP_SPIRV)?.as_slice(),
-                )
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
+                Shader::new_compute(read_blob(res_pak, scan_key)?.as_slice())
+                    .specialization_info(capabilities.workgroup_specialization_info()),
             )
             .context("Creating scan pipeline")?,
         );
@@ -60,40 +68,38 @@ impl ExclusiveSumPipeline {
         Ok(Self {
             reduce,
             scan,
-            subgroup_size,
+            workgroup_size: capabilities.workgroup_size,
         })
     }
 
     #[cfg(feature = "hot-shaders")]
     pub fn new(device: &Arc<Device>) -> anyhow::Result<Self> {
-        let PhysicalDeviceVulkan11Properties { subgroup_size, .. } = device.vulkan_1_1_properties;
+        let capabilities = DeviceCapabilities::new(device);
         let shader_dir = res_shader_dir();
 
         let reduce = HotComputePipeline::create(
             &device,
             ComputePipelineInfo::default(),
-            HotShader::new_compute(shader_dir.join("compute/excl_sum_reduce.comp"))
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
+            capabilities.hot_workgroup_ops_shader(shader_dir.join("compute/excl_sum_reduce.comp")),
         )
         .context("Creating hot reduce pipeline")?;
 
         let scan = HotComputePipeline::create(
             &device,
             ComputePipelineInfo::default(),
-            HotShader::new_compute(shader_dir.join("compute/excl_sum_scan.comp"))
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
+            capabilities.hot_workgroup_ops_shader(shader_dir.join("compute/excl_sum_scan.comp")),
         )
         .context("Creating hot scan pipeline")?;
 
         Ok(Self {
             reduce,
             scan,
-            subgroup_size,
+            workgroup_size: capabilities.workgroup_size,
         })
     }
 
     pub fn align_input_count(&self, input_count: u32) -> u32 {
-        align_up_u32(input_count, self.subgroup_size)
+        align_up_u32(input_count, self.workgroup_size)
     }
 
     pub fn record(
@@ -109,14 +115,14 @@ impl ExclusiveSumPipeline {
         }
 
         debug_assert!(
-            input_count % self.subgroup_size == 0,
-            "Input count is expected to be manually aligned to subgroup size"
+            input_count % self.workgroup_size == 0,
+            "Input count is expected to be manually aligned to workgroup size"
         );
 
         let input_buf = input_buf.into();
         let output_buf = output_buf.into();
 
-        let workgroup_count = input_count / self.subgroup_size;
+        let workgroup_count = input_count / self.workgroup_size;
         let reduce_count = workgroup_count - 1;
         let workgroup_buf = render_graph.bind_node(pool.lease(BufferInfo::new(
             reduce_count.max(1) as vk::DeviceSize * size_of::<u32>() as vk::DeviceSize,
@@ -125,7 +131,7 @@ impl ExclusiveSumPipeline {
 
         if reduce_count > 0 {
             render_graph
-                .begin_pass("exclusive sum reduce")
+                .begin_pass(pass_name("exclusive sum reduce"))
                 .bind_pipeline(self.reduce())
                 .read_descriptor(0, input_buf)
                 .write_descriptor(1, workgroup_buf)
@@ -135,7 +141,7 @@ impl ExclusiveSumPipeline {
         }
 
         render_graph
-            .begin_pass("exclusive sum scan")
+            .begin_pass(pass_name("exclusive sum scan"))
             .bind_pipeline(self.scan())
             .read_descriptor(0, workgroup_buf)
             .read_descriptor(1, input_buf)
@@ -168,23 +174,12 @@ impl ExclusiveSumPipeline {
 
         res
     }
-
-    fn subgroup_specialization_info(subgroup_size: u32) -> SpecializationInfo {
-        SpecializationInfo {
-            data: subgroup_size.to_ne_bytes().to_vec(),
-            map_entries: vec![vk::SpecializationMapEntry {
-                constant_id: 0,
-                offset: 0,
-                size: size_of::<u32>(),
-            }],
-        }
-    }
 }
 
 #[cfg(test)]
-pub(super) mod tests {
+mod tests {
     use {
-        super::*,
+        super::{super::tests::TestDevice, *},
         bytemuck::cast_slice,
         rand::{rngs::SmallRng, Rng, SeedableRng},
         std::{
@@ -198,7 +193,7 @@ pub(super) mod tests {
     use super::super::open_res_pak;
 
     fn assert_exclusive_sum(input_data: &[u32]) {
-        let device = Arc::new(Device::create_headless(DeviceInfo::new()).unwrap());
+        let device = TestDevice::new();
         let mut pool = LazyPool::new(&device);
 
         #[cfg(not(feature = "hot-shaders"))]
@@ -212,10 +207,10 @@ pub(super) mod tests {
         let mut excl_sum_pipeline = ExclusiveSumPipeline::new(&device).unwrap();
 
         // Trim input data because we expect applications to always provide data in multiples of
-        // device subgroup size
+        // workgroup size
         let input_data = &input_data[0..(input_data.len()
-            / excl_sum_pipeline.subgroup_size as usize)
-            * excl_sum_pipeline.subgroup_size as usize];
+            / excl_sum_pipeline.workgroup_size as usize)
+            * excl_sum_pipeline.workgroup_size as usize];
 
         let mut render_graph = RenderGraph::new();
 
@@ -270,28 +265,28 @@ pub(super) mod tests {
         }
     }
 
-    #[cfg_attr(not(target_os = "macos"), test)]
+    #[test]
     pub fn exclusive_sum1() {
         let input_data = (0u32..2_048).into_iter().collect::<Box<_>>();
 
         assert_exclusive_sum(&input_data);
     }
 
-    #[cfg_attr(not(target_os = "macos"), test)]
+    #[test]
     pub fn exclusive_sum2() {
         let input_data = (0u32..69).into_iter().collect::<Box<_>>();
 
         assert_exclusive_sum(&input_data);
     }
 
-    #[cfg_attr(not(target_os = "macos"), test)]
+    #[test]
     pub fn exclusive_sum3() {
         let input_data = repeat(1u32).take(99_048).into_iter().collect::<Box<_>>();
 
         assert_exclusive_sum(&input_data);
     }
 
-    #[cfg_attr(not(target_os = "macos"), test)]
+    #[test]
     pub fn exclusive_sum4() {
         let mut rng = SmallRng::seed_from_u64(42);
         let input_data = repeat_with(|| rng.gen_range(0u32..35))
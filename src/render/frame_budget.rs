@@ -0,0 +1,131 @@
+//! Spreads streaming work, such as texture uploads and acceleration structure builds, across frames
+//! so that it never causes a visible hitch during play.
+//!
+//! Each kind of work reports how long its items took, from timestamps taken around them, and the
+//! average time per unit of work (a byte, a triangle) is kept. Each frame only as much work is
+//! started as the estimates say fits in the limit, except that one item may always start in a frame
+//! which has started nothing else, so streaming never stops however small the limit is.
+
+/// The kinds of work which share the budget.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StreamingWork {
+    /// Building an acceleration structure, in triangles.
+    BlasBuild,
+
+    /// Uploading the mips of a streamed texture, in bytes.
+    TextureUpload,
+}
+
+impl StreamingWork {
+    const COUNT: usize = 2;
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FrameBudget {
+    /// Seconds of streaming work each frame may start, or `None` if unlimited, such as while
+    /// loading when nothing is drawn.
+    limit: Option<f32>,
+
+    /// Average seconds each unit of each kind of work has taken, if any has been measured.
+    secs_per_unit: [Option<f32>; StreamingWork::COUNT],
+
+    /// Estimated seconds of work started this frame.
+    spent: f32,
+
+    /// Set once any work has started this frame.
+    started: bool,
+}
+
+impl FrameBudget {
+    /// Weight of each new measurement in the averages.
+    const SMOOTHING: f32 = 0.25;
+
+    /// Starts a new frame, with the whole limit unspent.
+    pub fn begin_frame(&mut self) {
+        self.spent = 0.0;
+        self.started = false;
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.limit.is_some()
+    }
+
+    /// Adds the measured time of a finished item of work to the averages.
+    pub fn measure(&mut self, work: StreamingWork, units: usize, secs: f32) {
+        let secs_per_unit = secs / units.max(1) as f32;
+        let average = &mut self.secs_per_unit[work.index()];
+
+        *average = Some(average.map_or(secs_per_unit, |average| {
+            average + (secs_per_unit - average) * Self::SMOOTHING
+        }));
+    }
+
+    pub fn set_limit(&mut self, limit: Option<f32>) {
+        self.limit = limit;
+    }
+
+    /// Returns the estimated seconds of work started this frame and the limit, if any.
+    pub fn spent(&self) -> (f32, Option<f32>) {
+        (self.spent, self.limit)
+    }
+
+    /// Returns `true`, counting the item against this frame, if an item of work of the given size
+    /// may start.
+    ///
+    /// Until an item of a kind of work has been measured, only one item may start each frame.
+    pub fn try_spend(&mut self, work: StreamingWork, units: usize) -> bool {
+        let Some(limit) = self.limit else {
+            return true;
+        };
+
+        let cost =
+            self.secs_per_unit[work.index()].map(|secs_per_unit| secs_per_unit * units as f32);
+
+        if self.started && !cost.is_some_and(|cost| self.spent + cost <= limit) {
+            return false;
+        }
+
+        self.spent += cost.unwrap_or(limit);
+        self.started = true;
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn spread_work() {
+        let mut budget = FrameBudget::default();
+
+        // Without a limit everything starts
+        assert!((0..100).all(|_| budget.try_spend(StreamingWork::BlasBuild, 1_000)));
+
+        budget.set_limit(Some(0.0025));
+        budget.begin_frame();
+
+        // Unmeasured work starts one item at a time
+        assert!(budget.try_spend(StreamingWork::BlasBuild, 1_000));
+        assert!(!budget.try_spend(StreamingWork::BlasBuild, 1_000));
+
+        // A millisecond for every thousand triangles allows two builds each frame
+        budget.measure(StreamingWork::BlasBuild, 1_000, 0.001);
+        budget.begin_frame();
+
+        assert!(budget.try_spend(StreamingWork::BlasBuild, 1_000));
+        assert!(budget.try_spend(StreamingWork::BlasBuild, 1_000));
+        assert!(!budget.try_spend(StreamingWork::BlasBuild, 1_000));
+
+        // Work larger than the limit still starts in a frame of its own
+        budget.begin_frame();
+
+        assert!(budget.try_spend(StreamingWork::BlasBuild, 10_000));
+        assert!(!budget.try_spend(StreamingWork::TextureUpload, 1));
+    }
+}
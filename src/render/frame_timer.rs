@@ -0,0 +1,147 @@
+//! Measures how long the GPU spends executing each frame graph using timestamp queries, which,
+//! unlike the CPU time of a frame, is not hidden by recording running ahead of the GPU.
+
+use {super::debug::capture::pass_name, screen_13::prelude::*, std::sync::Arc};
+
+/// Writes timestamps at the start and end of each frame graph and reads them back once the frame
+/// has executed, a few frames later.
+#[derive(Debug)]
+pub struct GpuFrameTimer {
+    device: Arc<Device>,
+
+    /// Index of the query pair the current frame writes.
+    frame_index: usize,
+
+    /// GPU time, in seconds, of the latest frame which has been read back.
+    frame_time: Option<f32>,
+
+    pool: vk::QueryPool,
+
+    /// Nanoseconds per timestamp tick.
+    timestamp_period: f32,
+
+    /// Whether each query pair has been written, because unwritten queries may not be read.
+    written: [bool; Self::FRAME_COUNT],
+}
+
+impl GpuFrameTimer {
+    /// Frames which may be in flight at once, each of which has its own pair of queries.
+    const FRAME_COUNT: usize = 3;
+
+    /// Returns `None` if the graphics queue does not support timestamps.
+    pub fn new(device: &Arc<Device>) -> Option<Self> {
+        if device.physical_device.queue_families[0].timestamp_valid_bits == 0 {
+            info!("GPU timestamps unsupported");
+
+            return None;
+        }
+
+        let pool = unsafe {
+            device.create_query_pool(
+                &vk::QueryPoolCreateInfo::builder()
+                    .query_type(vk::QueryType::TIMESTAMP)
+                    .query_count(2 * Self::FRAME_COUNT as u32),
+                None,
+            )
+        }
+        .map_err(|err| warn!("Unable to create query pool: {err}"))
+        .ok()?;
+
+        Some(Self {
+            device: Arc::clone(device),
+            frame_index: 0,
+            frame_time: None,
+            pool,
+            timestamp_period: device
+                .physical_device
+                .properties_v1_0
+                .limits
+                .timestamp_period,
+            written: Default::default(),
+        })
+    }
+
+    /// Returns the GPU time, in seconds, of the latest frame which has finished executing.
+    pub fn frame_time(&self) -> Option<f32> {
+        self.frame_time
+    }
+
+    /// Records the timestamp which begins the frame, before any pass which accesses the given
+    /// image, after reading back the timestamps of the frame which last used the same queries.
+    pub fn record_begin(&mut self, render_graph: &mut RenderGraph, image: impl Into<AnyImageNode>) {
+        self.read_frame_time();
+
+        let first_query = 2 * self.frame_index as u32;
+        let pool = self.pool;
+
+        render_graph
+            .begin_pass(pass_name("Begin frame timestamp"))
+            .access_node(image.into(), AccessType::TransferWrite)
+            .record_cmd_buf(move |device, cmd_buf, _| unsafe {
+                device.cmd_reset_query_pool(cmd_buf, pool, first_query, 2);
+                device.cmd_write_timestamp(
+                    cmd_buf,
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    pool,
+                    first_query,
+                );
+            });
+    }
+
+    /// Records the timestamp which ends the frame, after every pass which accesses the given
+    /// image.
+    pub fn record_end(&mut self, render_graph: &mut RenderGraph, image: impl Into<AnyImageNode>) {
+        let query = 2 * self.frame_index as u32 + 1;
+        let pool = self.pool;
+
+        render_graph
+            .begin_pass(pass_name("End frame timestamp"))
+            .access_node(image.into(), AccessType::ColorAttachmentRead)
+            .record_cmd_buf(move |device, cmd_buf, _| unsafe {
+                device.cmd_write_timestamp(
+                    cmd_buf,
+                    vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                    pool,
+                    query,
+                );
+            });
+
+        self.written[self.frame_index] = true;
+        self.frame_index = (self.frame_index + 1) % Self::FRAME_COUNT;
+    }
+
+    fn read_frame_time(&mut self) {
+        if !self.written[self.frame_index] {
+            return;
+        }
+
+        let mut timestamps = [0u64; 2];
+
+        // Without waiting, the results are not ready if the frame is still executing, in which
+        // case the previous frame time is kept
+        if unsafe {
+            self.device.get_query_pool_results(
+                self.pool,
+                2 * self.frame_index as u32,
+                2,
+                &mut timestamps,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        }
+        .is_ok()
+        {
+            let [begin, end] = timestamps;
+
+            self.frame_time =
+                Some(end.saturating_sub(begin) as f32 * self.timestamp_period / 1_000_000_000.0);
+        }
+    }
+}
+
+impl Drop for GpuFrameTimer {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_query_pool(self.pool, None);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Tracks the GLSL sources of hot pipelines along with every file they include, so that editing
+//! an include reloads each pipeline which uses it and compile errors are shown on screen.
+//!
+//! Hot pipelines only watch the file they were created with, so when one of its includes changes
+//! that file is touched once it compiles; sources which do not compile are left alone so that the
+//! running pipeline is kept.
+
+use {
+    anyhow::anyhow,
+    log::warn,
+    parking_lot::Mutex,
+    shader_prepper::{
+        process_file, BoxedIncludeProviderError, IncludeProvider, ResolvedInclude,
+        ResolvedIncludePath,
+    },
+    shaderc::{CompileOptions, Compiler, EnvVersion, ShaderKind, SpirvVersion, TargetEnv},
+    std::{
+        collections::BTreeMap,
+        fs::{metadata, read_to_string, File},
+        io::Error,
+        path::{Path, PathBuf},
+        time::{Duration, Instant, SystemTime},
+    },
+};
+
+/// Time between checks of the watched files.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    last_poll: None,
+    sources: BTreeMap::new(),
+});
+
+/// Reads included files relative to the file which includes them, recording each one.
+struct IncludeCollector(Vec<PathBuf>);
+
+impl IncludeProvider for IncludeCollector {
+    type IncludeContext = PathBuf;
+
+    fn get_include(
+        &mut self,
+        path: &ResolvedIncludePath,
+    ) -> Result<String, BoxedIncludeProviderError> {
+        self.0.push(PathBuf::from(&path.0));
+
+        Ok(read_to_string(&path.0)?)
+    }
+
+    fn resolve_path(
+        &self,
+        path: &str,
+        context: &Self::IncludeContext,
+    ) -> Result<ResolvedInclude<Self::IncludeContext>, BoxedIncludeProviderError> {
+        let path = context.join(path);
+
+        Ok(ResolvedInclude {
+            resolved_path: ResolvedIncludePath(path.to_str().unwrap_or_default().to_string()),
+            context: path
+                .parent()
+                .map(|path| path.to_path_buf())
+                .unwrap_or_else(PathBuf::new),
+        })
+    }
+}
+
+struct Registry {
+    last_poll: Option<Instant>,
+    sources: BTreeMap<PathBuf, Source>,
+}
+
+/// A file which hot pipelines are created from.
+struct Source {
+    /// The file and every file it includes, directly or not.
+    files: Vec<PathBuf>,
+
+    /// Latest modification time of `files` as of the previous poll.
+    modified: Option<SystemTime>,
+}
+
+/// Compiles expanded source code only to find errors; hot pipelines compile their own.
+fn compile(path: &Path, source_code: &str) -> anyhow::Result<()> {
+    let kind = match path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_default()
+        .as_str()
+    {
+        "comp" => ShaderKind::Compute,
+        "frag" => ShaderKind::Fragment,
+        "vert" => ShaderKind::Vertex,
+        "rgen" => ShaderKind::RayGeneration,
+        "rchit" => ShaderKind::ClosestHit,
+        "rmiss" => ShaderKind::Miss,
+        "task" => ShaderKind::Task,
+        "mesh" => ShaderKind::Mesh,
+        ext => return Err(anyhow!("Unknown shader extension {ext}")),
+    };
+
+    let mut options = CompileOptions::new().ok_or_else(|| anyhow!("No compiler options"))?;
+    options.set_target_env(TargetEnv::Vulkan, EnvVersion::Vulkan1_2 as u32);
+    options.set_target_spirv(SpirvVersion::V1_5);
+
+    Compiler::new()
+        .ok_or_else(|| anyhow!("No shader compiler"))?
+        .compile_into_spirv(
+            source_code,
+            kind,
+            &path.to_string_lossy(),
+            "main",
+            Some(&options),
+        )?;
+
+    Ok(())
+}
+
+fn latest_modified(files: &[PathBuf]) -> Option<SystemTime> {
+    files
+        .iter()
+        .filter_map(|path| metadata(path).and_then(|metadata| metadata.modified()).ok())
+        .max()
+}
+
+/// Checks the watched files for changes and returns a message for each changed source which does
+/// not compile.
+pub fn poll() -> Vec<String> {
+    let mut registry = REGISTRY.lock();
+    let now = Instant::now();
+
+    if registry
+        .last_poll
+        .is_some_and(|last_poll| now - last_poll < POLL_INTERVAL)
+    {
+        return vec![];
+    }
+
+    registry.last_poll = Some(now);
+
+    let mut errors = vec![];
+
+    for (path, source) in &mut registry.sources {
+        let modified = latest_modified(&source.files);
+
+        if modified == source.modified {
+            continue;
+        }
+
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        // Includes may have been added or removed by the change
+        match preprocess(path).and_then(|(source_code, files)| {
+            source.files = files;
+
+            compile(path, &source_code)
+        }) {
+            Ok(_) => {
+                let source_modified = metadata(path).and_then(|metadata| metadata.modified()).ok();
+
+                if source_modified < modified {
+                    if let Err(err) = touch(path) {
+                        warn!("Unable to reload {}: {err}", path.display());
+
+                        errors.push(format!("Unable to reload {name}: {err}"));
+                    }
+                }
+            }
+            Err(err) => {
+                warn!("Shader {}: {err}", path.display());
+
+                // Compiler output holds a line per error; the first is enough to find the rest
+                let err = err.to_string();
+                let first_line = err.lines().next().unwrap_or_default();
+
+                errors.push(format!("Shader {name}: {first_line}"));
+            }
+        }
+
+        source.modified = latest_modified(&source.files);
+    }
+
+    errors
+}
+
+/// Returns the source code of a file with its includes expanded, along with every file read.
+fn preprocess(path: &Path) -> anyhow::Result<(String, Vec<PathBuf>)> {
+    let mut includes = IncludeCollector(vec![]);
+    let source_code = process_file(
+        path.to_string_lossy().as_ref(),
+        &mut includes,
+        PathBuf::new(),
+    )
+    .map_err(|err| anyhow!("{err}"))?
+    .iter()
+    .map(|chunk| chunk.source.as_str())
+    .collect();
+    let mut files = includes.0;
+
+    if !files.iter().any(|file| file == path) {
+        files.push(path.to_path_buf());
+    }
+
+    Ok((source_code, files))
+}
+
+/// Sets the modification time of a file to now, without changing it.
+fn touch(path: &Path) -> Result<(), Error> {
+    File::options()
+        .append(true)
+        .open(path)?
+        .set_modified(SystemTime::now())
+}
+
+/// Registers a file which hot pipelines are created from and returns its path, so that it may
+/// wrap the path given to `HotShader`.
+pub fn watch(path: PathBuf) -> PathBuf {
+    REGISTRY
+        .lock()
+        .sources
+        .entry(path.clone())
+        .or_insert_with(|| {
+            let files = preprocess(&path)
+                .map(|(_, files)| files)
+                .unwrap_or_else(|_| vec![path.clone()]);
+
+            Source {
+                modified: latest_modified(&files),
+                files,
+            }
+        });
+
+    path
+}
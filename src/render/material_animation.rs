@@ -0,0 +1,220 @@
+//! Animation of the materials in `art.pak`, such as scrolling lava or flickering monitors, along
+//! with how brightly emissive materials glow and how their textures are sampled, which `build.rs`
+//! reads from `art/material_animation.toml` and writes into a file next to the pak.
+//!
+//! This module is also compiled by `build.rs` and so may only depend on `pak` and `serde`.
+
+use {
+    pak::MaterialId,
+    serde::{Deserialize, Serialize},
+    std::collections::HashMap,
+};
+
+/// Name of the file, next to `art.pak`, which holds the material animations.
+pub const FILE_NAME: &str = "art_anim.bin";
+
+/// Frames of a material stored as a grid within each of its textures, left to right and then top
+/// to bottom.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Flipbook {
+    pub columns: u8,
+    pub rows: u8,
+
+    /// Number of frames played, which may be less than the size of the grid.
+    pub frame_count: u16,
+
+    /// Frames shown each second.
+    pub fps: f32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MaterialAnimation {
+    /// Multiplies the emissive texture, if the material has one.
+    #[serde(default = "MaterialAnimation::default_emissive_color")]
+    pub emissive_color: [f32; 3],
+
+    /// Multiplies the emissive texture, if the material has one; values above one light the
+    /// surroundings more brightly.
+    #[serde(default = "MaterialAnimation::default_emissive_intensity")]
+    pub emissive_intensity: f32,
+
+    #[serde(default)]
+    pub flipbook: Option<Flipbook>,
+
+    /// How the textures of the material are sampled.
+    #[serde(default)]
+    pub sampler: MaterialSampler,
+
+    /// Texture coordinates added each second.
+    #[serde(default)]
+    pub scroll: [f32; 2],
+}
+
+impl MaterialAnimation {
+    fn default_emissive_color() -> [f32; 3] {
+        [1.0; 3]
+    }
+
+    fn default_emissive_intensity() -> f32 {
+        1.0
+    }
+}
+
+impl Default for MaterialAnimation {
+    fn default() -> Self {
+        Self {
+            emissive_color: Self::default_emissive_color(),
+            emissive_intensity: Self::default_emissive_intensity(),
+            flipbook: None,
+            sampler: MaterialSampler::default(),
+            scroll: [0.0; 2],
+        }
+    }
+}
+
+/// How the textures of a material are sampled, such as with point filtering for pixel art
+/// surfaces.
+///
+/// Shaders select from a small set of immutable samplers, one for each distinct state, so point
+/// filtered materials ignore `anisotropy` as they do not blend texels.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct MaterialSampler {
+    #[serde(default)]
+    pub address: SamplerAddress,
+
+    #[serde(default)]
+    pub anisotropy: SamplerAnisotropy,
+
+    #[serde(default)]
+    pub filter: SamplerFilter,
+}
+
+impl MaterialSampler {
+    /// Every distinct sampler state, in the order of [`Self::index`].
+    pub const ALL: [Self; 8] = [
+        Self::new(
+            SamplerAddress::Repeat,
+            SamplerFilter::Linear,
+            SamplerAnisotropy::Off,
+        ),
+        Self::new(
+            SamplerAddress::Repeat,
+            SamplerFilter::Linear,
+            SamplerAnisotropy::Low,
+        ),
+        Self::new(
+            SamplerAddress::Repeat,
+            SamplerFilter::Linear,
+            SamplerAnisotropy::Full,
+        ),
+        Self::new(
+            SamplerAddress::Repeat,
+            SamplerFilter::Point,
+            SamplerAnisotropy::Off,
+        ),
+        Self::new(
+            SamplerAddress::Clamp,
+            SamplerFilter::Linear,
+            SamplerAnisotropy::Off,
+        ),
+        Self::new(
+            SamplerAddress::Clamp,
+            SamplerFilter::Linear,
+            SamplerAnisotropy::Low,
+        ),
+        Self::new(
+            SamplerAddress::Clamp,
+            SamplerFilter::Linear,
+            SamplerAnisotropy::Full,
+        ),
+        Self::new(
+            SamplerAddress::Clamp,
+            SamplerFilter::Point,
+            SamplerAnisotropy::Off,
+        ),
+    ];
+
+    const fn new(
+        address: SamplerAddress,
+        filter: SamplerFilter,
+        anisotropy: SamplerAnisotropy,
+    ) -> Self {
+        Self {
+            address,
+            anisotropy,
+            filter,
+        }
+    }
+
+    /// Returns the index of the sampler which shaders use for this state.
+    pub fn index(self) -> u8 {
+        let filter = match (self.filter, self.anisotropy) {
+            (SamplerFilter::Point, _) => 3,
+            (SamplerFilter::Linear, SamplerAnisotropy::Off) => 0,
+            (SamplerFilter::Linear, SamplerAnisotropy::Low) => 1,
+            (SamplerFilter::Linear, SamplerAnisotropy::Full) => 2,
+        };
+
+        self.address as u8 * 4 + filter
+    }
+}
+
+/// What texture coordinates outside of zero to one sample.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerAddress {
+    /// The texture tiles.
+    #[default]
+    Repeat,
+
+    /// The edge texels stretch, such as for decals and signs which should not bleed into their
+    /// opposite edge.
+    Clamp,
+}
+
+/// Anisotropic filtering of a material, which is never more than the texture filtering setting.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerAnisotropy {
+    Off,
+
+    /// At most 2x, for surfaces which are rarely seen at glancing angles.
+    Low,
+
+    #[default]
+    Full,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerFilter {
+    #[default]
+    Linear,
+
+    /// The nearest texel and mip level, for the blocky look of retro pixel art.
+    Point,
+}
+
+pub type MaterialAnimations = HashMap<MaterialId, MaterialAnimation>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn sampler_indices() {
+        for (index, sampler) in MaterialSampler::ALL.into_iter().enumerate() {
+            assert_eq!(sampler.index() as usize, index);
+        }
+
+        // Point filtering does not blend texels, so anisotropy makes no difference
+        let point = MaterialSampler {
+            address: SamplerAddress::Clamp,
+            anisotropy: SamplerAnisotropy::Full,
+            filter: SamplerFilter::Point,
+        };
+
+        assert_eq!(point.index(), 7);
+        assert_eq!(MaterialSampler::default().index(), 2);
+    }
+}
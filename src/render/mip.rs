@@ -0,0 +1,192 @@
+use {super::compressed_bitmap::CompressedFormat, screen_13::prelude::*, std::sync::Arc};
+
+/// Returns the image format which holds the blocks of a compressed bitmap.
+pub fn compressed_image_format(format: CompressedFormat) -> vk::Format {
+    match format {
+        CompressedFormat::Bc5 => vk::Format::BC5_UNORM_BLOCK,
+        CompressedFormat::Bc7 => vk::Format::BC7_UNORM_BLOCK,
+    }
+}
+
+/// Returns the number of levels in a complete mip chain for an image of the given size.
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+/// Returns a copy of the given single-level image which includes a complete mip chain.
+///
+/// Each level is produced by a linear blit of the previous level, so the image format must
+/// support linear filtering when used as a blit source.
+pub fn generate_mip_chain(
+    device: &Arc<Device>,
+    pool: &mut LazyPool,
+    queue_index: usize,
+    image: Arc<Image>,
+) -> Result<Arc<Image>, DriverError> {
+    let mip_level_count = mip_level_count(image.info.width, image.info.height);
+
+    if mip_level_count == 1 {
+        return Ok(image);
+    }
+
+    let mip_image = Arc::new(Image::create(
+        device,
+        ImageInfo::new_2d(
+            image.info.fmt,
+            image.info.width,
+            image.info.height,
+            vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_DST
+                | vk::ImageUsageFlags::TRANSFER_SRC,
+        )
+        .mip_level_count(mip_level_count),
+    )?);
+
+    let mut render_graph = RenderGraph::new();
+    let image = render_graph.bind_node(image);
+    let mip_image_node = render_graph.bind_node(&mip_image);
+
+    render_graph.copy_image_region(
+        image,
+        mip_image_node,
+        vk::ImageCopy {
+            src_subresource: subresource_layers(0),
+            src_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+            dst_subresource: subresource_layers(0),
+            dst_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+            extent: vk::Extent3D {
+                width: mip_image.info.width,
+                height: mip_image.info.height,
+                depth: 1,
+            },
+        },
+    );
+
+    for mip_level in 1..mip_level_count {
+        render_graph.blit_image_region(
+            mip_image_node,
+            mip_image_node,
+            vk::Filter::LINEAR,
+            vk::ImageBlit {
+                src_subresource: subresource_layers(mip_level - 1),
+                src_offsets: [
+                    vk::Offset3D { x: 0, y: 0, z: 0 },
+                    mip_offset(&mip_image.info, mip_level - 1),
+                ],
+                dst_subresource: subresource_layers(mip_level),
+                dst_offsets: [
+                    vk::Offset3D { x: 0, y: 0, z: 0 },
+                    mip_offset(&mip_image.info, mip_level),
+                ],
+            },
+        );
+    }
+
+    render_graph.resolve().submit(pool, 0, queue_index)?;
+
+    Ok(mip_image)
+}
+
+/// Returns an image containing the given mip levels, which must be tightly packed data of the
+/// given format starting with the full size level.
+///
+/// Used for block-compressed images, which cannot be blitted to produce their own mips.
+pub fn create_mip_chain(
+    device: &Arc<Device>,
+    pool: &mut LazyPool,
+    queue_index: usize,
+    fmt: vk::Format,
+    width: u32,
+    height: u32,
+    mips: &[Vec<u8>],
+) -> Result<Arc<Image>, DriverError> {
+    let mut render_graph = RenderGraph::new();
+    let image = record_mip_chain(device, &mut render_graph, fmt, width, height, mips)?;
+
+    render_graph.resolve().submit(pool, 0, queue_index)?;
+
+    Ok(image)
+}
+
+/// Like [`create_mip_chain`], but the upload is recorded into the given render graph so that the
+/// caller may choose where it is submitted.
+pub fn record_mip_chain(
+    device: &Arc<Device>,
+    render_graph: &mut RenderGraph,
+    fmt: vk::Format,
+    width: u32,
+    height: u32,
+    mips: &[Vec<u8>],
+) -> Result<Arc<Image>, DriverError> {
+    let image = Arc::new(Image::create(
+        device,
+        ImageInfo::new_2d(
+            fmt,
+            width,
+            height,
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        )
+        .mip_level_count(mips.len() as _),
+    )?);
+    let buf = Buffer::create_from_slice(device, vk::BufferUsageFlags::TRANSFER_SRC, mips.concat())?;
+    let buf = render_graph.bind_node(buf);
+    let image_node = render_graph.bind_node(&image);
+    let mut buffer_offset = 0;
+
+    for (mip_level, mip) in mips.iter().enumerate() {
+        let mip_level = mip_level as u32;
+        let extent = mip_offset(&image.info, mip_level);
+
+        render_graph.copy_buffer_to_image_region(
+            buf,
+            image_node,
+            vk::BufferImageCopy {
+                buffer_offset,
+                buffer_row_length: 0,
+                buffer_image_height: 0,
+                image_subresource: subresource_layers(mip_level),
+                image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+                image_extent: vk::Extent3D {
+                    width: extent.x as _,
+                    height: extent.y as _,
+                    depth: 1,
+                },
+            },
+        );
+
+        buffer_offset += mip.len() as vk::DeviceSize;
+    }
+
+    Ok(image)
+}
+
+fn mip_offset(info: &ImageInfo, mip_level: u32) -> vk::Offset3D {
+    vk::Offset3D {
+        x: (info.width >> mip_level).max(1) as _,
+        y: (info.height >> mip_level).max(1) as _,
+        z: 1,
+    }
+}
+
+fn subresource_layers(mip_level: u32) -> vk::ImageSubresourceLayers {
+    vk::ImageSubresourceLayers {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        mip_level,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn mip_level_counts() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(2, 1), 2);
+        assert_eq!(mip_level_count(256, 256), 9);
+        assert_eq!(mip_level_count(1024, 512), 11);
+        assert_eq!(mip_level_count(1000, 3), 10);
+    }
+}
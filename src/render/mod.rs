@@ -1,12 +1,34 @@
 pub mod bitmap;
 pub mod camera;
+pub mod capabilities;
+pub mod colorblind;
+pub mod compressed_bitmap;
+pub mod debug;
+pub mod depth_of_field;
+pub mod frame_timer;
+
+#[cfg(feature = "hot-shaders")]
+pub mod hot_shader;
+
+pub mod material_animation;
+pub mod mip;
 pub mod model;
+pub mod overlay;
+pub mod pipeline_cache;
+pub mod primitives;
+pub mod sky;
+pub mod transfer;
 
 mod bounding_sphere;
 mod excl_sum;
+mod frame_budget;
+mod outline;
+mod pending_pipelines;
+mod size_class_pool;
+mod ssao;
 
 use {
-    crate::res,
+    crate::{asset_key::BlobKey, res},
     bytemuck::{bytes_of, cast_slice, NoUninit},
     pak::{Pak, PakBuf},
     screen_13::prelude::*,
@@ -58,8 +80,8 @@ fn open_res_pak() -> Result<PakBuf, DriverError> {
     })
 }
 
-fn read_blob(pak: &mut PakBuf, key: &str) -> Result<Vec<u8>, DriverError> {
-    pak.read_blob(key).map_err(|err| {
+fn read_blob(pak: &mut PakBuf, key: BlobKey) -> Result<Vec<u8>, DriverError> {
+    pak.read_blob(key.as_str()).map_err(|err| {
         error!("Unable to read blob {key}: {err}");
 
         DriverError::InvalidData
@@ -73,15 +95,50 @@ fn res_shader_dir() -> PathBuf {
 
 #[cfg(test)]
 mod tests {
-    #[cfg_attr(target_os = "macos", test)]
-    pub fn run_tests() {
-        super::bounding_sphere::tests::bounding_sphere1();
-        super::bounding_sphere::tests::bounding_sphere2();
-        super::bounding_sphere::tests::bounding_sphere3();
-
-        super::excl_sum::tests::exclusive_sum1();
-        super::excl_sum::tests::exclusive_sum2();
-        super::excl_sum::tests::exclusive_sum3();
-        super::excl_sum::tests::exclusive_sum4();
+    use {
+        screen_13::prelude::*,
+        std::{ops::Deref, sync::Arc},
+    };
+
+    #[cfg(target_os = "macos")]
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+
+    /// A headless device for tests which use the graphics device.
+    ///
+    /// MoltenVK may report an error when several logical devices run at the same time, so on macOS
+    /// each test device holds a lock until it is dropped and those tests run one at a time.
+    pub struct TestDevice {
+        device: Arc<Device>,
+
+        // Declared after the device so that the device is destroyed first
+        #[cfg(target_os = "macos")]
+        _serial: MutexGuard<'static, ()>,
+    }
+
+    impl TestDevice {
+        pub fn new() -> Self {
+            #[cfg(target_os = "macos")]
+            let _serial = {
+                static SERIAL: Mutex<()> = Mutex::new(());
+
+                // A test which panicked while holding the lock has already dropped its device
+                SERIAL.lock().unwrap_or_else(PoisonError::into_inner)
+            };
+
+            Self {
+                device: Arc::new(Device::create_headless(DeviceInfo::new()).unwrap()),
+
+                #[cfg(target_os = "macos")]
+                _serial,
+            }
+        }
+    }
+
+    impl Deref for TestDevice {
+        type Target = Arc<Device>;
+
+        fn deref(&self) -> &Self::Target {
+            &self.device
+        }
     }
 }
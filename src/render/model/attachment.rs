@@ -0,0 +1,181 @@
+use {
+    super::ModelInstance,
+    glam::{Quat, Vec3},
+    std::collections::HashMap,
+};
+
+/// Where a child model instance sits relative to its parent.
+#[derive(Clone, Copy, Debug)]
+struct Attachment {
+    parent: ModelInstance,
+    rotation: Quat,
+    translation: Vec3,
+}
+
+/// Parent and child relationships between model instances, such as a weapon held by an actor or a
+/// keycard lying on a desk, so that moving the parent moves its children.
+///
+/// A child may itself be the parent of other instances; cycles are not allowed.
+#[derive(Debug, Default)]
+pub struct Attachments {
+    attachments: HashMap<ModelInstance, Attachment>,
+}
+
+impl Attachments {
+    /// Attaches `child` to `parent` at the given offset from the parent, replacing any earlier
+    /// attachment of the child.
+    ///
+    /// Returns `false`, without attaching anything, if `parent` is `child` or is attached, however
+    /// indirectly, to it.
+    pub fn attach(
+        &mut self,
+        child: ModelInstance,
+        parent: ModelInstance,
+        translation: Vec3,
+        rotation: Quat,
+    ) -> bool {
+        let mut ancestor = Some(parent);
+
+        while let Some(model_instance) = ancestor {
+            if model_instance == child {
+                return false;
+            }
+
+            ancestor = self.parent(model_instance);
+        }
+
+        self.attachments.insert(
+            child,
+            Attachment {
+                parent,
+                rotation,
+                translation,
+            },
+        );
+
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attachments.is_empty()
+    }
+
+    fn parent(&self, child: ModelInstance) -> Option<ModelInstance> {
+        self.attachments
+            .get(&child)
+            .map(|attachment| attachment.parent)
+    }
+
+    /// Forgets a model instance which no longer exists, detaching it from its parent and its
+    /// children from it.
+    ///
+    /// Returns the children which were detached, which stay wherever they were last resolved.
+    pub fn remove(&mut self, model_instance: ModelInstance) -> Vec<ModelInstance> {
+        self.attachments.remove(&model_instance);
+
+        let children = self
+            .attachments
+            .iter()
+            .filter(|(_, attachment)| attachment.parent == model_instance)
+            .map(|(&child, _)| child)
+            .collect::<Vec<_>>();
+
+        for child in &children {
+            self.attachments.remove(child);
+        }
+
+        children
+    }
+
+    /// Returns the world transform of each attached model instance, in no particular order, given
+    /// the transform of each instance which is not attached.
+    pub fn resolve(
+        &self,
+        transform: impl Fn(ModelInstance) -> (Vec3, Quat),
+    ) -> Vec<(ModelInstance, Vec3, Quat)> {
+        let mut resolved = HashMap::with_capacity(self.attachments.len());
+
+        for &child in self.attachments.keys() {
+            self.resolve_instance(child, &transform, &mut resolved);
+        }
+
+        resolved
+            .into_iter()
+            .map(|(model_instance, (translation, rotation))| {
+                (model_instance, translation, rotation)
+            })
+            .collect()
+    }
+
+    /// Resolves the parents of an attached instance first, so that each is resolved once however
+    /// deep the hierarchy is.
+    fn resolve_instance(
+        &self,
+        model_instance: ModelInstance,
+        transform: &impl Fn(ModelInstance) -> (Vec3, Quat),
+        resolved: &mut HashMap<ModelInstance, (Vec3, Quat)>,
+    ) -> (Vec3, Quat) {
+        let Some(attachment) = self.attachments.get(&model_instance) else {
+            return transform(model_instance);
+        };
+
+        if let Some(&world) = resolved.get(&model_instance) {
+            return world;
+        }
+
+        let (parent_translation, parent_rotation) =
+            self.resolve_instance(attachment.parent, transform, resolved);
+        let world = (
+            parent_translation + parent_rotation * attachment.translation,
+            (parent_rotation * attachment.rotation).normalize(),
+        );
+        resolved.insert(model_instance, world);
+
+        world
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::f32::consts::FRAC_PI_2};
+
+    #[test]
+    pub fn resolve_hierarchy() {
+        let actor = ModelInstance(0);
+        let weapon = ModelInstance(1);
+        let scope = ModelInstance(2);
+        let mut attachments = Attachments::default();
+
+        assert!(attachments.attach(scope, weapon, Vec3::Y, Quat::IDENTITY));
+        assert!(attachments.attach(weapon, actor, Vec3::X, Quat::IDENTITY));
+        assert!(!attachments.attach(actor, scope, Vec3::ZERO, Quat::IDENTITY));
+        assert!(!attachments.attach(actor, actor, Vec3::ZERO, Quat::IDENTITY));
+
+        // Turning a quarter about +Y points the +X axis of the actor down -Z
+        let actor_rotation = Quat::from_rotation_y(FRAC_PI_2);
+        let resolved = attachments.resolve(|model_instance| {
+            assert_eq!(model_instance, actor);
+
+            (Vec3::new(10.0, 0.0, 0.0), actor_rotation)
+        });
+        let world = |model_instance| {
+            resolved
+                .iter()
+                .find(|(resolved, ..)| *resolved == model_instance)
+                .map(|&(_, translation, rotation)| (translation, rotation))
+                .unwrap()
+        };
+
+        assert_eq!(resolved.len(), 2);
+        assert!(world(weapon)
+            .0
+            .abs_diff_eq(Vec3::new(10.0, 0.0, -1.0), 1e-5));
+        assert!(world(scope).0.abs_diff_eq(Vec3::new(10.0, 1.0, -1.0), 1e-5));
+        assert!(world(scope).1.abs_diff_eq(actor_rotation, 1e-5));
+
+        assert_eq!(attachments.remove(actor), [weapon]);
+        assert_eq!(attachments.parent(scope), Some(weapon));
+        assert_eq!(attachments.remove(weapon), [scope]);
+        assert!(attachments.is_empty());
+    }
+}
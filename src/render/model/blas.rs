@@ -0,0 +1,470 @@
+//! Builds the bottom-level acceleration structures of loaded models on a worker thread, so that
+//! loading a level never waits on them, and compacts each one once its compacted size is known.
+//!
+//! Compaction usually halves the memory of a BLAS, but the compacted size is only known after the
+//! build has executed, so every model is built, read back and then copied into a smaller
+//! structure before the ray trace technique uses it.
+//!
+//! During play the builds are paced by the [`FrameBudget`], using the time each build took on the
+//! GPU, so that building does not slow the frames drawn alongside it.
+
+use {
+    super::{
+        super::{
+            debug::capture::pass_name,
+            frame_budget::{FrameBudget, StreamingWork},
+        },
+        Geometry,
+    },
+    crossbeam_channel::{unbounded, Receiver, Sender},
+    screen_13::prelude::*,
+    std::{
+        collections::VecDeque,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread::{spawn, JoinHandle},
+        time::Instant,
+    },
+};
+
+/// Returns the range of every primitive of each geometry.
+pub fn build_ranges(
+    geometry_info: &AccelerationStructureGeometryInfo,
+) -> Box<[vk::AccelerationStructureBuildRangeInfoKHR]> {
+    geometry_info
+        .geometries
+        .iter()
+        .map(|geometry| vk::AccelerationStructureBuildRangeInfoKHR {
+            first_vertex: 0,
+            primitive_count: geometry.max_primitive_count,
+            primitive_offset: 0,
+            transform_offset: 0,
+        })
+        .collect()
+}
+
+/// Returns the layout used to build the acceleration structure of a model, whose geometry is read
+/// from the geometry buffer at the given device address.
+pub fn geometry_info(
+    geometry_address: vk::DeviceAddress,
+    geometries: &[Geometry],
+    flags: vk::BuildAccelerationStructureFlagsKHR,
+) -> AccelerationStructureGeometryInfo {
+    let geometries = geometries
+        .iter()
+        .map(|geom| AccelerationStructureGeometry {
+            max_primitive_count: geom.index_count / 3,
+            flags: vk::GeometryFlagsKHR::OPAQUE,
+            geometry: AccelerationStructureGeometryData::Triangles {
+                index_data: DeviceOrHostAddress::DeviceAddress(
+                    geometry_address + geom.index_offset,
+                ),
+                index_type: geom.flags.index_ty(),
+                max_vertex: geom.index_count,
+                transform_data: None,
+                vertex_data: DeviceOrHostAddress::DeviceAddress(
+                    geometry_address + geom.vertex_offset,
+                ),
+                vertex_format: vk::Format::R32G32B32_SFLOAT,
+                vertex_stride: geom.flags.vertex_stride(),
+            },
+        })
+        .collect();
+
+    AccelerationStructureGeometryInfo {
+        ty: vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL,
+        flags,
+        geometries,
+    }
+}
+
+/// Reads back queries of one type, such as the compacted size of an acceleration structure.
+struct Queries {
+    device: Arc<Device>,
+    pool: vk::QueryPool,
+}
+
+impl Queries {
+    fn new(
+        device: &Arc<Device>,
+        query_type: vk::QueryType,
+        query_count: u32,
+    ) -> Result<Self, DriverError> {
+        let pool = unsafe {
+            device.create_query_pool(
+                &vk::QueryPoolCreateInfo::builder()
+                    .query_type(query_type)
+                    .query_count(query_count),
+                None,
+            )
+        }
+        .map_err(|err| {
+            warn!("Unable to create query pool: {err}");
+
+            DriverError::Unsupported
+        })?;
+
+        Ok(Self {
+            device: Arc::clone(device),
+            pool,
+        })
+    }
+
+    /// Returns the results, which are only available once the command buffer which wrote them has
+    /// executed.
+    fn results<const N: usize>(&self) -> Result<[u64; N], DriverError> {
+        let mut res = [0u64; N];
+
+        unsafe {
+            self.device.get_query_pool_results(
+                self.pool,
+                0,
+                N as _,
+                &mut res,
+                vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT,
+            )
+        }
+        .map_err(|err| {
+            warn!("Unable to read queries: {err}");
+
+            DriverError::InvalidData
+        })?;
+
+        Ok(res)
+    }
+}
+
+impl Drop for Queries {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_query_pool(self.pool, None);
+        }
+    }
+}
+
+/// The geometry of one model, in the layout used to build its acceleration structure.
+#[derive(Debug)]
+struct BlasRequest {
+    model_idx: usize,
+    geometry_info: AccelerationStructureGeometryInfo,
+}
+
+impl BlasRequest {
+    fn primitive_count(&self) -> usize {
+        self.geometry_info
+            .geometries
+            .iter()
+            .map(|geometry| geometry.max_primitive_count as usize)
+            .sum()
+    }
+}
+
+/// An acceleration structure built by the worker, and the seconds the build took.
+type BuiltBlas = Result<(Arc<AccelerationStructure>, f32), DriverError>;
+
+#[derive(Debug)]
+pub struct BlasQueue {
+    /// Number of models whose acceleration structure has been built, or failed to build.
+    built: Arc<AtomicUsize>,
+
+    geometry_address: vk::DeviceAddress,
+
+    /// Primitive count and seconds of each build which has finished since the previous release.
+    measured: Vec<(usize, f32)>,
+
+    /// Requests held back until the frame budget allows them.
+    queued: VecDeque<BlasRequest>,
+
+    rx: Receiver<(usize, usize, BuiltBlas)>,
+    thread: Option<JoinHandle<()>>,
+
+    /// Set once a frame budget limits the builds; until then requests are sent as they are
+    /// pushed, so that loading is not slowed.
+    throttled: bool,
+
+    tx: Option<Sender<BlasRequest>>,
+}
+
+impl BlasQueue {
+    pub fn spawn(device: &Arc<Device>, geometry_buf: &Arc<Buffer>) -> Self {
+        let built = Arc::new(AtomicUsize::new(0));
+        let (request_tx, request_rx) = unbounded::<BlasRequest>();
+        let (tx, rx) = unbounded();
+        let thread = {
+            let built = Arc::clone(&built);
+            let device = Arc::clone(device);
+            let geometry_buf = Arc::clone(geometry_buf);
+
+            spawn(move || {
+                let mut pool = LazyPool::new(&device);
+
+                for request in request_rx {
+                    let primitive_count = request.primitive_count();
+                    let blas =
+                        build_compacted(&device, &mut pool, &geometry_buf, request.geometry_info);
+
+                    built.fetch_add(1, Ordering::Relaxed);
+
+                    if tx.send((request.model_idx, primitive_count, blas)).is_err() {
+                        break;
+                    }
+                }
+            })
+        };
+
+        Self {
+            built,
+            geometry_address: Buffer::device_address(geometry_buf),
+            measured: Default::default(),
+            queued: Default::default(),
+            rx,
+            thread: Some(thread),
+            throttled: false,
+            tx: Some(request_tx),
+        }
+    }
+
+    /// Returns the number of models which have been built, out of every model pushed.
+    pub fn built_count(&self) -> usize {
+        self.built.load(Ordering::Relaxed)
+    }
+
+    /// Returns the acceleration structures which have finished building since the previous call,
+    /// along with the index of their model.
+    pub fn finished(&mut self) -> Result<Vec<(usize, Arc<AccelerationStructure>)>, DriverError> {
+        self.rx
+            .try_iter()
+            .map(|(model_idx, primitive_count, blas)| {
+                blas.map(|(blas, secs)| {
+                    self.measured.push((primitive_count, secs));
+
+                    (model_idx, blas)
+                })
+            })
+            .collect()
+    }
+
+    /// Queues the acceleration structure of a model, whose geometry has already been uploaded.
+    pub fn push(&mut self, model_idx: usize, geometries: &[Geometry]) {
+        let geometry_info = geometry_info(
+            self.geometry_address,
+            geometries,
+            vk::BuildAccelerationStructureFlagsKHR::ALLOW_COMPACTION
+                | vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE,
+        );
+        let request = BlasRequest {
+            model_idx,
+            geometry_info,
+        };
+
+        if self.throttled {
+            self.queued.push_back(request);
+        } else {
+            self.send(request);
+        }
+    }
+
+    /// Adds the time of finished builds to the frame budget and then starts as many queued builds
+    /// as it allows.
+    pub fn release(&mut self, budget: &mut FrameBudget) {
+        for (primitive_count, secs) in self.measured.drain(..) {
+            budget.measure(StreamingWork::BlasBuild, primitive_count, secs);
+        }
+
+        self.throttled = budget.is_limited();
+
+        while let Some(request) = self.queued.front() {
+            if !budget.try_spend(StreamingWork::BlasBuild, request.primitive_count()) {
+                break;
+            }
+
+            let request = self.queued.pop_front().unwrap();
+            self.send(request);
+        }
+    }
+
+    fn send(&self, request: BlasRequest) {
+        self.tx.as_ref().unwrap().send(request).unwrap_or_default();
+    }
+}
+
+impl Drop for BlasQueue {
+    fn drop(&mut self) {
+        // The worker finishes the build it started, and any which are queued, before stopping
+        self.tx.take();
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap_or_default();
+        }
+    }
+}
+
+/// Builds an acceleration structure and then copies it into one of its compacted size, waiting
+/// for each step to execute.
+///
+/// Returns the structure and the seconds the build took on the GPU, or waiting for it where the
+/// queue does not support timestamps.
+fn build_compacted(
+    device: &Arc<Device>,
+    pool: &mut LazyPool,
+    geometry_buf: &Arc<Buffer>,
+    geometry_info: AccelerationStructureGeometryInfo,
+) -> BuiltBlas {
+    // The frame loop submits to the first queue, so builds use the last one when there are more
+    let queue_index = device.physical_device.queue_families[0]
+        .queue_count
+        .saturating_sub(1) as usize;
+
+    let size = AccelerationStructure::size_of(device, &geometry_info);
+    let query = Queries::new(
+        device,
+        vk::QueryType::ACCELERATION_STRUCTURE_COMPACTED_SIZE_KHR,
+        1,
+    )?;
+    let query_pool = query.pool;
+    let timestamps = Queries::new(device, vk::QueryType::TIMESTAMP, 2)?;
+    let timestamp_pool = timestamps.pool;
+
+    let mut render_graph = RenderGraph::new();
+    let geometry_buf = render_graph.bind_node(geometry_buf);
+    let blas = render_graph.bind_node(AccelerationStructure::create(
+        device,
+        AccelerationStructureInfo {
+            ty: vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL,
+            size: size.create_size,
+        },
+    )?);
+
+    let accel_struct_scratch_offset_alignment = device
+        .physical_device
+        .accel_struct_properties
+        .as_ref()
+        .unwrap()
+        .min_accel_struct_scratch_offset_alignment
+        as vk::DeviceSize;
+    let scratch_buf = render_graph.bind_node(
+        pool.lease(
+            BufferInfo::new(
+                size.build_size,
+                vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS | vk::BufferUsageFlags::STORAGE_BUFFER,
+            )
+            .alignment(accel_struct_scratch_offset_alignment),
+        )?,
+    );
+
+    render_graph
+        .begin_pass(pass_name("Begin BLAS timestamp"))
+        .access_node(geometry_buf, AccessType::AccelerationStructureBuildRead)
+        .record_cmd_buf(move |device, cmd_buf, _| unsafe {
+            device.cmd_reset_query_pool(cmd_buf, timestamp_pool, 0, 2);
+            device.cmd_write_timestamp(
+                cmd_buf,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                timestamp_pool,
+                0,
+            );
+        });
+    render_graph
+        .begin_pass(pass_name("Build BLAS"))
+        .access_node(geometry_buf, AccessType::AccelerationStructureBuildRead)
+        .access_node(scratch_buf, AccessType::AccelerationStructureBufferWrite)
+        .access_node(blas, AccessType::AccelerationStructureBuildWrite)
+        .record_acceleration(move |accel, _| {
+            let build_ranges = build_ranges(&geometry_info);
+
+            accel.build_structure(blas, scratch_buf, &geometry_info, &build_ranges);
+        });
+    render_graph
+        .begin_pass(pass_name("Query compacted BLAS size"))
+        .access_node(blas, AccessType::AccelerationStructureBuildRead)
+        .record_cmd_buf(move |device, cmd_buf, bindings| unsafe {
+            device.cmd_reset_query_pool(cmd_buf, query_pool, 0, 1);
+            device
+                .accel_struct_ext
+                .as_ref()
+                .unwrap()
+                .cmd_write_acceleration_structures_properties(
+                    cmd_buf,
+                    &[**bindings[blas]],
+                    vk::QueryType::ACCELERATION_STRUCTURE_COMPACTED_SIZE_KHR,
+                    query_pool,
+                    0,
+                );
+            device.cmd_write_timestamp(
+                cmd_buf,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                timestamp_pool,
+                1,
+            );
+        });
+
+    let blas = render_graph.unbind_node(blas);
+    let started = Instant::now();
+
+    render_graph
+        .resolve()
+        .submit(pool, 0, queue_index)?
+        .wait_until_executed()?;
+
+    let secs = if device.physical_device.queue_families[0].timestamp_valid_bits > 0 {
+        let [begin, end] = timestamps.results()?;
+        let timestamp_period = device
+            .physical_device
+            .properties_v1_0
+            .limits
+            .timestamp_period;
+
+        end.saturating_sub(begin) as f32 * timestamp_period / 1_000_000_000.0
+    } else {
+        started.elapsed().as_secs_f32()
+    };
+    let [compacted_size] = query.results()?;
+
+    if compacted_size == 0 || compacted_size >= size.create_size {
+        return Ok((blas, secs));
+    }
+
+    let mut render_graph = RenderGraph::new();
+    let src = render_graph.bind_node(blas);
+    let dst = render_graph.bind_node(AccelerationStructure::create(
+        device,
+        AccelerationStructureInfo {
+            ty: vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL,
+            size: compacted_size,
+        },
+    )?);
+
+    render_graph
+        .begin_pass(pass_name("Compact BLAS"))
+        .access_node(src, AccessType::AccelerationStructureBuildRead)
+        .access_node(dst, AccessType::AccelerationStructureBuildWrite)
+        .record_cmd_buf(move |device, cmd_buf, bindings| unsafe {
+            device
+                .accel_struct_ext
+                .as_ref()
+                .unwrap()
+                .cmd_copy_acceleration_structure(
+                    cmd_buf,
+                    &vk::CopyAccelerationStructureInfoKHR::builder()
+                        .src(**bindings[src])
+                        .dst(**bindings[dst])
+                        .mode(vk::CopyAccelerationStructureModeKHR::COMPACT),
+                );
+        });
+
+    let compacted = render_graph.unbind_node(dst);
+
+    render_graph
+        .resolve()
+        .submit(pool, 0, queue_index)?
+        .wait_until_executed()?;
+
+    trace!(
+        "Compacted BLAS from {} to {compacted_size} bytes",
+        size.create_size
+    );
+
+    Ok((compacted, secs))
+}
@@ -1,20 +1,54 @@
+mod attachment;
+mod blas;
 mod raster;
 mod ray_trace;
+mod reflection_probe;
 mod sbt;
+mod skin;
+mod spatial_index;
+mod texture_streaming;
+
+pub use self::{reflection_probe::ReflectionProbe, texture_streaming::base_mip_level};
 
 use {
-    self::{super::camera::Camera, raster::Raster, ray_trace::RayTrace},
-    crate::math::{align_up_u32, align_up_u64},
-    anyhow::Context,
+    self::{
+        super::{
+            camera::{Camera, Viewport},
+            capabilities::DeviceCapabilities,
+            compressed_bitmap::CompressedBitmaps,
+            debug::DebugMode,
+            depth_of_field::DepthOfField,
+            frame_budget::FrameBudget,
+            lease_buffer,
+            material_animation::{
+                Flipbook, MaterialAnimation, MaterialSampler, SamplerAddress, SamplerAnisotropy,
+                SamplerFilter,
+            },
+            sky::Sky,
+            transfer::{PendingUploads, TransferQueue},
+        },
+        attachment::Attachments,
+        raster::Raster,
+        ray_trace::RayTrace,
+        reflection_probe::{ReflectionProbeNodes, ReflectionProbes},
+        skin::{PosedModel, SkinnedMesh, Skinning},
+        spatial_index::SpatialIndex,
+        texture_streaming::TextureStreaming,
+    },
+    crate::math::{align_up_u32, align_up_u64, Aabb, Ray},
+    anyhow::{ensure, Context},
     bitflags::bitflags,
-    bytemuck::{bytes_of, cast_slice, Pod, Zeroable},
+    bytemuck::{bytes_of, cast_slice, pod_read_unaligned, Pod, Zeroable},
     derive_builder::{Builder, UninitializedFieldError},
-    glam::{Quat, Vec3},
-    pak::model::{ModelBuf, Vertex},
+    glam::{Mat4, Quat, UVec2, Vec3, Vec4},
+    pak::{
+        model::{ModelBuf, Vertex},
+        BitmapId,
+    },
     screen_13::prelude::*,
     serde::{Deserialize, Serialize},
     std::{
-        collections::HashMap,
+        collections::{BTreeMap, HashMap, VecDeque},
         fmt::Debug,
         iter::repeat,
         mem::size_of,
@@ -25,6 +59,13 @@ use {
 
 const MAX_MATERIALS_PER_MODEL: usize = 8;
 
+/// Binding of the first material sampler, which are bound consecutively in the order of
+/// [`MaterialSampler::ALL`] (see material_fns.glsl).
+const MATERIAL_SAMPLER_BINDING: u32 = 12;
+
+/// The binding and sampler information of each material sampler.
+type MaterialSamplerInfos = [(u32, SamplerInfo); MaterialSampler::ALL.len()];
+
 fn material_array(materials: &[Material]) -> [Material; MAX_MATERIALS_PER_MODEL] {
     debug_assert!(!materials.is_empty());
 
@@ -49,10 +90,47 @@ fn material_array(materials: &[Material]) -> [Material; MAX_MATERIALS_PER_MODEL]
     materials_array
 }
 
+/// Returns the copy between a viewport of the framebuffer and an image of its size.
+fn viewport_copy(viewport: Viewport, from_framebuffer: bool) -> vk::ImageCopy {
+    let subresource = vk::ImageSubresourceLayers {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        mip_level: 0,
+        base_array_layer: 0,
+        layer_count: 1,
+    };
+    let framebuffer_offset = vk::Offset3D {
+        x: viewport.offset.x as _,
+        y: viewport.offset.y as _,
+        z: 0,
+    };
+    let (src_offset, dst_offset) = if from_framebuffer {
+        (framebuffer_offset, vk::Offset3D::default())
+    } else {
+        (vk::Offset3D::default(), framebuffer_offset)
+    };
+
+    vk::ImageCopy {
+        src_subresource: subresource,
+        src_offset,
+        dst_subresource: subresource,
+        dst_offset,
+        extent: vk::Extent3D {
+            width: viewport.extent.x,
+            height: viewport.extent.y,
+            depth: 1,
+        },
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
 struct Geometry {
     flags: MeshFlags,
     index_count: u32,
     index_offset: vk::DeviceSize,
+
+    /// Index into the materials of each model instance.
+    material: u8,
+
     vertex_count: u32,
     vertex_offset: vk::DeviceSize,
 }
@@ -61,18 +139,63 @@ struct Geometry {
 #[repr(C)]
 pub struct Material {
     material_index: u32,
+    flags: MaterialFlags,
+    _0: [u8; 3],
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Pod, Zeroable)]
+impl Material {
+    pub fn is_emissive(self) -> bool {
+        self.flags.contains(MaterialFlags::EMISSIVE)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
 #[repr(C)]
 struct MaterialData {
     color_index: u32,
     flags: MaterialFlags,
-    _0: [u8; 3],
+
+    /// Index of the sampler which the textures of the material are sampled with.
+    sampler_index: u8,
+
+    _0: [u8; 2],
+    scroll: [f32; 2],
+    fps: f32,
+    frames: u32,
+    _1: [u32; 2],
+    emissive: MaterialEmissive,
 }
 
 impl MaterialData {
     const SIZE: vk::DeviceSize = size_of::<Self>() as _;
+
+    /// Offset of `emissive`, which is written on its own by [`ModelBuffer::set_material_emissive`].
+    const EMISSIVE_OFFSET: vk::DeviceSize = Self::SIZE - MaterialEmissive::SIZE;
+
+    /// Packs the frame count into the low 16 bits followed by the flipbook columns and rows, or
+    /// returns zero for materials without a flipbook.
+    fn pack_frames(flipbook: Option<Flipbook>) -> u32 {
+        flipbook
+            .map(|flipbook| {
+                flipbook.frame_count as u32
+                    | (flipbook.columns as u32) << 16
+                    | (flipbook.rows as u32) << 24
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Multiplies the emissive texture of a material, which is the light it gives off in both
+/// techniques.
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+struct MaterialEmissive {
+    color: Vec3,
+    intensity: f32,
+}
+
+impl MaterialEmissive {
+    const SIZE: vk::DeviceSize = size_of::<Self>() as _;
 }
 
 bitflags! {
@@ -80,6 +203,12 @@ bitflags! {
     #[repr(transparent)]
     pub struct MaterialFlags: u8 {
         const EMISSIVE = 0b0000_0001;
+
+        /// Color alpha below one half is discarded, for cutouts such as foliage and grates.
+        const MASKED = 0b0000_0010;
+
+        /// Back faces are drawn instead of culled.
+        const TWO_SIDED = 0b0000_0100;
     }
 }
 
@@ -126,6 +255,30 @@ impl MeshFlags {
     }
 }
 
+bitflags! {
+    /// Groups of model instances, of which each model buffer draws only some; see
+    /// [`ModelBuffer::set_layers`].
+    ///
+    /// Layers are also the ray tracing instance mask, so there may be no more than eight.
+    #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    pub struct RenderLayers: u8 {
+        /// The level and everything in it, which is the layer of new model instances.
+        const WORLD = 0b0000_0001;
+
+        /// The first-person weapon.
+        const VIEW_MODEL = 0b0000_0010;
+
+        /// Helpers, such as markers of triggers and sounds, which only the level editor shows.
+        const EDITOR = 0b0000_0100;
+    }
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self::WORLD | Self::VIEW_MODEL
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Model {
     mesh_idx: usize,
@@ -134,25 +287,98 @@ pub struct Model {
 
 #[derive(Debug)]
 pub struct ModelBuffer {
+    /// Model instances which follow another, and are moved to it each frame before recording.
+    attachments: Attachments,
+
+    debug_mode: DebugMode,
+    depth_of_field: Option<DepthOfField>,
+    depth_prepass: bool,
+
+    /// Paces texture streaming and acceleration structure builds during play.
+    frame_budget: FrameBudget,
+
     geometry_buf: Arc<Buffer>,
     geometry_len: vk::DeviceSize,
+
+    /// The model instance under the pick position, as of the previous frame.
+    hovered_instance: Option<ModelInstance>,
+
+    info: ModelBufferInfo,
+
+    /// Bounding sphere of each model instance, so that game code may find nearby instances.
+    instance_bounds: SpatialIndex<ModelInstance>,
+
+    /// Layers of the model instances which are drawn.
+    layers: RenderLayers,
+
     material_buf: Arc<Buffer>,
+
+    /// Index into `textures` of the color texture of each material, which a render target may
+    /// replace.
+    material_color_indices: Vec<usize>,
+
     material_count: usize,
+
+    /// Emissive factors of each material as it was loaded, by material index.
+    material_emissive: Vec<MaterialEmissive>,
+
+    /// Emissive factors set since the previous frame, by material index.
+    material_emissive_changes: BTreeMap<u32, MaterialEmissive>,
+
     mesh_buf: Arc<Buffer>,
     mesh_count: usize,
-    model_count: usize,
+    /// Object-space bounds of each loaded model.
+    model_bounds: Vec<Aabb>,
+
+    /// Geometry of each loaded model, and of each posed copy of a skinned model, retained so a new
+    /// technique may be built from it.
+    model_geometries: Vec<Box<[Geometry]>>,
+
     model_instance_id: usize,
     model_instance_index: HashMap<ModelInstance, usize>,
     model_instances: Vec<ModelInstance>,
+    overlay: bool,
+    pending_uploads: PendingUploads,
+
+    /// Buffers which the pick result is written to in turn, so that each is read back two frames
+    /// after it was written, once the GPU is known to have finished with it.
+    pick_bufs: [Arc<Buffer>; 3],
+
+    pick_frame: usize,
+
+    /// The viewports and buffer indices of pick results not yet read back, oldest first; the
+    /// oldest is read back once [`Self::PICK_LATENCY`] results are pending.
+    pick_pending: VecDeque<(Viewport, usize)>,
+
+    pick_position: Option<UVec2>,
     pool: LazyPool,
+    reflection_probes: ReflectionProbes,
+    render_targets: Vec<RenderTarget>,
+    skinning: Skinning,
+    sky: Sky,
     textures: Vec<Arc<Image>>,
     technique: Box<dyn Technique>,
+    technique_kind: ModelBufferTechnique,
+    texture_streaming: Option<TextureStreaming>,
+
+    /// Seconds which have passed, used to animate materials.
+    time: f32,
+
+    transfer_queue: TransferQueue,
 }
 
 impl ModelBuffer {
+    /// Frames between drawing a pick result and reading it back.
+    const PICK_LATENCY: usize = 2;
+
     pub fn new(device: &Arc<Device>, info: impl Into<ModelBufferInfo>) -> anyhow::Result<Self> {
         let info: ModelBufferInfo = info.into();
 
+        debug_assert!(
+            !info.overlay || info.technique != Some(ModelBufferTechnique::RayTrace),
+            "Overlay requires raster technique"
+        );
+
         if let Some(technique) = info.technique {
             info!(
                 "Using {} technique",
@@ -165,23 +391,26 @@ impl ModelBuffer {
         }
 
         let technique = info.technique.unwrap_or_else(|| {
-            if device.physical_device.ray_trace_properties.is_some() {
-                info!("Defaulting to ray trace technique");
+            let technique = Self::default_technique(device, info);
 
-                ModelBufferTechnique::RayTrace
-            } else {
-                info!("Using raster technique");
-
-                ModelBufferTechnique::Raster
+            if !info.overlay {
+                match technique {
+                    ModelBufferTechnique::Raster => info!("Using raster technique"),
+                    ModelBufferTechnique::RayTrace => info!("Defaulting to ray trace technique"),
+                }
             }
+
+            technique
         });
+
+        // Geometry is read by shaders using its device address, and is usable by acceleration
+        // structures whenever the device supports them so that the technique may be switched later
         let geometry_usage = vk::BufferUsageFlags::STORAGE_BUFFER
-            | match technique {
-                ModelBufferTechnique::Raster => vk::BufferUsageFlags::empty(),
-                ModelBufferTechnique::RayTrace => {
-                    vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
-                        | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS
-                }
+            | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS
+            | if Self::supports_technique(device, info, ModelBufferTechnique::RayTrace) {
+                vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
+            } else {
+                vk::BufferUsageFlags::empty()
             };
         let geometry_buf = Arc::new(Buffer::create(
             device,
@@ -208,34 +437,159 @@ impl ModelBuffer {
             ),
         )?);
 
-        let technique: Box<dyn Technique> = match technique {
-            ModelBufferTechnique::Raster => {
-                Box::new(Raster::new(device, info).context("Creating raster technique")?)
-            }
-            ModelBufferTechnique::RayTrace => {
-                Box::new(RayTrace::new(device, info).context("Creating ray trace technique")?)
-            }
+        let pick_buf = || {
+            Buffer::create(
+                device,
+                BufferInfo::new_mappable(
+                    size_of::<u32>() as _,
+                    vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+                ),
+            )
+            .map(Arc::new)
         };
+        let pick_bufs = [pick_buf()?, pick_buf()?, pick_buf()?];
 
-        let pool = LazyPool::new(device);
+        let technique_kind = technique;
+        let technique = Self::create_technique(device, info, technique, &geometry_buf)?;
+
+        let mut pool = LazyPool::new(device);
+        let reflection_probes =
+            ReflectionProbes::new(device, &mut pool, info.reflection_probe_capacity as _)
+                .context("Creating reflection probes")?;
+        let skinning = Skinning::new(device).context("Creating skinning")?;
 
         Ok(Self {
+            attachments: Default::default(),
+            debug_mode: Default::default(),
+            depth_of_field: None,
+            depth_prepass: false,
+            frame_budget: Default::default(),
             geometry_buf,
             geometry_len: 0,
+            hovered_instance: None,
+            info,
+            instance_bounds: Default::default(),
+            layers: Default::default(),
             material_buf,
+            material_color_indices: Default::default(),
             material_count: 0,
+            material_emissive: Default::default(),
+            material_emissive_changes: Default::default(),
             mesh_buf,
             mesh_count: 0,
-            model_count: 0,
+            model_bounds: Default::default(),
+            model_geometries: Default::default(),
             model_instance_id: 0,
             model_instance_index: Default::default(),
             model_instances: Default::default(),
+            overlay: info.overlay,
+            pending_uploads: Default::default(),
+            pick_bufs,
+            pick_frame: 0,
+            pick_pending: VecDeque::with_capacity(Self::PICK_LATENCY),
+            pick_position: None,
             pool,
+            reflection_probes,
+            render_targets: Default::default(),
+            skinning,
+            sky: Default::default(),
             textures: Default::default(),
             technique,
+            technique_kind,
+            texture_streaming: None,
+            time: 0.0,
+            transfer_queue: TransferQueue::new(device),
+        })
+    }
+
+    fn create_technique(
+        device: &Arc<Device>,
+        info: ModelBufferInfo,
+        technique: ModelBufferTechnique,
+        geometry_buf: &Arc<Buffer>,
+    ) -> anyhow::Result<Box<dyn Technique>> {
+        Ok(match technique {
+            ModelBufferTechnique::Raster => {
+                Box::new(Raster::new(device, info).context("Creating raster technique")?)
+            }
+            ModelBufferTechnique::RayTrace => Box::new(
+                RayTrace::new(device, info, geometry_buf)
+                    .context("Creating ray trace technique")?,
+            ),
         })
     }
 
+    /// Returns the technique used when none is given: ray tracing where it is supported.
+    fn default_technique(device: &Device, info: ModelBufferInfo) -> ModelBufferTechnique {
+        if Self::supports_technique(device, info, ModelBufferTechnique::RayTrace) {
+            ModelBufferTechnique::RayTrace
+        } else {
+            ModelBufferTechnique::Raster
+        }
+    }
+
+    /// Advances the animation of scrolling and flipbook materials, the time until each render
+    /// target is drawn again and the streaming of textures and acceleration structures, which is
+    /// done once a frame within the streaming budget.
+    pub fn advance_time(&mut self, dt: f32) {
+        self.time += dt;
+        self.sky.advance(dt);
+
+        for render_target in &mut self.render_targets {
+            render_target.since_drawn += dt;
+            render_target.due |= render_target.since_drawn >= render_target.interval;
+        }
+
+        self.frame_budget.begin_frame();
+
+        if let Some(texture_streaming) = &mut self.texture_streaming {
+            if let Err(err) = texture_streaming.update(&mut self.textures, &mut self.frame_budget) {
+                warn!("Unable to stream textures: {err}");
+            }
+        }
+
+        self.technique.stream(&mut self.frame_budget);
+    }
+
+    /// Attaches `child` to `parent` at the given offset from the parent, so that it follows the
+    /// parent from the next frame on, such as a weapon held by an actor or a keycard lying on a
+    /// desk. Attaching an attached instance again changes its parent or offset.
+    ///
+    /// The transform of an attached instance is replaced each frame; children of a removed parent
+    /// are detached and stay where they were last drawn.
+    pub fn attach_model_instance(
+        &mut self,
+        child: ModelInstance,
+        parent: ModelInstance,
+        translation: Vec3,
+        rotation: Quat,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            self.model_instance_index.contains_key(&child)
+                && self.model_instance_index.contains_key(&parent),
+            "Unknown model instance"
+        );
+        ensure!(
+            self.attachments
+                .attach(child, parent, translation, rotation),
+            "Model instance attached to itself"
+        );
+
+        Ok(())
+    }
+
+    pub fn debug_mode(&self) -> DebugMode {
+        self.debug_mode
+    }
+
+    /// Returns the model instance drawn at the pick position, if any.
+    ///
+    /// The result is read back from the GPU two frames after it is drawn, so it lags the pick
+    /// position by two frames; see [`Self::set_pick_position`].
+    pub fn hovered_instance(&self) -> Option<ModelInstance> {
+        self.hovered_instance
+    }
+
     pub fn insert_model_instance(
         &mut self,
         model: Model,
@@ -248,6 +602,28 @@ impl ModelBuffer {
         let model_instance = ModelInstance(self.model_instance_id);
         self.model_instance_id += 1;
 
+        // Each instance of a skinned model is drawn from a copy which is posed for it alone
+        let is_skinned = self.model_geometries[model.model_idx]
+            .iter()
+            .any(|geometry| geometry.flags.contains(MeshFlags::JOINTS_WEIGHTS));
+        let model = if is_skinned {
+            match self.posed_model(model) {
+                Ok(posed_model) => {
+                    let posed = posed_model.model;
+                    self.skinning.insert(model_instance, posed_model);
+
+                    posed
+                }
+                Err(err) => {
+                    warn!("Unable to pose model: {err}");
+
+                    model
+                }
+            }
+        } else {
+            model
+        };
+
         let index = self.model_instance_index.len();
         self.model_instance_index.insert(model_instance, index);
         self.model_instances.push(model_instance);
@@ -255,15 +631,246 @@ impl ModelBuffer {
         debug_assert_eq!(self.model_instance_index.len(), self.model_instances.len());
 
         self.technique.push_model_instance(ModelInstanceData {
+            highlight: None,
             materials,
+            layers: RenderLayers::WORLD,
+            metalness_scale: 1.0,
             model,
+            roughness_scale: 1.0,
             rotation,
+            tint: Vec4::ONE,
             translation,
+            visible: true,
         });
+        self.update_instance_bounds(model_instance, model, translation, rotation);
 
         model_instance
     }
 
+    /// Returns a copy of a skinned model which one model instance is posed with, reusing that of a
+    /// removed instance where there is one.
+    ///
+    /// The copy shares the indices of the model, and of any meshes of it which are not skinned, but
+    /// has vertices of its own for each skinned mesh, which start in the bind pose.
+    fn posed_model(&mut self, bind_model: Model) -> anyhow::Result<PosedModel> {
+        if let Some(posed_model) = self.skinning.take_spare(bind_model) {
+            return Ok(posed_model);
+        }
+
+        let geometries = self.model_geometries[bind_model.model_idx].clone();
+        let mesh_count = geometries.len();
+        let skinned_len = geometries
+            .iter()
+            .filter(|geometry| geometry.flags.contains(MeshFlags::JOINTS_WEIGHTS))
+            .map(|geometry| {
+                align_up_u64(
+                    geometry.vertex_count as vk::DeviceSize * geometry.flags.vertex_stride(),
+                    size_of::<f32>() as vk::DeviceSize,
+                )
+            })
+            .sum::<vk::DeviceSize>();
+
+        ensure!(
+            self.geometry_len + skinned_len <= self.geometry_buf.info.size,
+            "Geometry capacity exceeded"
+        );
+        ensure!(
+            ((self.mesh_count + mesh_count) as vk::DeviceSize) * Mesh::SIZE
+                <= self.mesh_buf.info.size,
+            "Mesh capacity exceeded"
+        );
+
+        let model = Model {
+            mesh_idx: self.mesh_count,
+            model_idx: self.model_geometries.len(),
+        };
+
+        let mut posed_geometries = Vec::with_capacity(mesh_count);
+        let mut meshes = Vec::with_capacity(mesh_count);
+        let mut skinned_meshes = vec![];
+
+        for geometry in geometries.iter().copied() {
+            let vertex_stride = geometry.flags.vertex_stride();
+            let mut posed_geometry = geometry;
+
+            if geometry.flags.contains(MeshFlags::JOINTS_WEIGHTS) {
+                posed_geometry.vertex_offset = self.geometry_len;
+                skinned_meshes.push(SkinnedMesh {
+                    src_offset: (geometry.vertex_offset / size_of::<f32>() as vk::DeviceSize) as _,
+                    dst_offset: (posed_geometry.vertex_offset / size_of::<f32>() as vk::DeviceSize)
+                        as _,
+                    vertex_count: geometry.vertex_count,
+                    vertex_stride: (vertex_stride / size_of::<f32>() as vk::DeviceSize) as _,
+                });
+
+                self.geometry_len = align_up_u64(
+                    self.geometry_len + geometry.vertex_count as vk::DeviceSize * vertex_stride,
+                    size_of::<f32>() as vk::DeviceSize,
+                );
+            }
+
+            let index_shift = if geometry.flags.contains(MeshFlags::INDEX_TYPE_UINT32) {
+                2
+            } else {
+                1
+            };
+
+            meshes.push(Mesh {
+                index_count: geometry.index_count,
+                index_offset: (geometry.index_offset >> index_shift) as _,
+                vertex_offset: (posed_geometry.vertex_offset / size_of::<f32>() as vk::DeviceSize)
+                    as _,
+                vertex_stride: (vertex_stride / size_of::<f32>() as vk::DeviceSize) as _,
+                material: geometry.material,
+                flags: geometry.flags,
+                _0: Default::default(),
+            });
+            posed_geometries.push(posed_geometry);
+        }
+
+        let mut render_graph = RenderGraph::new();
+        let geometry_buf = render_graph.bind_node(&self.geometry_buf);
+        let mesh_buf = render_graph.bind_node(&self.mesh_buf);
+        let staging_buf = render_graph.bind_node(lease_buffer(
+            &mut self.pool,
+            cast_slice(&meshes),
+            vk::BufferUsageFlags::TRANSFER_SRC,
+        )?);
+
+        render_graph.copy_buffer_region(
+            staging_buf,
+            mesh_buf,
+            vk::BufferCopy {
+                src_offset: 0,
+                dst_offset: Mesh::SIZE * self.mesh_count as vk::DeviceSize,
+                size: Mesh::SIZE * mesh_count as vk::DeviceSize,
+            },
+        );
+
+        // Vertices are in the bind pose before the technique finds anything from them
+        self.skinning.record_bind_pose(
+            &mut render_graph,
+            &mut self.pool,
+            geometry_buf,
+            &skinned_meshes,
+        )?;
+        self.technique
+            .load_posed_model(&mut render_graph, geometry_buf, &posed_geometries)?;
+
+        let cmd_buf = render_graph.resolve().submit(&mut self.pool, 0, 0)?;
+        self.pending_uploads.push(cmd_buf)?;
+
+        self.mesh_count += mesh_count;
+        self.model_bounds
+            .push(self.model_bounds[bind_model.model_idx]);
+        self.model_geometries
+            .push(posed_geometries.into_boxed_slice());
+
+        Ok(PosedModel::new(
+            bind_model,
+            model,
+            skinned_meshes.into_boxed_slice(),
+        ))
+    }
+
+    /// Returns the model instances whose bounding spheres the ray enters within `max_distance`,
+    /// nearest first, along with the distance to where the ray enters each.
+    ///
+    /// Bounds are coarse and hidden instances are included, so callers wanting exact hits should
+    /// test the results against their own geometry.
+    pub fn instances_hit_by_ray(&self, ray: Ray, max_distance: f32) -> Vec<(ModelInstance, f32)> {
+        self.instance_bounds.query_ray(ray, max_distance)
+    }
+
+    /// Returns the model instances whose bounding spheres overlap the given sphere, in no
+    /// particular order.
+    pub fn instances_in_sphere(&self, center: Vec3, radius: f32) -> Vec<ModelInstance> {
+        self.instance_bounds.query_sphere(center, radius)
+    }
+
+    /// Draws `camera` into the color texture of `material` in place of the image it was loaded
+    /// with, such as a security camera shown on in-world monitors; every model instance using the
+    /// material shows the camera.
+    ///
+    /// The camera sees the world layer and is drawn again each time `interval` seconds have
+    /// passed in [`Self::advance_time`], or every frame if `interval` is zero.
+    pub fn insert_render_target(
+        &mut self,
+        device: &Arc<Device>,
+        material: Material,
+        camera: Camera,
+        width: u32,
+        height: u32,
+        interval: f32,
+    ) -> Result<(), DriverError> {
+        let texture_idx = self.material_color_indices[material.material_index as usize];
+        let image = Arc::new(Image::create(
+            device,
+            ImageInfo::new_2d(
+                RenderTarget::FORMAT,
+                width,
+                height,
+                vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+            ),
+        )?);
+
+        // The material keeps its loaded texture if the image cannot be created
+        if let Some(texture_streaming) = &mut self.texture_streaming {
+            texture_streaming.remove(texture_idx);
+        }
+
+        self.textures[texture_idx] = image;
+        self.render_targets.push(RenderTarget {
+            camera,
+            due: true,
+            interval,
+            since_drawn: 0.0,
+            texture_idx,
+        });
+
+        Ok(())
+    }
+
+    /// Inserts a reflection probe which is captured the next time this buffer is recorded, so it
+    /// should be inserted once the surrounding models have been inserted.
+    pub fn insert_reflection_probe(&mut self, position: Vec3, radius: f32) {
+        if !self
+            .reflection_probes
+            .insert(ReflectionProbe { position, radius })
+        {
+            warn!("Ignoring reflection probe at {position}: capacity reached");
+        }
+    }
+
+    /// Returns `true` once the technique pipelines have been created on their worker thread; until
+    /// then recording clears the framebuffer, or does nothing for overlays.
+    pub fn is_ready(&self) -> bool {
+        self.technique.is_ready()
+    }
+
+    /// Returns the emissive color and intensity of a material as it was loaded, before any
+    /// [`Self::set_material_emissive`].
+    pub fn material_emissive(&self, material: Material) -> (Vec3, f32) {
+        let emissive = self.material_emissive[material.material_index as usize];
+
+        (emissive.color, emissive.intensity)
+    }
+
+    /// Returns how many of the loaded models the technique may draw, which for the ray trace
+    /// technique excludes those whose acceleration structures are still being built, along with
+    /// the number of loaded models.
+    pub fn model_progress(&self) -> (usize, usize) {
+        (
+            self.technique.loaded_model_count(),
+            self.model_geometries.len(),
+        )
+    }
+
+    /// Returns the layers of the model instances which are drawn.
+    pub fn layers(&self) -> RenderLayers {
+        self.layers
+    }
+
     pub fn load_material(
         &mut self,
         queue_index: usize,
@@ -271,16 +878,31 @@ impl ModelBuffer {
         normal: Arc<Image>,
         params: Arc<Image>,
         emissive: Option<Arc<Image>>,
+        mut flags: MaterialFlags,
+        animation: MaterialAnimation,
     ) -> Result<Material, DriverError> {
-        let mut flags = MaterialFlags::empty();
         flags.set(MaterialFlags::EMISSIVE, emissive.is_some());
 
         let material_data = MaterialData {
             color_index: self.textures.len() as _,
             flags,
+            sampler_index: animation.sampler.index(),
             _0: Default::default(),
+            scroll: animation.scroll,
+            fps: animation
+                .flipbook
+                .map(|flipbook| flipbook.fps)
+                .unwrap_or_default(),
+            frames: MaterialData::pack_frames(animation.flipbook),
+            _1: Default::default(),
+            emissive: MaterialEmissive {
+                color: Vec3::from_array(animation.emissive_color),
+                intensity: animation.emissive_intensity,
+            },
         };
 
+        self.material_color_indices.push(self.textures.len());
+        self.material_emissive.push(material_data.emissive);
         self.textures.push(color);
         self.textures.push(normal);
         self.textures.push(params);
@@ -314,12 +936,15 @@ impl ModelBuffer {
             },
         );
 
-        render_graph
-            .resolve()
-            .submit(&mut self.pool, 0, queue_index)?;
+        let cmd_buf = self
+            .transfer_queue
+            .submit(render_graph, &mut self.pool, queue_index)?;
+        self.pending_uploads.push(cmd_buf)?;
 
         let material = Material {
             material_index: self.material_count as _,
+            flags,
+            _0: Default::default(),
         };
         self.material_count += 1;
 
@@ -340,15 +965,17 @@ impl ModelBuffer {
 
         let model = Model {
             mesh_idx: self.mesh_count,
-            model_idx: self.model_count,
+            model_idx: self.model_geometries.len(),
         };
 
-        let mut render_graph = RenderGraph::new();
-        let geometry_buf = render_graph.bind_node(&self.geometry_buf);
-        let mesh_buf = render_graph.bind_node(&self.mesh_buf);
-
+        let mut bounds: Option<Aabb> = None;
         let mut geometries = Vec::with_capacity(mesh_parts.len());
 
+        // Every mesh part is staged into one buffer, which is copied from using two regions each
+        let mut staging: Vec<u8> = vec![];
+        let mut geometry_regions = Vec::with_capacity(mesh_parts.len());
+        let mut mesh_regions = Vec::with_capacity(mesh_parts.len());
+
         for mesh_part in mesh_parts.iter().copied() {
             let lods = mesh_part.lods();
 
@@ -375,6 +1002,15 @@ impl ModelBuffer {
             let vertex_stride = vertex_ty.stride() as u32;
             let vertex_count = vertex_len / vertex_stride;
 
+            // Positions are the first field of each vertex
+            if let Some(part_bounds) = Aabb::from_points(
+                vertex_buf
+                    .chunks_exact(vertex_stride as _)
+                    .map(|vertex| Vec3::from_array(pod_read_unaligned(&vertex[0..12]))),
+            ) {
+                bounds = Some(bounds.map_or(part_bounds, |bounds| bounds.union(part_bounds)));
+            }
+
             debug_assert!(vertex_len % size_of::<u32>() as u32 == 0);
 
             let index_is_u32 = vertex_count > u16::MAX as _;
@@ -406,58 +1042,49 @@ impl ModelBuffer {
                 _0: Default::default(),
             };
 
-            let temp_len = mesh_offset + Mesh::SIZE;
-            let temp_buf = {
-                let mut buf = self.pool.lease(BufferInfo::new_mappable(
-                    temp_len,
-                    vk::BufferUsageFlags::TRANSFER_SRC,
-                ))?;
+            // Each part starts aligned so that the index and vertex data keep their alignment
+            let staging_offset = staging.len() as vk::DeviceSize;
 
-                if index_is_u32 {
-                    Buffer::copy_from_slice(&mut buf, 0, cast_slice(&index_buf));
-                } else {
-                    let index_buf = index_buf
-                        .iter()
-                        .copied()
-                        .map(|idx| idx as u16)
-                        .collect::<Box<_>>();
-                    Buffer::copy_from_slice(&mut buf, 0, cast_slice(&index_buf));
-                };
-
-                Buffer::copy_from_slice(&mut buf, vertex_offset, vertex_buf);
-                Buffer::copy_from_slice(&mut buf, mesh_offset, bytes_of(&mesh));
-
-                render_graph.bind_node(buf)
+            if index_is_u32 {
+                staging.extend_from_slice(cast_slice(&index_buf));
+            } else {
+                let index_buf = index_buf
+                    .iter()
+                    .copied()
+                    .map(|idx| idx as u16)
+                    .collect::<Box<_>>();
+                staging.extend_from_slice(cast_slice(&index_buf));
             };
 
+            staging.resize((staging_offset + vertex_offset) as usize, 0);
+            staging.extend_from_slice(vertex_buf);
+            staging.extend_from_slice(bytes_of(&mesh));
+            staging.resize(
+                align_up_u64(staging.len() as _, size_of::<f32>() as vk::DeviceSize) as usize,
+                0,
+            );
+
             let dst_mesh_offset = Mesh::SIZE * self.mesh_count as vk::DeviceSize;
 
             debug_assert!(self.geometry_len + mesh_offset <= self.geometry_buf.info.size);
             debug_assert!(dst_mesh_offset + Mesh::SIZE <= self.mesh_buf.info.size);
 
-            render_graph.copy_buffer_region(
-                temp_buf,
-                geometry_buf,
-                vk::BufferCopy {
-                    src_offset: 0,
-                    dst_offset: self.geometry_len,
-                    size: mesh_offset,
-                },
-            );
-            render_graph.copy_buffer_region(
-                temp_buf,
-                mesh_buf,
-                vk::BufferCopy {
-                    src_offset: mesh_offset,
-                    dst_offset: dst_mesh_offset,
-                    size: Mesh::SIZE,
-                },
-            );
+            geometry_regions.push(vk::BufferCopy {
+                src_offset: staging_offset,
+                dst_offset: self.geometry_len,
+                size: mesh_offset,
+            });
+            mesh_regions.push(vk::BufferCopy {
+                src_offset: staging_offset + mesh_offset,
+                dst_offset: dst_mesh_offset,
+                size: Mesh::SIZE,
+            });
 
             geometries.push(Geometry {
                 flags,
                 index_count,
                 index_offset: self.geometry_len,
+                material,
                 vertex_count,
                 vertex_offset: self.geometry_len + vertex_offset,
             });
@@ -467,9 +1094,40 @@ impl ModelBuffer {
             self.mesh_count += 1;
         }
 
-        self.model_count += 1;
+        let mut render_graph = RenderGraph::new();
+        let geometry_buf = render_graph.bind_node(&self.geometry_buf);
+        let mesh_buf = render_graph.bind_node(&self.mesh_buf);
+
+        if !staging.is_empty() {
+            let staging_buf = render_graph.bind_node(lease_buffer(
+                &mut self.pool,
+                &staging,
+                vk::BufferUsageFlags::TRANSFER_SRC,
+            )?);
+
+            for region in geometry_regions {
+                render_graph.copy_buffer_region(staging_buf, geometry_buf, region);
+            }
+
+            for region in mesh_regions {
+                render_graph.copy_buffer_region(staging_buf, mesh_buf, region);
+            }
+        }
+
+        // Geometry is uploaded using the transfer queue; the technique then reads it using the
+        // graphics queue (for bounding spheres or acceleration structures) so we wait in between
+        self.transfer_queue
+            .submit(render_graph, &mut self.pool, queue_index)?
+            .wait_until_executed()?;
+
+        let mut render_graph = RenderGraph::new();
+        let geometry_buf = render_graph.bind_node(&self.geometry_buf);
+
+        self.model_bounds
+            .push(bounds.unwrap_or_else(|| Aabb::from_center_half_extents(Vec3::ZERO, Vec3::ZERO)));
         self.technique
             .load_model(&mut render_graph, geometry_buf, &geometries)?;
+        self.model_geometries.push(geometries.into_boxed_slice());
 
         render_graph
             .resolve()
@@ -484,6 +1142,7 @@ impl ModelBuffer {
         &mut self.technique[index]
     }
 
+    /// Records the models as seen by `camera` into the whole framebuffer.
     pub fn record(
         &mut self,
         render_graph: &mut RenderGraph,
@@ -491,23 +1150,280 @@ impl ModelBuffer {
         camera: &mut Camera,
     ) -> Result<(), DriverError> {
         let framebuffer = framebuffer.into();
+        let framebuffer_info = render_graph.node_info(framebuffer);
+
+        self.record_viewport(
+            render_graph,
+            framebuffer,
+            camera,
+            Viewport::full(framebuffer_info.width, framebuffer_info.height),
+        )
+    }
+
+    /// Records the models as seen by `camera` into one rectangle of the framebuffer, leaving the
+    /// rest of it untouched, so that several cameras may be recorded into one framebuffer. Each
+    /// viewport is culled against its own camera.
+    ///
+    /// The pick position is in framebuffer pixels and only picks within the viewport holding it.
+    pub fn record_viewport(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        framebuffer: impl Into<AnyImageNode>,
+        camera: &mut Camera,
+        viewport: Viewport,
+    ) -> Result<(), DriverError> {
+        let framebuffer = framebuffer.into();
+        let framebuffer_info = render_graph.node_info(framebuffer);
+        let is_full = viewport == Viewport::full(framebuffer_info.width, framebuffer_info.height);
+
+        self.pending_uploads.wait()?;
+        self.resolve_attachments();
+
+        if self.pick_position.is_none() {
+            self.pick_pending.clear();
+        }
+
+        // Picking is read back two frames late, by which time the frame which wrote it has been
+        // waited on, so that it never waits for the GPU or reads a buffer still being written;
+        // other viewports must not read the result of this one
+        if self.pick_pending.len() == Self::PICK_LATENCY && self.pick_pending[0].0 == viewport {
+            let (_, buf_idx) = self.pick_pending.pop_front().unwrap();
+            let buf = &self.pick_bufs[buf_idx];
+            let pick_id = u32::from_ne_bytes(
+                Buffer::mapped_slice(buf)[0..size_of::<u32>()]
+                    .try_into()
+                    .unwrap(),
+            );
+
+            // Zero means nothing was drawn at the pick position
+            self.hovered_instance = pick_id
+                .checked_sub(1)
+                .and_then(|idx| self.model_instances.get(idx as usize))
+                .copied();
+        } else if self
+            .pick_position
+            .map_or(true, |position| viewport.contains(position))
+        {
+            self.hovered_instance = None;
+        }
+
+        // Viewports smaller than the framebuffer are drawn into an image of their own size, which
+        // is copied into place once finished
+        let view_image: AnyImageNode = if is_full {
+            framebuffer
+        } else {
+            let view_image = render_graph.bind_node(self.pool.lease(ImageInfo::new_2d(
+                framebuffer_info.fmt,
+                viewport.extent.x,
+                viewport.extent.y,
+                framebuffer_info.usage
+                    | vk::ImageUsageFlags::TRANSFER_DST
+                    | vk::ImageUsageFlags::TRANSFER_SRC,
+            ))?);
+
+            // Overlays draw over what the framebuffer already holds
+            if self.overlay {
+                render_graph.copy_image_region(
+                    framebuffer,
+                    view_image,
+                    &viewport_copy(viewport, true),
+                );
+            }
+
+            view_image.into()
+        };
+
+        self.record_view(render_graph, view_image, camera, viewport)?;
+
+        if !is_full {
+            render_graph.copy_image_region(
+                view_image,
+                framebuffer,
+                &viewport_copy(viewport, false),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn record_view(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        framebuffer: AnyImageNode,
+        camera: &mut Camera,
+        viewport: Viewport,
+    ) -> Result<(), DriverError> {
+        if !self.technique.is_ready() {
+            if !self.overlay {
+                render_graph.clear_color_image(framebuffer);
+            }
+
+            return Ok(());
+        }
 
         let geometry_buf = render_graph.bind_node(&self.geometry_buf);
         let material_buf = render_graph.bind_node(&self.material_buf);
         let mesh_buf = render_graph.bind_node(&self.mesh_buf);
 
+        // Skinned vertices are written before anything this frame reads them
+        for model_idx in self
+            .skinning
+            .record(render_graph, &mut self.pool, geometry_buf)?
+        {
+            self.technique
+                .update_posed_model(render_graph, geometry_buf, model_idx)?;
+        }
+
+        if !self.material_emissive_changes.is_empty() {
+            let data = self
+                .material_emissive_changes
+                .values()
+                .copied()
+                .collect::<Box<_>>();
+            let staging_buf = render_graph.bind_node(lease_buffer(
+                &mut self.pool,
+                cast_slice(&data),
+                vk::BufferUsageFlags::TRANSFER_SRC,
+            )?);
+            let regions = self
+                .material_emissive_changes
+                .keys()
+                .enumerate()
+                .map(|(idx, &material_index)| vk::BufferCopy {
+                    src_offset: idx as vk::DeviceSize * MaterialEmissive::SIZE,
+                    dst_offset: material_index as vk::DeviceSize * MaterialData::SIZE
+                        + MaterialData::EMISSIVE_OFFSET,
+                    size: MaterialEmissive::SIZE,
+                })
+                .collect::<Vec<_>>();
+
+            render_graph.copy_buffer_regions(staging_buf, material_buf, regions);
+            self.material_emissive_changes.clear();
+        }
+
+        let reflection_probes = self.reflection_probes.bind(render_graph, &mut self.pool)?;
+        let framebuffer_info = render_graph.node_info(framebuffer);
+
+        self.request_texture_mips(camera, framebuffer_info.height);
+
+        let pick = self
+            .pick_position
+            .filter(|&position| viewport.contains(position))
+            .map(|position| {
+                // A result which was never read back, such as one from another viewport, is
+                // dropped so that its buffer may be written again
+                if self.pick_pending.len() == Self::PICK_LATENCY {
+                    self.pick_pending.pop_front();
+                }
+
+                self.pick_frame += 1;
+                let buf_idx = self.pick_frame % self.pick_bufs.len();
+                self.pick_pending.push_back((viewport, buf_idx));
+
+                Pick {
+                    buf: render_graph.bind_node(&self.pick_bufs[buf_idx]),
+                    position: position - viewport.offset,
+                }
+            });
+
+        // Textures are bound once and shared by every pass, which index them as one array
+        let textures = self
+            .textures
+            .iter()
+            .map(|texture| render_graph.bind_node(texture))
+            .collect::<Box<_>>();
+
+        // New probes are captured by drawing the scene once for each of their cube faces
+        for (layer, mut face_camera) in self.reflection_probes.take_uncaptured_faces() {
+            let face_image =
+                render_graph.bind_node(self.pool.lease(ReflectionProbes::face_image_info())?);
+
+            self.technique.record(
+                render_graph,
+                face_image.into(),
+                &mut face_camera,
+                DebugMode::Off,
+                None,
+                self.depth_prepass,
+                RenderLayers::WORLD,
+                geometry_buf,
+                material_buf,
+                mesh_buf,
+                reflection_probes,
+                self.sky,
+                &textures,
+                self.time,
+                None,
+            )?;
+
+            ReflectionProbes::copy_face(render_graph, face_image, reflection_probes, layer);
+        }
+
+        // Render targets are drawn before the view so that it shows them as of this frame; each is
+        // drawn into an image of its own and then copied, because it may see its own texture
+        for render_target in &mut self.render_targets {
+            if !render_target.due {
+                continue;
+            }
+
+            let texture = textures[render_target.texture_idx];
+            let texture_info = render_graph.node_info(texture);
+            let target_image = render_graph.bind_node(self.pool.lease(ImageInfo::new_2d(
+                RenderTarget::FORMAT,
+                texture_info.width,
+                texture_info.height,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | vk::ImageUsageFlags::STORAGE
+                    | vk::ImageUsageFlags::TRANSFER_SRC,
+            ))?);
+
+            self.technique.record(
+                render_graph,
+                target_image.into(),
+                &mut render_target.camera,
+                DebugMode::Off,
+                None,
+                self.depth_prepass,
+                RenderLayers::WORLD,
+                geometry_buf,
+                material_buf,
+                mesh_buf,
+                reflection_probes,
+                self.sky,
+                &textures,
+                self.time,
+                None,
+            )?;
+
+            render_graph.copy_image(target_image, texture);
+            render_target.due = false;
+            render_target.since_drawn = 0.0;
+        }
+
         self.technique.record(
             render_graph,
             framebuffer,
             camera,
+            self.debug_mode,
+            self.depth_of_field,
+            self.depth_prepass,
+            self.layers,
             geometry_buf,
             material_buf,
             mesh_buf,
-            &self.textures,
+            reflection_probes,
+            self.sky,
+            &textures,
+            self.time,
+            pick,
         )
     }
 
     pub fn remove_model_instance(&mut self, model_instance: ModelInstance) {
+        self.attachments.remove(model_instance);
+        self.instance_bounds.remove(model_instance);
+        self.skinning.remove(model_instance);
+
         let index = self.model_instance_index.remove(&model_instance).unwrap();
         self.technique.swap_remove_model_instance(index);
         self.model_instances.swap_remove(index);
@@ -520,6 +1436,149 @@ impl ModelBuffer {
         debug_assert_eq!(self.model_instance_index.len(), self.model_instances.len());
     }
 
+    /// Moves each attached model instance to where its parent now is, before the technique uploads
+    /// the model instances.
+    fn resolve_attachments(&mut self) {
+        if self.attachments.is_empty() {
+            return;
+        }
+
+        let resolved = self.attachments.resolve(|model_instance| {
+            let model_instance_data = &self.technique[self.model_instance_index[&model_instance]];
+
+            (
+                model_instance_data.translation,
+                model_instance_data.rotation,
+            )
+        });
+
+        for (model_instance, translation, rotation) in resolved {
+            self.set_model_instance_transform(model_instance, translation, rotation);
+        }
+    }
+
+    /// Requests the mip levels which the visible model instances near `camera` need of any
+    /// streamed textures, from the size of each instance on a framebuffer `height` pixels tall.
+    fn request_texture_mips(&mut self, camera: &Camera, height: u32) {
+        let Some(texture_streaming) = &mut self.texture_streaming else {
+            return;
+        };
+
+        // Pixels covered by one meter seen from one meter away
+        let pixels_per_meter = height as f32 * 0.5 / (camera.fov_y.to_radians() * 0.5).tan();
+
+        for model_instance in self
+            .instance_bounds
+            .query_sphere(camera.position, TextureStreaming::RADIUS)
+        {
+            let model_instance_data = &self.technique[self.model_instance_index[&model_instance]];
+
+            if !model_instance_data.visible || !model_instance_data.layers.intersects(self.layers) {
+                continue;
+            }
+
+            let (center, radius) = self.instance_bounds.sphere(model_instance).unwrap();
+            let distance = (camera.position.distance(center) - radius).max(Camera::Z_NEAR);
+            let pixels = 2.0 * radius * pixels_per_meter / distance;
+
+            for material in model_instance_data.materials {
+                let color_idx = self.material_color_indices[material.material_index as usize];
+
+                // Color, normal, params and then emissive, if any
+                for texture_idx in color_idx..color_idx + 3 + material.is_emissive() as usize {
+                    texture_streaming.request(texture_idx, pixels);
+                }
+            }
+        }
+    }
+
+    /// Sets how models are drawn; see [`DebugMode`].
+    pub fn set_debug_mode(&mut self, debug_mode: DebugMode) {
+        self.debug_mode = debug_mode;
+    }
+
+    /// Blurs models away from the focus distance of the camera, or draws everything in focus when
+    /// `None`, which is the default.
+    ///
+    /// Only the raster technique draws depth of field; other views of the models, such as
+    /// reflection probes and render targets, are always in focus.
+    pub fn set_depth_of_field(&mut self, depth_of_field: Option<DepthOfField>) {
+        self.depth_of_field = depth_of_field;
+    }
+
+    /// Draws the depth of opaque models before shading them, so that each pixel is shaded once,
+    /// which is off by default.
+    ///
+    /// Only the raster technique has a depth pre-pass, and it is skipped by the wireframe and
+    /// overdraw debug modes.
+    pub fn set_depth_prepass(&mut self, depth_prepass: bool) {
+        self.depth_prepass = depth_prepass;
+    }
+
+    /// Sets the layers of the model instances which are drawn, which by default are
+    /// [`RenderLayers::WORLD`] and [`RenderLayers::VIEW_MODEL`].
+    pub fn set_layers(&mut self, layers: RenderLayers) {
+        self.layers = layers;
+    }
+
+    /// Sets the framebuffer pixel, such as the crosshair or mouse cursor, whose model instance is
+    /// found while recording; `None` disables picking.
+    pub fn set_pick_position(&mut self, position: Option<UVec2>) {
+        self.pick_position = position;
+    }
+
+    /// Sets the factors which multiply the emissive texture of a material from the next frame on,
+    /// such as to flicker a light; materials without an emissive texture are unaffected.
+    pub fn set_material_emissive(&mut self, material: Material, color: Vec3, intensity: f32) {
+        self.material_emissive_changes.insert(
+            material.material_index,
+            MaterialEmissive { color, intensity },
+        );
+    }
+
+    /// Returns the sky drawn behind models, whose time of day moves with [`Self::advance_time`].
+    pub fn sky(&self) -> Sky {
+        self.sky
+    }
+
+    /// Sets the sky drawn behind models and used as their environment.
+    pub fn set_sky(&mut self, sky: Sky) {
+        self.sky = sky;
+    }
+
+    /// Limits the seconds of streaming work started each frame, or removes the limit so that it
+    /// finishes as soon as possible, such as while loading.
+    pub fn set_streaming_budget(&mut self, secs: Option<f32>) {
+        self.frame_budget.set_limit(secs);
+    }
+
+    /// Returns the estimated seconds of streaming work started this frame and the limit, if any.
+    pub fn streaming_spent(&self) -> (f32, Option<f32>) {
+        self.frame_budget.spent()
+    }
+
+    /// Outlines a model instance in the given color, such as the object the player may interact
+    /// with, or removes the outline when `None`.
+    ///
+    /// The ray trace technique has no outline pass, so highlighted model instances glow with the
+    /// color instead.
+    pub fn set_model_instance_highlight(
+        &mut self,
+        model_instance: ModelInstance,
+        color: Option<Vec4>,
+    ) {
+        self.model_instance_mut(model_instance).highlight = color;
+    }
+
+    /// Sets the layers a model instance belongs to, which by default is [`RenderLayers::WORLD`].
+    pub fn set_model_instance_layers(
+        &mut self,
+        model_instance: ModelInstance,
+        layers: RenderLayers,
+    ) {
+        self.model_instance_mut(model_instance).layers = layers;
+    }
+
     pub fn set_model_instance_material(
         &mut self,
         model_instance: ModelInstance,
@@ -530,6 +1589,20 @@ impl ModelBuffer {
         model_instance_data.materials[material_index] = material;
     }
 
+    /// Multiplies the roughness and metalness of every material of a model instance.
+    ///
+    /// The ray trace technique does not yet use roughness.
+    pub fn set_model_instance_material_params(
+        &mut self,
+        model_instance: ModelInstance,
+        roughness_scale: f32,
+        metalness_scale: f32,
+    ) {
+        let model_instance_data = self.model_instance_mut(model_instance);
+        model_instance_data.roughness_scale = roughness_scale;
+        model_instance_data.metalness_scale = metalness_scale;
+    }
+
     pub fn set_model_instance_materials(
         &mut self,
         model_instance: ModelInstance,
@@ -539,6 +1612,12 @@ impl ModelBuffer {
         model_instance_data.materials = material_array(materials);
     }
 
+    /// Multiplies the color of every material of a model instance, such as for team colors or
+    /// damage states, without loading additional materials.
+    pub fn set_model_instance_tint(&mut self, model_instance: ModelInstance, color: Vec4) {
+        self.model_instance_mut(model_instance).tint = color;
+    }
+
     pub fn set_model_instance_transform(
         &mut self,
         model_instance: ModelInstance,
@@ -548,16 +1627,143 @@ impl ModelBuffer {
         let model_instance_data = self.model_instance_mut(model_instance);
         model_instance_data.rotation = rotation;
         model_instance_data.translation = translation;
+
+        let model = model_instance_data.model;
+        self.update_instance_bounds(model_instance, model, translation, rotation);
     }
 
-    pub fn set_model_instance_pose(
+    /// Hides or shows a model instance, which is cheaper than removing and inserting it again.
+    pub fn set_model_instance_visible(&mut self, model_instance: ModelInstance, visible: bool) {
+        self.model_instance_mut(model_instance).visible = visible;
+    }
+
+    fn update_instance_bounds(
         &mut self,
         model_instance: ModelInstance,
-        pose: &[(&'static str, Quat)],
+        model: Model,
+        translation: Vec3,
+        rotation: Quat,
     ) {
-        let model_instance_data = self.model_instance_mut(model_instance);
+        let bounds = self.model_bounds[model.model_idx];
+        let center = translation + rotation * (bounds.min() + bounds.max()) * 0.5;
+        let radius = (bounds.max() - bounds.min()).length() * 0.5;
+
+        self.instance_bounds.insert(model_instance, center, radius);
+    }
 
-        todo!();
+    fn supports_technique(
+        device: &Device,
+        info: ModelBufferInfo,
+        technique: ModelBufferTechnique,
+    ) -> bool {
+        match technique {
+            ModelBufferTechnique::Raster => true,
+            ModelBufferTechnique::RayTrace => {
+                !info.overlay && DeviceCapabilities::new(device).ray_tracing
+            }
+        }
+    }
+
+    /// Replaces the technique used to record models, such as when graphics settings change,
+    /// without reloading anything.
+    ///
+    /// The new technique builds its state (acceleration structures or culling buffers) from the
+    /// models and model instances already loaded, which blocks until its pipelines are ready.
+    /// Reflection probes are captured again using the new technique. If no technique is given the
+    /// default one is used, as when the model buffer was created without one.
+    pub fn switch_technique(
+        &mut self,
+        device: &Arc<Device>,
+        technique: Option<ModelBufferTechnique>,
+    ) -> anyhow::Result<()> {
+        let technique = technique.unwrap_or_else(|| Self::default_technique(device, self.info));
+
+        if technique == self.technique_kind {
+            return Ok(());
+        }
+
+        ensure!(
+            Self::supports_technique(device, self.info, technique),
+            "Unsupported technique {technique:?}"
+        );
+
+        info!("Switching to {technique:?} technique");
+
+        self.pending_uploads.wait()?;
+
+        let mut new_technique =
+            Self::create_technique(device, self.info, technique, &self.geometry_buf)?;
+        let mut render_graph = RenderGraph::new();
+        let geometry_buf = render_graph.bind_node(&self.geometry_buf);
+
+        for (model_idx, geometries) in self.model_geometries.iter().enumerate() {
+            if self.skinning.is_posed(model_idx) {
+                new_technique
+                    .load_posed_model(&mut render_graph, geometry_buf, geometries)
+                    .context("Loading posed model")?;
+            } else {
+                new_technique
+                    .load_model(&mut render_graph, geometry_buf, geometries)
+                    .context("Loading model")?;
+            }
+        }
+
+        for index in 0..self.model_instances.len() {
+            new_technique.push_model_instance(self.technique[index]);
+        }
+
+        render_graph.resolve().submit(&mut self.pool, 0, 0)?;
+
+        self.technique = new_technique;
+        self.technique_kind = technique;
+        self.reflection_probes.recapture();
+        self.skinning.mark_all_dirty();
+
+        Ok(())
+    }
+
+    /// Streams the detailed mips of the given block-compressed textures, each of which was loaded
+    /// with only the mips from [`base_mip_level`], within the texture streaming budget.
+    pub fn stream_textures(
+        &mut self,
+        device: &Arc<Device>,
+        bitmaps: Arc<CompressedBitmaps>,
+        images: impl IntoIterator<Item = (BitmapId, Arc<Image>)>,
+    ) {
+        let mut texture_streaming =
+            TextureStreaming::new(device, bitmaps, self.info.texture_streaming_budget as _);
+
+        for (bitmap_id, image) in images {
+            // Materials which share a bitmap share its image, so each streamed image replaces all
+            // of them
+            let texture_indices = self
+                .textures
+                .iter()
+                .enumerate()
+                .filter(|(_, texture)| Arc::ptr_eq(texture, &image))
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>();
+
+            if !texture_indices.is_empty() {
+                texture_streaming.insert(bitmap_id, texture_indices);
+            }
+        }
+
+        self.texture_streaming = Some(texture_streaming);
+    }
+
+    /// Poses a model instance of a skinned model from the next frame on, using a matrix for each
+    /// joint of its skin which moves the joint from the bind pose, such as
+    /// [`Ragdoll::bone_matrices`](crate::game::physics::Ragdoll::bone_matrices); an empty slice
+    /// returns it to the bind pose. Returns `false`, and does nothing, if the model is not skinned.
+    ///
+    /// Only this instance is posed: other instances of the same model keep their own poses.
+    pub fn set_model_instance_pose(
+        &mut self,
+        model_instance: ModelInstance,
+        joints: &[Mat4],
+    ) -> bool {
+        self.skinning.set_pose(model_instance, joints)
     }
 }
 
@@ -573,6 +1779,12 @@ impl ModelBuffer {
     pattern = "owned"
 )]
 pub struct ModelBufferInfo {
+    /// Quality of screen-space ambient occlusion.
+    ///
+    /// Only used by the raster technique and ignored for overlays.
+    #[builder(default)]
+    pub ambient_occlusion: AmbientOcclusion,
+
     /// Fixed size capacity of the model geometry (indices and vertices) which may be loaded.
     #[builder(default = "10_000_000")]
     pub geometry_capacity: vk::DeviceSize,
@@ -589,9 +1801,28 @@ pub struct ModelBufferInfo {
     #[builder(default = "5_000")]
     pub model_capacity: vk::DeviceSize,
 
+    /// Draws on top of the existing framebuffer contents using a separate depth buffer and a
+    /// closer near plane, such as for first-person view models.
+    ///
+    /// Only supported by the raster technique.
+    #[builder(default)]
+    pub overlay: bool,
+
+    /// Fixed size capacity of reflection probes which may be inserted.
+    #[builder(default = "16")]
+    pub reflection_probe_capacity: u32,
+
     /// Technique to use when recording models.
     #[builder(default, setter(strip_option))]
     pub technique: Option<ModelBufferTechnique>,
+
+    /// Filtering used when sampling material textures.
+    #[builder(default)]
+    pub texture_filtering: TextureFiltering,
+
+    /// Bytes of streamed material textures which may be resident at once.
+    #[builder(default = "512 << 20")]
+    pub texture_streaming_budget: vk::DeviceSize,
 }
 
 impl ModelBufferInfo {
@@ -631,21 +1862,157 @@ impl From<UninitializedFieldError> for ModelBufferInfoBuilderError {
     }
 }
 
+/// Quality of the screen-space ambient occlusion used by the raster technique.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum AmbientOcclusion {
+    Off,
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum ModelBufferTechnique {
     Raster,
     RayTrace,
 }
 
+/// Filtering used when sampling mip-mapped material textures.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum TextureFiltering {
+    Bilinear,
+    Trilinear,
+    Anisotropic2x,
+    Anisotropic4x,
+    #[default]
+    Anisotropic8x,
+    Anisotropic16x,
+}
+
+impl TextureFiltering {
+    fn max_anisotropy(self) -> Option<f32> {
+        match self {
+            Self::Bilinear | Self::Trilinear => None,
+            Self::Anisotropic2x => Some(2.0),
+            Self::Anisotropic4x => Some(4.0),
+            Self::Anisotropic8x => Some(8.0),
+            Self::Anisotropic16x => Some(16.0),
+        }
+    }
+
+    /// Anisotropy of materials which ask for less of it than the setting.
+    const LOW_ANISOTROPY: f32 = 2.0;
+
+    /// Returns sampler information for material textures sampled with the given state, limited to
+    /// what the device supports.
+    fn sampler_info(self, device: &Device, sampler: MaterialSampler) -> SamplerInfo {
+        let max_anisotropy = match (sampler.filter, sampler.anisotropy) {
+            (SamplerFilter::Point, _) | (_, SamplerAnisotropy::Off) => None,
+            (_, SamplerAnisotropy::Low) => self
+                .max_anisotropy()
+                .map(|max_anisotropy| max_anisotropy.min(Self::LOW_ANISOTROPY)),
+            (_, SamplerAnisotropy::Full) => self.max_anisotropy(),
+        }
+        .filter(|_| device.physical_device.features_v1_0.sampler_anisotropy)
+        .map(|max_anisotropy| {
+            max_anisotropy.min(
+                device
+                    .physical_device
+                    .properties_v1_0
+                    .limits
+                    .max_sampler_anisotropy,
+            )
+        });
+        let (filter, mipmap_mode) = match sampler.filter {
+            SamplerFilter::Linear if self == Self::Bilinear => {
+                (vk::Filter::LINEAR, vk::SamplerMipmapMode::NEAREST)
+            }
+            SamplerFilter::Linear => (vk::Filter::LINEAR, vk::SamplerMipmapMode::LINEAR),
+            SamplerFilter::Point => (vk::Filter::NEAREST, vk::SamplerMipmapMode::NEAREST),
+        };
+        let address_mode = match sampler.address {
+            SamplerAddress::Repeat => vk::SamplerAddressMode::REPEAT,
+            SamplerAddress::Clamp => vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        };
+
+        SamplerInfo::new()
+            .mag_filter(filter)
+            .min_filter(filter)
+            .mipmap_mode(mipmap_mode)
+            .address_mode_u(address_mode)
+            .address_mode_v(address_mode)
+            .address_mode_w(address_mode)
+            .anisotropy_enable(max_anisotropy.is_some())
+            .max_anisotropy(max_anisotropy.unwrap_or(1.0))
+            .max_lod(vk::LOD_CLAMP_NONE)
+            .build()
+    }
+
+    /// Returns the binding and sampler information of each material sampler.
+    fn sampler_infos(self, device: &Device) -> MaterialSamplerInfos {
+        MaterialSampler::ALL.map(|sampler| {
+            (
+                MATERIAL_SAMPLER_BINDING + sampler.index() as u32,
+                self.sampler_info(device, sampler),
+            )
+        })
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct ModelInstance(usize);
 
+/// A camera drawn into the color texture of a material.
+#[derive(Debug)]
+struct RenderTarget {
+    camera: Camera,
+
+    /// Set once `interval` seconds have passed since the camera was drawn.
+    due: bool,
+
+    interval: f32,
+    since_drawn: f32,
+
+    /// Index into the textures of the model buffer.
+    texture_idx: usize,
+}
+
+impl RenderTarget {
+    const FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+}
+
+/// Where a technique writes the pick result: one more than the index of the model instance
+/// drawn at `position`, or zero if there is none, as a single `u32`.
+#[derive(Clone, Copy, Debug)]
+struct Pick {
+    buf: BufferNode,
+    position: UVec2,
+}
+
 #[derive(Clone, Copy, Debug)]
 struct ModelInstanceData {
+    highlight: Option<Vec4>,
+    layers: RenderLayers,
     materials: [Material; MAX_MATERIALS_PER_MODEL],
+    metalness_scale: f32,
     model: Model,
+    roughness_scale: f32,
     rotation: Quat,
+    tint: Vec4,
     translation: Vec3,
+    visible: bool,
+}
+
+impl ModelInstanceData {
+    /// Returns the layers shaders test against those being drawn, which is none when hidden.
+    fn layer_mask(&self) -> u32 {
+        if self.visible {
+            self.layers.bits() as _
+        } else {
+            0
+        }
+    }
 }
 
 trait Technique: Debug + Send + IndexMut<usize> + Index<usize, Output = ModelInstanceData> {
@@ -656,6 +2023,21 @@ trait Technique: Debug + Send + IndexMut<usize> + Index<usize, Output = ModelIns
         geometries: &[Geometry],
     ) -> Result<(), DriverError>;
 
+    /// Loads a copy of a skinned model whose vertices are rewritten by skinning; see
+    /// [`Self::update_posed_model`].
+    fn load_posed_model(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        geometry_buf: BufferNode,
+        geometries: &[Geometry],
+    ) -> Result<(), DriverError>;
+
+    /// Returns `true` once the pipelines, which are created in the background, may be recorded.
+    fn is_ready(&self) -> bool;
+
+    /// Returns the number of loaded models which may be drawn.
+    fn loaded_model_count(&self) -> usize;
+
     fn push_model_instance(&mut self, model_instance: ModelInstanceData);
 
     fn record(
@@ -663,11 +2045,32 @@ trait Technique: Debug + Send + IndexMut<usize> + Index<usize, Output = ModelIns
         render_graph: &mut RenderGraph,
         framebuffer: AnyImageNode,
         camera: &mut Camera,
+        debug_mode: DebugMode,
+        depth_of_field: Option<DepthOfField>,
+        depth_prepass: bool,
+        layers: RenderLayers,
         geometry_buf: BufferNode,
         material_buf: BufferNode,
         mesh_buf: BufferNode,
-        textures: &[Arc<Image>],
+        reflection_probes: ReflectionProbeNodes,
+        sky: Sky,
+        textures: &[ImageNode],
+        time: f32,
+        pick: Option<Pick>,
     ) -> Result<(), DriverError>;
 
+    /// Starts as much of the work of loading models, which is spread over frames, as the budget
+    /// allows; called once a frame.
+    fn stream(&mut self, budget: &mut FrameBudget);
+
     fn swap_remove_model_instance(&mut self, idx: usize);
+
+    /// Updates whatever was built from the vertices of a posed model, which skinning has just
+    /// written.
+    fn update_posed_model(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        geometry_buf: BufferNode,
+        model_idx: usize,
+    ) -> Result<(), DriverError>;
 }
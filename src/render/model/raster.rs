@@ -1,31 +1,49 @@
 use {
     super::{
         super::{
-            bounding_sphere::BoundingSpherePipeline, camera::Camera,
-            excl_sum::ExclusiveSumPipeline, lease_storage_buffer, lease_uniform_buffer,
+            bounding_sphere::BoundingSpherePipeline,
+            camera::{frustum_planes, Camera},
+            capabilities::DeviceCapabilities,
+            debug::{capture::pass_name, DebugMode},
+            depth_of_field::{DepthOfField, DepthOfFieldPipeline},
+            excl_sum::ExclusiveSumPipeline,
+            frame_budget::FrameBudget,
+            lease_buffer, lease_storage_buffer, lease_uniform_buffer,
+            outline::{Highlight, OutlinePipeline},
+            pending_pipelines::PendingPipelines,
+            size_class_pool::SizeClassPool,
+            sky::{Sky, SkyPipeline},
+            ssao::SsaoPipeline,
         },
-        Geometry, Mesh, MeshFlags, Model, ModelBufferInfo, ModelInstanceData, Technique,
-        MAX_MATERIALS_PER_MODEL,
+        AmbientOcclusion, Geometry, MaterialFlags, MaterialSamplerInfos, Mesh, MeshFlags, Model,
+        ModelBufferInfo, ModelInstanceData, Pick, ReflectionProbeNodes, ReflectionProbes,
+        RenderLayers, Technique, TextureFiltering, MAX_MATERIALS_PER_MODEL,
     },
     crate::res,
     anyhow::Context,
     bytemuck::{bytes_of, cast_slice, Pod, Zeroable},
-    glam::{Mat4, Quat, Vec3},
+    glam::{vec4, Mat4, Quat, Vec3, Vec4},
     screen_13::prelude::*,
     std::{
-        cell::RefCell,
+        collections::HashMap,
         iter::repeat,
-        mem::size_of,
-        ops::{Index, IndexMut},
+        mem::{replace, size_of},
+        ops::{Index, IndexMut, Range},
         sync::Arc,
     },
 };
 
 #[cfg(not(feature = "hot-shaders"))]
-use super::super::{open_res_pak, read_blob};
+use {
+    super::super::{open_res_pak, read_blob},
+    pak::PakBuf,
+};
 
 #[cfg(feature = "hot-shaders")]
-use {super::super::res_shader_dir, screen_13_hot::prelude::*};
+use {
+    super::super::{hot_shader::watch, res_shader_dir},
+    screen_13_hot::prelude::*,
+};
 
 #[derive(Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
@@ -38,6 +56,22 @@ impl BoundingSphere {
     const SIZE: vk::DeviceSize = size_of::<Self>() as vk::DeviceSize;
 }
 
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct CameraUniform {
+    projection_view: Mat4,
+    position: Vec3,
+    reflection_probe_count: u32,
+    sun_direction: Vec3,
+    sky_turbidity: f32,
+    time: f32,
+    fog_density: f32,
+    fog_height: f32,
+    fog_height_falloff: f32,
+    fog_color: Vec3,
+    _0: f32,
+}
+
 #[derive(Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
 struct MeshInstanceRef {
@@ -56,44 +90,148 @@ struct ModelInstanceRef {
     rotation: Quat,
     translation: Vec3,
     model_idx: u32,
+    tint: Vec4,
+    roughness_scale: f32,
+    metalness_scale: f32,
+    layer_mask: u32,
+    _0: [u8; 4],
 }
 
 impl ModelInstanceRef {
     const SIZE: vk::DeviceSize = size_of::<Self>() as vk::DeviceSize;
 }
 
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct MeshTaskPushConstants {
+    geometry_address: vk::DeviceAddress,
+    draw_instance_offset: u32,
+    _0: u32,
+}
+
+impl MeshTaskPushConstants {
+    fn new(
+        geometry_address: vk::DeviceAddress,
+        variant: MaterialVariant,
+        variant_instance_stride: u32,
+    ) -> Self {
+        Self {
+            geometry_address,
+            draw_instance_offset: variant.0 * variant_instance_stride,
+            _0: 0,
+        }
+    }
+}
+
+/// Indirect dispatch of the task shader workgroups of one material variant.
+///
+/// Must match `TaskCommand` in `mesh_task.glsl`.
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct TaskCommand {
+    group_count_x: u32,
+    group_count_y: u32,
+    group_count_z: u32,
+}
+
+impl TaskCommand {
+    const SIZE: vk::DeviceSize = size_of::<Self>() as vk::DeviceSize;
+}
+
+/// Materials which need their own mesh draw pipeline, indexed by the bits of [`Self::flags`].
+///
+/// Must match `material_variant` in `material.glsl`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct MaterialVariant(u32);
+
+impl MaterialVariant {
+    const ALL: [Self; 4] = [Self(0), Self(1), Self(2), Self(3)];
+
+    /// Variants drawn by the depth pre-pass; masked materials need their textures to find which
+    /// pixels they cover, so they are only drawn by the shading pass.
+    const UNMASKED: [Self; 2] = [Self(0), Self(2)];
+
+    fn flags(self) -> MaterialFlags {
+        let mut flags = MaterialFlags::empty();
+        flags.set(MaterialFlags::MASKED, self.0 & 0b01 != 0);
+        flags.set(MaterialFlags::TWO_SIDED, self.0 & 0b10 != 0);
+
+        flags
+    }
+
+    fn index(self) -> usize {
+        self.0 as _
+    }
+}
+
 #[cfg(not(feature = "hot-shaders"))]
 #[derive(Debug)]
 struct Pipelines {
     bounding_sphere: BoundingSpherePipeline,
+    depth_of_field: DepthOfFieldPipeline,
     excl_sum: ExclusiveSumPipeline,
     mesh_cmd: Arc<ComputePipeline>,
     mesh_cull: Arc<ComputePipeline>,
-    mesh_draw: Arc<GraphicPipeline>,
-    subgroup_size: u32,
+
+    /// One depth-only pipeline for each material variant of [`MaterialVariant::UNMASKED`].
+    mesh_depth: Vec<Arc<GraphicPipeline>>,
+
+    /// One pipeline for each material variant and debug mode, in the order of
+    /// [`MaterialVariant::ALL`] and then [`DebugMode::ALL`].
+    mesh_draw: Vec<Arc<GraphicPipeline>>,
+
+    mesh_pick: Arc<GraphicPipeline>,
+
+    /// Replaces the mesh command, cull, draw and pick pipelines on devices which support mesh
+    /// shaders.
+    mesh_task: Option<MeshTaskPipelines>,
+
+    outline: OutlinePipeline,
+    sky: SkyPipeline,
+    ssao: Option<SsaoPipeline>,
+    workgroup_size: u32,
 }
 
 #[cfg(feature = "hot-shaders")]
 #[derive(Debug)]
 struct Pipelines {
     bounding_sphere: BoundingSpherePipeline,
+    depth_of_field: DepthOfFieldPipeline,
     excl_sum: ExclusiveSumPipeline,
     mesh_cmd: HotComputePipeline,
     mesh_cull: HotComputePipeline,
-    mesh_draw: HotGraphicPipeline,
-    subgroup_size: u32,
+    mesh_depth: Vec<HotGraphicPipeline>,
+    mesh_draw: Vec<HotGraphicPipeline>,
+    mesh_pick: HotGraphicPipeline,
+    mesh_task: Option<MeshTaskPipelines>,
+    outline: OutlinePipeline,
+    sky: SkyPipeline,
+    ssao: Option<SsaoPipeline>,
+    workgroup_size: u32,
 }
 
 impl Pipelines {
     #[cfg(not(feature = "hot-shaders"))]
-    fn new(device: &Arc<Device>) -> anyhow::Result<Self> {
-        let Vulkan11Properties { subgroup_size, .. } = device.physical_device.properties_v1_1;
+    fn new(
+        device: &Arc<Device>,
+        texture_filtering: TextureFiltering,
+        ambient_occlusion: AmbientOcclusion,
+    ) -> anyhow::Result<Self> {
+        let capabilities = DeviceCapabilities::new(device);
         let mut res_pak = open_res_pak()?;
+        let texture_sampler_infos = texture_filtering.sampler_infos(device);
 
         let bounding_sphere = BoundingSpherePipeline::new(device, &mut res_pak)
             .context("Creating bounding sphere pipeline")?;
+        let depth_of_field = DepthOfFieldPipeline::new(device, &mut res_pak)
+            .context("Creating depth of field pipelines")?;
         let excl_sum = ExclusiveSumPipeline::new(device, &mut res_pak)
             .context("Creating exclusive sum pipelines")?;
+        let outline =
+            OutlinePipeline::new(device, &mut res_pak).context("Creating outline pipeline")?;
+        let sky = SkyPipeline::new(device, &mut res_pak).context("Creating sky pipeline")?;
+        let ssao = SsaoPipeline::new(device, &mut res_pak, ambient_occlusion)
+            .context("Creating ambient occlusion pipelines")?;
 
         let mesh_cmd = Arc::new(
             ComputePipeline::create(
@@ -103,7 +241,7 @@ impl Pipelines {
                     read_blob(&mut res_pak, res::SHADER_MODEL_RASTER_MESH_CMD_COMP_SPIRV)?
                         .as_slice(),
                 )
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
+                .specialization_info(capabilities.workgroup_specialization_info()),
             )
             .context("Creating mesh command pipeline")?,
         );
@@ -116,82 +254,224 @@ impl Pipelines {
                     read_blob(&mut res_pak, res::SHADER_MODEL_RASTER_MESH_CULL_COMP_SPIRV)?
                         .as_slice(),
                 )
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
+                .specialization_info(capabilities.workgroup_specialization_info()),
             )
             .context("Creating mesh cull pipeline")?,
         );
 
-        let mesh_draw = Arc::new(
+        let mesh_draw_vert =
+            read_blob(&mut res_pak, res::SHADER_MODEL_RASTER_MESH_DRAW_VERT_SPIRV)?;
+        let mesh_draw_frag =
+            read_blob(&mut res_pak, res::SHADER_MODEL_RASTER_MESH_DRAW_FRAG_SPIRV)?;
+        let mesh_depth_frag =
+            read_blob(&mut res_pak, res::SHADER_MODEL_RASTER_MESH_DEPTH_FRAG_SPIRV)?;
+        let mut mesh_depth = Vec::with_capacity(MaterialVariant::UNMASKED.len());
+
+        for variant in MaterialVariant::UNMASKED {
+            mesh_depth.push(Arc::new(
+                GraphicPipeline::create(
+                    device,
+                    Self::mesh_draw_info(device, variant, DebugMode::Off),
+                    [
+                        Shader::new_vertex(mesh_draw_vert.as_slice()),
+                        Shader::new_fragment(mesh_depth_frag.as_slice()),
+                    ],
+                )
+                .context("Creating mesh depth pipeline")?,
+            ));
+        }
+
+        let mut mesh_draw = Vec::with_capacity(MaterialVariant::ALL.len() * DebugMode::ALL.len());
+
+        for variant in MaterialVariant::ALL {
+            for debug_mode in DebugMode::ALL {
+                mesh_draw.push(Arc::new(
+                    GraphicPipeline::create(
+                        device,
+                        Self::mesh_draw_info(device, variant, debug_mode),
+                        [
+                            Shader::new_vertex(mesh_draw_vert.as_slice()),
+                            texture_sampler_infos.into_iter().fold(
+                                Shader::new_fragment(mesh_draw_frag.as_slice())
+                                    .specialization_info(Self::mesh_draw_specialization_info(
+                                        variant, debug_mode,
+                                    ))
+                                    .image_sampler(8, ReflectionProbes::sampler_info()),
+                                |shader, (binding, info)| shader.image_sampler(binding, info),
+                            ),
+                        ],
+                    )
+                    .context("Creating mesh draw pipeline")?,
+                ));
+            }
+        }
+
+        let mesh_pick = Arc::new(
             GraphicPipeline::create(
                 device,
-                GraphicPipelineInfo::new(),
+                Self::mesh_pick_info(),
                 [
-                    Shader::new_vertex(read_blob(
-                        &mut res_pak,
-                        res::SHADER_MODEL_RASTER_MESH_DRAW_VERT_SPIRV,
-                    )?),
-                    Shader::new_fragment(read_blob(
-                        &mut res_pak,
-                        res::SHADER_MODEL_RASTER_MESH_DRAW_FRAG_SPIRV,
-                    )?),
+                    Shader::new_vertex(mesh_draw_vert.as_slice()),
+                    texture_sampler_infos.into_iter().fold(
+                        Shader::new_fragment(
+                            read_blob(&mut res_pak, res::SHADER_MODEL_RASTER_MESH_PICK_FRAG_SPIRV)?
+                                .as_slice(),
+                        ),
+                        |shader, (binding, info)| shader.image_sampler(binding, info),
+                    ),
                 ],
             )
-            .context("Creating mesh draw pipeline")?,
+            .context("Creating mesh pick pipeline")?,
         );
 
+        let mesh_task = capabilities
+            .mesh_shaders
+            .then(|| {
+                MeshTaskPipelines::new(device, texture_sampler_infos, capabilities, &mut res_pak)
+                    .map_err(|err| warn!("Unable to create mesh task pipelines: {err:#}"))
+                    .ok()
+            })
+            .flatten();
+
         Ok(Self {
             bounding_sphere,
+            depth_of_field,
             excl_sum,
             mesh_cmd,
             mesh_cull,
+            mesh_depth,
             mesh_draw,
-            subgroup_size,
+            mesh_pick,
+            mesh_task,
+            outline,
+            sky,
+            ssao,
+            workgroup_size: capabilities.workgroup_size,
         })
     }
 
     #[cfg(feature = "hot-shaders")]
-    fn new(device: &Arc<Device>) -> anyhow::Result<Self> {
-        let PhysicalDeviceVulkan11Properties { subgroup_size, .. } = device.vulkan_1_1_properties;
+    fn new(
+        device: &Arc<Device>,
+        texture_filtering: TextureFiltering,
+        ambient_occlusion: AmbientOcclusion,
+    ) -> anyhow::Result<Self> {
+        let capabilities = DeviceCapabilities::new(device);
         let shader_dir = res_shader_dir();
+        let texture_sampler_infos = texture_filtering.sampler_infos(device);
 
         let bounding_sphere =
             BoundingSpherePipeline::new(device).context("Creating bounding sphere pipeline")?;
+        let depth_of_field =
+            DepthOfFieldPipeline::new(device).context("Creating depth of field pipelines")?;
         let excl_sum =
             ExclusiveSumPipeline::new(device).context("Creating exclusive sum pipelines")?;
+        let outline = OutlinePipeline::new(device).context("Creating outline pipeline")?;
+        let sky = SkyPipeline::new(device).context("Creating sky pipeline")?;
+        let ssao = SsaoPipeline::new(device, ambient_occlusion)
+            .context("Creating ambient occlusion pipelines")?;
 
         let mesh_cmd = HotComputePipeline::create(
             &device,
             ComputePipelineInfo::default(),
-            HotShader::new_compute(shader_dir.join("model/raster/mesh_cull.comp"))
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
+            HotShader::new_compute(watch(shader_dir.join("model/raster/mesh_cmd.comp")))
+                .specialization_info(capabilities.workgroup_specialization_info()),
         )
         .context("Creating hot mesh command pipeline")?;
 
         let mesh_cull = HotComputePipeline::create(
             &device,
             ComputePipelineInfo::default(),
-            HotShader::new_compute(shader_dir.join("model/raster/mesh_cull.comp"))
-                .specialization_info(Self::subgroup_specialization_info(subgroup_size)),
+            HotShader::new_compute(watch(shader_dir.join("model/raster/mesh_cull.comp")))
+                .specialization_info(capabilities.workgroup_specialization_info()),
         )
         .context("Creating hot mesh cull pipeline")?;
 
-        let mesh_draw = HotGraphicPipeline::create(
+        let mut mesh_depth = Vec::with_capacity(MaterialVariant::UNMASKED.len());
+
+        for variant in MaterialVariant::UNMASKED {
+            mesh_depth.push(
+                HotGraphicPipeline::create(
+                    &device,
+                    Self::mesh_draw_info(device, variant, DebugMode::Off),
+                    [
+                        HotShader::new_vertex(watch(
+                            shader_dir.join("model/raster/mesh_draw.vert"),
+                        )),
+                        HotShader::new_fragment(watch(
+                            shader_dir.join("model/raster/mesh_depth.frag"),
+                        )),
+                    ],
+                )
+                .context("Creating hot mesh depth pipeline")?,
+            );
+        }
+
+        let mut mesh_draw = Vec::with_capacity(MaterialVariant::ALL.len() * DebugMode::ALL.len());
+
+        for variant in MaterialVariant::ALL {
+            for debug_mode in DebugMode::ALL {
+                mesh_draw.push(
+                    HotGraphicPipeline::create(
+                        &device,
+                        Self::mesh_draw_info(device, variant, debug_mode),
+                        [
+                            HotShader::new_vertex(watch(
+                                shader_dir.join("model/raster/mesh_draw.vert"),
+                            )),
+                            texture_sampler_infos.into_iter().fold(
+                                HotShader::new_fragment(watch(
+                                    shader_dir.join("model/raster/mesh_draw.frag"),
+                                ))
+                                .specialization_info(Self::mesh_draw_specialization_info(
+                                    variant, debug_mode,
+                                ))
+                                .image_sampler(8, ReflectionProbes::sampler_info()),
+                                |shader, (binding, info)| shader.image_sampler(binding, info),
+                            ),
+                        ],
+                    )
+                    .context("Creating hot mesh draw pipeline")?,
+                );
+            }
+        }
+
+        let mesh_pick = HotGraphicPipeline::create(
             &device,
-            GraphicPipelineInfo::new(),
+            Self::mesh_pick_info(),
             [
-                HotShader::new_vertex(shader_dir.join("model/raster/mesh_draw.vert")),
-                HotShader::new_fragment(shader_dir.join("model/raster/mesh_draw.frag")),
+                HotShader::new_vertex(watch(shader_dir.join("model/raster/mesh_draw.vert"))),
+                texture_sampler_infos.into_iter().fold(
+                    HotShader::new_fragment(watch(shader_dir.join("model/raster/mesh_pick.frag"))),
+                    |shader, (binding, info)| shader.image_sampler(binding, info),
+                ),
             ],
         )
-        .context("Creating hot mesh draw pipeline")?;
+        .context("Creating hot mesh pick pipeline")?;
+
+        let mesh_task = capabilities
+            .mesh_shaders
+            .then(|| {
+                MeshTaskPipelines::new(device, texture_sampler_infos, capabilities)
+                    .map_err(|err| warn!("Unable to create hot mesh task pipelines: {err:#}"))
+                    .ok()
+            })
+            .flatten();
 
         Ok(Self {
             bounding_sphere,
+            depth_of_field,
             excl_sum,
             mesh_cmd,
             mesh_cull,
+            mesh_depth,
             mesh_draw,
-            subgroup_size,
+            mesh_pick,
+            mesh_task,
+            outline,
+            sky,
+            ssao,
+            workgroup_size: capabilities.workgroup_size,
         })
     }
 
@@ -217,27 +497,515 @@ impl Pipelines {
         res
     }
 
+    /// Returns the depth-only pipeline of a variant of [`MaterialVariant::UNMASKED`], which is the
+    /// task and mesh shader pipeline when there is one.
+    #[inline(always)]
+    fn mesh_depth(&mut self, variant: MaterialVariant) -> &Arc<GraphicPipeline> {
+        let idx = MaterialVariant::UNMASKED
+            .iter()
+            .position(|&unmasked| unmasked == variant)
+            .unwrap();
+
+        if let Some(mesh_task) = &mut self.mesh_task {
+            return mesh_task.depth(idx);
+        }
+
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.mesh_depth[idx];
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.mesh_depth[idx].hot();
+
+        res
+    }
+
+    /// Returns the task and mesh shader pipeline when there is one, which has the same
+    /// descriptor bindings as the vertex shader pipeline.
+    #[inline(always)]
+    fn mesh_draw(
+        &mut self,
+        variant: MaterialVariant,
+        debug_mode: DebugMode,
+    ) -> &Arc<GraphicPipeline> {
+        let idx = variant.index() * DebugMode::ALL.len() + debug_mode.index();
+
+        if let Some(mesh_task) = &mut self.mesh_task {
+            return mesh_task.draw(idx);
+        }
+
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.mesh_draw[idx];
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.mesh_draw[idx].hot();
+
+        res
+    }
+
+    fn mesh_draw_info(
+        device: &Device,
+        variant: MaterialVariant,
+        debug_mode: DebugMode,
+    ) -> GraphicPipelineInfoBuilder {
+        let mut info = GraphicPipelineInfo::new();
+
+        if variant.flags().contains(MaterialFlags::TWO_SIDED) {
+            info = info.cull_mode(vk::CullModeFlags::NONE);
+        }
+
+        match debug_mode {
+            DebugMode::Wireframe if DeviceCapabilities::new(device).wireframe => {
+                info.polygon_mode(vk::PolygonMode::LINE)
+            }
+            DebugMode::Wireframe => {
+                warn!("Wireframe requires fill mode non-solid");
+
+                info
+            }
+            DebugMode::Overdraw => info.blend(BlendMode {
+                blend_enable: true,
+                src_color_blend_factor: vk::BlendFactor::ONE,
+                dst_color_blend_factor: vk::BlendFactor::ONE,
+                color_blend_op: vk::BlendOp::ADD,
+                src_alpha_blend_factor: vk::BlendFactor::ONE,
+                dst_alpha_blend_factor: vk::BlendFactor::ZERO,
+                alpha_blend_op: vk::BlendOp::ADD,
+                color_write_mask: vk::ColorComponentFlags::RGBA,
+            }),
+            _ => info,
+        }
+    }
+
+    fn mesh_draw_specialization_info(
+        variant: MaterialVariant,
+        debug_mode: DebugMode,
+    ) -> SpecializationInfo {
+        let masked = variant.flags().contains(MaterialFlags::MASKED) as u32;
+
+        SpecializationInfo::new(
+            [
+                vk::SpecializationMapEntry {
+                    constant_id: 0,
+                    offset: 0,
+                    size: size_of::<u32>(),
+                },
+                vk::SpecializationMapEntry {
+                    constant_id: 1,
+                    offset: size_of::<u32>() as _,
+                    size: size_of::<u32>(),
+                },
+            ],
+            bytes_of(&[debug_mode as u32, masked]),
+        )
+    }
+
+    /// Returns the task and mesh shader pipeline when there is one.
+    #[inline(always)]
+    fn mesh_pick(&mut self) -> &Arc<GraphicPipeline> {
+        if let Some(mesh_task) = &mut self.mesh_task {
+            return mesh_task.pick();
+        }
+
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.mesh_pick;
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.mesh_pick.hot();
+
+        res
+    }
+
+    /// Picking draws both sides of every mesh, so masked and two-sided materials share a pipeline.
+    fn mesh_pick_info() -> GraphicPipelineInfoBuilder {
+        GraphicPipelineInfo::new().cull_mode(vk::CullModeFlags::NONE)
+    }
+}
+
+/// Pipelines which cull mesh instances into per-variant lists and draw each listed instance as
+/// meshlets using task and mesh shaders, without generating draw commands.
+#[cfg(not(feature = "hot-shaders"))]
+#[derive(Debug)]
+struct MeshTaskPipelines {
+    cull: Arc<ComputePipeline>,
+
+    /// One depth-only pipeline for each material variant of [`MaterialVariant::UNMASKED`].
+    depth: Vec<Arc<GraphicPipeline>>,
+
+    /// One pipeline for each material variant and debug mode, in the same order as
+    /// [`Pipelines::mesh_draw`].
+    draw: Vec<Arc<GraphicPipeline>>,
+
+    pick: Arc<GraphicPipeline>,
+}
+
+#[cfg(feature = "hot-shaders")]
+#[derive(Debug)]
+struct MeshTaskPipelines {
+    cull: HotComputePipeline,
+    depth: Vec<HotGraphicPipeline>,
+    draw: Vec<HotGraphicPipeline>,
+    pick: HotGraphicPipeline,
+}
+
+impl MeshTaskPipelines {
+    #[cfg(not(feature = "hot-shaders"))]
+    fn new(
+        device: &Arc<Device>,
+        texture_sampler_infos: MaterialSamplerInfos,
+        capabilities: DeviceCapabilities,
+        res_pak: &mut PakBuf,
+    ) -> anyhow::Result<Self> {
+        let cull = Arc::new(
+            ComputePipeline::create(
+                device,
+                ComputePipelineInfo::default(),
+                Shader::new_compute(
+                    read_blob(res_pak, res::SHADER_MODEL_RASTER_MESH_TASK_CULL_COMP_SPIRV)?
+                        .as_slice(),
+                )
+                .specialization_info(capabilities.workgroup_specialization_info()),
+            )
+            .context("Creating mesh task cull pipeline")?,
+        );
+
+        let mesh_draw_task = read_blob(res_pak, res::SHADER_MODEL_RASTER_MESH_DRAW_TASK_SPIRV)?;
+        let mesh_draw_mesh = read_blob(res_pak, res::SHADER_MODEL_RASTER_MESH_DRAW_MESH_SPIRV)?;
+        let mesh_draw_frag = read_blob(res_pak, res::SHADER_MODEL_RASTER_MESH_DRAW_FRAG_SPIRV)?;
+        let mesh_depth_frag = read_blob(res_pak, res::SHADER_MODEL_RASTER_MESH_DEPTH_FRAG_SPIRV)?;
+        let mut depth = Vec::with_capacity(MaterialVariant::UNMASKED.len());
+
+        for variant in MaterialVariant::UNMASKED {
+            depth.push(Arc::new(
+                GraphicPipeline::create(
+                    device,
+                    Pipelines::mesh_draw_info(device, variant, DebugMode::Off),
+                    [
+                        Shader::new_task(mesh_draw_task.as_slice()),
+                        Shader::new_mesh(mesh_draw_mesh.as_slice()),
+                        Shader::new_fragment(mesh_depth_frag.as_slice()),
+                    ],
+                )
+                .context("Creating mesh task depth pipeline")?,
+            ));
+        }
+
+        let mut draw = Vec::with_capacity(MaterialVariant::ALL.len() * DebugMode::ALL.len());
+
+        for variant in MaterialVariant::ALL {
+            for debug_mode in DebugMode::ALL {
+                draw.push(Arc::new(
+                    GraphicPipeline::create(
+                        device,
+                        Pipelines::mesh_draw_info(device, variant, debug_mode),
+                        [
+                            Shader::new_task(mesh_draw_task.as_slice()),
+                            Shader::new_mesh(mesh_draw_mesh.as_slice()),
+                            texture_sampler_infos.into_iter().fold(
+                                Shader::new_fragment(mesh_draw_frag.as_slice())
+                                    .specialization_info(Pipelines::mesh_draw_specialization_info(
+                                        variant, debug_mode,
+                                    ))
+                                    .image_sampler(8, ReflectionProbes::sampler_info()),
+                                |shader, (binding, info)| shader.image_sampler(binding, info),
+                            ),
+                        ],
+                    )
+                    .context("Creating mesh task draw pipeline")?,
+                ));
+            }
+        }
+
+        let pick = Arc::new(
+            GraphicPipeline::create(
+                device,
+                Pipelines::mesh_pick_info(),
+                [
+                    Shader::new_task(mesh_draw_task.as_slice()),
+                    Shader::new_mesh(mesh_draw_mesh.as_slice()),
+                    texture_sampler_infos.into_iter().fold(
+                        Shader::new_fragment(
+                            read_blob(res_pak, res::SHADER_MODEL_RASTER_MESH_PICK_FRAG_SPIRV)?
+                                .as_slice(),
+                        ),
+                        |shader, (binding, info)| shader.image_sampler(binding, info),
+                    ),
+                ],
+            )
+            .context("Creating mesh task pick pipeline")?,
+        );
+
+        Ok(Self {
+            cull,
+            depth,
+            draw,
+            pick,
+        })
+    }
+
+    #[cfg(feature = "hot-shaders")]
+    fn new(
+        device: &Arc<Device>,
+        texture_sampler_infos: MaterialSamplerInfos,
+        capabilities: DeviceCapabilities,
+    ) -> anyhow::Result<Self> {
+        let shader_dir = res_shader_dir();
+
+        let cull = HotComputePipeline::create(
+            device,
+            ComputePipelineInfo::default(),
+            HotShader::new_compute(watch(shader_dir.join("model/raster/mesh_task_cull.comp")))
+                .specialization_info(capabilities.workgroup_specialization_info()),
+        )
+        .context("Creating hot mesh task cull pipeline")?;
+
+        let mut depth = Vec::with_capacity(MaterialVariant::UNMASKED.len());
+
+        for variant in MaterialVariant::UNMASKED {
+            depth.push(
+                HotGraphicPipeline::create(
+                    device,
+                    Pipelines::mesh_draw_info(device, variant, DebugMode::Off),
+                    [
+                        HotShader::new_task(watch(shader_dir.join("model/raster/mesh_draw.task"))),
+                        HotShader::new_mesh(watch(shader_dir.join("model/raster/mesh_draw.mesh"))),
+                        HotShader::new_fragment(watch(
+                            shader_dir.join("model/raster/mesh_depth.frag"),
+                        )),
+                    ],
+                )
+                .context("Creating hot mesh task depth pipeline")?,
+            );
+        }
+
+        let mut draw = Vec::with_capacity(MaterialVariant::ALL.len() * DebugMode::ALL.len());
+
+        for variant in MaterialVariant::ALL {
+            for debug_mode in DebugMode::ALL {
+                draw.push(
+                    HotGraphicPipeline::create(
+                        device,
+                        Pipelines::mesh_draw_info(device, variant, debug_mode),
+                        [
+                            HotShader::new_task(watch(
+                                shader_dir.join("model/raster/mesh_draw.task"),
+                            )),
+                            HotShader::new_mesh(watch(
+                                shader_dir.join("model/raster/mesh_draw.mesh"),
+                            )),
+                            texture_sampler_infos.into_iter().fold(
+                                HotShader::new_fragment(watch(
+                                    shader_dir.join("model/raster/mesh_draw.frag"),
+                                ))
+                                .specialization_info(Pipelines::mesh_draw_specialization_info(
+                                    variant, debug_mode,
+                                ))
+                                .image_sampler(8, ReflectionProbes::sampler_info()),
+                                |shader, (binding, info)| shader.image_sampler(binding, info),
+                            ),
+                        ],
+                    )
+                    .context("Creating hot mesh task draw pipeline")?,
+                );
+            }
+        }
+
+        let pick = HotGraphicPipeline::create(
+            device,
+            Pipelines::mesh_pick_info(),
+            [
+                HotShader::new_task(watch(shader_dir.join("model/raster/mesh_draw.task"))),
+                HotShader::new_mesh(watch(shader_dir.join("model/raster/mesh_draw.mesh"))),
+                texture_sampler_infos.into_iter().fold(
+                    HotShader::new_fragment(watch(shader_dir.join("model/raster/mesh_pick.frag"))),
+                    |shader, (binding, info)| shader.image_sampler(binding, info),
+                ),
+            ],
+        )
+        .context("Creating hot mesh task pick pipeline")?;
+
+        Ok(Self {
+            cull,
+            depth,
+            draw,
+            pick,
+        })
+    }
+
+    #[inline(always)]
+    fn cull(&mut self) -> &Arc<ComputePipeline> {
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.cull;
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.cull.hot();
+
+        res
+    }
+
+    #[inline(always)]
+    fn depth(&mut self, idx: usize) -> &Arc<GraphicPipeline> {
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.depth[idx];
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.depth[idx].hot();
+
+        res
+    }
+
+    #[inline(always)]
+    fn draw(&mut self, idx: usize) -> &Arc<GraphicPipeline> {
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.draw[idx];
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.draw[idx].hot();
+
+        res
+    }
+
     #[inline(always)]
-    fn mesh_draw(&mut self) -> &Arc<GraphicPipeline> {
+    fn pick(&mut self) -> &Arc<GraphicPipeline> {
         #[cfg(not(feature = "hot-shaders"))]
-        let res = &self.mesh_draw;
+        let res = &self.pick;
 
         #[cfg(feature = "hot-shaders")]
-        let res = self.mesh_draw.hot();
+        let res = self.pick.hot();
+
+        res
+    }
+}
+
+/// Tracks which regions of `Raster::INSTANCE_GRANULARITY` elements of a buffer have changed since
+/// they were last uploaded.
+#[derive(Debug, Default)]
+struct DirtyRegions(Vec<bool>);
+
+impl DirtyRegions {
+    fn mark(&mut self, idx: usize) {
+        let region = idx / Raster::INSTANCE_GRANULARITY;
+
+        if region >= self.0.len() {
+            self.0.resize(region + 1, false);
+        }
+
+        self.0[region] = true;
+    }
+
+    /// Marks every region clean and returns the elements, out of the first `len`, which changed;
+    /// neighbouring regions are merged into one range.
+    fn take(&mut self, len: usize) -> Vec<Range<usize>> {
+        let mut res: Vec<Range<usize>> = vec![];
+
+        for (region, is_dirty) in self.0.iter_mut().enumerate() {
+            if !replace(is_dirty, false) {
+                continue;
+            }
+
+            let start = region * Raster::INSTANCE_GRANULARITY;
+            let end = len.min(start + Raster::INSTANCE_GRANULARITY);
+
+            if start >= end {
+                continue;
+            }
+
+            match res.last_mut() {
+                Some(range) if range.end == start => range.end = end,
+                _ => res.push(start..end),
+            }
+        }
 
         res
     }
+}
+
+/// Number of frames which may be recorded while the GPU still reads the uploads of earlier ones.
+const FRAMES_IN_FLIGHT: usize = 2;
+
+/// Host-visible buffers which uploads are written into, one for each frame in flight.
+///
+/// A buffer is written again only once the GPU has released it, which is when the submitted frame
+/// drops its reference; until then a new buffer is leased instead of waiting on the GPU.
+#[derive(Debug, Default)]
+struct StagingRing {
+    bufs: [Option<Arc<Lease<Buffer>>>; FRAMES_IN_FLIGHT],
+    idx: usize,
+}
 
-    fn subgroup_specialization_info(subgroup_size: u32) -> SpecializationInfo {
-        SpecializationInfo {
-            data: subgroup_size.to_ne_bytes().to_vec(),
-            map_entries: vec![vk::SpecializationMapEntry {
-                constant_id: 0,
-                offset: 0,
-                size: size_of::<u32>(),
-            }],
+impl StagingRing {
+    /// Returns the next staging buffer after `write` has filled its first `len` bytes.
+    fn write(
+        &mut self,
+        pool: &mut SizeClassPool,
+        len: vk::DeviceSize,
+        write: impl FnOnce(&mut [u8]),
+    ) -> Result<Arc<Lease<Buffer>>, DriverError> {
+        self.idx = (self.idx + 1) % FRAMES_IN_FLIGHT;
+
+        let slot = &mut self.bufs[self.idx];
+
+        if !slot
+            .as_mut()
+            .and_then(Arc::get_mut)
+            .is_some_and(|buf| buf.info.size >= len)
+        {
+            *slot = Some(Arc::new(pool.lease(BufferInfo::new_mappable(
+                len,
+                vk::BufferUsageFlags::TRANSFER_SRC,
+            ))?));
         }
+
+        let buf = slot.as_mut().unwrap();
+
+        write(&mut Buffer::mapped_slice_mut(Arc::get_mut(buf).unwrap())[0..len as usize]);
+
+        Ok(Arc::clone(buf))
+    }
+}
+
+/// Records a copy of each range of elements into the same range of `dst`, after `write` has
+/// filled the staging bytes of each range; nothing is recorded when no elements changed.
+#[allow(clippy::too_many_arguments)]
+fn upload_ranges(
+    render_graph: &mut RenderGraph,
+    pool: &mut SizeClassPool,
+    staging: &mut StagingRing,
+    dst: BufferNode,
+    ranges: &[Range<usize>],
+    stride: vk::DeviceSize,
+    mut write: impl FnMut(Range<usize>, &mut [u8]),
+) -> Result<(), DriverError> {
+    let len = ranges.iter().map(|range| range.len()).sum::<usize>() as vk::DeviceSize * stride;
+
+    if len == 0 {
+        return Ok(());
     }
+
+    let mut regions = Vec::with_capacity(ranges.len());
+    let staging_buf = staging.write(pool, len, |data| {
+        let mut src_offset = 0;
+
+        for range in ranges {
+            let size = range.len() as vk::DeviceSize * stride;
+
+            write(
+                range.clone(),
+                &mut data[src_offset as usize..(src_offset + size) as usize],
+            );
+            regions.push(vk::BufferCopy {
+                src_offset,
+                dst_offset: range.start as vk::DeviceSize * stride,
+                size,
+            });
+
+            src_offset += size;
+        }
+    })?;
+    let staging_buf = render_graph.bind_node(staging_buf);
+
+    render_graph.copy_buffer_regions(staging_buf, dst, regions);
+
+    Ok(())
 }
 
 #[derive(Debug)]
@@ -247,29 +1015,53 @@ pub(super) struct Raster {
     draw_count_buf: Arc<Buffer>,
     draw_instance_buf: Arc<Buffer>,
 
+    /// Number of draw instances reserved for each material variant.
+    variant_instance_capacity: u32,
+
     mesh_count: u32,
 
     mesh_instance_buf: Arc<Buffer>,
     mesh_instance_count: u32,
+
+    /// Index of the first model instance whose mesh instances have not been uploaded.
     mesh_instance_dirty: usize,
+    mesh_instance_staging: StagingRing,
 
     mesh_instance_count_buf: Arc<Buffer>,
-    mesh_instance_count_dirty: Vec<bool>,
+    mesh_instance_count_dirty: DirtyRegions,
+    mesh_instance_count_staging: StagingRing,
     mesh_instance_counts: Vec<u32>,
 
     model_instance_buf: Arc<Buffer>,
-    model_instance_dirty: Vec<bool>,
+    model_instance_dirty: DirtyRegions,
+    model_instance_staging: StagingRing,
     model_instances: Vec<ModelInstanceData>,
 
     model_mesh_count: Vec<u32>,
 
-    pool: LazyPool,
-    pipelines: Pipelines,
+    overlay: bool,
+    pool: SizeClassPool,
+    pipelines: PendingPipelines<Pipelines>,
+
+    /// The first mesh and geometry of each posed model, by model index, whose bounding spheres are
+    /// found again each time it is skinned.
+    posed_models: HashMap<usize, (u32, Box<[Geometry]>)>,
 }
 
 impl Raster {
+    /// Set in the layer mask of highlighted model instances, above the bits of [`RenderLayers`],
+    /// so that the outline pass culls every other model instance.
+    const HIGHLIGHT_LAYER_MASK: u32 = 1 << 8;
+
     const INSTANCE_GRANULARITY: usize = 64;
 
+    const OVERLAY_Z_NEAR: f32 = 0.01;
+
+    const DEPTH_STENCIL_MODE: DepthStencilMode = DepthStencilMode {
+        compare_op: vk::CompareOp::GREATER,
+        ..DepthStencilMode::DEPTH_WRITE
+    };
+
     pub fn new(device: &Arc<Device>, info: ModelBufferInfo) -> anyhow::Result<Self> {
         let bounding_sphere_buf = Arc::new(Buffer::create(
             device,
@@ -281,7 +1073,10 @@ impl Raster {
         let draw_cmd_buf = Arc::new(Buffer::create(
             device,
             BufferInfo::new(
-                info.mesh_capacity * size_of::<vk::DrawIndexedIndirectCommand>() as vk::DeviceSize,
+                // Each material variant has its own draw command for every mesh
+                MaterialVariant::ALL.len() as vk::DeviceSize
+                    * info.mesh_capacity
+                    * size_of::<vk::DrawIndirectCommand>() as vk::DeviceSize,
                 vk::BufferUsageFlags::INDIRECT_BUFFER | vk::BufferUsageFlags::STORAGE_BUFFER,
             ),
         )?);
@@ -295,7 +1090,9 @@ impl Raster {
         let draw_instance_buf = Arc::new(Buffer::create(
             device,
             BufferInfo::new(
-                info.mesh_capacity * size_of::<u32>() as vk::DeviceSize,
+                MaterialVariant::ALL.len() as vk::DeviceSize
+                    * info.mesh_capacity
+                    * size_of::<u32>() as vk::DeviceSize,
                 vk::BufferUsageFlags::STORAGE_BUFFER,
             ),
         )?);
@@ -322,133 +1119,130 @@ impl Raster {
                 vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
             ),
         )?);
-        let pipelines = Pipelines::new(device)?;
-
-        let mesh_dirty_len = (info.mesh_capacity as usize + Self::INSTANCE_GRANULARITY - 1)
-            / Self::INSTANCE_GRANULARITY;
-        let mesh_instance_dirty = vec![false; mesh_dirty_len];
-        let mesh_instance_count_dirty = vec![false; mesh_dirty_len];
+        // The overlay is drawn over an already occluded frame
+        let ambient_occlusion = if info.overlay {
+            AmbientOcclusion::Off
+        } else {
+            info.ambient_occlusion
+        };
+        let pipelines = {
+            let device = Arc::clone(device);
+            let texture_filtering = info.texture_filtering;
 
-        let model_instance_dirty_len = (info.model_capacity as usize + Self::INSTANCE_GRANULARITY
-            - 1)
-            / Self::INSTANCE_GRANULARITY;
-        let model_instance_dirty = vec![false; model_instance_dirty_len];
+            PendingPipelines::spawn(move || {
+                Pipelines::new(&device, texture_filtering, ambient_occlusion)
+            })
+        };
 
-        let pool = LazyPool::new(device);
+        let pool = SizeClassPool::new(device);
 
         Ok(Self {
             bounding_sphere_buf,
             draw_cmd_buf,
             draw_count_buf,
             draw_instance_buf,
+            variant_instance_capacity: info.mesh_capacity as _,
             mesh_count: 0,
             mesh_instance_buf,
             mesh_instance_count: 0,
             mesh_instance_dirty: 0,
+            mesh_instance_staging: Default::default(),
             mesh_instance_count_buf,
-            mesh_instance_count_dirty,
+            mesh_instance_count_dirty: Default::default(),
+            mesh_instance_count_staging: Default::default(),
             mesh_instance_counts: Default::default(),
             model_instance_buf,
-            model_instance_dirty,
+            model_instance_dirty: Default::default(),
+            model_instance_staging: Default::default(),
             model_instances: Default::default(),
             model_mesh_count: Vec::with_capacity(info.model_capacity as usize),
+            overlay: info.overlay,
             pool,
             pipelines,
+            posed_models: Default::default(),
         })
     }
 
-    fn update_mesh_instance_buf(
+    /// Finds the bounding sphere of each mesh of a model, the first of which is `first_mesh`.
+    fn record_bounding_spheres(
         &mut self,
         render_graph: &mut RenderGraph,
-    ) -> Result<BufferNode, DriverError> {
-        let mesh_instance_buf = render_graph.bind_node(&self.mesh_instance_buf);
-
-        if self.mesh_instance_dirty < self.model_instances.len() {
-            let temp_len = self.mesh_instance_count as vk::DeviceSize * MeshInstanceRef::SIZE;
-            let mut temp_buf = self.pool.lease(BufferInfo::new_mappable(
-                temp_len,
-                vk::BufferUsageFlags::TRANSFER_SRC,
-            ))?;
-            let temp_data = Buffer::mapped_slice_mut(&mut temp_buf);
-
-            let mut base = 0;
-            for (model_instance_idx, model_instance) in self.model_instances.iter().enumerate() {
-                let model_instance_idx = model_instance_idx as u32;
-
-                for mesh_offset in 0..self.model_mesh_count[model_instance.model.model_idx] {
-                    let start = base as usize * MeshInstanceRef::SIZE as usize;
-                    let end = start + MeshInstanceRef::SIZE as usize;
-                    let mesh_idx = model_instance.model.mesh_idx as u32 + mesh_offset;
+        geometry_buf: BufferNode,
+        first_mesh: u32,
+        geometries: &[Geometry],
+    ) -> Result<(), DriverError> {
+        let bounding_sphere_buf = render_graph.bind_node(&self.bounding_sphere_buf);
 
-                    temp_data[start..end].copy_from_slice(bytes_of(&MeshInstanceRef {
-                        mesh_idx,
-                        model_instance_idx,
-                    }));
+        // Bounding spheres are computed on the GPU, so loading blocks until pipelines are ready
+        let pipelines = self.pipelines.wait()?;
 
-                    base += 1;
-                }
-            }
+        for (geom_idx, geom) in geometries.iter().enumerate() {
+            pipelines.bounding_sphere.record(
+                render_graph,
+                &mut self.pool,
+                geometry_buf,
+                geom.vertex_count,
+                (geom.vertex_offset / size_of::<f32>() as vk::DeviceSize) as _,
+                geom.flags.vertex_stride() as _,
+                bounding_sphere_buf,
+                (first_mesh + geom_idx as u32) as vk::DeviceSize * BoundingSphere::SIZE,
+            )?;
+        }
 
-            let temp_buf = render_graph.bind_node(temp_buf);
-            render_graph.copy_buffer_region(
-                temp_buf,
+        Ok(())
+    }
+
+    fn update_mesh_instance_buf(
+        &mut self,
+        render_graph: &mut RenderGraph,
+    ) -> Result<BufferNode, DriverError> {
+        let mesh_instance_buf = render_graph.bind_node(&self.mesh_instance_buf);
+
+        if self.mesh_instance_dirty < self.model_instances.len() {
+            // Mesh instances are packed by model instance, so everything after the first changed
+            // model instance moves
+            let dirty_mesh_instance_idx = self.model_instances[..self.mesh_instance_dirty]
+                .iter()
+                .map(|model_instance| self.model_mesh_count[model_instance.model.model_idx])
+                .sum::<u32>() as usize;
+            let model_instances = &self.model_instances[self.mesh_instance_dirty..];
+            let model_mesh_count = &self.model_mesh_count;
+            let first_model_instance_idx = self.mesh_instance_dirty as u32;
+
+            upload_ranges(
+                render_graph,
+                &mut self.pool,
+                &mut self.mesh_instance_staging,
                 mesh_instance_buf,
-                vk::BufferCopy {
-                    src_offset: 0,
-                    dst_offset: 0,
-                    size: temp_len,
+                &[dirty_mesh_instance_idx..self.mesh_instance_count as usize],
+                MeshInstanceRef::SIZE,
+                |_, data| {
+                    let mut base = 0;
+                    for (model_instance_offset, model_instance) in
+                        model_instances.iter().enumerate()
+                    {
+                        let model_instance_idx =
+                            first_model_instance_idx + model_instance_offset as u32;
+
+                        for mesh_offset in 0..model_mesh_count[model_instance.model.model_idx] {
+                            let start = base * MeshInstanceRef::SIZE as usize;
+                            let end = start + MeshInstanceRef::SIZE as usize;
+                            let mesh_idx = model_instance.model.mesh_idx as u32 + mesh_offset;
+
+                            data[start..end].copy_from_slice(bytes_of(&MeshInstanceRef {
+                                mesh_idx,
+                                model_instance_idx,
+                            }));
+
+                            base += 1;
+                        }
+                    }
                 },
-            );
-
-            // let mut mesh_instance_count = 0;
-            // self.model_instances[self.mesh_instance_dirty..]
-            //     .iter()
-            //     .for_each(|model_instance| {
-            //         mesh_instance_count += self.model_mesh_count[model_instance.model.model_idx]
-            //     });
-
-            // let temp_len = mesh_instance_count as vk::DeviceSize * MeshInstanceRef::SIZE;
-            // let mut temp_buf = self.pool.lease(BufferInfo::new_mappable(
-            //     temp_len,
-            //     vk::BufferUsageFlags::TRANSFER_SRC,
-            // ))?;
-            // let temp_data = Buffer::mapped_slice_mut(&mut temp_buf);
-
-            // self.model_instances[self.mesh_instance_dirty..]
-            //     .iter()
-            //     .enumerate()
-            //     .for_each(|(model_instance_offset, model_instance)| {
-            //         let model_instance_idx =
-            //             (self.mesh_instance_dirty + model_instance_offset) as u32;
-
-            //         for mesh_offset in
-            //             0..self.model_mesh_count[model_instance.model.model_idx]
-            //         {
-            //             let start = mesh_offset as usize * MeshInstanceRef::SIZE as usize;
-            //             let end = start + MeshInstanceRef::SIZE as usize;
-            //             let mesh_idx = model_instance.model.mesh_idx as u32 + mesh_offset;
-
-            //             temp_data[start..end].copy_from_slice(bytes_of(&MeshInstanceRef {
-            //                 mesh_idx,
-            //                 model_instance_idx,
-            //             }));
-            //         }
-            //     });
-
-            // let temp_buf = render_graph.bind_node(temp_buf);
-            // render_graph.copy_buffer_region(
-            //     temp_buf,
-            //     mesh_instance_buf,
-            //     &vk::BufferCopy {
-            //         src_offset: 0,
-            //         dst_offset: self.mesh_instance_count as vk::DeviceSize * MeshInstanceRef::SIZE - temp_len,
-            //         size: temp_len,
-            //     },
-            // );
-
-            self.mesh_instance_dirty = self.model_instances.len();
+            )?;
         }
 
+        self.mesh_instance_dirty = self.model_instances.len();
+
         Ok(mesh_instance_buf)
     }
 
@@ -457,76 +1251,20 @@ impl Raster {
         render_graph: &mut RenderGraph,
     ) -> Result<BufferNode, DriverError> {
         let mesh_instance_count_buf = render_graph.bind_node(&self.mesh_instance_count_buf);
-
-        let temp_buf_len = self.mesh_instance_counts.len() as vk::DeviceSize * 4;
-        let mut temp_buf = self.pool.lease(BufferInfo::new_mappable(
-            temp_buf_len,
-            vk::BufferUsageFlags::TRANSFER_SRC,
-        ))?;
-
-        let temp_data = Buffer::mapped_slice_mut(&mut temp_buf);
-
-        temp_data[0..temp_buf_len as usize].copy_from_slice(cast_slice(&self.mesh_instance_counts));
-
-        let temp_buf = render_graph.bind_node(temp_buf);
-
-        render_graph.copy_buffer_region(
-            temp_buf,
+        let ranges = self
+            .mesh_instance_count_dirty
+            .take(self.mesh_instance_counts.len());
+        let mesh_instance_counts = &self.mesh_instance_counts;
+
+        upload_ranges(
+            render_graph,
+            &mut self.pool,
+            &mut self.mesh_instance_count_staging,
             mesh_instance_count_buf,
-            vk::BufferCopy {
-                src_offset: 0,
-                dst_offset: 0,
-                size: temp_buf_len,
-            },
-        );
-
-        // let region_count = self
-        //     .mesh_instance_count_dirty
-        //     .iter()
-        //     .copied()
-        //     .filter(|is_dirty| *is_dirty)
-        //     .count();
-        // if region_count == 0 {
-        //     return Ok(mesh_instance_count_buf);
-        // }
-
-        // const REGION_SIZE: vk::DeviceSize =
-        //     size_of::<u32>() as vk::DeviceSize * Raster::INSTANCE_GRANULARITY as vk::DeviceSize;
-
-        // let temp_buf_len = region_count as vk::DeviceSize * REGION_SIZE;
-        // let mut temp_buf = self.pool.lease(BufferInfo::new_mappable(
-        //     temp_buf_len,
-        //     vk::BufferUsageFlags::TRANSFER_SRC,
-        // ))?;
-
-        // let mut regions = Vec::with_capacity(region_count);
-        // let temp_data = Buffer::mapped_slice_mut(&mut temp_buf);
-
-        // for (src_idx, dst_idx) in self
-        //     .mesh_instance_count_dirty
-        //     .iter()
-        //     .copied()
-        //     .enumerate()
-        //     .filter_map(|(idx, is_dirty)| is_dirty.then(|| idx))
-        //     .enumerate()
-        // {
-        //     let src_offset = src_idx as vk::DeviceSize * REGION_SIZE;
-        //     temp_data[src_offset as usize..src_offset as usize + REGION_SIZE as usize]
-        //         .copy_from_slice(cast_slice(
-        //             &self.mesh_instance_counts[src_idx..src_idx + Raster::INSTANCE_GRANULARITY],
-        //         ));
-
-        //     regions.push(vk::BufferCopy {
-        //         src_offset,
-        //         dst_offset: dst_idx as vk::DeviceSize * REGION_SIZE,
-        //         size: REGION_SIZE,
-        //     });
-        // }
-
-        // let temp_buf = render_graph.bind_node(temp_buf);
-        // render_graph.copy_buffer_regions(temp_buf, mesh_instance_count_buf, regions);
-
-        // self.mesh_instance_count_dirty.fill(false);
+            &ranges,
+            size_of::<u32>() as _,
+            |range, data| data.copy_from_slice(cast_slice(&mesh_instance_counts[range])),
+        )?;
 
         Ok(mesh_instance_count_buf)
     }
@@ -536,232 +1274,82 @@ impl Raster {
         render_graph: &mut RenderGraph,
     ) -> Result<BufferNode, DriverError> {
         let model_instance_buf = render_graph.bind_node(&self.model_instance_buf);
+        let ranges = self.model_instance_dirty.take(self.model_instances.len());
+        let model_instances = &self.model_instances;
 
-        let temp_buf_len = self.model_instances.len() as vk::DeviceSize * ModelInstanceRef::SIZE;
-        let mut temp_buf = self.pool.lease(BufferInfo::new_mappable(
-            temp_buf_len,
-            vk::BufferUsageFlags::TRANSFER_SRC,
-        ))?;
-
-        let temp_data = Buffer::mapped_slice_mut(&mut temp_buf);
-        let model_instances = self
-            .model_instances
-            .iter()
-            .map(|model_instance| {
-                let mut material_indices = [0u32; MAX_MATERIALS_PER_MODEL];
-                for (idx, material) in model_instance.materials.iter().enumerate() {
-                    material_indices[idx] = material.material_index;
-                }
-
-                let ModelInstanceData {
-                    rotation,
-                    translation,
-                    model: Model { model_idx, .. },
-                    ..
-                } = *model_instance;
-
-                ModelInstanceRef {
-                    material_indices,
-                    rotation,
-                    translation,
-                    model_idx: model_idx as _,
-                }
-            })
-            .collect::<Box<_>>();
-
-        temp_data[0..temp_buf_len as usize].copy_from_slice(cast_slice(&model_instances));
-
-        let temp_buf = render_graph.bind_node(temp_buf);
-
-        render_graph.copy_buffer_region(
-            temp_buf,
+        upload_ranges(
+            render_graph,
+            &mut self.pool,
+            &mut self.model_instance_staging,
             model_instance_buf,
-            vk::BufferCopy {
-                src_offset: 0,
-                dst_offset: 0,
-                size: temp_buf_len,
+            &ranges,
+            ModelInstanceRef::SIZE,
+            |range, data| {
+                for (idx, model_instance) in model_instances[range].iter().enumerate() {
+                    let mut material_indices = [0u32; MAX_MATERIALS_PER_MODEL];
+                    for (idx, material) in model_instance.materials.iter().enumerate() {
+                        material_indices[idx] = material.material_index;
+                    }
+
+                    let ModelInstanceData {
+                        metalness_scale,
+                        roughness_scale,
+                        rotation,
+                        tint,
+                        translation,
+                        model: Model { model_idx, .. },
+                        ..
+                    } = *model_instance;
+
+                    let start = idx * ModelInstanceRef::SIZE as usize;
+                    let end = start + ModelInstanceRef::SIZE as usize;
+
+                    data[start..end].copy_from_slice(bytes_of(&ModelInstanceRef {
+                        material_indices,
+                        rotation,
+                        translation,
+                        model_idx: model_idx as _,
+                        tint,
+                        roughness_scale,
+                        metalness_scale,
+                        layer_mask: model_instance.layer_mask()
+                            | if model_instance.highlight.is_some() {
+                                Self::HIGHLIGHT_LAYER_MASK
+                            } else {
+                                0
+                            },
+                        _0: Default::default(),
+                    }));
+                }
             },
-        );
-
-        // let region_count = self
-        //     .model_instance_dirty
-        //     .iter()
-        //     .copied()
-        //     .filter(|is_dirty| *is_dirty)
-        //     .count();
-        // if region_count == 0 {
-        //     return Ok(model_instance_buf);
-        // }
-
-        // const REGION_SIZE: vk::DeviceSize =
-        //     ModelInstanceRef::SIZE * Raster::INSTANCE_GRANULARITY as vk::DeviceSize;
-
-        // let temp_buf_len = region_count as vk::DeviceSize * REGION_SIZE;
-        // let mut temp_buf = self.pool.lease(BufferInfo::new_mappable(
-        //     temp_buf_len,
-        //     vk::BufferUsageFlags::TRANSFER_SRC,
-        // ))?;
-
-        // let mut regions = Vec::with_capacity(region_count);
-        // let temp_data = Buffer::mapped_slice_mut(&mut temp_buf);
-
-        // for (src_idx, dst_idx) in self
-        //     .model_instance_dirty
-        //     .iter()
-        //     .copied()
-        //     .enumerate()
-        //     .filter_map(|(idx, is_dirty)| is_dirty.then(|| idx))
-        //     .enumerate()
-        // {
-        //     let src_offset = src_idx as vk::DeviceSize * REGION_SIZE;
-
-        //     thread_local! {
-        //         static REFS: RefCell<Vec<ModelInstanceRef>> = Default::default();
-        //     }
-
-        //     REFS.with(|refs| {
-        //         let mut refs = refs.borrow_mut();
-
-        //         let end_idx = self
-        //             .model_instances
-        //             .len()
-        //             .min(src_idx + Self::INSTANCE_GRANULARITY);
-
-        //         refs.clear();
-        //         refs.extend(self.model_instances[src_idx..end_idx].iter().map(|data| {
-        //             let mut material_indices = [0u32; MAX_MATERIALS_PER_MODEL];
-        //             for (idx, material) in data.materials.iter().enumerate() {
-        //                 material_indices[idx] = material.material_index;
-        //             }
-
-        //             let ModelInstanceData {
-        //                 rotation,
-        //                 translation,
-        //                 model: Model { model_idx, .. },
-        //                 ..
-        //             } = *data;
-
-        //             ModelInstanceRef {
-        //                 material_indices,
-        //                 rotation,
-        //                 translation,
-        //                 model_idx: model_idx as _,
-        //             }
-        //         }));
-
-        //         let src_offset = src_offset as usize;
-        //         let data = cast_slice(&refs);
-
-        //         temp_data[src_offset..src_offset + data.len()].copy_from_slice(data);
-        //     });
-
-        //     regions.push(vk::BufferCopy {
-        //         src_offset,
-        //         dst_offset: dst_idx as vk::DeviceSize * REGION_SIZE,
-        //         size: REGION_SIZE,
-        //     });
-        // }
-
-        // let temp_buf = render_graph.bind_node(temp_buf);
-        // render_graph.copy_buffer_regions(temp_buf, model_instance_buf, regions);
-
-        // self.model_instance_dirty.fill(false);
+        )?;
 
         Ok(model_instance_buf)
     }
-}
-
-impl Index<usize> for Raster {
-    type Output = ModelInstanceData;
-
-    fn index(&self, idx: usize) -> &Self::Output {
-        &self.model_instances[idx]
-    }
-}
-
-impl IndexMut<usize> for Raster {
-    fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
-        self.model_instance_dirty[idx / Self::INSTANCE_GRANULARITY] = true;
 
-        &mut self.model_instances[idx]
-    }
-}
-
-impl Technique for Raster {
-    fn load_model(
+    /// Culls mesh instances into the draw instance lists of each material variant and mesh, and
+    /// returns the indirect draw commands of every mesh of every variant; when `highlighted` only
+    /// highlighted model instances are kept.
+    #[allow(clippy::too_many_arguments)]
+    fn record_mesh_cull(
         &mut self,
         render_graph: &mut RenderGraph,
-        geometry_buf: BufferNode,
-        geometries: &[Geometry],
-    ) -> Result<(), DriverError> {
-        let bounding_sphere_buf = render_graph.bind_node(&self.bounding_sphere_buf);
-
-        for (geom_idx, geom) in geometries.iter().enumerate() {
-            self.pipelines.bounding_sphere.record(
-                render_graph,
-                &mut self.pool,
-                geometry_buf,
-                geom.vertex_count,
-                (geom.vertex_offset / size_of::<f32>() as vk::DeviceSize) as _,
-                geom.flags.vertex_stride() as _,
-                bounding_sphere_buf,
-                (self.mesh_count + geom_idx as u32) as vk::DeviceSize * BoundingSphere::SIZE,
-            )?;
-        }
-
-        let model_idx = self.model_mesh_count.len();
-        let mesh_count = geometries.len() as u32;
-
-        self.model_mesh_count.push(mesh_count);
-        self.mesh_count += mesh_count;
-        self.mesh_instance_counts
-            .extend(repeat(0).take(mesh_count as _));
-
-        let mesh_instance_count_dirty_len =
-            (model_idx + mesh_count as usize + Self::INSTANCE_GRANULARITY - 1)
-                / Self::INSTANCE_GRANULARITY;
-        while self.mesh_instance_count_dirty.len() < mesh_instance_count_dirty_len {
-            self.mesh_instance_count_dirty.push(true);
-        }
-
-        Ok(())
-    }
-
-    fn push_model_instance(&mut self, model_instance: ModelInstanceData) {
-        let dirty_idx = self.model_instances.len() / Self::INSTANCE_GRANULARITY;
-        if dirty_idx == self.model_instance_dirty.len() {
-            self.model_instance_dirty.push(true);
-        } else {
-            self.model_instance_dirty[dirty_idx] = true;
-        }
-
-        let mesh_count = self.model_mesh_count[model_instance.model.model_idx];
-
-        self.model_instances.push(model_instance);
-        self.mesh_instance_count += mesh_count;
-
-        for idx in
-            model_instance.model.mesh_idx..model_instance.model.mesh_idx + mesh_count as usize
-        {
-            self.mesh_instance_counts[idx] += 1;
-
-            let dirty_idx = idx / Self::INSTANCE_GRANULARITY;
-            self.mesh_instance_count_dirty[dirty_idx] = true;
-        }
-    }
-
-    fn record(
-        &mut self,
-        render_graph: &mut RenderGraph,
-        framebuffer: AnyImageNode,
-        camera: &mut Camera,
-        geometry_buf: BufferNode,
+        layers: RenderLayers,
+        highlighted: bool,
+        frustum_planes: [Vec4; 5],
+        draw_instance_buf: BufferNode,
         material_buf: BufferNode,
         mesh_buf: BufferNode,
-        textures: &[Arc<Image>],
-    ) -> Result<(), DriverError> {
+        mesh_instance_buf: BufferNode,
+        model_instance_buf: BufferNode,
+    ) -> Result<BufferNode, DriverError> {
+        let workgroup_size = self.pipelines.wait()?.workgroup_size;
         let mesh_instance_offset_buf = {
-            let mesh_count = self.pipelines.excl_sum.align_input_count(self.mesh_count);
+            let mesh_count = self
+                .pipelines
+                .wait()?
+                .excl_sum
+                .align_input_count(self.mesh_count);
             let mesh_instance_offset_buf =
                 render_graph.bind_node(self.pool.lease(BufferInfo::new(
                     (mesh_count as usize * size_of::<u32>()) as _,
@@ -769,7 +1357,7 @@ impl Technique for Raster {
                 ))?);
             let mesh_instance_count_buf = self.update_mesh_instance_count_buf(render_graph)?;
 
-            self.pipelines.excl_sum.record(
+            self.pipelines.wait()?.excl_sum.record(
                 render_graph,
                 &mut self.pool,
                 mesh_instance_count_buf,
@@ -784,20 +1372,23 @@ impl Technique for Raster {
 
         {
             let mesh_count = self.mesh_count;
-            let workgroup_count =
-                (mesh_count + self.pipelines.subgroup_size - 1) / self.pipelines.subgroup_size;
+            let workgroup_count = (mesh_count + workgroup_size - 1) / workgroup_size;
 
             #[derive(Clone, Copy, Pod, Zeroable)]
             #[repr(C)]
             struct PushConstants {
                 mesh_count: u32,
+                variant_instance_stride: u32,
             }
 
-            let push_consts = PushConstants { mesh_count };
+            let push_consts = PushConstants {
+                mesh_count,
+                variant_instance_stride: self.variant_instance_capacity,
+            };
 
             render_graph
-                .begin_pass("Mesh command")
-                .bind_pipeline(self.pipelines.mesh_cmd())
+                .begin_pass(pass_name("Mesh command"))
+                .bind_pipeline(self.pipelines.wait()?.mesh_cmd())
                 .access_descriptor(0, draw_cmd_buf, AccessType::ComputeShaderWrite)
                 .access_descriptor(1, mesh_buf, AccessType::ComputeShaderReadOther)
                 .access_descriptor(
@@ -813,18 +1404,36 @@ impl Technique for Raster {
         }
 
         let bounding_sphere_buf = render_graph.bind_node(&self.bounding_sphere_buf);
-        let draw_instance_buf = render_graph.bind_node(&self.draw_instance_buf);
-        let model_instance_buf = self.update_model_instance_buf(render_graph)?;
-        let mesh_instance_buf = self.update_mesh_instance_buf(render_graph)?;
 
         {
             let mesh_instance_count = self.mesh_instance_count;
-            let workgroup_count = (mesh_instance_count + self.pipelines.subgroup_size - 1)
-                / self.pipelines.subgroup_size;
+            let workgroup_count = (mesh_instance_count + workgroup_size - 1) / workgroup_size;
+
+            #[derive(Clone, Copy, Pod, Zeroable)]
+            #[repr(C)]
+            struct PushConstants {
+                mesh_instance_count: u32,
+                mesh_count: u32,
+                variant_instance_stride: u32,
+                layer_mask: u32,
+                required_layer_mask: u32,
+                _0: [u32; 3],
+                frustum_planes: [Vec4; 5],
+            }
+
+            let push_consts = PushConstants {
+                mesh_instance_count,
+                mesh_count: self.mesh_count,
+                variant_instance_stride: self.variant_instance_capacity,
+                layer_mask: layers.bits() as _,
+                required_layer_mask: Self::required_layer_mask(highlighted),
+                _0: Default::default(),
+                frustum_planes,
+            };
 
             render_graph
-                .begin_pass("Mesh cull")
-                .bind_pipeline(self.pipelines.mesh_cull())
+                .begin_pass(pass_name("Mesh cull"))
+                .bind_pipeline(self.pipelines.wait()?.mesh_cull())
                 .access_descriptor(0, draw_cmd_buf, AccessType::ComputeShaderWrite)
                 .access_descriptor(1, draw_instance_buf, AccessType::ComputeShaderWrite)
                 .access_descriptor(2, model_instance_buf, AccessType::ComputeShaderReadOther)
@@ -835,86 +1444,764 @@ impl Technique for Raster {
                     AccessType::ComputeShaderReadOther,
                 )
                 .access_descriptor(5, bounding_sphere_buf, AccessType::ComputeShaderReadOther)
+                .access_descriptor(6, mesh_buf, AccessType::ComputeShaderReadOther)
+                .access_descriptor(7, material_buf, AccessType::ComputeShaderReadOther)
                 .record_compute(move |compute, _| {
                     compute
-                        .push_constants(&mesh_instance_count.to_ne_bytes())
+                        .push_constants(bytes_of(&push_consts))
                         .dispatch(workgroup_count, 1, 1);
                 });
         }
 
+        Ok(draw_cmd_buf)
+    }
+
+    /// Draws the culled meshes of [`MaterialVariant::UNMASKED`] into the depth image, which is
+    /// cleared, so that the shading pass which follows only shades the nearest surface of each
+    /// pixel.
+    #[allow(clippy::too_many_arguments)]
+    fn record_mesh_depth(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        camera_buf: impl Into<AnyBufferNode>,
+        depth_image: impl Into<AnyImageNode>,
+        draw_cmd_buf: AnyBufferNode,
+        geometry_buf: BufferNode,
+        draw_instance_buf: BufferNode,
+        mesh_instance_buf: BufferNode,
+        mesh_buf: BufferNode,
+        model_instance_buf: BufferNode,
+    ) -> Result<(), DriverError> {
+        let camera_buf = camera_buf.into();
+        let depth_image = depth_image.into();
+        let geometry_address = render_graph.node_device_address(geometry_buf);
+        let mesh_count = self.mesh_count;
+        let mesh_tasks = self.pipelines.wait()?.mesh_task.is_some();
+        let variant_instance_stride = self.variant_instance_capacity;
+        let geometry_access = if mesh_tasks {
+            AccessType::AnyShaderReadOther
+        } else {
+            AccessType::VertexShaderReadOther
+        };
+
+        for variant in MaterialVariant::UNMASKED {
+            let mut pass = render_graph
+                .begin_pass(pass_name("Mesh depth"))
+                .bind_pipeline(self.pipelines.wait()?.mesh_depth(variant))
+                .set_depth_stencil(Self::DEPTH_STENCIL_MODE)
+                .access_node(draw_cmd_buf, AccessType::IndirectBuffer)
+                .access_node(geometry_buf, geometry_access)
+                .access_descriptor(0, camera_buf, AccessType::AnyShaderReadUniformBuffer)
+                .access_descriptor(1, draw_instance_buf, geometry_access)
+                .access_descriptor(2, mesh_instance_buf, geometry_access)
+                .access_descriptor(3, mesh_buf, geometry_access)
+                .access_descriptor(4, model_instance_buf, geometry_access);
+
+            pass = if variant == MaterialVariant::UNMASKED[0] {
+                pass.clear_depth_stencil_value(depth_image, 0.0, 0)
+            } else {
+                pass.load_depth_stencil(depth_image)
+            };
+
+            pass.store_depth_stencil(depth_image)
+                .record_subpass(move |subpass, _| {
+                    if mesh_tasks {
+                        subpass.push_constants(bytes_of(&MeshTaskPushConstants::new(
+                            geometry_address,
+                            variant,
+                            variant_instance_stride,
+                        )));
+                        subpass.draw_mesh_tasks_indirect(
+                            draw_cmd_buf,
+                            variant.index() as vk::DeviceSize * TaskCommand::SIZE,
+                            1,
+                            TaskCommand::SIZE as _,
+                        );
+                    } else {
+                        subpass.push_constants(bytes_of(&geometry_address));
+                        subpass.draw_indirect(
+                            draw_cmd_buf,
+                            variant.index() as vk::DeviceSize
+                                * mesh_count as vk::DeviceSize
+                                * size_of::<vk::DrawIndirectCommand>() as vk::DeviceSize,
+                            mesh_count,
+                            size_of::<vk::DrawIndirectCommand>() as _,
+                        );
+                    }
+                });
+        }
+
+        Ok(())
+    }
+
+    /// Draws the index plus one of the model instance covering each pixel of the `R32_UINT` ID
+    /// image, which is cleared to zero, reusing the draw commands of the culled meshes; masked
+    /// materials cut out the same pixels as when they are drawn.
+    ///
+    /// The depth image is cleared when `depth_stencil_mode` writes depth, and otherwise must hold
+    /// the depth of the same meshes drawn with the same camera.
+    #[allow(clippy::too_many_arguments)]
+    fn record_mesh_ids(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        name: &str,
+        camera_buf: impl Into<AnyBufferNode>,
+        id_image: impl Into<AnyImageNode>,
+        depth_image: impl Into<AnyImageNode>,
+        depth_stencil_mode: DepthStencilMode,
+        draw_cmd_buf: AnyBufferNode,
+        geometry_buf: BufferNode,
+        draw_instance_buf: BufferNode,
+        mesh_instance_buf: BufferNode,
+        mesh_buf: BufferNode,
+        model_instance_buf: BufferNode,
+        material_buf: BufferNode,
+        textures: &[ImageNode],
+    ) -> Result<(), DriverError> {
+        let id_image = id_image.into();
+        let depth_image = depth_image.into();
+        let geometry_address = render_graph.node_device_address(geometry_buf);
+        let mesh_count = self.mesh_count;
+        let mesh_tasks = self.pipelines.wait()?.mesh_task.is_some();
+        let variant_instance_stride = self.variant_instance_capacity;
+        let geometry_access = if mesh_tasks {
+            AccessType::AnyShaderReadOther
+        } else {
+            AccessType::VertexShaderReadOther
+        };
+
+        let mut pass = render_graph
+            .begin_pass(pass_name(name))
+            .bind_pipeline(self.pipelines.wait()?.mesh_pick())
+            .set_depth_stencil(depth_stencil_mode)
+            .access_node(draw_cmd_buf, AccessType::IndirectBuffer)
+            .access_node(geometry_buf, geometry_access)
+            .access_descriptor(0, camera_buf.into(), AccessType::AnyShaderReadUniformBuffer)
+            .access_descriptor(1, draw_instance_buf, geometry_access)
+            .access_descriptor(2, mesh_instance_buf, geometry_access)
+            .access_descriptor(3, mesh_buf, geometry_access)
+            .access_descriptor(4, model_instance_buf, geometry_access)
+            .access_descriptor(5, material_buf, AccessType::FragmentShaderReadOther)
+            .clear_color_value(0, id_image, [0u32, 0, 0, 0]);
+
+        for (idx, texture) in textures.iter().copied().enumerate() {
+            pass = pass.read_descriptor((6, [idx as u32]), texture);
+        }
+
+        pass = if depth_stencil_mode.depth_write {
+            pass.clear_depth_stencil_value(depth_image, 0.0, 0)
+        } else {
+            pass.load_depth_stencil(depth_image)
+        };
+
+        pass.store_color(0, id_image)
+            .record_subpass(move |subpass, _| {
+                if mesh_tasks {
+                    for variant in MaterialVariant::ALL {
+                        subpass.push_constants(bytes_of(&MeshTaskPushConstants::new(
+                            geometry_address,
+                            variant,
+                            variant_instance_stride,
+                        )));
+                        subpass.draw_mesh_tasks_indirect(
+                            draw_cmd_buf,
+                            variant.index() as vk::DeviceSize * TaskCommand::SIZE,
+                            1,
+                            TaskCommand::SIZE as _,
+                        );
+                    }
+
+                    return;
+                }
+
+                subpass.push_constants(bytes_of(&geometry_address));
+
+                for variant in MaterialVariant::ALL {
+                    subpass.draw_indirect(
+                        draw_cmd_buf,
+                        variant.index() as vk::DeviceSize
+                            * mesh_count as vk::DeviceSize
+                            * size_of::<vk::DrawIndirectCommand>() as vk::DeviceSize,
+                        mesh_count,
+                        size_of::<vk::DrawIndirectCommand>() as _,
+                    );
+                }
+            });
+
+        Ok(())
+    }
+
+    /// Culls mesh instances into one draw instance list for each material variant, and returns
+    /// the indirect task commands which draw each list.
+    #[allow(clippy::too_many_arguments)]
+    fn record_mesh_task_cull(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        layers: RenderLayers,
+        highlighted: bool,
+        frustum_planes: [Vec4; 5],
+        draw_instance_buf: BufferNode,
+        material_buf: BufferNode,
+        mesh_buf: BufferNode,
+        mesh_instance_buf: BufferNode,
+        model_instance_buf: BufferNode,
+    ) -> Result<BufferLeaseNode, DriverError> {
+        let workgroup_size = self.pipelines.wait()?.workgroup_size;
+        let task_cmd_buf = render_graph.bind_node(lease_buffer(
+            &mut self.pool,
+            cast_slice(
+                &[TaskCommand {
+                    group_count_x: 0,
+                    group_count_y: 1,
+                    group_count_z: 1,
+                }; MaterialVariant::ALL.len()],
+            ),
+            vk::BufferUsageFlags::INDIRECT_BUFFER | vk::BufferUsageFlags::STORAGE_BUFFER,
+        )?);
+
+        let mesh_instance_count = self.mesh_instance_count;
+        let workgroup_count = (mesh_instance_count + workgroup_size - 1) / workgroup_size;
+
+        #[derive(Clone, Copy, Pod, Zeroable)]
+        #[repr(C)]
+        struct PushConstants {
+            mesh_instance_count: u32,
+            variant_instance_stride: u32,
+            layer_mask: u32,
+            required_layer_mask: u32,
+            frustum_planes: [Vec4; 5],
+        }
+
+        let push_consts = PushConstants {
+            mesh_instance_count,
+            variant_instance_stride: self.variant_instance_capacity,
+            layer_mask: layers.bits() as _,
+            required_layer_mask: Self::required_layer_mask(highlighted),
+            frustum_planes,
+        };
+        let bounding_sphere_buf = render_graph.bind_node(&self.bounding_sphere_buf);
+
+        render_graph
+            .begin_pass(pass_name("Mesh task cull"))
+            .bind_pipeline(self.pipelines.wait()?.mesh_task.as_mut().unwrap().cull())
+            .access_descriptor(0, task_cmd_buf, AccessType::ComputeShaderWrite)
+            .access_descriptor(1, draw_instance_buf, AccessType::ComputeShaderWrite)
+            .access_descriptor(2, model_instance_buf, AccessType::ComputeShaderReadOther)
+            .access_descriptor(3, mesh_instance_buf, AccessType::ComputeShaderReadOther)
+            .access_descriptor(4, mesh_buf, AccessType::ComputeShaderReadOther)
+            .access_descriptor(5, material_buf, AccessType::ComputeShaderReadOther)
+            .access_descriptor(6, bounding_sphere_buf, AccessType::ComputeShaderReadOther)
+            .record_compute(move |compute, _| {
+                compute
+                    .push_constants(bytes_of(&push_consts))
+                    .dispatch(workgroup_count, 1, 1);
+            });
+
+        Ok(task_cmd_buf)
+    }
+
+    /// The layer bits every culled model instance must have.
+    fn required_layer_mask(highlighted: bool) -> u32 {
+        if highlighted {
+            Self::HIGHLIGHT_LAYER_MASK
+        } else {
+            0
+        }
+    }
+}
+
+impl Index<usize> for Raster {
+    type Output = ModelInstanceData;
+
+    fn index(&self, idx: usize) -> &Self::Output {
+        &self.model_instances[idx]
+    }
+}
+
+impl IndexMut<usize> for Raster {
+    fn index_mut(&mut self, idx: usize) -> &mut Self::Output {
+        self.model_instance_dirty.mark(idx);
+
+        &mut self.model_instances[idx]
+    }
+}
+
+impl Technique for Raster {
+    fn load_model(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        geometry_buf: BufferNode,
+        geometries: &[Geometry],
+    ) -> Result<(), DriverError> {
+        self.record_bounding_spheres(render_graph, geometry_buf, self.mesh_count, geometries)?;
+
+        let mesh_count = geometries.len() as u32;
+
+        for idx in self.mesh_count..self.mesh_count + mesh_count {
+            self.mesh_instance_count_dirty.mark(idx as _);
+        }
+
+        self.model_mesh_count.push(mesh_count);
+        self.mesh_count += mesh_count;
+        self.mesh_instance_counts
+            .extend(repeat(0).take(mesh_count as _));
+
+        Ok(())
+    }
+
+    fn load_posed_model(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        geometry_buf: BufferNode,
+        geometries: &[Geometry],
+    ) -> Result<(), DriverError> {
+        self.posed_models.insert(
+            self.model_mesh_count.len(),
+            (self.mesh_count, geometries.into()),
+        );
+
+        self.load_model(render_graph, geometry_buf, geometries)
+    }
+
+    fn is_ready(&self) -> bool {
+        self.pipelines.is_ready()
+    }
+
+    fn loaded_model_count(&self) -> usize {
+        self.model_mesh_count.len()
+    }
+
+    fn push_model_instance(&mut self, model_instance: ModelInstanceData) {
+        self.model_instance_dirty.mark(self.model_instances.len());
+
+        let mesh_count = self.model_mesh_count[model_instance.model.model_idx];
+
+        self.model_instances.push(model_instance);
+        self.mesh_instance_count += mesh_count;
+
+        for idx in
+            model_instance.model.mesh_idx..model_instance.model.mesh_idx + mesh_count as usize
         {
-            let framebuffer_info = render_graph.node_info(framebuffer);
-            let aspect_ratio = framebuffer_info.width as f32 / framebuffer_info.height as f32;
-            let view_target = Vec3::Z;
-            let view = Quat::from_rotation_y(camera.yaw.to_radians())
-                * Quat::from_rotation_x(camera.pitch.to_radians());
-            let view = Mat4::look_at_lh(
-                camera.position,
-                camera.position - view.mul_vec3(view_target),
-                -Vec3::Y,
-            );
-            let projection = Mat4::perspective_lh(camera.fov_y, aspect_ratio, 0.1, 1000.0);
-            let projection_view = projection * view;
-            let camera_buf =
-                render_graph.bind_node(lease_uniform_buffer(&mut self.pool, projection_view)?);
+            self.mesh_instance_counts[idx] += 1;
+            self.mesh_instance_count_dirty.mark(idx);
+        }
+    }
+
+    fn record(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        framebuffer: AnyImageNode,
+        camera: &mut Camera,
+        debug_mode: DebugMode,
+        depth_of_field: Option<DepthOfField>,
+        depth_prepass: bool,
+        layers: RenderLayers,
+        geometry_buf: BufferNode,
+        material_buf: BufferNode,
+        mesh_buf: BufferNode,
+        reflection_probes: ReflectionProbeNodes,
+        sky: Sky,
+        textures: &[ImageNode],
+        time: f32,
+        pick: Option<Pick>,
+    ) -> Result<(), DriverError> {
+        let draw_instance_buf = render_graph.bind_node(&self.draw_instance_buf);
+        let model_instance_buf = self.update_model_instance_buf(render_graph)?;
+        let mesh_instance_buf = self.update_mesh_instance_buf(render_graph)?;
+
+        let framebuffer_info = render_graph.node_info(framebuffer);
+        let aspect_ratio = framebuffer_info.width as f32 / framebuffer_info.height as f32;
+        let z_near = if self.overlay {
+            Self::OVERLAY_Z_NEAR
+        } else {
+            Camera::Z_NEAR
+        };
+        let projection_view = camera.projection_view(aspect_ratio, z_near);
+        let frustum_planes = frustum_planes(projection_view);
+
+        // Mesh shaders draw straight from the culled instance lists, so there are only task
+        // commands (one per material variant) instead of draw commands for every mesh
+        let mesh_tasks = self.pipelines.wait()?.mesh_task.is_some();
+        let draw_cmd_buf: AnyBufferNode = if mesh_tasks {
+            self.record_mesh_task_cull(
+                render_graph,
+                layers,
+                false,
+                frustum_planes,
+                draw_instance_buf,
+                material_buf,
+                mesh_buf,
+                mesh_instance_buf,
+                model_instance_buf,
+            )?
+            .into()
+        } else {
+            self.record_mesh_cull(
+                render_graph,
+                layers,
+                false,
+                frustum_planes,
+                draw_instance_buf,
+                material_buf,
+                mesh_buf,
+                mesh_instance_buf,
+                model_instance_buf,
+            )?
+            .into()
+        };
+
+        {
+            let camera_buf = render_graph.bind_node(lease_uniform_buffer(
+                &mut self.pool,
+                CameraUniform {
+                    projection_view,
+                    position: camera.position,
+                    reflection_probe_count: reflection_probes.count,
+                    sun_direction: sky.sun_direction(),
+                    sky_turbidity: sky.turbidity,
+                    time,
+                    fog_density: sky.fog_density,
+                    fog_height: sky.fog_height,
+                    fog_height_falloff: sky.fog_height_falloff,
+                    fog_color: sky.fog_color,
+                    _0: Default::default(),
+                },
+            )?);
 
             let depth_image = render_graph.bind_node(self.pool.lease(ImageInfo::new_2d(
                 vk::Format::D32_SFLOAT,
                 framebuffer_info.width,
                 framebuffer_info.height,
-                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
-                    | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
             ))?);
 
             let mesh_count = self.mesh_count;
 
-            let mut mesh_pass = render_graph
-                .begin_pass("Mesh draw")
-                .bind_pipeline(self.pipelines.mesh_draw())
-                .set_depth_stencil(DepthStencilMode::DEPTH_WRITE)
-                .access_node(draw_cmd_buf, AccessType::IndirectBuffer)
-                .access_node(geometry_buf, AccessType::IndexBuffer)
-                .access_descriptor(0, camera_buf, AccessType::VertexShaderReadUniformBuffer)
-                .access_descriptor(1, draw_instance_buf, AccessType::VertexShaderReadOther)
-                .access_descriptor(2, geometry_buf, AccessType::VertexShaderReadOther)
-                .access_descriptor(3, geometry_buf, AccessType::Nothing)
-                .access_descriptor(4, geometry_buf, AccessType::Nothing)
-                .access_descriptor(5, mesh_instance_buf, AccessType::VertexShaderReadOther)
-                .access_descriptor(6, mesh_buf, AccessType::VertexShaderReadOther)
-                .access_descriptor(7, model_instance_buf, AccessType::VertexShaderReadOther)
-                .access_descriptor(8, material_buf, AccessType::FragmentShaderReadOther);
-
-            for (idx, texture) in textures.iter().enumerate() {
-                let texture = mesh_pass.bind_node(texture);
-                mesh_pass = mesh_pass.read_descriptor((9, [idx as u32]), texture);
+            // Wireframe and overdraw do not cover the whole framebuffer so they start from black
+            let clear_color =
+                !self.overlay && matches!(debug_mode, DebugMode::Wireframe | DebugMode::Overdraw);
+
+            if clear_color {
+                render_graph.clear_color_image_value(framebuffer, [0.0, 0.0, 0.0, 1.0]);
             }
 
-            mesh_pass
-                .store_color(0, framebuffer)
-                .clear_depth_stencil(depth_image)
-                .store_depth_stencil(depth_image)
-                .record_subpass(move |subpass, _| {
-                    subpass.draw_indirect(
-                        draw_cmd_buf,
-                        0,
-                        mesh_count,
-                        size_of::<vk::DrawIndirectCommand>() as _,
-                    );
-                });
+            // Overdraw counts every layer, including those which are hidden
+            let depth_stencil_mode = if debug_mode == DebugMode::Overdraw {
+                DepthStencilMode {
+                    depth_test: false,
+                    depth_write: false,
+                    ..Self::DEPTH_STENCIL_MODE
+                }
+            } else {
+                Self::DEPTH_STENCIL_MODE
+            };
+
+            // Wireframe and overdraw show every layer, which the pre-pass would hide
+            let depth_prepass =
+                depth_prepass && !matches!(debug_mode, DebugMode::Wireframe | DebugMode::Overdraw);
+
+            if depth_prepass {
+                self.record_mesh_depth(
+                    render_graph,
+                    camera_buf,
+                    depth_image,
+                    draw_cmd_buf,
+                    geometry_buf,
+                    draw_instance_buf,
+                    mesh_instance_buf,
+                    mesh_buf,
+                    model_instance_buf,
+                    material_buf,
+                    textures,
+                )?;
+            }
+
+            let geometry_address = render_graph.node_device_address(geometry_buf);
+            let variant_instance_stride = self.variant_instance_capacity;
+
+            // Task and mesh shaders read the same buffers the vertex shader would
+            let geometry_access = if mesh_tasks {
+                AccessType::AnyShaderReadOther
+            } else {
+                AccessType::VertexShaderReadOther
+            };
+
+            // Each material variant is drawn by its own pass, all into the same depth image
+            for variant in MaterialVariant::ALL {
+                // After the pre-pass, only the surfaces which wrote the depth of a pixel are shaded
+                let depth_stencil_mode =
+                    if depth_prepass && MaterialVariant::UNMASKED.contains(&variant) {
+                        DepthStencilMode {
+                            compare_op: vk::CompareOp::EQUAL,
+                            depth_write: false,
+                            ..depth_stencil_mode
+                        }
+                    } else {
+                        depth_stencil_mode
+                    };
+
+                let mut mesh_pass = render_graph
+                    .begin_pass(pass_name("Mesh draw"))
+                    .bind_pipeline(self.pipelines.wait()?.mesh_draw(variant, debug_mode))
+                    .set_depth_stencil(depth_stencil_mode)
+                    .access_node(draw_cmd_buf, AccessType::IndirectBuffer)
+                    .access_node(geometry_buf, geometry_access)
+                    .access_descriptor(0, camera_buf, AccessType::AnyShaderReadUniformBuffer)
+                    .access_descriptor(1, draw_instance_buf, geometry_access)
+                    .access_descriptor(2, mesh_instance_buf, geometry_access)
+                    .access_descriptor(3, mesh_buf, geometry_access)
+                    .access_descriptor(4, model_instance_buf, geometry_access)
+                    .access_descriptor(5, material_buf, AccessType::FragmentShaderReadOther)
+                    .access_descriptor(
+                        7,
+                        reflection_probes.buf,
+                        AccessType::FragmentShaderReadOther,
+                    )
+                    .read_descriptor(8, reflection_probes.image);
+
+                for (idx, texture) in textures.iter().copied().enumerate() {
+                    mesh_pass = mesh_pass.read_descriptor((6, [idx as u32]), texture);
+                }
+
+                let first_pass = variant == MaterialVariant::ALL[0];
+
+                if !first_pass || self.overlay || clear_color {
+                    mesh_pass = mesh_pass.load_color(0, framebuffer);
+                }
+
+                mesh_pass = if first_pass && !depth_prepass {
+                    mesh_pass.clear_depth_stencil_value(depth_image, 0.0, 0)
+                } else {
+                    mesh_pass.load_depth_stencil(depth_image)
+                };
+
+                mesh_pass
+                    .store_color(0, framebuffer)
+                    .store_depth_stencil(depth_image)
+                    .record_subpass(move |subpass, _| {
+                        if mesh_tasks {
+                            subpass.push_constants(bytes_of(&MeshTaskPushConstants::new(
+                                geometry_address,
+                                variant,
+                                variant_instance_stride,
+                            )));
+                            subpass.draw_mesh_tasks_indirect(
+                                draw_cmd_buf,
+                                variant.index() as vk::DeviceSize * TaskCommand::SIZE,
+                                1,
+                                TaskCommand::SIZE as _,
+                            );
+                        } else {
+                            subpass.push_constants(bytes_of(&geometry_address));
+                            subpass.draw_indirect(
+                                draw_cmd_buf,
+                                variant.index() as vk::DeviceSize
+                                    * mesh_count as vk::DeviceSize
+                                    * size_of::<vk::DrawIndirectCommand>() as vk::DeviceSize,
+                                mesh_count,
+                                size_of::<vk::DrawIndirectCommand>() as _,
+                            );
+                        }
+                    });
+            }
+
+            if let Some(pick) = pick {
+                // The pick pixel is scaled up to fill a 1x1 image, reusing the draw commands of
+                // the culled meshes because the pixel is within the view of the camera
+                let width = framebuffer_info.width as f32;
+                let height = framebuffer_info.height as f32;
+                let center_x = (pick.position.x as f32 + 0.5) / width * 2.0 - 1.0;
+                let center_y = (pick.position.y as f32 + 0.5) / height * 2.0 - 1.0;
+                let pick_projection = Mat4::from_cols(
+                    vec4(width, 0.0, 0.0, 0.0),
+                    vec4(0.0, height, 0.0, 0.0),
+                    Vec4::Z,
+                    vec4(-center_x * width, -center_y * height, 0.0, 1.0),
+                );
+                let pick_camera_buf = render_graph.bind_node(lease_uniform_buffer(
+                    &mut self.pool,
+                    CameraUniform {
+                        projection_view: pick_projection * projection_view,
+                        position: camera.position,
+                        reflection_probe_count: reflection_probes.count,
+                        sun_direction: sky.sun_direction(),
+                        sky_turbidity: sky.turbidity,
+                        time,
+                        fog_density: sky.fog_density,
+                        fog_height: sky.fog_height,
+                        fog_height_falloff: sky.fog_height_falloff,
+                        fog_color: sky.fog_color,
+                        _0: Default::default(),
+                    },
+                )?);
+                let pick_image = render_graph.bind_node(self.pool.lease(ImageInfo::new_2d(
+                    vk::Format::R32_UINT,
+                    1,
+                    1,
+                    vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+                ))?);
+                let pick_depth_image =
+                    render_graph.bind_node(self.pool.lease(ImageInfo::new_2d(
+                        vk::Format::D32_SFLOAT,
+                        1,
+                        1,
+                        vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+                    ))?);
+
+                self.record_mesh_ids(
+                    render_graph,
+                    "Mesh pick",
+                    pick_camera_buf,
+                    pick_image,
+                    pick_depth_image,
+                    Self::DEPTH_STENCIL_MODE,
+                    draw_cmd_buf,
+                    geometry_buf,
+                    draw_instance_buf,
+                    mesh_instance_buf,
+                    mesh_buf,
+                    model_instance_buf,
+                    material_buf,
+                    textures,
+                )?;
+
+                render_graph.copy_image_to_buffer(pick_image, pick.buf);
+            }
+
+            // The overlay draws over an existing frame and debug modes show only the models
+            if !self.overlay && debug_mode == DebugMode::Off {
+                if let Some(ssao) = &mut self.pipelines.wait()?.ssao {
+                    ssao.record(
+                        render_graph,
+                        &mut self.pool,
+                        framebuffer,
+                        depth_image,
+                        camera,
+                        aspect_ratio,
+                        z_near,
+                    )?;
+                }
+
+                self.pipelines.wait()?.sky.record(
+                    render_graph,
+                    framebuffer,
+                    depth_image,
+                    camera,
+                    projection_view,
+                    sky,
+                );
+                self.pipelines.wait()?.sky.record_god_rays(
+                    render_graph,
+                    framebuffer,
+                    depth_image,
+                    projection_view,
+                    sky,
+                );
+
+                if let Some(depth_of_field) = depth_of_field {
+                    self.pipelines.wait()?.depth_of_field.record(
+                        render_graph,
+                        &mut self.pool,
+                        framebuffer,
+                        depth_image,
+                        depth_of_field,
+                        z_near,
+                    )?;
+                }
+            }
+
+            let highlights = self
+                .model_instances
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, model_instance)| {
+                    model_instance
+                        .highlight
+                        .map(|color| Highlight::new(idx, color))
+                })
+                .collect::<Box<_>>();
+
+            if !highlights.is_empty() {
+                let id_image = render_graph.bind_node(self.pool.lease(ImageInfo::new_2d(
+                    vk::Format::R32_UINT,
+                    framebuffer_info.width,
+                    framebuffer_info.height,
+                    vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+                ))?);
+
+                // Only highlighted model instances are culled again, and drawn over the depth
+                // every mesh already wrote so only their visible surfaces write IDs
+                let draw_cmd_buf: AnyBufferNode = if mesh_tasks {
+                    self.record_mesh_task_cull(
+                        render_graph,
+                        layers,
+                        true,
+                        frustum_planes,
+                        draw_instance_buf,
+                        material_buf,
+                        mesh_buf,
+                        mesh_instance_buf,
+                        model_instance_buf,
+                    )?
+                    .into()
+                } else {
+                    self.record_mesh_cull(
+                        render_graph,
+                        layers,
+                        true,
+                        frustum_planes,
+                        draw_instance_buf,
+                        material_buf,
+                        mesh_buf,
+                        mesh_instance_buf,
+                        model_instance_buf,
+                    )?
+                    .into()
+                };
+
+                self.record_mesh_ids(
+                    render_graph,
+                    "Mesh highlight",
+                    camera_buf,
+                    id_image,
+                    depth_image,
+                    DepthStencilMode {
+                        compare_op: vk::CompareOp::GREATER_OR_EQUAL,
+                        depth_write: false,
+                        ..Self::DEPTH_STENCIL_MODE
+                    },
+                    draw_cmd_buf,
+                    geometry_buf,
+                    draw_instance_buf,
+                    mesh_instance_buf,
+                    mesh_buf,
+                    model_instance_buf,
+                    material_buf,
+                    textures,
+                )?;
+
+                self.pipelines.wait()?.outline.record(
+                    render_graph,
+                    &mut self.pool,
+                    framebuffer,
+                    id_image,
+                    &highlights,
+                )?;
+            }
         }
 
         Ok(())
     }
 
+    fn stream(&mut self, _: &mut FrameBudget) {
+        // Models are drawn as soon as their geometry is uploaded, so there is nothing to stream
+    }
+
     fn swap_remove_model_instance(&mut self, idx: usize) {
         self.mesh_instance_dirty = self.mesh_instance_dirty.min(idx);
 
-        // If the instance we are swapping was marked dirty we mark the new instance region as dirty
-        let last_idx = (self.model_instances.len() - 1) / Self::INSTANCE_GRANULARITY;
-        if self.model_instance_dirty[last_idx] {
-            let swapped_idx = idx / Self::INSTANCE_GRANULARITY;
-            self.model_instance_dirty[swapped_idx] = true;
-        }
+        // The last instance moves into the removed slot
+        self.model_instance_dirty.mark(idx);
 
         let removed_model_instance = self.model_instances.swap_remove(idx);
         let removed_mesh_count = self.model_mesh_count[removed_model_instance.model.model_idx];
@@ -925,7 +2212,108 @@ impl Technique for Raster {
             ..removed_model_instance.model.mesh_idx + removed_mesh_count as usize
         {
             self.mesh_instance_counts[idx] -= 1;
-            self.mesh_instance_count_dirty[idx / Self::INSTANCE_GRANULARITY] = true;
+            self.mesh_instance_count_dirty.mark(idx);
         }
     }
+
+    fn update_posed_model(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        geometry_buf: BufferNode,
+        model_idx: usize,
+    ) -> Result<(), DriverError> {
+        // Skinned meshes are culled using spheres around their current pose
+        let (first_mesh, geometries) = self.posed_models[&model_idx].clone();
+
+        self.record_bounding_spheres(render_graph, geometry_buf, first_mesh, &geometries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::render::tests::TestDevice};
+
+    #[test]
+    pub fn dirty_regions() {
+        let mut dirty = DirtyRegions::default();
+
+        dirty.mark(0);
+        dirty.mark(Raster::INSTANCE_GRANULARITY);
+        dirty.mark(Raster::INSTANCE_GRANULARITY * 3 + 1);
+
+        let len = Raster::INSTANCE_GRANULARITY * 3 + 10;
+
+        assert_eq!(
+            dirty.take(len),
+            [
+                0..Raster::INSTANCE_GRANULARITY * 2,
+                Raster::INSTANCE_GRANULARITY * 3..len
+            ]
+        );
+
+        // An idle scene uploads nothing
+        assert!(dirty.take(len).is_empty());
+
+        // Regions past the end, left by removed instances, are not uploaded
+        dirty.mark(len + Raster::INSTANCE_GRANULARITY);
+
+        assert!(dirty.take(len).is_empty());
+    }
+
+    #[test]
+    pub fn idle_scene_uploads_nothing() {
+        let device = TestDevice::new();
+        let mut raster = Raster::new(&device, ModelBufferInfo::default()).unwrap();
+
+        // A model of two meshes, loaded without recording its bounding spheres
+        raster.model_mesh_count.push(2);
+        raster.mesh_count = 2;
+        raster.mesh_instance_counts.extend([0, 0]);
+
+        raster.push_model_instance(ModelInstanceData {
+            highlight: None,
+            layers: RenderLayers::WORLD,
+            materials: [Zeroable::zeroed(); MAX_MATERIALS_PER_MODEL],
+            metalness_scale: 1.0,
+            model: Model {
+                mesh_idx: 0,
+                model_idx: 0,
+            },
+            roughness_scale: 1.0,
+            rotation: Quat::IDENTITY,
+            tint: Vec4::ONE,
+            translation: Vec3::ZERO,
+            visible: true,
+        });
+
+        // Returns the staging buffer each instance buffer was last written through
+        fn update(raster: &mut Raster) -> [usize; 3] {
+            let mut render_graph = RenderGraph::new();
+
+            raster.update_mesh_instance_buf(&mut render_graph).unwrap();
+            raster
+                .update_mesh_instance_count_buf(&mut render_graph)
+                .unwrap();
+            raster.update_model_instance_buf(&mut render_graph).unwrap();
+
+            [
+                raster.mesh_instance_staging.idx,
+                raster.mesh_instance_count_staging.idx,
+                raster.model_instance_staging.idx,
+            ]
+        }
+
+        let uploaded = update(&mut raster);
+
+        assert_eq!(uploaded, [1, 1, 1]);
+
+        // The staging rings only advance when a copy is recorded
+        assert_eq!(update(&mut raster), uploaded);
+        assert_eq!(update(&mut raster), uploaded);
+
+        // Moving an instance uploads the model instance alone
+        raster[0].translation = Vec3::X;
+
+        assert_eq!(update(&mut raster), [1, 1, 0]);
+    }
 }
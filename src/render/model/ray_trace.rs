@@ -1,16 +1,26 @@
 use {
     super::{
-        super::{camera::Camera, lease_storage_buffer},
+        super::{
+            camera::Camera,
+            debug::{capture::pass_name, DebugMode},
+            depth_of_field::DepthOfField,
+            frame_budget::FrameBudget,
+            lease_storage_buffer,
+            pending_pipelines::PendingPipelines,
+            sky::Sky,
+        },
+        blas::{build_ranges, geometry_info, BlasQueue},
         sbt::{ShaderBindingGroup, ShaderBindingTable},
-        Geometry, Material, Model, ModelBufferInfo, ModelInstanceData, Technique,
-        MAX_MATERIALS_PER_MODEL,
+        Geometry, Material, Model, ModelBufferInfo, ModelInstanceData, Pick, ReflectionProbeNodes,
+        RenderLayers, Technique, TextureFiltering, MAX_MATERIALS_PER_MODEL,
     },
     crate::res,
     anyhow::Context,
     bytemuck::{bytes_of, Pod, Zeroable},
-    glam::{Mat3, Mat4, Vec3, Vec4},
+    glam::{Mat3, Mat4, UVec2, Vec3, Vec4},
     screen_13::prelude::*,
     std::{
+        collections::HashMap,
         ops::{Index, IndexMut},
         sync::Arc,
     },
@@ -20,7 +30,10 @@ use {
 use super::super::{open_res_pak, read_blob};
 
 #[cfg(feature = "hot-shaders")]
-use {super::super::res_shader_dir, screen_13_hot::prelude::*};
+use {
+    super::super::{hot_shader::watch, res_shader_dir},
+    screen_13_hot::prelude::*,
+};
 
 fn material_index_array(
     materials: [Material; MAX_MATERIALS_PER_MODEL],
@@ -33,32 +46,67 @@ fn material_index_array(
     res
 }
 
+/// Returns a color packed as `unpackUnorm4x8` reads it, with an alpha of one.
+fn pack_unorm_rgb(color: Vec3) -> u32 {
+    let [r, g, b] = (color.clamp(Vec3::ZERO, Vec3::ONE) * 255.0)
+        .round()
+        .to_array();
+
+    u32::from_le_bytes([r as u8, g as u8, b as u8, 0xff])
+}
+
+/// Returns the rows of the object-to-world matrix of a model instance, which is the layout of
+/// both acceleration structure instances and [`Light::transform`].
+fn transform_rows(model_instance: &ModelInstanceData) -> [f32; 12] {
+    let mut res = [0.0; 12];
+    res.copy_from_slice(
+        &Mat4::from_rotation_translation(model_instance.rotation, model_instance.translation)
+            .transpose()
+            .to_cols_array()[0..12],
+    );
+
+    res
+}
+
+/// A mesh of a model instance which uses an emissive material; the ray gen shader samples a point
+/// on one light each frame and traces a shadow ray towards it.
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct Light {
+    transform: [f32; 12],
+    model_instance_index: u32,
+    mesh_index: u32,
+    triangle_count: u32,
+    _0: u32,
+}
+
 #[derive(Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
 struct ModelInstanceRef {
     material_indices: [u32; MAX_MATERIALS_PER_MODEL],
     mesh_index: u32,
+    _0: [u8; 12],
+    tint: Vec4,
+    roughness_scale: f32,
+    metalness_scale: f32,
+    _1: [u8; 8],
+    highlight: Vec4,
 }
 
+/// The ray trace pipeline and the shader binding table built for it.
 #[derive(Debug)]
-pub(super) struct RayTrace {
-    device: Arc<Device>,
-    frame_idx: u32,
-    model_blas: Vec<Arc<AccelerationStructure>>,
-    model_instances: Vec<ModelInstanceData>,
-
+struct Pipelines {
     #[cfg(not(feature = "hot-shaders"))]
     pipeline: Arc<RayTracePipeline>,
 
     #[cfg(feature = "hot-shaders")]
     pipeline: HotRayTracePipeline,
 
-    pool: LazyPool,
     sbt: ShaderBindingTable,
 }
 
-impl RayTrace {
-    pub fn new(device: &Arc<Device>, info: ModelBufferInfo) -> anyhow::Result<Self> {
+impl Pipelines {
+    fn new(device: &Arc<Device>, texture_filtering: TextureFiltering) -> anyhow::Result<Self> {
         #[cfg(not(feature = "hot-shaders"))]
         let mut res_pak = open_res_pak()?;
 
@@ -72,6 +120,7 @@ impl RayTrace {
             RayTraceShaderGroup::new_general(3),
         ];
         let pipeline_info = RayTracePipelineInfo::new().max_ray_recursion_depth(1);
+        let texture_sampler_infos = texture_filtering.sampler_infos(device);
 
         let gbuffer_rchit_specialization_info = SpecializationInfo::new(
             [vk::SpecializationMapEntry {
@@ -88,21 +137,27 @@ impl RayTrace {
                 &device,
                 pipeline_info,
                 [
-                    Shader::new_ray_gen(
-                        read_blob(
-                            &mut res_pak,
-                            res::SHADER_MODEL_RAY_TRACE_REFERENCE_RGEN_SPIRV,
-                        )?
-                        .as_slice(),
+                    texture_sampler_infos.into_iter().fold(
+                        Shader::new_ray_gen(
+                            read_blob(
+                                &mut res_pak,
+                                res::SHADER_MODEL_RAY_TRACE_REFERENCE_RGEN_SPIRV,
+                            )?
+                            .as_slice(),
+                        ),
+                        |shader, (binding, info)| shader.image_sampler(binding, info),
+                    ),
+                    texture_sampler_infos.into_iter().fold(
+                        Shader::new_closest_hit(
+                            read_blob(
+                                &mut res_pak,
+                                res::SHADER_MODEL_RAY_TRACE_GBUFFER_RCHIT_SPIRV,
+                            )?
+                            .as_slice(),
+                        )
+                        .specialization_info(gbuffer_rchit_specialization_info),
+                        |shader, (binding, info)| shader.image_sampler(binding, info),
                     ),
-                    Shader::new_closest_hit(
-                        read_blob(
-                            &mut res_pak,
-                            res::SHADER_MODEL_RAY_TRACE_GBUFFER_RCHIT_SPIRV,
-                        )?
-                        .as_slice(),
-                    )
-                    .specialization_info(gbuffer_rchit_specialization_info),
                     Shader::new_miss(
                         read_blob(
                             &mut res_pak,
@@ -125,11 +180,17 @@ impl RayTrace {
             &device,
             pipeline_info,
             [
-                HotShader::new_ray_gen(shader_dir.join("reference.rgen")),
-                HotShader::new_closest_hit(shader_dir.join("gbuffer.rchit"))
-                    .specialization_info(gbuffer_rchit_specialization_info),
-                HotShader::new_miss(shader_dir.join("gbuffer.rmiss")),
-                HotShader::new_miss(shader_dir.join("shadow.rmiss")),
+                texture_sampler_infos.into_iter().fold(
+                    HotShader::new_ray_gen(watch(shader_dir.join("reference.rgen"))),
+                    |shader, (binding, info)| shader.image_sampler(binding, info),
+                ),
+                texture_sampler_infos.into_iter().fold(
+                    HotShader::new_closest_hit(watch(shader_dir.join("gbuffer.rchit")))
+                        .specialization_info(gbuffer_rchit_specialization_info),
+                    |shader, (binding, info)| shader.image_sampler(binding, info),
+                ),
+                HotShader::new_miss(watch(shader_dir.join("gbuffer.rmiss"))),
+                HotShader::new_miss(watch(shader_dir.join("shadow.rmiss"))),
             ],
             shader_groups,
         )
@@ -142,106 +203,68 @@ impl RayTrace {
             #[cfg(feature = "hot-shaders")]
             let pipeline = pipeline.cold();
 
-            Self::build_sbt(device, pipeline)?
+            RayTrace::build_sbt(device, pipeline)?
         };
 
+        Ok(Self { pipeline, sbt })
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct RayTrace {
+    blas_queue: BlasQueue,
+    device: Arc<Device>,
+    frame_idx: u32,
+    geometry_address: vk::DeviceAddress,
+
+    /// The acceleration structure of each loaded model, once its worker thread build finished;
+    /// instances of models without one are not drawn yet.
+    model_blas: Vec<Option<Arc<AccelerationStructure>>>,
+
+    model_instances: Vec<ModelInstanceData>,
+
+    /// The material and triangle count of each mesh of each loaded model, used to find lights.
+    model_meshes: Vec<Box<[(u8, u32)]>>,
+
+    pipelines: PendingPipelines<Pipelines>,
+    pool: LazyPool,
+
+    /// The layout of each posed model, by model index, whose acceleration structure is built the
+    /// first time it is skinned and refit each time after.
+    posed_models: HashMap<usize, AccelerationStructureGeometryInfo>,
+}
+
+impl RayTrace {
+    pub fn new(
+        device: &Arc<Device>,
+        info: ModelBufferInfo,
+        geometry_buf: &Arc<Buffer>,
+    ) -> anyhow::Result<Self> {
+        let blas_queue = BlasQueue::spawn(device, geometry_buf);
+        let geometry_address = Buffer::device_address(geometry_buf);
+        let pipelines = {
+            let device = Arc::clone(device);
+            let texture_filtering = info.texture_filtering;
+
+            PendingPipelines::spawn(move || Pipelines::new(&device, texture_filtering))
+        };
         let pool = LazyPool::new(device);
         let device = Arc::clone(device);
 
         Ok(Self {
+            blas_queue,
             device,
             frame_idx: 0,
+            geometry_address,
             model_blas: Default::default(),
             model_instances: Default::default(),
-            pipeline,
+            model_meshes: Default::default(),
+            pipelines,
             pool,
-            sbt,
+            posed_models: Default::default(),
         })
     }
 
-    fn build_blas(
-        &mut self,
-        render_graph: &mut RenderGraph,
-        geometry_buf: BufferNode,
-        geometries: &[Geometry],
-    ) -> Result<AccelerationStructureNode, DriverError> {
-        let geometry_address = render_graph.node_device_address(geometry_buf);
-        let geometries = geometries
-            .iter()
-            .map(|geom| AccelerationStructureGeometry {
-                max_primitive_count: geom.index_count / 3,
-                flags: vk::GeometryFlagsKHR::OPAQUE,
-                geometry: AccelerationStructureGeometryData::Triangles {
-                    index_data: DeviceOrHostAddress::DeviceAddress(
-                        geometry_address + geom.index_offset,
-                    ),
-                    index_type: geom.flags.index_ty(),
-                    max_vertex: geom.index_count,
-                    transform_data: None,
-                    vertex_data: DeviceOrHostAddress::DeviceAddress(
-                        geometry_address + geom.vertex_offset,
-                    ),
-                    vertex_format: vk::Format::R32G32B32_SFLOAT,
-                    vertex_stride: geom.flags.vertex_stride(),
-                },
-            })
-            .collect();
-
-        let geometry_info = AccelerationStructureGeometryInfo {
-            ty: vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL,
-            flags: vk::BuildAccelerationStructureFlagsKHR::empty(),
-            geometries,
-        };
-        let blas_size = AccelerationStructure::size_of(&self.device, &geometry_info);
-        let blas = render_graph.bind_node(AccelerationStructure::create(
-            &self.device,
-            AccelerationStructureInfo {
-                ty: vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL,
-                size: blas_size.create_size,
-            },
-        )?);
-
-        let accel_struct_scratch_offset_alignment =
-            self.device
-                .physical_device
-                .accel_struct_properties
-                .as_ref()
-                .unwrap()
-                .min_accel_struct_scratch_offset_alignment as vk::DeviceSize;
-        let scratch_buf = render_graph.bind_node(
-            self.pool.lease(
-                BufferInfo::new(
-                    blas_size.build_size,
-                    vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS
-                        | vk::BufferUsageFlags::STORAGE_BUFFER,
-                )
-                .alignment(accel_struct_scratch_offset_alignment),
-            )?,
-        );
-
-        render_graph
-            .begin_pass("Build BLAS")
-            .access_node(geometry_buf, AccessType::AccelerationStructureBuildRead)
-            .access_node(scratch_buf, AccessType::AccelerationStructureBufferWrite)
-            .access_node(blas, AccessType::AccelerationStructureBuildWrite)
-            .record_acceleration(move |accel, _| {
-                let build_ranges = geometry_info
-                    .geometries
-                    .iter()
-                    .map(|geometry| vk::AccelerationStructureBuildRangeInfoKHR {
-                        first_vertex: 0,
-                        primitive_count: geometry.max_primitive_count,
-                        primitive_offset: 0,
-                        transform_offset: 0,
-                    })
-                    .collect::<Box<_>>();
-
-                accel.build_structure(blas, scratch_buf, &geometry_info, &build_ranges);
-            });
-
-        Ok(blas)
-    }
-
     fn build_tlas(
         &mut self,
         render_graph: &mut RenderGraph,
@@ -250,24 +273,17 @@ impl RayTrace {
             .model_instances
             .iter()
             .enumerate()
-            .map(|(model_instance_index, model_instance_data)| {
+            .filter_map(|(model_instance_index, model_instance_data)| {
                 let Model { model_idx, .. } = model_instance_data.model;
-                let blas = &self.model_blas[model_idx];
-                let mut matrix = [0.0; 12];
-                matrix.copy_from_slice(
-                    &Mat4::from_rotation_translation(
-                        model_instance_data.rotation,
-                        model_instance_data.translation,
-                    )
-                    .transpose()
-                    .to_cols_array()[0..12],
-                );
+                let blas = self.model_blas[model_idx].as_ref()?;
 
-                vk::AccelerationStructureInstanceKHR {
-                    transform: vk::TransformMatrixKHR { matrix },
+                Some(vk::AccelerationStructureInstanceKHR {
+                    transform: vk::TransformMatrixKHR {
+                        matrix: transform_rows(model_instance_data),
+                    },
                     instance_custom_index_and_mask: vk::Packed24_8::new(
                         model_instance_index as _,
-                        0xff,
+                        model_instance_data.layer_mask() as _,
                     ),
                     instance_shader_binding_table_record_offset_and_flags: vk::Packed24_8::new(
                         0,
@@ -276,7 +292,7 @@ impl RayTrace {
                     acceleration_structure_reference: vk::AccelerationStructureReferenceKHR {
                         device_handle: AccelerationStructure::device_address(blas),
                     },
-                }
+                })
             })
             .collect::<Box<_>>();
         let instance_count = instances.len() as _;
@@ -328,9 +344,9 @@ impl RayTrace {
         );
         let tlas = render_graph.bind_node(tlas);
 
-        let mut pass = render_graph.begin_pass("Build TLAS");
+        let mut pass = render_graph.begin_pass(pass_name("Build TLAS"));
 
-        for blas in &self.model_blas {
+        for blas in self.model_blas.iter().flatten() {
             let blas = pass.bind_node(blas);
             pass.access_node_mut(blas, AccessType::AccelerationStructureBuildRead);
         }
@@ -355,15 +371,61 @@ impl RayTrace {
         Ok(tlas)
     }
 
+    /// Returns the material and triangle count of each mesh of a model.
+    fn model_meshes(geometries: &[Geometry]) -> Box<[(u8, u32)]> {
+        geometries
+            .iter()
+            .map(|geometry| (geometry.material, geometry.index_count / 3))
+            .collect()
+    }
+
+    /// Returns each mesh which emits light from the model instances of the given layers.
+    fn lights(&self, layers: RenderLayers) -> Vec<Light> {
+        let mut res = vec![];
+
+        for (model_instance_index, model_instance) in self.model_instances.iter().enumerate() {
+            if model_instance.layer_mask() & layers.bits() as u32 == 0 {
+                continue;
+            }
+
+            let Model {
+                mesh_idx,
+                model_idx,
+            } = model_instance.model;
+
+            // Shadow rays cannot hit models which have not been built yet
+            if self.model_blas[model_idx].is_none() {
+                continue;
+            }
+
+            for (mesh_offset, &(material, triangle_count)) in
+                self.model_meshes[model_idx].iter().enumerate()
+            {
+                if model_instance.materials[material as usize].is_emissive() {
+                    res.push(Light {
+                        transform: transform_rows(model_instance),
+                        model_instance_index: model_instance_index as _,
+                        mesh_index: (mesh_idx + mesh_offset) as _,
+                        triangle_count,
+                        _0: Default::default(),
+                    });
+                }
+            }
+        }
+
+        res
+    }
+
     fn build_sbt(
         device: &Arc<Device>,
         pipeline: &Arc<RayTracePipeline>,
     ) -> Result<ShaderBindingTable, DriverError> {
+        // Miss groups are the primary ray (sky) followed by the shadow ray
         ShaderBindingTable::new(
             device,
             pipeline,
             ShaderBindingGroup::new(1, 1),
-            ShaderBindingGroup::new(2, 1),
+            ShaderBindingGroup::new(2, 2),
             None,
         )
     }
@@ -386,18 +448,47 @@ impl IndexMut<usize> for RayTrace {
 impl Technique for RayTrace {
     fn load_model(
         &mut self,
-        render_graph: &mut RenderGraph,
-        geometry_buf: BufferNode,
+        _: &mut RenderGraph,
+        _: BufferNode,
         geometries: &[Geometry],
     ) -> Result<(), DriverError> {
-        let blas = self.build_blas(render_graph, geometry_buf, geometries)?;
-        let blas = render_graph.unbind_node(blas);
+        self.blas_queue.push(self.model_blas.len(), geometries);
+        self.model_blas.push(None);
+        self.model_meshes.push(Self::model_meshes(geometries));
 
-        self.model_blas.push(blas);
+        Ok(())
+    }
+
+    fn load_posed_model(
+        &mut self,
+        _: &mut RenderGraph,
+        _: BufferNode,
+        geometries: &[Geometry],
+    ) -> Result<(), DriverError> {
+        // Posed models are refit rather than compacted, because their vertices move every frame
+        self.posed_models.insert(
+            self.model_blas.len(),
+            geometry_info(
+                self.geometry_address,
+                geometries,
+                vk::BuildAccelerationStructureFlagsKHR::ALLOW_UPDATE
+                    | vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_BUILD,
+            ),
+        );
+        self.model_blas.push(None);
+        self.model_meshes.push(Self::model_meshes(geometries));
 
         Ok(())
     }
 
+    fn is_ready(&self) -> bool {
+        self.pipelines.is_ready()
+    }
+
+    fn loaded_model_count(&self) -> usize {
+        self.blas_queue.built_count() + self.posed_models.len()
+    }
+
     fn push_model_instance(&mut self, model_instance: ModelInstanceData) {
         self.model_instances.push(model_instance);
     }
@@ -407,11 +498,23 @@ impl Technique for RayTrace {
         render_graph: &mut RenderGraph,
         framebuffer: AnyImageNode,
         camera: &mut Camera,
+        debug_mode: DebugMode,
+        _depth_of_field: Option<DepthOfField>,
+        _depth_prepass: bool,
+        layers: RenderLayers,
         geometry_buf: BufferNode,
         material_buf: BufferNode,
         mesh_buf: BufferNode,
-        textures: &[Arc<Image>],
+        _reflection_probes: ReflectionProbeNodes,
+        sky: Sky,
+        textures: &[ImageNode],
+        time: f32,
+        pick: Option<Pick>,
     ) -> Result<(), DriverError> {
+        for (model_idx, blas) in self.blas_queue.finished()? {
+            self.model_blas[model_idx] = Some(blas);
+        }
+
         // TODO: Rebuild these two only when needed
         let tlas = self.build_tlas(render_graph)?;
         let model_instances_buf = render_graph.bind_node(lease_storage_buffer(
@@ -422,32 +525,61 @@ impl Technique for RayTrace {
                 .map(|model_instance| ModelInstanceRef {
                     material_indices: material_index_array(model_instance.materials),
                     mesh_index: model_instance.model.mesh_idx as _,
+                    _0: Default::default(),
+                    tint: model_instance.tint,
+                    roughness_scale: model_instance.roughness_scale,
+                    metalness_scale: model_instance.metalness_scale,
+                    _1: Default::default(),
+                    highlight: model_instance.highlight.unwrap_or_default(),
                 })
                 .collect::<Box<_>>(),
         )?);
 
+        // Every descriptor must be bound, so a level without lights binds one which is never read
+        let lights = self.lights(layers);
+        let light_count = lights.len() as u32;
+        let light_buf = render_graph.bind_node(if lights.is_empty() {
+            lease_storage_buffer(&mut self.pool, &[Light::zeroed()])?
+        } else {
+            lease_storage_buffer(&mut self.pool, &lights)?
+        });
+
+        // Without picking the primary rays write to an unused buffer, because every descriptor
+        // must be bound
+        let (pick_buf, pick_position) = match pick {
+            Some(Pick { buf, position }) => (AnyBufferNode::from(buf), position),
+            None => (
+                render_graph
+                    .bind_node(lease_storage_buffer(&mut self.pool, &[0u32])?)
+                    .into(),
+                UVec2::splat(u32::MAX),
+            ),
+        };
+
+        let pipelines = self.pipelines.wait()?;
+
         #[cfg(not(feature = "hot-shaders"))]
-        let pipeline = &self.pipeline;
+        let pipeline = &pipelines.pipeline;
 
         #[cfg(feature = "hot-shaders")]
-        let pipeline = self.pipeline.hot();
+        let pipeline = pipelines.pipeline.hot();
 
         #[cfg(feature = "hot-shaders")]
         // Shader binding table becomes invalid if the pipeline is recompiled
-        if !self.sbt.is_valid(pipeline) {
-            self.sbt = Self::build_sbt(&self.device, pipeline)?;
+        if !pipelines.sbt.is_valid(pipeline) {
+            pipelines.sbt = Self::build_sbt(&self.device, pipeline)?;
         }
 
-        let sbt = render_graph.bind_node(&self.sbt.buffer);
+        let sbt = render_graph.bind_node(&pipelines.sbt.buffer);
         let (
             raygen_shader_binding_tables,
             hit_shader_binding_tables,
             miss_shader_binding_tables,
             callable_shader_binding_tables,
-        ) = self.sbt.regions();
+        ) = pipelines.sbt.regions();
 
         let mut pass = render_graph
-            .begin_pass("Reference path trace")
+            .begin_pass(pass_name("Reference path trace"))
             .bind_pipeline(pipeline)
             .access_node(sbt, AccessType::RayTracingShaderReadOther)
             .write_descriptor(0, framebuffer)
@@ -463,10 +595,11 @@ impl Technique for RayTrace {
                 6,
                 model_instances_buf,
                 AccessType::RayTracingShaderReadOther,
-            );
+            )
+            .access_descriptor(8, pick_buf, AccessType::AnyShaderWrite)
+            .access_descriptor(9, light_buf, AccessType::RayTracingShaderReadOther);
 
-        for (idx, texture) in textures.iter().enumerate() {
-            let texture = pass.bind_node(texture);
+        for (idx, texture) in textures.iter().copied().enumerate() {
             pass = pass.read_descriptor((7, [idx as u32]), texture);
         }
 
@@ -487,16 +620,36 @@ impl Technique for RayTrace {
             aspect_ratio: f32,
             fov_y: f32, // in radians
             frame_index: u32,
-            _0: [u8; 8],
+            debug_mode: u32,
+            time: f32,
+            sun_direction: Vec3,
+            sky_turbidity: f32,
+            pick_position: UVec2,
+            layer_mask: u32,
+            light_count: u32,
+            fog_color: u32,
+            fog_density: f32,
+            fog_height: f32,
+            fog_height_falloff: f32,
         }
 
         let push_consts = PushConstants {
             aspect_ratio: camera.aspect_ratio,
             fov_y: camera.fov_y.to_radians(),
             frame_index: self.frame_idx,
+            debug_mode: debug_mode as _,
+            fog_color: pack_unorm_rgb(sky.fog_color),
+            fog_density: sky.fog_density,
+            fog_height: sky.fog_height,
+            fog_height_falloff: sky.fog_height_falloff,
+            layer_mask: layers.bits() as _,
+            light_count,
+            pick_position,
+            sky_turbidity: sky.turbidity,
+            sun_direction: sky.sun_direction(),
             view_position: camera.position,
+            time,
             view,
-            _0: Default::default(),
         };
         let ImageInfo { width, height, .. } = pass.node_info(framebuffer);
 
@@ -517,7 +670,86 @@ impl Technique for RayTrace {
         Ok(())
     }
 
+    fn stream(&mut self, budget: &mut FrameBudget) {
+        self.blas_queue.release(budget);
+    }
+
     fn swap_remove_model_instance(&mut self, idx: usize) {
         self.model_instances.swap_remove(idx);
     }
+
+    fn update_posed_model(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        geometry_buf: BufferNode,
+        model_idx: usize,
+    ) -> Result<(), DriverError> {
+        let geometry_info = self.posed_models[&model_idx].clone();
+        let size = AccelerationStructure::size_of(&self.device, &geometry_info);
+        let (blas, is_refit) = match &self.model_blas[model_idx] {
+            Some(blas) => (Arc::clone(blas), true),
+            None => (
+                Arc::new(AccelerationStructure::create(
+                    &self.device,
+                    AccelerationStructureInfo {
+                        ty: vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL,
+                        size: size.create_size,
+                    },
+                )?),
+                false,
+            ),
+        };
+
+        let accel_struct_scratch_offset_alignment =
+            self.device
+                .physical_device
+                .accel_struct_properties
+                .as_ref()
+                .unwrap()
+                .min_accel_struct_scratch_offset_alignment as vk::DeviceSize;
+        let scratch_buf = render_graph.bind_node(
+            self.pool.lease(
+                BufferInfo::new(
+                    if is_refit {
+                        size.update_size
+                    } else {
+                        size.build_size
+                    },
+                    vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS
+                        | vk::BufferUsageFlags::STORAGE_BUFFER,
+                )
+                .alignment(accel_struct_scratch_offset_alignment),
+            )?,
+        );
+        let blas_node = render_graph.bind_node(&blas);
+
+        render_graph
+            .begin_pass(pass_name(if is_refit {
+                "Refit posed BLAS"
+            } else {
+                "Build posed BLAS"
+            }))
+            .access_node(geometry_buf, AccessType::AccelerationStructureBuildRead)
+            .access_node(scratch_buf, AccessType::AccelerationStructureBufferWrite)
+            .access_node(blas_node, AccessType::AccelerationStructureBuildWrite)
+            .record_acceleration(move |accel, _| {
+                let build_ranges = build_ranges(&geometry_info);
+
+                if is_refit {
+                    accel.update_structure(
+                        blas_node,
+                        blas_node,
+                        scratch_buf,
+                        &geometry_info,
+                        &build_ranges,
+                    );
+                } else {
+                    accel.build_structure(blas_node, scratch_buf, &geometry_info, &build_ranges);
+                }
+            });
+
+        self.model_blas[model_idx] = Some(blas);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,249 @@
+use {
+    super::super::{camera::Camera, lease_storage_buffer},
+    bytemuck::{Pod, Zeroable},
+    glam::Vec3,
+    screen_13::prelude::*,
+    std::sync::Arc,
+};
+
+/// Yaw and pitch, in degrees, of the camera used to capture each cube face in the order +X, -X,
+/// +Y, -Y, +Z, -Z.
+///
+/// Captured images are flipped horizontally when copied into the cube array because cube faces
+/// are addressed as if seen from outside of the cube.
+const FACE_YAW_PITCH: [(f32, f32); 6] = [
+    (-90.0, 0.0),
+    (90.0, 0.0),
+    (180.0, 90.0),
+    (180.0, -90.0),
+    (180.0, 0.0),
+    (0.0, 0.0),
+];
+
+/// A point from which the surrounding scene is captured into a cube map, used for reflections of
+/// surfaces within `radius`.
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct ReflectionProbe {
+    pub position: Vec3,
+    pub radius: f32,
+}
+
+impl ReflectionProbe {
+    pub const DEFAULT_RADIUS: f32 = 10.0;
+}
+
+/// Render graph nodes used by techniques to sample reflection probes.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct ReflectionProbeNodes {
+    pub buf: BufferLeaseNode,
+    pub count: u32,
+    pub image: ImageNode,
+}
+
+/// Reflection probes which are captured at runtime, the first time the model buffer is recorded
+/// after they are inserted, and stored as one cube array.
+#[derive(Debug)]
+pub(super) struct ReflectionProbes {
+    capacity: usize,
+    captured_count: usize,
+    image: Arc<Image>,
+    probes: Vec<ReflectionProbe>,
+}
+
+impl ReflectionProbes {
+    /// Width and height of each cube face.
+    const FACE_SIZE: u32 = 128;
+
+    const FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+    pub fn new(
+        device: &Arc<Device>,
+        pool: &mut LazyPool,
+        capacity: usize,
+    ) -> Result<Self, DriverError> {
+        // The cube array always has one cube so that it may be bound when there are no probes
+        let capacity = capacity.max(1);
+        let image = Arc::new(Image::create(
+            device,
+            ImageInfo::new_cube(
+                Self::FORMAT,
+                Self::FACE_SIZE,
+                vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+            )
+            .ty(ImageType::CubeArray)
+            .array_elements(6 * capacity as u32),
+        )?);
+
+        let mut render_graph = RenderGraph::new();
+        let image_node = render_graph.bind_node(&image);
+        render_graph.clear_color_image(image_node);
+        render_graph.resolve().submit(pool, 0, 0)?;
+
+        Ok(Self {
+            capacity,
+            captured_count: 0,
+            image,
+            probes: Default::default(),
+        })
+    }
+
+    pub fn bind(
+        &self,
+        render_graph: &mut RenderGraph,
+        pool: &mut LazyPool,
+    ) -> Result<ReflectionProbeNodes, DriverError> {
+        // Storage buffers may not be empty
+        let buf = if self.probes.is_empty() {
+            lease_storage_buffer(pool, &[ReflectionProbe::zeroed()])?
+        } else {
+            lease_storage_buffer(pool, &self.probes)?
+        };
+
+        Ok(ReflectionProbeNodes {
+            buf: render_graph.bind_node(buf),
+            count: self.probes.len() as _,
+            image: render_graph.bind_node(&self.image),
+        })
+    }
+
+    /// Copies a captured face image into the given cube array layer.
+    pub fn copy_face(
+        render_graph: &mut RenderGraph,
+        face_image: impl Into<AnyImageNode>,
+        nodes: ReflectionProbeNodes,
+        layer: u32,
+    ) {
+        let size = Self::FACE_SIZE as i32;
+
+        render_graph.blit_image_region(
+            face_image,
+            nodes.image,
+            vk::Filter::NEAREST,
+            vk::ImageBlit {
+                src_subresource: vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+                src_offsets: [
+                    vk::Offset3D {
+                        x: size,
+                        y: 0,
+                        z: 0,
+                    },
+                    vk::Offset3D {
+                        x: 0,
+                        y: size,
+                        z: 1,
+                    },
+                ],
+                dst_subresource: vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: layer,
+                    layer_count: 1,
+                },
+                dst_offsets: [
+                    vk::Offset3D { x: 0, y: 0, z: 0 },
+                    vk::Offset3D {
+                        x: size,
+                        y: size,
+                        z: 1,
+                    },
+                ],
+            },
+        );
+    }
+
+    /// Returns information for leasing an image which one cube face may be captured into, using
+    /// either technique.
+    pub fn face_image_info() -> ImageInfoBuilder {
+        ImageInfo::new_2d(
+            Self::FORMAT,
+            Self::FACE_SIZE,
+            Self::FACE_SIZE,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::STORAGE
+                | vk::ImageUsageFlags::TRANSFER_SRC,
+        )
+    }
+
+    /// Returns `false` if the cube array is full.
+    pub fn insert(&mut self, probe: ReflectionProbe) -> bool {
+        if self.probes.len() == self.capacity {
+            return false;
+        }
+
+        self.probes.push(probe);
+
+        true
+    }
+
+    /// Considers every probe uncaptured, so they are all captured again the next time the model
+    /// buffer is recorded.
+    pub fn recapture(&mut self) {
+        self.captured_count = 0;
+    }
+
+    /// Returns the cube array layer and camera of each face which has not yet been captured and
+    /// then considers those faces captured.
+    pub fn take_uncaptured_faces(&mut self) -> Vec<(u32, Camera)> {
+        let faces = self.probes[self.captured_count..]
+            .iter()
+            .zip(self.captured_count..)
+            .flat_map(|(probe, probe_index)| {
+                FACE_YAW_PITCH
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .map(move |(face, (yaw, pitch))| {
+                        (
+                            (6 * probe_index + face) as u32,
+                            Camera {
+                                aspect_ratio: 1.0,
+                                fov_y: 90.0,
+                                pitch,
+                                yaw,
+                                position: probe.position,
+                            },
+                        )
+                    })
+            })
+            .collect();
+
+        self.captured_count = self.probes.len();
+
+        faces
+    }
+
+    /// Sampler information used for the cube array.
+    pub fn sampler_info() -> SamplerInfo {
+        SamplerInfo::new()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, glam::Quat};
+
+    #[test]
+    pub fn face_directions() {
+        let directions = [Vec3::X, -Vec3::X, Vec3::Y, -Vec3::Y, Vec3::Z, -Vec3::Z];
+
+        for ((yaw, pitch), direction) in FACE_YAW_PITCH.into_iter().zip(directions) {
+            let rotation =
+                Quat::from_rotation_y(yaw.to_radians()) * Quat::from_rotation_x(pitch.to_radians());
+
+            // Cameras look along -Z
+            assert!(rotation.mul_vec3(-Vec3::Z).abs_diff_eq(direction, 1e-5));
+        }
+    }
+}
@@ -69,10 +69,10 @@ impl ShaderBindingTable {
 
         Self::copy_group_handle(pipeline, data, 0, group_size)?;
 
-        let hit_offset = Self::copy_group_handle(pipeline, data, hit.group_index, group_size)?;
-        let miss_offset = Self::copy_group_handle(pipeline, data, miss.group_index, group_size)?;
+        let hit_offset = Self::copy_group_handles(pipeline, data, hit, group_size)?;
+        let miss_offset = Self::copy_group_handles(pipeline, data, miss, group_size)?;
         let callable_offset = callable
-            .map(|group| Self::copy_group_handle(pipeline, data, group.group_index, group_size))
+            .map(|group| Self::copy_group_handles(pipeline, data, group, group_size))
             .transpose()?
             .unwrap_or_default();
 
@@ -85,12 +85,12 @@ impl ShaderBindingTable {
             size: ray_gen_size as _,
         };
         let hit = vk::StridedDeviceAddressRegionKHR {
-            device_address: device_address + ray_gen_size as vk::DeviceAddress,
+            device_address: device_address + hit_offset,
             stride: handle_size as _,
             size: hit_size as _,
         };
         let miss = vk::StridedDeviceAddressRegionKHR {
-            device_address: device_address + hit_size as vk::DeviceAddress,
+            device_address: device_address + miss_offset,
             stride: group_size as _,
             size: miss_size as _,
         };
@@ -129,6 +129,23 @@ impl ShaderBindingTable {
         Ok(start as _)
     }
 
+    /// Copies the handle of each shader group of a binding group, which are consecutive in both
+    /// the pipeline and the table, and returns the offset of the first.
+    fn copy_group_handles(
+        pipeline: &RayTracePipeline,
+        data: &mut [u8],
+        group: ShaderBindingGroup,
+        group_size: u32,
+    ) -> Result<vk::DeviceAddress, DriverError> {
+        let offset = Self::copy_group_handle(pipeline, data, group.group_index, group_size)?;
+
+        for group_index in group.group_index + 1..group.group_index + group.shader_count as usize {
+            Self::copy_group_handle(pipeline, data, group_index, group_size)?;
+        }
+
+        Ok(offset)
+    }
+
     #[allow(unused)]
     pub fn is_valid(&self, pipeline: &Arc<RayTracePipeline>) -> bool {
         Arc::ptr_eq(&self._pipeline, pipeline)
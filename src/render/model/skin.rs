@@ -0,0 +1,306 @@
+//! Poses skinned models on the GPU before either technique records them.
+//!
+//! Each model instance of a skinned model is drawn from a posed model of its own, which shares the
+//! indices of the skinned model but has a copy of its vertices. A compute pass writes the posed
+//! vertices from the bind pose vertices and the joint matrices of the instance whenever they
+//! change, so the raster technique fetches posed vertices like any others and the ray trace
+//! technique refits the acceleration structure of the posed model.
+
+use {
+    super::{
+        super::{debug::capture::pass_name, lease_storage_buffer},
+        Model, ModelInstance,
+    },
+    crate::res,
+    anyhow::Context,
+    bytemuck::{bytes_of, Pod, Zeroable},
+    glam::Mat4,
+    screen_13::prelude::*,
+    std::{collections::HashMap, mem::take, sync::Arc},
+};
+
+#[cfg(not(feature = "hot-shaders"))]
+use super::super::{open_res_pak, read_blob};
+
+#[cfg(feature = "hot-shaders")]
+use {
+    super::super::{hot_shader::watch, res_shader_dir},
+    screen_13_hot::prelude::*,
+};
+
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct PushConstants {
+    src_offset: u32,
+    dst_offset: u32,
+    vertex_count: u32,
+    vertex_stride: u32,
+    joint_offset: u32,
+    joint_count: u32,
+}
+
+/// The copy of a skinned model which one model instance is drawn from.
+#[derive(Debug)]
+pub struct PosedModel {
+    /// The skinned model this is a copy of.
+    pub bind_model: Model,
+
+    /// Set when the posed vertices must be written again.
+    dirty: bool,
+
+    joint_matrices: Vec<Mat4>,
+
+    /// The meshes of the posed model whose vertices are skinned.
+    meshes: Box<[SkinnedMesh]>,
+
+    pub model: Model,
+}
+
+impl PosedModel {
+    pub fn new(bind_model: Model, model: Model, meshes: Box<[SkinnedMesh]>) -> Self {
+        Self {
+            bind_model,
+            dirty: true,
+            joint_matrices: vec![],
+            meshes,
+            model,
+        }
+    }
+}
+
+/// Where the vertices of one skinned mesh are read from and written to, in floats from the start of
+/// the geometry buffer.
+#[derive(Clone, Copy, Debug)]
+pub struct SkinnedMesh {
+    pub src_offset: u32,
+    pub dst_offset: u32,
+    pub vertex_count: u32,
+    pub vertex_stride: u32,
+}
+
+#[derive(Debug)]
+pub struct Skinning {
+    #[cfg(not(feature = "hot-shaders"))]
+    pipeline: Arc<ComputePipeline>,
+
+    #[cfg(feature = "hot-shaders")]
+    pipeline: HotComputePipeline,
+
+    posed_models: HashMap<ModelInstance, PosedModel>,
+
+    /// Posed models of removed model instances, by the model they copy, which new instances of
+    /// that model reuse so that inserting and removing instances does not use up geometry capacity.
+    spare_models: HashMap<Model, Vec<PosedModel>>,
+}
+
+impl Skinning {
+    const WORKGROUP_SIZE: u32 = 64;
+
+    #[cfg(not(feature = "hot-shaders"))]
+    pub fn new(device: &Arc<Device>) -> anyhow::Result<Self> {
+        let mut res_pak = open_res_pak()?;
+        let pipeline = Arc::new(
+            ComputePipeline::create(
+                device,
+                ComputePipelineInfo::default(),
+                Shader::new_compute(
+                    read_blob(&mut res_pak, res::SHADER_COMPUTE_SKIN_COMP_SPIRV)?.as_slice(),
+                ),
+            )
+            .context("Creating skinning pipeline")?,
+        );
+
+        Ok(Self {
+            pipeline,
+            posed_models: Default::default(),
+            spare_models: Default::default(),
+        })
+    }
+
+    #[cfg(feature = "hot-shaders")]
+    pub fn new(device: &Arc<Device>) -> anyhow::Result<Self> {
+        let pipeline = HotComputePipeline::create(
+            device,
+            ComputePipelineInfo::default(),
+            HotShader::new_compute(watch(res_shader_dir().join("compute/skin.comp"))),
+        )
+        .context("Creating hot skinning pipeline")?;
+
+        Ok(Self {
+            pipeline,
+            posed_models: Default::default(),
+            spare_models: Default::default(),
+        })
+    }
+
+    pub fn insert(&mut self, model_instance: ModelInstance, posed_model: PosedModel) {
+        self.posed_models.insert(model_instance, posed_model);
+    }
+
+    /// Returns `true` if the given model is the posed model of a model instance, or a spare one.
+    pub fn is_posed(&self, model_idx: usize) -> bool {
+        self.posed_models
+            .values()
+            .chain(self.spare_models.values().flatten())
+            .any(|posed_model| posed_model.model.model_idx == model_idx)
+    }
+
+    /// Marks every posed model to be written again, such as after the technique changes.
+    pub fn mark_all_dirty(&mut self) {
+        for posed_model in self.posed_models.values_mut() {
+            posed_model.dirty = true;
+        }
+    }
+
+    #[inline(always)]
+    fn pipeline(&mut self) -> &Arc<ComputePipeline> {
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.pipeline;
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.pipeline.hot();
+
+        res
+    }
+
+    /// Writes the vertices of each posed model whose joint matrices changed since the previous
+    /// call, and returns the index of each of those models.
+    pub fn record(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        pool: &mut LazyPool,
+        geometry_buf: BufferNode,
+    ) -> Result<Vec<usize>, DriverError> {
+        let mut joint_matrices = vec![];
+        let mut dispatches = vec![];
+        let mut model_indices = vec![];
+
+        for posed_model in self.posed_models.values_mut() {
+            if !take(&mut posed_model.dirty) {
+                continue;
+            }
+
+            let joint_offset = joint_matrices.len() as u32;
+            let joint_count = posed_model.joint_matrices.len() as u32;
+            joint_matrices.extend_from_slice(&posed_model.joint_matrices);
+
+            for mesh in posed_model.meshes.iter() {
+                dispatches.push(PushConstants {
+                    src_offset: mesh.src_offset,
+                    dst_offset: mesh.dst_offset,
+                    vertex_count: mesh.vertex_count,
+                    vertex_stride: mesh.vertex_stride,
+                    joint_offset,
+                    joint_count,
+                });
+            }
+
+            model_indices.push(posed_model.model.model_idx);
+        }
+
+        self.record_dispatches(
+            render_graph,
+            pool,
+            geometry_buf,
+            &joint_matrices,
+            &dispatches,
+        )?;
+
+        Ok(model_indices)
+    }
+
+    /// Writes the bind pose vertices of a new posed model, which the technique then loads.
+    pub fn record_bind_pose(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        pool: &mut LazyPool,
+        geometry_buf: BufferNode,
+        meshes: &[SkinnedMesh],
+    ) -> Result<(), DriverError> {
+        let dispatches = meshes
+            .iter()
+            .map(|mesh| PushConstants {
+                src_offset: mesh.src_offset,
+                dst_offset: mesh.dst_offset,
+                vertex_count: mesh.vertex_count,
+                vertex_stride: mesh.vertex_stride,
+                joint_offset: 0,
+                joint_count: 0,
+            })
+            .collect::<Box<_>>();
+
+        self.record_dispatches(render_graph, pool, geometry_buf, &[], &dispatches)
+    }
+
+    fn record_dispatches(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        pool: &mut LazyPool,
+        geometry_buf: BufferNode,
+        joint_matrices: &[Mat4],
+        dispatches: &[PushConstants],
+    ) -> Result<(), DriverError> {
+        if dispatches.is_empty() {
+            return Ok(());
+        }
+
+        // Every descriptor must be bound, so the bind pose binds a matrix which is never read
+        let joint_buf = render_graph.bind_node(if joint_matrices.is_empty() {
+            lease_storage_buffer(pool, &[Mat4::IDENTITY])?
+        } else {
+            lease_storage_buffer(pool, joint_matrices)?
+        });
+        let dispatches = dispatches.to_vec();
+
+        render_graph
+            .begin_pass(pass_name("Skin vertices"))
+            .bind_pipeline(self.pipeline())
+            .write_descriptor(0, geometry_buf)
+            .read_descriptor(1, joint_buf)
+            .record_compute(move |compute, _| {
+                for push_consts in &dispatches {
+                    let workgroup_count = (push_consts.vertex_count + Self::WORKGROUP_SIZE - 1)
+                        / Self::WORKGROUP_SIZE;
+
+                    compute
+                        .push_constants(bytes_of(push_consts))
+                        .dispatch(workgroup_count, 1, 1);
+                }
+            });
+
+        Ok(())
+    }
+
+    /// Forgets a removed model instance, keeping its posed model for the next instance of the
+    /// same model.
+    pub fn remove(&mut self, model_instance: ModelInstance) {
+        if let Some(posed_model) = self.posed_models.remove(&model_instance) {
+            self.spare_models
+                .entry(posed_model.bind_model)
+                .or_default()
+                .push(posed_model);
+        }
+    }
+
+    /// Sets the joint matrices of a model instance, returning `false` if it is not skinned.
+    pub fn set_pose(&mut self, model_instance: ModelInstance, joint_matrices: &[Mat4]) -> bool {
+        let Some(posed_model) = self.posed_models.get_mut(&model_instance) else {
+            return false;
+        };
+
+        posed_model.joint_matrices.clear();
+        posed_model.joint_matrices.extend_from_slice(joint_matrices);
+        posed_model.dirty = true;
+
+        true
+    }
+
+    /// Returns a spare posed model of the given model, if there is one, in its bind pose.
+    pub fn take_spare(&mut self, bind_model: Model) -> Option<PosedModel> {
+        let mut posed_model = self.spare_models.get_mut(&bind_model)?.pop()?;
+        posed_model.joint_matrices.clear();
+        posed_model.dirty = true;
+
+        Some(posed_model)
+    }
+}
@@ -0,0 +1,282 @@
+use {
+    crate::math::Ray,
+    glam::{IVec3, Vec3},
+    std::{
+        collections::{HashMap, HashSet},
+        hash::Hash,
+    },
+};
+
+/// Returns the distance along the ray to where it enters the sphere, which is zero if the ray
+/// starts inside it.
+fn intersect_sphere(ray: Ray, center: Vec3, radius: f32) -> Option<f32> {
+    let offset = ray.position() - center;
+    let b = offset.dot(ray.normal());
+    let c = offset.length_squared() - radius * radius;
+
+    if c > 0.0 && b > 0.0 {
+        return None;
+    }
+
+    let discriminant = b * b - c;
+
+    (discriminant >= 0.0).then(|| (-b - discriminant.sqrt()).max(0.0))
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    center: Vec3,
+    radius: f32,
+
+    /// The first and last cells which hold the entry, or `None` if it is too large for cells.
+    cells: Option<(IVec3, IVec3)>,
+}
+
+/// Bounding spheres kept in a uniform grid of cells on the CPU, so that callers may find which
+/// keys are near a point or along a ray without reading anything back from the GPU.
+#[derive(Debug)]
+pub struct SpatialIndex<T> {
+    cells: HashMap<IVec3, Vec<T>>,
+    entries: HashMap<T, Entry>,
+
+    /// Entries spanning too many cells, which every query tests instead.
+    large: Vec<T>,
+}
+
+impl<T> SpatialIndex<T>
+where
+    T: Copy + Eq + Hash,
+{
+    const CELL_SIZE: f32 = 8.0;
+
+    /// Entries, and sphere queries, spanning more cells than this along any axis skip the grid.
+    const MAX_CELL_SPAN: i32 = 4;
+
+    fn cell(position: Vec3) -> IVec3 {
+        (position / Self::CELL_SIZE).floor().as_ivec3()
+    }
+
+    fn cells_between(min: IVec3, max: IVec3) -> impl Iterator<Item = IVec3> {
+        (min.x..=max.x).flat_map(move |x| {
+            (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| IVec3::new(x, y, z)))
+        })
+    }
+
+    /// Inserts, or moves, the bounding sphere of a key.
+    pub fn insert(&mut self, key: T, center: Vec3, radius: f32) {
+        self.remove(key);
+
+        let min = Self::cell(center - radius);
+        let max = Self::cell(center + radius);
+        let cells = if (max - min).max_element() < Self::MAX_CELL_SPAN {
+            for cell in Self::cells_between(min, max) {
+                self.cells.entry(cell).or_default().push(key);
+            }
+
+            Some((min, max))
+        } else {
+            self.large.push(key);
+
+            None
+        };
+
+        self.entries.insert(
+            key,
+            Entry {
+                center,
+                radius,
+                cells,
+            },
+        );
+    }
+
+    /// Returns the keys whose bounding spheres overlap the given sphere, in no particular order.
+    pub fn query_sphere(&self, center: Vec3, radius: f32) -> Vec<T> {
+        let mut res = vec![];
+        let mut tested = HashSet::new();
+        let mut test = |key: T| {
+            if !tested.insert(key) {
+                return;
+            }
+
+            let entry = self.entries[&key];
+            let distance = entry.radius + radius;
+
+            if entry.center.distance_squared(center) <= distance * distance {
+                res.push(key);
+            }
+        };
+
+        let min = Self::cell(center - radius);
+        let max = Self::cell(center + radius);
+
+        if (max - min).max_element() < Self::MAX_CELL_SPAN {
+            self.large.iter().copied().for_each(&mut test);
+
+            for cell in Self::cells_between(min, max) {
+                if let Some(keys) = self.cells.get(&cell) {
+                    keys.iter().copied().for_each(&mut test);
+                }
+            }
+        } else {
+            self.entries.keys().copied().for_each(test);
+        }
+
+        res
+    }
+
+    /// Returns the keys whose bounding spheres the ray enters within `max_distance`, nearest
+    /// first, along with the distance to where the ray enters each; an infinite distance finds
+    /// every key along the ray.
+    pub fn query_ray(&self, ray: Ray, max_distance: f32) -> Vec<(T, f32)> {
+        // The cells are only walked as far as the furthest entry, so that they end
+        let max_distance = if max_distance.is_finite() {
+            max_distance
+        } else {
+            self.entries
+                .values()
+                .map(|entry| entry.center.distance(ray.position()) + entry.radius)
+                .fold(0.0, f32::max)
+        };
+
+        let mut res = vec![];
+        let mut tested = HashSet::new();
+        let mut test = |key: T| {
+            if !tested.insert(key) {
+                return;
+            }
+
+            let entry = self.entries[&key];
+
+            if let Some(distance) = intersect_sphere(ray, entry.center, entry.radius)
+                .filter(|&distance| distance <= max_distance)
+            {
+                res.push((key, distance));
+            }
+        };
+
+        self.large.iter().copied().for_each(&mut test);
+
+        // Visits each cell the ray passes through, in order (Amanatides and Woo)
+        let position = ray.position();
+        let normal = ray.normal();
+        let mut cell = Self::cell(position);
+        let step = IVec3::from_array(normal.to_array().map(|n| {
+            if n > 0.0 {
+                1
+            } else if n < 0.0 {
+                -1
+            } else {
+                0
+            }
+        }));
+        let boundary = (cell + step.max(IVec3::ZERO)).as_vec3() * Self::CELL_SIZE;
+        let mut next = Vec3::INFINITY;
+        let mut delta = Vec3::INFINITY;
+
+        for axis in 0..3 {
+            if step[axis] != 0 {
+                next[axis] = (boundary[axis] - position[axis]) / normal[axis];
+                delta[axis] = Self::CELL_SIZE / normal[axis].abs();
+            }
+        }
+
+        let mut distance = 0.0;
+
+        while distance <= max_distance {
+            if let Some(keys) = self.cells.get(&cell) {
+                keys.iter().copied().for_each(&mut test);
+            }
+
+            let axis = if next.x < next.y && next.x < next.z {
+                0
+            } else if next.y < next.z {
+                1
+            } else {
+                2
+            };
+
+            distance = next[axis];
+            cell[axis] += step[axis];
+            next[axis] += delta[axis];
+        }
+
+        res.sort_by(|(_, lhs), (_, rhs)| lhs.total_cmp(rhs));
+        res
+    }
+
+    pub fn remove(&mut self, key: T) {
+        let Some(entry) = self.entries.remove(&key) else {
+            return;
+        };
+
+        if let Some((min, max)) = entry.cells {
+            for cell in Self::cells_between(min, max) {
+                if let Some(keys) = self.cells.get_mut(&cell) {
+                    keys.retain(|&other| other != key);
+
+                    if keys.is_empty() {
+                        self.cells.remove(&cell);
+                    }
+                }
+            }
+        } else {
+            self.large.retain(|&other| other != key);
+        }
+    }
+
+    /// Returns the center and radius of the bounding sphere of a key.
+    pub fn sphere(&self, key: T) -> Option<(Vec3, f32)> {
+        self.entries
+            .get(&key)
+            .map(|entry| (entry.center, entry.radius))
+    }
+}
+
+impl<T> Default for SpatialIndex<T> {
+    fn default() -> Self {
+        Self {
+            cells: Default::default(),
+            entries: Default::default(),
+            large: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, glam::vec3};
+
+    #[test]
+    pub fn query_sphere_and_ray() {
+        let mut index = SpatialIndex::default();
+        index.insert(0, vec3(0.0, 0.0, -10.0), 1.0);
+        index.insert(1, vec3(0.0, 0.0, -30.0), 1.0);
+        index.insert(2, vec3(20.0, 0.0, 0.0), 1.0);
+        index.insert(3, vec3(0.0, 0.0, -50.0), 100.0);
+
+        let mut near = index.query_sphere(Vec3::ZERO, 10.0);
+        near.sort();
+
+        assert_eq!(near, [0, 3]);
+
+        let ray = Ray::new(Vec3::ZERO, -Vec3::Z);
+        let hits = index
+            .query_ray(ray, 40.0)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+
+        assert_eq!(hits, [3, 0, 1]);
+
+        // Moving an entry updates the cells which hold it
+        index.insert(0, vec3(20.0, 0.0, -10.0), 1.0);
+        index.remove(3);
+
+        assert!(index.query_sphere(Vec3::ZERO, 10.0).is_empty());
+        assert_eq!(index.query_ray(ray, 40.0).len(), 1);
+        assert_eq!(index.query_ray(ray, 20.0).len(), 0);
+        assert_eq!(index.query_ray(ray, f32::INFINITY).len(), 1);
+        assert_eq!(index.query_ray(ray, f32::NAN).len(), 1);
+    }
+}
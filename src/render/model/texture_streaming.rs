@@ -0,0 +1,383 @@
+//! Streams the detailed mip levels of block-compressed material textures in and out as the camera
+//! moves, so that large levels need not keep every texture resident at full size.
+//!
+//! Each texture is loaded with only its smallest mips, which stay resident. While recording, the
+//! model buffer requests the mip level each texture needs from the size on screen of the model
+//! instances using it, and once a frame textures are replaced by copies holding more, or fewer,
+//! levels, as many as the [`FrameBudget`] allows. Requests which do not fit in the memory budget
+//! are coarsened, most detailed first.
+
+use {
+    super::super::{
+        compressed_bitmap::{CompressedBitmap, CompressedBitmaps},
+        frame_budget::{FrameBudget, StreamingWork},
+        mip::{compressed_image_format, record_mip_chain},
+        transfer::TransferQueue,
+    },
+    pak::BitmapId,
+    screen_13::prelude::*,
+    std::{
+        cmp::Reverse,
+        collections::{BinaryHeap, HashMap},
+        sync::Arc,
+        time::Instant,
+    },
+};
+
+/// Largest size, in pixels, of the mips loaded with each streamed texture.
+const RESIDENT_SIZE: u32 = 64;
+
+/// Textures usually tile across a model, so they are given one more level of detail than the size
+/// of the model on screen alone needs.
+const DETAIL_BIAS: f32 = 1.0;
+
+/// Returns the most detailed mip level loaded with a bitmap of the given size, which stays
+/// resident while it is streamed.
+pub fn base_mip_level(width: u32, height: u32) -> u32 {
+    (width.max(height) / RESIDENT_SIZE).max(1).ilog2()
+}
+
+/// Returns the mip level which shows a texture of `size` pixels at about one texel per pixel on a
+/// model instance `pixels` across on screen.
+fn desired_mip_level(size: u32, pixels: f32) -> u32 {
+    ((size as f32 / pixels.max(1.0)).log2() - DETAIL_BIAS).max(0.0) as u32
+}
+
+#[derive(Debug)]
+struct StreamedTexture {
+    base_mip: u32,
+
+    /// Bytes of each mip level, starting with the full size level.
+    mip_lens: Box<[usize]>,
+
+    /// Most detailed mip level requested since the textures were last streamed.
+    requested_mip: u32,
+
+    resident_mip: u32,
+
+    /// Width or height, whichever is larger, of the full size level.
+    size: u32,
+
+    /// Indices into the textures of the model buffer which hold this texture.
+    texture_indices: Vec<usize>,
+}
+
+impl StreamedTexture {
+    /// Returns the bytes of the mip levels from `mip_level` to the smallest.
+    fn resident_len(&self, mip_level: u32) -> usize {
+        self.mip_lens[mip_level as usize..].iter().sum()
+    }
+}
+
+/// A replacement texture being uploaded on the transfer queue.
+#[derive(Debug)]
+struct PendingStream {
+    cmd_buf: Lease<CommandBuffer>,
+    image: Arc<Image>,
+    mip_level: u32,
+    texture: usize,
+}
+
+#[derive(Debug)]
+pub struct TextureStreaming {
+    bitmaps: Arc<CompressedBitmaps>,
+
+    /// Bytes of streamed textures, including their base mips, which may be resident at once.
+    budget: usize,
+
+    device: Arc<Device>,
+    pending: Vec<PendingStream>,
+    pool: LazyPool,
+
+    /// Set when any mip level has been requested since the textures were last streamed.
+    requested: bool,
+
+    /// Index into `textures` of each model buffer texture which is streamed.
+    texture_streams: HashMap<usize, usize>,
+
+    textures: Vec<(BitmapId, StreamedTexture)>,
+    transfer_queue: TransferQueue,
+}
+
+impl TextureStreaming {
+    /// Distance from the camera within which model instances request mip levels; textures only
+    /// used further away keep their base mips.
+    pub const RADIUS: f32 = 100.0;
+
+    pub fn new(device: &Arc<Device>, bitmaps: Arc<CompressedBitmaps>, budget: usize) -> Self {
+        Self {
+            bitmaps,
+            budget,
+            device: Arc::clone(device),
+            pending: Default::default(),
+            pool: LazyPool::new(device),
+            requested: false,
+            texture_streams: Default::default(),
+            textures: Default::default(),
+            transfer_queue: TransferQueue::new(device),
+        }
+    }
+
+    /// Streams the given bitmap, which is held by the given model buffer textures and was loaded
+    /// with the mips from [`base_mip_level`].
+    pub fn insert(&mut self, bitmap_id: BitmapId, texture_indices: Vec<usize>) {
+        let bitmap = &self.bitmaps[&bitmap_id];
+        let base_mip = base_mip_level(bitmap.width, bitmap.height);
+        let stream = self.textures.len();
+
+        for &texture_idx in &texture_indices {
+            self.texture_streams.insert(texture_idx, stream);
+        }
+
+        self.textures.push((
+            bitmap_id,
+            StreamedTexture {
+                base_mip,
+                mip_lens: bitmap.mips.iter().map(Vec::len).collect(),
+                requested_mip: base_mip,
+                resident_mip: base_mip,
+                size: bitmap.width.max(bitmap.height),
+                texture_indices,
+            },
+        ));
+    }
+
+    /// Stops streaming into the given model buffer texture, such as when it is replaced.
+    pub fn remove(&mut self, texture_idx: usize) {
+        if let Some(stream) = self.texture_streams.remove(&texture_idx) {
+            self.textures[stream]
+                .1
+                .texture_indices
+                .retain(|&idx| idx != texture_idx);
+        }
+    }
+
+    /// Requests enough detail for the given model buffer texture to be drawn on a model instance
+    /// `pixels` across on screen.
+    pub fn request(&mut self, texture_idx: usize, pixels: f32) {
+        if let Some(&stream) = self.texture_streams.get(&texture_idx) {
+            let texture = &mut self.textures[stream].1;
+
+            texture.requested_mip = texture
+                .requested_mip
+                .min(desired_mip_level(texture.size, pixels));
+            self.requested = true;
+        }
+    }
+
+    /// Swaps in the textures uploaded since the previous update which have finished, and then
+    /// starts uploading the next textures which should change, as many as the frame budget allows.
+    pub fn update(
+        &mut self,
+        textures: &mut [Arc<Image>],
+        budget: &mut FrameBudget,
+    ) -> Result<(), DriverError> {
+        let mut pending_idx = 0;
+
+        while pending_idx < self.pending.len() {
+            if !self.pending[pending_idx].cmd_buf.has_executed()? {
+                pending_idx += 1;
+                continue;
+            }
+
+            let pending = self.pending.swap_remove(pending_idx);
+            let texture = &mut self.textures[pending.texture].1;
+
+            for &texture_idx in &texture.texture_indices {
+                textures[texture_idx] = Arc::clone(&pending.image);
+            }
+
+            texture.resident_mip = pending.mip_level;
+        }
+
+        if !self.requested {
+            return Ok(());
+        }
+
+        let streamed = self
+            .textures
+            .iter()
+            .map(|(_, texture)| texture)
+            .collect::<Box<_>>();
+        let mut targets = plan(&streamed, self.budget);
+        let over_budget = streamed
+            .iter()
+            .map(|texture| texture.resident_len(texture.resident_mip))
+            .sum::<usize>()
+            > self.budget;
+        let mut next = vec![];
+
+        // Textures being uploaded, or chosen to be, are left alone until their upload finishes
+        for pending in &self.pending {
+            targets[pending.texture] = streamed[pending.texture].resident_mip;
+        }
+
+        while let Some((stream, mip_level)) = next_stream(&streamed, &targets, over_budget) {
+            let len = streamed[stream].resident_len(mip_level);
+
+            if !budget.try_spend(StreamingWork::TextureUpload, len) {
+                break;
+            }
+
+            targets[stream] = streamed[stream].resident_mip;
+            next.push((stream, mip_level, len));
+        }
+
+        for (_, texture) in &mut self.textures {
+            texture.requested_mip = texture.base_mip;
+        }
+
+        self.requested = false;
+
+        for (stream, mip_level, len) in next {
+            let started = Instant::now();
+            let (bitmap_id, _) = &self.textures[stream];
+            let bitmap = &self.bitmaps[bitmap_id];
+            let (width, height) =
+                CompressedBitmap::mip_size(bitmap.width, bitmap.height, mip_level);
+            let mut render_graph = RenderGraph::new();
+            let image = record_mip_chain(
+                &self.device,
+                &mut render_graph,
+                compressed_image_format(bitmap.format),
+                width,
+                height,
+                &bitmap.mips[mip_level as usize..],
+            )?;
+            let cmd_buf = self
+                .transfer_queue
+                .submit(render_graph, &mut self.pool, 0)?;
+
+            // Uploads run on the transfer queue, so the frame only waits on recording them
+            budget.measure(
+                StreamingWork::TextureUpload,
+                len,
+                started.elapsed().as_secs_f32(),
+            );
+
+            self.pending.push(PendingStream {
+                cmd_buf,
+                image,
+                mip_level,
+                texture: stream,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the mip level each texture should have resident: the level requested, coarsened where
+/// needed to fit in `budget` bytes starting with the most detailed levels.
+fn plan(textures: &[&StreamedTexture], budget: usize) -> Vec<u32> {
+    let mut targets = textures
+        .iter()
+        .map(|texture| texture.requested_mip.min(texture.base_mip))
+        .collect::<Vec<_>>();
+    let mut len = textures
+        .iter()
+        .zip(&targets)
+        .map(|(texture, &mip_level)| texture.resident_len(mip_level))
+        .sum::<usize>();
+    let mut most_detailed_first = targets
+        .iter()
+        .enumerate()
+        .filter(|(idx, &mip_level)| mip_level < textures[*idx].base_mip)
+        .map(|(idx, &mip_level)| Reverse((mip_level, idx)))
+        .collect::<BinaryHeap<_>>();
+
+    while len > budget {
+        let Some(Reverse((mip_level, idx))) = most_detailed_first.pop() else {
+            break;
+        };
+
+        len -= textures[idx].mip_lens[mip_level as usize];
+        targets[idx] = mip_level + 1;
+
+        if targets[idx] < textures[idx].base_mip {
+            most_detailed_first.push(Reverse((targets[idx], idx)));
+        }
+    }
+
+    targets
+}
+
+/// Returns the texture to stream next and the mip level it should have.
+///
+/// Memory is freed before more is used. Unless over budget, textures are only streamed out once
+/// two levels more detailed than needed, so that moving back and forth does not stream them
+/// repeatedly.
+fn next_stream(
+    textures: &[&StreamedTexture],
+    targets: &[u32],
+    over_budget: bool,
+) -> Option<(usize, u32)> {
+    let min_coarsening = if over_budget { 1 } else { 2 };
+    let changes = || textures.iter().zip(targets).enumerate();
+    let stream_out = changes()
+        .filter(|(_, (texture, &target))| target >= texture.resident_mip + min_coarsening)
+        .max_by_key(|(_, (texture, &target))| target - texture.resident_mip);
+    let stream_in = changes()
+        .filter(|(_, (texture, &target))| target < texture.resident_mip)
+        .max_by_key(|(_, (texture, &target))| texture.resident_mip - target);
+
+    stream_out
+        .or(stream_in)
+        .map(|(idx, (_, &target))| (idx, target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texture(size: u32, requested_mip: u32, resident_mip: u32) -> StreamedTexture {
+        StreamedTexture {
+            base_mip: base_mip_level(size, size),
+            mip_lens: (0..=size.ilog2())
+                .map(|mip_level| CompressedBitmap::mip_len(size, size, mip_level))
+                .collect(),
+            requested_mip,
+            resident_mip,
+            size,
+            texture_indices: vec![],
+        }
+    }
+
+    #[test]
+    pub fn mip_levels() {
+        assert_eq!(base_mip_level(1024, 512), 4);
+        assert_eq!(base_mip_level(64, 64), 0);
+        assert_eq!(base_mip_level(16, 16), 0);
+
+        assert_eq!(desired_mip_level(1024, 2048.0), 0);
+        assert_eq!(desired_mip_level(1024, 256.0), 1);
+        assert_eq!(desired_mip_level(1024, 0.0), 9);
+    }
+
+    #[test]
+    pub fn plan_within_budget() {
+        let near = texture(1024, 0, 4);
+        let far = texture(1024, 2, 4);
+        let textures = [&near, &far];
+
+        assert_eq!(plan(&textures, usize::MAX), [0, 2]);
+
+        // Without room for both at full detail, the most detailed is coarsened first
+        let budget = near.resident_len(1) + far.resident_len(2);
+
+        assert_eq!(plan(&textures, budget), [1, 2]);
+        assert_eq!(plan(&textures, 0), [4, 4]);
+    }
+
+    #[test]
+    pub fn stream_out_before_in() {
+        let near = texture(1024, 0, 4);
+        let far = texture(1024, 4, 1);
+        let textures = [&near, &far];
+
+        assert_eq!(next_stream(&textures, &[0, 4], false), Some((1, 4)));
+        assert_eq!(next_stream(&textures, &[0, 2], false), Some((0, 0)));
+        assert_eq!(next_stream(&textures, &[0, 2], true), Some((1, 2)));
+        assert_eq!(next_stream(&textures, &[4, 1], false), None);
+    }
+}
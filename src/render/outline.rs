@@ -0,0 +1,163 @@
+use {
+    super::{debug::capture::pass_name, lease_storage_buffer},
+    crate::res,
+    anyhow::Context,
+    bytemuck::{bytes_of, Pod, Zeroable},
+    glam::Vec4,
+    screen_13::prelude::*,
+    std::sync::Arc,
+};
+
+#[cfg(not(feature = "hot-shaders"))]
+use {super::read_blob, pak::PakBuf};
+
+#[cfg(feature = "hot-shaders")]
+use {
+    super::{hot_shader::watch, res_shader_dir},
+    screen_13_hot::prelude::*,
+};
+
+/// The outline color of one highlighted model instance.
+///
+/// Must match `Highlight` in `outline.frag`.
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct Highlight {
+    color: Vec4,
+
+    /// The model instance index plus one, as written to the ID image.
+    id: u32,
+
+    _0: [u32; 3],
+}
+
+impl Highlight {
+    pub fn new(model_instance_idx: usize, color: Vec4) -> Self {
+        Self {
+            color,
+            id: model_instance_idx as u32 + 1,
+            _0: Default::default(),
+        }
+    }
+}
+
+/// Draws outlines around highlighted model instances, such as the object the player may interact
+/// with, from an image of the model instance ID covering each pixel.
+///
+/// Pixels not covered by a highlighted model instance, but within a few pixels of one, are blended
+/// with its color. IDs are only written where the model instance is visible, so the parts hidden
+/// behind other models have no outline.
+#[derive(Debug)]
+pub struct OutlinePipeline {
+    #[cfg(not(feature = "hot-shaders"))]
+    pipeline: Arc<GraphicPipeline>,
+
+    #[cfg(feature = "hot-shaders")]
+    pipeline: HotGraphicPipeline,
+}
+
+impl OutlinePipeline {
+    /// Height of the framebuffer, in pixels, drawn with outlines a single pixel wide.
+    const PIXELS_PER_WIDTH: u32 = 360;
+
+    #[cfg(not(feature = "hot-shaders"))]
+    pub fn new(device: &Arc<Device>, res_pak: &mut PakBuf) -> anyhow::Result<Self> {
+        let pipeline = Arc::new(
+            GraphicPipeline::create(
+                device,
+                GraphicPipelineInfo::new().blend(BlendMode::ALPHA),
+                [
+                    Shader::new_vertex(
+                        read_blob(res_pak, res::SHADER_FULLSCREEN_VERT_SPIRV)?.as_slice(),
+                    ),
+                    Shader::new_fragment(
+                        read_blob(res_pak, res::SHADER_OUTLINE_FRAG_SPIRV)?.as_slice(),
+                    )
+                    .image_sampler(0, Self::id_sampler_info()),
+                ],
+            )
+            .context("Creating outline pipeline")?,
+        );
+
+        Ok(Self { pipeline })
+    }
+
+    #[cfg(feature = "hot-shaders")]
+    pub fn new(device: &Arc<Device>) -> anyhow::Result<Self> {
+        let shader_dir = res_shader_dir();
+        let pipeline = HotGraphicPipeline::create(
+            device,
+            GraphicPipelineInfo::new().blend(BlendMode::ALPHA),
+            [
+                HotShader::new_vertex(watch(shader_dir.join("fullscreen.vert"))),
+                HotShader::new_fragment(watch(shader_dir.join("outline.frag")))
+                    .image_sampler(0, Self::id_sampler_info()),
+            ],
+        )
+        .context("Creating hot outline pipeline")?;
+
+        Ok(Self { pipeline })
+    }
+
+    /// IDs are read with `texelFetch`, but the image must still be bound with a sampler.
+    fn id_sampler_info() -> SamplerInfo {
+        SamplerInfo::new()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .build()
+    }
+
+    #[inline(always)]
+    fn pipeline(&mut self) -> &Arc<GraphicPipeline> {
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.pipeline;
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.pipeline.hot();
+
+        res
+    }
+
+    /// Blends outlines of the given highlights into the framebuffer, which must be the size of
+    /// `id_image`.
+    pub fn record(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        pool: &mut impl Pool<BufferInfoBuilder, Buffer>,
+        framebuffer: impl Into<AnyImageNode>,
+        id_image: impl Into<AnyImageNode>,
+        highlights: &[Highlight],
+    ) -> Result<(), DriverError> {
+        #[derive(Clone, Copy, Pod, Zeroable)]
+        #[repr(C)]
+        struct PushConstants {
+            highlight_count: u32,
+            width: i32,
+        }
+
+        let framebuffer = framebuffer.into();
+        let framebuffer_info = render_graph.node_info(framebuffer);
+        let highlight_buf = render_graph.bind_node(lease_storage_buffer(pool, highlights)?);
+        let push_consts = PushConstants {
+            highlight_count: highlights.len() as _,
+            width: (framebuffer_info.height / Self::PIXELS_PER_WIDTH).max(1) as _,
+        };
+
+        render_graph
+            .begin_pass(pass_name("Outline"))
+            .bind_pipeline(self.pipeline())
+            .read_descriptor(0, id_image.into())
+            .access_descriptor(1, highlight_buf, AccessType::FragmentShaderReadOther)
+            .load_color(0, framebuffer)
+            .store_color(0, framebuffer)
+            .record_subpass(move |subpass, _| {
+                subpass
+                    .push_constants(bytes_of(&push_consts))
+                    .draw(3, 1, 0, 0);
+            });
+
+        Ok(())
+    }
+}
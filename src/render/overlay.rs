@@ -0,0 +1,310 @@
+//! Records the HUD onto its own render graph on a worker thread while the world is recorded onto
+//! the frame graph, so that neither waits for the other as instance counts and UI complexity
+//! grow.
+//!
+//! Render graphs cannot be joined, so the overlay graph draws into an image of its own which is
+//! submitted before the frame graph and then blended over a target image, such as the HUD image,
+//! by it; the access of the image is tracked across both graphs.
+
+use {
+    super::debug::capture::pass_name,
+    crate::res,
+    anyhow::Context,
+    crossbeam_channel::{unbounded, Receiver, Sender},
+    screen_13::prelude::*,
+    std::{
+        mem::transmute,
+        panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+        sync::Arc,
+        thread::{Builder, JoinHandle},
+        time::Instant,
+    },
+};
+
+#[cfg(not(feature = "hot-shaders"))]
+use super::{open_res_pak, read_blob};
+
+#[cfg(feature = "hot-shaders")]
+use {
+    super::{hot_shader::watch, res_shader_dir},
+    screen_13_hot::prelude::*,
+};
+
+/// Work for the overlay thread; see [`Overlay::record`] for how its borrows are kept alive.
+type Job = Box<dyn FnOnce() + Send>;
+
+#[derive(Debug)]
+pub struct Overlay {
+    #[cfg(not(feature = "hot-shaders"))]
+    pipeline: Arc<GraphicPipeline>,
+
+    #[cfg(feature = "hot-shaders")]
+    pipeline: HotGraphicPipeline,
+
+    pool: LazyPool,
+    timings: OverlayTimings,
+    worker: Worker,
+}
+
+impl Overlay {
+    #[cfg(not(feature = "hot-shaders"))]
+    pub fn new(device: &Arc<Device>) -> anyhow::Result<Self> {
+        let mut res_pak = open_res_pak()?;
+        let pipeline = Arc::new(
+            GraphicPipeline::create(
+                device,
+                Self::pipeline_info(),
+                [
+                    Shader::new_vertex(
+                        read_blob(&mut res_pak, res::SHADER_FULLSCREEN_VERT_SPIRV)?.as_slice(),
+                    ),
+                    Shader::new_fragment(
+                        read_blob(&mut res_pak, res::SHADER_OVERLAY_FRAG_SPIRV)?.as_slice(),
+                    )
+                    .image_sampler(0, Self::sampler_info()),
+                ],
+            )
+            .context("Creating overlay pipeline")?,
+        );
+
+        Ok(Self {
+            pipeline,
+            pool: LazyPool::new(device),
+            timings: Default::default(),
+            worker: Worker::spawn()?,
+        })
+    }
+
+    #[cfg(feature = "hot-shaders")]
+    pub fn new(device: &Arc<Device>) -> anyhow::Result<Self> {
+        let shader_dir = res_shader_dir();
+        let pipeline = HotGraphicPipeline::create(
+            device,
+            Self::pipeline_info(),
+            [
+                HotShader::new_vertex(watch(shader_dir.join("fullscreen.vert"))),
+                HotShader::new_fragment(watch(shader_dir.join("overlay.frag")))
+                    .image_sampler(0, Self::sampler_info()),
+            ],
+        )
+        .context("Creating hot overlay pipeline")?;
+
+        Ok(Self {
+            pipeline,
+            pool: LazyPool::new(device),
+            timings: Default::default(),
+            worker: Worker::spawn()?,
+        })
+    }
+
+    #[inline(always)]
+    fn pipeline(&mut self) -> &Arc<GraphicPipeline> {
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.pipeline;
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.pipeline.hot();
+
+        res
+    }
+
+    /// The overlay image holds color already multiplied by coverage: text and primitives are
+    /// alpha-blended over transparent black, so it must not be multiplied by alpha again.
+    ///
+    /// Coverage accumulates in the alpha of the target, so that it may itself be a premultiplied
+    /// image such as [`crate::ui::DrawContext::hud_image`].
+    fn pipeline_info() -> GraphicPipelineInfoBuilder {
+        GraphicPipelineInfo::new().blend(BlendMode {
+            blend_enable: true,
+            src_color_blend_factor: vk::BlendFactor::ONE,
+            dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ONE,
+            dst_alpha_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            alpha_blend_op: vk::BlendOp::ADD,
+            color_write_mask: vk::ColorComponentFlags::RGBA,
+        })
+    }
+
+    /// Calls `record_frame` with the frame graph on this thread and `record_overlay` with a new
+    /// graph and a transparent image the size of `target_image` on the overlay thread, then blends
+    /// that image over the target image.
+    ///
+    /// Nothing is blended if either closure returns an error.
+    pub fn record(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        target_image: impl Into<AnyImageNode>,
+        record_frame: impl FnOnce(&mut RenderGraph) -> anyhow::Result<()>,
+        record_overlay: impl FnOnce(&mut RenderGraph, ImageLeaseNode) -> anyhow::Result<()> + Send,
+    ) -> anyhow::Result<()> {
+        let target_image = target_image.into();
+        let target_info = render_graph.node_info(target_image);
+        let overlay_image = self.pool.lease(ImageInfo::new_2d(
+            vk::Format::R8G8B8A8_UNORM,
+            target_info.width,
+            target_info.height,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+                | vk::ImageUsageFlags::SAMPLED
+                | vk::ImageUsageFlags::TRANSFER_DST,
+        ))?;
+
+        let mut overlay = None;
+        let mut overlay_secs = 0.0;
+        let job: Box<dyn FnOnce() + Send + '_> = Box::new(|| {
+            let started = Instant::now();
+
+            overlay = Some(catch_unwind(AssertUnwindSafe(move || {
+                let mut render_graph = RenderGraph::new();
+                let overlay_image = render_graph.bind_node(overlay_image);
+
+                render_graph.clear_color_image_value(overlay_image, [0x00, 0x00, 0x00, 0x00]);
+                record_overlay(&mut render_graph, overlay_image)
+                    .map(|_| (render_graph, overlay_image))
+            })));
+            overlay_secs = started.elapsed().as_secs_f32();
+        });
+
+        // SAFETY: The job borrows from this stack frame, which is not left until the worker has
+        // run it: `wait` blocks until then when dropped, including while unwinding
+        let job: Job = unsafe { transmute(job) };
+        let (frame, frame_secs, wait_secs) = {
+            let wait = self.worker.run(job)?;
+            let started = Instant::now();
+            let frame = record_frame(render_graph);
+            let frame_secs = started.elapsed().as_secs_f32();
+            let started = Instant::now();
+
+            drop(wait);
+
+            (frame, frame_secs, started.elapsed().as_secs_f32())
+        };
+
+        self.timings = OverlayTimings {
+            frame_secs,
+            overlay_secs,
+            wait_secs,
+        };
+
+        // A panic while recording the overlay is a panic of the frame
+        let overlay = overlay
+            .context("Overlay not recorded")?
+            .unwrap_or_else(|err| resume_unwind(err));
+        let (mut overlay_graph, overlay_image) = frame.and(overlay)?;
+        let overlay_image = overlay_graph.unbind_node(overlay_image);
+
+        // Submitted first, so the frame graph waits on the overlay before reading it
+        overlay_graph
+            .resolve()
+            .submit(&mut self.pool, 0, 0)
+            .context("Submitting overlay")?;
+
+        let overlay_image = render_graph.bind_node(overlay_image);
+
+        render_graph
+            .begin_pass(pass_name("Overlay"))
+            .bind_pipeline(self.pipeline())
+            .read_descriptor(0, overlay_image)
+            .load_color(0, target_image)
+            .store_color(0, target_image)
+            .record_subpass(move |subpass, _| {
+                subpass.draw(3, 1, 0, 0);
+            });
+
+        Ok(())
+    }
+
+    fn sampler_info() -> SamplerInfo {
+        SamplerInfo::new()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .build()
+    }
+
+    /// Returns how long the previous call to [`Self::record`] spent recording.
+    pub fn timings(&self) -> OverlayTimings {
+        self.timings
+    }
+}
+
+/// CPU time, in seconds, spent recording one frame, which shows how much recording the overlay on
+/// another thread saves.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OverlayTimings {
+    /// Recording the frame graph, on the calling thread.
+    pub frame_secs: f32,
+
+    /// Recording the overlay graph, on the overlay thread.
+    pub overlay_secs: f32,
+
+    /// Waiting for the overlay after the frame graph was recorded, which is the only part of
+    /// `overlay_secs` the frame is not recorded during.
+    pub wait_secs: f32,
+}
+
+/// Blocks until the worker has run the job it was returned for, when dropped.
+struct Wait<'a>(&'a Receiver<()>);
+
+impl Drop for Wait<'_> {
+    fn drop(&mut self) {
+        // An error means the worker is gone, which it only is once it has finished every job
+        self.0.recv().ok();
+    }
+}
+
+/// The thread which records the overlay, which lives as long as the overlay so that no thread is
+/// spawned each frame.
+#[derive(Debug)]
+struct Worker {
+    done: Receiver<()>,
+    jobs: Option<Sender<Job>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    /// Runs the given job on the worker, returning a guard which waits for it to finish.
+    fn run(&self, job: Job) -> anyhow::Result<Wait<'_>> {
+        self.jobs
+            .as_ref()
+            .context("Overlay thread stopped")?
+            .send(job)
+            .ok()
+            .context("Overlay thread stopped")?;
+
+        Ok(Wait(&self.done))
+    }
+
+    fn spawn() -> anyhow::Result<Self> {
+        let (jobs, job_rx) = unbounded::<Job>();
+        let (done_tx, done) = unbounded();
+        let thread = Builder::new()
+            .name("Overlay".to_owned())
+            .spawn(move || {
+                for job in job_rx {
+                    job();
+
+                    if done_tx.send(()).is_err() {
+                        break;
+                    }
+                }
+            })
+            .context("Spawning overlay thread")?;
+
+        Ok(Self {
+            done,
+            jobs: Some(jobs),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // Closing the channel ends the loop of the thread
+        self.jobs.take();
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use {
+    screen_13::prelude::*,
+    std::thread::{spawn, JoinHandle},
+};
+
+/// Pipelines which are compiled on a worker thread, because some drivers take seconds to do so
+/// and would otherwise stall the first loading screen.
+#[derive(Debug)]
+pub struct PendingPipelines<T> {
+    pipelines: Option<T>,
+    thread: Option<JoinHandle<anyhow::Result<T>>>,
+}
+
+impl<T> PendingPipelines<T>
+where
+    T: Send + 'static,
+{
+    pub fn spawn(create_fn: impl FnOnce() -> anyhow::Result<T> + Send + 'static) -> Self {
+        Self {
+            pipelines: None,
+            thread: Some(spawn(create_fn)),
+        }
+    }
+
+    /// Returns `true` once the worker thread has finished, even if it failed to create the
+    /// pipelines; the failure is returned by [`Self::wait`].
+    pub fn is_ready(&self) -> bool {
+        self.thread
+            .as_ref()
+            .map(JoinHandle::is_finished)
+            .unwrap_or(true)
+    }
+
+    /// Blocks until the pipelines have been created.
+    pub fn wait(&mut self) -> Result<&mut T, DriverError> {
+        if let Some(thread) = self.thread.take() {
+            let pipelines = thread
+                .join()
+                .map_err(|_| {
+                    error!("Pipeline thread panicked");
+
+                    DriverError::InvalidData
+                })?
+                .map_err(|err| {
+                    error!("Unable to create pipelines: {err:?}");
+
+                    DriverError::InvalidData
+                })?;
+
+            self.pipelines = Some(pipelines);
+        }
+
+        self.pipelines.as_mut().ok_or(DriverError::InvalidData)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn wait() {
+        let mut pipelines = PendingPipelines::spawn(|| Ok(42));
+
+        assert_eq!(*pipelines.wait().unwrap(), 42);
+        assert!(pipelines.is_ready());
+
+        let mut pipelines = PendingPipelines::<()>::spawn(|| anyhow::bail!("Compile error"));
+
+        assert!(pipelines.wait().is_err());
+        assert!(pipelines.wait().is_err());
+    }
+}
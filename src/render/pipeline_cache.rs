@@ -0,0 +1,164 @@
+//! Persists the pipeline cache of the device between runs so that the driver only compiles shaders
+//! the first time they are used.
+//!
+//! Every pipeline is created using the device pipeline cache, so data read here at startup applies
+//! to all graphic, compute and ray trace pipelines, including those created on worker threads.
+
+use {
+    crate::fs::project_dirs,
+    screen_13::prelude::*,
+    std::{
+        fs::{create_dir_all, read as read_file, write as write_file},
+        path::PathBuf,
+    },
+};
+
+/// Name of the file, in the cache directory, which holds pipeline cache data of the previous run.
+const FILE_NAME: &str = "pipeline_cache.bin";
+
+/// Size of `VkPipelineCacheHeaderVersionOne`.
+const HEADER_LEN: usize = 32;
+
+/// Returns `true` if the data was written by the same device and driver, as given by its header;
+/// drivers are not required to reject data from another device and some crash instead.
+fn is_compatible(
+    data: &[u8],
+    vendor_id: u32,
+    device_id: u32,
+    pipeline_cache_uuid: &[u8; vk::UUID_SIZE],
+) -> bool {
+    if data.len() < HEADER_LEN {
+        return false;
+    }
+
+    // Header fields are always written least significant byte first
+    let read_u32 = |offset: usize| {
+        u32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    };
+
+    read_u32(0) as usize >= HEADER_LEN
+        && read_u32(4) == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+        && read_u32(8) == vendor_id
+        && read_u32(12) == device_id
+        && data[16..HEADER_LEN] == pipeline_cache_uuid[..]
+}
+
+fn path() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.cache_dir().join(FILE_NAME))
+}
+
+/// Merges the data saved by a previous run into the pipeline cache of the device.
+///
+/// Must be called before any pipelines are created in order to be useful.
+pub fn read(device: &Device) {
+    let Some(path) = path() else {
+        return;
+    };
+
+    let Ok(data) = read_file(&path) else {
+        info!("No pipeline cache");
+
+        return;
+    };
+
+    let properties = &device.physical_device.properties_v1_0;
+
+    if !is_compatible(
+        &data,
+        properties.vendor_id,
+        properties.device_id,
+        &properties.pipeline_cache_uuid,
+    ) {
+        info!("Ignoring pipeline cache of another device or driver");
+
+        return;
+    }
+
+    info!("Reading {}", path.display());
+
+    unsafe {
+        let src_cache = match device.create_pipeline_cache(
+            &vk::PipelineCacheCreateInfo::builder().initial_data(&data),
+            None,
+        ) {
+            Ok(src_cache) => src_cache,
+            Err(err) => {
+                warn!("Unable to create pipeline cache: {err}");
+
+                return;
+            }
+        };
+
+        if let Err(err) = device.merge_pipeline_caches(Device::pipeline_cache(device), &[src_cache])
+        {
+            warn!("Unable to merge pipeline cache: {err}");
+        }
+
+        device.destroy_pipeline_cache(src_cache, None);
+    }
+}
+
+/// Saves the pipeline cache of the device so that the next run may use it.
+pub fn write(device: &Device) {
+    let Some(path) = path() else {
+        return;
+    };
+
+    let data = match unsafe { device.get_pipeline_cache_data(Device::pipeline_cache(device)) } {
+        Ok(data) => data,
+        Err(err) => {
+            warn!("Unable to get pipeline cache data: {err}");
+
+            return;
+        }
+    };
+
+    trace!("Writing {}", path.display());
+
+    if let Err(err) = path
+        .parent()
+        .map(create_dir_all)
+        .unwrap_or(Ok(()))
+        .and_then(|_| write_file(&path, data))
+    {
+        warn!("Unable to write pipeline cache: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(vendor_id: u32, device_id: u32, pipeline_cache_uuid: [u8; vk::UUID_SIZE]) -> Vec<u8> {
+        let mut res = vec![];
+        res.extend_from_slice(&(HEADER_LEN as u32).to_le_bytes());
+        res.extend_from_slice(&1u32.to_le_bytes());
+        res.extend_from_slice(&vendor_id.to_le_bytes());
+        res.extend_from_slice(&device_id.to_le_bytes());
+        res.extend_from_slice(&pipeline_cache_uuid);
+        res
+    }
+
+    #[test]
+    pub fn compatible_header() {
+        let uuid = [7; vk::UUID_SIZE];
+        let mut data = header(0x10de, 0x2204, uuid);
+        data.extend_from_slice(&[0; 64]);
+
+        assert!(is_compatible(&data, 0x10de, 0x2204, &uuid));
+        assert!(!is_compatible(&data, 0x1002, 0x2204, &uuid));
+        assert!(!is_compatible(&data, 0x10de, 0x2205, &uuid));
+        assert!(!is_compatible(&data, 0x10de, 0x2204, &[8; vk::UUID_SIZE]));
+        assert!(!is_compatible(
+            &data[..HEADER_LEN - 1],
+            0x10de,
+            0x2204,
+            &uuid
+        ));
+    }
+}
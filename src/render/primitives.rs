@@ -0,0 +1,209 @@
+use {
+    super::{debug::capture::pass_name, lease_storage_buffer},
+    crate::res,
+    anyhow::Context,
+    bytemuck::{bytes_of, Pod, Zeroable},
+    glam::{vec2, Vec2},
+    pak::Pak,
+    screen_13::prelude::*,
+    std::{f32::consts::TAU, sync::Arc},
+};
+
+/// Length, in pixels, of the edges which approximate a circle.
+const CIRCLE_SEGMENT_LEN: f32 = 4.0;
+
+const MIN_CIRCLE_SEGMENTS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+struct Vertex {
+    position: Vec2,
+
+    /// RGBA color packed as read by `unpackUnorm4x8`.
+    color: u32,
+
+    _0: u32,
+}
+
+/// Immediate-mode renderer of lines and filled shapes, for the HUD, maps and debug overlays.
+///
+/// Shapes are given in framebuffer pixels with the origin at the top-left and are drawn in the
+/// order they were added, over whatever is already in the framebuffer, each time
+/// [`record`](Self::record) is called.
+#[derive(Debug)]
+pub struct PrimitiveBuffer {
+    pipeline: Arc<GraphicPipeline>,
+    pool: LazyPool,
+    vertices: Vec<Vertex>,
+}
+
+impl PrimitiveBuffer {
+    pub fn new(device: &Arc<Device>) -> anyhow::Result<Self> {
+        let mut res_pak = res::open_pak().context("Opening pak")?;
+        let pipeline = Arc::new(
+            GraphicPipeline::create(
+                device,
+                GraphicPipelineInfo::new()
+                    .blend(BlendMode::ALPHA)
+                    .cull_mode(vk::CullModeFlags::NONE),
+                [
+                    Shader::new_vertex(
+                        res_pak
+                            .read_blob(res::SHADER_PRIMITIVE_VERT_SPIRV.as_str())
+                            .context("Reading vert shader")?
+                            .as_slice(),
+                    ),
+                    Shader::new_fragment(
+                        res_pak
+                            .read_blob(res::SHADER_PRIMITIVE_FRAG_SPIRV.as_str())
+                            .context("Reading frag shader")?
+                            .as_slice(),
+                    ),
+                ],
+            )
+            .context("Creating pipeline")?,
+        );
+
+        Ok(Self {
+            pipeline,
+            pool: LazyPool::new(device),
+            vertices: Default::default(),
+        })
+    }
+
+    /// Adds a filled circle approximated by enough edges that it looks round at its size.
+    pub fn draw_circle(&mut self, center: Vec2, radius: f32, color: [u8; 4]) {
+        for triangle in circle_triangles(center, radius) {
+            self.push_triangle(triangle, color);
+        }
+    }
+
+    /// Adds a line of the given width, in pixels, with square ends.
+    pub fn draw_line(&mut self, start: Vec2, end: Vec2, width: f32, color: [u8; 4]) {
+        if let Some(quad) = line_quad(start, end, width) {
+            self.push_quad(quad, color);
+        }
+    }
+
+    /// Adds a filled rectangle with its top-left corner at `position`.
+    pub fn draw_rect(&mut self, position: Vec2, size: Vec2, color: [u8; 4]) {
+        self.push_quad(
+            [
+                position,
+                position + vec2(size.x, 0.0),
+                position + size,
+                position + vec2(0.0, size.y),
+            ],
+            color,
+        );
+    }
+
+    fn push_quad(&mut self, [a, b, c, d]: [Vec2; 4], color: [u8; 4]) {
+        self.push_triangle([a, b, c], color);
+        self.push_triangle([a, c, d], color);
+    }
+
+    fn push_triangle(&mut self, positions: [Vec2; 3], color: [u8; 4]) {
+        let color = u32::from_le_bytes(color);
+
+        self.vertices
+            .extend(positions.into_iter().map(|position| Vertex {
+                position,
+                color,
+                _0: 0,
+            }));
+    }
+
+    /// Records drawing of everything added since the previous call, then clears it.
+    pub fn record(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        framebuffer_image: impl Into<AnyImageNode>,
+    ) -> Result<(), DriverError> {
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+
+        let framebuffer_image = framebuffer_image.into();
+        let framebuffer_info = render_graph.node_info(framebuffer_image);
+        let framebuffer_size = vec2(framebuffer_info.width as _, framebuffer_info.height as _);
+        let vertex_buf =
+            render_graph.bind_node(lease_storage_buffer(&mut self.pool, &self.vertices)?);
+        let vertex_count = self.vertices.len() as u32;
+
+        self.vertices.clear();
+
+        render_graph
+            .begin_pass(pass_name("Primitives"))
+            .bind_pipeline(&self.pipeline)
+            .access_descriptor(0, vertex_buf, AccessType::VertexShaderReadOther)
+            .load_color(0, framebuffer_image)
+            .store_color(0, framebuffer_image)
+            .record_subpass(move |subpass, _| {
+                subpass
+                    .push_constants(bytes_of(&framebuffer_size))
+                    .draw(vertex_count, 1, 0, 0);
+            });
+
+        Ok(())
+    }
+}
+
+/// Returns the triangles of a filled circle, fanned out from the center.
+fn circle_triangles(center: Vec2, radius: f32) -> impl Iterator<Item = [Vec2; 3]> {
+    let segments = ((TAU * radius / CIRCLE_SEGMENT_LEN).ceil() as usize).max(MIN_CIRCLE_SEGMENTS);
+    let point = move |idx: usize| {
+        let angle = idx as f32 * TAU / segments as f32;
+
+        center + radius * vec2(angle.cos(), angle.sin())
+    };
+
+    (0..segments).map(move |idx| [center, point(idx), point(idx + 1)])
+}
+
+/// Returns the corners of a line drawn as a quad, so that it does not depend on wide line
+/// support, or `None` if the line has no length.
+fn line_quad(start: Vec2, end: Vec2, width: f32) -> Option<[Vec2; 4]> {
+    let direction = (end - start).normalize_or_zero();
+
+    if direction == Vec2::ZERO {
+        return None;
+    }
+
+    let normal = direction.perp() * (width * 0.5);
+
+    Some([start + normal, end + normal, end - normal, start - normal])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn circle() {
+        let center = vec2(10.0, 20.0);
+        let triangles = circle_triangles(center, 2.0).collect::<Vec<_>>();
+
+        assert_eq!(triangles.len(), MIN_CIRCLE_SEGMENTS);
+
+        for [a, b, c] in triangles {
+            assert_eq!(a, center);
+            assert!((b.distance(center) - 2.0).abs() < 1e-4);
+            assert!((c.distance(center) - 2.0).abs() < 1e-4);
+        }
+
+        // Larger circles use more edges so they stay round
+        assert!(circle_triangles(center, 100.0).count() > MIN_CIRCLE_SEGMENTS);
+    }
+
+    #[test]
+    pub fn line() {
+        let [a, b, c, d] = line_quad(vec2(0.0, 0.0), vec2(10.0, 0.0), 2.0).unwrap();
+
+        assert_eq!(a.y.abs(), 1.0);
+        assert_eq!(b, vec2(10.0, a.y));
+        assert_eq!(c, vec2(10.0, -a.y));
+        assert_eq!(d, vec2(0.0, -a.y));
+        assert!(line_quad(Vec2::ONE, Vec2::ONE, 2.0).is_none());
+    }
+}
@@ -0,0 +1,71 @@
+use {crate::math::align_up_u64, screen_13::prelude::*, std::sync::Arc};
+
+/// A pool of transient resources which rounds buffer sizes up to a small set of size classes.
+///
+/// Buffers which are leased every frame rarely have the exact same size twice (instance counts
+/// change as models move in and out of view), so a plain [`LazyPool`] keeps creating new buffers.
+/// Rounding each request up to one of four sizes per power of two lets those leases reuse the
+/// buffers returned by previous frames, while wasting at most a quarter of each buffer.
+#[derive(Debug)]
+pub struct SizeClassPool {
+    pool: LazyPool,
+}
+
+impl SizeClassPool {
+    /// The smallest buffer size leased, in bytes.
+    const MIN_SIZE: vk::DeviceSize = 256;
+
+    pub fn new(device: &Arc<Device>) -> Self {
+        Self {
+            pool: LazyPool::new(device),
+        }
+    }
+
+    /// Returns the size, in bytes, of the buffer leased for the given requested size.
+    fn size_class(size: vk::DeviceSize) -> vk::DeviceSize {
+        if size <= Self::MIN_SIZE {
+            return Self::MIN_SIZE;
+        }
+
+        align_up_u64(size, (size.next_power_of_two() >> 3).max(Self::MIN_SIZE))
+    }
+}
+
+impl Pool<BufferInfo, Buffer> for SizeClassPool {
+    fn lease(&mut self, mut info: BufferInfo) -> Result<Lease<Buffer>, DriverError> {
+        info.size = Self::size_class(info.size);
+
+        self.pool.lease(info)
+    }
+}
+
+impl Pool<ImageInfo, Image> for SizeClassPool {
+    fn lease(&mut self, info: ImageInfo) -> Result<Lease<Image>, DriverError> {
+        self.pool.lease(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn size_class() {
+        assert_eq!(SizeClassPool::size_class(0), 256);
+        assert_eq!(SizeClassPool::size_class(1), 256);
+        assert_eq!(SizeClassPool::size_class(256), 256);
+        assert_eq!(SizeClassPool::size_class(257), 512);
+        assert_eq!(SizeClassPool::size_class(1024), 1024);
+        assert_eq!(SizeClassPool::size_class(1025), 1280);
+        assert_eq!(SizeClassPool::size_class(1281), 1536);
+        assert_eq!(SizeClassPool::size_class(2000), 2048);
+        assert_eq!(SizeClassPool::size_class(100_000), 114_688);
+
+        for size in 1..10_000 {
+            let size_class = SizeClassPool::size_class(size);
+
+            assert!(size_class >= size);
+            assert!(size_class <= (size * 2).max(SizeClassPool::MIN_SIZE));
+        }
+    }
+}
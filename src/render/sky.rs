@@ -0,0 +1,430 @@
+use {
+    super::{camera::Camera, debug::capture::pass_name},
+    crate::res,
+    anyhow::Context,
+    bytemuck::{bytes_of, Pod, Zeroable},
+    glam::{Mat4, Quat, Vec2, Vec3},
+    pak::PakBuf,
+    screen_13::prelude::*,
+    std::{f32::consts::TAU, sync::Arc},
+};
+
+#[cfg(not(feature = "hot-shaders"))]
+use super::read_blob;
+
+#[cfg(feature = "hot-shaders")]
+use {
+    super::{hot_shader::watch, res_shader_dir},
+    screen_13_hot::prelude::*,
+};
+
+/// Parameters of the analytic daylight sky which is drawn wherever no model is, and which both
+/// techniques use as the environment.
+///
+/// Levels set these using the properties of a scene ref, see [`crate::level::Level::read_sky`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sky {
+    /// Seconds for a full day to pass, or zero for a sky which stays at `time_of_day`.
+    pub day_length: f32,
+
+    /// Hours since midnight: the sun rises at 6, is highest at 12 and sets at 18.
+    pub time_of_day: f32,
+
+    /// Degrees, around the Y axis, from +X to the direction of sunrise.
+    pub sun_azimuth: f32,
+
+    /// Degrees above the horizon of the sun at noon.
+    pub sun_max_elevation: f32,
+
+    /// Amount of haze in the air: around 2 is a clear day and 10 is a hazy day.
+    pub turbidity: f32,
+
+    /// Color which fogged surfaces, and the sky near the horizon, fade towards.
+    pub fog_color: Vec3,
+
+    /// Fog per meter at `fog_height`, or zero for no fog.
+    pub fog_density: f32,
+
+    /// Height, in meters, at which the fog has `fog_density`.
+    pub fog_height: f32,
+
+    /// Rate at which the fog thins above `fog_height` and thickens below it; zero is even fog.
+    pub fog_height_falloff: f32,
+
+    /// Brightness of the shafts of sunlight which shine past the edges of models in front of the
+    /// sun, or zero for none.
+    pub god_rays: f32,
+}
+
+impl Sky {
+    /// Moves the time of day forward by the given seconds, wrapping around at midnight.
+    pub fn advance(&mut self, secs: f32) {
+        if self.day_length > 0.0 {
+            self.time_of_day = (self.time_of_day + secs * 24.0 / self.day_length).rem_euclid(24.0);
+        }
+    }
+
+    /// Returns the unit direction towards the sun, which is below the horizon at night.
+    pub fn sun_direction(&self) -> Vec3 {
+        // Angle travelled from the sunrise horizon, so that 6:00 is 0 and noon is a quarter turn
+        let angle = (self.time_of_day / 24.0 - 0.25) * TAU;
+
+        // The sun path is tilted away from straight overhead so that noon has the given elevation
+        let tilt = (90.0 - self.sun_max_elevation.clamp(0.0, 90.0)).to_radians();
+        let rotation =
+            Quat::from_rotation_y(self.sun_azimuth.to_radians()) * Quat::from_rotation_x(tilt);
+
+        rotation.mul_vec3(Vec3::new(angle.cos(), angle.sin(), 0.0))
+    }
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self {
+            day_length: 0.0,
+            time_of_day: 14.0,
+            sun_azimuth: 0.0,
+            sun_max_elevation: 60.0,
+            turbidity: 2.5,
+            fog_color: Vec3::new(0.5, 0.55, 0.6),
+            fog_density: 0.0,
+            fog_height: 0.0,
+            fog_height_falloff: 0.2,
+            god_rays: 0.0,
+        }
+    }
+}
+
+/// Draws the sky as a full-screen pass over those pixels which were not covered by any model, and
+/// the god rays of the sun over the whole framebuffer.
+#[derive(Debug)]
+pub struct SkyPipeline {
+    #[cfg(not(feature = "hot-shaders"))]
+    god_rays: Arc<GraphicPipeline>,
+
+    #[cfg(feature = "hot-shaders")]
+    god_rays: HotGraphicPipeline,
+
+    #[cfg(not(feature = "hot-shaders"))]
+    pipeline: Arc<GraphicPipeline>,
+
+    #[cfg(feature = "hot-shaders")]
+    pipeline: HotGraphicPipeline,
+}
+
+impl SkyPipeline {
+    /// Only pixels left at the cleared depth, which is infinitely far with reverse-Z, pass.
+    const DEPTH_STENCIL_MODE: DepthStencilMode = DepthStencilMode {
+        compare_op: vk::CompareOp::EQUAL,
+        depth_test: true,
+        depth_write: false,
+        ..DepthStencilMode::DEPTH_WRITE
+    };
+
+    #[cfg(not(feature = "hot-shaders"))]
+    pub fn new(device: &Arc<Device>, res_pak: &mut PakBuf) -> anyhow::Result<Self> {
+        let pipeline = Arc::new(
+            GraphicPipeline::create(
+                device,
+                GraphicPipelineInfo::new(),
+                [
+                    Shader::new_vertex(
+                        read_blob(res_pak, res::SHADER_FULLSCREEN_VERT_SPIRV)?.as_slice(),
+                    ),
+                    Shader::new_fragment(
+                        read_blob(res_pak, res::SHADER_SKY_FRAG_SPIRV)?.as_slice(),
+                    ),
+                ],
+            )
+            .context("Creating sky pipeline")?,
+        );
+        let god_rays = Arc::new(
+            GraphicPipeline::create(
+                device,
+                Self::god_rays_info(),
+                [
+                    Shader::new_vertex(
+                        read_blob(res_pak, res::SHADER_FULLSCREEN_VERT_SPIRV)?.as_slice(),
+                    ),
+                    Shader::new_fragment(
+                        read_blob(res_pak, res::SHADER_GOD_RAYS_FRAG_SPIRV)?.as_slice(),
+                    )
+                    .image_sampler(0, Self::depth_sampler_info()),
+                ],
+            )
+            .context("Creating god rays pipeline")?,
+        );
+
+        Ok(Self { god_rays, pipeline })
+    }
+
+    #[cfg(feature = "hot-shaders")]
+    pub fn new(device: &Arc<Device>) -> anyhow::Result<Self> {
+        let shader_dir = res_shader_dir();
+        let pipeline = HotGraphicPipeline::create(
+            device,
+            GraphicPipelineInfo::new(),
+            [
+                HotShader::new_vertex(watch(shader_dir.join("fullscreen.vert"))),
+                HotShader::new_fragment(watch(shader_dir.join("sky.frag"))),
+            ],
+        )
+        .context("Creating hot sky pipeline")?;
+        let god_rays = HotGraphicPipeline::create(
+            device,
+            Self::god_rays_info(),
+            [
+                HotShader::new_vertex(watch(shader_dir.join("fullscreen.vert"))),
+                HotShader::new_fragment(watch(shader_dir.join("god_rays.frag")))
+                    .image_sampler(0, Self::depth_sampler_info()),
+            ],
+        )
+        .context("Creating hot god rays pipeline")?;
+
+        Ok(Self { god_rays, pipeline })
+    }
+
+    fn depth_sampler_info() -> SamplerInfo {
+        SamplerInfo::new()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .build()
+    }
+
+    #[inline(always)]
+    fn god_rays(&mut self) -> &Arc<GraphicPipeline> {
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.god_rays;
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.god_rays.hot();
+
+        res
+    }
+
+    /// God rays add to the light already in the framebuffer.
+    fn god_rays_info() -> GraphicPipelineInfoBuilder {
+        GraphicPipelineInfo::new().blend(BlendMode {
+            blend_enable: true,
+            src_color_blend_factor: vk::BlendFactor::ONE,
+            dst_color_blend_factor: vk::BlendFactor::ONE,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ZERO,
+            dst_alpha_blend_factor: vk::BlendFactor::ONE,
+            alpha_blend_op: vk::BlendOp::ADD,
+            color_write_mask: vk::ColorComponentFlags::RGBA,
+        })
+    }
+
+    #[inline(always)]
+    fn pipeline(&mut self) -> &Arc<GraphicPipeline> {
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.pipeline;
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.pipeline.hot();
+
+        res
+    }
+
+    /// Records drawing of the sky using the depth image of a previous pass, which must use
+    /// reverse-Z and have been cleared to zero.
+    pub fn record(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        framebuffer: impl Into<AnyImageNode>,
+        depth_image: impl Into<AnyImageNode>,
+        camera: &Camera,
+        projection_view: Mat4,
+        sky: Sky,
+    ) {
+        #[derive(Clone, Copy, Pod, Zeroable)]
+        #[repr(C)]
+        struct PushConstants {
+            inv_projection_view: Mat4,
+            camera_position: Vec3,
+            turbidity: f32,
+            sun_direction: Vec3,
+            fog_density: f32,
+            fog_color: Vec3,
+            fog_height: f32,
+            fog_height_falloff: f32,
+            _0: [u8; 12],
+        }
+
+        let framebuffer = framebuffer.into();
+        let push_consts = PushConstants {
+            inv_projection_view: projection_view.inverse(),
+            camera_position: camera.position,
+            turbidity: sky.turbidity,
+            sun_direction: sky.sun_direction(),
+            fog_density: sky.fog_density,
+            fog_color: sky.fog_color,
+            fog_height: sky.fog_height,
+            fog_height_falloff: sky.fog_height_falloff,
+            _0: Default::default(),
+        };
+
+        render_graph
+            .begin_pass(pass_name("Sky"))
+            .bind_pipeline(self.pipeline())
+            .set_depth_stencil(Self::DEPTH_STENCIL_MODE)
+            .load_color(0, framebuffer)
+            .store_color(0, framebuffer)
+            .load_depth_stencil(depth_image)
+            .record_subpass(move |subpass, _| {
+                subpass
+                    .push_constants(bytes_of(&push_consts))
+                    .draw(3, 1, 0, 0);
+            });
+    }
+
+    /// Records shafts of sunlight shining past the edges of models in front of the sun, using the
+    /// depth image of a previous pass as in [`Self::record`], which must be sampled.
+    ///
+    /// Nothing is recorded when the sky has no god rays or the sun is behind the camera.
+    pub fn record_god_rays(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        framebuffer: impl Into<AnyImageNode>,
+        depth_image: impl Into<AnyImageNode>,
+        projection_view: Mat4,
+        sky: Sky,
+    ) {
+        #[derive(Clone, Copy, Pod, Zeroable)]
+        #[repr(C)]
+        struct PushConstants {
+            sun_direction: Vec3,
+            turbidity: f32,
+            sun_position: Vec2,
+            intensity: f32,
+        }
+
+        let sun_direction = sky.sun_direction();
+        let Some((sun_position, intensity)) =
+            god_rays_source(projection_view, sun_direction, sky.god_rays)
+        else {
+            return;
+        };
+
+        let framebuffer = framebuffer.into();
+        let push_consts = PushConstants {
+            sun_direction,
+            turbidity: sky.turbidity,
+            sun_position,
+            intensity,
+        };
+
+        render_graph
+            .begin_pass(pass_name("God rays"))
+            .bind_pipeline(self.god_rays())
+            .read_descriptor(0, depth_image)
+            .load_color(0, framebuffer)
+            .store_color(0, framebuffer)
+            .record_subpass(move |subpass, _| {
+                subpass
+                    .push_constants(bytes_of(&push_consts))
+                    .draw(3, 1, 0, 0);
+            });
+    }
+}
+
+/// Returns the texture coordinates of the sun on screen and the intensity of its god rays, which
+/// fade out as the sun leaves the screen; or `None` if there are none to draw.
+fn god_rays_source(
+    projection_view: Mat4,
+    sun_direction: Vec3,
+    god_rays: f32,
+) -> Option<(Vec2, f32)> {
+    if god_rays <= 0.0 {
+        return None;
+    }
+
+    // The sun is infinitely far away, so only the direction towards it is projected
+    let clip = projection_view * sun_direction.extend(0.0);
+
+    if clip.w <= 0.0 {
+        return None;
+    }
+
+    let ndc = clip.truncate().truncate() / clip.w;
+    let fade = (2.0 - ndc.abs().max_element()).clamp(0.0, 1.0);
+
+    (fade > 0.0).then(|| (ndc * 0.5 + 0.5, god_rays * fade))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn advance() {
+        let mut sky = Sky {
+            time_of_day: 23.0,
+            ..Default::default()
+        };
+
+        sky.advance(60.0);
+
+        assert_eq!(sky.time_of_day, 23.0);
+
+        sky.day_length = 240.0;
+        sky.advance(20.0);
+
+        assert!((sky.time_of_day - 1.0).abs() < 1e-4);
+
+        sky.advance(-20.0);
+
+        assert!((sky.time_of_day - 23.0).abs() < 1e-4);
+    }
+
+    #[test]
+    pub fn sun_direction() {
+        let mut sky = Sky {
+            day_length: 0.0,
+            time_of_day: 12.0,
+            sun_azimuth: 0.0,
+            sun_max_elevation: 90.0,
+            turbidity: 2.0,
+            ..Default::default()
+        };
+
+        assert!(sky.sun_direction().abs_diff_eq(Vec3::Y, 1e-5));
+
+        sky.time_of_day = 6.0;
+
+        assert!(sky.sun_direction().abs_diff_eq(Vec3::X, 1e-5));
+
+        sky.time_of_day = 12.0;
+        sky.sun_max_elevation = 30.0;
+
+        assert!((sky.sun_direction().y - 30f32.to_radians().sin()).abs() < 1e-5);
+
+        sky.time_of_day = 0.0;
+
+        assert!(sky.sun_direction().y < 0.0);
+    }
+
+    #[test]
+    pub fn god_rays_source_on_screen() {
+        let camera = Camera {
+            aspect_ratio: 1.0,
+            fov_y: 60.0,
+            pitch: 0.0,
+            yaw: 0.0,
+            position: Vec3::ONE,
+        };
+        let projection_view = camera.projection_view(camera.aspect_ratio, Camera::Z_NEAR);
+        let forward = -Vec3::Z;
+
+        assert_eq!(god_rays_source(projection_view, forward, 0.0), None);
+        assert_eq!(god_rays_source(projection_view, -forward, 1.0), None);
+
+        let (sun_position, intensity) = god_rays_source(projection_view, forward, 1.0).unwrap();
+
+        assert!(sun_position.abs_diff_eq(Vec2::splat(0.5), 1e-5));
+        assert_eq!(intensity, 1.0);
+    }
+}
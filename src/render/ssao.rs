@@ -0,0 +1,301 @@
+use {
+    super::{camera::Camera, debug::capture::pass_name, model::AmbientOcclusion},
+    crate::res,
+    anyhow::Context,
+    bytemuck::{bytes_of, Pod, Zeroable},
+    glam::Vec2,
+    pak::PakBuf,
+    screen_13::prelude::*,
+    std::{mem::size_of, sync::Arc},
+};
+
+#[cfg(not(feature = "hot-shaders"))]
+use super::read_blob;
+
+#[cfg(feature = "hot-shaders")]
+use {
+    super::{hot_shader::watch, res_shader_dir},
+    screen_13_hot::prelude::*,
+};
+
+/// Sampling used for each quality level of ambient occlusion.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Quality {
+    direction_count: u32,
+    step_count: u32,
+
+    /// Occlusion is computed at the framebuffer size shifted right by this amount.
+    resolution_shift: u32,
+}
+
+impl Quality {
+    fn new(ambient_occlusion: AmbientOcclusion) -> Option<Self> {
+        let (direction_count, step_count, resolution_shift) = match ambient_occlusion {
+            AmbientOcclusion::Off => return None,
+            AmbientOcclusion::Low => (4, 3, 1),
+            AmbientOcclusion::Medium => (6, 4, 1),
+            AmbientOcclusion::High => (8, 6, 0),
+        };
+
+        Some(Self {
+            direction_count,
+            step_count,
+            resolution_shift,
+        })
+    }
+
+    fn specialization_info(self) -> SpecializationInfo {
+        SpecializationInfo::new(
+            [
+                vk::SpecializationMapEntry {
+                    constant_id: 0,
+                    offset: 0,
+                    size: size_of::<u32>(),
+                },
+                vk::SpecializationMapEntry {
+                    constant_id: 1,
+                    offset: size_of::<u32>() as _,
+                    size: size_of::<u32>(),
+                },
+            ],
+            bytes_of(&[self.direction_count, self.step_count]),
+        )
+    }
+}
+
+/// Screen-space ambient occlusion which darkens creases and contacts using only a depth buffer,
+/// approximating the shadowing which the ray trace technique gets for free.
+#[derive(Debug)]
+pub struct SsaoPipeline {
+    #[cfg(not(feature = "hot-shaders"))]
+    apply: Arc<GraphicPipeline>,
+
+    #[cfg(feature = "hot-shaders")]
+    apply: HotGraphicPipeline,
+
+    #[cfg(not(feature = "hot-shaders"))]
+    occlusion: Arc<GraphicPipeline>,
+
+    #[cfg(feature = "hot-shaders")]
+    occlusion: HotGraphicPipeline,
+
+    quality: Quality,
+}
+
+impl SsaoPipeline {
+    /// World-space distance within which surfaces occlude each other.
+    const RADIUS: f32 = 0.75;
+
+    const INTENSITY: f32 = 1.5;
+
+    /// Returns `None` when ambient occlusion is off.
+    #[cfg(not(feature = "hot-shaders"))]
+    pub fn new(
+        device: &Arc<Device>,
+        res_pak: &mut PakBuf,
+        ambient_occlusion: AmbientOcclusion,
+    ) -> anyhow::Result<Option<Self>> {
+        let Some(quality) = Quality::new(ambient_occlusion) else {
+            return Ok(None);
+        };
+
+        let fullscreen_vert = read_blob(res_pak, res::SHADER_FULLSCREEN_VERT_SPIRV)?;
+
+        let apply = Arc::new(
+            GraphicPipeline::create(
+                device,
+                Self::apply_info(),
+                [
+                    Shader::new_vertex(fullscreen_vert.as_slice()),
+                    Shader::new_fragment(
+                        read_blob(res_pak, res::SHADER_SSAO_APPLY_FRAG_SPIRV)?.as_slice(),
+                    )
+                    .image_sampler(0, Self::linear_sampler_info()),
+                ],
+            )
+            .context("Creating apply pipeline")?,
+        );
+
+        let occlusion = Arc::new(
+            GraphicPipeline::create(
+                device,
+                GraphicPipelineInfo::new(),
+                [
+                    Shader::new_vertex(fullscreen_vert.as_slice()),
+                    Shader::new_fragment(
+                        read_blob(res_pak, res::SHADER_SSAO_OCCLUSION_FRAG_SPIRV)?.as_slice(),
+                    )
+                    .specialization_info(quality.specialization_info())
+                    .image_sampler(0, Self::nearest_sampler_info()),
+                ],
+            )
+            .context("Creating occlusion pipeline")?,
+        );
+
+        Ok(Some(Self {
+            apply,
+            occlusion,
+            quality,
+        }))
+    }
+
+    /// Returns `None` when ambient occlusion is off.
+    #[cfg(feature = "hot-shaders")]
+    pub fn new(
+        device: &Arc<Device>,
+        ambient_occlusion: AmbientOcclusion,
+    ) -> anyhow::Result<Option<Self>> {
+        let Some(quality) = Quality::new(ambient_occlusion) else {
+            return Ok(None);
+        };
+
+        let shader_dir = res_shader_dir();
+
+        let apply = HotGraphicPipeline::create(
+            device,
+            Self::apply_info(),
+            [
+                HotShader::new_vertex(watch(shader_dir.join("fullscreen.vert"))),
+                HotShader::new_fragment(watch(shader_dir.join("ssao/apply.frag")))
+                    .image_sampler(0, Self::linear_sampler_info()),
+            ],
+        )
+        .context("Creating hot apply pipeline")?;
+
+        let occlusion = HotGraphicPipeline::create(
+            device,
+            GraphicPipelineInfo::new(),
+            [
+                HotShader::new_vertex(watch(shader_dir.join("fullscreen.vert"))),
+                HotShader::new_fragment(watch(shader_dir.join("ssao/occlusion.frag")))
+                    .specialization_info(quality.specialization_info())
+                    .image_sampler(0, Self::nearest_sampler_info()),
+            ],
+        )
+        .context("Creating hot occlusion pipeline")?;
+
+        Ok(Some(Self {
+            apply,
+            occlusion,
+            quality,
+        }))
+    }
+
+    #[inline(always)]
+    fn apply(&mut self) -> &Arc<GraphicPipeline> {
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.apply;
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.apply.hot();
+
+        res
+    }
+
+    /// The framebuffer is multiplied by the occlusion.
+    fn apply_info() -> GraphicPipelineInfoBuilder {
+        GraphicPipelineInfo::new().blend(BlendMode {
+            blend_enable: true,
+            src_color_blend_factor: vk::BlendFactor::ZERO,
+            dst_color_blend_factor: vk::BlendFactor::SRC_COLOR,
+            color_blend_op: vk::BlendOp::ADD,
+            src_alpha_blend_factor: vk::BlendFactor::ZERO,
+            dst_alpha_blend_factor: vk::BlendFactor::ONE,
+            alpha_blend_op: vk::BlendOp::ADD,
+            color_write_mask: vk::ColorComponentFlags::RGBA,
+        })
+    }
+
+    fn linear_sampler_info() -> SamplerInfo {
+        SamplerInfo::new()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .build()
+    }
+
+    fn nearest_sampler_info() -> SamplerInfo {
+        SamplerInfo::new()
+            .mag_filter(vk::Filter::NEAREST)
+            .min_filter(vk::Filter::NEAREST)
+            .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+            .build()
+    }
+
+    #[inline(always)]
+    fn occlusion(&mut self) -> &Arc<GraphicPipeline> {
+        #[cfg(not(feature = "hot-shaders"))]
+        let res = &self.occlusion;
+
+        #[cfg(feature = "hot-shaders")]
+        let res = self.occlusion.hot();
+
+        res
+    }
+
+    /// Records ambient occlusion of the given reverse-Z depth image, which must be sampled, and
+    /// multiplies the framebuffer by it.
+    ///
+    /// `aspect_ratio` and `z_near` must match the projection used to draw the depth image.
+    pub fn record(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        pool: &mut impl Pool<ImageInfoBuilder, Image>,
+        framebuffer: impl Into<AnyImageNode>,
+        depth_image: impl Into<AnyImageNode>,
+        camera: &Camera,
+        aspect_ratio: f32,
+        z_near: f32,
+    ) -> Result<(), DriverError> {
+        #[derive(Clone, Copy, Pod, Zeroable)]
+        #[repr(C)]
+        struct PushConstants {
+            inv_focal_len: Vec2,
+            z_near: f32,
+            radius: f32,
+            intensity: f32,
+        }
+
+        let framebuffer = framebuffer.into();
+        let depth_image = depth_image.into();
+        let framebuffer_info = render_graph.node_info(framebuffer);
+        let occlusion_image = render_graph.bind_node(pool.lease(ImageInfo::new_2d(
+            vk::Format::R8_UNORM,
+            (framebuffer_info.width >> self.quality.resolution_shift).max(1),
+            (framebuffer_info.height >> self.quality.resolution_shift).max(1),
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+        ))?);
+
+        let push_consts = PushConstants {
+            inv_focal_len: Vec2::new(aspect_ratio, 1.0) * (camera.fov_y.to_radians() * 0.5).tan(),
+            z_near,
+            radius: Self::RADIUS,
+            intensity: Self::INTENSITY,
+        };
+
+        render_graph
+            .begin_pass(pass_name("Ambient occlusion"))
+            .bind_pipeline(self.occlusion())
+            .read_descriptor(0, depth_image)
+            .store_color(0, occlusion_image)
+            .record_subpass(move |subpass, _| {
+                subpass
+                    .push_constants(bytes_of(&push_consts))
+                    .draw(3, 1, 0, 0);
+            });
+
+        render_graph
+            .begin_pass(pass_name("Apply ambient occlusion"))
+            .bind_pipeline(self.apply())
+            .read_descriptor(0, occlusion_image)
+            .load_color(0, framebuffer)
+            .store_color(0, framebuffer)
+            .record_subpass(move |subpass, _| {
+                subpass.draw(3, 1, 0, 0);
+            });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use screen_13::prelude::*;
+
+/// The queue used to upload asset data: a dedicated transfer queue family if the device has one,
+/// otherwise the graphics queue family.
+///
+/// Uploads submitted to a dedicated transfer queue run alongside the frame loop instead of being
+/// serialized with graphics work.
+#[derive(Clone, Copy, Debug)]
+pub struct TransferQueue {
+    family_index: usize,
+    queue_count: usize,
+}
+
+impl TransferQueue {
+    pub fn new(device: &Device) -> Self {
+        let families = &device.physical_device.queue_families;
+        let family_index = dedicated_family_index(families).unwrap_or_default();
+
+        if family_index != 0 {
+            debug!("Uploading using transfer queue family {family_index}");
+        }
+
+        Self {
+            family_index,
+            queue_count: families[family_index].queue_count.max(1) as _,
+        }
+    }
+
+    /// Submits a render graph containing only transfer commands.
+    ///
+    /// The returned command buffer must be waited on before any resource it writes is read by
+    /// another queue; see [`PendingUploads`].
+    pub fn submit(
+        self,
+        render_graph: RenderGraph,
+        pool: &mut LazyPool,
+        queue_index: usize,
+    ) -> Result<Lease<CommandBuffer>, DriverError> {
+        render_graph
+            .resolve()
+            .submit(pool, self.family_index, queue_index % self.queue_count)
+    }
+}
+
+/// Returns the index of a queue family which supports transfers but not graphics or compute.
+fn dedicated_family_index(families: &[vk::QueueFamilyProperties]) -> Option<usize> {
+    families.iter().position(|family| {
+        family.queue_count > 0
+            && family.queue_flags.contains(vk::QueueFlags::TRANSFER)
+            && !family
+                .queue_flags
+                .intersects(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+    })
+}
+
+/// Uploads which were submitted to the [`TransferQueue`] and may still be executing.
+///
+/// The render graph cannot wait on semaphores signalled by another queue, so instead any pending
+/// uploads are waited on the first time the uploaded resources are recorded into a frame. Loading
+/// usually finishes well before that happens, in which case waiting costs nothing.
+#[derive(Debug, Default)]
+pub struct PendingUploads {
+    cmd_bufs: Vec<Lease<CommandBuffer>>,
+}
+
+impl PendingUploads {
+    pub fn push(&mut self, cmd_buf: Lease<CommandBuffer>) -> Result<(), DriverError> {
+        // Drop uploads which have already finished so this does not grow while loading
+        let mut res = Ok(());
+        self.cmd_bufs
+            .retain(|cmd_buf| match cmd_buf.has_executed() {
+                Ok(has_executed) => !has_executed,
+                Err(err) => {
+                    res = Err(err);
+
+                    true
+                }
+            });
+        self.cmd_bufs.push(cmd_buf);
+
+        res
+    }
+
+    /// Blocks until all pending uploads have finished executing.
+    pub fn wait(&mut self) -> Result<(), DriverError> {
+        for mut cmd_buf in self.cmd_bufs.drain(..) {
+            cmd_buf.wait_until_executed()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn family(queue_flags: vk::QueueFlags) -> vk::QueueFamilyProperties {
+        vk::QueueFamilyProperties {
+            queue_flags,
+            queue_count: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    pub fn dedicated_transfer_family() {
+        let graphics =
+            family(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER);
+        let compute = family(vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER);
+        let transfer = family(vk::QueueFlags::TRANSFER | vk::QueueFlags::SPARSE_BINDING);
+
+        assert_eq!(dedicated_family_index(&[graphics, compute]), None);
+        assert_eq!(
+            dedicated_family_index(&[graphics, compute, transfer]),
+            Some(2)
+        );
+    }
+}
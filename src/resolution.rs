@@ -0,0 +1,137 @@
+/// Lowers the framebuffer height while frames take longer than the framerate limit allows, and
+/// raises it again once there is time to spare.
+///
+/// The scale only changes in small steps, at most once every [`Self::COOLDOWN`] frames, and frame
+/// times between the lower and raise thresholds leave it unchanged so that it does not oscillate
+/// between two sizes. The HUD is drawn into an image of its own at full scale, so it stays sharp
+/// while the scale falls; other UIs are drawn into the framebuffer and grow as the scale falls.
+#[derive(Debug)]
+pub struct DynamicResolution {
+    /// Frames remaining until the scale may change again.
+    cooldown: u32,
+
+    /// Smoothed time, in seconds, of recent frames.
+    frame_time: Option<f32>,
+
+    scale: f32,
+}
+
+impl DynamicResolution {
+    /// Frames after each change before the scale may change again, so that the effect of each
+    /// change is measured before the next.
+    const COOLDOWN: u32 = 30;
+
+    /// Fraction of the target frame time above which the scale is lowered.
+    const LOWER_THRESHOLD: f32 = 0.95;
+
+    pub const MAX_SCALE: f32 = 1.0;
+    pub const MIN_SCALE: f32 = 0.5;
+
+    /// Fraction of the target frame time below which the scale is raised.
+    const RAISE_THRESHOLD: f32 = 0.75;
+
+    /// Fraction of the previous smoothed frame time kept with each new frame.
+    const SMOOTHING: f32 = 0.9;
+
+    /// Amount the scale changes at once.
+    const STEP: f32 = 0.05;
+
+    /// Returns the given framebuffer height at the current scale.
+    pub fn framebuffer_height(&self, framebuffer_height: u32) -> u32 {
+        ((framebuffer_height as f32 * self.scale).round() as u32).max(1)
+    }
+
+    /// Fraction (`MIN_SCALE..=MAX_SCALE`) of the full framebuffer height which is drawn.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Adds the time (in seconds) a frame took, which is GPU time where timestamps are supported
+    /// and otherwise the CPU time without any wait for the framerate limit, and adjusts the scale
+    /// towards the given target frame time.
+    pub fn update(&mut self, frame_time: f32, target_frame_time: f32) {
+        let frame_time = self
+            .frame_time
+            .map(|smoothed| smoothed * Self::SMOOTHING + frame_time * (1.0 - Self::SMOOTHING))
+            .unwrap_or(frame_time);
+        self.frame_time = Some(frame_time);
+
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+
+            return;
+        }
+
+        let scale = if frame_time > target_frame_time * Self::LOWER_THRESHOLD {
+            self.scale - Self::STEP
+        } else if frame_time < target_frame_time * Self::RAISE_THRESHOLD {
+            self.scale + Self::STEP
+        } else {
+            self.scale
+        }
+        .clamp(Self::MIN_SCALE, Self::MAX_SCALE);
+
+        if scale != self.scale {
+            debug!("Resolution scale: {scale:.2}");
+
+            self.cooldown = Self::COOLDOWN;
+            self.scale = scale;
+        }
+    }
+}
+
+impl Default for DynamicResolution {
+    fn default() -> Self {
+        Self {
+            cooldown: 0,
+            frame_time: None,
+            scale: Self::MAX_SCALE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn dynamic_resolution() {
+        const TARGET: f32 = 1.0 / 60.0;
+
+        let mut resolution = DynamicResolution::default();
+
+        assert_eq!(resolution.framebuffer_height(300), 300);
+
+        // Slow frames lower the scale one step at a time
+        resolution.update(TARGET * 2.0, TARGET);
+
+        assert_eq!(resolution.scale(), 1.0 - DynamicResolution::STEP);
+
+        for _ in 0..DynamicResolution::COOLDOWN {
+            resolution.update(TARGET * 2.0, TARGET);
+        }
+
+        assert_eq!(resolution.scale(), 1.0 - DynamicResolution::STEP);
+
+        for _ in 0..1_000 {
+            resolution.update(TARGET * 2.0, TARGET);
+        }
+
+        assert_eq!(resolution.scale(), DynamicResolution::MIN_SCALE);
+        assert_eq!(resolution.framebuffer_height(300), 150);
+
+        // Frames just within the target are left alone
+        for _ in 0..1_000 {
+            resolution.update(TARGET * 0.85, TARGET);
+        }
+
+        assert_eq!(resolution.scale(), DynamicResolution::MIN_SCALE);
+
+        // Fast frames raise the scale back to full
+        for _ in 0..1_000 {
+            resolution.update(TARGET * 0.5, TARGET);
+        }
+
+        assert_eq!(resolution.scale(), DynamicResolution::MAX_SCALE);
+    }
+}
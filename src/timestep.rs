@@ -0,0 +1,67 @@
+/// Divides variable frame times into fixed simulation steps so that movement, physics and AI
+/// behave the same at any framerate.
+///
+/// Time left over after the last whole step is carried into the next frame; drawing interpolates
+/// between the previous and the current step using [`FixedTimestep::alpha`] so that motion stays
+/// smooth when the framerate is not a multiple of the step rate.
+#[derive(Debug, Default)]
+pub struct FixedTimestep {
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    /// Duration, in seconds, of each simulation step.
+    pub const DT: f32 = 1.0 / 60.0;
+
+    /// The most steps run for a single frame; after a long stall (such as a breakpoint or a
+    /// loading hitch) the simulation slows down instead of spending ever longer catching up.
+    const MAX_STEPS: u32 = 8;
+
+    /// Adds the time of a frame and returns the number of whole steps which have elapsed.
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt.max(0.0);
+
+        let steps = (self.accumulator / Self::DT) as u32;
+        self.accumulator -= steps as f32 * Self::DT;
+
+        if steps > Self::MAX_STEPS {
+            self.accumulator = 0.0;
+
+            Self::MAX_STEPS
+        } else {
+            steps
+        }
+    }
+
+    /// Fraction of a step, from `0.0` to `1.0`, which has elapsed since the latest step.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / Self::DT).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn fixed_timestep() {
+        let mut timestep = FixedTimestep::default();
+
+        assert_eq!(timestep.advance(0.0), 0);
+        assert_eq!(timestep.alpha(), 0.0);
+
+        // Half a step is carried into the next frame
+        assert_eq!(timestep.advance(FixedTimestep::DT * 0.5), 0);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-3);
+
+        assert_eq!(timestep.advance(FixedTimestep::DT * 0.75), 1);
+        assert!((timestep.alpha() - 0.25).abs() < 1e-3);
+
+        // A 30 Hz frame runs two steps
+        assert_eq!(timestep.advance(FixedTimestep::DT * 2.0), 2);
+
+        // A long stall is capped
+        assert_eq!(timestep.advance(10.0), FixedTimestep::MAX_STEPS);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+}
@@ -6,24 +6,34 @@ use {
     },
     crate::{
         art,
-        math::{Plane, Ray},
+        asset_key::SceneKey,
+        level::scene::Scene,
+        math::{Aabb, Plane, Ray},
         render::{
-            camera::Camera,
+            camera::{Camera, CameraPath},
             model::{Material, Model, ModelBuffer},
         },
     },
-    glam::{vec2, vec3, Vec3},
-    pak::scene::SceneBuf,
+    anyhow::Context,
+    glam::{vec2, vec3, Quat, Vec3},
     screen_13::prelude::*,
     screen_13_fx::BitmapFont,
     std::{
+        fmt::Write,
+        fs::write,
+        path::{Path, PathBuf},
         sync::Arc,
         time::{Duration, Instant},
     },
 };
 
+const CAMERA_PATH: &str = include_str!("../../res/bench/level_01.toml");
+
 struct Boot {
     device: Arc<Device>,
+    instance_count: usize,
+    output: Option<PathBuf>,
+    scene: SceneKey,
     step: Option<BootStep>,
 }
 
@@ -50,6 +60,18 @@ impl Ui for Boot {
                 color,
                 text,
             );
+
+            let status = loader.status();
+            let ([status_x, _], [status_width, _]) = font.measure(&status);
+
+            font.print(
+                frame.render_graph,
+                frame.framebuffer_image,
+                (framebuffer_info.width as i32 / 2 - status_width as i32 / 2 + status_x / 2) as f32,
+                (y + height as i32) as f32,
+                color,
+                status,
+            );
         }
     }
 
@@ -60,6 +82,8 @@ impl Ui for Boot {
                     Loader::spawn_threads(
                         &self.device,
                         ui.config.graphics,
+                        ui.config.texture_filtering,
+                        ui.config.ambient_occlusion,
                         LoadInfo::default().fonts(&[art::FONT_KENNEY_MINI_SQUARE_MONO]),
                     )
                     .unwrap(),
@@ -73,15 +97,17 @@ impl Ui for Boot {
                     let mut loader = loader.unwrap();
                     let font = loader
                         .fonts
-                        .remove(art::FONT_KENNEY_MINI_SQUARE_MONO)
+                        .remove(&art::FONT_KENNEY_MINI_SQUARE_MONO)
                         .unwrap();
                     let loader = Box::new(
                         Loader::spawn_threads(
                             &self.device,
                             ui.config.graphics,
+                            ui.config.texture_filtering,
+                            ui.config.ambient_occlusion,
                             LoadInfo::default()
                                 .fonts(&[art::FONT_KENNEY_MINI_SQUARE_MONO])
-                                .scenes(&[art::SCENE_LEVEL_01]),
+                                .scenes(&[self.scene]),
                         )
                         .unwrap(),
                     );
@@ -98,23 +124,27 @@ impl Ui for Boot {
                     let mut loader = loader.unwrap();
                     let mut model_buf = loader.model_buf.unwrap();
 
+                    let (scene_layer, scene) = loader.scenes.remove(&self.scene).unwrap();
                     let content = Content {
                         dare_font: loader
                             .fonts
-                            .remove(art::FONT_KENNEY_MINI_SQUARE_MONO)
+                            .remove(&art::FONT_KENNEY_MINI_SQUARE_MONO)
                             .unwrap(),
-                        level: loader.scenes.remove(art::SCENE_LEVEL_01).unwrap(),
+                        level: Scene::new(scene),
                     };
 
+                    let mut models = vec![];
+
                     for scene_ref in content.level.refs() {
-                        if let Some(model) =
-                            scene_ref.model().map(|id| loader.models[&IdOrKey::Id(id)])
+                        if let Some(model) = scene_ref
+                            .model()
+                            .map(|id| loader.models[&IdOrKey::Id(scene_layer, id)])
                         {
                             let materials = scene_ref
                                 .materials()
                                 .iter()
                                 .copied()
-                                .map(|id| loader.materials[&IdOrKey::Id(id)])
+                                .map(|id| loader.materials[&IdOrKey::Id(scene_layer, id)])
                                 .collect::<Box<_>>();
                             model_buf.insert_model_instance(
                                 model,
@@ -122,9 +152,35 @@ impl Ui for Boot {
                                 scene_ref.position(),
                                 scene_ref.rotation(),
                             );
+                            models.push((model, materials));
                         }
                     }
 
+                    let bounds = Aabb::from_points(
+                        content.level.refs().map(|scene_ref| scene_ref.position()),
+                    )
+                    .unwrap_or_else(|| Aabb::from_center_half_extents(Vec3::ZERO, Vec3::ONE));
+                    let center = (bounds.min() + bounds.max()) * 0.5;
+
+                    // Extra instances cycle through the scene models so that culling and
+                    // acceleration structure costs may be measured at a known instance count
+                    if self.instance_count > 0 {
+                        info!("Spawning {} benchmark instances", self.instance_count);
+                    }
+
+                    for (position, (model, materials)) in
+                        grid_positions(vec3(center.x, bounds.min().y, center.z))
+                            .zip(models.iter().cycle())
+                            .take(self.instance_count)
+                    {
+                        model_buf.insert_model_instance(
+                            *model,
+                            materials,
+                            position,
+                            Quat::IDENTITY,
+                        );
+                    }
+
                     let camera = {
                         let position = Vec3::new(40.0, 11.0, 0.0);
                         Camera {
@@ -136,12 +192,24 @@ impl Ui for Boot {
                         }
                     };
 
+                    // Only level 01 has a scripted flythrough; other scenes are circled
+                    let camera_path = if self.scene == art::SCENE_LEVEL_01 {
+                        CameraPath::from_toml(CAMERA_PATH).unwrap()
+                    } else {
+                        let radius = (bounds.max() - bounds.min()).length().max(10.0);
+
+                        CameraPath::orbit(center, radius, radius * 0.5, Bench::ORBIT_SECS)
+                    };
                     let bench = Bench {
                         camera,
+                        camera_path,
                         content,
                         device,
-                        frame_index: 0,
+                        frame_times: Vec::with_capacity(Bench::FRAME_CAPACITY),
+                        model_record_times: Vec::with_capacity(Bench::FRAME_CAPACITY),
                         model_buf,
+                        output: self.output.take(),
+                        path_time: 0.0,
                         time_started: Instant::now(),
                     };
 
@@ -168,26 +236,45 @@ enum BootStep {
 
 struct Content {
     dare_font: BitmapFont,
-    level: SceneBuf,
+    level: Scene,
 }
 
 pub struct Bench {
     camera: Camera,
+    camera_path: CameraPath,
     content: Content,
     device: Arc<Device>,
-    frame_index: usize,
+    frame_times: Vec<f32>,
     model_buf: ModelBuffer,
+    model_record_times: Vec<f32>,
+    output: Option<PathBuf>,
+    path_time: f32,
     // pool: LazyPool,
     time_started: Instant,
 }
 
 impl Bench {
-    const FRAME_COUNT: usize = 1000;
+    const FRAME_CAPACITY: usize = 10_000;
+
+    /// Seconds taken to circle scenes without a scripted flythrough.
+    const ORBIT_SECS: f32 = 30.0;
 
-    pub fn boot(device: &Arc<Device>) -> impl Ui {
+    /// Benchmarks the given scene, with `instance_count` extra copies of its models.
+    pub fn boot(
+        device: &Arc<Device>,
+        scene: SceneKey,
+        instance_count: usize,
+        output: Option<PathBuf>,
+    ) -> impl Ui {
         let device = Arc::clone(device);
 
-        Boot { device, step: None }
+        Boot {
+            device,
+            instance_count,
+            output,
+            scene,
+            step: None,
+        }
     }
 }
 
@@ -196,6 +283,9 @@ impl Ui for Bench {
         let framebuffer_info = frame.render_graph.node_info(frame.framebuffer_image);
 
         self.camera.aspect_ratio = framebuffer_info.width as f32 / framebuffer_info.height as f32;
+        self.camera_path.sample(self.path_time, &mut self.camera);
+
+        let started = Instant::now();
 
         self.model_buf
             .record(
@@ -206,17 +296,30 @@ impl Ui for Bench {
             )
             .unwrap();
 
-        self.frame_index += 1;
+        self.model_record_times
+            .push((Instant::now() - started).as_secs_f32());
     }
 
-    fn update(self: Box<Self>, ui: UpdateContext) -> Option<Box<dyn Ui>> {
-        if self.frame_index == Self::FRAME_COUNT {
-            let frames_per_sec = Self::FRAME_COUNT * 1_000
-                / Instant::now().duration_since(self.time_started).as_millis() as usize;
+    fn update(mut self: Box<Self>, ui: UpdateContext) -> Option<Box<dyn Ui>> {
+        self.frame_times.push(ui.dt);
+        self.model_buf.advance_time(ui.dt);
+        self.path_time += ui.dt;
+
+        if self.path_time >= self.camera_path.duration() {
+            let elapsed = (Instant::now() - self.time_started).as_secs_f32();
+            let report = BenchReport::new(&self.frame_times, &self.model_record_times, elapsed);
+
+            info!("{report:#?}");
+
+            if let Some(output) = &self.output {
+                if let Err(err) = report.write(output) {
+                    error!("Unable to write benchmark output: {err:?}");
+                }
+            }
 
             Some(Box::new(BenchResult {
                 font: self.content.dare_font,
-                frames_per_sec,
+                report,
             }))
         } else if ui.keyboard.any_pressed() {
             None
@@ -226,9 +329,137 @@ impl Ui for Bench {
     }
 }
 
+/// Frame timing statistics of a completed benchmark run; all times are in milliseconds.
+#[derive(Debug)]
+struct BenchReport {
+    avg_frame_time: f32,
+    frame_times: Vec<f32>,
+    frames_per_sec: f32,
+    max_frame_time: f32,
+    min_frame_time: f32,
+
+    /// Average CPU time spent recording the model passes onto the render graph, which does not
+    /// include the time the GPU spends executing them.
+    model_cpu_record_time: f32,
+
+    p99_frame_time: f32,
+}
+
+impl BenchReport {
+    fn new(frame_times: &[f32], model_record_times: &[f32], elapsed_secs: f32) -> Self {
+        let frame_times = frame_times
+            .iter()
+            .map(|secs| secs * 1_000.0)
+            .collect::<Vec<_>>();
+        let mut sorted_frame_times = frame_times.clone();
+        sorted_frame_times.sort_by(f32::total_cmp);
+
+        Self {
+            avg_frame_time: average(&frame_times),
+            frames_per_sec: frame_times.len() as f32 / elapsed_secs.max(f32::EPSILON),
+            max_frame_time: sorted_frame_times.last().copied().unwrap_or_default(),
+            min_frame_time: sorted_frame_times.first().copied().unwrap_or_default(),
+            model_cpu_record_time: average(model_record_times) * 1_000.0,
+            p99_frame_time: percentile(&sorted_frame_times, 0.99),
+            frame_times,
+        }
+    }
+
+    /// Returns a row for each frame followed by summary rows, which are named in place of the
+    /// frame index.
+    fn to_csv(&self) -> String {
+        let mut res = String::from("frame,frame_time_ms\n");
+
+        for (frame_index, frame_time) in self.frame_times.iter().enumerate() {
+            writeln!(res, "{frame_index},{frame_time}").unwrap();
+        }
+
+        for (name, frame_time) in [
+            ("min", self.min_frame_time),
+            ("avg", self.avg_frame_time),
+            ("p99", self.p99_frame_time),
+            ("max", self.max_frame_time),
+        ] {
+            writeln!(res, "{name},{frame_time}").unwrap();
+        }
+
+        res
+    }
+
+    fn to_json(&self) -> String {
+        let frame_times = self
+            .frame_times
+            .iter()
+            .map(|frame_time| frame_time.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{{\"frames\":{},\"frames_per_sec\":{},\"min_frame_time_ms\":{},\
+            \"avg_frame_time_ms\":{},\"p99_frame_time_ms\":{},\"max_frame_time_ms\":{},\
+            \"cpu_record_time_ms\":{{\"model\":{}}},\"frame_times_ms\":[{frame_times}]}}",
+            self.frame_times.len(),
+            self.frames_per_sec,
+            self.min_frame_time,
+            self.avg_frame_time,
+            self.p99_frame_time,
+            self.max_frame_time,
+            self.model_cpu_record_time,
+        )
+    }
+
+    /// Writes the report as CSV if the path has a `csv` extension, otherwise as JSON.
+    fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let is_csv = path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("csv"))
+            .unwrap_or_default();
+        let data = if is_csv {
+            self.to_csv()
+        } else {
+            self.to_json()
+        };
+
+        write(path, data).with_context(|| format!("Writing {}", path.display()))?;
+
+        info!("Wrote benchmark output to {}", path.display());
+
+        Ok(())
+    }
+}
+
+/// Returns positions on the XZ plane which spiral outward from `center` over a square grid, so
+/// that any number of positions stays compact.
+fn grid_positions(center: Vec3) -> impl Iterator<Item = Vec3> {
+    const SPACING: f32 = 4.0;
+
+    (0..).flat_map(move |ring: i32| {
+        // Each ring is the border of a square which is two cells wider than the previous one
+        (-ring..=ring)
+            .flat_map(move |x| (-ring..=ring).map(move |z| (x, z)))
+            .filter(move |(x, z)| x.abs() == ring || z.abs() == ring)
+            .map(move |(x, z)| center + vec3(x as f32, 0.0, z as f32) * SPACING)
+    })
+}
+
+fn average(values: &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len().max(1) as f32
+}
+
+/// Returns the value at the given percentile (`0.0..=1.0`) of already-sorted values.
+fn percentile(sorted_values: &[f32], percentile: f32) -> f32 {
+    if sorted_values.is_empty() {
+        return 0.0;
+    }
+
+    let index = ((sorted_values.len() - 1) as f32 * percentile).round() as usize;
+
+    sorted_values[index]
+}
+
 pub struct BenchResult {
     font: BitmapFont,
-    frames_per_sec: usize,
+    report: BenchReport,
 }
 
 impl Ui for BenchResult {
@@ -237,7 +468,11 @@ impl Ui for BenchResult {
             .render_graph
             .clear_color_image(frame.framebuffer_image);
 
-        let text = format!("{} FPS", self.frames_per_sec);
+        let text = format!(
+            "{} FPS (p99 {:.2} ms)",
+            self.report.frames_per_sec.round(),
+            self.report.p99_frame_time
+        );
         let ([x, y], [width, height]) = self.font.measure(&text);
         let framebuffer_info = frame.render_graph.node_info(frame.framebuffer_image);
         let x = framebuffer_info.width as i32 / 2 - width as i32 / 2 + x / 2;
@@ -262,3 +497,39 @@ impl Ui for BenchResult {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn grid() {
+        let positions = grid_positions(Vec3::ONE).take(25).collect::<Vec<_>>();
+
+        assert_eq!(positions[0], Vec3::ONE);
+
+        // The first 25 positions fill a 5x5 square without repeats
+        for (idx, position) in positions.iter().enumerate() {
+            assert_eq!(position.y, 1.0);
+            assert!((position.x - 1.0).abs() <= 8.0 && (position.z - 1.0).abs() <= 8.0);
+            assert!(!positions[idx + 1..].contains(position));
+        }
+    }
+
+    #[test]
+    pub fn report_percentiles() {
+        let frame_times = (1..=100).map(|ms| ms as f32 / 1_000.0).collect::<Vec<_>>();
+        let report = BenchReport::new(&frame_times, &[], 5.05);
+
+        assert_eq!(report.min_frame_time.round(), 1.0);
+        assert_eq!(report.max_frame_time.round(), 100.0);
+        assert_eq!(report.p99_frame_time.round(), 99.0);
+        assert_eq!(report.avg_frame_time.round(), 51.0);
+
+        // A header, a row per frame and the summary rows
+        let csv = report.to_csv();
+
+        assert_eq!(csv.lines().count(), 105);
+        assert!(csv.contains("\np99,"));
+    }
+}
@@ -1,9 +1,13 @@
 use {
     super::{
+        error::ErrorScreen,
+        play::Play,
         title::Title,
         transition::{Transition, TransitionInfo},
         DrawContext, Operation, Ui, UpdateContext,
     },
+    crate::{art, demo::DemoLevel, game::world_delta::WorldDeltas},
+    anyhow::anyhow,
     screen_13::prelude::*,
     std::{sync::Arc, time::Duration},
 };
@@ -34,7 +38,9 @@ impl Ui for Boot {
     fn update(mut self: Box<Self>, ui: UpdateContext) -> Option<Box<dyn Ui>> {
         if let Some(loader) = &self.loader {
             if loader.is_err() {
-                panic!();
+                let err = self.loader.take().unwrap().unwrap_err();
+
+                return Some(Box::new(ErrorScreen::new(&self.device, err)));
             }
 
             if loader.is_done() {
@@ -56,7 +62,84 @@ impl Ui for Boot {
         } else {
             ui.window.set_cursor_visible(false);
 
-            self.loader = Some(Box::new(Title::load(&self.device).unwrap()));
+            match Title::load(&self.device) {
+                Ok(title) => self.loader = Some(Box::new(title)),
+                Err(err) => return Some(Box::new(ErrorScreen::new(&self.device, err))),
+            }
+        }
+
+        Some(self)
+    }
+}
+
+/// Starts demo playback by loading the level the demo was recorded in, skipping the title and
+/// menus, which demos do not record.
+pub struct DemoBoot {
+    device: Arc<Device>,
+    level: DemoLevel,
+    loader: Option<Box<dyn Operation<Play>>>,
+}
+
+impl DemoBoot {
+    pub fn new(device: &Arc<Device>, level: DemoLevel) -> Self {
+        let device = Arc::clone(device);
+
+        Self {
+            device,
+            level,
+            loader: None,
+        }
+    }
+}
+
+impl Ui for DemoBoot {
+    fn draw(&mut self, frame: DrawContext) {
+        frame
+            .render_graph
+            .clear_color_image(frame.framebuffer_image);
+    }
+
+    fn update(mut self: Box<Self>, ui: UpdateContext) -> Option<Box<dyn Ui>> {
+        if let Some(loader) = &self.loader {
+            if loader.is_err() {
+                let err = self.loader.take().unwrap().unwrap_err();
+
+                return Some(Box::new(ErrorScreen::new(&self.device, err)));
+            }
+
+            if loader.is_done() {
+                let mut play = Box::new(self.loader.take().unwrap().unwrap());
+                play.set_difficulty(self.level.difficulty);
+
+                *ui.cursor = None;
+
+                return Some(play);
+            }
+        } else {
+            ui.window.set_cursor_visible(false);
+
+            let Some(scene) = art::SCENES
+                .iter()
+                .copied()
+                .find(|scene| scene.as_str() == self.level.scene)
+            else {
+                let err = anyhow!("Unknown demo scene {}", self.level.scene);
+
+                return Some(Box::new(ErrorScreen::new(&self.device, err)));
+            };
+
+            match Play::load(
+                &self.device,
+                scene,
+                self.level.spawn.clone(),
+                WorldDeltas::default(),
+                ui.config.graphics,
+                ui.config.texture_filtering,
+                ui.config.ambient_occlusion,
+            ) {
+                Ok(play) => self.loader = Some(Box::new(play)),
+                Err(err) => return Some(Box::new(ErrorScreen::new(&self.device, err))),
+            }
         }
 
         Some(self)
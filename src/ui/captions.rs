@@ -0,0 +1,205 @@
+use {
+    screen_13::prelude::*,
+    screen_13_fx::BitmapFont,
+    serde::Deserialize,
+    std::{
+        cell::Cell,
+        collections::{HashMap, VecDeque},
+    },
+};
+
+const CAPTIONS: &str = include_str!("../../res/captions.toml");
+
+/// Who (or what) is responsible for a caption; each speaker is drawn using its own color.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub enum Speaker {
+    Narrator,
+    Player,
+    Sound,
+}
+
+impl Speaker {
+    fn color(self) -> [u8; 3] {
+        match self {
+            Self::Narrator => [0xff, 0xd7, 0x40],
+            Self::Player => [0xff, 0xff, 0xff],
+            Self::Sound => [0xaa, 0xaa, 0xaa],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct CaptionInfo {
+    speaker: Speaker,
+    text: String,
+
+    /// Seconds the caption remains on screen.
+    secs: f32,
+}
+
+struct Line {
+    /// Key of the caption info, or `None` for text which was pushed directly.
+    key: Option<&'static str>,
+
+    /// The line has not been printed yet, so its time on screen has not started.
+    pending: Cell<bool>,
+
+    remaining_secs: f32,
+    speaker: Speaker,
+    text: String,
+}
+
+/// Queue of timed caption lines which are drawn at the bottom of the framebuffer.
+pub struct Captions {
+    infos: HashMap<String, CaptionInfo>,
+    lines: VecDeque<Line>,
+}
+
+impl Captions {
+    /// Maximum number of lines shown at once; older lines are dropped first.
+    const MAX_LINES: usize = 3;
+
+    pub fn new() -> anyhow::Result<Self> {
+        let infos = toml::from_str(CAPTIONS)?;
+
+        Ok(Self {
+            infos,
+            lines: Default::default(),
+        })
+    }
+
+    /// Draws the current caption lines centered at the bottom of the framebuffer.
+    pub fn print(
+        &self,
+        font: &BitmapFont,
+        render_graph: &mut RenderGraph,
+        framebuffer_image: impl Into<AnyImageNode>,
+    ) {
+        let framebuffer_image = framebuffer_image.into();
+        let framebuffer_info = render_graph.node_info(framebuffer_image);
+        let mut bottom = framebuffer_info.height as i32 - 2;
+
+        for line in self.lines.iter().rev() {
+            line.pending.set(false);
+
+            let ([x, y], [width, height]) = font.measure(&line.text);
+
+            bottom -= height as i32;
+
+            font.print(
+                render_graph,
+                framebuffer_image,
+                (framebuffer_info.width as i32 / 2 - width as i32 / 2 + x / 2) as _,
+                (bottom + y / 2) as _,
+                line.speaker.color(),
+                &line.text,
+            );
+        }
+    }
+
+    /// Queues the caption with the given key; if it is already showing it is shown for longer
+    /// instead.
+    pub fn push(&mut self, key: &'static str) {
+        let Some(info) = self.infos.get(key) else {
+            warn!("Unknown caption {key}");
+
+            return;
+        };
+
+        if let Some(line) = self.lines.iter_mut().find(|line| line.key == Some(key)) {
+            line.remaining_secs = info.secs;
+
+            return;
+        }
+
+        let line = Line {
+            key: Some(key),
+            pending: Cell::new(false),
+            remaining_secs: info.secs,
+            speaker: info.speaker,
+            text: info.text.clone(),
+        };
+        self.push_line(line);
+    }
+
+    fn push_line(&mut self, line: Line) {
+        if self.lines.len() == Self::MAX_LINES {
+            self.lines.pop_front();
+        }
+
+        self.lines.push_back(line);
+    }
+
+    /// Queues a narrator notice, such as one found at startup, which is shown for the given
+    /// seconds once a state first prints captions; states such as loading screens do not.
+    pub fn push_notice(&mut self, text: impl Into<String>, secs: f32) {
+        self.push_line(Line {
+            key: None,
+            pending: Cell::new(true),
+            remaining_secs: secs,
+            speaker: Speaker::Narrator,
+            text: text.into(),
+        });
+    }
+
+    /// Queues text which is not found in the captions file, such as messages from the game itself.
+    pub fn push_text(&mut self, speaker: Speaker, text: impl Into<String>, secs: f32) {
+        self.push_line(Line {
+            key: None,
+            pending: Cell::new(false),
+            remaining_secs: secs,
+            speaker,
+            text: text.into(),
+        });
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for line in self.lines.iter_mut().filter(|line| !line.pending.get()) {
+            line.remaining_secs -= dt;
+        }
+
+        self.lines.retain(|line| line.remaining_secs > 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn captions_queue() {
+        let mut captions = Captions::new().unwrap();
+
+        captions.push("laser_fire");
+        captions.push("laser_fire");
+        captions.push("unknown");
+
+        assert_eq!(captions.lines.len(), 1);
+
+        captions.push("scatter_laser_fire");
+        captions.update(0.8);
+
+        assert_eq!(captions.lines.len(), 1);
+        assert_eq!(captions.lines[0].key, Some("scatter_laser_fire"));
+
+        captions.update(1.0);
+
+        assert!(captions.lines.is_empty());
+    }
+
+    #[test]
+    pub fn notices_wait_until_printed() {
+        let mut captions = Captions::new().unwrap();
+
+        captions.push_notice("Crash report written", 1.0);
+        captions.update(10.0);
+
+        assert_eq!(captions.lines.len(), 1);
+
+        // Printing starts the time on screen
+        captions.lines[0].pending.set(false);
+        captions.update(1.0);
+
+        assert!(captions.lines.is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+use {
+    crate::render::primitives::PrimitiveBuffer,
+    glam::{vec2, Vec2},
+    serde::{Deserialize, Serialize},
+};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum CrosshairStyle {
+    /// Four lines around the center which spread apart while moving and firing.
+    #[default]
+    Cross,
+
+    /// A single dot which does not show spread.
+    Dot,
+}
+
+/// The HUD crosshair, which spreads while the player moves or fires and briefly shows a hit
+/// marker when a shot hits a target.
+#[derive(Debug, Default)]
+pub struct Crosshair {
+    /// RGBA color, which hit markers do not use.
+    pub color: [u8; 4],
+
+    /// Spread caused by firing, in pixels, which recovers over time.
+    fire_spread: f32,
+
+    /// Seconds the hit marker remains visible.
+    hit_marker_secs: f32,
+
+    /// Spread caused by moving, in pixels.
+    move_spread: f32,
+
+    /// Multiplies the size of the crosshair and hit marker, but not how far the crosshair spreads.
+    pub scale: f32,
+
+    pub style: CrosshairStyle,
+}
+
+impl Crosshair {
+    const DOT_RADIUS: f32 = 1.0;
+
+    /// Spread added by each shot, in pixels.
+    const FIRE_SPREAD: f32 = 3.0;
+
+    /// Fraction of the spread caused by firing which remains after each second.
+    const FIRE_SPREAD_RECOVERY: f32 = 0.002;
+
+    /// Distance from the center to the lines of the cross while still, in pixels.
+    const GAP: f32 = 3.0;
+
+    const HIT_MARKER_COLOR: [u8; 3] = [0xff, 0x40, 0x40];
+
+    /// Distance from the center to the start of the hit marker lines, in pixels.
+    const HIT_MARKER_GAP: f32 = 4.0;
+
+    const HIT_MARKER_LEN: f32 = 3.0;
+    const HIT_MARKER_SECS: f32 = 0.2;
+    const LINE_LEN: f32 = 4.0;
+    const LINE_WIDTH: f32 = 1.0;
+    const MAX_SPREAD: f32 = 12.0;
+
+    /// Spread while moving at [`Self::MOVE_SPEED`], in pixels.
+    const MOVE_SPREAD: f32 = 4.0;
+
+    /// Speed, in meters per second, at which the full movement spread is reached.
+    const MOVE_SPEED: f32 = 4.0;
+
+    /// Adds the crosshair, and any hit marker, centered on the given position.
+    pub fn draw(&self, primitives: &mut PrimitiveBuffer, center: Vec2) {
+        let color = self.color;
+        let scale = self.scale;
+
+        match self.style {
+            CrosshairStyle::Cross => {
+                let gap = Self::GAP * scale + self.spread();
+
+                for direction in [Vec2::X, Vec2::Y, -Vec2::X, -Vec2::Y] {
+                    primitives.draw_line(
+                        center + direction * gap,
+                        center + direction * (gap + Self::LINE_LEN * scale),
+                        Self::LINE_WIDTH * scale,
+                        color,
+                    );
+                }
+            }
+            CrosshairStyle::Dot => {
+                primitives.draw_circle(center, Self::DOT_RADIUS * scale, color);
+            }
+        }
+
+        if self.hit_marker_secs > 0.0 {
+            let [r, g, b] = Self::HIT_MARKER_COLOR;
+            let alpha = (self.hit_marker_secs / Self::HIT_MARKER_SECS * 255.0) as u8;
+
+            for direction in [
+                vec2(1.0, 1.0),
+                vec2(1.0, -1.0),
+                vec2(-1.0, 1.0),
+                vec2(-1.0, -1.0),
+            ] {
+                let direction = direction.normalize();
+
+                primitives.draw_line(
+                    center + direction * Self::HIT_MARKER_GAP * scale,
+                    center + direction * (Self::HIT_MARKER_GAP + Self::HIT_MARKER_LEN) * scale,
+                    Self::LINE_WIDTH * scale,
+                    [r, g, b, alpha],
+                );
+            }
+        }
+    }
+
+    /// Spreads the crosshair for a fired shot.
+    pub fn fire(&mut self) {
+        self.fire_spread += Self::FIRE_SPREAD;
+    }
+
+    /// Shows the hit marker, as when a shot hits a target.
+    pub fn hit(&mut self) {
+        self.hit_marker_secs = Self::HIT_MARKER_SECS;
+    }
+
+    /// Returns the current distance the crosshair has spread, in pixels.
+    pub fn spread(&self) -> f32 {
+        (self.fire_spread + self.move_spread).min(Self::MAX_SPREAD)
+    }
+
+    /// Recovers from firing and follows the speed, in meters per second, of the player.
+    pub fn update(&mut self, dt: f32, speed: f32) {
+        self.fire_spread =
+            (self.fire_spread * Self::FIRE_SPREAD_RECOVERY.powf(dt)).min(Self::MAX_SPREAD);
+        self.move_spread = speed / Self::MOVE_SPEED * Self::MOVE_SPREAD;
+        self.hit_marker_secs = (self.hit_marker_secs - dt).max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn crosshair_spread() {
+        let mut crosshair = Crosshair::default();
+
+        assert_eq!(crosshair.spread(), 0.0);
+
+        crosshair.fire();
+        crosshair.fire();
+
+        assert_eq!(crosshair.spread(), Crosshair::FIRE_SPREAD * 2.0);
+
+        // Firing spread recovers while standing still
+        crosshair.update(1.0, 0.0);
+
+        assert!(crosshair.spread() < 0.1);
+
+        // Moving spreads the crosshair for as long as the player moves
+        crosshair.update(1.0, Crosshair::MOVE_SPEED);
+
+        assert!((crosshair.spread() - Crosshair::MOVE_SPREAD).abs() < 0.1);
+
+        for _ in 0..100 {
+            crosshair.fire();
+        }
+
+        assert_eq!(crosshair.spread(), Crosshair::MAX_SPREAD);
+    }
+
+    #[test]
+    pub fn hit_marker() {
+        let mut crosshair = Crosshair::default();
+        crosshair.hit();
+        crosshair.update(Crosshair::HIT_MARKER_SECS * 0.5, 0.0);
+
+        assert!(crosshair.hit_marker_secs > 0.0);
+
+        crosshair.update(Crosshair::HIT_MARKER_SECS, 0.0);
+
+        assert_eq!(crosshair.hit_marker_secs, 0.0);
+    }
+}
@@ -0,0 +1,304 @@
+use {
+    super::{
+        loader::{LoadInfo, LoadResult, Loader},
+        title::Title,
+        transition::{Transition, TransitionInfo},
+        ui_sound::UiSound,
+        CursorStyle, DrawContext, Operation, Ui, UpdateContext,
+    },
+    crate::{art, crash, fs::APPLICATION},
+    arboard::Clipboard,
+    screen_13::prelude::*,
+    screen_13_fx::BitmapFont,
+    std::{
+        io,
+        mem::take,
+        path::{Path, PathBuf},
+        process::Command,
+        sync::Arc,
+        time::Duration,
+    },
+};
+
+/// Shown in place of a state which failed, such as when content cannot be loaded, so that the
+/// game does not exit while fullscreen without telling the player why.
+///
+/// The error is written to a report in the cache directory, which the player may open or copy to
+/// the clipboard before returning to the title screen.
+pub struct ErrorScreen {
+    device: Arc<Device>,
+    font: Option<BitmapFont>,
+
+    /// The font could not be loaded, so the error is yet to be shown another way.
+    font_failed: bool,
+
+    font_loader: Option<Box<dyn Operation<LoadResult>>>,
+
+    /// The error followed by each of its causes.
+    lines: Vec<String>,
+
+    /// Shown below the prompt once the error is copied or the report is opened.
+    notice: Option<String>,
+
+    report_path: Option<PathBuf>,
+    title: Option<Box<dyn Operation<Title>>>,
+}
+
+impl ErrorScreen {
+    const BACKGROUND_COLOR: [f32; 4] = [0.2, 0.0, 0.0, 1.0];
+    const MARGIN: i32 = 4;
+    const PROMPT: &str = "C: copy error  L: open log  Enter: return to title";
+    const TEXT_COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+
+    pub fn new(device: &Arc<Device>, err: anyhow::Error) -> Self {
+        error!("{err:?}");
+
+        let lines = err.chain().map(|cause| cause.to_string()).collect();
+        let report_path = crash::write_error_report(&err);
+
+        // The font is loaded again because the state which failed may not have loaded it yet
+        let font_loader = Loader::spawn_threads(
+            device,
+            None,
+            Default::default(),
+            Default::default(),
+            LoadInfo::default().fonts(&[art::FONT_KENNEY_MINI_SQUARE_MONO]),
+        )
+        .map(|loader| Box::new(loader) as Box<dyn Operation<LoadResult>>)
+        .map_err(|err| warn!("Unable to load font: {err}"))
+        .ok();
+
+        // Without the font the error is shown once the window is available
+        let font_failed = font_loader.is_none();
+
+        Self {
+            device: Arc::clone(device),
+            font: None,
+            font_failed,
+            font_loader,
+            lines,
+            notice: None,
+            report_path,
+            title: None,
+        }
+    }
+
+    fn copy_to_clipboard(&self) -> Result<(), arboard::Error> {
+        Clipboard::new()?.set_text(self.lines.join("\nCaused by: "))
+    }
+
+    /// Shows the error without the font, which is read from the same pak that may have failed:
+    /// it is written to standard error and its first line becomes the window title.
+    fn show_without_font(&self, window: &Window) {
+        eprintln!("Something went wrong");
+
+        for (index, line) in self.lines.iter().enumerate() {
+            if index == 0 {
+                eprintln!("{line}");
+            } else {
+                eprintln!("Caused by: {line}");
+            }
+        }
+
+        if let Some(path) = &self.report_path {
+            eprintln!("Error report written to {}", path.display());
+        }
+
+        window.set_title(&format!(
+            "{APPLICATION}: {}",
+            self.lines.first().map(String::as_str).unwrap_or_default()
+        ));
+    }
+
+    fn update_font(&mut self, window: &Window) {
+        if take(&mut self.font_failed) {
+            self.show_without_font(window);
+        }
+
+        let Some(font_loader) = self.font_loader.take() else {
+            return;
+        };
+
+        if font_loader.is_err() {
+            warn!("Unable to load font: {}", font_loader.unwrap_err());
+
+            self.show_without_font(window);
+        } else if font_loader.is_done() {
+            self.font = font_loader
+                .unwrap()
+                .fonts
+                .remove(&art::FONT_KENNEY_MINI_SQUARE_MONO);
+        } else {
+            self.font_loader = Some(font_loader);
+        }
+    }
+}
+
+impl Ui for ErrorScreen {
+    fn draw(&mut self, frame: DrawContext) {
+        frame
+            .render_graph
+            .clear_color_image_value(frame.framebuffer_image, Self::BACKGROUND_COLOR);
+
+        let Some(font) = &self.font else {
+            return;
+        };
+
+        let framebuffer_info = frame.render_graph.node_info(frame.framebuffer_image);
+        let (_, [char_width, line_height]) = font.measure("M");
+        let max_chars =
+            (framebuffer_info.width as i32 - 2 * Self::MARGIN) / (char_width as i32).max(1);
+        let mut y = Self::MARGIN;
+        let mut print = |text: &str| {
+            for line in wrap(text, max_chars.max(1) as _) {
+                font.print(
+                    frame.render_graph,
+                    frame.framebuffer_image,
+                    Self::MARGIN as _,
+                    y as _,
+                    Self::TEXT_COLOR,
+                    line,
+                );
+
+                y += line_height as i32;
+            }
+        };
+
+        print("Something went wrong");
+        print("");
+
+        for (index, line) in self.lines.iter().enumerate() {
+            if index == 0 {
+                print(line);
+            } else {
+                print(&format!("Caused by: {line}"));
+            }
+        }
+
+        print("");
+        print(Self::PROMPT);
+
+        if let Some(notice) = &self.notice {
+            print(notice);
+        }
+    }
+
+    fn update(mut self: Box<Self>, ui: UpdateContext) -> Option<Box<dyn Ui>> {
+        *ui.cursor = Some(CursorStyle::PointerShadow);
+        ui.focus.set_grab(ui.window, false);
+
+        self.update_font(ui.window);
+
+        if let Some(title) = self.title.take() {
+            if title.is_err() {
+                return Some(Box::new(Self::new(&self.device, title.unwrap_err())));
+            }
+
+            if !title.is_done() {
+                self.title = Some(title);
+
+                return Some(self);
+            }
+
+            let title = Box::new(title.unwrap());
+
+            return Some(Box::new(Transition::new(
+                self,
+                title,
+                TransitionInfo::Fade,
+                Duration::from_secs_f32(0.25),
+            )));
+        }
+
+        if ui.keyboard.is_pressed(&VirtualKeyCode::C) {
+            ui.ui_sounds.emit(UiSound::Click);
+            self.notice = Some(match self.copy_to_clipboard() {
+                Ok(_) => "Copied the error to the clipboard".to_owned(),
+                Err(err) => format!("Unable to copy: {err}"),
+            });
+        }
+
+        if ui.keyboard.is_pressed(&VirtualKeyCode::L) {
+            ui.ui_sounds.emit(UiSound::Click);
+            self.notice = Some(match &self.report_path {
+                Some(path) => match open(path) {
+                    Ok(_) => format!("Opened {}", path.display()),
+                    Err(err) => format!("Unable to open the log: {err}"),
+                },
+                None => "No log was written".to_owned(),
+            });
+        }
+
+        if ui.keyboard.is_pressed(&VirtualKeyCode::Return) {
+            ui.ui_sounds.emit(UiSound::Click);
+
+            match Title::load(&self.device) {
+                Ok(title) => self.title = Some(Box::new(title)),
+                Err(err) => return Some(Box::new(Self::new(&self.device, err))),
+            }
+        }
+
+        Some(self)
+    }
+}
+
+/// Opens a file using the program the platform associates with it.
+fn open(path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+
+    #[cfg(target_os = "macos")]
+    let program = "open";
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let program = "xdg-open";
+
+    Command::new(program).arg(path).spawn().map(|_| ())
+}
+
+/// Splits text into lines of no more than the given number of characters, breaking at spaces
+/// where possible.
+fn wrap(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut lines = vec![];
+    let mut rest = text;
+
+    while rest.chars().count() > max_chars {
+        let split = rest
+            .char_indices()
+            .nth(max_chars)
+            .map(|(index, _)| index)
+            .unwrap_or(rest.len());
+        let space = if rest[split..].starts_with(' ') {
+            Some(split)
+        } else {
+            rest[..split].rfind(' ').filter(|&space| space > 0)
+        };
+        let (line, next) = match space {
+            Some(space) => (&rest[..space], &rest[space + 1..]),
+            None => rest.split_at(split),
+        };
+
+        lines.push(line);
+        rest = next;
+    }
+
+    lines.push(rest);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn wrap_text() {
+        assert_eq!(wrap("", 10), [""]);
+        assert_eq!(wrap("Scene level_01", 20), ["Scene level_01"]);
+        assert_eq!(wrap("Unable to open", 9), ["Unable to", "open"]);
+        assert_eq!(
+            wrap("Unable to open art.pak", 12),
+            ["Unable to", "open art.pak"]
+        );
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+    }
+}
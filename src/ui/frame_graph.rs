@@ -0,0 +1,63 @@
+use {
+    crate::{frame_stats::FrameStats, render::primitives::PrimitiveBuffer},
+    glam::vec2,
+};
+
+/// Overlay which graphs recent frame times so that hitches (such as those caused by streaming) may
+/// be seen as they happen.
+#[derive(Default)]
+pub struct FrameGraph {
+    pub visible: bool,
+}
+
+impl FrameGraph {
+    const BAR_COLOR: [u8; 4] = [0x3d, 0x8e, 0xd8, 0xff];
+
+    /// Frame time (in seconds) drawn as a horizontal line across the graph.
+    const BUDGET_FRAME_TIME: f32 = 1.0 / 60.0;
+
+    const BUDGET_COLOR: [u8; 4] = [0xff, 0xff, 0xff, 0xc0];
+
+    const HEIGHT: i32 = 48;
+
+    /// Frame time (in seconds) at the top of the graph; longer frames are clipped.
+    const MAX_FRAME_TIME: f32 = 1.0 / 20.0;
+
+    /// Number of recent frames graphed, one pixel each.
+    pub const WIDTH: i32 = 120;
+
+    /// Draws the graph with the lower-left corner at the given position.
+    pub fn draw(
+        &self,
+        frame_stats: &FrameStats,
+        left: i32,
+        bottom: i32,
+        primitives: &mut PrimitiveBuffer,
+    ) {
+        let frame_times = frame_stats.frame_times();
+        let skip = frame_times.len().saturating_sub(Self::WIDTH as usize);
+
+        for (x, frame_time) in frame_times.skip(skip).enumerate() {
+            let height = Self::bar_height(frame_time);
+
+            primitives.draw_rect(
+                vec2((left + x as i32) as _, (bottom - height) as _),
+                vec2(1.0, height as _),
+                Self::BAR_COLOR,
+            );
+        }
+
+        let budget_y = (bottom - Self::bar_height(Self::BUDGET_FRAME_TIME)) as f32 + 0.5;
+
+        primitives.draw_line(
+            vec2(left as _, budget_y),
+            vec2((left + Self::WIDTH) as _, budget_y),
+            1.0,
+            Self::BUDGET_COLOR,
+        );
+    }
+
+    fn bar_height(frame_time: f32) -> i32 {
+        ((frame_time / Self::MAX_FRAME_TIME).min(1.0) * Self::HEIGHT as f32).max(1.0) as _
+    }
+}
@@ -0,0 +1,903 @@
+//! A small retained layout engine for menus: elements are anchored within the slot provided by
+//! their parent, sized in pixels, percentages of the parent, or to fit their content, and are
+//! snapped to whole pixels.
+
+use {
+    super::ui_sound::UiSound,
+    crate::render::bitmap::{Bitmap, Rect},
+    screen_13::prelude::*,
+    screen_13_fx::BitmapFont,
+    std::mem::take,
+};
+
+/// Measures the offset and size of a line of text, as [`BitmapFont::measure`] does.
+pub trait MeasureText {
+    fn measure_text(&self, text: &str) -> ([i32; 2], [u32; 2]);
+}
+
+impl MeasureText for BitmapFont {
+    fn measure_text(&self, text: &str) -> ([i32; 2], [u32; 2]) {
+        self.measure(text)
+    }
+}
+
+/// Position of an element within the slot provided by its parent.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    #[default]
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    fn fractions(self) -> (f32, f32) {
+        match self {
+            Self::TopLeft => (0.0, 0.0),
+            Self::Top => (0.5, 0.0),
+            Self::TopRight => (1.0, 0.0),
+            Self::Left => (0.0, 0.5),
+            Self::Center => (0.5, 0.5),
+            Self::Right => (1.0, 0.5),
+            Self::BottomLeft => (0.0, 1.0),
+            Self::Bottom => (0.5, 1.0),
+            Self::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// A length along one axis of the parent slot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+    Pixels(i32),
+
+    /// Fraction (`0.0..=1.0`) of the parent slot.
+    Percent(f32),
+}
+
+impl Length {
+    fn resolve(self, parent: i32) -> i32 {
+        match self {
+            Self::Pixels(pixels) => pixels,
+            Self::Percent(percent) => (parent as f32 * percent).round() as _,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Margin {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Margin {
+    pub const fn uniform(margin: i32) -> Self {
+        Self {
+            left: margin,
+            top: margin,
+            right: margin,
+            bottom: margin,
+        }
+    }
+
+    fn horizontal(self) -> i32 {
+        self.left + self.right
+    }
+
+    fn vertical(self) -> i32 {
+        self.top + self.bottom
+    }
+}
+
+/// Bitmaps drawn as a scalable box: corners and sides are mirrored from the left and top
+/// bitmaps and the middle is stretched.
+#[derive(Clone, Copy, Debug)]
+pub struct SixSlice {
+    pub top_corner: Bitmap,
+    pub top: Bitmap,
+    pub side: Bitmap,
+    pub bottom_corner: Bitmap,
+    pub bottom: Bitmap,
+    pub middle: Bitmap,
+}
+
+impl SixSlice {
+    /// Returns these slices multiplied by the given color when drawn.
+    pub fn tinted(self, tint: [u8; 3]) -> Self {
+        Self {
+            top_corner: self.top_corner.tinted(tint),
+            top: self.top.tinted(tint),
+            side: self.side.tinted(tint),
+            bottom_corner: self.bottom_corner.tinted(tint),
+            bottom: self.bottom.tinted(tint),
+            middle: self.middle.tinted(tint),
+        }
+    }
+
+    pub fn draw(self, rect: Rect, bitmaps: &mut Vec<(Bitmap, Rect)>) {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = rect;
+        let (top_corner_width, top_corner_height) = self.top_corner.size();
+        let (top_corner_width, top_corner_height) =
+            (top_corner_width as i32, top_corner_height as i32);
+        let (_, top_height) = self.top.size();
+        let top_height = top_height as i32;
+        let (side_width, _) = self.side.size();
+        let side_width = side_width as i32;
+        let (bottom_corner_width, bottom_corner_height) = self.bottom_corner.size();
+        let (bottom_corner_width, bottom_corner_height) =
+            (bottom_corner_width as i32, bottom_corner_height as i32);
+        let side_height = height - (top_corner_height + bottom_corner_height);
+
+        // Top left, top, and top right
+        bitmaps.push((
+            self.top_corner,
+            Rect::new(x, y, top_corner_width, top_corner_height),
+        ));
+        bitmaps.push((
+            self.top,
+            Rect::new(
+                x + top_corner_width,
+                y,
+                width - 2 * top_corner_width,
+                top_height,
+            ),
+        ));
+        bitmaps.push((
+            self.top_corner,
+            Rect::new(x + width, y, -top_corner_width, top_corner_height),
+        ));
+
+        // Left and right
+        bitmaps.push((
+            self.side,
+            Rect::new(x, y + top_corner_height, side_width, side_height),
+        ));
+        bitmaps.push((
+            self.side,
+            Rect::new(x + width, y + top_corner_height, -side_width, side_height),
+        ));
+
+        // Bottom left, bottom, and bottom right
+        let bottom_y = y + height - bottom_corner_height;
+        bitmaps.push((
+            self.bottom_corner,
+            Rect::new(x, bottom_y, bottom_corner_width, bottom_corner_height),
+        ));
+        bitmaps.push((
+            self.bottom,
+            Rect::new(
+                x + bottom_corner_width,
+                bottom_y,
+                width - 2 * bottom_corner_width,
+                bottom_corner_height,
+            ),
+        ));
+        bitmaps.push((
+            self.bottom_corner,
+            Rect::new(
+                x + width,
+                bottom_y,
+                -bottom_corner_width,
+                bottom_corner_height,
+            ),
+        ));
+
+        bitmaps.push((
+            self.middle,
+            Rect::new(
+                x + side_width,
+                y + top_height,
+                width - 2 * side_width,
+                height - (top_height + bottom_corner_height),
+            ),
+        ));
+    }
+}
+
+/// A menu input from the keyboard or a gamepad.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Navigation {
+    Up,
+    Down,
+    Left,
+    Right,
+    Activate,
+    Back,
+}
+
+/// Bitmaps used when drawing widgets.
+#[derive(Clone, Copy, Debug)]
+pub struct Style {
+    pub button: SixSlice,
+
+    /// Drawn behind the focused button, outset from its edges.
+    pub focus: SixSlice,
+
+    pub panel: SixSlice,
+}
+
+#[derive(Debug)]
+pub enum Widget {
+    /// Text on a six-slice background which may be hit-tested and focused by id.
+    Button {
+        text: &'static str,
+    },
+
+    Label {
+        text: &'static str,
+        color: [u8; 3],
+    },
+
+    /// A six-slice background around a single child.
+    Panel {
+        child: Box<Element>,
+        padding: i32,
+    },
+
+    /// Children placed one after another along an axis.
+    Stack {
+        axis: Axis,
+        spacing: i32,
+        children: Vec<Element>,
+    },
+
+    /// Children placed in uniformly sized cells, filling each row before the next.
+    Grid {
+        columns: usize,
+        spacing: i32,
+        children: Vec<Element>,
+    },
+}
+
+/// A widget along with how it is sized and positioned within the slot of its parent.
+#[derive(Debug)]
+pub struct Element {
+    pub anchor: Anchor,
+    pub height: Option<Length>,
+    pub id: Option<&'static str>,
+    pub margin: Margin,
+    pub widget: Widget,
+    pub width: Option<Length>,
+}
+
+impl Element {
+    pub fn new(widget: Widget) -> Self {
+        Self {
+            anchor: Anchor::default(),
+            height: None,
+            id: None,
+            margin: Margin::default(),
+            widget,
+            width: None,
+        }
+    }
+
+    pub fn button(text: &'static str) -> Self {
+        Self::new(Widget::Button { text })
+    }
+
+    pub fn grid(columns: usize, spacing: i32, children: Vec<Element>) -> Self {
+        debug_assert_ne!(columns, 0);
+
+        Self::new(Widget::Grid {
+            columns,
+            spacing,
+            children,
+        })
+    }
+
+    pub fn label(text: &'static str, color: [u8; 3]) -> Self {
+        Self::new(Widget::Label { text, color })
+    }
+
+    pub fn panel(padding: i32, child: Element) -> Self {
+        Self::new(Widget::Panel {
+            child: Box::new(child),
+            padding,
+        })
+    }
+
+    pub fn stack(axis: Axis, spacing: i32, children: Vec<Element>) -> Self {
+        Self::new(Widget::Stack {
+            axis,
+            spacing,
+            children,
+        })
+    }
+
+    pub fn anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn height(mut self, height: Length) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    pub fn id(mut self, id: &'static str) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn margin(mut self, margin: Margin) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn width(mut self, width: Length) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Returns the size of this element, including margins, given the size of the parent slot.
+    fn measure(&self, font: &impl MeasureText, parent: (i32, i32)) -> (i32, i32) {
+        let (content_width, content_height) = self.measure_content(font, parent);
+        let width = self
+            .width
+            .map(|width| width.resolve(parent.0))
+            .unwrap_or(content_width);
+        let height = self
+            .height
+            .map(|height| height.resolve(parent.1))
+            .unwrap_or(content_height);
+
+        (
+            width.max(0) + self.margin.horizontal(),
+            height.max(0) + self.margin.vertical(),
+        )
+    }
+
+    fn measure_content(&self, font: &impl MeasureText, parent: (i32, i32)) -> (i32, i32) {
+        match &self.widget {
+            Widget::Button { text } => {
+                let (_, [width, height]) = font.measure_text(text);
+
+                (
+                    width as i32 + Layout::BUTTON_PADDING.0,
+                    height as i32 + Layout::BUTTON_PADDING.1,
+                )
+            }
+            Widget::Label { text, .. } => {
+                let (_, [width, height]) = font.measure_text(text);
+
+                (width as _, height as _)
+            }
+            Widget::Panel { child, padding } => {
+                let (width, height) = child.measure(font, parent);
+
+                (width + 2 * padding, height + 2 * padding)
+            }
+            Widget::Stack {
+                axis,
+                spacing,
+                children,
+            } => {
+                let gaps = children.len().saturating_sub(1) as i32 * spacing;
+                let sizes = children.iter().map(|child| child.measure(font, parent));
+
+                match axis {
+                    Axis::Horizontal => sizes.fold((gaps, 0), |(width, height), size| {
+                        (width + size.0, height.max(size.1))
+                    }),
+                    Axis::Vertical => sizes.fold((0, gaps), |(width, height), size| {
+                        (width.max(size.0), height + size.1)
+                    }),
+                }
+            }
+            Widget::Grid {
+                columns,
+                spacing,
+                children,
+            } => {
+                if children.is_empty() {
+                    return (0, 0);
+                }
+
+                let (cell_width, cell_height) = Self::grid_cell(font, parent, children);
+                let columns = (*columns).min(children.len()) as i32;
+                let rows = (children.len() as i32 + columns - 1) / columns;
+
+                (
+                    columns * cell_width + (columns - 1) * spacing,
+                    rows * cell_height + (rows - 1) * spacing,
+                )
+            }
+        }
+    }
+
+    fn grid_cell(font: &impl MeasureText, parent: (i32, i32), children: &[Element]) -> (i32, i32) {
+        children
+            .iter()
+            .map(|child| child.measure(font, parent))
+            .fold((0, 0), |(width, height), size| {
+                (width.max(size.0), height.max(size.1))
+            })
+    }
+
+    /// Positions this element within the given slot and then recursively positions children.
+    fn arrange(&self, font: &impl MeasureText, slot: Rect, placed: &mut Vec<Placed>) {
+        let (width, height) = self.measure(font, (slot.width, slot.height));
+        let (width, height) = (
+            width - self.margin.horizontal(),
+            height - self.margin.vertical(),
+        );
+        let (anchor_x, anchor_y) = self.anchor.fractions();
+        let free_width = slot.width - self.margin.horizontal() - width;
+        let free_height = slot.height - self.margin.vertical() - height;
+        let rect = Rect::new(
+            slot.x + self.margin.left + (free_width as f32 * anchor_x).round() as i32,
+            slot.y + self.margin.top + (free_height as f32 * anchor_y).round() as i32,
+            width,
+            height,
+        );
+
+        match &self.widget {
+            Widget::Button { text } => {
+                placed.push(Placed {
+                    id: self.id,
+                    kind: PlacedKind::Button,
+                    rect,
+                    text: Some((text, [0x00, 0x00, 0x00], font.measure_text(text))),
+                });
+            }
+            Widget::Label { text, color } => {
+                placed.push(Placed {
+                    id: self.id,
+                    kind: PlacedKind::Label,
+                    rect,
+                    text: Some((text, *color, font.measure_text(text))),
+                });
+            }
+            Widget::Panel { child, padding } => {
+                placed.push(Placed {
+                    id: self.id,
+                    kind: PlacedKind::Panel,
+                    rect,
+                    text: None,
+                });
+
+                child.arrange(
+                    font,
+                    Rect::new(
+                        rect.x + padding,
+                        rect.y + padding,
+                        rect.width - 2 * padding,
+                        rect.height - 2 * padding,
+                    ),
+                    placed,
+                );
+            }
+            Widget::Stack {
+                axis,
+                spacing,
+                children,
+            } => {
+                let mut offset = 0;
+
+                for child in children {
+                    let (child_width, child_height) =
+                        child.measure(font, (rect.width, rect.height));
+                    let child_slot = match axis {
+                        Axis::Horizontal => {
+                            Rect::new(rect.x + offset, rect.y, child_width, rect.height)
+                        }
+                        Axis::Vertical => {
+                            Rect::new(rect.x, rect.y + offset, rect.width, child_height)
+                        }
+                    };
+
+                    child.arrange(font, child_slot, placed);
+
+                    offset += spacing
+                        + match axis {
+                            Axis::Horizontal => child_width,
+                            Axis::Vertical => child_height,
+                        };
+                }
+            }
+            Widget::Grid {
+                columns,
+                spacing,
+                children,
+            } => {
+                let (cell_width, cell_height) =
+                    Self::grid_cell(font, (rect.width, rect.height), children);
+
+                for (idx, child) in children.iter().enumerate() {
+                    let column = (idx % columns) as i32;
+                    let row = (idx / columns) as i32;
+
+                    child.arrange(
+                        font,
+                        Rect::new(
+                            rect.x + column * (cell_width + spacing),
+                            rect.y + row * (cell_height + spacing),
+                            cell_width,
+                            cell_height,
+                        ),
+                        placed,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum PlacedKind {
+    Button,
+    Label,
+    Panel,
+}
+
+/// A widget which has been positioned on the framebuffer.
+#[derive(Debug)]
+struct Placed {
+    id: Option<&'static str>,
+    kind: PlacedKind,
+    rect: Rect,
+    text: Option<(&'static str, [u8; 3], ([i32; 2], [u32; 2]))>,
+}
+
+/// A tree of elements which is laid out again whenever the framebuffer size changes.
+///
+/// Buttons with an id may be focused; the focus order is the order in which buttons appear in
+/// the tree.
+#[derive(Debug)]
+pub struct Layout {
+    focus: Option<&'static str>,
+    placed: Vec<Placed>,
+    root: Element,
+
+    /// Interactions since the previous call to [`Layout::take_sounds`].
+    sounds: Vec<UiSound>,
+
+    valid_framebuffer: Option<(u32, u32)>,
+}
+
+impl Layout {
+    /// Space around the text of buttons, in pixels.
+    const BUTTON_PADDING: (i32, i32) = (10, 8);
+
+    /// Distance, in pixels, the focus background extends past each edge of the focused button.
+    const FOCUS_OUTSET: i32 = 2;
+
+    pub fn new(root: Element) -> Self {
+        Self {
+            focus: None,
+            placed: Default::default(),
+            root,
+            sounds: vec![],
+            valid_framebuffer: None,
+        }
+    }
+
+    /// Adds the backgrounds of all buttons and panels to the given list of bitmaps.
+    pub fn draw(&self, style: &Style, bitmaps: &mut Vec<(Bitmap, Rect)>) {
+        for placed in &self.placed {
+            if placed.kind == PlacedKind::Button && placed.id.is_some() && placed.id == self.focus {
+                style.focus.draw(
+                    Rect::new(
+                        placed.rect.x - Self::FOCUS_OUTSET,
+                        placed.rect.y - Self::FOCUS_OUTSET,
+                        placed.rect.width + 2 * Self::FOCUS_OUTSET,
+                        placed.rect.height + 2 * Self::FOCUS_OUTSET,
+                    ),
+                    bitmaps,
+                );
+            }
+
+            match placed.kind {
+                PlacedKind::Button => style.button.draw(placed.rect, bitmaps),
+                PlacedKind::Label => (),
+                PlacedKind::Panel => style.panel.draw(placed.rect, bitmaps),
+            }
+        }
+    }
+
+    /// Returns the id of the focused button.
+    pub fn focus(&self) -> Option<&'static str> {
+        self.focus
+    }
+
+    /// Ids of the buttons which may be focused, in focus order.
+    fn focus_order(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.placed
+            .iter()
+            .filter(|placed| placed.kind == PlacedKind::Button)
+            .filter_map(|placed| placed.id)
+    }
+
+    /// Returns the id of the top-most button containing the given framebuffer position.
+    pub fn hit(&self, x: i32, y: i32) -> Option<&'static str> {
+        self.placed
+            .iter()
+            .rev()
+            .filter(|placed| placed.kind == PlacedKind::Button)
+            .find(|placed| {
+                x >= placed.rect.x
+                    && y >= placed.rect.y
+                    && x < placed.rect.x + placed.rect.width
+                    && y < placed.rect.y + placed.rect.height
+            })
+            .and_then(|placed| placed.id)
+    }
+
+    /// Focuses the button under the mouse cursor, if any, and returns its id.
+    pub fn hover(&mut self, x: i32, y: i32) -> Option<&'static str> {
+        let hit = self.hit(x, y);
+
+        if hit.is_some() && hit != self.focus {
+            self.focus = hit;
+            self.sounds.push(UiSound::Hover);
+        }
+
+        hit
+    }
+
+    pub fn is_valid(&self, framebuffer_width: u32, framebuffer_height: u32) -> bool {
+        self.valid_framebuffer == Some((framebuffer_width, framebuffer_height))
+    }
+
+    /// Positions all elements for the given framebuffer size; does nothing if the size has not
+    /// changed since the previous call.
+    pub fn layout(
+        &mut self,
+        font: &impl MeasureText,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+    ) {
+        if self.is_valid(framebuffer_width, framebuffer_height) {
+            return;
+        }
+
+        self.placed.clear();
+        self.root.arrange(
+            font,
+            Rect::new(0, 0, framebuffer_width as _, framebuffer_height as _),
+            &mut self.placed,
+        );
+
+        self.valid_framebuffer = Some((framebuffer_width, framebuffer_height));
+    }
+
+    /// Prints the text of all buttons and labels; call after recording the bitmaps from
+    /// [`Layout::draw`].
+    pub fn print(
+        &self,
+        font: &BitmapFont,
+        render_graph: &mut RenderGraph,
+        framebuffer_image: impl Into<AnyImageNode>,
+    ) {
+        let framebuffer_image = framebuffer_image.into();
+
+        for placed in &self.placed {
+            if let Some((text, color, ([x, y], [width, height]))) = placed.text {
+                // Button text sits slightly above center to account for the bottom bevel
+                let bevel = if placed.kind == PlacedKind::Button {
+                    3
+                } else {
+                    0
+                };
+
+                font.print(
+                    render_graph,
+                    framebuffer_image,
+                    (placed.rect.x + placed.rect.width / 2 - width as i32 / 2 + x / 2) as _,
+                    (placed.rect.y + placed.rect.height / 2 - height as i32 / 2 + y / 2 - bevel)
+                        as _,
+                    color,
+                    text,
+                );
+            }
+        }
+    }
+
+    /// Moves the focus for directional navigation, wrapping around at either end, and returns
+    /// the id of the focused button when it is activated.
+    ///
+    /// The first button is focused by any navigation while there is no focus. [`Navigation::Back`]
+    /// is left to the caller.
+    pub fn navigate(&mut self, navigation: Navigation) -> Option<&'static str> {
+        let ids = self.focus_order().collect::<Vec<_>>();
+        let idx = self
+            .focus
+            .and_then(|focus| ids.iter().position(|&id| id == focus));
+
+        let Some(idx) = idx else {
+            self.focus = ids.first().copied();
+
+            if self.focus.is_some() {
+                self.sounds.push(UiSound::Hover);
+            }
+
+            return None;
+        };
+
+        let focus = match navigation {
+            Navigation::Up | Navigation::Left => ids[(idx + ids.len() - 1) % ids.len()],
+            Navigation::Down | Navigation::Right => ids[(idx + 1) % ids.len()],
+            Navigation::Activate => {
+                self.sounds.push(UiSound::Click);
+
+                return self.focus;
+            }
+            Navigation::Back => return None,
+        };
+
+        if self.focus != Some(focus) {
+            self.focus = Some(focus);
+            self.sounds.push(UiSound::Hover);
+        }
+
+        None
+    }
+
+    /// Returns the framebuffer rectangle of the element with the given id.
+    pub fn rect(&self, id: &str) -> Option<Rect> {
+        self.placed
+            .iter()
+            .find(|placed| placed.id == Some(id))
+            .map(|placed| placed.rect)
+    }
+
+    /// Focuses the button with the given id, without a sound; used to set the initial focus.
+    pub fn set_focus(&mut self, id: Option<&'static str>) {
+        self.focus = id;
+    }
+
+    /// Returns the interactions since the previous call, in order, so that they may be emitted
+    /// to [`UiSounds`](super::ui_sound::UiSounds).
+    pub fn take_sounds(&mut self) -> Vec<UiSound> {
+        take(&mut self.sounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every character is 4x6 pixels
+    struct FixedWidthFont;
+
+    impl MeasureText for FixedWidthFont {
+        fn measure_text(&self, text: &str) -> ([i32; 2], [u32; 2]) {
+            ([0, 0], [text.len() as u32 * 4, 6])
+        }
+    }
+
+    fn rect_tuple(rect: Rect) -> (i32, i32, i32, i32) {
+        (rect.x, rect.y, rect.width, rect.height)
+    }
+
+    #[test]
+    pub fn layout_centered_button() {
+        let mut layout = Layout::new(Element::button("Play").id("play"));
+        layout.layout(&FixedWidthFont, 100, 50);
+
+        // 16x6 text plus 10x8 padding, centered
+        assert_eq!(rect_tuple(layout.rect("play").unwrap()), (37, 18, 26, 14));
+        assert_eq!(layout.hit(40, 20), Some("play"));
+        assert_eq!(layout.hit(0, 0), None);
+        assert!(layout.is_valid(100, 50));
+        assert!(!layout.is_valid(200, 50));
+    }
+
+    #[test]
+    pub fn layout_anchors_and_percentages() {
+        let mut layout = Layout::new(
+            Element::label("ab", [0xff; 3])
+                .id("label")
+                .anchor(Anchor::BottomRight)
+                .width(Length::Percent(0.25))
+                .margin(Margin::uniform(2)),
+        );
+        layout.layout(&FixedWidthFont, 101, 50);
+
+        // 25% of 101 rounds to 25 pixels
+        assert_eq!(rect_tuple(layout.rect("label").unwrap()), (74, 42, 25, 6));
+    }
+
+    #[test]
+    pub fn layout_stack_and_grid() {
+        let mut layout = Layout::new(
+            Element::stack(
+                Axis::Vertical,
+                2,
+                vec![
+                    Element::label("abcd", [0xff; 3])
+                        .id("title")
+                        .anchor(Anchor::Left),
+                    Element::grid(
+                        2,
+                        1,
+                        vec![
+                            Element::label("a", [0xff; 3]).id("a"),
+                            Element::label("bb", [0xff; 3]).id("b"),
+                            Element::label("c", [0xff; 3]).id("c"),
+                        ],
+                    ),
+                ],
+            )
+            .anchor(Anchor::TopLeft),
+        );
+        layout.layout(&FixedWidthFont, 100, 100);
+
+        assert_eq!(rect_tuple(layout.rect("title").unwrap()), (0, 0, 16, 6));
+        assert_eq!(rect_tuple(layout.rect("a").unwrap()), (2, 8, 4, 6));
+        assert_eq!(rect_tuple(layout.rect("b").unwrap()), (9, 8, 8, 6));
+        assert_eq!(rect_tuple(layout.rect("c").unwrap()), (2, 15, 4, 6));
+    }
+
+    #[test]
+    pub fn layout_focus_navigation() {
+        let mut layout = Layout::new(Element::stack(
+            Axis::Vertical,
+            2,
+            vec![
+                Element::button("Play").id("play"),
+                Element::label("or", [0xff; 3]).id("or"),
+                Element::button("Quit").id("quit"),
+                Element::button("Unfocusable"),
+            ],
+        ));
+        layout.layout(&FixedWidthFont, 100, 100);
+
+        // The first navigation only focuses the first button
+        assert_eq!(layout.navigate(Navigation::Activate), None);
+        assert_eq!(layout.focus(), Some("play"));
+        assert_eq!(layout.navigate(Navigation::Activate), Some("play"));
+
+        // Labels and buttons without ids are skipped, and focus wraps
+        layout.navigate(Navigation::Down);
+        assert_eq!(layout.focus(), Some("quit"));
+        layout.navigate(Navigation::Right);
+        assert_eq!(layout.focus(), Some("play"));
+        layout.navigate(Navigation::Up);
+        assert_eq!(layout.focus(), Some("quit"));
+        assert_eq!(layout.navigate(Navigation::Back), None);
+        assert_eq!(layout.focus(), Some("quit"));
+
+        // The mouse may focus buttons directly
+        let play = layout.rect("play").unwrap();
+        assert_eq!(layout.hover(play.x, play.y), Some("play"));
+        assert_eq!(layout.navigate(Navigation::Activate), Some("play"));
+
+        // Hovering the focused button again is silent
+        layout.hover(play.x, play.y);
+
+        assert_eq!(
+            layout.take_sounds(),
+            [
+                UiSound::Hover,
+                UiSound::Click,
+                UiSound::Hover,
+                UiSound::Hover,
+                UiSound::Hover,
+                UiSound::Hover,
+                UiSound::Click,
+            ]
+        );
+        assert!(layout.take_sounds().is_empty());
+    }
+}
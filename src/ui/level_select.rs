@@ -0,0 +1,389 @@
+//! A debug screen which lists the spawns of every scene in the art pak, next to a thumbnail of the
+//! scene baked by `build.rs`, and starts play at the chosen one without going through the title
+//! and menu, so that content may be reviewed quickly.
+//!
+//! It is opened with the `levels` console command during play, or by typing `levels` on the menu.
+
+use {
+    super::{
+        error::ErrorScreen,
+        layout::Navigation,
+        loader::{LoadInfo, LoadResult, Loader},
+        play::Play,
+        transition::{Transition, TransitionInfo},
+        ui_sound::UiSound,
+        DrawContext, Operation, Ui, UpdateContext,
+    },
+    crate::{
+        art,
+        asset_key::{BitmapKey, SceneKey},
+        game::{checkpoint::Checkpoints, world_delta::WorldDeltas},
+        level::scene::Scene,
+        render::bitmap::{Bitmap, BitmapBuffer, Rect},
+    },
+    anyhow::Context,
+    screen_13::prelude::*,
+    screen_13_fx::BitmapFont,
+    std::{collections::HashMap, sync::Arc, time::Duration},
+};
+
+/// Where play may start: a spawn or checkpoint of a scene.
+#[derive(Debug)]
+struct Entry {
+    scene: SceneKey,
+    spawn: String,
+}
+
+struct Content {
+    bitmap_buf: Option<BitmapBuffer>,
+    font: BitmapFont,
+    thumbnails: HashMap<SceneKey, Bitmap>,
+}
+
+pub struct LevelSelect {
+    content: Option<Content>,
+    content_loader: Option<Box<dyn Operation<LoadResult>>>,
+    device: Arc<Device>,
+    entries: Vec<Entry>,
+    play: Option<Box<dyn Operation<Play>>>,
+
+    /// The screen which opened this one, which is returned to when backing out.
+    previous: Box<dyn Ui>,
+
+    selected: usize,
+
+    /// Changes made to levels in the game in progress, which the chosen level is loaded with.
+    world_deltas: WorldDeltas,
+}
+
+impl LevelSelect {
+    const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.1, 0.2, 1.0];
+
+    /// Typed into the console, or on the menu, to open this screen.
+    pub const COMMAND: &str = "levels";
+
+    const MARGIN: i32 = 4;
+    const PROMPT: &str = "Enter: play  Escape: back";
+    const SELECTED_COLOR: [u8; 3] = [0xff, 0xff, 0x00];
+    const TEXT_COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+
+    /// Reads the spawns of every scene and starts loading thumbnails, returning to `previous` when
+    /// backed out of.
+    pub fn new(
+        device: &Arc<Device>,
+        world_deltas: WorldDeltas,
+        previous: Box<dyn Ui>,
+    ) -> anyhow::Result<Self> {
+        let mut paks = art::open_pak_stack().context("Opening art pak")?;
+        let mut entries = vec![];
+
+        for &scene_key in art::SCENES {
+            let (_, pak) = paks.resolve(scene_key.as_str());
+            let scene = pak
+                .read_scene(scene_key.as_str())
+                .with_context(|| format!("Reading {scene_key}"))?;
+
+            entries.extend(spawns(&Scene::new(scene)).map(|spawn| Entry {
+                scene: scene_key,
+                spawn,
+            }));
+        }
+
+        let thumbnails = art::SCENE_THUMBNAILS
+            .iter()
+            .map(|&(_, thumbnail)| thumbnail)
+            .collect::<Box<[BitmapKey]>>();
+        let content_loader = Box::new(Loader::spawn_threads(
+            device,
+            None,
+            Default::default(),
+            Default::default(),
+            LoadInfo::default()
+                .bitmaps(&thumbnails)
+                .fonts(&[art::FONT_KENNEY_MINI_SQUARE_MONO]),
+        )?);
+
+        Ok(Self {
+            content: None,
+            content_loader: Some(content_loader),
+            device: Arc::clone(device),
+            entries,
+            play: None,
+            previous,
+            selected: 0,
+            world_deltas,
+        })
+    }
+
+    fn update_content(&mut self) -> anyhow::Result<()> {
+        let Some(content_loader) = self.content_loader.take() else {
+            return Ok(());
+        };
+
+        if content_loader.is_err() {
+            return Err(content_loader.unwrap_err());
+        }
+
+        if !content_loader.is_done() {
+            self.content_loader = Some(content_loader);
+
+            return Ok(());
+        }
+
+        let mut loader = content_loader.unwrap();
+        let thumbnails = art::SCENE_THUMBNAILS
+            .iter()
+            .filter_map(|&(scene, thumbnail)| Some((scene, loader.bitmaps.remove(&thumbnail)?)))
+            .collect();
+
+        self.content = Some(Content {
+            bitmap_buf: loader.bitmap_buf,
+            font: loader
+                .fonts
+                .remove(&art::FONT_KENNEY_MINI_SQUARE_MONO)
+                .unwrap(),
+            thumbnails,
+        });
+
+        Ok(())
+    }
+}
+
+impl Ui for LevelSelect {
+    fn draw(&mut self, frame: DrawContext) {
+        frame
+            .render_graph
+            .clear_color_image_value(frame.framebuffer_image, Self::BACKGROUND_COLOR);
+
+        let Some(content) = &mut self.content else {
+            return;
+        };
+
+        let framebuffer_info = frame.render_graph.node_info(frame.framebuffer_image);
+        let selected = self.entries.get(self.selected);
+        let mut x = Self::MARGIN;
+
+        // The thumbnail of the selected scene is shown at the left, at most a third of the width
+        if let Some((&thumbnail, bitmap_buf)) = selected
+            .and_then(|entry| content.thumbnails.get(&entry.scene))
+            .zip(content.bitmap_buf.as_mut())
+        {
+            let (width, height) = thumbnail.size();
+            let scale = (framebuffer_info.width as f32 / 3.0 / width as f32).min(1.0);
+            let rect = Rect::new(
+                x,
+                Self::MARGIN,
+                (width as f32 * scale) as _,
+                (height as f32 * scale) as _,
+            );
+
+            if let Err(err) = bitmap_buf.record(
+                frame.render_graph,
+                frame.framebuffer_image,
+                [&(thumbnail, rect)],
+            ) {
+                warn!("Unable to draw thumbnail: {err}");
+            }
+
+            x += rect.width + Self::MARGIN;
+        }
+
+        let font = &content.font;
+        let (_, [_, line_height]) = font.measure("M");
+        let line_height = line_height.max(1) as i32;
+
+        // Only the entries around the selected one fit, leaving a line for the prompt
+        let visible =
+            ((framebuffer_info.height as i32 - 2 * Self::MARGIN) / line_height - 1).max(1);
+        let first = self
+            .selected
+            .saturating_sub(visible as usize / 2)
+            .min(self.entries.len().saturating_sub(visible as _));
+
+        for (idx, entry) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(visible as _)
+        {
+            let (prefix, color) = if idx == self.selected {
+                ("> ", Self::SELECTED_COLOR)
+            } else {
+                ("  ", Self::TEXT_COLOR)
+            };
+
+            font.print(
+                frame.render_graph,
+                frame.framebuffer_image,
+                x as _,
+                (Self::MARGIN + (idx - first) as i32 * line_height) as _,
+                color,
+                format!("{prefix}{} {}", entry.scene, entry.spawn),
+            );
+        }
+
+        let prompt = if let Some(play) = &self.play {
+            format!("{}... {}%", play.status(), (play.progress() * 100.0) as u8)
+        } else if self.entries.is_empty() {
+            "No scenes".to_owned()
+        } else {
+            Self::PROMPT.to_owned()
+        };
+
+        font.print(
+            frame.render_graph,
+            frame.framebuffer_image,
+            Self::MARGIN as _,
+            (framebuffer_info.height as i32 - Self::MARGIN - line_height) as _,
+            Self::TEXT_COLOR,
+            prompt,
+        );
+    }
+
+    fn update(mut self: Box<Self>, ui: UpdateContext) -> Option<Box<dyn Ui>> {
+        *ui.cursor = None;
+
+        if let Err(err) = self.update_content() {
+            return Some(Box::new(ErrorScreen::new(&self.device, err)));
+        }
+
+        if let Some(play) = &self.play {
+            if play.is_err() {
+                let err = self.play.take().unwrap().unwrap_err();
+
+                return Some(Box::new(ErrorScreen::new(&self.device, err)));
+            }
+
+            if play.is_done() {
+                let play = Box::new(self.play.take().unwrap().unwrap());
+
+                #[cfg(not(debug_assertions))]
+                ui.focus.set_grab(ui.window, true);
+
+                ui.set_cursor_position_center();
+
+                return Some(Box::new(Transition::new(
+                    self,
+                    play,
+                    TransitionInfo::Fade,
+                    Duration::from_secs_f32(0.25),
+                )));
+            }
+
+            return Some(self);
+        }
+
+        match ui.navigation() {
+            Some(Navigation::Up) if !self.entries.is_empty() => {
+                ui.ui_sounds.emit(UiSound::Hover);
+                self.selected = self
+                    .selected
+                    .checked_sub(1)
+                    .unwrap_or(self.entries.len() - 1);
+            }
+            Some(Navigation::Down) if !self.entries.is_empty() => {
+                ui.ui_sounds.emit(UiSound::Hover);
+                self.selected = (self.selected + 1) % self.entries.len();
+            }
+            Some(Navigation::Activate) => {
+                let Some(entry) = self.entries.get(self.selected) else {
+                    return Some(self);
+                };
+
+                ui.ui_sounds.emit(UiSound::Click);
+                info!("Starting {} at {}", entry.scene, entry.spawn);
+
+                match Play::load(
+                    &self.device,
+                    entry.scene,
+                    Some(entry.spawn.clone()),
+                    self.world_deltas.clone(),
+                    ui.config.graphics,
+                    ui.config.texture_filtering,
+                    ui.config.ambient_occlusion,
+                ) {
+                    Ok(play) => self.play = Some(Box::new(play)),
+                    Err(err) => return Some(Box::new(ErrorScreen::new(&self.device, err))),
+                }
+            }
+            Some(Navigation::Back) => {
+                ui.ui_sounds.emit(UiSound::Back);
+
+                return Some(self.previous);
+            }
+            _ => (),
+        }
+
+        Some(self)
+    }
+}
+
+/// Letters typed on a screen, such as the menu, which opens the level select screen once they
+/// spell [`LevelSelect::COMMAND`].
+#[derive(Debug, Default)]
+pub struct CheatCode {
+    typed: String,
+}
+
+impl CheatCode {
+    /// Returns `true` once the characters received this frame complete the code.
+    pub fn update(&mut self, events: &[Event<()>]) -> bool {
+        let mut res = false;
+
+        for event in events {
+            if let Event::WindowEvent {
+                event: WindowEvent::ReceivedCharacter(char),
+                ..
+            } = event
+            {
+                res |= self.type_char(*char);
+            }
+        }
+
+        res
+    }
+
+    fn type_char(&mut self, char: char) -> bool {
+        self.typed.push(char.to_ascii_lowercase());
+
+        while self.typed.len() > LevelSelect::COMMAND.len() {
+            self.typed.remove(0);
+        }
+
+        if self.typed == LevelSelect::COMMAND {
+            self.typed.clear();
+
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Returns the names of the scene refs play may start at: spawns, then checkpoints.
+fn spawns(scene: &Scene) -> impl Iterator<Item = String> + '_ {
+    scene
+        .refs_prefixed(Play::SPAWN)
+        .chain(scene.refs_prefixed(Checkpoints::PREFIX))
+        .map(|(_, id)| id.name.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn cheat_code() {
+        let mut cheat_code = CheatCode::default();
+
+        assert!(!"leve".chars().any(|char| cheat_code.type_char(char)));
+        assert!(!cheat_code.type_char('x'));
+        assert!("xxLEVELS"
+            .chars()
+            .map(|char| cheat_code.type_char(char))
+            .last()
+            .unwrap());
+        assert!(cheat_code.typed.is_empty());
+    }
+}
@@ -1,13 +1,21 @@
 use {
     super::Operation,
     crate::{
-        art::open_pak,
+        art::{open_pak_stack, read_compressed_bitmaps, read_material_animations},
+        asset_key::{BitmapKey, FontKey, MaterialKey, ModelKey, SceneKey, SoundKey},
+        mods::{PakLayer, PakStack, BASE_LAYER},
         render::{
             bitmap::{Bitmap, BitmapBuffer},
-            model::{Material, Model, ModelBuffer, ModelBufferInfo, ModelBufferTechnique},
+            compressed_bitmap::{CompressedBitmap, CompressedBitmaps},
+            material_animation::{MaterialAnimation, MaterialAnimations},
+            mip::{compressed_image_format, create_mip_chain, generate_mip_chain},
+            model::{
+                base_mip_level, AmbientOcclusion, Material, MaterialFlags, Model, ModelBuffer,
+                ModelBufferInfo, ModelBufferTechnique, TextureFiltering,
+            },
         },
     },
-    anyhow::Context,
+    anyhow::{anyhow, Context},
     bmfont::{BMFont, OrdinateOrientation},
     crossbeam_channel::unbounded,
     kira::sound::static_sound::{StaticSoundData, StaticSoundSettings},
@@ -16,77 +24,91 @@ use {
     screen_13::prelude::*,
     screen_13_fx::{BitmapFont, ImageFormat, ImageLoader},
     std::{
+        borrow::Cow,
         collections::{HashMap, HashSet},
         io::Cursor,
         sync::{
-            atomic::{AtomicBool, AtomicUsize, Ordering},
+            atomic::{AtomicUsize, Ordering},
             Arc,
         },
         thread::{spawn, JoinHandle},
     },
 };
 
+/// Identifies a loaded asset either by the key it was loaded with or, for assets referred to by
+/// scenes, by its ID within the pak layer the scene was read from.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-pub enum IdOrKey<T> {
-    Id(T),
-    Key(&'static str),
+pub enum IdOrKey<I, K> {
+    Id(PakLayer, I),
+    Key(K),
 }
 
 #[derive(Clone, Copy, Debug, Default)]
 pub struct LoadInfo<'a> {
-    pub bitmaps: &'a [&'static str],
-    pub fonts: &'a [&'static str],
-    pub materials: &'a [&'static str],
-    pub models: &'a [&'static str],
-    pub scenes: &'a [&'static str],
-    pub sounds: &'a [&'static str],
+    pub bitmaps: &'a [BitmapKey],
+    pub fonts: &'a [FontKey],
+    pub materials: &'a [MaterialKey],
+    pub models: &'a [ModelKey],
+    pub scenes: &'a [SceneKey],
+    pub sounds: &'a [SoundKey],
 }
 
 impl<'a> LoadInfo<'a> {
-    pub fn bitmaps(mut self, bitmaps: &'a [&'static str]) -> Self {
+    pub fn bitmaps(mut self, bitmaps: &'a [BitmapKey]) -> Self {
         self.bitmaps = bitmaps;
         self
     }
 
-    pub fn fonts(mut self, fonts: &'a [&'static str]) -> Self {
+    pub fn fonts(mut self, fonts: &'a [FontKey]) -> Self {
         self.fonts = fonts;
         self
     }
 
-    pub fn materials(mut self, materials: &'a [&'static str]) -> Self {
+    pub fn materials(mut self, materials: &'a [MaterialKey]) -> Self {
         self.materials = materials;
         self
     }
 
-    pub fn models(mut self, models: &'a [&'static str]) -> Self {
+    pub fn models(mut self, models: &'a [ModelKey]) -> Self {
         self.models = models;
         self
     }
 
-    pub fn scenes(mut self, scenes: &'a [&'static str]) -> Self {
+    pub fn scenes(mut self, scenes: &'a [SceneKey]) -> Self {
         self.scenes = scenes;
         self
     }
 
-    pub fn sounds(mut self, sounds: &'a [&'static str]) -> Self {
+    pub fn sounds(mut self, sounds: &'a [SoundKey]) -> Self {
         self.sounds = sounds;
         self
     }
 }
 
+type BitmapCache = HashMap<(PakLayer, BitmapId), Arc<Mutex<Option<(Arc<Image>, bool)>>>>;
+
 pub struct Loader {
     bitmap_buf: Arc<Mutex<Option<BitmapBuffer>>>,
-    bitmaps: Arc<Mutex<HashMap<&'static str, Bitmap>>>,
-    err: Arc<AtomicBool>,
-    fonts: Arc<Mutex<HashMap<&'static str, BitmapFont>>>,
+    bitmap_cache: Arc<Mutex<BitmapCache>>,
+    bitmaps: Arc<Mutex<HashMap<BitmapKey, Bitmap>>>,
+    compressed_bitmaps: Arc<CompressedBitmaps>,
+    device: Arc<Device>,
+
+    /// The first error of any load thread, after which that thread stops.
+    err: Arc<Mutex<Option<anyhow::Error>>>,
+
+    fonts: Arc<Mutex<HashMap<FontKey, BitmapFont>>>,
     loaded: Arc<AtomicUsize>,
-    materials: Arc<Mutex<HashMap<IdOrKey<MaterialId>, Material>>>,
+    materials: Arc<Mutex<HashMap<IdOrKey<MaterialId, MaterialKey>, Material>>>,
     model_buf: Arc<Mutex<Option<ModelBuffer>>>,
-    models: Arc<Mutex<HashMap<IdOrKey<ModelId>, Model>>>,
+    models: Arc<Mutex<HashMap<IdOrKey<ModelId, ModelKey>, Model>>>,
     threads: Vec<JoinHandle<()>>,
     total: usize,
-    scenes: Arc<Mutex<HashMap<&'static str, SceneBuf>>>,
-    sounds: Arc<Mutex<HashMap<&'static str, StaticSoundData>>>,
+    scenes: Arc<Mutex<HashMap<SceneKey, (PakLayer, SceneBuf)>>>,
+    sounds: Arc<Mutex<HashMap<SoundKey, StaticSoundData>>>,
+
+    /// Describes the key most recently started by any load thread.
+    status: Arc<Mutex<String>>,
 }
 
 impl Loader {
@@ -95,48 +117,79 @@ impl Loader {
     pub fn spawn_threads(
         device: &Arc<Device>,
         graphics: Option<ModelBufferTechnique>,
+        texture_filtering: TextureFiltering,
+        ambient_occlusion: AmbientOcclusion,
+        info: LoadInfo,
+    ) -> anyhow::Result<Self> {
+        let mut model_buf_info = ModelBufferInfo::new()
+            .ambient_occlusion(ambient_occlusion)
+            .texture_filtering(texture_filtering);
+
+        if let Some(graphics) = graphics {
+            model_buf_info = model_buf_info.technique(graphics);
+        }
+
+        Self::spawn_threads_model_buf(device, model_buf_info.build(), info)
+    }
+
+    /// Like [`Loader::spawn_threads`], but any models or materials are loaded into a model buffer
+    /// created using the given information.
+    pub fn spawn_threads_model_buf(
+        device: &Arc<Device>,
+        model_buf_info: ModelBufferInfo,
         info: LoadInfo,
     ) -> anyhow::Result<Self> {
         #[cfg(debug_assertions)]
         {
             let mut keys = HashSet::new();
 
+            // Keys of different asset types may not share a name inside the pak either
             for key in info
                 .bitmaps
                 .iter()
-                .chain(info.fonts.iter())
-                .chain(info.materials.iter())
-                .chain(info.models.iter())
-                .chain(info.scenes.iter())
-                .chain(info.sounds.iter())
-                .copied()
+                .map(|key| key.as_str())
+                .chain(info.fonts.iter().map(|key| key.as_str()))
+                .chain(info.materials.iter().map(|key| key.as_str()))
+                .chain(info.models.iter().map(|key| key.as_str()))
+                .chain(info.scenes.iter().map(|key| key.as_str()))
+                .chain(info.sounds.iter().map(|key| key.as_str()))
             {
                 assert!(keys.insert(key), "Duplicate key {}", key);
             }
         }
 
-        let mut model_buf_info = ModelBufferInfo::new();
-
-        if let Some(graphics) = graphics {
-            model_buf_info = model_buf_info.technique(graphics);
-        }
-
-        let model_buf_info = model_buf_info.build();
-
         let bitmap_buf: Option<BitmapBuffer> = None;
         let image_loader: Option<ImageLoader> = None;
         let model_buf: Option<ModelBuffer> = None;
 
-        type BitmapCache = HashMap<BitmapId, Arc<Mutex<Option<(Arc<Image>, bool)>>>>;
         let bitmap_cache: BitmapCache = HashMap::new();
         let bitmap_cache = Arc::new(Mutex::new(bitmap_cache));
 
+        // Only material bitmaps are compressed, and only devices with BC support may use them
+        let compressed_bitmaps = Arc::new(
+            if device.physical_device.features_v1_0.texture_compression_bc
+                && (!info.materials.is_empty() || !info.scenes.is_empty())
+            {
+                read_compressed_bitmaps()
+            } else {
+                CompressedBitmaps::default()
+            },
+        );
+
+        let material_animations =
+            Arc::new(if !info.materials.is_empty() || !info.scenes.is_empty() {
+                read_material_animations()
+            } else {
+                MaterialAnimations::default()
+            });
+
         let bitmap_buf = Arc::new(Mutex::new(bitmap_buf));
         let image_loader = Arc::new(Mutex::new(image_loader));
         let model_buf = Arc::new(Mutex::new(model_buf));
 
-        let err = Arc::new(AtomicBool::new(false));
+        let err = Arc::new(Mutex::new(None));
         let loaded = Arc::new(AtomicUsize::new(0));
+        let status = Arc::new(Mutex::new(String::new()));
         let mut threads = vec![];
 
         let bitmaps = Arc::new(Mutex::new(HashMap::new()));
@@ -164,31 +217,58 @@ impl Loader {
         #[derive(Clone, Copy)]
         enum Message {
             Done,
-            Bitmap(&'static str),
-            Font(&'static str),
-            Material(&'static str),
-            Model(&'static str),
-            Scene(&'static str),
-            Sound(&'static str),
+            Bitmap(BitmapKey),
+            Font(FontKey),
+            Material(MaterialKey),
+            Model(ModelKey),
+            Scene(SceneKey),
+            Sound(SoundKey),
+        }
+
+        impl Message {
+            fn key(self) -> Option<&'static str> {
+                Some(match self {
+                    Self::Done => return None,
+                    Self::Bitmap(key) => key.as_str(),
+                    Self::Font(key) => key.as_str(),
+                    Self::Material(key) => key.as_str(),
+                    Self::Model(key) => key.as_str(),
+                    Self::Scene(key) => key.as_str(),
+                    Self::Sound(key) => key.as_str(),
+                })
+            }
+
+            fn status(self) -> Option<String> {
+                self.key().map(|key| format!("Loading {key}"))
+            }
         }
 
         fn load_bitmap(
             device: &Arc<Device>,
-            pak: &mut PakBuf,
-            key: &'static str,
+            paks: &mut PakStack,
+            key: BitmapKey,
             bitmap_cache: &Arc<Mutex<BitmapCache>>,
             image_loader: &Arc<Mutex<Option<ImageLoader>>>,
             bitmap_buf: &Arc<Mutex<Option<BitmapBuffer>>>,
-            bitmaps: &Arc<Mutex<HashMap<&'static str, Bitmap>>>,
+            bitmaps: &Arc<Mutex<HashMap<BitmapKey, Bitmap>>>,
             queue_index: usize,
         ) -> anyhow::Result<()> {
+            let (layer, pak) = paks.resolve(key.as_str());
             let id = pak
-                .bitmap_id(key)
+                .bitmap_id(key.as_str())
                 .ok_or(DriverError::InvalidData)
                 .context("Getting bitmap ID")?;
-            let (image, has_alpha) =
-                read_image(device, pak, id, bitmap_cache, image_loader, queue_index)
-                    .context("Reading bitmap image")?;
+            let (image, has_alpha) = read_image(
+                device,
+                pak,
+                layer,
+                id,
+                None,
+                bitmap_cache,
+                image_loader,
+                queue_index,
+            )
+            .context("Reading bitmap image")?;
             let mut bitmap_buf = bitmap_buf.lock();
 
             if bitmap_buf.is_none() {
@@ -208,13 +288,14 @@ impl Loader {
 
         fn load_font(
             device: &Arc<Device>,
-            pak: &mut PakBuf,
-            key: &'static str,
+            paks: &mut PakStack,
+            key: FontKey,
             image_loader: &Arc<Mutex<Option<ImageLoader>>>,
-            fonts: &Arc<Mutex<HashMap<&'static str, BitmapFont>>>,
+            fonts: &Arc<Mutex<HashMap<FontKey, BitmapFont>>>,
             queue_index: usize,
         ) -> anyhow::Result<()> {
-            let font = pak.read_bitmap_font(key).context("Reading font")?;
+            let (_, pak) = paks.resolve(key.as_str());
+            let font = pak.read_bitmap_font(key.as_str()).context("Reading font")?;
 
             let page_bufs = font.pages();
             let mut pages = Vec::with_capacity(page_bufs.len());
@@ -256,26 +337,38 @@ impl Loader {
 
         fn load_material(
             device: &Arc<Device>,
-            pak: &mut PakBuf,
-            key: &'static str,
+            paks: &mut PakStack,
+            key: MaterialKey,
+            compressed_bitmaps: &CompressedBitmaps,
+            material_animations: &MaterialAnimations,
             bitmap_cache: &Arc<Mutex<BitmapCache>>,
             image_loader: &Arc<Mutex<Option<ImageLoader>>>,
             model_buf: &Arc<Mutex<Option<ModelBuffer>>>,
             model_buf_info: ModelBufferInfo,
-            materials: &Arc<Mutex<HashMap<IdOrKey<MaterialId>, Material>>>,
+            materials: &Arc<Mutex<HashMap<IdOrKey<MaterialId, MaterialKey>, Material>>>,
             queue_index: usize,
         ) -> anyhow::Result<()> {
+            let (layer, pak) = paks.resolve(key.as_str());
             let id = pak
-                .material_id(key)
+                .material_id(key.as_str())
                 .ok_or(DriverError::InvalidData)
                 .context("Getting material ID")?;
-            let (color, normal, params, emissive) =
-                read_material(device, pak, id, bitmap_cache, image_loader, queue_index)
-                    .context("Reading material")?;
+            let (color, normal, params, emissive, flags) = read_material(
+                device,
+                pak,
+                layer,
+                id,
+                compressed_bitmaps,
+                bitmap_cache,
+                image_loader,
+                queue_index,
+            )
+            .context("Reading material")?;
+            let animation = material_animation(material_animations, layer, id);
 
             let mut materials = materials.lock();
             let key = IdOrKey::Key(key);
-            let id = IdOrKey::Id(id);
+            let id = IdOrKey::Id(layer, id);
 
             if !materials.contains_key(&id) {
                 let mut model_buf = model_buf.lock();
@@ -290,7 +383,15 @@ impl Loader {
                 let material = model_buf
                     .as_mut()
                     .unwrap()
-                    .load_material(queue_index, color, normal, params, emissive)
+                    .load_material(
+                        queue_index,
+                        color,
+                        normal,
+                        params,
+                        emissive,
+                        flags,
+                        animation,
+                    )
                     .context("Loading material")?;
 
                 materials.insert(id, material);
@@ -307,22 +408,23 @@ impl Loader {
 
         fn load_model(
             device: &Arc<Device>,
-            pak: &mut PakBuf,
-            key: &'static str,
+            paks: &mut PakStack,
+            key: ModelKey,
             model_buf: &Arc<Mutex<Option<ModelBuffer>>>,
             model_buf_info: ModelBufferInfo,
-            models: &Arc<Mutex<HashMap<IdOrKey<ModelId>, Model>>>,
+            models: &Arc<Mutex<HashMap<IdOrKey<ModelId, ModelKey>, Model>>>,
             queue_index: usize,
         ) -> anyhow::Result<()> {
+            let (layer, pak) = paks.resolve(key.as_str());
             let id = pak
-                .model_id(key)
+                .model_id(key.as_str())
                 .ok_or(DriverError::InvalidData)
                 .context("Getting model ID")?;
-            let model = pak.read_model(key).context("Reading model")?;
+            let model = pak.read_model(key.as_str()).context("Reading model")?;
 
             let mut models = models.lock();
             let key = IdOrKey::Key(key);
-            let id = IdOrKey::Id(id);
+            let id = IdOrKey::Id(layer, id);
 
             if !models.contains_key(&id) {
                 let mut model_buf = model_buf.lock();
@@ -354,33 +456,39 @@ impl Loader {
 
         fn load_scene(
             device: &Arc<Device>,
-            pak: &mut PakBuf,
-            key: &'static str,
-            scenes: &Arc<Mutex<HashMap<&'static str, SceneBuf>>>,
+            paks: &mut PakStack,
+            key: SceneKey,
+            scenes: &Arc<Mutex<HashMap<SceneKey, (PakLayer, SceneBuf)>>>,
+            compressed_bitmaps: &CompressedBitmaps,
+            material_animations: &MaterialAnimations,
             bitmap_cache: &Arc<Mutex<BitmapCache>>,
             image_loader: &Arc<Mutex<Option<ImageLoader>>>,
             model_buf: &Arc<Mutex<Option<ModelBuffer>>>,
             model_buf_info: ModelBufferInfo,
-            materials: &Arc<Mutex<HashMap<IdOrKey<MaterialId>, Material>>>,
-            models: &Arc<Mutex<HashMap<IdOrKey<ModelId>, Model>>>,
+            materials: &Arc<Mutex<HashMap<IdOrKey<MaterialId, MaterialKey>, Material>>>,
+            models: &Arc<Mutex<HashMap<IdOrKey<ModelId, ModelKey>, Model>>>,
             queue_index: usize,
         ) -> anyhow::Result<()> {
-            let scene = pak.read_scene(key).context("Reading scene")?;
+            let (layer, pak) = paks.resolve(key.as_str());
+            let scene = pak.read_scene(key.as_str()).context("Reading scene")?;
 
             for scene_ref in scene.refs() {
                 for material_id in scene_ref.materials().iter().copied() {
-                    let (color, normal, params, emissive) = read_material(
+                    let (color, normal, params, emissive, flags) = read_material(
                         device,
                         pak,
+                        layer,
                         material_id,
+                        compressed_bitmaps,
                         bitmap_cache,
                         image_loader,
                         queue_index,
                     )
                     .with_context(|| format!("Reading material {material_id:?}"))?;
 
+                    let animation = material_animation(material_animations, layer, material_id);
                     let mut materials = materials.lock();
-                    let material_id = IdOrKey::Id(material_id);
+                    let material_id = IdOrKey::Id(layer, material_id);
 
                     if !materials.contains_key(&material_id) {
                         let mut model_buf = model_buf.lock();
@@ -395,7 +503,15 @@ impl Loader {
                         let material = model_buf
                             .as_mut()
                             .unwrap()
-                            .load_material(queue_index, color, normal, params, emissive)
+                            .load_material(
+                                queue_index,
+                                color,
+                                normal,
+                                params,
+                                emissive,
+                                flags,
+                                animation,
+                            )
                             .context("Loading material")?;
 
                         materials.insert(material_id, material);
@@ -408,7 +524,7 @@ impl Loader {
                         .with_context(|| format!("Reading model {model_id:?}"))?;
 
                     let mut models = models.lock();
-                    let model_id = IdOrKey::Id(model_id);
+                    let model_id = IdOrKey::Id(layer, model_id);
 
                     if !models.contains_key(&model_id) {
                         let mut model_buf = model_buf.lock();
@@ -431,17 +547,18 @@ impl Loader {
                 }
             }
 
-            scenes.lock().insert(key, scene);
+            scenes.lock().insert(key, (layer, scene));
 
             Ok(())
         }
 
         fn load_sound(
-            pak: &mut PakBuf,
-            key: &'static str,
-            sounds: &Arc<Mutex<HashMap<&'static str, StaticSoundData>>>,
+            paks: &mut PakStack,
+            key: SoundKey,
+            sounds: &Arc<Mutex<HashMap<SoundKey, StaticSoundData>>>,
         ) -> anyhow::Result<()> {
-            let sound = pak.read_blob(key).context("Reading sound")?;
+            let (_, pak) = paks.resolve(key.as_str());
+            let sound = pak.read_blob(key.as_str()).context("Reading sound")?;
             let sound =
                 StaticSoundData::from_cursor(Cursor::new(sound), StaticSoundSettings::new())
                     .context("Loading sound")?;
@@ -454,43 +571,73 @@ impl Loader {
         fn read_image(
             device: &Arc<Device>,
             pak: &mut PakBuf,
+            layer: PakLayer,
             id: BitmapId,
+            compressed: Option<&CompressedBitmap>,
             bitmap_cache: &Arc<Mutex<BitmapCache>>,
             image_loader: &Arc<Mutex<Option<ImageLoader>>>,
             queue_index: usize,
         ) -> anyhow::Result<(Arc<Image>, bool)> {
-            let bitmap_cache = bitmap_cache.lock().entry(id).or_default().clone();
+            let bitmap_cache = bitmap_cache.lock().entry((layer, id)).or_default().clone();
             let mut bitmap_entry = bitmap_cache.lock();
 
             if bitmap_entry.is_none() {
-                let bitmap = pak.read_bitmap_id(id).context("Reading bitmap")?;
-                let bitmap_format = bitmap.format();
-                let mut image_loader = image_loader.lock();
-
-                if image_loader.is_none() {
-                    *image_loader =
-                        Some(ImageLoader::new(device).context("Creating image loader")?);
-                }
-
-                let image = image_loader
-                    .as_mut()
-                    .unwrap()
-                    .decode_linear(
-                        0,
+                if let Some(compressed) = compressed {
+                    // Compressed bitmaps are baked with their mips, which cannot be blitted; only
+                    // the smallest are loaded and the model buffer streams in the rest
+                    let base_mip = base_mip_level(compressed.width, compressed.height)
+                        .min(compressed.mips.len() as u32 - 1);
+                    let (width, height) =
+                        CompressedBitmap::mip_size(compressed.width, compressed.height, base_mip);
+                    let image = create_mip_chain(
+                        device,
+                        &mut LazyPool::new(device),
                         queue_index,
-                        bitmap.pixels(),
-                        match bitmap_format {
-                            BitmapFormat::R => ImageFormat::R8,
-                            BitmapFormat::Rg => ImageFormat::R8G8,
-                            BitmapFormat::Rgb => ImageFormat::R8G8B8,
-                            BitmapFormat::Rgba => ImageFormat::R8G8B8A8,
-                        },
-                        bitmap.width(),
-                        bitmap.height(),
+                        compressed_image_format(compressed.format),
+                        width,
+                        height,
+                        &compressed.mips[base_mip as usize..],
                     )
-                    .context("Loading image")?;
+                    .context("Creating compressed image")?;
+
+                    *bitmap_entry = Some((image, compressed.has_alpha));
+                } else {
+                    let bitmap = pak.read_bitmap_id(id).context("Reading bitmap")?;
+                    let bitmap_format = bitmap.format();
+                    let mut image_loader = image_loader.lock();
+
+                    if image_loader.is_none() {
+                        *image_loader =
+                            Some(ImageLoader::new(device).context("Creating image loader")?);
+                    }
+
+                    let image = image_loader
+                        .as_mut()
+                        .unwrap()
+                        .decode_linear(
+                            0,
+                            queue_index,
+                            bitmap.pixels(),
+                            match bitmap_format {
+                                BitmapFormat::R => ImageFormat::R8,
+                                BitmapFormat::Rg => ImageFormat::R8G8,
+                                BitmapFormat::Rgb => ImageFormat::R8G8B8,
+                                BitmapFormat::Rgba => ImageFormat::R8G8B8A8,
+                            },
+                            bitmap.width(),
+                            bitmap.height(),
+                        )
+                        .context("Loading image")?;
 
-                *bitmap_entry = Some((image, bitmap_format == BitmapFormat::Rgba));
+                    drop(image_loader);
+
+                    // Textures are minified heavily in first-person views and shimmer without mips
+                    let image =
+                        generate_mip_chain(device, &mut LazyPool::new(device), queue_index, image)
+                            .context("Generating image mip chain")?;
+
+                    *bitmap_entry = Some((image, bitmap_format == BitmapFormat::Rgba));
+                }
             }
 
             Ok(bitmap_entry
@@ -502,11 +649,19 @@ impl Loader {
         fn read_material(
             device: &Arc<Device>,
             pak: &mut PakBuf,
+            layer: PakLayer,
             id: MaterialId,
+            compressed_bitmaps: &CompressedBitmaps,
             bitmap_cache: &Arc<Mutex<BitmapCache>>,
             image_loader: &Arc<Mutex<Option<ImageLoader>>>,
             queue_index: usize,
-        ) -> anyhow::Result<(Arc<Image>, Arc<Image>, Arc<Image>, Option<Arc<Image>>)> {
+        ) -> anyhow::Result<(
+            Arc<Image>,
+            Arc<Image>,
+            Arc<Image>,
+            Option<Arc<Image>>,
+            MaterialFlags,
+        )> {
             let info = pak.read_material_id(id).context("Reading material info")?;
 
             // Get the unique list of bitmaps in this material (In practice they are always unique!)
@@ -524,31 +679,58 @@ impl Loader {
             bitmap_ids.sort_unstable();
 
             let mut images = HashMap::with_capacity(bitmap_ids.len());
+            let mut flags = MaterialFlags::empty();
             for bitmap_id in bitmap_ids.iter().copied() {
-                let (image, _) = read_image(
+                // Bitmaps are only compressed when baking the base pak
+                let (image, has_alpha) = read_image(
                     device,
                     pak,
+                    layer,
                     bitmap_id,
+                    compressed_bitmaps
+                        .get(&bitmap_id)
+                        .filter(|_| layer == BASE_LAYER),
                     bitmap_cache,
                     image_loader,
                     queue_index,
                 )
                 .context("Reading material image")?;
+
+                // Color alpha is a cutout mask, and cutouts like foliage are seen from both sides
+                if bitmap_id == info.color && has_alpha {
+                    flags |= MaterialFlags::MASKED | MaterialFlags::TWO_SIDED;
+                }
+
                 images.insert(bitmap_id, image);
             }
 
             let color = images[&info.color].clone();
-            let normal = images[&info.color].clone();
-            let params = images[&info.color].clone();
+            let normal = images[&info.normal].clone();
+            let params = images[&info.params].clone();
             let emissive = info.emissive.map(|id| images[&id].clone());
 
-            Ok((color, normal, params, emissive))
+            Ok((color, normal, params, emissive, flags))
+        }
+
+        /// Returns the animation of a material; like compressed bitmaps, animations are only baked
+        /// for the base pak.
+        fn material_animation(
+            material_animations: &MaterialAnimations,
+            layer: PakLayer,
+            id: MaterialId,
+        ) -> MaterialAnimation {
+            material_animations
+                .get(&id)
+                .filter(|_| layer == BASE_LAYER)
+                .copied()
+                .unwrap_or_default()
         }
 
         for thread_index in 0..thread_count {
             let err = Arc::clone(&err);
             let loaded = Arc::clone(&loaded);
             let rx = rx.clone();
+            let status = Arc::clone(&status);
 
             let queue_index = thread_index;
 
@@ -556,6 +738,8 @@ impl Loader {
 
             let bitmap_buf = Arc::clone(&bitmap_buf);
             let bitmap_cache = Arc::clone(&bitmap_cache);
+            let compressed_bitmaps = Arc::clone(&compressed_bitmaps);
+            let material_animations = Arc::clone(&material_animations);
             let model_buf = Arc::clone(&model_buf);
             let image_loader = Arc::clone(&image_loader);
 
@@ -567,28 +751,34 @@ impl Loader {
             let sounds = Arc::clone(&sounds);
 
             threads.push(spawn(move || {
-                let pak = open_pak();
-                if let Err(e) = &pak {
-                    error!("Pak error: {e}");
-
-                    err.fetch_or(true, Ordering::Relaxed);
-                    return;
-                }
+                let mut paks = match open_pak_stack() {
+                    Ok(paks) => paks,
+                    Err(e) => {
+                        error!("Pak error: {e}");
 
-                let mut pak = pak.unwrap();
+                        err.lock().get_or_insert(anyhow!(e).context("Opening pak"));
+                        return;
+                    }
+                };
 
                 loop {
-                    if let Err(e) = match rx.recv().unwrap_or_else(|recv_err| {
+                    let message = rx.recv().unwrap_or_else(|recv_err| {
                         error!("Receive error: {recv_err}");
 
-                        err.store(true, Ordering::Relaxed);
+                        err.lock().get_or_insert(anyhow!(recv_err));
 
                         Message::Done
-                    }) {
+                    });
+
+                    if let Some(message_status) = message.status() {
+                        *status.lock() = message_status;
+                    }
+
+                    if let Err(e) = match message {
                         Message::Done => break,
                         Message::Bitmap(key) => load_bitmap(
                             &device,
-                            &mut pak,
+                            &mut paks,
                             key,
                             &bitmap_cache,
                             &image_loader,
@@ -598,13 +788,15 @@ impl Loader {
                         )
                         .with_context(|| format!("Bitmap {key}")),
                         Message::Font(key) => {
-                            load_font(&device, &mut pak, key, &image_loader, &fonts, queue_index)
+                            load_font(&device, &mut paks, key, &image_loader, &fonts, queue_index)
                                 .with_context(|| format!("Font {key}"))
                         }
                         Message::Material(key) => load_material(
                             &device,
-                            &mut pak,
+                            &mut paks,
                             key,
+                            &compressed_bitmaps,
+                            &material_animations,
                             &bitmap_cache,
                             &image_loader,
                             &model_buf,
@@ -615,7 +807,7 @@ impl Loader {
                         .with_context(|| format!("Material {key}")),
                         Message::Model(key) => load_model(
                             &device,
-                            &mut pak,
+                            &mut paks,
                             key,
                             &model_buf,
                             model_buf_info,
@@ -625,9 +817,11 @@ impl Loader {
                         .with_context(|| format!("Model {key}")),
                         Message::Scene(key) => load_scene(
                             &device,
-                            &mut pak,
+                            &mut paks,
                             key,
                             &scenes,
+                            &compressed_bitmaps,
+                            &material_animations,
                             &bitmap_cache,
                             &image_loader,
                             &model_buf,
@@ -637,12 +831,12 @@ impl Loader {
                             queue_index,
                         )
                         .with_context(|| format!("Scene {key}")),
-                        Message::Sound(key) => load_sound(&mut pak, key, &sounds)
+                        Message::Sound(key) => load_sound(&mut paks, key, &sounds)
                             .with_context(|| format!("Sound {key}")),
                     } {
                         error!("Load error: {e:?}");
 
-                        err.store(true, Ordering::SeqCst);
+                        err.lock().get_or_insert(e);
                         break;
                     }
 
@@ -690,6 +884,9 @@ impl Loader {
         Ok(Self {
             bitmaps,
             bitmap_buf,
+            bitmap_cache,
+            compressed_bitmaps,
+            device: Arc::clone(device),
             err,
             fonts,
             loaded,
@@ -700,24 +897,78 @@ impl Loader {
             total,
             scenes,
             sounds,
+            status,
+        })
+    }
+}
+
+impl Loader {
+    /// Model buffer pipelines are compiled in the background while loading, so they count as one
+    /// more loaded item.
+    fn is_model_buf_ready(&self) -> bool {
+        // Loader threads hold the lock while loading so we report busy instead of waiting
+        self.model_buf
+            .try_lock()
+            .map(|model_buf| {
+                model_buf
+                    .as_ref()
+                    .map(ModelBuffer::is_ready)
+                    .unwrap_or(true)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of loaded models which may be drawn, along with the number of loaded
+    /// models; the ray trace technique builds the acceleration structure of each model in the
+    /// background, so they also count as loaded items.
+    fn model_progress(&self) -> Option<(usize, usize)> {
+        self.model_buf.try_lock().map(|model_buf| {
+            model_buf
+                .as_ref()
+                .map(ModelBuffer::model_progress)
+                .unwrap_or_default()
         })
     }
 }
 
 impl Operation<LoadResult> for Loader {
     fn progress(&self) -> f32 {
-        let loaded = self.loaded.load(Ordering::Relaxed).min(self.total);
+        let (built, models) = self.model_progress().unwrap_or_default();
+        let loaded = self.loaded.load(Ordering::Relaxed).min(self.total)
+            + self.is_model_buf_ready() as usize
+            + built;
 
-        loaded as f32 / self.total.max(1) as f32
+        loaded as f32 / (self.total + 1 + models) as f32
+    }
+
+    fn status(&self) -> Cow<str> {
+        if self.loaded.load(Ordering::Relaxed) < self.total {
+            Cow::Owned(self.status.lock().clone())
+        } else if !self.is_model_buf_ready() {
+            Cow::Borrowed("Compiling pipelines")
+        } else if let Some((built, models)) = self
+            .model_progress()
+            .filter(|(built, models)| built < models)
+        {
+            Cow::Owned(format!(
+                "Building acceleration structures ({built}/{models})"
+            ))
+        } else {
+            Cow::Borrowed("Done")
+        }
     }
 
     fn is_done(&self) -> bool {
         let loaded = self.loaded.load(Ordering::Relaxed);
         loaded == self.total
+            && self.is_model_buf_ready()
+            && self
+                .model_progress()
+                .is_some_and(|(built, models)| built == models)
     }
 
     fn is_err(&self) -> bool {
-        self.err.load(Ordering::Relaxed)
+        self.err.lock().is_some()
     }
 
     fn unwrap(self: Box<Self>) -> LoadResult {
@@ -729,7 +980,21 @@ impl Operation<LoadResult> for Loader {
         }
 
         let bitmap_buf = Arc::try_unwrap(self.bitmap_buf).unwrap().into_inner();
-        let model_buf = Arc::try_unwrap(self.model_buf).unwrap().into_inner();
+        let mut model_buf = Arc::try_unwrap(self.model_buf).unwrap().into_inner();
+
+        if let Some(model_buf) = &mut model_buf {
+            let compressed_images = self
+                .bitmap_cache
+                .lock()
+                .iter()
+                .filter(|((layer, id), _)| {
+                    *layer == BASE_LAYER && self.compressed_bitmaps.contains_key(id)
+                })
+                .filter_map(|(&(_, id), entry)| Some((id, Arc::clone(&entry.lock().as_ref()?.0))))
+                .collect::<Vec<_>>();
+
+            model_buf.stream_textures(&self.device, self.compressed_bitmaps, compressed_images);
+        }
 
         let bitmaps = Arc::try_unwrap(self.bitmaps).unwrap().into_inner();
         let fonts = Arc::try_unwrap(self.fonts).unwrap().into_inner();
@@ -760,16 +1025,26 @@ impl Operation<LoadResult> for Loader {
             sounds,
         }
     }
+
+    fn unwrap_err(self: Box<Self>) -> anyhow::Error {
+        debug_assert!(self.is_err());
+
+        // Other threads may still be loading, so they are not joined
+        self.err
+            .lock()
+            .take()
+            .unwrap_or_else(|| anyhow!("Unknown load error"))
+    }
 }
 
 pub struct LoadResult {
     pub bitmap_buf: Option<BitmapBuffer>,
     pub model_buf: Option<ModelBuffer>,
 
-    pub bitmaps: HashMap<&'static str, Bitmap>,
-    pub fonts: HashMap<&'static str, BitmapFont>,
-    pub materials: HashMap<IdOrKey<MaterialId>, Material>,
-    pub models: HashMap<IdOrKey<ModelId>, Model>,
-    pub scenes: HashMap<&'static str, SceneBuf>,
-    pub sounds: HashMap<&'static str, StaticSoundData>,
+    pub bitmaps: HashMap<BitmapKey, Bitmap>,
+    pub fonts: HashMap<FontKey, BitmapFont>,
+    pub materials: HashMap<IdOrKey<MaterialId, MaterialKey>, Material>,
+    pub models: HashMap<IdOrKey<ModelId, ModelKey>, Model>,
+    pub scenes: HashMap<SceneKey, (PakLayer, SceneBuf)>,
+    pub sounds: HashMap<SoundKey, StaticSoundData>,
 }
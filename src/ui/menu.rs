@@ -1,205 +1,157 @@
 use {
     super::{
-        loader::{LoadInfo, LoadResult, Loader},
+        error::ErrorScreen,
+        layout::{Anchor, Axis, Element, Layout, Navigation, SixSlice, Style},
+        level_select::{CheatCode, LevelSelect},
+        loader::{IdOrKey, LoadInfo, LoadResult, Loader},
         play::Play,
         transition::{Transition, TransitionInfo},
+        ui_sound::UiSound,
         CursorStyle, DrawContext, Operation, Ui, UpdateContext,
     },
     crate::{
         art,
-        render::bitmap::{Bitmap, BitmapBuffer, Rect},
+        asset_key::SceneKey,
+        config::{Config, FullscreenMode},
+        display,
+        game::{difficulty::Difficulty, world_delta::WorldDeltas},
+        level::scene::Scene,
+        math::Aabb,
+        mods::active_mods,
+        render::{
+            bitmap::{Bitmap, BitmapBuffer, Rect},
+            camera::{Camera, CameraPath},
+            capabilities::DeviceCapabilities,
+            depth_of_field::DepthOfField,
+            model::{ModelBuffer, ModelBufferInfo, ModelBufferTechnique, TextureFiltering},
+        },
     },
+    glam::Vec3,
     kira::sound::static_sound::StaticSoundData,
     screen_13::prelude::*,
     screen_13_fx::BitmapFont,
-    std::{cell::RefCell, sync::Arc, time::Duration},
+    std::{borrow::Cow, cell::RefCell, sync::Arc, time::Duration},
 };
 
-struct Button {
-    x: i32,
-    y: i32,
-    width: u32,
-    height: u32,
-    text: &'static str,
-    text_layout: ([i32; 2], [u32; 2]),
-    is_pressed: bool,
+/// A level drawn behind the menu by a camera which slowly circles it, blurred by depth of field so
+/// that the menu stands out.
+struct Background {
+    camera: Camera,
+    camera_path: CameraPath,
+    model_buf: ModelBuffer,
+    path_time: f32,
 }
 
-struct Content {
-    blue_button_bottom: Bitmap,
-    blue_button_bottom_corner: Bitmap,
-    blue_button_middle: Bitmap,
-    blue_button_side: Bitmap,
-    blue_button_top_corner: Bitmap,
-    blue_button_top: Bitmap,
-
-    beep_sound: StaticSoundData,
-    small_font: BitmapFont,
-}
+impl Background {
+    /// Seconds taken to circle the scene once.
+    const ORBIT_SECS: f32 = 120.0;
 
-impl Content {
-    fn draw_blue_button(
-        &self,
-        x: i32,
-        y: i32,
-        width: u32,
-        height: u32,
-        bitmaps: &mut Vec<(Bitmap, Rect)>,
-    ) {
-        Self::draw_six_slice(
-            self.blue_button_top_corner,
-            self.blue_button_top,
-            self.blue_button_side,
-            self.blue_button_bottom_corner,
-            self.blue_button_bottom,
-            self.blue_button_middle,
-            x,
-            y,
-            width,
-            height,
-            bitmaps,
-        );
-    }
+    const SCENE: SceneKey = art::SCENE_LEVEL_01;
 
-    fn draw_six_slice(
-        top_corner: Bitmap,
-        top: Bitmap,
-        side: Bitmap,
-        bottom_corner: Bitmap,
-        bottom: Bitmap,
-        middle: Bitmap,
-        x: i32,
-        y: i32,
-        width: u32,
-        height: u32,
-        bitmaps: &mut Vec<(Bitmap, Rect)>,
-    ) {
-        let (top_corner_width, top_corner_height) = top_corner.size();
-        let (_, top_height) = top.size();
-        let (side_width, _) = side.size();
-        let (bottom_corner_width, bottom_corner_height) = bottom_corner.size();
-
-        // Top left
-        bitmaps.push((
-            top_corner,
-            Rect::new(x, y, top_corner_width as _, top_corner_height as _),
-        ));
-
-        bitmaps.push((
-            top,
-            Rect::new(
-                x + top_corner_width as i32,
-                y,
-                width as i32 - (2 * (top_corner_width as i32)),
-                top_height as i32,
-            ),
-        ));
-
-        // Top right
-        bitmaps.push((
-            top_corner,
-            Rect::new(
-                x + width as i32,
-                y,
-                -(top_corner_width as i32),
-                top_corner_height as _,
-            ),
-        ));
-
-        // Left
-        bitmaps.push((
-            side,
-            Rect::new(
-                x,
-                y + top_corner_height as i32,
-                side_width as _,
-                height as i32 - (top_corner_height as i32 + bottom_corner_height as i32),
-            ),
-        ));
-
-        // Right
-        bitmaps.push((
-            side,
-            Rect::new(
-                x + width as i32,
-                y + top_corner_height as i32,
-                -(side_width as i32),
-                height as i32 - (top_corner_height as i32 + bottom_corner_height as i32),
-            ),
-        ));
-
-        // Bottom left
-        bitmaps.push((
-            bottom_corner,
-            Rect::new(
-                x,
-                y + height as i32 - bottom_corner_height as i32,
-                bottom_corner_width as _,
-                bottom_corner_height as _,
-            ),
-        ));
-
-        bitmaps.push((
-            bottom,
-            Rect::new(
-                x + bottom_corner_width as i32,
-                y + height as i32 - bottom_corner_height as i32,
-                width as i32 - (2 * (bottom_corner_width as i32)),
-                bottom_corner_height as _,
-            ),
-        ));
-
-        // Bottom right
-        bitmaps.push((
-            bottom_corner,
-            Rect::new(
-                x + width as i32,
-                y + height as i32 - bottom_corner_height as i32,
-                -(bottom_corner_width as i32),
-                bottom_corner_height as _,
-            ),
-        ));
-
-        bitmaps.push((
-            middle,
-            Rect::new(
-                x + side_width as i32,
-                y + top_height as i32,
-                width as i32 - 2 * (side_width as i32),
-                height as i32 - (top_height as i32 + bottom_corner_height as i32),
-            ),
-        ));
+    /// Loads the scene into a model buffer of its own, which uses the raster technique and no
+    /// reflection probes so that it stays light next to the level loading for play.
+    fn load(device: &Arc<Device>, texture_filtering: TextureFiltering) -> anyhow::Result<Loader> {
+        Loader::spawn_threads_model_buf(
+            device,
+            ModelBufferInfo::new()
+                .technique(ModelBufferTechnique::Raster)
+                .reflection_probe_capacity(0)
+                .texture_filtering(texture_filtering)
+                .build(),
+            LoadInfo::default().scenes(&[Self::SCENE]),
+        )
     }
-}
 
-struct Gui {
-    play_button: Button,
-    valid_framebuffer: (u32, u32),
-}
+    fn new(mut loader: LoadResult) -> Self {
+        let mut model_buf = loader.model_buf.unwrap();
+        let (scene_layer, scene) = loader.scenes.remove(&Self::SCENE).unwrap();
+        let scene = Scene::new(scene);
+
+        for scene_ref in scene.refs() {
+            let Some(model) = scene_ref
+                .model()
+                .map(|id| loader.models[&IdOrKey::Id(scene_layer, id)])
+            else {
+                continue;
+            };
+
+            let materials = scene_ref
+                .materials()
+                .iter()
+                .copied()
+                .map(|id| loader.materials[&IdOrKey::Id(scene_layer, id)])
+                .collect::<Box<_>>();
+            model_buf.insert_model_instance(
+                model,
+                &materials,
+                scene_ref.position(),
+                scene_ref.rotation(),
+            );
+        }
 
-impl Gui {
-    fn is_valid(&self, framebuffer_width: u32, framebuffer_height: u32) -> bool {
-        self.valid_framebuffer == (framebuffer_width, framebuffer_height)
+        let bounds = Aabb::from_points(scene.refs().map(|scene_ref| scene_ref.position()))
+            .unwrap_or_else(|| Aabb::from_center_half_extents(Vec3::ZERO, Vec3::ONE));
+        let center = (bounds.min() + bounds.max()) * 0.5;
+
+        // The camera stays within the scene, looking down at its center, which is kept in focus
+        let radius = ((bounds.max() - bounds.min()).length() * 0.25).max(5.0);
+        let height = radius * 0.25;
+        let focus_distance = Vec3::new(radius, height, 0.0).length();
+
+        model_buf.set_depth_of_field(Some(DepthOfField {
+            focus_distance,
+            focus_range: focus_distance * 0.5,
+        }));
+
+        Self {
+            camera: Camera {
+                aspect_ratio: 0.0,
+                fov_y: 45.0,
+                pitch: 0.0,
+                yaw: 0.0,
+                position: center,
+            },
+            camera_path: CameraPath::orbit(center, radius, height, Self::ORBIT_SECS),
+            model_buf,
+            path_time: 0.0,
+        }
     }
 
-    fn layout(&mut self, content: &Content, framebuffer_width: u32, framebuffer_height: u32) {
-        if self.is_valid(framebuffer_width, framebuffer_height) {
-            return;
-        }
+    fn record(
+        &mut self,
+        render_graph: &mut RenderGraph,
+        framebuffer: impl Into<AnyImageNode>,
+    ) -> Result<(), DriverError> {
+        let framebuffer = framebuffer.into();
+        let framebuffer_info = render_graph.node_info(framebuffer);
 
-        self.play_button.text_layout = content.small_font.measure(&self.play_button.text);
-        self.play_button.width = self.play_button.text_layout.1[0] + 10;
-        self.play_button.height = self.play_button.text_layout.1[1] + 8;
-        self.play_button.x = framebuffer_width as i32 / 2 - self.play_button.width as i32 / 2;
-        self.play_button.y = framebuffer_height as i32 / 2 - self.play_button.height as i32 / 2;
+        self.camera.aspect_ratio = framebuffer_info.width as f32 / framebuffer_info.height as f32;
+        self.camera_path.sample(self.path_time, &mut self.camera);
 
-        self.valid_framebuffer = (framebuffer_width, framebuffer_height);
+        self.model_buf
+            .record(render_graph, framebuffer, &mut self.camera)
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.model_buf.advance_time(dt);
+        self.path_time = (self.path_time + dt) % self.camera_path.duration();
     }
 }
 
+struct Content {
+    blue_button: SixSlice,
+
+    /// Given to [`UiSounds`](super::ui_sound::UiSounds) on the first update, after which every
+    /// screen shares it.
+    beep_sound: Option<StaticSoundData>,
+
+    small_font: BitmapFont,
+}
+
 struct Load {
     device: Arc<Device>,
     loader: Box<dyn Operation<LoadResult>>,
+    texture_filtering: TextureFiltering,
 }
 
 impl Operation<Menu> for Load {
@@ -207,6 +159,10 @@ impl Operation<Menu> for Load {
         self.loader.progress()
     }
 
+    fn status(&self) -> Cow<str> {
+        self.loader.status()
+    }
+
     fn is_done(&self) -> bool {
         self.loader.is_done()
     }
@@ -217,80 +173,212 @@ impl Operation<Menu> for Load {
 
     fn unwrap(self: Box<Self>) -> Menu {
         let device = Arc::clone(&self.device);
+        let texture_filtering = self.texture_filtering;
         let mut loader = self.loader.unwrap();
         let bitmap_buf = loader.bitmap_buf.unwrap();
 
+        let mut bitmap = |key| loader.bitmaps.remove(&key).unwrap();
+        let blue_button = SixSlice {
+            top_corner: bitmap(art::BITMAP_BLUE_BUTTON_TOP_CORNER_PNG),
+            top: bitmap(art::BITMAP_BLUE_BUTTON_TOP_PNG),
+            side: bitmap(art::BITMAP_BLUE_BUTTON_SIDE_PNG),
+            bottom_corner: bitmap(art::BITMAP_BLUE_BUTTON_BOTTOM_CORNER_PNG),
+            bottom: bitmap(art::BITMAP_BLUE_BUTTON_BOTTOM_PNG),
+            middle: bitmap(art::BITMAP_BLUE_BUTTON_MIDDLE_PNG),
+        };
+
         let content = Content {
-            blue_button_bottom: loader
-                .bitmaps
-                .remove(art::BITMAP_BLUE_BUTTON_BOTTOM_PNG)
-                .unwrap(),
-            blue_button_bottom_corner: loader
-                .bitmaps
-                .remove(art::BITMAP_BLUE_BUTTON_BOTTOM_CORNER_PNG)
-                .unwrap(),
-            blue_button_middle: loader
-                .bitmaps
-                .remove(art::BITMAP_BLUE_BUTTON_MIDDLE_PNG)
-                .unwrap(),
-            blue_button_side: loader
-                .bitmaps
-                .remove(art::BITMAP_BLUE_BUTTON_SIDE_PNG)
-                .unwrap(),
-            blue_button_top: loader
-                .bitmaps
-                .remove(art::BITMAP_BLUE_BUTTON_TOP_PNG)
-                .unwrap(),
-            blue_button_top_corner: loader
-                .bitmaps
-                .remove(art::BITMAP_BLUE_BUTTON_TOP_CORNER_PNG)
-                .unwrap(),
+            blue_button,
 
-            beep_sound: loader
-                .sounds
-                .remove(art::SOUND_DIGITAL_THREE_TONE_1_OGG)
-                .unwrap(),
+            beep_sound: loader.sounds.remove(&art::SOUND_DIGITAL_THREE_TONE_1_OGG),
             small_font: loader
                 .fonts
-                .remove(art::FONT_KENNEY_MINI_SQUARE_MONO)
+                .remove(&art::FONT_KENNEY_MINI_SQUARE_MONO)
                 .unwrap(),
         };
 
+        let mut layout = Layout::new(
+            Element::stack(
+                Axis::Vertical,
+                8,
+                vec![
+                    Element::label("Mood", [0xcc, 0xcc, 0xcc]),
+                    Element::button("New game").id(Menu::PLAY_BUTTON),
+                    Element::button("Settings").id(Menu::SETTINGS_BUTTON),
+                    Element::button("Mods").id(Menu::MODS_BUTTON),
+                ],
+            )
+            .anchor(Anchor::Center),
+        );
+
+        // Keyboard and gamepad players start on the first button
+        layout.set_focus(Some(Menu::PLAY_BUTTON));
+
+        // The menu is shown without a background until it loads, or if it fails to
+        let background_loader = Background::load(&device, texture_filtering)
+            .map(|loader| Box::new(loader) as Box<dyn Operation<LoadResult>>)
+            .map_err(|err| warn!("Unable to load menu background: {err:#}"))
+            .ok();
+
         Menu {
+            background: None,
+            background_loader,
             bitmap_buf,
+            cheat_code: Default::default(),
             content,
             device,
-            gui: Gui {
-                play_button: Button {
-                    x: 0,
-                    y: 0,
-                    width: 0,
-                    height: 0,
-                    text: "Press any key to continue",
-                    text_layout: ([0, 0], [0, 0]),
-                    is_pressed: false,
-                },
-                valid_framebuffer: (0, 0),
-            },
+            difficulty: None,
+            layout,
+            mods_layout: Menu::mods_layout(),
+            new_game_layout: Menu::new_game_layout(),
             play: None,
+
+            // Rebuilt from the config each time the settings screen is shown
+            settings_layout: Menu::settings_layout(&Config::default(), None),
+
+            showing_mods: false,
+            showing_new_game: false,
+            showing_settings: false,
         }
     }
+
+    fn unwrap_err(self: Box<Self>) -> anyhow::Error {
+        self.loader.unwrap_err()
+    }
 }
 
 pub struct Menu {
+    background: Option<Background>,
+    background_loader: Option<Box<dyn Operation<LoadResult>>>,
     bitmap_buf: BitmapBuffer,
+
+    /// Typing the level select command opens that screen, for reviewing content.
+    cheat_code: CheatCode,
+
     content: Content,
     device: Arc<Device>,
-    gui: Gui,
+
+    /// The difficulty chosen for a new game, which starts once the level has loaded.
+    difficulty: Option<Difficulty>,
+
+    layout: Layout,
+
+    /// The mods screen, which lists the mods layered over the game.
+    mods_layout: Layout,
+
+    /// The new game screen, which asks for the difficulty.
+    new_game_layout: Layout,
+
     play: Option<Box<dyn Operation<Play>>>,
+
+    /// The settings screen, whose buttons show and change the current value of each setting.
+    settings_layout: Layout,
+
+    showing_mods: bool,
+    showing_new_game: bool,
+    showing_settings: bool,
 }
 
 impl Menu {
-    pub fn load(device: &Arc<Device>) -> anyhow::Result<impl Operation<Self>> {
+    const BACK_BUTTON: &str = "back";
+
+    /// Color the focus background is multiplied by, so it stands out from the button it outlines.
+    const FOCUS_TINT: [u8; 3] = [0xff, 0xcc, 0x33];
+
+    const DISPLAY_BUTTON: &str = "display";
+    const GRAPHICS_BUTTON: &str = "graphics";
+    const MODS_BUTTON: &str = "mods";
+    const MONITOR_BUTTON: &str = "monitor";
+
+    /// Text of the monitor button for each monitor index; monitors past these are not offered.
+    const MONITOR_TEXT: [&str; 8] = [
+        "Monitor: 1",
+        "Monitor: 2",
+        "Monitor: 3",
+        "Monitor: 4",
+        "Monitor: 5",
+        "Monitor: 6",
+        "Monitor: 7",
+        "Monitor: 8",
+    ];
+
+    const PLAY_BUTTON: &str = "play";
+    const SETTINGS_BUTTON: &str = "settings";
+
+    /// Changes the setting of the given settings button to its next value and applies it,
+    /// returning `true` if it changed.
+    ///
+    /// Windowed mode is not written to the config because it is chosen by the `--window`
+    /// argument; the fullscreen mode and monitor are.
+    fn change_setting(&self, id: &str, config: &mut Config, window: &Window) -> bool {
+        match id {
+            Self::DISPLAY_BUTTON => {
+                let mode = match display::fullscreen_mode(window) {
+                    None => Some(FullscreenMode::Borderless),
+                    Some(FullscreenMode::Borderless) => Some(FullscreenMode::Exclusive),
+                    Some(FullscreenMode::Exclusive) => None,
+                };
+
+                if let Some(mode) = mode {
+                    config.fullscreen_mode = mode;
+                }
+
+                display::set_fullscreen(window, mode, config.monitor);
+            }
+            Self::GRAPHICS_BUTTON => {
+                // Ray tracing is skipped where it is unsupported, so that automatic is the same
+                let ray_tracing = DeviceCapabilities::new(&self.device).ray_tracing;
+
+                config.graphics = match config.graphics {
+                    None => Some(ModelBufferTechnique::Raster),
+                    Some(ModelBufferTechnique::Raster) if ray_tracing => {
+                        Some(ModelBufferTechnique::RayTrace)
+                    }
+                    Some(_) => None,
+                };
+            }
+            Self::MONITOR_BUTTON => {
+                let monitor_count = window
+                    .available_monitors()
+                    .count()
+                    .min(Self::MONITOR_TEXT.len());
+
+                config.monitor = match config.monitor {
+                    None => Some(0),
+                    Some(index) if index + 1 < monitor_count => Some(index + 1),
+                    Some(_) => None,
+                };
+
+                display::set_fullscreen(window, display::fullscreen_mode(window), config.monitor);
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn current_layout(&mut self) -> &mut Layout {
+        if self.showing_mods {
+            &mut self.mods_layout
+        } else if self.showing_new_game {
+            &mut self.new_game_layout
+        } else if self.showing_settings {
+            &mut self.settings_layout
+        } else {
+            &mut self.layout
+        }
+    }
+
+    pub fn load(
+        device: &Arc<Device>,
+        texture_filtering: TextureFiltering,
+    ) -> anyhow::Result<impl Operation<Self>> {
         let device = Arc::clone(device);
         let loader = Box::new(Loader::spawn_threads(
             &device,
             None,
+            texture_filtering,
+            Default::default(),
             LoadInfo::default()
                 .bitmaps(&[
                     art::BITMAP_BLUE_BUTTON_BOTTOM_PNG,
@@ -304,15 +392,110 @@ impl Menu {
                 .sounds(&[art::SOUND_DIGITAL_THREE_TONE_1_OGG]),
         )?);
 
-        Ok(Load { device, loader })
+        Ok(Load {
+            device,
+            loader,
+            texture_filtering,
+        })
+    }
+
+    /// Lists the active mods; they are chosen by placing paks in the mods directory, so the list
+    /// may not be changed here.
+    fn mods_layout() -> Layout {
+        let mods = active_mods();
+        let mut children = vec![Element::label("Mods", [0xcc, 0xcc, 0xcc])];
+
+        if mods.is_empty() {
+            children.push(Element::label("No mods installed", [0x99, 0x99, 0x99]));
+        } else {
+            // Later mods override earlier ones, so they are listed with the highest priority first
+            children.extend(
+                mods.iter()
+                    .rev()
+                    .map(|mod_info| Element::label(&mod_info.name, [0xff, 0xff, 0xff])),
+            );
+        }
+
+        children.push(Element::button("Back").id(Menu::BACK_BUTTON));
+
+        let mut layout =
+            Layout::new(Element::stack(Axis::Vertical, 8, children).anchor(Anchor::Center));
+        layout.set_focus(Some(Menu::BACK_BUTTON));
+
+        layout
+    }
+
+    /// Lists the difficulties a new game may be played at, starting on normal.
+    fn new_game_layout() -> Layout {
+        let mut children = vec![Element::label("Difficulty", [0xcc, 0xcc, 0xcc])];
+        children.extend(
+            Difficulty::ALL
+                .map(|difficulty| Element::button(difficulty.label()).id(difficulty.name())),
+        );
+        children.push(Element::button("Back").id(Menu::BACK_BUTTON));
+
+        let mut layout =
+            Layout::new(Element::stack(Axis::Vertical, 8, children).anchor(Anchor::Center));
+        layout.set_focus(Some(Difficulty::Normal.name()));
+
+        layout
+    }
+
+    /// Lists the settings which are changed by activating them, each of which applies
+    /// immediately; `fullscreen_mode` is that of the window, which is `None` while windowed.
+    fn settings_layout(config: &Config, fullscreen_mode: Option<FullscreenMode>) -> Layout {
+        let display = match fullscreen_mode {
+            None => "Display: Windowed",
+            Some(FullscreenMode::Borderless) => "Display: Borderless fullscreen",
+            Some(FullscreenMode::Exclusive) => "Display: Exclusive fullscreen",
+        };
+        let graphics = match config.graphics {
+            None => "Graphics: Automatic",
+            Some(ModelBufferTechnique::Raster) => "Graphics: Raster",
+            Some(ModelBufferTechnique::RayTrace) => "Graphics: Ray tracing",
+        };
+        let monitor = config
+            .monitor
+            .and_then(|index| Menu::MONITOR_TEXT.get(index).copied())
+            .unwrap_or("Monitor: Primary");
+
+        let mut layout = Layout::new(
+            Element::stack(
+                Axis::Vertical,
+                8,
+                vec![
+                    Element::label("Settings", [0xcc, 0xcc, 0xcc]),
+                    Element::button(display).id(Menu::DISPLAY_BUTTON),
+                    Element::button(monitor).id(Menu::MONITOR_BUTTON),
+                    Element::button(graphics).id(Menu::GRAPHICS_BUTTON),
+                    Element::button("Back").id(Menu::BACK_BUTTON),
+                ],
+            )
+            .anchor(Anchor::Center),
+        );
+        layout.set_focus(Some(Menu::DISPLAY_BUTTON));
+
+        layout
     }
 }
 
 impl Ui for Menu {
     fn draw(&mut self, frame: DrawContext) {
-        frame
-            .render_graph
-            .clear_color_image_value(frame.framebuffer_image, [0.25, 0.0, 0.25, 1.0]);
+        if let Some(Err(err)) = self
+            .background
+            .as_mut()
+            .map(|background| background.record(frame.render_graph, frame.framebuffer_image))
+        {
+            warn!("Unable to draw menu background: {err}");
+
+            self.background = None;
+        }
+
+        if self.background.is_none() {
+            frame
+                .render_graph
+                .clear_color_image_value(frame.framebuffer_image, [0.25, 0.0, 0.25, 1.0]);
+        }
 
         thread_local! {
             static BITMAPS: RefCell<Vec<(Bitmap, Rect)>> = Default::default();
@@ -320,22 +503,31 @@ impl Ui for Menu {
 
         let framebuffer_info = frame.render_graph.node_info(frame.framebuffer_image);
 
-        self.gui.layout(
-            &self.content,
+        let layout = if self.showing_mods {
+            &mut self.mods_layout
+        } else if self.showing_new_game {
+            &mut self.new_game_layout
+        } else if self.showing_settings {
+            &mut self.settings_layout
+        } else {
+            &mut self.layout
+        };
+        layout.layout(
+            &self.content.small_font,
             framebuffer_info.width,
             framebuffer_info.height,
         );
 
+        let style = Style {
+            button: self.content.blue_button,
+            focus: self.content.blue_button.tinted(Menu::FOCUS_TINT),
+            panel: self.content.blue_button,
+        };
+
         BITMAPS.with(|bitmaps| {
             let mut bitmaps = bitmaps.borrow_mut();
             bitmaps.clear();
-            self.content.draw_blue_button(
-                self.gui.play_button.x,
-                self.gui.play_button.y,
-                self.gui.play_button.width,
-                self.gui.play_button.height as _,
-                &mut bitmaps,
-            );
+            layout.draw(&style, &mut bitmaps);
 
             self.bitmap_buf
                 .record(
@@ -346,16 +538,10 @@ impl Ui for Menu {
                 .unwrap();
         });
 
-        self.content.small_font.print(
+        layout.print(
+            &self.content.small_font,
             frame.render_graph,
             frame.framebuffer_image,
-            (self.gui.play_button.x + (self.gui.play_button.width as i32 / 2)
-                - (self.gui.play_button.text_layout.1[0] as i32 / 2)) as _,
-            (self.gui.play_button.y + (self.gui.play_button.height as i32 / 2)
-                - (self.gui.play_button.text_layout.1[1] as i32 / 2)
-                - 3) as _,
-            [0x00, 0x00, 0x00],
-            self.gui.play_button.text,
         );
 
         self.content.small_font.print(
@@ -366,66 +552,193 @@ impl Ui for Menu {
             [0xff, 0xff, 0xff],
             format!("FPS: {}", (1.0 / frame.dt).round()),
         );
+
+        // The level loads while the menu is shown
+        if let Some(play) = self.play.as_ref().filter(|play| !play.is_done()) {
+            let text = format!("{}... {}%", play.status(), (play.progress() * 100.0) as u8);
+            let (_, [_, height]) = self.content.small_font.measure(&text);
+
+            self.content.small_font.print(
+                frame.render_graph,
+                frame.framebuffer_image,
+                0.0,
+                framebuffer_info.height.saturating_sub(height) as f32,
+                [0xff, 0xff, 0xff],
+                text,
+            );
+        }
+
+        // Notices found at startup, such as a previous crash, are first shown here
+        frame.captions.print(
+            &self.content.small_font,
+            frame.render_graph,
+            frame.framebuffer_image,
+        );
     }
 
     fn update(mut self: Box<Self>, ui: UpdateContext) -> Option<Box<dyn Ui>> {
         *ui.cursor = Some(CursorStyle::PointerShadow);
 
-        #[cfg(debug_assertions)]
-        if ui.keyboard.is_pressed(&VirtualKeyCode::Escape) {
-            return None;
+        // There is no hover sound yet; one beep for each focus change would be too much
+        if let Some(beep_sound) = self.content.beep_sound.take() {
+            ui.ui_sounds
+                .insert(UiSound::Click, [beep_sound.clone()])
+                .insert(UiSound::Back, [beep_sound]);
+        }
+
+        if self
+            .background_loader
+            .as_ref()
+            .is_some_and(|loader| loader.is_done() || loader.is_err())
+        {
+            let loader = self.background_loader.take().unwrap();
+
+            if loader.is_err() {
+                warn!("Unable to load menu background: {:#}", loader.unwrap_err());
+            } else {
+                self.background = Some(Background::new(loader.unwrap()));
+            }
+        }
+
+        if let Some(background) = &mut self.background {
+            background.update(ui.dt);
+        }
+
+        if self.cheat_code.update(ui.events) {
+            let device = Arc::clone(&self.device);
+
+            return Some(
+                match LevelSelect::new(&device, WorldDeltas::default(), self) {
+                    Ok(level_select) => Box::new(level_select),
+                    Err(err) => Box::new(ErrorScreen::new(&device, err)),
+                },
+            );
+        }
+
+        let navigation = ui.navigation();
+
+        if navigation == Some(Navigation::Back) {
+            ui.ui_sounds.emit(UiSound::Back);
+
+            // The menu is the first screen, so backing up from it quits
+            if !self.showing_mods && !self.showing_new_game && !self.showing_settings {
+                return None;
+            }
+
+            self.difficulty = None;
+            self.showing_mods = false;
+            self.showing_new_game = false;
+            self.showing_settings = false;
+
+            return Some(self);
         }
 
         if self.play.is_none() {
-            self.play = Some(Box::new(
-                Play::load(&self.device, ui.config.graphics).unwrap(),
-            ));
+            match Play::load(
+                &self.device,
+                Play::DEFAULT_SCENE,
+                None,
+                WorldDeltas::default(),
+                ui.config.graphics,
+                ui.config.texture_filtering,
+                ui.config.ambient_occlusion,
+            ) {
+                Ok(play) => self.play = Some(Box::new(play)),
+                Err(err) => return Some(Box::new(ErrorScreen::new(&self.device, err))),
+            }
         }
 
         if let Some(play) = &self.play {
             if play.is_err() {
-                panic!();
+                let err = self.play.take().unwrap().unwrap_err();
+
+                return Some(Box::new(ErrorScreen::new(&self.device, err)));
+            }
+        }
+
+        // The mods and settings screens may be shown while the level loads
+        let layout = self.current_layout();
+        let activated = if layout.is_valid(ui.framebuffer_width, ui.framebuffer_height) {
+            let (mouse_x, mouse_y) = ui.mouse.position();
+            let mouse_x = (mouse_x / ui.framebuffer_scale) as i32;
+            let mouse_y = (mouse_y / ui.framebuffer_scale) as i32;
+            let hovered = layout.hover(mouse_x, mouse_y);
+            let activated = if let Some(navigation) = navigation {
+                layout.navigate(navigation)
+            } else if ui.mouse.is_pressed(MouseButton::Left) && hovered.is_some() {
+                ui.ui_sounds.emit(UiSound::Click);
+
+                hovered
+            } else {
+                None
+            };
+
+            for sound in layout.take_sounds() {
+                ui.ui_sounds.emit(sound);
             }
 
-            if play.is_done() {
-                if self
-                    .gui
-                    .is_valid(ui.framebuffer_width, ui.framebuffer_height)
-                {
-                    if true || ui.mouse.is_pressed(MouseButton::Left) {
-                        let (mouse_x, mouse_y) = ui.mouse.position();
-                        let mouse_x = (mouse_x / ui.framebuffer_scale) as i32;
-                        let mouse_y = (mouse_y / ui.framebuffer_scale) as i32;
-
-                        if true
-                            || mouse_x >= self.gui.play_button.x
-                                && mouse_y >= self.gui.play_button.y
-                                && mouse_x
-                                    <= self.gui.play_button.x + self.gui.play_button.width as i32
-                                && mouse_y
-                                    <= self.gui.play_button.y + self.gui.play_button.height as i32
-                        {
-                            let play = Box::new(self.play.take().unwrap().unwrap());
-
-                            *ui.cursor = None;
-
-                            #[cfg(not(debug_assertions))]
-                            ui.window
-                                .set_cursor_grab(CursorGrabMode::Confined)
-                                .unwrap_or_default();
-
-                            ui.set_cursor_position_center();
-
-                            return Some(Box::new(Transition::new(
-                                self,
-                                play,
-                                TransitionInfo::Fade,
-                                Duration::from_secs_f32(0.25),
-                            )));
+            activated
+        } else {
+            None
+        };
+
+        match activated {
+            Some(Self::BACK_BUTTON) => {
+                self.difficulty = None;
+                self.showing_mods = false;
+                self.showing_new_game = false;
+                self.showing_settings = false;
+            }
+            Some(Self::MODS_BUTTON) => self.showing_mods = true,
+            Some(Self::PLAY_BUTTON) => self.showing_new_game = true,
+            Some(Self::SETTINGS_BUTTON) => {
+                self.settings_layout =
+                    Self::settings_layout(ui.config, display::fullscreen_mode(ui.window));
+                self.showing_settings = true;
+            }
+            Some(id) if self.showing_settings => {
+                if self.change_setting(id, ui.config, ui.window) {
+                    // The changed button keeps the focus as the layout is rebuilt with its value
+                    self.settings_layout =
+                        Self::settings_layout(ui.config, display::fullscreen_mode(ui.window));
+                    self.settings_layout.set_focus(Some(id));
+
+                    if !ui.is_demo_playback {
+                        if let Err(err) = ui.config.write() {
+                            warn!("Unable to write config: {err}");
                         }
                     }
                 }
             }
+            Some(id) => {
+                if let Some(difficulty) = Difficulty::from_name(id) {
+                    self.difficulty = Some(difficulty);
+                }
+            }
+            None => (),
+        }
+
+        // A new game starts once a difficulty has been chosen and the level has loaded
+        if let Some(difficulty) = self
+            .difficulty
+            .filter(|_| self.play.as_ref().is_some_and(|play| play.is_done()))
+        {
+            let mut play = Box::new(self.play.take().unwrap().unwrap());
+            play.set_difficulty(difficulty);
+
+            *ui.cursor = None;
+
+            #[cfg(not(debug_assertions))]
+            ui.focus.set_grab(ui.window, true);
+
+            ui.set_cursor_position_center();
+
+            return Some(Box::new(Transition::new(
+                self,
+                play,
+                TransitionInfo::Fade,
+                Duration::from_secs_f32(0.25),
+            )));
         }
 
         Some(self)
@@ -1,13 +1,33 @@
 use {
-    super::Config,
-    kira::manager::{backend::cpal::CpalBackend, AudioManager},
+    self::{captions::Captions, layout::Navigation, ui_sound::UiSounds},
+    super::{
+        audio::ReverbMixer,
+        demo::DemoLevel,
+        focus::WindowFocus,
+        frame_stats::FrameStats,
+        input::{GamepadBuf, GamepadButton, MouseExtraBuf},
+        Config, Cvars,
+    },
+    glam::Vec2,
+    kira::{
+        manager::{backend::cpal::CpalBackend, AudioManager},
+        sound::static_sound::StaticSoundData,
+    },
     screen_13::prelude::*,
     screen_13_fx::TransitionPipeline,
+    std::borrow::Cow,
 };
 
 pub mod bench;
 pub mod boot;
+pub mod captions;
+pub mod crosshair;
+pub mod error;
+pub mod ui_sound;
 
+mod frame_graph;
+mod layout;
+mod level_select;
 mod loader;
 mod menu;
 mod play;
@@ -21,21 +41,63 @@ pub enum CursorStyle {
 }
 
 pub struct DrawContext<'a> {
+    pub captions: &'a Captions,
     pub dt: f32,
+
+    /// Fraction of a simulation step which has elapsed since the latest step, used to interpolate
+    /// between the previous and current simulated transforms.
+    pub fixed_alpha: f32,
+
+    pub frame_stats: &'a FrameStats,
     pub framebuffer_image: ImageLeaseNode,
+
+    /// Transparent image, at the height set by the UI scale, which is blended over the framebuffer
+    /// as it is presented; the HUD is drawn into it so that it stays sharp while dynamic resolution
+    /// lowers the framebuffer height. Its color is premultiplied by alpha.
+    pub hud_image: ImageLeaseNode,
+
     pub pool: &'a mut LazyPool,
+
+    /// How the swapchain presents frames, which the debug overlay shows.
+    pub present_mode: vk::PresentModeKHR,
+
     pub render_graph: &'a mut RenderGraph,
+
+    /// Height of the framebuffer relative to the height set by the UI scale; lowered by dynamic
+    /// resolution while frames are slow.
+    pub resolution_scale: f32,
+
     pub transition_pipeline: &'a mut TransitionPipeline,
 }
 
 pub trait Operation<T> {
     fn progress(&self) -> f32;
+
+    /// Describes what this operation is doing now, such as `Compiling pipelines`, so that long
+    /// pauses while loading may be attributed.
+    fn status(&self) -> Cow<str>;
+
     fn is_done(&self) -> bool;
     fn is_err(&self) -> bool;
     fn unwrap(self: Box<Self>) -> T;
+
+    /// Returns the error which stopped this operation; only called once [`Self::is_err`] is
+    /// `true`.
+    fn unwrap_err(self: Box<Self>) -> anyhow::Error;
 }
 
 pub trait Ui {
+    /// Returns `true` if demos record and replay input while this is the active state.
+    fn accepts_demo_input(&self) -> bool {
+        false
+    }
+
+    /// Returns the level which a demo recorded from this state starts in; playback loads it
+    /// directly.
+    fn demo_level(&self) -> Option<DemoLevel> {
+        None
+    }
+
     fn draw(&mut self, frame: DrawContext);
 
     fn update(self: Box<Self>, ui: UpdateContext) -> Option<Box<dyn Ui>>;
@@ -43,28 +105,147 @@ pub trait Ui {
 
 pub struct UpdateContext<'a> {
     pub audio: Option<&'a mut AudioManager<CpalBackend>>,
-    pub config: &'a Config,
+    pub captions: &'a mut Captions,
+    /// Settings, which states such as play may change and write.
+    pub config: &'a mut Config,
     pub cursor: &'a mut Option<CursorStyle>,
+
+    /// Named settings and debug toggles, which the console changes.
+    pub cvars: &'a mut Cvars,
+
     pub dt: f32,
     pub events: &'a [Event<'a, ()>],
+
+    /// Number of simulation steps, each
+    /// [`FixedTimestep::DT`](crate::timestep::FixedTimestep::DT) seconds long, which have elapsed
+    /// since the previous update; may be zero at high framerates.
+    pub fixed_steps: u32,
+
+    /// Whether input is used, which it is not while the window is in the background or waits to
+    /// be clicked; UI states grab the cursor through it.
+    pub focus: &'a mut WindowFocus,
+
+    pub frame_stats: &'a FrameStats,
     pub framebuffer_aspect_ratio: f32,
     pub framebuffer_height: u32,
     pub framebuffer_scale: f32,
     pub framebuffer_width: u32,
+    pub gamepad: &'a GamepadBuf,
+
+    /// Input is being replayed from a demo and so does not depend on window focus.
+    pub is_demo_playback: bool,
+
     pub keyboard: &'a KeyBuf,
     pub mouse: &'a MouseBuf,
+    pub mouse_extra: &'a MouseExtraBuf,
+
+    /// The mixer track which sounds in the level play through, if audio is enabled.
+    pub reverb: Option<&'a mut ReverbMixer>,
+
+    /// Widget interactions of the current frame, which are heard once the UI has updated.
+    pub ui_sounds: &'a mut UiSounds,
+
     pub window: &'a Window,
 }
 
 impl<'a> UpdateContext<'a> {
-    fn set_cursor_position_center(&self) -> (f32, f32) {
-        if !self.window.has_focus() {
-            return (0.0, 0.0);
+    /// Raw mouse motion counts treated as moving the cursor across the window, so that both
+    /// input paths feel alike at the same sensitivity.
+    const RAW_MOTION_COUNTS_PER_WIDTH: f32 = 1920.0;
+
+    /// Returns the menu navigation input of the current frame, from either the keyboard or a
+    /// gamepad.
+    fn navigation(&self) -> Option<Navigation> {
+        const INPUTS: [(VirtualKeyCode, GamepadButton, Navigation); 6] = [
+            (VirtualKeyCode::Up, GamepadButton::DPadUp, Navigation::Up),
+            (
+                VirtualKeyCode::Down,
+                GamepadButton::DPadDown,
+                Navigation::Down,
+            ),
+            (
+                VirtualKeyCode::Left,
+                GamepadButton::DPadLeft,
+                Navigation::Left,
+            ),
+            (
+                VirtualKeyCode::Right,
+                GamepadButton::DPadRight,
+                Navigation::Right,
+            ),
+            (
+                VirtualKeyCode::Return,
+                GamepadButton::South,
+                Navigation::Activate,
+            ),
+            (
+                VirtualKeyCode::Escape,
+                GamepadButton::East,
+                Navigation::Back,
+            ),
+        ];
+
+        INPUTS
+            .into_iter()
+            .find(|(key, button, _)| {
+                self.keyboard.is_pressed(key) || self.gamepad.is_pressed(*button)
+            })
+            .map(|(_, _, navigation)| navigation)
+    }
+
+    /// Plays the given sound, if audio is enabled, and queues the given caption key, if captions
+    /// are enabled.
+    fn play_sound(&mut self, sound: &StaticSoundData, caption: Option<&'static str>) {
+        if let Some(audio) = self.audio.as_mut() {
+            if let Err(err) = audio.play(sound.clone()) {
+                warn!("Unable to play sound: {err}");
+            }
+        }
+
+        if let Some(caption) = caption.filter(|_| self.config.captions) {
+            self.captions.push(caption);
         }
+    }
+
+    /// Plays the given sound of something within the level, using the reverb of the space around
+    /// the listener; see [`Self::play_sound`].
+    fn play_world_sound(&mut self, sound: &StaticSoundData, caption: Option<&'static str>) {
+        let sound_with_reverb = self.reverb.as_ref().map(|reverb| reverb.route(sound));
 
+        self.play_sound(sound_with_reverb.as_ref().unwrap_or(sound), caption);
+    }
+
+    /// Returns the mouse movement of the current frame, in window widths, and keeps the cursor
+    /// centered.
+    ///
+    /// Raw mouse motion is used when enabled and reported by the platform, otherwise movement is
+    /// measured from the cursor position after the previous warp to the center.
+    fn mouse_look_delta(&self) -> Vec2 {
+        let (x, y) = self.set_cursor_position_center();
+
+        if !self.is_demo_playback && !self.focus.is_active() {
+            return Vec2::ZERO;
+        }
+
+        if self.config.mouse_raw_input && self.mouse_extra.has_raw_motion() {
+            self.mouse_extra.raw_motion() / Self::RAW_MOTION_COUNTS_PER_WIDTH
+        } else {
+            Vec2::new(x, y)
+        }
+    }
+
+    fn set_cursor_position_center(&self) -> (f32, f32) {
         let size = self.window.inner_size();
-        let center = PhysicalPosition::new(size.width >> 1, size.height >> 1);
-        self.window.set_cursor_position(center).unwrap_or_default();
+
+        // Replayed cursor positions were recorded relative to the warped cursor already
+        if !self.is_demo_playback {
+            if !self.focus.is_active() {
+                return (0.0, 0.0);
+            }
+
+            let center = PhysicalPosition::new(size.width >> 1, size.height >> 1);
+            self.window.set_cursor_position(center).unwrap_or_default();
+        }
 
         let (x, y) = self.mouse.position();
 
@@ -1,246 +1,2486 @@
 use {
+    self::{
+        accessibility::AccessibilityPanel,
+        cheats::{Cheat, Cheats},
+        console::{Console, ConsoleCommand},
+        corpse::Corpse,
+        decals::Decals,
+        editor::{Editor, EditorRef},
+        flicker::Flicker,
+        floating_text::FloatingText,
+        objective_list::ObjectiveList,
+        projectile_fx::ProjectileFx,
+        toasts::Toasts,
+    },
     super::{
+        captions::Speaker,
+        crosshair::Crosshair,
+        error::ErrorScreen,
+        frame_graph::FrameGraph,
+        level_select::LevelSelect,
         loader::{IdOrKey, LoadInfo, LoadResult, Loader},
         DrawContext, Operation, Ui, UpdateContext,
     },
     crate::{
         art,
+        asset_key::{MaterialKey, SceneKey, SoundKey},
+        audio::{MusicIntensity, ReverbPreset, SfxBank, SoundPriority, SoundWorld},
+        demo::DemoLevel,
+        game::{
+            achievements::Achievements,
+            checkpoint::{Checkpoints, SaveGame},
+            difficulty::{Difficulty, DifficultyTable},
+            events::{GameEvent, GameEventBus},
+            game_rules::GameRules,
+            impact_table::{ImpactKind, ImpactTable},
+            inventory::{Inventory, PickupKind},
+            objectives::{ObjectiveInfo, Objectives},
+            physics::RigidBody,
+            profile::StatsProfile,
+            projectile::ProjectileKind,
+            quick_save::QuickSave,
+            weapons::{WeaponInfo, Weapons},
+            world::{EntityId, Player, PlayerInput, Transform, World, WorldEvent},
+            world_delta::{WorldChange, WorldDeltas},
+        },
+        input::{ExtraButton, MouseLook},
         level::{
-            nav_mesh::{MeshLocation, NavigationMesh},
+            ambient::AmbientEmitters,
+            collision::{CollisionMesh, RaycastHit},
+            entities::EntityKind,
+            nav_mesh::{MeshLocation, NavMeshDebug, NavigationMesh},
+            reverb::ReverbZones,
+            scene::{read_geometry, RefId, Scene},
+            surface::Surface,
+            triggers::{Trigger, TriggerEvent, TriggerEventKind, TriggerHooks, Triggers},
             Level,
         },
+        math::Ray,
+        mods::active_mod_names,
         render::{
-            camera::Camera,
-            model::{ModelBuffer, ModelBufferTechnique},
+            camera::{Camera, CameraEffects},
+            debug::DebugMode,
+            model::{
+                AmbientOcclusion, Material, Model, ModelBuffer, ModelBufferInfo,
+                ModelBufferTechnique, ModelInstance, ReflectionProbe, RenderLayers,
+                TextureFiltering,
+            },
+            overlay::Overlay,
+            primitives::PrimitiveBuffer,
         },
+        timestep::FixedTimestep,
     },
-    glam::{vec2, vec3, Mat4, Vec2, Vec3},
-    pak::scene::SceneBufGeometry,
+    anyhow::{anyhow, bail, Context},
+    glam::{uvec2, vec2, Quat, Vec2, Vec3, Vec4},
+    kira::sound::static_sound::StaticSoundData,
     screen_13::prelude::*,
     screen_13_fx::BitmapFont,
-    std::sync::Arc,
+    std::{
+        borrow::Cow,
+        collections::{BTreeMap, HashMap, HashSet},
+        mem::take,
+        sync::Arc,
+        thread::JoinHandle,
+    },
 };
 
-fn read_geometry(geom: &SceneBufGeometry) -> (Vec<u32>, Vec<Vec3>) {
-    let transform = Mat4::from_rotation_translation(geom.rotation(), geom.position());
-    let indices = geom.index_buf().as_u32();
-    let vertex_data = geom.vertex_data();
-    let vertex_count = vertex_data.len() / 12;
-    let mut vertices = Vec::with_capacity(vertex_count);
+mod accessibility;
+mod cheats;
+mod console;
+mod corpse;
+mod decals;
+mod editor;
+mod flicker;
+mod floating_text;
+mod objective_list;
+mod projectile_fx;
+mod toasts;
 
-    for idx in 0..vertex_count {
-        let vertex = &vertex_data[idx * 12..];
-        let x = f32::from_ne_bytes([vertex[0], vertex[1], vertex[2], vertex[3]]);
-        let y = f32::from_ne_bytes([vertex[4], vertex[5], vertex[6], vertex[7]]);
-        let z = f32::from_ne_bytes([vertex[8], vertex[9], vertex[10], vertex[11]]);
-        let vertex = transform.mul_vec4(vec3(x, y, z).extend(1.0)).truncate();
+/// Returns the sound bank event of an impact on the given surface.
+fn impact_event(surface: Surface, kind: ImpactKind) -> String {
+    format!("impact_{}_{}", surface.name(), kind.name())
+}
 
-        vertices.push(vertex);
-    }
+/// Returns the key of the art material with the given name, such as a decal listed by the impact
+/// table.
+fn material_key(key: &str) -> Option<MaterialKey> {
+    art::MATERIALS
+        .iter()
+        .copied()
+        .find(|material| material.as_str() == key)
+}
 
-    (indices, vertices)
+/// Returns the key of the art sound with the given name, such as one listed by the impact table.
+fn sound_key(key: &str) -> Option<SoundKey> {
+    art::SOUNDS
+        .iter()
+        .copied()
+        .find(|sound| sound.as_str() == key)
 }
 
 struct Content {
+    corpse_model: (Model, Material),
     dare_font: BitmapFont,
+
+    /// The material of each decal listed by the impact table, by its key.
+    decal_materials: HashMap<String, Material>,
+
+    decal_model: Model,
+    impacts: ImpactTable,
+    projectile_models: HashMap<ProjectileKind, (Model, Material)>,
+    sfx: SfxBank,
+    sounds: HashMap<SoundKey, StaticSoundData>,
+}
+
+/// The game state which trigger hooks may change.
+struct TriggerContext<'a, 'b> {
+    game_events: &'a mut GameEventBus,
+    level_completed: &'a mut bool,
+
+    /// Seconds of play since the level started.
+    level_secs: f32,
+
+    objective_infos: &'a [ObjectiveInfo],
+    objectives: &'a mut Objectives,
+
+    /// Names of the secrets found in this session, which are only announced once.
+    secrets_found: &'a mut HashSet<String>,
+
+    scene: SceneKey,
+    sounds: &'a HashMap<SoundKey, StaticSoundData>,
+    ui: &'a mut UpdateContext<'b>,
+}
+
+impl TriggerContext<'_, '_> {
+    /// Lists an objective on the HUD, announcing it unless it is already listed or completed.
+    fn add_objective(&mut self, id: &str) {
+        if self.objectives.add(id) {
+            self.game_events.publish(GameEvent::ObjectiveAdded {
+                id: id.to_owned(),
+                text: ObjectiveInfo::text(self.objective_infos, id).to_owned(),
+            });
+            self.play_objective_sound();
+        }
+    }
+
+    /// Completes an objective, announcing it unless it was already completed.
+    fn complete_objective(&mut self, id: &str) {
+        if self.objectives.complete(id) {
+            self.game_events.publish(GameEvent::ObjectiveCompleted {
+                id: id.to_owned(),
+                text: ObjectiveInfo::text(self.objective_infos, id).to_owned(),
+            });
+            self.play_objective_sound();
+        }
+    }
+
+    fn play_objective_sound(&mut self) {
+        self.ui
+            .play_world_sound(&self.sounds[&Play::OBJECTIVE_SOUND], None);
+    }
 }
 
 struct Load {
+    device: Arc<Device>,
+    difficulties: DifficultyTable,
+    graphics: Option<ModelBufferTechnique>,
+    impacts: ImpactTable,
     loader: Box<dyn Operation<LoadResult>>,
+
+    /// The objectives of the scene.
+    objective_infos: Vec<ObjectiveInfo>,
+
+    scene: SceneKey,
+    spawn: Option<String>,
+    view_model_loader: Box<dyn Operation<LoadResult>>,
+    world_deltas: WorldDeltas,
 }
 
 impl Operation<Play> for Load {
     fn progress(&self) -> f32 {
-        self.loader.progress()
+        (self.loader.progress() + self.view_model_loader.progress()) / 2.0
+    }
+
+    fn status(&self) -> Cow<str> {
+        if self.loader.is_done() {
+            self.view_model_loader.status()
+        } else {
+            self.loader.status()
+        }
     }
 
     fn is_done(&self) -> bool {
-        self.loader.is_done()
+        self.loader.is_done() && self.view_model_loader.is_done()
     }
 
     fn is_err(&self) -> bool {
-        self.loader.is_err()
+        self.loader.is_err() || self.view_model_loader.is_err()
     }
 
     fn unwrap(self: Box<Self>) -> Play {
         let mut loader = self.loader.unwrap();
         let mut model_buf = loader.model_buf.unwrap();
 
+        let mut sfx = SfxBank::default();
+
+        for weapon in Play::WEAPONS {
+            sfx.insert(
+                weapon.fire_sound.as_str(),
+                [loader.sounds[&weapon.fire_sound].clone()],
+            );
+        }
+
+        for surface in Surface::ALL {
+            sfx.insert(
+                surface.footstep_event(),
+                surface
+                    .footstep_sounds()
+                    .map(|sound| loader.sounds[&sound].clone()),
+            );
+
+            for kind in ImpactKind::ALL {
+                if let Some(effect) = self.impacts.effect(surface.name(), kind) {
+                    sfx.insert(
+                        impact_event(surface, kind),
+                        effect
+                            .sounds
+                            .iter()
+                            .filter_map(|key| sound_key(key))
+                            .map(|sound| loader.sounds[&sound].clone()),
+                    );
+                }
+            }
+        }
+
         let content = Content {
+            corpse_model: (
+                loader.models[&IdOrKey::Key(Corpse::MODEL)],
+                loader.materials[&IdOrKey::Key(Corpse::MATERIAL)],
+            ),
             dare_font: loader
                 .fonts
-                .remove(art::FONT_KENNEY_MINI_SQUARE_MONO)
+                .remove(&art::FONT_KENNEY_MINI_SQUARE_MONO)
                 .unwrap(),
+            decal_materials: self
+                .impacts
+                .effects()
+                .filter_map(|effect| effect.decal.as_ref())
+                .filter_map(|key| {
+                    let material = material_key(key)?;
+
+                    Some((key.clone(), loader.materials[&IdOrKey::Key(material)]))
+                })
+                .collect(),
+            decal_model: loader.models[&IdOrKey::Key(Decals::MODEL)],
+            impacts: self.impacts,
+            projectile_models: ProjectileKind::ALL
+                .into_iter()
+                .map(|kind| {
+                    let info = kind.info();
+
+                    (
+                        kind,
+                        (
+                            loader.models[&IdOrKey::Key(info.model)],
+                            loader.materials[&IdOrKey::Key(info.material)],
+                        ),
+                    )
+                })
+                .collect(),
+            sfx,
+            sounds: loader.sounds,
         };
 
-        let scene = loader.scenes.remove(art::SCENE_LEVEL_01).unwrap();
+        let (scene_layer, scene) = loader.scenes.remove(&self.scene).unwrap();
+        let scene = Scene::new(scene);
+        let mut editor_refs = HashMap::new();
+        let mut entity_refs = HashMap::new();
+        let mut flickering_materials = HashMap::new();
+        let mut model_instances = HashMap::new();
+        let mut named_instances = HashMap::new();
+        let mut parented_instances = vec![];
+        let mut world = World::default();
 
         for scene_ref in scene.refs() {
-            if let Some(model) = scene_ref.model().map(|id| loader.models[&IdOrKey::Id(id)]) {
+            let id = scene_ref.id().map(RefId::parse);
+            let model_instance = scene_ref.model().map(|model| {
                 let materials = scene_ref
                     .materials()
                     .iter()
                     .copied()
-                    .map(|id| loader.materials[&IdOrKey::Id(id)])
+                    .map(|id| loader.materials[&IdOrKey::Id(scene_layer, id)])
                     .collect::<Box<_>>();
+
                 model_buf.insert_model_instance(
-                    model,
+                    loader.models[&IdOrKey::Id(scene_layer, model)],
                     &materials,
                     scene_ref.position(),
                     scene_ref.rotation(),
+                )
+            });
+
+            if let Some((model_instance, id)) = model_instance.zip(scene_ref.id()) {
+                editor_refs.insert(
+                    model_instance,
+                    EditorRef::new(id, scene_ref.position(), scene_ref.rotation()),
                 );
             }
+
+            let transform = Transform {
+                position: scene_ref.position(),
+                rotation: scene_ref.rotation(),
+            };
+            let entity = if let Some((_, kind)) =
+                model_instance.zip(id.as_ref().and_then(EntityKind::from_id))
+            {
+                Some(world.spawn_mover(kind, transform))
+            } else if let Some((_, body)) =
+                model_instance.zip(id.as_ref().and_then(RigidBody::from_id))
+            {
+                let entity = world.spawn_body(body, transform);
+
+                if let Some(health) = id.as_ref().and_then(|id| id.property("health")) {
+                    world.set_prop_health(entity, health);
+                }
+
+                Some(entity)
+            } else {
+                // Pickups without a model are invisible, which level designers may use for secrets
+                id.as_ref()
+                    .and_then(PickupKind::from_id)
+                    .map(|kind| world.spawn_pickup(kind, transform))
+            };
+
+            if let Some((entity, id)) = entity.zip(id.as_ref()) {
+                entity_refs.insert(entity, id.name.to_owned());
+            }
+
+            if let Some((entity, model_instance)) = entity.zip(model_instance) {
+                model_instances.insert(entity, model_instance);
+            }
+
+            if let Some(rate) = id.as_ref().and_then(|id| id.property::<f32>("flicker")) {
+                for &material in scene_ref.materials() {
+                    let material = loader.materials[&IdOrKey::Id(scene_layer, material)];
+
+                    if material.is_emissive() {
+                        flickering_materials.insert(material, rate);
+                    }
+                }
+            }
+
+            // Refs without an entity, such as a keycard lying on a moving platform, may follow the
+            // ref named by their `parent` property
+            if let Some((model_instance, id)) = model_instance.zip(id.as_ref()) {
+                let transform = (model_instance, scene_ref.position(), scene_ref.rotation());
+                named_instances.insert(id.name.to_owned(), transform);
+
+                if let Some(parent) = id.property::<String>("parent").filter(|_| entity.is_none()) {
+                    parented_instances.push((transform, parent));
+                }
+            }
+        }
+
+        for ((child, position, rotation), parent) in parented_instances {
+            let Some(&(parent_instance, parent_position, parent_rotation)) =
+                named_instances.get(parent.as_str())
+            else {
+                warn!("Unknown parent {parent}");
+                continue;
+            };
+
+            let inverse_rotation = parent_rotation.inverse();
+
+            if let Err(err) = model_buf.attach_model_instance(
+                child,
+                parent_instance,
+                inverse_rotation * (position - parent_position),
+                inverse_rotation * rotation,
+            ) {
+                warn!("Unable to attach to {parent}: {err}");
+            }
+        }
+
+        let flickers = flickering_materials
+            .into_iter()
+            .enumerate()
+            .map(|(seed, (material, rate))| Flicker::new(&model_buf, material, rate, seed as _))
+            .collect();
+
+        for (scene_ref, id) in scene.refs_prefixed(Play::REFLECTION_PROBE_PREFIX) {
+            model_buf.insert_reflection_probe(
+                scene_ref.position(),
+                id.property("radius")
+                    .unwrap_or(ReflectionProbe::DEFAULT_RADIUS),
+            );
+        }
+
+        for (scene_ref, id) in scene.refs_prefixed(Play::SECURITY_CAMERA_PREFIX) {
+            let Some(&material) = scene_ref.materials().first() else {
+                warn!("Security camera {} has no material to show it on", id.name);
+                continue;
+            };
+
+            // Cameras without a positive rate are drawn every frame
+            let fps = id
+                .property::<f32>("fps")
+                .unwrap_or(Play::SECURITY_CAMERA_FPS);
+            let interval = if fps > 0.0 { 1.0 / fps } else { 0.0 };
+
+            if let Err(err) = model_buf.insert_render_target(
+                &self.device,
+                loader.materials[&IdOrKey::Id(scene_layer, material)],
+                Camera::from_transform(
+                    scene_ref.position(),
+                    scene_ref.rotation(),
+                    id.property("fov_y").unwrap_or(Play::SECURITY_CAMERA_FOV_Y),
+                ),
+                id.property("width").unwrap_or(Play::SECURITY_CAMERA_WIDTH),
+                id.property("height")
+                    .unwrap_or(Play::SECURITY_CAMERA_HEIGHT),
+                interval,
+            ) {
+                warn!("Unable to create security camera {}: {err}", id.name);
+            }
         }
 
-        let spawn = scene
-            .refs()
-            .find(|scene_ref| scene_ref.id() == Some("Spawn"))
-            .unwrap();
+        model_buf.set_sky(Level::read_sky(&scene));
+
+        let checkpoints = Checkpoints::from_scene(&scene);
+        let spawn = self
+            .spawn
+            .as_deref()
+            .and_then(|name| {
+                let spawn = scene.find_ref(name);
+
+                if spawn.is_none() {
+                    warn!("Unknown spawn {name}");
+                }
+
+                spawn
+            })
+            .unwrap_or_else(|| scene.find_ref(Play::SPAWN).unwrap());
 
         let nav_mesh = {
-            let walkable_region = scene
-                .geometries()
-                .find(|geom| geom.id() == Some("Walkable Region"))
-                .unwrap();
+            let walkable_region = scene.geometry("Walkable Region").unwrap();
             let (indices, vertices) = read_geometry(&walkable_region);
 
-            NavigationMesh::new(&indices, &vertices)
+            let mut nav_mesh = NavigationMesh::new(&indices, &vertices);
+            nav_mesh.add_scene_links(&scene);
+
+            nav_mesh
         };
-        let current_location = nav_mesh.locate(spawn.position());
+        let spawn_location = nav_mesh.locate(spawn.position());
+        let player = world.spawn_player(spawn_location);
+        let nav_mesh_debug = NavMeshDebug::new(&self.device, &nav_mesh).unwrap();
+        let overlay = Overlay::new(&self.device).unwrap();
+        let primitives = PrimitiveBuffer::new(&self.device).unwrap();
 
-        let camera = {
-            let position = current_location.position() + Play::CAMERA_OFFSET;
-            Camera {
-                aspect_ratio: 0.0,
-                fov_y: 45.0,
-                pitch: 0.0,
-                yaw: 0.0,
-                position,
-            }
+        let camera = Camera {
+            aspect_ratio: 0.0,
+            fov_y: 45.0,
+            pitch: 0.0,
+            yaw: 0.0,
+            position: spawn_location.position() + Player::EYE_OFFSET,
+        };
+
+        let collision = CollisionMesh::from_scene(&scene);
+        let surfaces = CollisionMesh::surfaces_from_scene(&scene);
+        let ambient_emitters = AmbientEmitters::from_scene(&scene);
+        let reverb_zones = ReverbZones::from_scene(&scene);
+        let triggers = Triggers::from_scene(&scene);
+        let level = Level {
+            ambient_emitters,
+            collision,
+            nav_mesh,
+            reverb_zones,
+            scene,
+            surfaces,
+            triggers,
+        };
+
+        let weapons = Weapons::new(Play::WEAPONS);
+
+        let view_model_loader = self.view_model_loader.unwrap();
+        let mut view_model_buf = view_model_loader.model_buf.unwrap();
+        let view_model_materials = Play::WEAPONS
+            .iter()
+            .map(|weapon| view_model_loader.materials[&IdOrKey::Key(weapon.material)])
+            .collect::<Box<_>>();
+        let view_model = view_model_buf.insert_model_instance(
+            view_model_loader.models[&IdOrKey::Key(weapons.current().model)],
+            &view_model_materials[0..1],
+            weapons.view_model_position(),
+            Quat::IDENTITY,
+        );
+        view_model_buf.set_layers(RenderLayers::VIEW_MODEL);
+        view_model_buf.set_model_instance_layers(view_model, RenderLayers::VIEW_MODEL);
+        let view_model_camera = Camera {
+            aspect_ratio: 0.0,
+            fov_y: Play::VIEW_MODEL_FOV_Y,
+            pitch: 0.0,
+            yaw: 0.0,
+            position: Vec3::ZERO,
         };
 
-        let level = Level { nav_mesh };
+        let achievements = Achievements::read().unwrap_or_else(|err| {
+            warn!("Unable to read achievements: {err:#}");
+
+            Default::default()
+        });
+        let profile = StatsProfile::read().unwrap_or_else(|err| {
+            warn!("Unable to read profile: {err:#}");
 
-        Play {
+            Default::default()
+        });
+
+        let objectives = Objectives::new(&self.objective_infos);
+        let mut play = Play {
+            accessibility: Default::default(),
+            achievements,
             camera,
+            camera_effects: Default::default(),
+            cheats: Default::default(),
+            checkpoints,
+            console: Default::default(),
             content,
-            current_location,
+            corpse: None,
+            crosshair: Default::default(),
+            decals: Default::default(),
+            device: self.device,
+            editor_refs,
+            entity_refs,
+            err: None,
+            fire_latched: false,
+            flickers,
+            floating_text: Default::default(),
+            footstep_distance: 0.0,
+            frame_graph: Default::default(),
+            free_fly: None,
+            game_events: Default::default(),
+            graphics: self.graphics,
+            highlighted: None,
             level,
+            level_completed: false,
+            level_secs: 0.0,
             model_buf,
+            model_instances,
+            mouse_look: Default::default(),
+            music_intensity: Default::default(),
+            nav_mesh_debug,
+            nav_mesh_visible: false,
+            objective_infos: self.objective_infos,
+            objective_list: Default::default(),
+            objectives,
+            overlay,
+            paused: false,
+            player,
+            primitives,
+            profile,
+            projectile_fx: Default::default(),
+            projectile_instances: Default::default(),
+            quick_load: None,
+            quick_save: None,
+            rules: GameRules::new(self.difficulties, player),
+            save_game: None,
+            scene: self.scene,
+            secrets_found: Default::default(),
+            sound_world: None,
+            spawn: self.spawn.clone(),
+            spawn_location,
+            toasts: Default::default(),
+            trigger_events: Default::default(),
+            view_model,
+            view_model_buf,
+            view_model_camera,
+            view_model_materials,
+            weapons,
+            wheel_scroll: 0.0,
+            world,
+            world_deltas: self.world_deltas,
+            world_events: Default::default(),
+        };
+        play.apply_world_delta();
+
+        play
+    }
+
+    fn unwrap_err(self: Box<Self>) -> anyhow::Error {
+        if self.loader.is_err() {
+            self.loader.unwrap_err()
+        } else {
+            self.view_model_loader.unwrap_err()
         }
     }
 }
 
 pub struct Play {
+    accessibility: AccessibilityPanel,
+    achievements: Achievements,
     camera: Camera,
+    camera_effects: CameraEffects,
+    cheats: Cheats,
+    checkpoints: Checkpoints,
+    console: Console,
     content: Content,
-    current_location: MeshLocation,
+
+    /// The body of the local player while they are dead, which the camera follows.
+    corpse: Option<Corpse>,
+
+    crosshair: Crosshair,
+    decals: Decals,
+    device: Arc<Device>,
+
+    /// Scene refs with ids, whose model instances the level editor may move.
+    editor_refs: HashMap<ModelInstance, EditorRef>,
+
+    /// Name of the scene ref each world entity was spawned from, which changes to the level are
+    /// recorded by.
+    entity_refs: HashMap<EntityId, String>,
+
+    /// An error from drawing, which is shown by the next update.
+    err: Option<anyhow::Error>,
+
+    /// The fire button was pressed since the previous simulation step, so a click which is
+    /// released before the next step still fires.
+    fire_latched: bool,
+
+    /// Emissive materials of scene refs with a `flicker` property.
+    flickers: Vec<Flicker>,
+
+    floating_text: FloatingText,
+
+    /// Distance walked since the previous footstep.
+    footstep_distance: f32,
+
+    frame_graph: FrameGraph,
+
+    /// Position of the camera while it flies freely, detached from the player, for inspecting the
+    /// level.
+    free_fly: Option<Vec3>,
+
+    /// Gameplay events published since the previous update, which listeners such as achievements
+    /// receive at the end of each update.
+    game_events: GameEventBus,
+
+    /// The graphics setting the model buffer technique was chosen by, which is switched when the
+    /// setting is changed in the settings menu while the level is loaded.
+    graphics: Option<ModelBufferTechnique>,
+
+    /// The pickup model instance outlined because the camera looks at it.
+    highlighted: Option<ModelInstance>,
+
     level: Level,
+    level_completed: bool,
+
+    /// Seconds of play since the level started, which do not count while paused.
+    level_secs: f32,
+
     model_buf: ModelBuffer,
+
+    /// The model instance drawn for each world entity which has one.
+    model_instances: HashMap<EntityId, ModelInstance>,
+
+    mouse_look: MouseLook,
+    music_intensity: MusicIntensity,
+    nav_mesh_debug: NavMeshDebug,
+    nav_mesh_visible: bool,
+
+    /// The objectives of the level, which triggers add and complete by id.
+    objective_infos: Vec<ObjectiveInfo>,
+
+    objective_list: ObjectiveList,
+
+    /// Progress through the objectives of the level, which is written into saves.
+    objectives: Objectives,
+
+    /// The HUD, which is recorded on another thread while the world is recorded.
+    overlay: Overlay,
+
+    /// The simulation and input stop while the window is in the background, and until it is
+    /// clicked again.
+    paused: bool,
+
+    player: EntityId,
+    primitives: PrimitiveBuffer,
+    profile: StatsProfile,
+    projectile_fx: ProjectileFx,
+
+    /// The model instance drawn for each projectile in flight, which are inserted and removed as
+    /// projectiles are fired and detonate.
+    projectile_instances: HashMap<EntityId, ModelInstance>,
+
+    /// Reading of the quick save slot, which replaces the world once finished.
+    quick_load: Option<JoinHandle<anyhow::Result<Option<QuickSave>>>>,
+
+    /// Writing of the quick save slot; further quick saves wait until it has finished.
+    quick_save: Option<JoinHandle<anyhow::Result<()>>>,
+
+    /// The difficulty and the gameplay parameters it scales, which the simulation queries.
+    rules: GameRules,
+
+    /// The game as it was at the latest checkpoint, which is restored when the player dies.
+    save_game: Option<SaveGame>,
+
+    scene: SceneKey,
+    secrets_found: HashSet<String>,
+
+    /// Ambient sounds of the level, which start on the first update because audio is not
+    /// available while loading.
+    sound_world: Option<SoundWorld>,
+
+    /// Name of the scene ref the player started at, if not the default spawn point.
+    spawn: Option<String>,
+
+    spawn_location: MeshLocation,
+    toasts: Toasts,
+    trigger_events: Vec<TriggerEvent>,
+    view_model: ModelInstance,
+    view_model_buf: ModelBuffer,
+    view_model_camera: Camera,
+    view_model_materials: Box<[Material]>,
+    weapons: Weapons,
+
+    /// Scroll wheel movement, in lines, not yet used to cycle weapons; touchpads scroll by
+    /// fractions of a line per frame.
+    wheel_scroll: f32,
+
+    /// The simulated state of the level, which is presented by this state.
+    world: World,
+
+    /// Changes made to each level of the game in progress, which are written into autosaves.
+    world_deltas: WorldDeltas,
+
+    world_events: Vec<WorldEvent>,
 }
 
 impl Play {
-    const CAMERA_OFFSET: Vec3 = vec3(0.0, 1.7, 0.0);
+    /// Camera shake from a detonation, which fades out with distance as splash damage does.
+    const EXPLOSION_SHAKE_SECS: f32 = 0.5;
+    const EXPLOSION_SHAKE_STRENGTH: f32 = 0.6;
+
+    /// Camera shake each time a weapon fires.
+    const FIRE_SHAKE_SECS: f32 = 0.15;
+    const FIRE_SHAKE_STRENGTH: f32 = 0.1;
+
+    /// Distance walked between footsteps.
+    const FOOTSTEP_STRIDE: f32 = 2.0;
+
+    /// Seconds the notification of a reached checkpoint is shown.
+    const CHECKPOINT_NOTIFICATION_SECS: f32 = 2.0;
 
+    const PICKUP_SOUND: SoundKey = art::SOUND_DIGITAL_THREE_TONE_1_OGG;
+
+    /// Free-fly camera speed, in meters per second, which is faster while holding shift and
+    /// slower while holding alt.
+    const FREE_FLY_SPEED: f32 = 8.0;
+    const FREE_FLY_SPEED_FAST: f32 = 32.0;
+    const FREE_FLY_SPEED_SLOW: f32 = 2.0;
+
+    /// Outline of the pickup the camera looks at, within a distance in meters.
+    const HIGHLIGHT_COLOR: Vec4 = Vec4::new(1.0, 0.85, 0.3, 0.9);
+    const HIGHLIGHT_DISTANCE: f32 = 4.0;
+
+    /// Meters above the highlighted pickup which its name is shown.
+    const HIGHLIGHT_LABEL_HEIGHT: f32 = 0.5;
+
+    /// Played when an objective is added or completed.
+    const OBJECTIVE_SOUND: SoundKey = art::SOUND_DIGITAL_THREE_TONE_1_OGG;
+
+    /// Seconds the notification of each collected pickup is shown.
+    const PICKUP_NOTIFICATION_SECS: f32 = 2.0;
+
+    /// Seconds the notification of each quick save or quick load is shown.
+    const QUICK_SAVE_NOTIFICATION_SECS: f32 = 2.0;
+
+    /// Prefix of scene refs which place reflection probes, such as `Probe_hall(radius=20)`.
+    const REFLECTION_PROBE_PREFIX: &str = "Probe";
+
+    /// The scene played from the menu.
+    pub const DEFAULT_SCENE: SceneKey = art::SCENE_LEVEL_01;
+
+    /// Prefix of scene refs which draw what they see onto the first of their materials, such as
+    /// `SecurityCamera_lobby(fps=10, fov_y=70, width=320, height=240)` for the screen of a
+    /// monitor.
+    const SECURITY_CAMERA_PREFIX: &str = "SecurityCamera";
+
+    const SECURITY_CAMERA_FOV_Y: f32 = 60.0;
+
+    /// Security cameras are drawn less often than the view, which also makes them look the part.
+    const SECURITY_CAMERA_FPS: f32 = 15.0;
+
+    const SECURITY_CAMERA_HEIGHT: u32 = 240;
+    const SECURITY_CAMERA_WIDTH: u32 = 320;
+
+    /// Name of the scene ref where play starts, unless another is chosen by the level select
+    /// screen.
+    pub const SPAWN: &str = "Spawn";
+
+    const VIEW_MODEL_FOV_Y: f32 = 55.0;
+    const WEAPONS: [WeaponInfo; 4] = [
+        WeaponInfo::LASER,
+        WeaponInfo::SCATTER_LASER,
+        WeaponInfo::ROCKET_LAUNCHER,
+        WeaponInfo::GRENADE_LAUNCHER,
+    ];
+
+    /// Returns the model instance under the crosshair while a debug mode is shown, as of the
+    /// previous frame.
+    pub fn hovered_instance(&self) -> Option<ModelInstance> {
+        self.model_buf.hovered_instance()
+    }
+
+    /// Loads a scene to play, starting at the scene ref named `spawn`, such as a checkpoint, or at
+    /// [`Self::SPAWN`]; the changes which `world_deltas` records for the scene are applied to it.
     pub fn load(
         device: &Arc<Device>,
+        scene: SceneKey,
+        spawn: Option<String>,
+        world_deltas: WorldDeltas,
         graphics: Option<ModelBufferTechnique>,
+        texture_filtering: TextureFiltering,
+        ambient_occlusion: AmbientOcclusion,
     ) -> anyhow::Result<impl Operation<Self>> {
+        let impacts = art::read_impact_table();
+        let sounds = [art::SOUND_DIGITAL_THREE_TONE_1_OGG]
+            .into_iter()
+            .chain(Surface::ALL.into_iter().flat_map(Surface::footstep_sounds))
+            .chain(AmbientEmitters::sounds())
+            .chain(
+                impacts
+                    .effects()
+                    .flat_map(|effect| &effect.sounds)
+                    .filter_map(|key| {
+                        let sound = sound_key(key);
+
+                        if sound.is_none() {
+                            warn!("Unknown impact sound {key}");
+                        }
+
+                        sound
+                    }),
+            )
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Box<_>>();
+        let projectile_materials = ProjectileKind::ALL
+            .map(|kind| kind.info().material)
+            .into_iter()
+            .chain([Corpse::MATERIAL])
+            .chain(
+                impacts
+                    .effects()
+                    .filter_map(|effect| effect.decal.as_ref())
+                    .filter_map(|key| {
+                        let material = material_key(key);
+
+                        if material.is_none() {
+                            warn!("Unknown impact decal {key}");
+                        }
+
+                        material
+                    }),
+            )
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Box<_>>();
+        let projectile_models = ProjectileKind::ALL
+            .map(|kind| kind.info().model)
+            .into_iter()
+            .chain([Corpse::MODEL, Decals::MODEL])
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Box<_>>();
         let loader = Box::new(Loader::spawn_threads(
             device,
             graphics,
+            texture_filtering,
+            ambient_occlusion,
             LoadInfo::default()
                 .fonts(&[art::FONT_KENNEY_MINI_SQUARE_MONO])
-                .scenes(&[art::SCENE_LEVEL_01]),
+                .materials(&projectile_materials)
+                .models(&projectile_models)
+                .scenes(&[scene])
+                .sounds(&sounds),
+        )?);
+
+        // The first-person weapon is drawn by a separate raster-only model buffer so that it may
+        // use its own depth range and field of view
+        let view_model_loader = Box::new(Loader::spawn_threads_model_buf(
+            device,
+            ModelBufferInfo::new()
+                .technique(ModelBufferTechnique::Raster)
+                .overlay(true)
+                .texture_filtering(texture_filtering)
+                .geometry_capacity(1_000_000)
+                .material_capacity(Self::WEAPONS.len() as _)
+                .mesh_capacity(64)
+                .model_capacity(Self::WEAPONS.len() as _)
+                .reflection_probe_capacity(0)
+                .build(),
+            LoadInfo::default()
+                .materials(&Self::WEAPONS.map(|weapon| weapon.material))
+                .models(&[WeaponInfo::LASER.model]),
         )?);
 
-        Ok(Load { loader })
+        Ok(Load {
+            device: Arc::clone(device),
+            difficulties: art::read_difficulty_table(),
+            graphics,
+            impacts,
+            loader,
+            objective_infos: art::read_objective_table().level(scene.as_str()).to_vec(),
+            scene,
+            spawn,
+            view_model_loader,
+            world_deltas,
+        })
+    }
+
+    /// Sets the difficulty of a new game, which the level may have been loaded before choosing.
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        info!("Playing on {} difficulty", difficulty.name());
+
+        self.rules.set_difficulty(difficulty);
+    }
+
+    fn update_ambience(&mut self, ui: &mut UpdateContext) {
+        if self.sound_world.is_none() {
+            if ui.audio.is_some() {
+                self.sound_world = Some(SoundWorld::spawn(
+                    &self.level.ambient_emitters,
+                    &self.content.sounds,
+                    ui.reverb.as_deref(),
+                ));
+            }
+        }
+
+        if let Some((sound_world, audio)) = self.sound_world.as_mut().zip(ui.audio.as_deref_mut()) {
+            let right = Quat::from_rotation_y(self.camera.yaw.to_radians()) * Vec3::X;
+
+            sound_world.update(audio, self.camera.position, right, ui.dt);
+        }
     }
 
-    fn update_camera(&mut self, ui: UpdateContext) {
-        let (yaw_delta, pitch_delta) = ui.set_cursor_position_center();
+    /// Turns the camera using the mouse, which happens every frame rather than each simulation
+    /// step so that looking around is as responsive as the framerate allows.
+    fn update_camera(&mut self, ui: &UpdateContext) {
+        let look_delta = self.mouse_look.update(
+            ui.mouse_look_delta(),
+            ui.dt,
+            ui.config.mouse_smoothing,
+            ui.config.mouse_acceleration,
+        );
 
-        self.camera.yaw -= yaw_delta * ui.config.mouse_sensitivity;
-        self.camera.pitch -= pitch_delta * ui.config.mouse_sensitivity;
+        self.camera.yaw -= look_delta.x * ui.config.mouse_sensitivity;
+        self.camera.pitch -= look_delta.y * ui.config.mouse_sensitivity;
 
         self.camera.yaw %= 360.0;
         self.camera.pitch = self.camera.pitch.clamp(-80.0, 80.0);
+    }
+
+    /// Moves the free-fly camera, if enabled, the same way as the camera of the editor.
+    fn update_free_fly(&mut self, ui: &UpdateContext) {
+        let Some(position) = &mut self.free_fly else {
+            return;
+        };
+
+        let rotation = Quat::from_rotation_y(self.camera.yaw.to_radians())
+            * Quat::from_rotation_x(self.camera.pitch.to_radians());
+        let mut direction = Vec3::ZERO;
+
+        for (key, key_direction) in [
+            (VirtualKeyCode::W, rotation * -Vec3::Z),
+            (VirtualKeyCode::S, rotation * Vec3::Z),
+            (VirtualKeyCode::D, rotation * Vec3::X),
+            (VirtualKeyCode::A, rotation * -Vec3::X),
+            (VirtualKeyCode::E, Vec3::Y),
+            (VirtualKeyCode::Q, -Vec3::Y),
+        ] {
+            if ui.keyboard.is_down(key) {
+                direction += key_direction;
+            }
+        }
+
+        let speed = if ui.keyboard.is_down(VirtualKeyCode::LShift) {
+            Self::FREE_FLY_SPEED_FAST
+        } else if ui.keyboard.is_down(VirtualKeyCode::LAlt) {
+            Self::FREE_FLY_SPEED_SLOW
+        } else {
+            Self::FREE_FLY_SPEED
+        };
 
-        let mut direction = Vec2::ZERO;
+        *position += direction.normalize_or_zero() * speed * ui.dt;
+        self.camera.position = *position;
+    }
+
+    /// Returns the player controlled by this state.
+    fn local_player(&self) -> &Player {
+        self.world.player(self.player).unwrap()
+    }
+
+    /// Returns the input of the local player for the next simulation step.
+    fn player_input(&self, ui: &UpdateContext) -> PlayerInput {
+        let mut movement = Vec2::ZERO;
+
+        // The player stands still while the camera flies freely or follows their corpse
+        if self.free_fly.is_some() || self.corpse.is_some() {
+            return PlayerInput {
+                pitch: self.camera.pitch,
+                yaw: self.camera.yaw,
+                ..Default::default()
+            };
+        }
 
         if ui.keyboard.is_down(VirtualKeyCode::W) {
-            direction.y += 1.0;
+            movement.y += 1.0;
         }
 
         if ui.keyboard.is_down(VirtualKeyCode::A) {
-            direction.x += 1.0;
+            movement.x += 1.0;
         }
 
         if ui.keyboard.is_down(VirtualKeyCode::S) {
-            direction.y -= 1.0;
+            movement.y -= 1.0;
         }
 
         if ui.keyboard.is_down(VirtualKeyCode::D) {
-            direction.x -= 1.0;
+            movement.x -= 1.0;
         }
 
-        if ui.keyboard.is_down(VirtualKeyCode::LShift) {
-            direction.y *= 1.5;
+        PlayerInput {
+            movement,
+            pitch: self.camera.pitch,
+            sprint: ui.keyboard.is_down(VirtualKeyCode::LShift),
+            yaw: self.camera.yaw,
         }
+    }
 
-        let yaw = self.camera.yaw - 90f32;
-        let yaw = yaw.to_radians();
-        let yaw_sin = yaw.sin();
-        let yaw_cos = yaw.cos();
-        direction = vec2(
-            yaw_sin * direction.x - yaw_cos * direction.y,
-            yaw_cos * direction.x + yaw_sin * direction.y,
-        );
+    /// Returns the hooks which run when the player enters or exits a trigger with the given name.
+    fn trigger_hooks<'a, 'b>() -> TriggerHooks<TriggerContext<'a, 'b>> {
+        fn alarm(context: &mut TriggerContext, _: &Trigger, kind: TriggerEventKind) {
+            if kind == TriggerEventKind::Enter {
+                context
+                    .ui
+                    .play_world_sound(&context.sounds[&art::SOUND_DIGITAL_THREE_TONE_1_OGG], None);
+            }
+        }
+
+        fn exit(context: &mut TriggerContext, _: &Trigger, kind: TriggerEventKind) {
+            if kind == TriggerEventKind::Enter && !*context.level_completed {
+                *context.level_completed = true;
+
+                // Levels without an exit objective have nothing to announce
+                if context
+                    .objective_infos
+                    .iter()
+                    .any(|info| info.id == Objectives::EXIT)
+                {
+                    context.complete_objective(Objectives::EXIT);
+                }
+
+                context.game_events.publish(GameEvent::LevelCompleted {
+                    level: context.scene.as_str().to_owned(),
+                    secs: context.level_secs,
+                });
+            }
+        }
 
-        direction *= ui.dt * 4.0;
+        // Each objective is named with a property, such as `Trigger_objective(add=find_key)` or
+        // `Trigger_objective(complete=find_key)`
+        fn objective(context: &mut TriggerContext, trigger: &Trigger, kind: TriggerEventKind) {
+            if kind != TriggerEventKind::Enter {
+                return;
+            }
+
+            if let Some(id) = trigger.id().property::<String>("add") {
+                context.add_objective(&id);
+            }
+
+            if let Some(id) = trigger.id().property::<String>("complete") {
+                context.complete_objective(&id);
+            }
+        }
+
+        // Each secret is named with a property, such as `Trigger_secret(name=vault)`
+        fn secret(context: &mut TriggerContext, trigger: &Trigger, kind: TriggerEventKind) {
+            let secret = trigger.id().property("name").unwrap_or_default();
+
+            if kind == TriggerEventKind::Enter && context.secrets_found.insert(secret.clone()) {
+                context
+                    .game_events
+                    .publish(GameEvent::SecretFound { secret });
+            }
+        }
 
-        self.current_location = self.level.nav_mesh.walk(self.current_location, direction);
-        self.camera.position = self.current_location.position() + Self::CAMERA_OFFSET;
+        let mut res = TriggerHooks::default();
+        res.register("Trigger_alarm", alarm)
+            .register("Trigger_exit", exit)
+            .register("Trigger_objective", objective)
+            .register("Trigger_secret", secret);
+        res
     }
-}
 
-impl Ui for Play {
-    fn draw(&mut self, frame: DrawContext) {
-        let framebuffer_info = frame.render_graph.node_info(frame.framebuffer_image);
+    /// Replaces the world with a quick save and shows or hides the model instance of each entity
+    /// to match it.
+    fn load_quick_save(&mut self, quick_save: QuickSave) -> anyhow::Result<()> {
+        let Some(player) = quick_save.world.player(self.player) else {
+            bail!("Quick save does not have a player");
+        };
 
-        self.camera.aspect_ratio = framebuffer_info.width as f32 / framebuffer_info.height as f32;
+        self.camera.pitch = quick_save.pitch;
+        self.camera.yaw = quick_save.yaw;
+        self.camera.position = player.eye_position();
+        self.rules.set_difficulty(quick_save.difficulty);
+        self.restore_objectives(quick_save.objectives);
+        self.footstep_distance = 0.0;
 
-        // TODO: Remove before flight
-        frame
-            .render_graph
-            .clear_color_image_value(frame.framebuffer_image, [0xFF, 0x00, 0xFF, 0xFF]);
+        if let Some(corpse) = self.corpse.take() {
+            corpse.remove(&mut self.world, &mut self.model_buf);
+        }
 
-        self.model_buf
-            .record(
-                frame.render_graph,
-                frame.framebuffer_image,
-                &mut self.camera,
-                // &self.sun,
-            )
-            .unwrap();
+        self.world = quick_save.world;
+        self.world_events.clear();
+        self.sync_projectile_instances();
 
-        self.content.dare_font.print(
-            frame.render_graph,
-            frame.framebuffer_image,
-            0.0,
-            0.0,
-            [0xff, 0xff, 0xff],
-            format!("FPS: {}", (1.0 / frame.dt).round()),
-        );
+        for (&entity, &model_instance) in &self.model_instances {
+            self.model_buf
+                .set_model_instance_visible(model_instance, self.world.contains(entity));
+        }
+
+        if let Some(index) = Self::WEAPONS
+            .iter()
+            .position(|weapon| weapon.id == quick_save.weapon)
+        {
+            self.weapons.select(index);
+        }
+
+        Ok(())
     }
 
-    fn update(mut self: Box<Self>, ui: UpdateContext) -> Option<Box<dyn Ui>> {
-        #[cfg(debug_assertions)]
-        if ui.keyboard.is_pressed(&VirtualKeyCode::Escape) {
-            return None;
+    /// Starts a quick save or quick load when their bound keys (F5 and F9 by default) are pressed,
+    /// and shows the result of each once it has finished on its background thread.
+    fn update_quick_save(&mut self, ui: &mut UpdateContext) {
+        if ui.keyboard.is_pressed(&ui.config.quick_save_key.0) && self.quick_save.is_none() {
+            self.quick_save = Some(
+                QuickSave {
+                    difficulty: self.rules.difficulty(),
+                    objectives: self.objectives.clone(),
+                    pitch: self.camera.pitch,
+                    scene: self.scene.as_str().to_owned(),
+                    weapon: self.weapons.current().id.to_owned(),
+                    world: self.world.clone(),
+                    yaw: self.camera.yaw,
+                }
+                .write(),
+            );
+        }
+
+        if ui.keyboard.is_pressed(&ui.config.quick_load_key.0) && self.quick_load.is_none() {
+            self.quick_load = Some(QuickSave::read());
+        }
+
+        let mut notify = |text: &'static str| {
+            ui.captions
+                .push_text(Speaker::Narrator, text, Self::QUICK_SAVE_NOTIFICATION_SECS);
+        };
+
+        if self
+            .quick_save
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+        {
+            match self
+                .quick_save
+                .take()
+                .unwrap()
+                .join()
+                .unwrap_or_else(|_| Err(anyhow!("Quick save panicked")))
+            {
+                Ok(()) => notify("Quick saved"),
+                Err(err) => {
+                    warn!("Unable to quick save: {err:#}");
+
+                    notify("Quick save failed");
+                }
+            }
+        }
+
+        if self
+            .quick_load
+            .as_ref()
+            .is_some_and(JoinHandle::is_finished)
+        {
+            match self
+                .quick_load
+                .take()
+                .unwrap()
+                .join()
+                .unwrap_or_else(|_| Err(anyhow!("Quick load panicked")))
+                .and_then(|quick_save| match quick_save {
+                    // The world of another scene does not fit this one, so it is left unloaded
+                    Some(quick_save) if quick_save.scene != self.scene.as_str() => {
+                        Ok("Quick save is from another level")
+                    }
+                    Some(quick_save) => self.load_quick_save(quick_save).map(|_| "Quick loaded"),
+                    None => Ok("No quick save"),
+                }) {
+                Ok(text) => notify(text),
+                Err(err) => {
+                    warn!("Unable to quick load: {err:#}");
+
+                    // A damaged quick save returns the player to their latest checkpoint instead
+                    if self.load_checkpoint() {
+                        notify("Quick load failed; returned to checkpoint");
+                    } else {
+                        notify("Quick load failed");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the player to their latest checkpoint, reading the autosave of an earlier session
+    /// if none has been reached in this one; returns `false` if there is no checkpoint in this
+    /// scene.
+    fn load_checkpoint(&mut self) -> bool {
+        if self.save_game.is_none() {
+            self.save_game = SaveGame::read_autosave().unwrap_or_else(|err| {
+                warn!("Unable to read autosave: {err:#}");
+
+                None
+            });
+
+            if let Some(save_game) = &self.save_game {
+                let mods = active_mod_names();
+
+                if save_game.mods != mods {
+                    warn!(
+                        "Autosave was written with mods [{}] but [{}] are active",
+                        save_game.mods.join(", "),
+                        mods.join(", ")
+                    );
+                }
+
+                // Levels of the earlier session keep the changes made to them
+                self.world_deltas.extend(save_game.world_deltas.clone());
+            }
+
+            self.apply_world_delta();
+
+            // A checkpoint of another scene does not fit this one, so the player respawns at the
+            // spawn point instead
+            if self
+                .save_game
+                .as_ref()
+                .is_some_and(|save_game| save_game.scene != self.scene.as_str())
+            {
+                info!("Autosave is not of {}", self.scene);
+
+                self.save_game = None;
+            }
+        }
+
+        if self.save_game.is_none() {
+            return false;
         }
 
-        self.update_camera(ui);
+        self.respawn();
+
+        true
+    }
+
+    /// Applies a cheat code typed during play, confirming it on the HUD.
+    fn apply_cheat(&mut self, cheat: Cheat) {
+        info!("Cheat: {cheat:?}");
+
+        let toast = match cheat {
+            Cheat::GiveAll => {
+                let inventory = &mut self.world.player_mut(self.player).unwrap().inventory;
+                inventory.ammo = Inventory::MAX_AMMO;
+                inventory.health = Inventory::MAX_HEALTH;
+                inventory
+                    .weapons
+                    .extend(Self::WEAPONS.map(|weapon| weapon.id.to_owned()));
+
+                "All weapons and ammo"
+            }
+            Cheat::GodMode => {
+                self.rules.set_god_mode(!self.rules.god_mode());
+
+                if self.rules.god_mode() {
+                    "God mode on"
+                } else {
+                    "God mode off"
+                }
+            }
+            Cheat::Noclip => {
+                if self.free_fly.take().is_some() {
+                    // The player lands on the walkable region nearest the camera
+                    let location = self
+                        .level
+                        .nav_mesh
+                        .locate(self.camera.position - Player::EYE_OFFSET);
+                    self.world.teleport_player(self.player, location);
+                    self.camera.position = self.local_player().eye_position();
+
+                    "Noclip off"
+                } else {
+                    self.free_fly = Some(self.camera.position);
+
+                    "Noclip on"
+                }
+            }
+        };
+
+        self.toasts.push(toast);
+    }
+
+    /// Applies the changes recorded for this level: collected pickups and destroyed props are
+    /// despawned, and opened doors are latched open.
+    fn apply_world_delta(&mut self) {
+        let Some(delta) = self.world_deltas.level(self.scene.as_str()) else {
+            return;
+        };
+
+        for (&entity, name) in &self.entity_refs {
+            match delta.change(name) {
+                Some(WorldChange::Collected | WorldChange::Destroyed) => {
+                    self.world.despawn(entity);
+
+                    if let Some(&model_instance) = self.model_instances.get(&entity) {
+                        self.model_buf
+                            .set_model_instance_visible(model_instance, false);
+                    }
+                }
+                Some(WorldChange::Opened) => self.world.latch_open(entity),
+                None => (),
+            }
+        }
+    }
+
+    /// Records a change to an entity spawned from a scene ref, so that it persists when this
+    /// level is entered again.
+    fn record_world_change(&mut self, entity: EntityId, change: WorldChange) {
+        if let Some(name) = self.entity_refs.get(&entity) {
+            self.world_deltas.record(self.scene.as_str(), name, change);
+        }
+    }
+
+    /// Replaces the progress through objectives with that of a save, which lists the objectives
+    /// the level starts with if the save was written before objectives were recorded.
+    fn restore_objectives(&mut self, objectives: Objectives) {
+        self.objectives = if objectives == Objectives::default() {
+            Objectives::new(&self.objective_infos)
+        } else {
+            objectives
+        };
+    }
+
+    /// Returns the player to the latest checkpoint, or to the spawn point with a new inventory if
+    /// no checkpoint has been reached.
+    fn respawn(&mut self) {
+        if let Some(save_game) = &self.save_game {
+            info!("Reloading {}", save_game.checkpoint);
+
+            let location = self
+                .level
+                .nav_mesh
+                .locate(Vec3::from_array(save_game.position));
+            self.world.teleport_player(self.player, location);
+            self.world.player_mut(self.player).unwrap().inventory = save_game.inventory.clone();
+            self.rules.set_difficulty(save_game.difficulty);
+            self.restore_objectives(save_game.objectives.clone());
+            self.camera.pitch = save_game.pitch;
+            self.camera.yaw = save_game.yaw;
+
+            if let Some(index) = Self::WEAPONS
+                .iter()
+                .position(|weapon| weapon.id == save_game.weapon)
+            {
+                self.weapons.select(index);
+            }
+        } else {
+            info!("Respawning");
+
+            self.world.teleport_player(self.player, self.spawn_location);
+            self.world.player_mut(self.player).unwrap().inventory = Inventory::default();
+            self.objectives = Objectives::new(&self.objective_infos);
+            self.camera.pitch = 0.0;
+            self.camera.yaw = 0.0;
+        }
+
+        self.camera.position = self.local_player().eye_position();
+    }
+
+    /// Returns the name of a pickup shown to players, such as the name of a weapon.
+    fn pickup_name(kind: &PickupKind) -> String {
+        match kind {
+            PickupKind::Weapon(id) => Self::WEAPONS
+                .iter()
+                .find(|weapon| weapon.id == id.as_str())
+                .map(|weapon| weapon.name.to_owned())
+                .unwrap_or_else(|| id.clone()),
+            kind => kind.to_string(),
+        }
+    }
+
+    /// Presents a world event to the player, such as by playing sounds.
+    fn present(&mut self, ui: &mut UpdateContext, event: WorldEvent, is_sprinting: bool) {
+        match event {
+            // The HUD already shows the health of the player
+            WorldEvent::Damaged {
+                amount,
+                position,
+                victim,
+            } if victim != self.player => self.floating_text.damage(position, amount),
+            WorldEvent::Damaged { .. } => (),
+            WorldEvent::Detonated { kind, position, .. } => {
+                let info = kind.info();
+
+                self.projectile_fx.flash(
+                    position,
+                    info.color,
+                    info.splash_radius * 0.5,
+                    ui.config.flash_intensity,
+                );
+
+                // Explosions in the air sound like they hit the default surface
+                let hit = self
+                    .level
+                    .surfaces
+                    .raycast(Ray::new(position, -Vec3::Y), info.splash_radius)
+                    .unwrap_or(RaycastHit {
+                        distance: 0.0,
+                        normal: Vec3::Y,
+                        position,
+                        surface: Surface::default(),
+                    });
+                self.play_impact(ui, &hit, ImpactKind::Explosion);
+
+                let falloff = info.splash_damage(position.distance(self.camera.position))
+                    / info.splash_damage;
+
+                if falloff > 0.0 {
+                    self.camera_effects.shake(
+                        Self::EXPLOSION_SHAKE_STRENGTH * falloff,
+                        Self::EXPLOSION_SHAKE_SECS,
+                    );
+                }
+            }
+            WorldEvent::PickedUp {
+                kind,
+                pickup,
+                player,
+                position,
+            } => {
+                // The model instance is kept so that loading a quick save may show it again
+                if let Some(&model_instance) = self.model_instances.get(&pickup) {
+                    self.model_buf
+                        .set_model_instance_visible(model_instance, false);
+                }
+
+                self.record_world_change(pickup, WorldChange::Collected);
+
+                if player != self.player {
+                    return;
+                }
+
+                let item = Self::pickup_name(&kind);
+
+                debug!("Picked up {item}");
+
+                self.floating_text.pickup(position, item.clone());
+
+                ui.captions.push_text(
+                    Speaker::Narrator,
+                    format!("Picked up {item}"),
+                    Self::PICKUP_NOTIFICATION_SECS,
+                );
+                let sound = self.content.sounds[&Self::PICKUP_SOUND].clone();
+
+                self.play_spatial_sound(ui, &sound, position, SoundPriority::High, Some("pickup"));
+            }
+            WorldEvent::Destroyed { body, .. } => {
+                if let Some(&model_instance) = self.model_instances.get(&body) {
+                    self.model_buf
+                        .set_model_instance_visible(model_instance, false);
+                }
+
+                self.record_world_change(body, WorldChange::Destroyed);
+            }
+            WorldEvent::Killed { killer, player } => {
+                if killer == self.player && player != self.player {
+                    self.game_events
+                        .publish(GameEvent::EnemyKilled { enemy: player });
+                }
+            }
+            WorldEvent::Opened { mover } => self.record_world_change(mover, WorldChange::Opened),
+            WorldEvent::Walked { distance, player } if player == self.player => {
+                let speed = distance / FixedTimestep::DT;
+
+                self.footstep_distance += distance;
+                self.camera_effects.update(
+                    FixedTimestep::DT,
+                    speed,
+                    is_sprinting,
+                    ui.config.head_bob,
+                );
+                self.crosshair.color = ui.config.crosshair_color;
+                self.crosshair.scale = ui.config.hud_scale;
+                self.crosshair.style = ui.config.crosshair_style;
+                self.crosshair.update(FixedTimestep::DT, speed);
+            }
+            WorldEvent::Walked { .. } => (),
+        }
+    }
+
+    /// Runs the simulation steps which have elapsed since the previous update.
+    fn simulate(&mut self, ui: &mut UpdateContext) {
+        for _ in 0..ui.fixed_steps {
+            let input = self.player_input(ui);
+            let is_sprinting = input.sprint && input.movement.y > 0.0;
+            let mut events = take(&mut self.world_events);
+
+            self.world.step(
+                &mut self.level.nav_mesh,
+                &self.level.collision,
+                &BTreeMap::from([(self.player, input)]),
+                &self.rules,
+                FixedTimestep::DT,
+                &mut events,
+            );
+            self.camera.position = self
+                .free_fly
+                .or_else(|| {
+                    self.corpse
+                        .as_ref()
+                        .and_then(|corpse| corpse.eye_position(&self.world))
+                })
+                .unwrap_or_else(|| self.local_player().eye_position());
+
+            for event in events.drain(..) {
+                self.present(ui, event, is_sprinting);
+            }
+
+            self.world_events = events;
+            self.level_secs += FixedTimestep::DT;
+            self.music_intensity.update(FixedTimestep::DT);
+
+            if self.corpse.is_none() {
+                self.update_firing(ui, FixedTimestep::DT);
+            }
+
+            self.update_projectiles();
+            self.update_footsteps(ui);
+            self.update_checkpoints(ui);
+            self.update_triggers(ui);
+        }
+    }
+
+    /// Poses the body of the local player, who respawns once the camera has followed it long
+    /// enough.
+    fn update_corpse(&mut self, dt: f32) {
+        let Some(corpse) = &mut self.corpse else {
+            return;
+        };
+
+        if corpse.update(&self.world, &mut self.model_buf, dt) {
+            self.corpse
+                .take()
+                .unwrap()
+                .remove(&mut self.world, &mut self.model_buf);
+            self.respawn();
+        }
+    }
+
+    /// Saves the game the first time the player reaches each checkpoint.
+    fn update_checkpoints(&mut self, ui: &mut UpdateContext) {
+        let player = self.world.player(self.player).unwrap();
+        let Some(checkpoint) = self.checkpoints.update(player.location.position()) else {
+            return;
+        };
+
+        debug!("Reached {}", checkpoint.id());
+
+        let save_game = SaveGame {
+            checkpoint: checkpoint.id().to_owned(),
+            difficulty: self.rules.difficulty(),
+            inventory: player.inventory.clone(),
+            mods: active_mod_names(),
+            objectives: self.objectives.clone(),
+            pitch: self.camera.pitch,
+            position: player.location.position().to_array(),
+            scene: self.scene.as_str().to_owned(),
+            weapon: self.weapons.current().id.to_owned(),
+            world_deltas: self.world_deltas.clone(),
+            yaw: self.camera.yaw,
+        };
+
+        if let Err(err) = save_game.write_autosave() {
+            warn!("Unable to write autosave: {err:#}");
+        }
+
+        ui.captions.push_text(
+            Speaker::Narrator,
+            "Checkpoint reached",
+            Self::CHECKPOINT_NOTIFICATION_SECS,
+        );
+
+        self.save_game = Some(save_game);
+    }
+
+    /// Delivers the gameplay events of this update to each listener, writing the achievements and
+    /// profile if they changed.
+    fn update_game_events(&mut self, ui: &UpdateContext) {
+        if !self.game_events.dispatch(&mut [
+            &mut self.achievements,
+            &mut self.music_intensity,
+            &mut self.profile,
+            &mut self.toasts,
+        ]) {
+            return;
+        }
+
+        for achievement in self.achievements.take_unlocked() {
+            info!("Unlocked {achievement}");
+
+            self.toasts
+                .push(format!("Achievement unlocked: {achievement}"));
+        }
+
+        // Demos replay earlier play, which should not count twice
+        if ui.is_demo_playback {
+            return;
+        }
+
+        if let Err(err) = self.achievements.write() {
+            warn!("Unable to write achievements: {err:#}");
+        }
+
+        if let Err(err) = self.profile.write() {
+            warn!("Unable to write profile: {err:#}");
+        }
+    }
+
+    /// Plays a footstep each stride, using the sounds of the surface below the player.
+    fn update_footsteps(&mut self, ui: &mut UpdateContext) {
+        if self.footstep_distance < Self::FOOTSTEP_STRIDE {
+            return;
+        }
+
+        self.footstep_distance %= Self::FOOTSTEP_STRIDE;
+
+        // Surface geometry may lie slightly above or below the walkable region
+        let surface = self
+            .level
+            .surfaces
+            .raycast(
+                Ray::new(
+                    self.local_player().position() + Player::EYE_OFFSET * 0.5,
+                    -Vec3::Y,
+                ),
+                Player::EYE_OFFSET.y,
+            )
+            .map(|hit| hit.surface)
+            .unwrap_or_default();
+
+        if let Some(sound) = self.content.sfx.sound(surface.footstep_event()) {
+            let position = self.local_player().position();
+
+            self.play_spatial_sound(ui, &sound, position, SoundPriority::Low, None);
+        }
+    }
+
+    /// Outlines the nearest pickup the camera looks at, unless it is out of reach or behind a wall.
+    fn update_highlight(&mut self) {
+        let rotation = Quat::from_rotation_y(self.camera.yaw.to_radians())
+            * Quat::from_rotation_x(self.camera.pitch.to_radians());
+        let ray = Ray::new(self.camera.position, rotation * -Vec3::Z);
+        let max_distance = self
+            .level
+            .surfaces
+            .raycast(ray, Self::HIGHLIGHT_DISTANCE)
+            .map_or(Self::HIGHLIGHT_DISTANCE, |hit| hit.distance);
+        let highlighted_pickup = self
+            .model_buf
+            .instances_hit_by_ray(ray, max_distance)
+            .into_iter()
+            .find_map(|(model_instance, _)| {
+                self.model_instances
+                    .iter()
+                    .find(|(&entity, &entity_instance)| {
+                        entity_instance == model_instance && self.world.pickup(entity).is_some()
+                    })
+                    .map(|(&entity, _)| (entity, model_instance))
+            });
+        let highlighted = highlighted_pickup.map(|(_, model_instance)| model_instance);
+
+        if highlighted == self.highlighted {
+            return;
+        }
+
+        self.floating_text
+            .set_interaction(highlighted_pickup.and_then(|(entity, _)| {
+                let position = self.world.transform(entity)?.position;
+                let kind = self.world.pickup(entity)?;
+
+                Some((
+                    position + Vec3::Y * Self::HIGHLIGHT_LABEL_HEIGHT,
+                    Self::pickup_name(kind),
+                ))
+            }));
+
+        if let Some(model_instance) = self.highlighted {
+            self.model_buf
+                .set_model_instance_highlight(model_instance, None);
+        }
+
+        if let Some(model_instance) = highlighted {
+            self.model_buf
+                .set_model_instance_highlight(model_instance, Some(Self::HIGHLIGHT_COLOR));
+        }
+
+        self.highlighted = highlighted;
+    }
+
+    /// Blends the reverb of world sounds towards the zone the camera is in.
+    fn update_reverb(&self, ui: &mut UpdateContext) {
+        let preset = if ui.config.reverb {
+            self.level.reverb_zones.preset(self.camera.position)
+        } else {
+            ReverbPreset::Dry
+        };
+
+        if let Some(reverb) = ui.reverb.as_mut() {
+            reverb.set_preset(preset);
+        }
+    }
+
+    fn update_triggers(&mut self, ui: &mut UpdateContext) {
+        self.trigger_events.clear();
+        self.level
+            .triggers
+            .update(self.camera.position, &mut self.trigger_events);
+
+        // Events are rare so the hook table is only built when needed
+        if !self.trigger_events.is_empty() {
+            Self::trigger_hooks().dispatch(
+                &mut TriggerContext {
+                    game_events: &mut self.game_events,
+                    level_completed: &mut self.level_completed,
+                    level_secs: self.level_secs,
+                    objective_infos: &self.objective_infos,
+                    objectives: &mut self.objectives,
+                    secrets_found: &mut self.secrets_found,
+                    scene: self.scene,
+                    sounds: &self.content.sounds,
+                    ui,
+                },
+                &self.level.triggers,
+                &self.trigger_events,
+            );
+        }
+    }
+
+    fn update_weapons(&mut self, ui: &UpdateContext) {
+        let previous_weapon = self.weapons.current_index();
+
+        for (index, key) in [
+            VirtualKeyCode::Key1,
+            VirtualKeyCode::Key2,
+            VirtualKeyCode::Key3,
+            VirtualKeyCode::Key4,
+        ]
+        .iter()
+        .enumerate()
+        {
+            if ui.keyboard.is_pressed(key) {
+                self.weapons.select(index);
+            }
+        }
+
+        // Scrolling down (towards the user) or pressing forward selects the next weapon
+        self.wheel_scroll += ui.mouse_extra.wheel_delta();
+
+        while self.wheel_scroll >= 1.0 {
+            self.wheel_scroll -= 1.0;
+            self.weapons.cycle(-1);
+        }
+
+        while self.wheel_scroll <= -1.0 {
+            self.wheel_scroll += 1.0;
+            self.weapons.cycle(1);
+        }
+
+        if ui.mouse_extra.is_pressed(ExtraButton::Back) {
+            self.weapons.cycle(-1);
+        }
+
+        if ui.mouse_extra.is_pressed(ExtraButton::Forward) {
+            self.weapons.cycle(1);
+        }
+
+        let index = self.weapons.current_index();
+        if index != previous_weapon {
+            self.view_model_buf.set_model_instance_materials(
+                self.view_model,
+                &self.view_model_materials[index..index + 1],
+            );
+        }
+
+        self.view_model_buf.set_model_instance_transform(
+            self.view_model,
+            self.weapons.view_model_position(),
+            Quat::IDENTITY,
+        );
+    }
+
+    /// Fires the current weapon, while the mouse button is held or once it has been clicked, for
+    /// one simulation step.
+    fn update_firing(&mut self, ui: &mut UpdateContext, dt: f32) {
+        self.weapons.update(dt);
+
+        if take(&mut self.fire_latched) || ui.mouse.is_down(MouseButton::Left) {
+            let (victims, targets): (Vec<_>, Vec<_>) =
+                self.world.shot_targets(self.player).into_iter().unzip();
+
+            if let Some(pellets) = self.weapons.fire(
+                self.camera.position,
+                self.camera.yaw,
+                self.camera.pitch,
+                &targets,
+            ) {
+                let weapon = *self.weapons.current();
+
+                if let Some(sound) = self.content.sfx.sound(weapon.fire_sound.as_str()) {
+                    self.play_spatial_sound(
+                        ui,
+                        &sound,
+                        self.camera.position,
+                        SoundPriority::High,
+                        Some(weapon.fire_caption),
+                    );
+                }
+
+                self.camera_effects
+                    .shake(Self::FIRE_SHAKE_STRENGTH, Self::FIRE_SHAKE_SECS);
+                self.crosshair.fire();
+
+                for pellet in pellets {
+                    if let Some(kind) = weapon.projectile {
+                        self.world.spawn_projectile(kind, self.player, pellet.ray);
+
+                        continue;
+                    }
+
+                    let distance = pellet.hit.map_or(weapon.range, |hit| hit.distance);
+
+                    // Level collision stops shots which would otherwise pass through walls
+                    if let Some(level_hit) = self.level.collision.raycast(pellet.ray, distance) {
+                        self.play_impact(ui, &level_hit, ImpactKind::Bullet);
+
+                        continue;
+                    }
+
+                    if let Some(hit) = pellet.hit {
+                        debug!(
+                            "{} hit target {} at {:?} ({} damage)",
+                            weapon.name, hit.target_index, hit.position, hit.damage
+                        );
+
+                        // Blocking volumes, such as closed doors, only stop the shot
+                        if let Some(victim) = victims[hit.target_index] {
+                            let mut events = take(&mut self.world_events);
+
+                            self.world.shoot(
+                                &self.rules,
+                                self.player,
+                                victim,
+                                hit.damage,
+                                hit.position,
+                                &mut events,
+                            );
+
+                            // The marker only flashes once an actor has been damaged
+                            self.crosshair.hit();
+
+                            for event in events.drain(..) {
+                                self.present(ui, event, false);
+                            }
+
+                            self.world_events = events;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Inserts a model instance for each projectile which does not have one yet, and removes those
+    /// of projectiles which have detonated.
+    fn sync_projectile_instances(&mut self) {
+        self.projectile_instances
+            .retain(|&entity, &mut model_instance| {
+                let is_flying = self.world.contains(entity);
+
+                if !is_flying {
+                    self.model_buf.remove_model_instance(model_instance);
+                }
+
+                is_flying
+            });
+
+        for (entity, projectile, transform) in self.world.projectiles() {
+            if self.projectile_instances.contains_key(&entity) {
+                continue;
+            }
+
+            let (model, material) = self.content.projectile_models[&projectile.kind];
+
+            self.projectile_instances.insert(
+                entity,
+                self.model_buf.insert_model_instance(
+                    model,
+                    &[material],
+                    transform.position,
+                    transform.rotation,
+                ),
+            );
+        }
+    }
+
+    /// Keeps the model instances of projectiles in step with the world and leaves a puff of trail
+    /// behind each one, once per simulation step.
+    fn update_projectiles(&mut self) {
+        self.sync_projectile_instances();
+
+        for (_, projectile, transform) in self.world.projectiles() {
+            self.projectile_fx
+                .puff(transform.position, projectile.kind.info().color);
+        }
+    }
+
+    /// Leaves the decal, throws off the particles and plays the sound listed in the impact table
+    /// for something hitting the level.
+    fn play_impact(&mut self, ui: &mut UpdateContext, hit: &RaycastHit, kind: ImpactKind) {
+        let Some(effect) = self.content.impacts.effect(hit.surface.name(), kind) else {
+            return;
+        };
+
+        trace!(
+            "{} impact on {} at {:?} facing {:?}",
+            kind.name(),
+            hit.surface.name(),
+            hit.position,
+            hit.normal
+        );
+
+        if let Some(&material) = effect
+            .decal
+            .as_ref()
+            .and_then(|key| self.content.decal_materials.get(key))
+        {
+            self.decals.spawn(
+                &mut self.model_buf,
+                self.content.decal_model,
+                material,
+                hit.position,
+                hit.normal,
+            );
+        }
+
+        if let Some(particles) = effect
+            .particles
+            .as_ref()
+            .and_then(|name| self.content.impacts.particles.get(name))
+        {
+            self.projectile_fx
+                .burst(hit.position, hit.normal, particles);
+        }
+
+        if let Some(sound) = self.content.sfx.sound(&impact_event(hit.surface, kind)) {
+            let priority = match kind {
+                ImpactKind::Bullet => SoundPriority::Normal,
+                ImpactKind::Explosion => SoundPriority::High,
+            };
+
+            self.play_spatial_sound(ui, &sound, hit.position, priority, None);
+        }
+    }
+
+    /// Plays a sound once at the given position within the level, using one of the voices of the
+    /// sound world, and queues the given caption key, if captions are enabled.
+    fn play_spatial_sound(
+        &mut self,
+        ui: &mut UpdateContext,
+        sound: &StaticSoundData,
+        position: Vec3,
+        priority: SoundPriority,
+        caption: Option<&'static str>,
+    ) {
+        if let Some((sound_world, audio)) = self.sound_world.as_mut().zip(ui.audio.as_deref_mut()) {
+            sound_world.play(audio, ui.reverb.as_deref(), sound, position, priority);
+        }
+
+        if let Some(caption) = caption.filter(|_| ui.config.captions) {
+            ui.captions.push(caption);
+        }
+    }
+}
+
+impl Ui for Play {
+    fn accepts_demo_input(&self) -> bool {
+        true
+    }
+
+    fn demo_level(&self) -> Option<DemoLevel> {
+        Some(DemoLevel {
+            difficulty: self.rules.difficulty(),
+            scene: self.scene.as_str().to_owned(),
+            spawn: self.spawn.clone(),
+        })
+    }
+
+    fn draw(&mut self, frame: DrawContext) {
+        let framebuffer_info = frame.render_graph.node_info(frame.framebuffer_image);
+
+        self.camera.aspect_ratio = framebuffer_info.width as f32 / framebuffer_info.height as f32;
+
+        // TODO: Remove before flight
+        frame
+            .render_graph
+            .clear_color_image_value(frame.framebuffer_image, [0xFF, 0x00, 0xFF, 0xFF]);
+
+        // The simulation runs at a fixed rate, so the camera and entities are drawn part of the way
+        // between the previous and current steps
+        let mut camera = self.camera;
+
+        for (entity, transform) in self.world.interpolate(frame.fixed_alpha) {
+            if entity == self.player {
+                camera.position = self
+                    .free_fly
+                    .or_else(|| {
+                        self.corpse
+                            .as_ref()
+                            .and_then(|corpse| corpse.eye_position(&self.world))
+                    })
+                    .unwrap_or(transform.position + Player::EYE_OFFSET);
+            } else if let Some(&model_instance) = self
+                .model_instances
+                .get(&entity)
+                .or_else(|| self.projectile_instances.get(&entity))
+            {
+                self.model_buf.set_model_instance_transform(
+                    model_instance,
+                    transform.position,
+                    transform.rotation,
+                );
+            }
+        }
+
+        // Effects only change the drawn view; aiming and collision use the undisturbed camera
+        let mut camera = self.camera_effects.apply(&camera);
+
+        // Debug modes show what is under the crosshair, which is the center of the framebuffer
+        self.model_buf.set_pick_position(
+            (self.model_buf.debug_mode() != DebugMode::Off)
+                .then(|| uvec2(framebuffer_info.width, framebuffer_info.height) / 2),
+        );
+
+        // The HUD is recorded on another thread, so whatever it shows of the models is read first;
+        // picking is a frame late regardless
+        let debug_mode = self.model_buf.debug_mode();
+        let hovered_instance = self.hovered_instance();
+        let streaming_spent = self.model_buf.streaming_spent();
+        let music_intensity = self.music_intensity.intensity();
+        let voice_count = self.sound_world.as_ref().map(SoundWorld::voice_count);
+        let overlay_timings = self.overlay.timings();
+        let player_location = self.local_player().location;
+        let hud_info = frame.render_graph.node_info(frame.hud_image);
+        let hud_size = vec2(hud_info.width as _, hud_info.height as _);
+        let hud_camera = camera;
+
+        self.view_model_camera.aspect_ratio = self.camera.aspect_ratio;
+
+        if let Err(err) = self.overlay.record(
+            frame.render_graph,
+            frame.hud_image,
+            |render_graph| {
+                self.model_buf
+                    .record(render_graph, frame.framebuffer_image, &mut camera)
+                    .context("Recording models")?;
+
+                if self.nav_mesh_visible {
+                    self.nav_mesh_debug.debug_draw(
+                        render_graph,
+                        frame.framebuffer_image,
+                        &camera,
+                        player_location,
+                    );
+                }
+
+                // The view model is drawn after the world so that it never clips into walls
+                self.view_model_buf
+                    .record(
+                        render_graph,
+                        frame.framebuffer_image,
+                        &mut self.view_model_camera,
+                    )
+                    .context("Recording view model")?;
+
+                Ok(())
+            },
+            |render_graph, overlay_image| {
+                let font = &self.content.dare_font;
+
+                font.print(
+                    render_graph,
+                    overlay_image,
+                    0.0,
+                    0.0,
+                    [0xff, 0xff, 0xff],
+                    format!("FPS: {}", (1.0 / frame.dt).round()),
+                );
+
+                frame.captions.print(font, render_graph, overlay_image);
+                self.toasts.draw(font, render_graph, overlay_image);
+
+                // Listed below the line which debug modes print at the top right
+                let (_, [_, line_height]) = font.measure("M");
+                self.objective_list
+                    .draw(font, render_graph, overlay_image, line_height);
+
+                if self.free_fly.is_some() {
+                    let text = "Free fly";
+                    let (_, [_, height]) = font.measure(text);
+
+                    font.print(
+                        render_graph,
+                        overlay_image,
+                        0.0,
+                        hud_info.height.saturating_sub(height) as _,
+                        [0xff, 0xff, 0x00],
+                        text,
+                    );
+                }
+
+                if self.accessibility.visible {
+                    self.accessibility.draw(font, render_graph, overlay_image);
+                }
+
+                if self.console.visible {
+                    self.console.draw(font, render_graph, overlay_image);
+                }
+
+                if self.paused {
+                    let text = "Click to resume";
+                    let (_, [width, height]) = font.measure(text);
+
+                    font.print(
+                        render_graph,
+                        overlay_image,
+                        (hud_info.width.saturating_sub(width) / 2) as _,
+                        (hud_info.height.saturating_sub(height) / 2) as _,
+                        [0xff, 0xff, 0xff],
+                        text,
+                    );
+                }
+
+                if debug_mode != DebugMode::Off {
+                    let text = match hovered_instance {
+                        Some(model_instance) => {
+                            format!("Debug: {debug_mode} ({model_instance:?})")
+                        }
+                        None => format!("Debug: {debug_mode}"),
+                    };
+                    let (_, [width, _]) = font.measure(&text);
+
+                    font.print(
+                        render_graph,
+                        overlay_image,
+                        hud_info.width.saturating_sub(width) as _,
+                        0.0,
+                        [0xff, 0xff, 0x00],
+                        text,
+                    );
+                }
+
+                self.projectile_fx
+                    .draw(&mut self.primitives, &hud_camera, hud_size);
+                self.floating_text
+                    .draw(font, render_graph, overlay_image, &hud_camera);
+
+                // Drawn after the view model so the weapon never covers it
+                self.crosshair.draw(&mut self.primitives, hud_size * 0.5);
+
+                if self.frame_graph.visible {
+                    let left = hud_info.width as i32 - FrameGraph::WIDTH - 4;
+                    let bottom = hud_info.height as i32 - 4;
+
+                    self.frame_graph
+                        .draw(frame.frame_stats, left, bottom, &mut self.primitives);
+
+                    let (_, [_, line_height]) = font.measure("FPS");
+
+                    font.print(
+                        render_graph,
+                        overlay_image,
+                        0.0,
+                        line_height as _,
+                        [0xff, 0xff, 0xff],
+                        format!(
+                            "1% low: {} 0.1% low: {}",
+                            frame.frame_stats.one_percent_low().round(),
+                            frame.frame_stats.point_one_percent_low().round()
+                        ),
+                    );
+
+                    font.print(
+                        render_graph,
+                        overlay_image,
+                        0.0,
+                        2.0 * line_height as f32,
+                        [0xff, 0xff, 0xff],
+                        format!(
+                            "Resolution: {}% ({}p)",
+                            (frame.resolution_scale * 100.0).round(),
+                            framebuffer_info.height
+                        ),
+                    );
+
+                    font.print(
+                        render_graph,
+                        overlay_image,
+                        0.0,
+                        3.0 * line_height as f32,
+                        [0xff, 0xff, 0xff],
+                        format!("Present mode: {:?}", frame.present_mode),
+                    );
+
+                    // There are no music tracks to play yet, so the intensity they would follow is
+                    // shown
+                    font.print(
+                        render_graph,
+                        overlay_image,
+                        0.0,
+                        4.0 * line_height as f32,
+                        [0xff, 0xff, 0xff],
+                        format!("Music intensity: {}%", (music_intensity * 100.0).round()),
+                    );
+
+                    if let Some((playing, voice_count)) = voice_count {
+                        font.print(
+                            render_graph,
+                            overlay_image,
+                            0.0,
+                            5.0 * line_height as f32,
+                            [0xff, 0xff, 0xff],
+                            format!("Voices: {playing} of {voice_count}"),
+                        );
+                    }
+
+                    if let (spent, Some(limit)) = streaming_spent {
+                        font.print(
+                            render_graph,
+                            overlay_image,
+                            0.0,
+                            6.0 * line_height as f32,
+                            [0xff, 0xff, 0xff],
+                            format!(
+                                "Streaming: {:.2} of {:.2} ms",
+                                spent * 1_000.0,
+                                limit * 1_000.0
+                            ),
+                        );
+                    }
+
+                    // The HUD is recorded alongside the world, so only the wait adds to the frame
+                    font.print(
+                        render_graph,
+                        overlay_image,
+                        0.0,
+                        7.0 * line_height as f32,
+                        [0xff, 0xff, 0xff],
+                        format!(
+                            "Recording: world {:.2} ms, HUD {:.2} ms, waited {:.2} ms",
+                            overlay_timings.frame_secs * 1_000.0,
+                            overlay_timings.overlay_secs * 1_000.0,
+                            overlay_timings.wait_secs * 1_000.0
+                        ),
+                    );
+                }
+
+                self.primitives
+                    .record(render_graph, overlay_image)
+                    .context("Recording primitives")?;
+
+                Ok(())
+            },
+        ) {
+            self.err = Some(err);
+        }
+    }
+
+    fn update(mut self: Box<Self>, mut ui: UpdateContext) -> Option<Box<dyn Ui>> {
+        if let Some(err) = self.err.take() {
+            return Some(Box::new(ErrorScreen::new(&self.device, err)));
+        }
+
+        self.paused = !ui.is_demo_playback && !ui.focus.is_active();
+
+        if self.paused {
+            return Some(self);
+        }
+
+        // While the accessibility panel is open the arrow keys change settings and escape closes it
+        let accessibility_was_visible = self.accessibility.visible;
+
+        if ui.keyboard.is_pressed(&VirtualKeyCode::F11) {
+            self.accessibility.visible = !self.accessibility.visible;
+        }
+
+        if self.accessibility.visible {
+            let navigation = ui.navigation();
+
+            if self.accessibility.update(ui.config, navigation) && !ui.is_demo_playback {
+                if let Err(err) = ui.config.write() {
+                    warn!("Unable to write config: {err}");
+                }
+            }
+        }
+
+        // The console is typed into, so gameplay holds while it is open; escape closes it
+        let console_was_visible = self.console.visible;
+
+        if !ui.is_demo_playback
+            && (ui.keyboard.is_pressed(&VirtualKeyCode::Grave)
+                || console_was_visible && ui.keyboard.is_pressed(&VirtualKeyCode::Escape))
+        {
+            self.console.visible = !self.console.visible;
+        }
+
+        if self.console.visible {
+            self.console.update(ui.cvars, ui.config, ui.events);
+        } else if ui.cvars.is_set(ui.config, "debug.cheats") {
+            for cheat in self.cheats.update(ui.events) {
+                self.apply_cheat(cheat);
+            }
+        }
+
+        match self.console.take_command() {
+            Some(ConsoleCommand::LevelSelect) => {
+                let device = Arc::clone(&self.device);
+
+                self.console.visible = false;
+
+                return Some(
+                    match LevelSelect::new(&device, self.world_deltas.clone(), self) {
+                        Ok(level_select) => Box::new(level_select),
+                        Err(err) => Box::new(ErrorScreen::new(&device, err)),
+                    },
+                );
+            }
+            Some(ConsoleCommand::FreeFly) => {
+                self.free_fly = match self.free_fly {
+                    Some(_) => None,
+                    None => Some(self.camera.position),
+                };
+
+                info!("Free fly: {}", self.free_fly.is_some());
+            }
+            Some(ConsoleCommand::TimeOfDay(hours)) => {
+                let mut sky = self.model_buf.sky();
+                sky.time_of_day = hours;
+                self.model_buf.set_sky(sky);
+            }
+            None => (),
+        }
+
+        #[cfg(debug_assertions)]
+        if !accessibility_was_visible
+            && !console_was_visible
+            && ui.keyboard.is_pressed(&VirtualKeyCode::Escape)
+        {
+            return None;
+        }
+
+        // Debug overlays are cvars, which are toggled using function keys or from the console
+        if ui.keyboard.is_pressed(&VirtualKeyCode::F2) {
+            ui.cvars.toggle(ui.config, "debug.show_navmesh").unwrap();
+        }
+
+        if ui.keyboard.is_pressed(&VirtualKeyCode::F3) {
+            ui.cvars
+                .toggle(ui.config, "debug.show_frame_graph")
+                .unwrap();
+        }
+
+        if ui.keyboard.is_pressed(&VirtualKeyCode::F4) {
+            let debug_mode = self.model_buf.debug_mode().next();
+            self.model_buf.set_debug_mode(debug_mode);
+
+            info!("Debug mode: {debug_mode}");
+        }
+
+        if ui.keyboard.is_pressed(&VirtualKeyCode::F7) {
+            return Some(Box::new(Editor::new(self)));
+        }
+
+        if ui.config.graphics != self.graphics {
+            self.graphics = ui.config.graphics;
+
+            if let Err(err) = self.model_buf.switch_technique(&self.device, self.graphics) {
+                warn!("Unable to switch technique: {err:#}");
+            }
+        }
+
+        self.camera.fov_y = ui.config.fov;
+        self.frame_graph.visible = ui.cvars.is_set(ui.config, "debug.show_frame_graph");
+        self.nav_mesh_visible = ui.cvars.is_set(ui.config, "debug.show_navmesh");
+        self.model_buf
+            .set_depth_prepass(ui.cvars.is_set(ui.config, "render.depth_prepass"));
+
+        if self.console.visible {
+            return Some(self);
+        }
+
+        // The player respawns once the camera has followed their body for a while
+        if self.local_player().inventory.health == 0 && self.corpse.is_none() {
+            let player = self.local_player().clone();
+            self.corpse = Some(Corpse::spawn(
+                &mut self.world,
+                &mut self.model_buf,
+                self.content.corpse_model,
+                &player,
+            ));
+        }
+
+        self.update_quick_save(&mut ui);
+        self.update_camera(&ui);
+        self.update_free_fly(&ui);
+        self.update_weapons(&ui);
+
+        // Frames may run no simulation steps, so clicks wait for the next step
+        self.fire_latched |= ui.mouse.is_pressed(MouseButton::Left);
+        self.simulate(&mut ui);
+        self.update_corpse(ui.dt);
+
+        for flicker in &mut self.flickers {
+            flicker.update(&mut self.model_buf, self.level_secs);
+        }
+        self.update_highlight();
+        self.update_game_events(&ui);
+        self.floating_text.update(ui.dt);
+        self.objective_list
+            .update(&self.objectives, &self.objective_infos);
+        self.projectile_fx.update(ui.dt);
+        self.toasts.update(ui.dt);
+        self.model_buf
+            .set_streaming_budget(Some(ui.config.streaming_budget / 1_000.0));
+        self.model_buf.advance_time(ui.dt);
+        self.update_reverb(&mut ui);
+        self.update_ambience(&mut ui);
 
         Some(self)
     }
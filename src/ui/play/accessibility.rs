@@ -0,0 +1,233 @@
+//! A panel of accessibility settings shown over play, opened with F11, which changes the config
+//! as the arrow keys (or the d-pad) are pressed so that each change is seen immediately.
+
+use {
+    super::super::layout::Navigation,
+    crate::{config::Config, render::colorblind::ColorblindFilter},
+    screen_13::prelude::*,
+    screen_13_fx::BitmapFont,
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Setting {
+    ColorblindFilter,
+    CrosshairColor,
+    FlashIntensity,
+    HudScale,
+}
+
+impl Setting {
+    const ALL: [Self; 4] = [
+        Self::ColorblindFilter,
+        Self::CrosshairColor,
+        Self::FlashIntensity,
+        Self::HudScale,
+    ];
+
+    /// Changes the setting in the given direction, which is `1` or `-1`, returning `true` if it
+    /// changed.
+    fn change(self, config: &mut Config, direction: i32) -> bool {
+        fn set<T: PartialEq>(value: &mut T, new_value: T) -> bool {
+            let changed = *value != new_value;
+            *value = new_value;
+
+            changed
+        }
+
+        let step = AccessibilityPanel::STEP * direction as f32;
+
+        match self {
+            Self::ColorblindFilter => {
+                let len = ColorblindFilter::ALL.len() as i32;
+                let index = config.colorblind_filter.index() as i32 + direction;
+
+                set(
+                    &mut config.colorblind_filter,
+                    ColorblindFilter::ALL[index.rem_euclid(len) as usize],
+                )
+            }
+            Self::CrosshairColor => {
+                let [r, g, b, a] = config.crosshair_color;
+                let colors = &AccessibilityPanel::CROSSHAIR_COLORS;
+                let index = colors
+                    .iter()
+                    .position(|&(_, color)| color == [r, g, b])
+                    .map(|index| index as i32 + direction)
+                    .unwrap_or_default();
+                let (_, [r, g, b]) = colors[index.rem_euclid(colors.len() as i32) as usize];
+
+                set(&mut config.crosshair_color, [r, g, b, a])
+            }
+            Self::FlashIntensity => set(
+                &mut config.flash_intensity,
+                (config.flash_intensity + step).clamp(0.0, 1.0),
+            ),
+            Self::HudScale => set(
+                &mut config.hud_scale,
+                (config.hud_scale + step).clamp(Config::MIN_HUD_SCALE, Config::MAX_HUD_SCALE),
+            ),
+        }
+    }
+
+    fn text(self, config: &Config) -> String {
+        match self {
+            Self::ColorblindFilter => format!("Colorblind filter: {}", config.colorblind_filter),
+            Self::CrosshairColor => {
+                let [r, g, b, _] = config.crosshair_color;
+                let name = AccessibilityPanel::CROSSHAIR_COLORS
+                    .iter()
+                    .find(|(_, color)| *color == [r, g, b])
+                    .map(|(name, _)| *name)
+                    .unwrap_or("Custom");
+
+                format!("Crosshair color: {name}")
+            }
+            Self::FlashIntensity => format!(
+                "Flash intensity: {}%",
+                (config.flash_intensity * 100.0).round()
+            ),
+            Self::HudScale => format!("HUD scale: {}%", (config.hud_scale * 100.0).round()),
+        }
+    }
+}
+
+/// Accessibility settings, which are changed during play instead of in the settings menu so that
+/// each change is seen in the level.
+#[derive(Debug, Default)]
+pub struct AccessibilityPanel {
+    /// Text of each setting as of the latest update.
+    lines: Vec<String>,
+
+    selected: usize,
+    pub visible: bool,
+}
+
+impl AccessibilityPanel {
+    /// Colors the crosshair may be switched between, which are easily told apart from each other
+    /// and most levels.
+    const CROSSHAIR_COLORS: [(&'static str, [u8; 3]); 6] = [
+        ("White", [0xff, 0xff, 0xff]),
+        ("Green", [0x00, 0xff, 0x00]),
+        ("Yellow", [0xff, 0xff, 0x00]),
+        ("Cyan", [0x00, 0xff, 0xff]),
+        ("Magenta", [0xff, 0x00, 0xff]),
+        ("Red", [0xff, 0x00, 0x00]),
+    ];
+
+    const SELECTED_COLOR: [u8; 3] = [0xff, 0xff, 0x00];
+
+    /// Change of the flash intensity and HUD scale for each press.
+    const STEP: f32 = 0.25;
+
+    /// Prints each setting centered on the framebuffer, highlighting the selected one.
+    pub fn draw(
+        &self,
+        font: &BitmapFont,
+        render_graph: &mut RenderGraph,
+        framebuffer_image: impl Into<AnyImageNode>,
+    ) {
+        let framebuffer_image = framebuffer_image.into();
+        let framebuffer_info = render_graph.node_info(framebuffer_image);
+        let title = "Accessibility (F11)";
+        let (_, [_, line_height]) = font.measure(title);
+        let mut y = framebuffer_info
+            .height
+            .saturating_sub(line_height * (self.lines.len() as u32 + 1))
+            / 2;
+
+        for (index, line) in [title]
+            .into_iter()
+            .chain(self.lines.iter().map(String::as_str))
+            .enumerate()
+        {
+            let (_, [width, _]) = font.measure(line);
+            let color = if index == self.selected + 1 {
+                Self::SELECTED_COLOR
+            } else {
+                [0xff, 0xff, 0xff]
+            };
+
+            font.print(
+                render_graph,
+                framebuffer_image,
+                (framebuffer_info.width.saturating_sub(width) / 2) as _,
+                y as _,
+                color,
+                line,
+            );
+
+            y += line_height;
+        }
+    }
+
+    /// Selects a setting using up and down and changes it using left and right; back closes the
+    /// panel.
+    ///
+    /// Returns `true` if the config changed.
+    pub fn update(&mut self, config: &mut Config, navigation: Option<Navigation>) -> bool {
+        let setting_count = Setting::ALL.len();
+        let direction = match navigation {
+            Some(Navigation::Up) => {
+                self.selected = (self.selected + setting_count - 1) % setting_count;
+                0
+            }
+            Some(Navigation::Down) => {
+                self.selected = (self.selected + 1) % setting_count;
+                0
+            }
+            Some(Navigation::Left) => -1,
+            Some(Navigation::Right | Navigation::Activate) => 1,
+            Some(Navigation::Back) => {
+                self.visible = false;
+                0
+            }
+            None => 0,
+        };
+        let changed = direction != 0 && Setting::ALL[self.selected].change(config, direction);
+
+        self.lines = Setting::ALL
+            .iter()
+            .map(|setting| setting.text(config))
+            .collect();
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn change_settings() {
+        let mut config = Config::default();
+        let mut panel = AccessibilityPanel::default();
+
+        assert!(panel.update(&mut config, Some(Navigation::Right)));
+        assert_eq!(config.colorblind_filter, ColorblindFilter::Deuteranopia);
+
+        assert!(panel.update(&mut config, Some(Navigation::Left)));
+        assert!(panel.update(&mut config, Some(Navigation::Left)));
+        assert_eq!(config.colorblind_filter, ColorblindFilter::Tritanopia);
+
+        // The crosshair keeps its alpha as its color changes
+        assert!(!panel.update(&mut config, Some(Navigation::Down)));
+        assert!(panel.update(&mut config, Some(Navigation::Right)));
+        assert_eq!(config.crosshair_color, [0x00, 0xff, 0x00, 0xc0]);
+
+        // Flash intensity stops at full
+        assert!(!panel.update(&mut config, Some(Navigation::Down)));
+        assert!(!panel.update(&mut config, Some(Navigation::Right)));
+        assert!(panel.update(&mut config, Some(Navigation::Left)));
+        assert_eq!(config.flash_intensity, 0.75);
+
+        assert!(!panel.update(&mut config, Some(Navigation::Up)));
+        assert!(!panel.update(&mut config, Some(Navigation::Up)));
+        assert!(!panel.update(&mut config, Some(Navigation::Up)));
+        assert_eq!(panel.selected, 0);
+
+        assert!(!panel.update(&mut config, Some(Navigation::Up)));
+        assert_eq!(Setting::ALL[panel.selected], Setting::HudScale);
+        assert_eq!(panel.lines[3], "HUD scale: 100%");
+    }
+}
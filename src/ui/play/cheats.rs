@@ -0,0 +1,91 @@
+//! Codes typed during play, such as `iddqd`, which change the game for testing; they only take
+//! effect while the `debug.cheats` cvar is set.
+
+use screen_13::prelude::*;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Cheat {
+    /// Gives every weapon, and full ammo and health.
+    GiveAll,
+
+    /// Toggles whether the player takes damage.
+    GodMode,
+
+    /// Toggles flying through the level, landing wherever the camera is when toggled off.
+    Noclip,
+}
+
+impl Cheat {
+    /// Every cheat and the code which triggers it.
+    const CODES: [(&'static str, Self); 3] = [
+        ("iddqd", Self::GodMode),
+        ("idkfa", Self::GiveAll),
+        ("idclip", Self::Noclip),
+    ];
+}
+
+/// Watches the characters typed during play for cheat codes.
+#[derive(Debug, Default)]
+pub struct Cheats {
+    /// The latest characters typed, at most as many as the longest code.
+    typed: String,
+}
+
+impl Cheats {
+    fn type_char(&mut self, char: char) -> Option<Cheat> {
+        let max_len = Cheat::CODES
+            .iter()
+            .map(|(code, _)| code.len())
+            .max()
+            .unwrap_or_default();
+
+        self.typed.push(char.to_ascii_lowercase());
+
+        while self.typed.len() > max_len {
+            self.typed.remove(0);
+        }
+
+        let (_, cheat) = Cheat::CODES
+            .iter()
+            .find(|(code, _)| self.typed.ends_with(code))?;
+
+        self.typed.clear();
+
+        Some(*cheat)
+    }
+
+    /// Returns each cheat whose code was completed by the characters received this frame.
+    pub fn update(&mut self, events: &[Event<()>]) -> Vec<Cheat> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::WindowEvent {
+                    event: WindowEvent::ReceivedCharacter(char),
+                    ..
+                } => self.type_char(*char),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn type_codes() {
+        let mut cheats = Cheats::default();
+        let mut type_str = |text: &str| -> Vec<_> {
+            text.chars()
+                .filter_map(|char| cheats.type_char(char))
+                .collect()
+        };
+
+        assert!(type_str("iddq").is_empty());
+        assert_eq!(type_str("d"), [Cheat::GodMode]);
+        assert!(type_str("idk").is_empty());
+        assert_eq!(type_str("wasdIDKFAidclip"), [Cheat::GiveAll, Cheat::Noclip]);
+        assert!(cheats.typed.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+//! A command line shown over play, opened with the grave key, which gets and sets cvars such as
+//! `set debug.show_navmesh true`, opens the level select screen with `levels`, sets the time of day
+//! with `time 18` and toggles the free-fly camera with `freefly`.
+
+use {
+    super::super::level_select::LevelSelect,
+    crate::{config::Config, cvar::Cvars},
+    screen_13::prelude::*,
+    screen_13_fx::BitmapFont,
+    std::{collections::VecDeque, mem::take},
+};
+
+/// A command which changes play itself, instead of a cvar, and so is handled by play.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConsoleCommand {
+    /// Toggle the free-fly camera.
+    FreeFly,
+
+    /// Leave for the level select screen.
+    LevelSelect,
+
+    /// Set the time of day to the given hour.
+    TimeOfDay(f32),
+}
+
+#[derive(Debug, Default)]
+pub struct Console {
+    /// The most recent command which play has not yet handled.
+    command: Option<ConsoleCommand>,
+
+    /// The command being typed.
+    input: String,
+
+    /// Commands and their output, oldest first.
+    lines: VecDeque<String>,
+
+    pub visible: bool,
+}
+
+impl Console {
+    const COLOR: [u8; 3] = [0xc0, 0xff, 0xc0];
+
+    /// Lines beyond this many are removed, oldest first.
+    const MAX_LINES: usize = 12;
+
+    const FREE_FLY_COMMAND: &str = "freefly";
+    const TIME_COMMAND: &str = "time";
+
+    /// Prints the latest lines down the top left of the framebuffer, followed by the input.
+    pub fn draw(
+        &self,
+        font: &BitmapFont,
+        render_graph: &mut RenderGraph,
+        framebuffer_image: impl Into<AnyImageNode>,
+    ) {
+        let framebuffer_image = framebuffer_image.into();
+        let input = format!("> {}_", self.input);
+        let mut y = 0;
+
+        for line in self
+            .lines
+            .iter()
+            .map(String::as_str)
+            .chain([input.as_str()])
+        {
+            let (_, [_, height]) = font.measure(line);
+
+            font.print(
+                render_graph,
+                framebuffer_image,
+                0.0,
+                y as _,
+                Self::COLOR,
+                line,
+            );
+
+            y += height;
+        }
+    }
+
+    fn print(&mut self, text: &str) {
+        for line in text.lines() {
+            if self.lines.len() == Self::MAX_LINES {
+                self.lines.pop_front();
+            }
+
+            self.lines.push_back(line.to_owned());
+        }
+    }
+
+    fn type_char(&mut self, cvars: &mut Cvars, config: &mut Config, char: char) {
+        match char {
+            // Backspace
+            '\u{8}' => {
+                self.input.pop();
+            }
+            '\n' | '\r' => {
+                let command = take(&mut self.input);
+                self.print(&format!("> {command}"));
+
+                if command.trim() == LevelSelect::COMMAND {
+                    self.command = Some(ConsoleCommand::LevelSelect);
+
+                    return;
+                }
+
+                if command.trim() == Self::FREE_FLY_COMMAND {
+                    self.command = Some(ConsoleCommand::FreeFly);
+
+                    return;
+                }
+
+                if let Some(hours) = command
+                    .trim()
+                    .strip_prefix(Self::TIME_COMMAND)
+                    .filter(|hours| hours.is_empty() || hours.starts_with(' '))
+                {
+                    match hours.trim().parse::<f32>() {
+                        Ok(hours) if (0.0..24.0).contains(&hours) => {
+                            self.command = Some(ConsoleCommand::TimeOfDay(hours));
+                            self.print(&format!("time = {hours}"));
+                        }
+                        _ => self.print("Expected an hour from 0 to 24"),
+                    }
+
+                    return;
+                }
+
+                match cvars.execute(config, &command) {
+                    Ok(output) => self.print(&output),
+                    Err(err) => self.print(&format!("{err:#}")),
+                }
+            }
+            // The key which opens the console is not typed into it
+            '`' | '~' => (),
+            char if !char.is_control() => self.input.push(char),
+            _ => (),
+        }
+    }
+
+    /// Returns the command which was most recently run, once.
+    pub fn take_command(&mut self) -> Option<ConsoleCommand> {
+        self.command.take()
+    }
+
+    /// Types the characters received this frame, running the command once enter is pressed.
+    pub fn update(&mut self, cvars: &mut Cvars, config: &mut Config, events: &[Event<()>]) {
+        for event in events {
+            if let Event::WindowEvent {
+                event: WindowEvent::ReceivedCharacter(char),
+                ..
+            } = event
+            {
+                self.type_char(cvars, config, *char);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn run_commands() {
+        let mut config = Config::default();
+        let mut console = Console::default();
+        let mut cvars = Cvars::default();
+
+        for char in "`set render.fov 55\u{8}0\r".chars() {
+            console.type_char(&mut cvars, &mut config, char);
+        }
+
+        assert_eq!(config.fov, 50.0);
+        assert_eq!(console.lines, ["> set render.fov 50", "render.fov = 50"]);
+
+        for char in "get nope\r".chars() {
+            console.type_char(&mut cvars, &mut config, char);
+        }
+
+        assert_eq!(console.lines[3], "Unknown cvar");
+        assert!(console.input.is_empty());
+        assert_eq!(console.take_command(), None);
+
+        for char in "levels\r".chars() {
+            console.type_char(&mut cvars, &mut config, char);
+        }
+
+        assert_eq!(console.take_command(), Some(ConsoleCommand::LevelSelect));
+        assert_eq!(console.take_command(), None);
+
+        for char in "time 25\rtime 18.5\r".chars() {
+            console.type_char(&mut cvars, &mut config, char);
+        }
+
+        assert_eq!(
+            console.take_command(),
+            Some(ConsoleCommand::TimeOfDay(18.5))
+        );
+        assert_eq!(console.lines[6], "Expected an hour from 0 to 24");
+
+        for char in "freefly\r".chars() {
+            console.type_char(&mut cvars, &mut config, char);
+        }
+
+        assert_eq!(console.take_command(), Some(ConsoleCommand::FreeFly));
+    }
+}
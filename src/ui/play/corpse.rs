@@ -0,0 +1,161 @@
+//! The body of the local player once they die: a ragdoll which falls from where they stood, which
+//! the camera follows until the player respawns.
+//!
+//! A skinned body model is posed from the bones of the ragdoll, one joint matrix per bone; any
+//! other model is drawn as a capsule along each bone instead.
+
+use {
+    crate::{
+        art,
+        asset_key::{MaterialKey, ModelKey},
+        game::{
+            physics::{Ragdoll, RagdollJoint},
+            world::{EntityId, Player, World},
+        },
+        render::model::{Material, Model, ModelBuffer, ModelInstance},
+    },
+    glam::{vec3, Mat4, Quat, Vec3},
+};
+
+pub struct Corpse {
+    ragdoll: EntityId,
+
+    /// Seconds since the player died.
+    secs: f32,
+
+    /// The capsule drawn along each bone, in the order of [`Ragdoll::bone_matrices`], or the one
+    /// skinned body when `skinned` is set.
+    segments: Vec<ModelInstance>,
+
+    skinned: bool,
+}
+
+impl Corpse {
+    pub const MATERIAL: MaterialKey = art::MATERIAL_DARK_GREY;
+    pub const MODEL: ModelKey = art::MODEL_PROP_CAPSULE;
+
+    /// Index of the joint the camera follows.
+    const HEAD: usize = 2;
+
+    /// Joints of a standing body, relative to where it stands and facing -Z: the pelvis, chest and
+    /// head, followed by each foot, along with the joint each one hangs from.
+    const JOINTS: [(Vec3, Option<usize>); 5] = [
+        (vec3(0.0, 0.9, 0.0), None),
+        (vec3(0.0, 1.3, 0.0), Some(0)),
+        (Player::EYE_OFFSET, Some(1)),
+        (vec3(-0.15, 0.0, 0.0), Some(0)),
+        (vec3(0.15, 0.0, 0.0), Some(0)),
+    ];
+
+    /// Radius, in meters, of the sphere at each joint.
+    const RADIUS: f32 = 0.15;
+
+    /// Seconds the camera follows the body before the player respawns.
+    const SECS: f32 = 3.0;
+
+    /// Radians the body leans forward as it dies, so that it topples instead of folding in place.
+    const TOPPLE_ANGLE: f32 = 0.2;
+
+    /// Replaces a player who died with a ragdoll in the same pose.
+    pub fn spawn(
+        world: &mut World,
+        model_buf: &mut ModelBuffer,
+        (model, material): (Model, Material),
+        player: &Player,
+    ) -> Self {
+        let rotation = Quat::from_rotation_y(player.yaw.to_radians())
+            * Quat::from_rotation_x(-Self::TOPPLE_ANGLE);
+        let position = player.position();
+        let joints = Self::JOINTS.map(|(bind_position, parent)| RagdollJoint {
+            bind_position,
+            parent,
+            position: position + rotation * bind_position,
+        });
+        let ragdoll = world.spawn_ragdoll(Ragdoll::new(&joints, Self::RADIUS, Vec3::ZERO));
+
+        // Bone matrices are in world space, so a skinned body stays at the origin
+        let body = model_buf.insert_model_instance(model, &[material], Vec3::ZERO, Quat::IDENTITY);
+        let bone_matrices = world
+            .ragdoll(ragdoll)
+            .unwrap()
+            .bone_matrices()
+            .collect::<Vec<_>>();
+        let skinned = model_buf.set_model_instance_pose(body, &bone_matrices);
+        let mut segments = vec![body];
+
+        if !skinned {
+            segments.extend(
+                bone_matrices.iter().skip(1).map(|_| {
+                    model_buf.insert_model_instance(model, &[material], position, rotation)
+                }),
+            );
+        }
+
+        let mut corpse = Self {
+            ragdoll,
+            secs: 0.0,
+            segments,
+            skinned,
+        };
+        corpse.pose(world, model_buf);
+
+        corpse
+    }
+
+    /// Returns where the camera looks from while following the body.
+    pub fn eye_position(&self, world: &World) -> Option<Vec3> {
+        world
+            .ragdoll(self.ragdoll)
+            .map(|ragdoll| ragdoll.joints()[Self::HEAD])
+    }
+
+    /// Poses the skinned body, or moves each capsule along the bone it is drawn for; capsules are
+    /// modeled along -Z.
+    fn pose(&self, world: &World, model_buf: &mut ModelBuffer) {
+        let Some(ragdoll) = world.ragdoll(self.ragdoll) else {
+            return;
+        };
+
+        if self.skinned {
+            let bone_matrices = ragdoll.bone_matrices().collect::<Vec<Mat4>>();
+            model_buf.set_model_instance_pose(self.segments[0], &bone_matrices);
+
+            return;
+        }
+
+        let bones = Self::JOINTS
+            .iter()
+            .filter_map(|&(child, parent)| Some((Self::JOINTS[parent?].0, child)));
+
+        for ((matrix, (parent, child)), &segment) in
+            ragdoll.bone_matrices().zip(bones).zip(&self.segments)
+        {
+            let (_, rotation, _) = matrix.to_scale_rotation_translation();
+            let bind_direction = (child - parent).normalize();
+
+            model_buf.set_model_instance_transform(
+                segment,
+                matrix.transform_point3((parent + child) * 0.5),
+                rotation * Quat::from_rotation_arc(Vec3::NEG_Z, bind_direction),
+            );
+        }
+    }
+
+    /// Removes the body, such as once the player respawns.
+    pub fn remove(self, world: &mut World, model_buf: &mut ModelBuffer) {
+        world.despawn(self.ragdoll);
+
+        for segment in self.segments {
+            model_buf.remove_model_instance(segment);
+        }
+    }
+
+    /// Poses the capsules after the ragdoll has been stepped, returning `true` once the player
+    /// should respawn.
+    pub fn update(&mut self, world: &World, model_buf: &mut ModelBuffer, dt: f32) -> bool {
+        self.pose(world, model_buf);
+        self.secs += dt;
+
+        self.secs >= Self::SECS
+    }
+}
@@ -0,0 +1,53 @@
+//! Marks left on the level by impacts, such as bullet holes and scorch marks, each drawn as a small
+//! quad with the decal material which the impact table lists for the surface.
+
+use {
+    crate::{
+        art,
+        asset_key::ModelKey,
+        render::model::{Material, Model, ModelBuffer, ModelInstance},
+    },
+    glam::{Quat, Vec3},
+    std::collections::VecDeque,
+};
+
+/// The decals left by impacts, of which only the most recent are kept.
+#[derive(Default)]
+pub struct Decals {
+    /// Oldest first.
+    model_instances: VecDeque<ModelInstance>,
+}
+
+impl Decals {
+    /// Leaving another decal beyond this many removes the oldest.
+    const MAX_COUNT: usize = 64;
+
+    /// A quad a quarter of a meter wide, facing +Z.
+    pub const MODEL: ModelKey = art::MODEL_PROP_DECAL;
+
+    /// Meters each decal is lifted off the surface so that it does not fight for depth with it.
+    const OFFSET: f32 = 0.005;
+
+    /// Leaves a decal on the surface at `position`, facing along its normal.
+    pub fn spawn(
+        &mut self,
+        model_buf: &mut ModelBuffer,
+        model: Model,
+        material: Material,
+        position: Vec3,
+        normal: Vec3,
+    ) {
+        if self.model_instances.len() == Self::MAX_COUNT {
+            let oldest = self.model_instances.pop_front().unwrap();
+            model_buf.remove_model_instance(oldest);
+        }
+
+        self.model_instances
+            .push_back(model_buf.insert_model_instance(
+                model,
+                &[material],
+                position + normal * Self::OFFSET,
+                Quat::from_rotation_arc(Vec3::Z, normal),
+            ));
+    }
+}
@@ -0,0 +1,513 @@
+use {
+    super::{
+        super::{error::ErrorScreen, CursorStyle, DrawContext, Ui, UpdateContext},
+        Play,
+    },
+    crate::{
+        asset_key::SceneKey,
+        env::current_exe_dir,
+        level::placement::{self, Placement, Placements},
+        render::{
+            camera::{project, Camera},
+            model::{ModelInstance, RenderLayers},
+        },
+        ui::captions::Speaker,
+    },
+    glam::{uvec2, vec2, Quat, UVec2, Vec2, Vec3},
+    screen_13::prelude::*,
+    std::{
+        f32::consts::TAU,
+        fmt::{Display, Formatter, Result as FmtResult},
+        fs::{create_dir_all, read_to_string, write},
+        io::{Error, ErrorKind},
+        path::{Path, PathBuf},
+    },
+};
+
+/// A scene ref with an id, whose model instance the editor may move.
+#[derive(Clone, Debug)]
+pub struct EditorRef {
+    id: String,
+
+    /// Set once the editor has moved this ref, so that only changed refs are exported.
+    modified: bool,
+
+    position: Vec3,
+    rotation: Quat,
+}
+
+impl EditorRef {
+    pub fn new(id: impl Into<String>, position: Vec3, rotation: Quat) -> Self {
+        Self {
+            id: id.into(),
+            modified: false,
+            position,
+            rotation,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum Axis {
+    X,
+    #[default]
+    Y,
+    Z,
+}
+
+impl Axis {
+    const ALL: [Self; 3] = [Self::X, Self::Y, Self::Z];
+
+    fn color(self) -> [u8; 3] {
+        match self {
+            Self::X => [0xff, 0x40, 0x40],
+            Self::Y => [0x40, 0xff, 0x40],
+            Self::Z => [0x40, 0x80, 0xff],
+        }
+    }
+
+    fn direction(self) -> Vec3 {
+        match self {
+            Self::X => Vec3::X,
+            Self::Y => Vec3::Y,
+            Self::Z => Vec3::Z,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum Gizmo {
+    Rotate,
+    #[default]
+    Translate,
+}
+
+impl Display for Gizmo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Self::Rotate => "Rotate",
+            Self::Translate => "Translate",
+        })
+    }
+}
+
+/// Moves the props of a level while the game is paused, toggled using F7 from [`Play`].
+///
+/// The camera flies freely while the right mouse button is held; clicking selects the model
+/// instance under the cursor, using the picking of the model buffer. The selected prop moves or
+/// rotates along the highlighted gizmo axis using the arrow keys, and Ctrl+S writes the moved refs
+/// to the placements of the scene, which `build.rs` applies when the scene is next baked.
+///
+/// Model instances of [`RenderLayers::EDITOR`] are only drawn while editing.
+pub struct Editor {
+    axis: Axis,
+    camera: Camera,
+
+    /// Framebuffer pixel under the mouse cursor, which is picked while drawing.
+    cursor_position: Option<UVec2>,
+
+    gizmo: Gizmo,
+    play: Box<Play>,
+    selected: Option<ModelInstance>,
+}
+
+impl Editor {
+    /// Length, in pixels, of the gizmo axes and radius of its rings.
+    const GIZMO_SIZE: f32 = 40.0;
+
+    const LOOK_SENSITIVITY: f32 = 100.0;
+
+    /// Seconds the notification of an export is shown.
+    const NOTIFICATION_SECS: f32 = 3.0;
+
+    /// Rotation of each step, in degrees.
+    const ROTATE_STEP: f32 = 15.0;
+
+    /// Camera speed, in meters per second, which is faster while holding shift.
+    const SPEED: f32 = 5.0;
+    const SPEED_FAST: f32 = 20.0;
+
+    /// Distance of each step, in meters, which is smaller while holding shift.
+    const TRANSLATE_STEP: f32 = 0.25;
+    const TRANSLATE_STEP_FINE: f32 = 0.01;
+
+    pub fn new(mut play: Box<Play>) -> Self {
+        info!("Editing level");
+
+        let layers = play.model_buf.layers();
+        play.model_buf.set_layers(layers | RenderLayers::EDITOR);
+
+        Self {
+            axis: Default::default(),
+            camera: play.camera,
+            cursor_position: None,
+            gizmo: Default::default(),
+            play,
+            selected: None,
+        }
+    }
+
+    fn draw_gizmo(&mut self, framebuffer_size: Vec2) {
+        let Some(model_instance) = self.selected else {
+            return;
+        };
+        let Some(editor_ref) = self.play.editor_refs.get(&model_instance) else {
+            return;
+        };
+        let primitives = &mut self.play.primitives;
+
+        let projection_view = self
+            .camera
+            .projection_view(self.camera.aspect_ratio, Camera::Z_NEAR);
+        let to_screen = |position| project(projection_view, framebuffer_size, position);
+        let Some(center) = to_screen(editor_ref.position) else {
+            return;
+        };
+
+        // Gizmos keep the same size on screen at any distance
+        let scale = Self::GIZMO_SIZE / framebuffer_size.y
+            * editor_ref.position.distance(self.camera.position)
+            * (self.camera.fov_y.to_radians() * 0.5).tan()
+            * 2.0;
+
+        for axis in Axis::ALL {
+            let [r, g, b] = axis.color();
+            let (alpha, width) = if axis == self.axis {
+                (0xff, 3.0)
+            } else {
+                (0x80, 1.0)
+            };
+            let color = [r, g, b, alpha];
+
+            match self.gizmo {
+                Gizmo::Translate => {
+                    if let Some(end) = to_screen(editor_ref.position + axis.direction() * scale) {
+                        primitives.draw_line(center, end, width, color);
+                    }
+                }
+                Gizmo::Rotate => {
+                    const SEGMENTS: usize = 32;
+
+                    let rotation = Quat::from_rotation_arc(Vec3::Z, axis.direction());
+                    let points = (0..=SEGMENTS)
+                        .map(|idx| {
+                            let angle = idx as f32 / SEGMENTS as f32 * TAU;
+                            let offset = rotation * Vec3::new(angle.cos(), angle.sin(), 0.0);
+
+                            to_screen(editor_ref.position + offset * scale)
+                        })
+                        .collect::<Box<_>>();
+
+                    for segment in points.windows(2) {
+                        if let [Some(start), Some(end)] = *segment {
+                            primitives.draw_line(start, end, width, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes every moved ref to the placements of the scene in the art directory.
+    fn export(&self) -> Result<PathBuf, Error> {
+        let path = placements_path(self.play.scene);
+        let mut placements = if path.exists() {
+            toml::from_str(&read_to_string(&path)?)
+                .map_err(|_| Error::from(ErrorKind::InvalidData))?
+        } else {
+            Placements::default()
+        };
+
+        for editor_ref in self
+            .play
+            .editor_refs
+            .values()
+            .filter(|editor_ref| editor_ref.modified)
+        {
+            placements.insert(Placement {
+                id: editor_ref.id.clone(),
+                position: editor_ref.position.to_array(),
+                rotation: editor_ref.rotation.to_array(),
+            });
+        }
+
+        info!("Writing {}", path.display());
+
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+
+        write(
+            &path,
+            toml::to_string(&placements).map_err(|_| Error::from(ErrorKind::InvalidData))?,
+        )?;
+
+        Ok(path)
+    }
+
+    fn update_camera(&mut self, ui: &UpdateContext) {
+        if ui.mouse.is_down(MouseButton::Right) {
+            let look_delta = ui.mouse_look_delta() * Self::LOOK_SENSITIVITY;
+
+            self.camera.yaw = (self.camera.yaw - look_delta.x) % 360.0;
+            self.camera.pitch = (self.camera.pitch - look_delta.y).clamp(-89.0, 89.0);
+        }
+
+        let rotation = Quat::from_rotation_y(self.camera.yaw.to_radians())
+            * Quat::from_rotation_x(self.camera.pitch.to_radians());
+        let mut direction = Vec3::ZERO;
+
+        for (key, key_direction) in [
+            (VirtualKeyCode::W, rotation * -Vec3::Z),
+            (VirtualKeyCode::S, rotation * Vec3::Z),
+            (VirtualKeyCode::D, rotation * Vec3::X),
+            (VirtualKeyCode::A, rotation * -Vec3::X),
+            (VirtualKeyCode::E, Vec3::Y),
+            (VirtualKeyCode::Q, -Vec3::Y),
+        ] {
+            if ui.keyboard.is_down(key) {
+                direction += key_direction;
+            }
+        }
+
+        let speed = if ui.keyboard.is_down(VirtualKeyCode::LShift) {
+            Self::SPEED_FAST
+        } else {
+            Self::SPEED
+        };
+
+        self.camera.position += direction.normalize_or_zero() * speed * ui.dt;
+    }
+
+    fn update_selection(&mut self, ui: &mut UpdateContext) {
+        if !ui.mouse.is_down(MouseButton::Right) {
+            let (x, y) = ui.mouse.position();
+
+            self.cursor_position = Some(uvec2(
+                (x / ui.framebuffer_scale) as _,
+                (y / ui.framebuffer_scale) as _,
+            ));
+        } else {
+            self.cursor_position = None;
+        }
+
+        // Only refs with an id can be exported, so other model instances are not selectable
+        if ui.mouse.is_pressed(MouseButton::Left) {
+            self.selected = self
+                .play
+                .model_buf
+                .hovered_instance()
+                .filter(|model_instance| self.play.editor_refs.contains_key(model_instance));
+
+            if let Some(editor_ref) = self
+                .selected
+                .and_then(|model_instance| self.play.editor_refs.get(&model_instance))
+            {
+                debug!("Selected {}", editor_ref.id);
+            }
+        }
+
+        for (key, gizmo) in [
+            (VirtualKeyCode::G, Gizmo::Translate),
+            (VirtualKeyCode::R, Gizmo::Rotate),
+        ] {
+            if ui.keyboard.is_pressed(&key) {
+                self.gizmo = gizmo;
+            }
+        }
+
+        for (key, axis) in [
+            (VirtualKeyCode::X, Axis::X),
+            (VirtualKeyCode::Y, Axis::Y),
+            (VirtualKeyCode::Z, Axis::Z),
+        ] {
+            if ui.keyboard.is_pressed(&key) {
+                self.axis = axis;
+            }
+        }
+
+        let mut steps = 0.0;
+
+        if ui.keyboard.is_pressed(&VirtualKeyCode::Right) {
+            steps += 1.0;
+        }
+
+        if ui.keyboard.is_pressed(&VirtualKeyCode::Left) {
+            steps -= 1.0;
+        }
+
+        if steps == 0.0 {
+            return;
+        }
+
+        let Some(model_instance) = self.selected else {
+            return;
+        };
+        let Some(editor_ref) = self.play.editor_refs.get_mut(&model_instance) else {
+            return;
+        };
+
+        let is_fine = ui.keyboard.is_down(VirtualKeyCode::LShift);
+
+        match self.gizmo {
+            Gizmo::Rotate => {
+                let angle = if is_fine {
+                    Self::ROTATE_STEP / 3.0
+                } else {
+                    Self::ROTATE_STEP
+                };
+
+                editor_ref.rotation =
+                    (Quat::from_axis_angle(self.axis.direction(), (angle * steps).to_radians())
+                        * editor_ref.rotation)
+                        .normalize();
+            }
+            Gizmo::Translate => {
+                let distance = if is_fine {
+                    Self::TRANSLATE_STEP_FINE
+                } else {
+                    Self::TRANSLATE_STEP
+                };
+
+                editor_ref.position += self.axis.direction() * distance * steps;
+            }
+        }
+
+        editor_ref.modified = true;
+
+        self.play.model_buf.set_model_instance_transform(
+            model_instance,
+            editor_ref.position,
+            editor_ref.rotation,
+        );
+    }
+}
+
+impl Ui for Editor {
+    fn draw(&mut self, frame: DrawContext) {
+        let framebuffer_info = frame.render_graph.node_info(frame.framebuffer_image);
+        let framebuffer_size = vec2(framebuffer_info.width as _, framebuffer_info.height as _);
+
+        self.camera.aspect_ratio = framebuffer_size.x / framebuffer_size.y;
+
+        self.play.model_buf.set_pick_position(self.cursor_position);
+        if let Err(err) = self.play.model_buf.record(
+            frame.render_graph,
+            frame.framebuffer_image,
+            &mut self.camera,
+        ) {
+            self.play.err = Some(anyhow::Error::new(err).context("Recording models"));
+
+            return;
+        }
+
+        self.draw_gizmo(framebuffer_size);
+
+        let font = &self.play.content.dare_font;
+        let (_, [_, line_height]) = font.measure("Editor");
+        let selected = self
+            .selected
+            .and_then(|model_instance| self.play.editor_refs.get(&model_instance))
+            .map(|editor_ref| editor_ref.id.as_str())
+            .unwrap_or("Nothing selected");
+
+        for (line, text) in [
+            format!(
+                "Editor: {} {:?} (G/R, X/Y/Z, Left/Right)",
+                self.gizmo, self.axis
+            ),
+            selected.to_owned(),
+            "Ctrl+S: export, F7: play".to_owned(),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            font.print(
+                frame.render_graph,
+                frame.framebuffer_image,
+                0.0,
+                (line * line_height as usize) as _,
+                [0xff, 0xff, 0x00],
+                text,
+            );
+        }
+
+        frame
+            .captions
+            .print(font, frame.render_graph, frame.framebuffer_image);
+
+        if let Err(err) = self
+            .play
+            .primitives
+            .record(frame.render_graph, frame.framebuffer_image)
+        {
+            self.play.err = Some(anyhow::Error::new(err).context("Recording primitives"));
+        }
+    }
+
+    fn update(mut self: Box<Self>, mut ui: UpdateContext) -> Option<Box<dyn Ui>> {
+        if let Some(err) = self.play.err.take() {
+            return Some(Box::new(ErrorScreen::new(&self.play.device, err)));
+        }
+
+        if ui.keyboard.is_pressed(&VirtualKeyCode::F7) {
+            info!("Playing level");
+
+            *ui.cursor = None;
+
+            let layers = self.play.model_buf.layers();
+            self.play
+                .model_buf
+                .set_layers(layers - RenderLayers::EDITOR);
+
+            return Some(self.play);
+        }
+
+        *ui.cursor = (!ui.mouse.is_down(MouseButton::Right)).then_some(CursorStyle::Pointer);
+
+        self.update_camera(&ui);
+        self.update_selection(&mut ui);
+
+        let is_ctrl_held = ui.keyboard.is_held(&VirtualKeyCode::LControl)
+            || ui.keyboard.is_held(&VirtualKeyCode::RControl);
+
+        if is_ctrl_held && ui.keyboard.is_pressed(&VirtualKeyCode::S) {
+            let text = match self.export() {
+                Ok(path) => format!("Exported {}", path.display()),
+                Err(err) => {
+                    warn!("Unable to export placements: {err}");
+
+                    format!("Unable to export placements: {err}")
+                }
+            };
+
+            ui.captions
+                .push_text(Speaker::Narrator, text, Self::NOTIFICATION_SECS);
+        }
+
+        Some(self)
+    }
+}
+
+/// Returns the art directory of the checkout which the game was built from: the first `art`
+/// directory holding `pak.toml` above the executable, such as `target/debug`, or else an `art`
+/// directory next to the executable.
+fn art_dir() -> PathBuf {
+    let exe_dir = current_exe_dir();
+
+    exe_dir
+        .ancestors()
+        .map(|dir| dir.join("art"))
+        .find(|dir| dir.join("pak.toml").is_file())
+        .unwrap_or_else(|| exe_dir.join("art"))
+}
+
+/// Returns the file, in the art directory, holding the placements of the given scene.
+fn placements_path(scene: SceneKey) -> PathBuf {
+    let scene = Path::new(scene.as_str());
+
+    art_dir()
+        .join(scene.parent().unwrap_or(Path::new("")))
+        .join(placement::DIR_NAME)
+        .join(scene.with_extension("toml").file_name().unwrap_or_default())
+}
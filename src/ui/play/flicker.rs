@@ -0,0 +1,57 @@
+//! Lights which flicker, such as a failing fluorescent tube, by dimming the emissive material of a
+//! scene ref named with a `flicker` property, such as `light_tube(flicker=12)`.
+
+use {
+    crate::render::model::{Material, ModelBuffer},
+    glam::Vec3,
+};
+
+pub struct Flicker {
+    color: Vec3,
+
+    /// Emissive intensity of the material while it is lit.
+    intensity: f32,
+
+    lit: bool,
+    material: Material,
+
+    /// Times each second the light may go dark.
+    rate: f32,
+
+    /// Offsets the pattern so that lights with the same rate flicker differently.
+    seed: u32,
+}
+
+impl Flicker {
+    /// Multiplies the emissive intensity of the material while it is dark.
+    const DIM_SCALE: f32 = 0.15;
+
+    pub fn new(model_buf: &ModelBuffer, material: Material, rate: f32, seed: u32) -> Self {
+        let (color, intensity) = model_buf.material_emissive(material);
+
+        Self {
+            color,
+            intensity,
+            lit: true,
+            material,
+            rate,
+            seed,
+        }
+    }
+
+    /// Dims or lights the material for the given level time, only setting its emissive factors when
+    /// it changes.
+    pub fn update(&mut self, model_buf: &mut ModelBuffer, secs: f32) {
+        // One step in eight is dark, chosen by hashing the step
+        let step = ((secs * self.rate) as u32).wrapping_add(self.seed);
+        let lit = step.wrapping_mul(0x9e37_79b9) >> 29 != 0;
+
+        if lit == self.lit {
+            return;
+        }
+
+        let scale = if lit { 1.0 } else { Self::DIM_SCALE };
+        model_buf.set_material_emissive(self.material, self.color, self.intensity * scale);
+        self.lit = lit;
+    }
+}
@@ -0,0 +1,173 @@
+//! Text shown at positions in the world, such as damage numbers over whatever a detonation hurt
+//! and the name of the pickup the player looks at.
+//!
+//! Each label is projected from the world onto the framebuffer after the models are drawn, so
+//! walls do not hide it. Labels shrink with distance so that far away ones do not crowd the view.
+
+use {
+    crate::render::camera::{project, Camera},
+    glam::{vec2, Vec3},
+    screen_13::prelude::*,
+    screen_13_fx::BitmapFont,
+};
+
+struct Label {
+    /// Seconds since the label appeared.
+    age: f32,
+
+    color: [u8; 3],
+
+    /// Seconds the label lasts, rising and fading out.
+    lifetime: f32,
+
+    position: Vec3,
+    text: String,
+}
+
+/// Damage numbers and pickup names which rise and fade out, and the label of what the player may
+/// interact with.
+#[derive(Default)]
+pub struct FloatingText {
+    /// Where and what the label of the highlighted object is, which stays until it is replaced.
+    interaction: Option<(Vec3, String)>,
+
+    labels: Vec<Label>,
+}
+
+impl FloatingText {
+    const DAMAGE_COLOR: [u8; 3] = [0xff, 0x40, 0x40];
+    const DAMAGE_SECS: f32 = 1.0;
+    const INTERACTION_COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+
+    /// Scale of labels as near to the camera as possible, so that they do not cover the view.
+    const MAX_SCALE: f32 = 2.0;
+
+    /// Scale of labels far from the camera, so that they stay legible.
+    const MIN_SCALE: f32 = 0.5;
+
+    const PICKUP_COLOR: [u8; 3] = [0xff, 0xd7, 0x00];
+    const PICKUP_SECS: f32 = 1.5;
+
+    /// Distance, in meters, at which labels are drawn at the size of the font.
+    const REFERENCE_DISTANCE: f32 = 4.0;
+
+    /// Meters each label rises per second while it fades out.
+    const RISE_SPEED: f32 = 0.75;
+
+    /// Adds a number showing damage dealt at the given position.
+    pub fn damage(&mut self, position: Vec3, amount: u32) {
+        self.push(
+            position,
+            amount.to_string(),
+            Self::DAMAGE_COLOR,
+            Self::DAMAGE_SECS,
+        );
+    }
+
+    /// Prints each label centered on its projected position, scaled by its distance from the
+    /// camera.
+    pub fn draw(
+        &self,
+        font: &BitmapFont,
+        render_graph: &mut RenderGraph,
+        framebuffer_image: impl Into<AnyImageNode>,
+        camera: &Camera,
+    ) {
+        let framebuffer_image = framebuffer_image.into();
+        let framebuffer_info = render_graph.node_info(framebuffer_image);
+        let framebuffer_size = vec2(framebuffer_info.width as _, framebuffer_info.height as _);
+        let projection_view = camera.projection_view(camera.aspect_ratio, Camera::Z_NEAR);
+        let interaction = self
+            .interaction
+            .iter()
+            .map(|(position, text)| (*position, text.as_str(), Self::INTERACTION_COLOR, 1.0));
+        let labels = self.labels.iter().map(|label| {
+            (
+                label.position + Vec3::Y * label.age * Self::RISE_SPEED,
+                label.text.as_str(),
+                label.color,
+                1.0 - label.age / label.lifetime,
+            )
+        });
+
+        for (position, text, [r, g, b], opacity) in interaction.chain(labels) {
+            let Some(center) = project(projection_view, framebuffer_size, position) else {
+                continue;
+            };
+
+            let scale = Self::scale(camera.position.distance(position));
+            let (_, [width, height]) = font.measure(text);
+
+            font.print_scale(
+                render_graph,
+                framebuffer_image,
+                center.x - width as f32 * scale * 0.5,
+                center.y - height as f32 * scale * 0.5,
+                [r, g, b, (opacity * 255.0) as u8],
+                text,
+                scale,
+            );
+        }
+    }
+
+    /// Adds the name of a collected pickup at the position it was collected from.
+    pub fn pickup(&mut self, position: Vec3, name: impl Into<String>) {
+        self.push(position, name, Self::PICKUP_COLOR, Self::PICKUP_SECS);
+    }
+
+    fn push(&mut self, position: Vec3, text: impl Into<String>, color: [u8; 3], lifetime: f32) {
+        self.labels.push(Label {
+            age: 0.0,
+            color,
+            lifetime,
+            position,
+            text: text.into(),
+        });
+    }
+
+    /// Returns the scale of labels at the given distance, in meters, from the camera.
+    fn scale(distance: f32) -> f32 {
+        (Self::REFERENCE_DISTANCE / distance.max(f32::EPSILON))
+            .clamp(Self::MIN_SCALE, Self::MAX_SCALE)
+    }
+
+    /// Sets the label of what the player may interact with, such as the pickup they look at, or
+    /// removes it.
+    pub fn set_interaction(&mut self, interaction: Option<(Vec3, String)>) {
+        self.interaction = interaction;
+    }
+
+    /// Ages each label by `dt` seconds, removing those which have faded out.
+    pub fn update(&mut self, dt: f32) {
+        self.labels.retain_mut(|label| {
+            label.age += dt;
+            label.age < label.lifetime
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn labels_fade_out() {
+        let mut floating_text = FloatingText::default();
+        floating_text.damage(Vec3::ZERO, 25);
+        floating_text.pickup(Vec3::ONE, "Shotgun");
+        floating_text.set_interaction(Some((Vec3::ONE, "Shotgun".to_owned())));
+        floating_text.update(FloatingText::DAMAGE_SECS);
+
+        assert_eq!(floating_text.labels.len(), 1);
+        assert_eq!(floating_text.labels[0].text, "Shotgun");
+
+        floating_text.update(FloatingText::PICKUP_SECS);
+
+        assert!(floating_text.labels.is_empty());
+        assert!(floating_text.interaction.is_some());
+
+        assert_eq!(FloatingText::scale(FloatingText::REFERENCE_DISTANCE), 1.0);
+        assert_eq!(FloatingText::scale(0.0), FloatingText::MAX_SCALE);
+        assert_eq!(FloatingText::scale(1_000.0), FloatingText::MIN_SCALE);
+    }
+}
@@ -0,0 +1,91 @@
+//! The objectives of the level which the player has yet to complete, listed down the right of the
+//! HUD in the order they were added.
+
+use {
+    crate::game::objectives::{ObjectiveInfo, Objectives},
+    screen_13::prelude::*,
+    screen_13_fx::BitmapFont,
+};
+
+#[derive(Default)]
+pub struct ObjectiveList {
+    /// The text of each active objective, as of the latest update.
+    lines: Vec<String>,
+}
+
+impl ObjectiveList {
+    const COLOR: [u8; 3] = [0xff, 0xff, 0xff];
+    const HEADING: &str = "Objectives";
+    const HEADING_COLOR: [u8; 3] = [0xff, 0xd7, 0x00];
+
+    /// Prints the heading and each objective right-aligned, starting at `top`, unless there are
+    /// no active objectives.
+    pub fn draw(
+        &self,
+        font: &BitmapFont,
+        render_graph: &mut RenderGraph,
+        framebuffer_image: impl Into<AnyImageNode>,
+        top: u32,
+    ) {
+        if self.lines.is_empty() {
+            return;
+        }
+
+        let framebuffer_image = framebuffer_image.into();
+        let framebuffer_info = render_graph.node_info(framebuffer_image);
+        let heading = (Self::HEADING, Self::HEADING_COLOR);
+        let lines = self.lines.iter().map(|line| (line.as_str(), Self::COLOR));
+        let mut y = top;
+
+        for (text, color) in [heading].into_iter().chain(lines) {
+            let (_, [width, height]) = font.measure(text);
+
+            font.print(
+                render_graph,
+                framebuffer_image,
+                framebuffer_info.width.saturating_sub(width) as _,
+                y as _,
+                color,
+                text,
+            );
+
+            y += height;
+        }
+    }
+
+    /// Lists the text of each active objective.
+    pub fn update(&mut self, objectives: &Objectives, infos: &[ObjectiveInfo]) {
+        self.lines.clear();
+        self.lines.extend(
+            objectives
+                .active()
+                .map(|id| ObjectiveInfo::text(infos, id).to_owned()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn list_active_objectives() {
+        let infos = [ObjectiveInfo {
+            id: "find_key".to_owned(),
+            start: true,
+            text: "Find the red key".to_owned(),
+        }];
+        let mut objectives = Objectives::new(&infos);
+        objectives.add("open_vault");
+
+        let mut objective_list = ObjectiveList::default();
+        objective_list.update(&objectives, &infos);
+
+        assert_eq!(objective_list.lines, ["Find the red key", "open_vault"]);
+
+        objectives.complete("find_key");
+        objective_list.update(&objectives, &infos);
+
+        assert_eq!(objective_list.lines, ["open_vault"]);
+    }
+}
@@ -0,0 +1,201 @@
+//! Trails behind projectiles, flashes where they detonate and particles thrown off by impacts.
+//!
+//! Each puff of a trail, flash and particle is a circle projected from the world onto the
+//! framebuffer. They are drawn over the level, so walls do not hide them, and flashes do not light
+//! anything.
+
+use {
+    crate::{
+        game::impact_table::ParticleEffect,
+        render::{camera::Camera, primitives::PrimitiveBuffer},
+    },
+    glam::{vec3, Vec2, Vec3},
+};
+
+struct Sprite {
+    /// Seconds since the sprite appeared.
+    age: f32,
+
+    color: [u8; 3],
+
+    /// Meters per second squared the sprite falls at; zero for sprites which hang in the air.
+    gravity: f32,
+
+    /// Seconds the sprite lasts, fading out and growing to `radius`.
+    lifetime: f32,
+
+    /// Opacity, from zero to one, when the sprite appears.
+    opacity: f32,
+
+    position: Vec3,
+
+    /// Radius, in meters, once fully grown.
+    radius: f32,
+
+    /// Meters per second.
+    velocity: Vec3,
+}
+
+/// The trails and flashes of every projectile, and the particles of every impact.
+pub struct ProjectileFx {
+    seed: u32,
+    sprites: Vec<Sprite>,
+}
+
+impl ProjectileFx {
+    const FLASH_SECS: f32 = 0.3;
+    const GRAVITY: f32 = 9.8;
+    const PUFF_RADIUS: f32 = 0.15;
+    const PUFF_SECS: f32 = 0.5;
+
+    /// Throws the sprites of a particle effect off a surface, spread around its normal.
+    pub fn burst(&mut self, position: Vec3, normal: Vec3, effect: &ParticleEffect) {
+        for _ in 0..effect.count {
+            let spread =
+                vec3(self.next_random(), self.next_random(), self.next_random()) * 2.0 - 1.0;
+            let direction = (normal + spread * 0.75).try_normalize().unwrap_or(normal);
+            let speed = effect.speed * (0.5 + 0.5 * self.next_random());
+
+            self.sprites.push(Sprite {
+                age: 0.0,
+                color: effect.color,
+                gravity: Self::GRAVITY,
+                lifetime: effect.lifetime,
+                opacity: 1.0,
+                position,
+                radius: effect.radius,
+                velocity: direction * speed,
+            });
+        }
+    }
+
+    /// Draws each sprite, largest first so that trails show through flashes.
+    pub fn draw(&self, primitives: &mut PrimitiveBuffer, camera: &Camera, framebuffer_size: Vec2) {
+        let projection_view = camera.projection_view(camera.aspect_ratio, Camera::Z_NEAR);
+        let pixels_per_meter = framebuffer_size.y * 0.5 / (camera.fov_y.to_radians() * 0.5).tan();
+
+        for sprite in &self.sprites {
+            let clip = projection_view * sprite.position.extend(1.0);
+
+            // Behind the camera
+            if clip.w <= Camera::Z_NEAR {
+                continue;
+            }
+
+            let t = sprite.age / sprite.lifetime;
+            let center = (clip.truncate().truncate() / clip.w * 0.5 + 0.5) * framebuffer_size;
+            let radius = sprite.radius * (0.5 + 0.5 * t) * pixels_per_meter / clip.w;
+            let [r, g, b] = sprite.color;
+
+            let alpha = (1.0 - t) * sprite.opacity * 255.0;
+
+            primitives.draw_circle(center, radius, [r, g, b, alpha as u8]);
+        }
+    }
+
+    /// Adds a flash of light, such as where a projectile detonated, which `intensity` (from zero
+    /// to one) dims for players sensitive to flashing light.
+    pub fn flash(&mut self, position: Vec3, color: [u8; 3], radius: f32, intensity: f32) {
+        // Flashes are drawn before puffs, which are much smaller
+        self.sprites.insert(
+            0,
+            Sprite {
+                age: 0.0,
+                color,
+                gravity: 0.0,
+                lifetime: Self::FLASH_SECS,
+                opacity: intensity,
+                position,
+                radius,
+                velocity: Vec3::ZERO,
+            },
+        );
+    }
+
+    /// Adds one puff of the trail behind a projectile.
+    pub fn puff(&mut self, position: Vec3, color: [u8; 3]) {
+        self.sprites.push(Sprite {
+            age: 0.0,
+            color,
+            gravity: 0.0,
+            lifetime: Self::PUFF_SECS,
+            opacity: 1.0,
+            position,
+            radius: Self::PUFF_RADIUS,
+            velocity: Vec3::ZERO,
+        });
+    }
+
+    /// Ages and moves each sprite by `dt` seconds, removing those which have faded out.
+    pub fn update(&mut self, dt: f32) {
+        self.sprites.retain_mut(|sprite| {
+            sprite.age += dt;
+            sprite.velocity.y -= sprite.gravity * dt;
+            sprite.position += sprite.velocity * dt;
+            sprite.age < sprite.lifetime
+        });
+    }
+
+    /// Simple xorshift generator; particles do not need anything better.
+    fn next_random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+
+        self.seed as f32 / u32::MAX as f32
+    }
+}
+
+impl Default for ProjectileFx {
+    fn default() -> Self {
+        Self {
+            // Xorshift never leaves zero
+            seed: 0x9e37_79b9,
+            sprites: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn sprites_fade_out() {
+        let mut fx = ProjectileFx::default();
+        fx.puff(Vec3::ZERO, [0xff; 3]);
+        fx.flash(Vec3::ZERO, [0xff; 3], 2.0, 1.0);
+        fx.update(ProjectileFx::FLASH_SECS);
+
+        assert_eq!(fx.sprites.len(), 1);
+        assert_eq!(fx.sprites[0].lifetime, ProjectileFx::PUFF_SECS);
+
+        fx.update(ProjectileFx::PUFF_SECS);
+
+        assert!(fx.sprites.is_empty());
+    }
+
+    #[test]
+    pub fn particles_fly_off_surface() {
+        let mut fx = ProjectileFx::default();
+        fx.burst(
+            Vec3::ZERO,
+            Vec3::Y,
+            &ParticleEffect {
+                color: [0xff; 3],
+                count: 8,
+                lifetime: 1.0,
+                radius: 0.05,
+                speed: 4.0,
+            },
+        );
+        fx.update(0.01);
+
+        assert_eq!(fx.sprites.len(), 8);
+        assert!(fx.sprites.iter().all(|sprite| sprite.position.y > 0.0));
+
+        fx.update(1.0);
+
+        assert!(fx.sprites.is_empty());
+    }
+}
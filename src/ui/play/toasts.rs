@@ -0,0 +1,122 @@
+//! Short notices shown at the top of the HUD, such as when a secret is found or an achievement is
+//! unlocked, which stack up and fade away on their own.
+
+use {
+    crate::game::events::{GameEvent, GameEventListener},
+    screen_13::prelude::*,
+    screen_13_fx::BitmapFont,
+    std::collections::VecDeque,
+};
+
+struct Toast {
+    /// Seconds until the toast is removed.
+    remaining_secs: f32,
+
+    text: String,
+}
+
+#[derive(Default)]
+pub struct Toasts {
+    toasts: VecDeque<Toast>,
+}
+
+impl Toasts {
+    const COLOR: [u8; 3] = [0xff, 0xd7, 0x00];
+
+    /// Toasts beyond this many are removed, oldest first, so a burst of them does not cover the
+    /// view.
+    const MAX_TOASTS: usize = 4;
+
+    const SECS: f32 = 3.0;
+
+    /// Prints each toast centered along the top of the framebuffer, newest at the bottom.
+    pub fn draw(
+        &self,
+        font: &BitmapFont,
+        render_graph: &mut RenderGraph,
+        framebuffer_image: impl Into<AnyImageNode>,
+    ) {
+        let framebuffer_image = framebuffer_image.into();
+        let framebuffer_info = render_graph.node_info(framebuffer_image);
+        let mut y = 0;
+
+        for toast in &self.toasts {
+            let (_, [width, height]) = font.measure(&toast.text);
+
+            font.print(
+                render_graph,
+                framebuffer_image,
+                (framebuffer_info.width.saturating_sub(width) / 2) as _,
+                y as _,
+                Self::COLOR,
+                &toast.text,
+            );
+
+            y += height;
+        }
+    }
+
+    pub fn push(&mut self, text: impl Into<String>) {
+        if self.toasts.len() == Self::MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+
+        self.toasts.push_back(Toast {
+            remaining_secs: Self::SECS,
+            text: text.into(),
+        });
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.toasts.retain_mut(|toast| {
+            toast.remaining_secs -= dt;
+            toast.remaining_secs > 0.0
+        });
+    }
+}
+
+impl GameEventListener for Toasts {
+    fn on_game_event(&mut self, event: &GameEvent) {
+        match event {
+            // Kills are too frequent to announce
+            GameEvent::EnemyKilled { .. } => (),
+            GameEvent::LevelCompleted { secs, .. } => {
+                let secs = secs.round() as u32;
+
+                self.push(format!("Level complete in {}:{:02}", secs / 60, secs % 60));
+            }
+            GameEvent::ObjectiveAdded { text, .. } => self.push(format!("New objective: {text}")),
+            GameEvent::ObjectiveCompleted { text, .. } => {
+                self.push(format!("Objective complete: {text}"))
+            }
+            GameEvent::SecretFound { .. } => self.push("Secret found"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn toasts_expire() {
+        let mut toasts = Toasts::default();
+        toasts.on_game_event(&GameEvent::LevelCompleted {
+            level: "level_01".to_owned(),
+            secs: 125.4,
+        });
+
+        assert_eq!(toasts.toasts[0].text, "Level complete in 2:05");
+
+        for _ in 0..Toasts::MAX_TOASTS {
+            toasts.push("Secret found");
+        }
+
+        assert_eq!(toasts.toasts.len(), Toasts::MAX_TOASTS);
+        assert_eq!(toasts.toasts[0].text, "Secret found");
+
+        toasts.update(Toasts::SECS);
+
+        assert!(toasts.toasts.is_empty());
+    }
+}
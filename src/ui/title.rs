@@ -1,5 +1,6 @@
 use {
     super::{
+        error::ErrorScreen,
         loader::{LoadInfo, LoadResult, Loader},
         menu::Menu,
         transition::{Transition, TransitionInfo},
@@ -10,6 +11,7 @@ use {
     screen_13::prelude::*,
     screen_13_fx::BitmapFont,
     std::{
+        borrow::Cow,
         sync::Arc,
         time::{Duration, Instant},
     },
@@ -30,6 +32,10 @@ impl Operation<Title> for Load {
         self.loader.progress()
     }
 
+    fn status(&self) -> Cow<str> {
+        self.loader.status()
+    }
+
     fn is_done(&self) -> bool {
         self.loader.is_done()
     }
@@ -45,11 +51,11 @@ impl Operation<Title> for Load {
         let content = Content {
             beep_sound: loader
                 .sounds
-                .remove(art::SOUND_DIGITAL_THREE_TONE_1_OGG)
+                .remove(&art::SOUND_DIGITAL_THREE_TONE_1_OGG)
                 .unwrap(),
             small_font: loader
                 .fonts
-                .remove(art::FONT_KENNEY_MINI_SQUARE_MONO)
+                .remove(&art::FONT_KENNEY_MINI_SQUARE_MONO)
                 .unwrap(),
         };
 
@@ -62,6 +68,10 @@ impl Operation<Title> for Load {
             started: Instant::now(),
         }
     }
+
+    fn unwrap_err(self: Box<Self>) -> anyhow::Error {
+        self.loader.unwrap_err()
+    }
 }
 
 pub struct Title {
@@ -79,6 +89,8 @@ impl Title {
         let loader = Box::new(Loader::spawn_threads(
             &device,
             None,
+            Default::default(),
+            Default::default(),
             LoadInfo::default()
                 .fonts(&[art::FONT_KENNEY_MINI_SQUARE_MONO])
                 .sounds(&[art::SOUND_DIGITAL_THREE_TONE_1_OGG]),
@@ -123,7 +135,7 @@ impl Ui for Title {
         }
     }
 
-    fn update(mut self: Box<Self>, ui: UpdateContext) -> Option<Box<dyn Ui>> {
+    fn update(mut self: Box<Self>, mut ui: UpdateContext) -> Option<Box<dyn Ui>> {
         #[cfg(debug_assertions)]
         if ui.keyboard.is_pressed(&VirtualKeyCode::Escape) {
             return None;
@@ -134,7 +146,10 @@ impl Ui for Title {
         }
 
         if self.menu.is_none() {
-            self.menu = Some(Box::new(Menu::load(&self.device).unwrap()));
+            match Menu::load(&self.device, ui.config.texture_filtering) {
+                Ok(menu) => self.menu = Some(Box::new(menu)),
+                Err(err) => return Some(Box::new(ErrorScreen::new(&self.device, err))),
+            }
         }
 
         let elapsed = (Instant::now() - self.started).as_secs_f32();
@@ -142,9 +157,7 @@ impl Ui for Title {
         if !self.beeped {
             self.beeped = true;
 
-            if let Some(audio) = ui.audio {
-                audio.play(self.content.beep_sound.clone()).unwrap();
-            }
+            ui.play_sound(&self.content.beep_sound, None);
         }
 
         #[cfg(debug_assertions)]
@@ -160,7 +173,9 @@ impl Ui for Title {
         if self.skip_requested {
             if let Some(menu) = &self.menu {
                 if menu.is_err() {
-                    panic!("Unable to load menu");
+                    let err = self.menu.take().unwrap().unwrap_err();
+
+                    return Some(Box::new(ErrorScreen::new(&self.device, err)));
                 }
 
                 if menu.is_done() {
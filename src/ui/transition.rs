@@ -41,19 +41,43 @@ impl Ui for Transition {
         let b_framebuffer = frame
             .render_graph
             .bind_node(frame.pool.lease(framebuffer_info).unwrap());
+        let hud_info = frame.render_graph.node_info(frame.hud_image);
+        let a_hud = frame
+            .render_graph
+            .bind_node(frame.pool.lease(hud_info).unwrap());
+        let b_hud = frame
+            .render_graph
+            .bind_node(frame.pool.lease(hud_info).unwrap());
+
+        frame
+            .render_graph
+            .clear_color_image_value(a_hud, [0x00, 0x00, 0x00, 0x00])
+            .clear_color_image_value(b_hud, [0x00, 0x00, 0x00, 0x00]);
 
         self.a.draw(DrawContext {
+            captions: frame.captions,
             dt: frame.dt,
+            fixed_alpha: frame.fixed_alpha,
+            frame_stats: frame.frame_stats,
             framebuffer_image: a_framebuffer,
+            hud_image: a_hud,
             pool: frame.pool,
+            present_mode: frame.present_mode,
             render_graph: frame.render_graph,
+            resolution_scale: frame.resolution_scale,
             transition_pipeline: frame.transition_pipeline,
         });
         self.b.draw(DrawContext {
+            captions: frame.captions,
             dt: frame.dt,
+            fixed_alpha: frame.fixed_alpha,
+            frame_stats: frame.frame_stats,
             framebuffer_image: b_framebuffer,
+            hud_image: b_hud,
             pool: frame.pool,
+            present_mode: frame.present_mode,
             render_graph: frame.render_graph,
+            resolution_scale: frame.resolution_scale,
             transition_pipeline: frame.transition_pipeline,
         });
 
@@ -67,6 +91,14 @@ impl Ui for Transition {
             self.info,
             self.progress,
         );
+        frame.transition_pipeline.apply_to(
+            frame.render_graph,
+            a_hud,
+            b_hud,
+            frame.hud_image,
+            self.info,
+            self.progress,
+        );
     }
 
     fn update(self: Box<Self>, _: UpdateContext) -> Option<Box<dyn Ui>> {
@@ -0,0 +1,117 @@
+//! Audio feedback for menu widgets: screens and layouts emit what the player did, and the sounds
+//! of a frame are reduced to the single most important one so that every screen sounds alike and
+//! quickly sweeping over buttons does not stack beeps.
+
+use {crate::audio::SfxBank, kira::sound::static_sound::StaticSoundData, std::mem::take};
+
+/// A widget interaction which is heard.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum UiSound {
+    /// The focused widget changed, from navigation or the mouse.
+    Hover,
+
+    /// A widget was activated.
+    Click,
+
+    /// The screen was backed out of.
+    Back,
+}
+
+impl UiSound {
+    /// Name of the event of this interaction in the sound bank.
+    fn event(self) -> &'static str {
+        match self {
+            Self::Hover => "ui_hover",
+            Self::Click => "ui_click",
+            Self::Back => "ui_back",
+        }
+    }
+}
+
+/// The sounds of each widget interaction, shared by all screens.
+#[derive(Default)]
+pub struct UiSounds {
+    bank: SfxBank,
+
+    /// Seconds until another hover may be heard.
+    hover_cooldown: f32,
+
+    /// The most important interaction of the current frame, which is the one heard.
+    pending: Option<UiSound>,
+}
+
+impl UiSounds {
+    /// Minimum seconds between hover sounds.
+    const HOVER_INTERVAL: f32 = 0.08;
+
+    /// Records an interaction of the current frame; only the most important one of each frame is
+    /// heard.
+    pub fn emit(&mut self, sound: UiSound) {
+        self.pending = self.pending.max(Some(sound));
+    }
+
+    /// Adds sounds which may play for an interaction; several sounds are chosen between as
+    /// [`SfxBank`] does.
+    pub fn insert(
+        &mut self,
+        sound: UiSound,
+        sounds: impl IntoIterator<Item = StaticSoundData>,
+    ) -> &mut Self {
+        self.bank.insert(sound.event(), sounds);
+        self
+    }
+
+    /// Returns the interaction heard this frame, if any, clearing those emitted.
+    fn next(&mut self, dt: f32) -> Option<UiSound> {
+        self.hover_cooldown = (self.hover_cooldown - dt).max(0.0);
+
+        let sound = take(&mut self.pending)
+            .filter(|&sound| sound != UiSound::Hover || self.hover_cooldown == 0.0)?;
+
+        // Any sound holds off hovers, so that clicking does not also beep for the new focus
+        self.hover_cooldown = Self::HOVER_INTERVAL;
+
+        Some(sound)
+    }
+
+    /// Returns the sound to play for the interactions of the frame which lasted `dt` seconds;
+    /// called once per frame after the UI has updated.
+    pub fn take_frame_sound(&mut self, dt: f32) -> Option<StaticSoundData> {
+        let sound = self.next(dt)?;
+
+        self.bank.sound(sound.event())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn one_sound_per_frame() {
+        let mut sounds = UiSounds::default();
+
+        assert_eq!(sounds.next(0.016), None);
+
+        sounds.emit(UiSound::Hover);
+        sounds.emit(UiSound::Click);
+        sounds.emit(UiSound::Hover);
+
+        assert_eq!(sounds.next(0.016), Some(UiSound::Click));
+        assert_eq!(sounds.next(0.016), None);
+
+        // Hovers which come too quickly are dropped, not delayed
+        sounds.emit(UiSound::Hover);
+
+        assert_eq!(sounds.next(0.016), None);
+        assert_eq!(sounds.next(0.1), None);
+
+        sounds.emit(UiSound::Hover);
+
+        assert_eq!(sounds.next(0.016), Some(UiSound::Hover));
+
+        sounds.emit(UiSound::Back);
+
+        assert_eq!(sounds.next(0.016), Some(UiSound::Back));
+    }
+}